@@ -0,0 +1,142 @@
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::Duration;
+
+use riscv_emu::console::Console;
+
+/// Console on files. The output is written to a file, and the input is read from
+/// another file if any. The files are opened by threads since a named pipe blocks
+/// until the other side opens it, and a named pipe of the input is opened again
+/// after the writer closes it.
+pub struct FileConsole {
+    output: Sender<u8>,
+    input: Option<Receiver<u8>>,
+    /// the input received by wait_input, which getchar returns first.
+    pending: Option<u8>,
+}
+
+impl FileConsole {
+    pub fn new(output_path: &Path, input_path: Option<&Path>) -> Self {
+        let (output, receiver) = channel();
+        let path = output_path.to_path_buf();
+        thread::spawn(move || write_output(path, receiver));
+        let input = match input_path {
+            Some(input_path) => {
+                let (sender, input) = channel();
+                let path = input_path.to_path_buf();
+                thread::spawn(move || read_input(path, sender));
+                Some(input)
+            }
+            None => None,
+        };
+        FileConsole {
+            output,
+            input,
+            pending: None,
+        }
+    }
+
+    /// the pipe of QEMU: path.in for the input and path.out for the output, or path
+    /// for both if they do not exist.
+    pub fn new_pipe(path: &str) -> Self {
+        let input_path = PathBuf::from(format!("{}.in", path));
+        let output_path = PathBuf::from(format!("{}.out", path));
+        match input_path.exists() && output_path.exists() {
+            true => FileConsole::new(output_path.as_path(), Some(input_path.as_path())),
+            false => FileConsole::new(Path::new(path), Some(Path::new(path))),
+        }
+    }
+}
+
+fn write_output(path: PathBuf, receiver: Receiver<u8>) {
+    let mut file = match OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(&path)
+    {
+        Ok(file) => file,
+        Err(why) => panic!("Failed to open {}: {}", path.display(), why),
+    };
+    while let Ok(c) = receiver.recv() {
+        let mut data = vec![c];
+        data.extend(receiver.try_iter());
+        match file.write_all(&data) {
+            Err(why) => panic!("Failed to write {}: {}", path.display(), why),
+            _ => {}
+        };
+    }
+}
+
+fn read_input(path: PathBuf, sender: Sender<u8>) {
+    let mut buffer = [0; 0x1000];
+    loop {
+        let mut file = match File::open(&path) {
+            Ok(file) => file,
+            Err(why) => panic!("Failed to open {}: {}", path.display(), why),
+        };
+        loop {
+            match file.read(&mut buffer) {
+                Ok(0) | Err(_) => break,
+                Ok(size) => {
+                    for c in buffer[..size].iter() {
+                        if sender.send(*c).is_err() {
+                            return;
+                        }
+                    }
+                }
+            }
+        }
+        // a regular file ends, and a named pipe waits for the next writer.
+        match file.metadata() {
+            Ok(metadata) if !metadata.is_file() => {}
+            _ => return,
+        }
+    }
+}
+
+impl Console for FileConsole {
+    fn putchar(&mut self, c: u8) {
+        let _ = self.output.send(c);
+    }
+
+    fn getchar(&mut self) -> u8 {
+        match (self.pending.take(), &self.input) {
+            (Some(c), _) => c,
+            (None, Some(input)) => input.try_recv().unwrap_or_default(),
+            (None, None) => 0,
+        }
+    }
+
+    fn set_input(&mut self, _c: u8) {}
+
+    fn get_output(&mut self) -> u8 {
+        0
+    }
+
+    fn wait_input(&mut self, timeout: Duration) -> bool {
+        if self.pending.is_some() {
+            return true;
+        }
+        match &self.input {
+            Some(input) => match input.recv_timeout(timeout) {
+                Ok(c) => {
+                    self.pending = Some(c);
+                    true
+                }
+                Err(RecvTimeoutError::Timeout) => false,
+                Err(RecvTimeoutError::Disconnected) => {
+                    thread::sleep(timeout);
+                    false
+                }
+            },
+            None => {
+                thread::sleep(timeout);
+                false
+            }
+        }
+    }
+}
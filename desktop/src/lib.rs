@@ -1 +1,2 @@
+pub mod file_console;
 pub mod tty;
@@ -2,15 +2,23 @@ extern crate getopts;
 extern crate riscv_emu;
 
 use riscv_emu::bus::bus::Device;
-use riscv_emu::console::TtyDummy;
-use riscv_emu::emulator::Emulator;
+use riscv_emu::console::{Console, TtyDummy};
+use riscv_emu::cpu::mmu::MisalignedAccess;
+use riscv_emu::device_tree;
+use riscv_emu::emulator::{Emulator, ExecutionEngine, IdleMode};
 use riscv_emu::machine::Machine;
+use riscv_emu::network::loopback::LoopbackNetwork;
+use riscv_emu::network::pcap::PcapNetwork;
+use riscv_emu::network::user::UserNetwork;
+use riscv_emu::peripherals::virtio_console::CONFIG_MAX_NR_PORTS;
+use riscv_emu::peripherals::virtio_rng::EntropySource;
 
+use riscv_emu_desktop::file_console::FileConsole;
 use riscv_emu_desktop::tty::Tty;
 
 use getopts::Options;
-use std::path::PathBuf;
-use std::{env, process};
+use std::path::{Path, PathBuf};
+use std::{env, fs, process};
 
 fn main() {
     let args: Vec<String> = env::args().collect();
@@ -24,6 +32,12 @@ fn main() {
         "File system image file",
         "./artifacts/xv6/fs.img",
     );
+    opts.optopt(
+        "F",
+        "filesystem-ro",
+        "File system image file which the guest cannot write",
+        "./artifacts/xv6/fs.img",
+    );
     opts.optopt(
         "d",
         "dtb",
@@ -36,6 +50,69 @@ fn main() {
         "Target machine (SiFive_e|SiFive_u|Qemu_virt)",
         "SiFive_e",
     );
+    opts.optopt("v", "vlen", "Bits in a single vector register (VLEN)", "128");
+    opts.optopt("p", "pmp", "Number of PMP entries (0-64)", "16");
+    opts.optopt("s", "smp", "Number of harts (1-5)", "1");
+    opts.optopt(
+        "r",
+        "reservation-timeout",
+        "Cycles until an LR reservation expires (0: never)",
+        "0",
+    );
+    opts.optopt(
+        "a",
+        "misaligned",
+        "Misaligned load/store handling (trap|hardware|emulate)",
+        "hardware",
+    );
+    opts.optopt(
+        "e",
+        "engine",
+        "Execution engine (interpreter|block)",
+        "interpreter",
+    );
+    opts.optopt(
+        "i",
+        "idle",
+        "Idle harts skip the time or sleep in real time (skip|realtime)",
+        "realtime",
+    );
+    opts.optopt(
+        "n",
+        "net",
+        "Backend of the virtio network device (user|loopback|pcap:CAPTURE[:REPLAY])",
+        "user",
+    );
+    opts.optopt(
+        "c",
+        "console",
+        "Backend of the console port hvc0 of the virtio console (tty|file:PATH|pipe:PATH)",
+        "tty",
+    );
+    opts.optmulti(
+        "o",
+        "port",
+        "Named port of the virtio console, which can be repeated (NAME=file:PATH|pipe:PATH)",
+        "org.test.0=pipe:/tmp/port0",
+    );
+    opts.optopt(
+        "g",
+        "rng",
+        "Source of the virtio entropy device (host|seed:N)",
+        "host",
+    );
+    opts.optopt(
+        "x",
+        "share",
+        "Host directory exported by the virtio 9P device with the mount tag host0",
+        "./shared",
+    );
+    opts.optopt(
+        "X",
+        "share-ro",
+        "Host directory exported read-only by the virtio 9P device",
+        "./shared",
+    );
     opts.optflag("t", "testmode", "Testmode is enabled");
     opts.optflag("h", "help", "Help message");
 
@@ -59,7 +136,15 @@ fn main() {
             process::exit(0);
         }
     };
-    let fs_path = matches.opt_str("f");
+    let (fs_path, fs_read_only) = match (matches.opt_str("f"), matches.opt_str("F")) {
+        (Some(path), None) => (Some(path), false),
+        (None, Some(path)) => (Some(path), true),
+        (None, None) => (None, false),
+        _ => {
+            print_usage(&program, &opts);
+            process::exit(0);
+        }
+    };
     let dtb_path = matches.opt_str("d");
     let testmode = matches.opt_present("t");
     let machine = match matches.opt_str("m") {
@@ -71,8 +156,11 @@ fn main() {
         },
         None => Machine::SiFiveU,
     };
+    let qemu_virt = matches!(machine, Machine::QemuVirt);
+    // the terminal is the console of the UART unless the console port takes it.
+    let tty_console_port = matches.opt_str("c").as_deref() == Some("tty");
     let mut emu;
-    if testmode {
+    if testmode || tty_console_port {
         let tty = Box::new(TtyDummy::new());
         emu = Emulator::new(machine, tty, testmode);
     } else {
@@ -80,6 +168,212 @@ fn main() {
         emu = Emulator::new(machine, tty, testmode);
     }
 
+    match matches.opt_str("v") {
+        Some(vlen) => match vlen.parse::<usize>() {
+            Ok(vlen) => emu.set_vlen(vlen),
+            Err(_) => {
+                print_usage(&program, &opts);
+                process::exit(0);
+            }
+        },
+        None => {}
+    }
+
+    match matches.opt_str("p") {
+        Some(entries) => match entries.parse::<usize>() {
+            Ok(entries) => emu.set_pmp_entries(entries),
+            Err(_) => {
+                print_usage(&program, &opts);
+                process::exit(0);
+            }
+        },
+        None => {}
+    }
+
+    let harts = match matches.opt_str("s") {
+        Some(harts) => match harts.parse::<usize>() {
+            Ok(harts) => harts,
+            Err(_) => {
+                print_usage(&program, &opts);
+                process::exit(0);
+            }
+        },
+        None => 1,
+    };
+    emu.set_harts(harts);
+
+    match matches.opt_str("r") {
+        Some(cycles) => match cycles.parse::<u64>() {
+            Ok(cycles) => emu.set_reservation_timeout(cycles),
+            Err(_) => {
+                print_usage(&program, &opts);
+                process::exit(0);
+            }
+        },
+        None => {}
+    }
+
+    match matches.opt_str("a") {
+        Some(mode) => match &*mode {
+            "trap" => emu.set_misaligned_access(MisalignedAccess::Trap),
+            "hardware" => emu.set_misaligned_access(MisalignedAccess::Hardware),
+            "emulate" => emu.set_misaligned_access(MisalignedAccess::Emulate),
+            _ => {
+                print_usage(&program, &opts);
+                process::exit(0);
+            }
+        },
+        None => {}
+    }
+
+    match matches.opt_str("e") {
+        Some(engine) => match &*engine {
+            "interpreter" => emu.set_execution_engine(ExecutionEngine::Interpreter),
+            "block" => emu.set_execution_engine(ExecutionEngine::Block),
+            _ => {
+                print_usage(&program, &opts);
+                process::exit(0);
+            }
+        },
+        None => {}
+    }
+
+    match matches.opt_str("i") {
+        Some(mode) => match &*mode {
+            "skip" => emu.set_idle_mode(IdleMode::Skip),
+            "realtime" => emu.set_idle_mode(IdleMode::RealTime),
+            _ => {
+                print_usage(&program, &opts);
+                process::exit(0);
+            }
+        },
+        None => {
+            if !testmode {
+                emu.set_idle_mode(IdleMode::RealTime)
+            }
+        }
+    }
+
+    match matches.opt_str("n") {
+        Some(net) => {
+            let mut params = net.split(':');
+            match params.next() {
+                Some("user") => emu.set_network(Box::new(UserNetwork::new())),
+                Some("loopback") => emu.set_network(Box::new(LoopbackNetwork::new())),
+                Some("pcap") => match params.next() {
+                    Some(capture) => {
+                        let capture = PathBuf::from(capture);
+                        let replay = params.next().map(PathBuf::from);
+                        match PcapNetwork::new(capture.as_path(), replay.as_deref()) {
+                            Ok(network) => emu.set_network(Box::new(network)),
+                            Err(why) => {
+                                eprintln!("{}", why);
+                                process::exit(1);
+                            }
+                        }
+                    }
+                    None => {
+                        print_usage(&program, &opts);
+                        process::exit(0);
+                    }
+                },
+                _ => {
+                    print_usage(&program, &opts);
+                    process::exit(0);
+                }
+            }
+        }
+        None => {}
+    }
+
+    match matches.opt_str("c") {
+        Some(backend) => {
+            let console = match &*backend {
+                "tty" => Some(Box::new(Tty::new()) as Box<dyn Console>),
+                _ => open_console(&backend),
+            };
+            match console {
+                Some(console) => match emu.set_console_port(0, "", console) {
+                    Ok(()) => {}
+                    Err(()) => {
+                        eprintln!("The virtio console does not have the port 0.");
+                        process::exit(1);
+                    }
+                },
+                None => {
+                    print_usage(&program, &opts);
+                    process::exit(0);
+                }
+            }
+        }
+        None => {}
+    }
+
+    let entropy_source = match matches.opt_str("g") {
+        Some(source) => {
+            let mut params = source.splitn(2, ':');
+            match (params.next(), params.next()) {
+                (Some("host"), None) => Some(EntropySource::Host),
+                (Some("seed"), Some(seed)) => match seed.parse::<u64>() {
+                    Ok(seed) => Some(EntropySource::Seed(seed)),
+                    Err(_e) => {
+                        print_usage(&program, &opts);
+                        process::exit(0);
+                    }
+                },
+                _ => {
+                    print_usage(&program, &opts);
+                    process::exit(0);
+                }
+            }
+        }
+        None if !testmode && qemu_virt => Some(EntropySource::Host),
+        None => None,
+    };
+    match entropy_source {
+        Some(source) => match emu.set_entropy_source(source) {
+            Ok(()) => {}
+            Err(e) => {
+                eprintln!("The virtio entropy device does not have the source: {}", e);
+                process::exit(1);
+            }
+        },
+        None => {}
+    }
+
+    match (matches.opt_str("x"), matches.opt_str("X")) {
+        (Some(path), None) => emu.set_shared_directory(Path::new(&path), false),
+        (None, Some(path)) => emu.set_shared_directory(Path::new(&path), true),
+        (None, None) => {}
+        _ => {
+            print_usage(&program, &opts);
+            process::exit(0);
+        }
+    }
+
+    for (i, port) in matches.opt_strs("o").iter().enumerate() {
+        let mut params = port.splitn(2, '=');
+        match (params.next(), params.next().and_then(open_console)) {
+            (Some(name), Some(console)) if !name.is_empty() => {
+                match emu.set_console_port(i as u32 + 1, name, console) {
+                    Ok(()) => {}
+                    Err(()) => {
+                        eprintln!(
+                            "The virtio console does not have the port {}: it has {} ports.",
+                            i + 1,
+                            CONFIG_MAX_NR_PORTS
+                        );
+                        process::exit(1);
+                    }
+                }
+            }
+            _ => {
+                print_usage(&program, &opts);
+                process::exit(0);
+            }
+        }
+    }
+
     /*
     let data = vec![
         0x13, 0x85, 0x87, 0xfd // addi a0,a5,-40
@@ -100,15 +394,31 @@ fn main() {
         Some(filepath) => {
             let fs = PathBuf::from(filepath);
             emu.set_data_from_file(Device::Disk, fs.as_path());
+            if fs_read_only {
+                emu.set_disk_read_only(true);
+            }
         }
         None => {}
     }
 
-    // download dtb image
+    // download dtb image, whose cpu nodes are enabled for the harts.
     match dtb_path {
         Some(filepath) => {
-            let fs = PathBuf::from(filepath);
-            emu.set_data_from_file(Device::DTB, fs.as_path());
+            let mut dtb = match fs::read(&filepath) {
+                Ok(dtb) => dtb,
+                Err(why) => {
+                    eprintln!("Failed to read {}: {}", filepath, why);
+                    process::exit(1);
+                }
+            };
+            match device_tree::enable_harts(&mut dtb, harts) {
+                Ok(()) => {}
+                Err(e) => {
+                    eprintln!("{}: {}", filepath, e);
+                    process::exit(1);
+                }
+            }
+            emu.set_data_from_binary(Device::DTB, dtb);
         }
         None => {}
     }
@@ -121,6 +431,16 @@ fn main() {
     println!("Result: {}", result);
 }
 
+/// the console on the file or the pipe of the backend.
+fn open_console(backend: &str) -> Option<Box<dyn Console>> {
+    let mut params = backend.splitn(2, ':');
+    match (params.next(), params.next()) {
+        (Some("file"), Some(path)) => Some(Box::new(FileConsole::new(Path::new(path), None))),
+        (Some("pipe"), Some(path)) => Some(Box::new(FileConsole::new_pipe(path))),
+        _ => None,
+    }
+}
+
 fn print_usage(program: &str, opts: &Options) {
     let brief = format!("Usage: {} FILE [options]", program);
     print!("{}", opts.usage(&brief));
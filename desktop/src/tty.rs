@@ -2,6 +2,7 @@ extern crate pancurses;
 
 use self::pancurses::*;
 use std::str;
+use std::time::Duration;
 
 use riscv_emu::console::Console;
 
@@ -97,4 +98,19 @@ impl Console for Tty {
     fn get_output(&mut self) -> u8 {
         0
     }
+
+    /// getch waits up to the timeout, and the input is pushed back for getchar.
+    fn wait_input(&mut self, timeout: Duration) -> bool {
+        let millis = timeout.as_micros().div_ceil(1000);
+        self.window.timeout(millis.min(i32::MAX as u128) as i32);
+        let input = self.window.getch();
+        self.window.nodelay(true);
+        match input {
+            Some(input) => {
+                self.window.ungetch(&input);
+                true
+            }
+            None => false,
+        }
+    }
 }
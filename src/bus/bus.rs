@@ -1,4 +1,9 @@
+use std::path::Path;
+
 use crate::console::Console;
+use crate::network::Network;
+use crate::peripherals::memory::PageWrites;
+use crate::peripherals::virtio_rng::EntropySource;
 
 #[allow(dead_code)]
 #[derive(Debug)]
@@ -13,9 +18,41 @@ pub trait Bus {
     fn set_device_data(&mut self, device: Device, data: Vec<u8>);
     fn get_base_address(&mut self, device: Device) -> u64;
     fn get_console(&mut self) -> &mut Box<dyn Console>;
-    fn tick(&mut self) -> Vec<bool>;
+    /// connect the network device of the machine to the network.
+    fn set_network(&mut self, network: Box<dyn Network>);
+    /// connect the port of the virtio console of the machine to the console. Err if
+    /// the virtio console does not have the port.
+    fn set_console_port(
+        &mut self,
+        id: u32,
+        name: &str,
+        console: Box<dyn Console>,
+    ) -> Result<(), ()>;
+    /// set the source of the entropy of the virtio entropy device of the machine.
+    /// Err if the source is not available.
+    fn set_entropy_source(&mut self, source: EntropySource) -> Result<(), String>;
+    /// forbid the writes of the guest to the disk of the virtio block device.
+    fn set_disk_read_only(&mut self, read_only: bool);
+    /// export the directory of the host by the virtio 9P device of the machine.
+    fn set_shared_directory(&mut self, path: &Path, read_only: bool);
+    /// run the devices for cycles. The events scheduled by the devices in the
+    /// cycles run in order of their time.
+    fn tick(&mut self, cycles: u64);
+    /// cycles until the earliest event scheduled by the devices. The interrupts of
+    /// the devices and the timer do not change until then without the accesses to
+    /// the devices.
+    fn get_cycles_to_next_event(&mut self) -> Option<u64>;
+    fn get_external_interrupts(&mut self, core: usize) -> Vec<bool>;
     fn is_pending_software_interrupt(&mut self, core: usize) -> bool;
     fn is_pending_timer_interrupt(&mut self, core: usize) -> bool;
+    fn get_mtime(&mut self) -> u64;
+    /// mtime of the CLINT after the devices run cycles.
+    fn get_mtime_after(&mut self, cycles: u64) -> u64;
+    /// mtime ticks per second of the CLINT.
+    fn get_timebase_frequency(&mut self) -> u64;
+    /// base addresses and page write counters of the memories which instructions
+    /// can be fetched from, for the decoded instruction caches of the harts.
+    fn get_page_writes(&mut self) -> Vec<(u64, PageWrites)>;
     fn read8(&mut self, addr: u64) -> Result<u8, ()>;
     fn read16(&mut self, addr: u64) -> Result<u16, ()>;
     fn read32(&mut self, addr: u64) -> Result<u32, ()>;
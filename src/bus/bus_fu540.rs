@@ -1,16 +1,21 @@
 // FU540 SoC
 // https://static.dev.sifive.com/FU540-C000-v1.0.pdf
 
+use std::path::Path;
+
 use crate::bus::bus::*;
 use crate::console::*;
+use crate::network::Network;
 use crate::peripherals::fe310_g002::fe310_uart::Fe310Uart;
 use crate::peripherals::fe310_g002::gpio::Gpio;
 use crate::peripherals::fe310_g002::prci::Prci;
 use crate::peripherals::fu540_c000::clint::Clint;
 use crate::peripherals::fu540_c000::plic::Plic;
 use crate::peripherals::intc::Intc;
-use crate::peripherals::memory::Memory;
+use crate::peripherals::memory::{Memory, PageWrites};
+use crate::peripherals::scheduler::{Event, Scheduler, SharedScheduler};
 use crate::peripherals::timer::Timer;
+use crate::peripherals::virtio_rng::EntropySource;
 
 const _DEBUG_ADDRESS_START: u64 = 0x0000_0000;
 const _DEBUG_ADDRESS_END: u64 = 0x0000_0FFF;
@@ -26,6 +31,8 @@ const DTIM_ADDRESS_END: u64 = 0x0100_1FFF;
 
 const TIMER_ADDRESS_START: u64 = 0x0200_0000;
 const TIMER_ADDRESS_END: u64 = 0x0200_FFFF;
+/// mtime of the CLINT is driven by the 1 MHz RTCCLK.
+const TIMEBASE_FREQUENCY: u64 = 1_000_000;
 
 const INTC_ADDRESS_START: u64 = 0x0C00_0000;
 const INTC_ADDRESS_END: u64 = 0x0FFF_FFFF;
@@ -52,7 +59,7 @@ const FLASH_SIZE: usize = 1024 * 1024 * 512;
 const DRAM_SIZE: usize = 1024 * 1024 * 128;
 
 pub struct BusFu540 {
-    clock: u64,
+    scheduler: SharedScheduler,
     dtim: Memory,
     flash: Memory,
     dram: Memory,
@@ -66,17 +73,18 @@ pub struct BusFu540 {
 
 impl BusFu540 {
     pub fn new(console: Box<dyn Console>) -> Self {
+        let scheduler = Scheduler::new_shared();
         Self {
-            clock: 0,
             dtim: Memory::new(DTIM_SIZE),
             flash: Memory::new(FLASH_SIZE),
             dram: Memory::new(DRAM_SIZE),
-            timer: Box::new(Clint::new()),
+            timer: Box::new(Clint::new(scheduler.clone(), TIMEBASE_FREQUENCY)),
             intc: Box::new(Plic::new()),
-            uart0: Fe310Uart::new(console),
-            uart1: Fe310Uart::new(Box::new(TtyDummy::new())),
+            uart0: Fe310Uart::new(console, scheduler.clone(), 0),
+            uart1: Fe310Uart::new(Box::new(TtyDummy::new()), scheduler.clone(), 1),
             prci: Prci::new(),
             gpio: Gpio::new(),
+            scheduler,
         }
     }
 }
@@ -95,15 +103,57 @@ impl Bus for BusFu540 {
         self.uart0.get_console()
     }
 
-    fn tick(&mut self) -> Vec<bool> {
-        self.clock = self.clock.wrapping_add(1);
+    fn set_network(&mut self, _network: Box<dyn Network>) {
+        panic!("Unexpected device: network");
+    }
+
+    fn set_console_port(
+        &mut self,
+        _id: u32,
+        _name: &str,
+        _console: Box<dyn Console>,
+    ) -> Result<(), ()> {
+        panic!("Unexpected device: virtio console");
+    }
+
+    fn set_entropy_source(&mut self, _source: EntropySource) -> Result<(), String> {
+        panic!("Unexpected device: virtio entropy");
+    }
+
+    fn set_disk_read_only(&mut self, _read_only: bool) {
+        panic!("Unexpected device: virtio block");
+    }
 
-        self.timer.tick();
-        self.prci.tick();
-        self.gpio.tick();
-        self.uart0.tick();
-        self.uart1.tick();
+    fn set_shared_directory(&mut self, _path: &Path, _read_only: bool) {
+        panic!("Unexpected device: virtio 9p");
+    }
 
+    fn tick(&mut self, cycles: u64) {
+        let time = self.scheduler.borrow().get_time() + cycles;
+        loop {
+            let event = match self.scheduler.borrow_mut().pop(time) {
+                Some(event) => event,
+                None => break,
+            };
+            match event {
+                Event::UartReceive(0) => self.uart0.receive(),
+                Event::UartReceive(_) => self.uart1.receive(),
+                Event::UartTransmit(0) => self.uart0.transmit(),
+                Event::UartTransmit(_) => self.uart1.transmit(),
+                // the timer interrupt follows mtime without the event.
+                _ => {}
+            }
+        }
+    }
+
+    fn get_cycles_to_next_event(&mut self) -> Option<u64> {
+        let scheduler = self.scheduler.borrow();
+        scheduler
+            .get_next_event_time()
+            .map(|time| time - scheduler.get_time())
+    }
+
+    fn get_external_interrupts(&mut self, core: usize) -> Vec<bool> {
         let mut interrupts: Vec<usize> = Vec::new();
         if self.uart0.is_irq() {
             interrupts.push(3); // Interrupt ID for UART0
@@ -111,7 +161,7 @@ impl Bus for BusFu540 {
         if self.uart1.is_irq() {
             interrupts.push(4); // Interrupt ID for UART1
         }
-        self.intc.tick(0, interrupts)
+        self.intc.tick(core, interrupts)
     }
 
     fn is_pending_software_interrupt(&mut self, core: usize) -> bool {
@@ -122,6 +172,26 @@ impl Bus for BusFu540 {
         self.timer.is_pending_timer_interrupt(core)
     }
 
+    fn get_mtime(&mut self) -> u64 {
+        self.timer.read_mtime()
+    }
+
+    fn get_mtime_after(&mut self, cycles: u64) -> u64 {
+        self.timer.get_mtime_after(cycles)
+    }
+
+    fn get_timebase_frequency(&mut self) -> u64 {
+        self.timer.get_timebase_frequency()
+    }
+
+    fn get_page_writes(&mut self) -> Vec<(u64, PageWrites)> {
+        vec![
+            (DTIM_ADDRESS_START, self.dtim.get_page_writes()),
+            (SPIFLASH_ADDRESS_START, self.flash.get_page_writes()),
+            (DRAM_ADDRESS_START, self.dram.get_page_writes()),
+        ]
+    }
+
     fn get_base_address(&mut self, device: Device) -> u64 {
         match device {
             Device::SpiFlash => SPIFLASH_ADDRESS_START,
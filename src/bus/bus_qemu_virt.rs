@@ -1,14 +1,22 @@
 // QEMU Virt Machine
 
+use std::path::Path;
+
 use crate::bus::bus::*;
 use crate::console::*;
+use crate::network::Network;
 use crate::peripherals::fu540_c000::clint::Clint;
 use crate::peripherals::fu540_c000::plic::Plic;
 use crate::peripherals::intc::Intc;
-use crate::peripherals::memory::Memory;
+use crate::peripherals::memory::{Memory, PageWrites};
+use crate::peripherals::scheduler::{Event, Scheduler, SharedScheduler};
 use crate::peripherals::timer::Timer;
 use crate::peripherals::uart::Uart;
 use crate::peripherals::virtio::Virtio;
+use crate::peripherals::virtio_9p::Virtio9p;
+use crate::peripherals::virtio_console::VirtioConsole;
+use crate::peripherals::virtio_net::VirtioNet;
+use crate::peripherals::virtio_rng::{EntropySource, VirtioRng};
 
 const DTB_ADDRESS_START: u64 = 0x0000_1020;
 const DTB_ADDRESS_END: u64 = 0x0000_1FFF;
@@ -18,6 +26,8 @@ const MROM_ADDRESS_END: u64 = 0x0000_FFFF;
 
 const TIMER_ADDRESS_START: u64 = 0x0200_0000;
 const TIMER_ADDRESS_END: u64 = 0x0200_FFFF;
+/// timebase-frequency of the virt machine of QEMU, which is the frequency of mtime.
+const TIMEBASE_FREQUENCY: u64 = 10_000_000;
 
 const INTC_ADDRESS_START: u64 = 0x0C00_0000;
 const INTC_ADDRESS_END: u64 = 0x0FFF_FFFF;
@@ -28,6 +38,18 @@ const UART_ADDRESS_END: u64 = 0x1000_0FFF;
 const VIRTIO_ADDRESS_START: u64 = 0x1000_1000;
 const VIRTIO_ADDRESS_END: u64 = 0x1000_1FFF;
 
+const VIRTIO_NET_ADDRESS_START: u64 = 0x1000_2000;
+const VIRTIO_NET_ADDRESS_END: u64 = 0x1000_2FFF;
+
+const VIRTIO_CONSOLE_ADDRESS_START: u64 = 0x1000_3000;
+const VIRTIO_CONSOLE_ADDRESS_END: u64 = 0x1000_3FFF;
+
+const VIRTIO_RNG_ADDRESS_START: u64 = 0x1000_4000;
+const VIRTIO_RNG_ADDRESS_END: u64 = 0x1000_4FFF;
+
+const VIRTIO_9P_ADDRESS_START: u64 = 0x1000_5000;
+const VIRTIO_9P_ADDRESS_END: u64 = 0x1000_5FFF;
+
 const DRAM_ADDRESS_START: u64 = 0x8000_0000;
 
 const MROM_SIZE: usize = 0xF000;
@@ -35,7 +57,7 @@ pub const DRAM_SIZE: usize = 1024 * 1024 * 256; // todo: support command line to
 const DTB_SIZE: usize = 0xfe0;
 
 pub struct BusQemuVirt {
-    clock: u64,
+    scheduler: SharedScheduler,
     dtb: Vec<u8>,
     mrom: Memory,
     dram: Memory,
@@ -43,19 +65,28 @@ pub struct BusQemuVirt {
     intc: Box<dyn Intc>,
     uart: Uart,
     virtio: Virtio,
+    virtio_net: VirtioNet,
+    virtio_console: VirtioConsole,
+    virtio_rng: VirtioRng,
+    virtio_9p: Virtio9p,
 }
 
 impl BusQemuVirt {
     pub fn new(console: Box<dyn Console>) -> Self {
+        let scheduler = Scheduler::new_shared();
         Self {
-            clock: 0,
             dtb: vec![0; DTB_SIZE],
             mrom: Memory::new(MROM_SIZE),
             dram: Memory::new(DRAM_SIZE),
-            timer: Box::new(Clint::new()),
+            timer: Box::new(Clint::new(scheduler.clone(), TIMEBASE_FREQUENCY)),
             intc: Box::new(Plic::new()),
-            uart: Uart::new(console),
-            virtio: Virtio::new(DRAM_ADDRESS_START),
+            uart: Uart::new(console, scheduler.clone()),
+            virtio: Virtio::new(DRAM_ADDRESS_START, scheduler.clone()),
+            virtio_net: VirtioNet::new(DRAM_ADDRESS_START, scheduler.clone()),
+            virtio_console: VirtioConsole::new(DRAM_ADDRESS_START, scheduler.clone()),
+            virtio_rng: VirtioRng::new(DRAM_ADDRESS_START, scheduler.clone()),
+            virtio_9p: Virtio9p::new(DRAM_ADDRESS_START, scheduler.clone()),
+            scheduler,
         }
     }
 }
@@ -78,15 +109,64 @@ impl Bus for BusQemuVirt {
 
     fn get_console(&mut self) -> &mut Box<dyn Console> {
         self.uart.get_console()
-    }    
+    }
+
+    fn set_network(&mut self, network: Box<dyn Network>) {
+        self.virtio_net.set_network(network);
+    }
+
+    fn set_console_port(
+        &mut self,
+        id: u32,
+        name: &str,
+        console: Box<dyn Console>,
+    ) -> Result<(), ()> {
+        self.virtio_console.set_port(id, name, console)
+    }
 
-    fn tick(&mut self) -> Vec<bool> {
-        self.clock = self.clock.wrapping_add(1);
+    fn set_entropy_source(&mut self, source: EntropySource) -> Result<(), String> {
+        self.virtio_rng.set_entropy_source(source)
+    }
+
+    fn set_disk_read_only(&mut self, read_only: bool) {
+        self.virtio.set_read_only(read_only);
+    }
+
+    fn set_shared_directory(&mut self, path: &Path, read_only: bool) {
+        self.virtio_9p.set_shared_directory(path, read_only);
+    }
 
-        self.virtio.tick(&mut self.dram);
-        self.timer.tick();
-        self.uart.tick();
+    fn tick(&mut self, cycles: u64) {
+        let time = self.scheduler.borrow().get_time() + cycles;
+        loop {
+            let event = match self.scheduler.borrow_mut().pop(time) {
+                Some(event) => event,
+                None => break,
+            };
+            match event {
+                Event::UartReceive(_) => self.uart.receive(),
+                Event::UartTransmit(_) => self.uart.transmit(),
+                Event::VirtioCompletion => self.virtio.complete(&mut self.dram),
+                Event::VirtioNetReceive => self.virtio_net.receive(&mut self.dram),
+                Event::VirtioNetTransmit => self.virtio_net.transmit(&mut self.dram),
+                Event::VirtioConsoleReceive => self.virtio_console.receive(&mut self.dram),
+                Event::VirtioConsoleTransmit => self.virtio_console.transmit(&mut self.dram),
+                Event::VirtioRngCompletion => self.virtio_rng.complete(&mut self.dram),
+                Event::Virtio9pCompletion => self.virtio_9p.complete(&mut self.dram),
+                // the timer interrupt follows mtime without the event.
+                Event::TimerExpiry(_) => {}
+            }
+        }
+    }
+
+    fn get_cycles_to_next_event(&mut self) -> Option<u64> {
+        let scheduler = self.scheduler.borrow();
+        scheduler
+            .get_next_event_time()
+            .map(|time| time - scheduler.get_time())
+    }
 
+    fn get_external_interrupts(&mut self, core: usize) -> Vec<bool> {
         // https://github.com/mit-pdos/xv6-riscv/blob/riscv/kernel/memlayout.h
         let mut interrupts: Vec<usize> = Vec::new();
         if self.uart.is_irq() {
@@ -95,7 +175,19 @@ impl Bus for BusQemuVirt {
         if self.virtio.is_irq() {
             interrupts.push(1); // Interrupt ID for Virtio
         }
-        self.intc.tick(0, interrupts)
+        if self.virtio_net.is_irq() {
+            interrupts.push(2); // Interrupt ID for Virtio network
+        }
+        if self.virtio_console.is_irq() {
+            interrupts.push(3); // Interrupt ID for Virtio console
+        }
+        if self.virtio_rng.is_irq() {
+            interrupts.push(4); // Interrupt ID for Virtio entropy
+        }
+        if self.virtio_9p.is_irq() {
+            interrupts.push(5); // Interrupt ID for Virtio 9P
+        }
+        self.intc.tick(core, interrupts)
     }
 
     fn is_pending_software_interrupt(&mut self, core: usize) -> bool {
@@ -106,6 +198,25 @@ impl Bus for BusQemuVirt {
         self.timer.is_pending_timer_interrupt(core)
     }
 
+    fn get_mtime(&mut self) -> u64 {
+        self.timer.read_mtime()
+    }
+
+    fn get_mtime_after(&mut self, cycles: u64) -> u64 {
+        self.timer.get_mtime_after(cycles)
+    }
+
+    fn get_timebase_frequency(&mut self) -> u64 {
+        self.timer.get_timebase_frequency()
+    }
+
+    fn get_page_writes(&mut self) -> Vec<(u64, PageWrites)> {
+        vec![
+            (MROM_ADDRESS_START, self.mrom.get_page_writes()),
+            (DRAM_ADDRESS_START, self.dram.get_page_writes()),
+        ]
+    }
+
     fn get_base_address(&mut self, device: Device) -> u64 {
         match device {
             Device::Dram => DRAM_ADDRESS_START,
@@ -133,6 +244,26 @@ impl Bus for BusQemuVirt {
                 let data = ((self.virtio.read(virtio_addr) >> 8 * (addr & 0x3)) & 0xff) as u8;
                 Ok(data)
             }
+            VIRTIO_NET_ADDRESS_START..=VIRTIO_NET_ADDRESS_END => {
+                let virtio_addr = (addr - VIRTIO_NET_ADDRESS_START) & 0xffff_fffc;
+                let data = ((self.virtio_net.read(virtio_addr) >> (8 * (addr & 0x3))) & 0xff) as u8;
+                Ok(data)
+            }
+            VIRTIO_CONSOLE_ADDRESS_START..=VIRTIO_CONSOLE_ADDRESS_END => {
+                let virtio_addr = (addr - VIRTIO_CONSOLE_ADDRESS_START) & 0xffff_fffc;
+                let data = ((self.virtio_console.read(virtio_addr) >> (8 * (addr & 0x3))) & 0xff) as u8;
+                Ok(data)
+            }
+            VIRTIO_RNG_ADDRESS_START..=VIRTIO_RNG_ADDRESS_END => {
+                let virtio_addr = (addr - VIRTIO_RNG_ADDRESS_START) & 0xffff_fffc;
+                let data = ((self.virtio_rng.read(virtio_addr) >> (8 * (addr & 0x3))) & 0xff) as u8;
+                Ok(data)
+            }
+            VIRTIO_9P_ADDRESS_START..=VIRTIO_9P_ADDRESS_END => {
+                let virtio_addr = (addr - VIRTIO_9P_ADDRESS_START) & 0xffff_fffc;
+                let data = ((self.virtio_9p.read(virtio_addr) >> (8 * (addr & 0x3))) & 0xff) as u8;
+                Ok(data)
+            }
             _ => Err(()),
         }
     }
@@ -160,6 +291,10 @@ impl Bus for BusQemuVirt {
                 Ok(data)
             }
             VIRTIO_ADDRESS_START..=VIRTIO_ADDRESS_END => panic!("Unexpected size access."),
+            VIRTIO_NET_ADDRESS_START..=VIRTIO_NET_ADDRESS_END => panic!("Unexpected size access."),
+            VIRTIO_CONSOLE_ADDRESS_START..=VIRTIO_CONSOLE_ADDRESS_END => panic!("Unexpected size access."),
+            VIRTIO_RNG_ADDRESS_START..=VIRTIO_RNG_ADDRESS_END => panic!("Unexpected size access."),
+            VIRTIO_9P_ADDRESS_START..=VIRTIO_9P_ADDRESS_END => panic!("Unexpected size access."),
             _ => Err(()),
         }
     }
@@ -198,6 +333,18 @@ impl Bus for BusQemuVirt {
             VIRTIO_ADDRESS_START..=VIRTIO_ADDRESS_END => {
                 Ok(self.virtio.read(addr - VIRTIO_ADDRESS_START))
             }
+            VIRTIO_NET_ADDRESS_START..=VIRTIO_NET_ADDRESS_END => {
+                Ok(self.virtio_net.read(addr - VIRTIO_NET_ADDRESS_START))
+            }
+            VIRTIO_CONSOLE_ADDRESS_START..=VIRTIO_CONSOLE_ADDRESS_END => {
+                Ok(self.virtio_console.read(addr - VIRTIO_CONSOLE_ADDRESS_START))
+            }
+            VIRTIO_RNG_ADDRESS_START..=VIRTIO_RNG_ADDRESS_END => {
+                Ok(self.virtio_rng.read(addr - VIRTIO_RNG_ADDRESS_START))
+            }
+            VIRTIO_9P_ADDRESS_START..=VIRTIO_9P_ADDRESS_END => {
+                Ok(self.virtio_9p.read(addr - VIRTIO_9P_ADDRESS_START))
+            }
             _ => Err(()),
         }
     }
@@ -259,6 +406,30 @@ impl Bus for BusQemuVirt {
                     | ((self.virtio.read(virtio_addr.wrapping_add(4)) as u64) << 32);
                 Ok(data)
             }
+            VIRTIO_NET_ADDRESS_START..=VIRTIO_NET_ADDRESS_END => {
+                let virtio_addr = addr - VIRTIO_NET_ADDRESS_START;
+                let data = self.virtio_net.read(virtio_addr) as u64
+                    | ((self.virtio_net.read(virtio_addr.wrapping_add(4)) as u64) << 32);
+                Ok(data)
+            }
+            VIRTIO_CONSOLE_ADDRESS_START..=VIRTIO_CONSOLE_ADDRESS_END => {
+                let virtio_addr = addr - VIRTIO_CONSOLE_ADDRESS_START;
+                let data = self.virtio_console.read(virtio_addr) as u64
+                    | ((self.virtio_console.read(virtio_addr.wrapping_add(4)) as u64) << 32);
+                Ok(data)
+            }
+            VIRTIO_RNG_ADDRESS_START..=VIRTIO_RNG_ADDRESS_END => {
+                let virtio_addr = addr - VIRTIO_RNG_ADDRESS_START;
+                let data = self.virtio_rng.read(virtio_addr) as u64
+                    | ((self.virtio_rng.read(virtio_addr.wrapping_add(4)) as u64) << 32);
+                Ok(data)
+            }
+            VIRTIO_9P_ADDRESS_START..=VIRTIO_9P_ADDRESS_END => {
+                let virtio_addr = addr - VIRTIO_9P_ADDRESS_START;
+                let data = self.virtio_9p.read(virtio_addr) as u64
+                    | ((self.virtio_9p.read(virtio_addr.wrapping_add(4)) as u64) << 32);
+                Ok(data)
+            }
             _ => Err(()),
         }
     }
@@ -276,6 +447,10 @@ impl Bus for BusQemuVirt {
                 Ok(self.uart.write(addr - UART_ADDRESS_START, data))
             }
             VIRTIO_ADDRESS_START..=VIRTIO_ADDRESS_END => panic!("Unexpected size access."),
+            VIRTIO_NET_ADDRESS_START..=VIRTIO_NET_ADDRESS_END => panic!("Unexpected size access."),
+            VIRTIO_CONSOLE_ADDRESS_START..=VIRTIO_CONSOLE_ADDRESS_END => panic!("Unexpected size access."),
+            VIRTIO_RNG_ADDRESS_START..=VIRTIO_RNG_ADDRESS_END => panic!("Unexpected size access."),
+            VIRTIO_9P_ADDRESS_START..=VIRTIO_9P_ADDRESS_END => panic!("Unexpected size access."),
             _ => Err(()),
         }
     }
@@ -297,6 +472,10 @@ impl Bus for BusQemuVirt {
                 Ok(())
             }
             VIRTIO_ADDRESS_START..=VIRTIO_ADDRESS_END => panic!("Unexpected size access."),
+            VIRTIO_NET_ADDRESS_START..=VIRTIO_NET_ADDRESS_END => panic!("Unexpected size access."),
+            VIRTIO_CONSOLE_ADDRESS_START..=VIRTIO_CONSOLE_ADDRESS_END => panic!("Unexpected size access."),
+            VIRTIO_RNG_ADDRESS_START..=VIRTIO_RNG_ADDRESS_END => panic!("Unexpected size access."),
+            VIRTIO_9P_ADDRESS_START..=VIRTIO_9P_ADDRESS_END => panic!("Unexpected size access."),
             _ => Err(()),
         }
     }
@@ -328,6 +507,22 @@ impl Bus for BusQemuVirt {
             VIRTIO_ADDRESS_START..=VIRTIO_ADDRESS_END => {
                 Ok(self.virtio.write(addr - VIRTIO_ADDRESS_START, data))
             }
+            VIRTIO_NET_ADDRESS_START..=VIRTIO_NET_ADDRESS_END => {
+                self.virtio_net.write(addr - VIRTIO_NET_ADDRESS_START, data);
+                Ok(())
+            }
+            VIRTIO_CONSOLE_ADDRESS_START..=VIRTIO_CONSOLE_ADDRESS_END => {
+                self.virtio_console.write(addr - VIRTIO_CONSOLE_ADDRESS_START, data);
+                Ok(())
+            }
+            VIRTIO_RNG_ADDRESS_START..=VIRTIO_RNG_ADDRESS_END => {
+                self.virtio_rng.write(addr - VIRTIO_RNG_ADDRESS_START, data);
+                Ok(())
+            }
+            VIRTIO_9P_ADDRESS_START..=VIRTIO_9P_ADDRESS_END => {
+                self.virtio_9p.write(addr - VIRTIO_9P_ADDRESS_START, data);
+                Ok(())
+            }
             _ => Err(()),
         }
     }
@@ -385,6 +580,42 @@ impl Bus for BusQemuVirt {
                 );
                 Ok(())
             }
+            VIRTIO_NET_ADDRESS_START..=VIRTIO_NET_ADDRESS_END => {
+                let virtio_addr = addr - VIRTIO_NET_ADDRESS_START;
+                self.virtio_net.write(virtio_addr, data as u32);
+                self.virtio_net.write(
+                    virtio_addr.wrapping_add(4),
+                    ((data >> 32) & 0xffffffff) as u32,
+                );
+                Ok(())
+            }
+            VIRTIO_CONSOLE_ADDRESS_START..=VIRTIO_CONSOLE_ADDRESS_END => {
+                let virtio_addr = addr - VIRTIO_CONSOLE_ADDRESS_START;
+                self.virtio_console.write(virtio_addr, data as u32);
+                self.virtio_console.write(
+                    virtio_addr.wrapping_add(4),
+                    ((data >> 32) & 0xffffffff) as u32,
+                );
+                Ok(())
+            }
+            VIRTIO_RNG_ADDRESS_START..=VIRTIO_RNG_ADDRESS_END => {
+                let virtio_addr = addr - VIRTIO_RNG_ADDRESS_START;
+                self.virtio_rng.write(virtio_addr, data as u32);
+                self.virtio_rng.write(
+                    virtio_addr.wrapping_add(4),
+                    ((data >> 32) & 0xffffffff) as u32,
+                );
+                Ok(())
+            }
+            VIRTIO_9P_ADDRESS_START..=VIRTIO_9P_ADDRESS_END => {
+                let virtio_addr = addr - VIRTIO_9P_ADDRESS_START;
+                self.virtio_9p.write(virtio_addr, data as u32);
+                self.virtio_9p.write(
+                    virtio_addr.wrapping_add(4),
+                    ((data >> 32) & 0xffffffff) as u32,
+                );
+                Ok(())
+            }
             _ => Err(()),
         }
     }
@@ -1,8 +1,18 @@
+use std::thread;
+use std::time::Duration;
+
 pub trait Console {
     fn putchar(&mut self, c: u8);
     fn getchar(&mut self) -> u8;
     fn set_input(&mut self, c: u8);
     fn get_output(&mut self) -> u8;
+
+    /// wait up to timeout for an input, and tell whether getchar has an input. The
+    /// consoles without an input to wait for sleep for the timeout.
+    fn wait_input(&mut self, timeout: Duration) -> bool {
+        thread::sleep(timeout);
+        false
+    }
 }
 
 pub struct TtyDummy {}
@@ -0,0 +1,87 @@
+use crate::cpu::decode_cache::{CacheableMemories, DecodedInstruction};
+use crate::peripherals::memory::PageWrites;
+use std::rc::Rc;
+
+const BLOCK_CACHE_ENTRIES: usize = 4096;
+
+/// the maximum number of instructions in a basic block.
+pub const BLOCK_INSTRUCTIONS_MAX: usize = 64;
+
+/// A basic block is the instructions decoded from a physical address up to a control
+/// transfer, a system instruction, a fence or the end of the page. bytes is the
+/// length of the block, and compressed tells whether it has a compressed instruction.
+pub struct Block {
+    p_addr: u64,
+    page_writes: u64,
+    pub bytes: u64,
+    pub compressed: bool,
+    pub instructions: Vec<DecodedInstruction>,
+}
+
+/// The block cache holds the basic blocks translated by the block engine. It is
+/// direct-mapped by the physical address of the first instruction, and a block is
+/// valid while the write counter of its page is unchanged as the decoded instruction
+/// cache.
+pub struct BlockCache {
+    entries: Vec<Option<Rc<Block>>>,
+    memories: CacheableMemories,
+}
+
+impl BlockCache {
+    pub fn new(memories: Vec<(u64, PageWrites)>) -> Self {
+        BlockCache {
+            entries: vec![None; BLOCK_CACHE_ENTRIES],
+            memories: CacheableMemories::new(memories),
+        }
+    }
+
+    pub fn get(&self, p_addr: u64) -> Option<Rc<Block>> {
+        match &self.entries[Self::index(p_addr)] {
+            Some(block) if block.p_addr == p_addr && self.is_valid(block) => Some(block.clone()),
+            _ => None,
+        }
+    }
+
+    /// create a block from the instructions decoded at p_addr, or None if the
+    /// address is not in the memories.
+    pub fn insert(
+        &mut self,
+        p_addr: u64,
+        compressed: bool,
+        instructions: Vec<DecodedInstruction>,
+    ) -> Option<Rc<Block>> {
+        let page_writes = match self.memories.get_page_writes(p_addr) {
+            Some(page_writes) => page_writes,
+            None => return None,
+        };
+        let block = Rc::new(Block {
+            p_addr,
+            page_writes,
+            bytes: instructions.iter().map(|i| i.size).sum(),
+            compressed,
+            instructions,
+        });
+        self.entries[Self::index(p_addr)] = Some(block.clone());
+        Some(block)
+    }
+
+    /// check the page of the block has not been written since it was translated.
+    pub fn is_valid(&self, block: &Block) -> bool {
+        self.memories.get_page_writes(block.p_addr) == Some(block.page_writes)
+    }
+
+    pub fn is_cacheable(&self, p_addr: u64) -> bool {
+        self.memories.contains(p_addr)
+    }
+
+    /// fence.i and the change of XLEN drop all blocks.
+    pub fn flush(&mut self) {
+        for entry in self.entries.iter_mut() {
+            *entry = None;
+        }
+    }
+
+    fn index(p_addr: u64) -> usize {
+        ((p_addr >> 1) as usize) & (BLOCK_CACHE_ENTRIES - 1)
+    }
+}
@@ -1,11 +1,14 @@
-use crate::bus::bus::Device;
-use crate::console::Console;
+use crate::bus::bus::{Bus, Device};
+use crate::cpu::block_cache::{Block, BlockCache, BLOCK_INSTRUCTIONS_MAX};
 use crate::cpu::cpu_csr::*;
 use crate::cpu::cpu_instruction::{Opecode, OPECODES};
 use crate::cpu::cpu_instruction_comp::*;
-use crate::cpu::mmu::Mmu;
+use crate::cpu::cpu_instruction_vector::VLEN_DEFAULT;
+use crate::cpu::decode_cache::{DecodeCache, DecodedInstruction};
+use crate::cpu::mmu::{Mmu, Reservations};
 use crate::cpu::trap::*;
-use crate::machine::Machine;
+use std::cell::RefCell;
+use std::rc::Rc;
 
 #[derive(Clone, Debug)]
 pub enum Xlen {
@@ -13,43 +16,66 @@ pub enum Xlen {
     X64 = 1,
 }
 
+/// HS-mode of the hypervisor extension is Supervisor with V=0, and VS-mode and
+/// VU-mode are Supervisor and User with V=1 respectively.
 #[derive(Clone, Debug)]
 pub enum Privilege {
     User = 0,
     Supervisor = 1,
-    Hypervisor = 2,
     Machine = 3,
 }
 
 pub struct Cpu {
-    cycle: u64,
+    hart_id: usize,
+    pub cycle: u64,
     pub pc: u64,
     pub wfi: bool,
     pub xlen: Xlen,
     pub privilege: Privilege,
+    pub virtualization_mode: bool,
     pub x: [i64; 32],
-    pub f: [f64; 32],
+    pub f: [u64; 32],
+    pub v: Vec<u8>,
     pub csr: Csr,
     pub mmu: Mmu,
+    decode_cache: DecodeCache,
+    block_cache: BlockCache,
     testmode: bool,
 }
 
 impl Cpu {
-    pub fn new(machine_: Machine, console: Box<dyn Console>, testmode_: bool) -> Self {
+    pub fn new(
+        hart_id_: usize,
+        bus: Rc<RefCell<dyn Bus>>,
+        reserved_address: Reservations,
+        testmode_: bool,
+    ) -> Self {
+        let decode_cache = DecodeCache::new(bus.borrow_mut().get_page_writes());
+        let block_cache = BlockCache::new(bus.borrow_mut().get_page_writes());
         let mut cpu = Cpu {
+            hart_id: hart_id_,
             cycle: 0,
             pc: 0,
             wfi: false,
             xlen: Xlen::X64,
             privilege: Privilege::Machine,
+            virtualization_mode: false,
             x: [0; 32],
-            f: [0.0; 32],
+            f: [0; 32],
+            v: vec![],
             csr: Csr::new(),
-            mmu: Mmu::new(Xlen::X64, machine_, console),
+            mmu: Mmu::new(Xlen::X64, hart_id_, bus, reserved_address),
+            decode_cache,
+            block_cache,
             testmode: testmode_,
         };
 
-        // initial value for Linux booting (DTB start address).
+        cpu.set_vlen(VLEN_DEFAULT);
+        cpu.set_pmp_entries(PMP_ENTRIES_DEFAULT);
+        cpu.csr.write_direct(CSR_MHARTID, hart_id_ as u64);
+
+        // initial value for Linux booting (hart ID and DTB start address).
+        cpu.x[0xa] = hart_id_ as i64;
         cpu.x[0xb] = cpu.mmu.get_bus().get_base_address(Device::DTB) as i64;
         cpu
     }
@@ -58,19 +84,47 @@ impl Cpu {
         self.pc = 0;
         self.cycle = 0;
         self.privilege = Privilege::Machine;
+        self.virtualization_mode = false;
         self.wfi = false;
         self.xlen = Xlen::X64;
         self.x = [0; 32];
-        self.f = [0.0; 32];
+        self.f = [0; 32];
+        for data in self.v.iter_mut() {
+            *data = 0;
+        }
     }
 
     pub fn set_pc(&mut self, pc: u64) {
         self.pc = pc;
     }
 
+    /// set the number of bits in a single vector register (VLEN).
+    /// VLEN must be a power of 2 and no less than ELEN (64 bits).
+    pub fn set_vlen(&mut self, vlen: usize) {
+        if !vlen.is_power_of_two() || !(64..=65536).contains(&vlen) {
+            panic!("Unsupported VLEN: {}", vlen);
+        }
+        self.v = vec![0; vlen / 8 * 32];
+        self.csr.write_direct(CSR_VLENB, (vlen / 8) as u64);
+    }
+
+    /// set the number of physical memory protection entries (0 to 64).
+    pub fn set_pmp_entries(&mut self, entries: usize) {
+        self.csr.set_pmp_entries(entries);
+        self.mmu.update_pmp(&self.csr);
+    }
+
     pub fn set_xlen(&mut self, xlen: Xlen) {
         self.xlen = xlen;
         self.mmu.set_xlen(&self.xlen);
+        self.csr.set_xlen(&self.xlen);
+        self.flush_instruction_caches();
+    }
+
+    /// drop the decoded instructions and the basic blocks.
+    pub fn flush_instruction_caches(&mut self) {
+        self.decode_cache.flush();
+        self.block_cache.flush();
     }
 
     pub fn tick(&mut self) {
@@ -86,57 +140,153 @@ impl Cpu {
                 Err(e) => self.catch_exception(e, instruction_addr),
             }
         }
+        self.tick_cycle();
+    }
+
+    /// run the basic blocks from PC by the block engine until cycles_max cycles, and
+    /// return the number of cycles. bus_ticks is the number of ticks of the bus since
+    /// the harts started the blocks. The bus is advanced before a load or store to a
+    /// device and between the blocks, so that the devices and the time CSR see the
+    /// same cycle as the interpreter. The run stops early at the block which cannot
+    /// be followed by another one without the bus: it waits for an interrupt, it
+    /// traps, it accesses a device, or it is run by the interpreter.
+    pub fn tick_block(&mut self, bus_ticks: &mut u64, cycles_max: u64) -> u64 {
+        let mut cycles = 0;
+        loop {
+            let (block_cycles, stopped) = self.run_block(bus_ticks, cycles);
+            cycles += block_cycles;
+            if stopped || cycles >= cycles_max {
+                return cycles;
+            }
+            self.mmu.set_bus_ticks(*bus_ticks, cycles);
+            self.mmu.tick_bus();
+            *bus_ticks = self.mmu.get_bus_ticks();
+            self.mmu.set_bus_ticks(0, 0);
+            let mtime = self.mmu.get_bus().get_mtime();
+            self.csr.set_time(mtime);
+        }
+    }
 
-        // run peripherals.
-        let bus = self.mmu.get_bus();
-        let irqs = bus.tick();
+    /// run the basic block at PC, and return the number of cycles and whether the
+    /// run of the blocks stops. The interrupts are taken only before the block, and
+    /// the block exits after a trap, after a load or store to a device other than
+    /// the memories, and after a store into its own page, so PC and the registers
+    /// are the same as the interpreter when the block exits. start is the number of
+    /// cycles run before the block.
+    fn run_block(&mut self, bus_ticks: &mut u64, start: u64) -> (u64, bool) {
+        match self.check_interrupts() {
+            Some(interrupt) => self.interrupt_handler(interrupt),
+            None => {}
+        }
 
-        // handle interrupt.
-        self.tick_interrupt(&irqs);
+        if self.wfi {
+            self.tick_cycle();
+            return (1, true);
+        }
+        let block = match self.get_block() {
+            Some(block) => block,
+            None => {
+                let instruction_addr = self.pc;
+                match self.tick_execute() {
+                    Ok(()) => {}
+                    Err(e) => self.catch_exception(e, instruction_addr),
+                }
+                self.tick_cycle();
+                return (1, true);
+            }
+        };
+
+        self.mmu.take_mmio_access();
+        let mut cycles = 0;
+        let mut stopped = false;
+        for entry in block.instructions.iter() {
+            let instruction_addr = self.pc;
+            self.pc = instruction_addr.wrapping_add(entry.size);
+            self.mmu.set_bus_ticks(*bus_ticks, start + cycles);
+            let result = self.execute(instruction_addr, entry);
+            *bus_ticks = self.mmu.get_bus_ticks();
+            cycles += 1;
+            if let Err(e) = result {
+                self.catch_exception(e, instruction_addr);
+                self.tick_cycle();
+                stopped = true;
+                break;
+            }
+            self.tick_cycle();
+            if self.mmu.take_mmio_access() {
+                stopped = true;
+                break;
+            }
+            if matches!(entry.word & 0x7f, 0x23 | 0x27 | 0x2f) && !self.block_cache.is_valid(&block)
+            {
+                break;
+            }
+        }
+        self.mmu.set_bus_ticks(0, 0);
+        (cycles, stopped)
+    }
 
+    fn tick_cycle(&mut self) {
+        for _ in 0..self.mmu.take_tlb_misses() {
+            self.csr.count_event(HPM_EVENT_TLB_MISS);
+        }
         self.cycle = self.cycle.wrapping_add(1);
-        self.csr.write_direct(CSR_CYCLE, self.cycle);
         self.csr.tick();
     }
 
     fn tick_execute(&mut self) -> Result<(), Trap> {
         let instruction_addr = self.pc;
-        let word = match self.fetch() {
-            Ok(_word) => _word,
+        let decoded = match self.fetch_decode() {
+            Ok(decoded) => decoded,
             Err(e) => return Err(e),
         };
+        self.execute(instruction_addr, &decoded)
+    }
 
-        let mut debug_message = String::new();
+    /// execute the decoded instruction at instruction_addr. PC has been advanced to
+    /// the next instruction.
+    fn execute(&mut self, instruction_addr: u64, decoded: &DecodedInstruction) -> Result<(), Trap> {
+        let instruction = decoded.instruction;
+        let word = decoded.word;
         if self.testmode {
+            let mut debug_message = String::new();
             debug_message += &format!("[PC]: {:016x}", instruction_addr);
             debug_message += &format!(" [P]: {:?}", self.privilege);
+            debug_message += &format!(" [V]: {:?}", self.virtualization_mode);
             debug_message += &format!(" [XLEN]: {:?} |", self.xlen);
             debug_message += &format!("    {:08x}    ", word);
             match self.pc.wrapping_sub(instruction_addr) {
                 0x2 => debug_message += "(C)",
                 _ => debug_message += "   ",
             };
-        }
-
-        // instruction decode.
-        let instruction = match self.decode(word) {
-            Ok(opecode) => match (opecode.operation)(self, instruction_addr, word) {
-                Ok(_instruction) => _instruction,
-                Err(()) => panic!("Not found instruction: {:016x}", instruction_addr),
-            },
-            Err(e) => return Err(e),
-        };
-
-        // instruction execute.
-        if self.testmode {
             let dis = (instruction.disassemble)(self, instruction.mnemonic, word);
             debug_message += &format!("{}", dis);
             println!("{}", debug_message);
         }
-        match (instruction.operation)(self, instruction_addr, word) {
+
+        // instruction execute.
+        let next_pc = self.pc;
+        let result = match decoded.operation {
+            Some(operation) => operation(self, instruction_addr, &decoded.operands),
+            None => (instruction.operation)(self, instruction_addr, word),
+        };
+        match result {
+            // The illegal instruction exception of a compressed instruction reports
+            // the 16-bit instruction instead of the expanded one.
+            Err(Trap {
+                exception: Exception::IllegalInstruction,
+                ..
+            }) if next_pc.wrapping_sub(instruction_addr) == 2 => {
+                return Err(Trap {
+                    exception: Exception::IllegalInstruction,
+                    value: self.read_compressed_instruction(instruction_addr),
+                })
+            }
             Err(e) => return Err(e),
             _ => {}
         }
+        self.csr.retire();
+        self.count_events(word, next_pc);
 
         // x0 register is hardwired to the constant 0. To simplify the implementation,
         // I don't care that x0 is always zero in each instruction implementation.
@@ -145,8 +295,191 @@ impl Cpu {
         return Ok(());
     }
 
-    fn tick_interrupt(&mut self, irqs: &Vec<bool>) {
-        let bus = self.mmu.get_bus();
+    /// read the 16 bits of the compressed instruction at instruction_addr, which has
+    /// been fetched successfully.
+    fn read_compressed_instruction(&mut self, instruction_addr: u64) -> u64 {
+        match self.mmu.translate_fetch(instruction_addr, 2) {
+            Ok(p_addr) => match self.mmu.get_bus().read16(p_addr) {
+                Ok(halfword) => halfword as u64,
+                Err(()) => 0,
+            },
+            Err(_) => 0,
+        }
+    }
+
+    /// find the basic block at PC, or translate the instructions from PC into a new
+    /// block. None if the instruction at PC is run by the interpreter, since it traps
+    /// on the fetch, it is not in the memories, or PMP does not allow the fetch of
+    /// the whole block.
+    fn get_block(&mut self) -> Option<Rc<Block>> {
+        let instruction_addr = self.pc;
+        let p_addr = match self.mmu.translate_fetch(instruction_addr, 2) {
+            Ok(p_addr) => p_addr,
+            Err(_) => return None,
+        };
+        let block = match self.block_cache.get(p_addr) {
+            Some(block) => block,
+            None => match self.translate_block(instruction_addr, p_addr) {
+                Some(block) => block,
+                None => return None,
+            },
+        };
+        if block.compressed && !self.csr.is_compressed_enabled() {
+            return None;
+        }
+        if block.bytes > 2
+            && self
+                .mmu
+                .translate_fetch(instruction_addr, block.bytes)
+                .is_err()
+        {
+            return None;
+        }
+        Some(block)
+    }
+
+    /// decode the instructions from p_addr up to the end of the basic block. The
+    /// instruction which does not decode ends the block before it, so that the
+    /// interpreter raises the exception.
+    fn translate_block(&mut self, instruction_addr: u64, p_addr: u64) -> Option<Rc<Block>> {
+        if !self.block_cache.is_cacheable(p_addr) {
+            return None;
+        }
+        let page_end = (p_addr | 0xfff) + 1;
+        let mut instructions = vec![];
+        let mut compressed = false;
+        let mut addr = p_addr;
+        while instructions.len() < BLOCK_INSTRUCTIONS_MAX && addr + 2 <= page_end {
+            let halfword = match self.mmu.get_bus().read16(addr) {
+                Ok(data) => data as u32,
+                Err(()) => break,
+            };
+            let v_addr = instruction_addr.wrapping_add(addr - p_addr);
+            let (word, size) = match (halfword & 0x3) == 0x3 {
+                true => {
+                    if addr + 4 > page_end {
+                        break;
+                    }
+                    match self.mmu.get_bus().read32(addr) {
+                        Ok(word) => (word, 4),
+                        Err(()) => break,
+                    }
+                }
+                false => {
+                    if !self.csr.is_compressed_enabled() {
+                        break;
+                    }
+                    match instruction_decompress(self, v_addr, halfword) {
+                        Ok(word) => (word, 2),
+                        Err(()) => break,
+                    }
+                }
+            };
+            let instruction = match self.decode(word) {
+                Ok(opecode) => match (opecode.operation)(self, v_addr, word) {
+                    Ok(instruction) => instruction,
+                    Err(()) => break,
+                },
+                Err(_) => break,
+            };
+            compressed |= size == 2;
+            instructions.push(DecodedInstruction::new(word, size, instruction));
+            addr += size;
+
+            // control transfers, system instructions and fences end the block.
+            match word & 0x7f {
+                0x0f | 0x63 | 0x67 | 0x6f | 0x73 => break,
+                _ => {}
+            }
+        }
+        if instructions.is_empty() {
+            return None;
+        }
+        self.block_cache.insert(p_addr, compressed, instructions)
+    }
+
+    /// fetch the instruction at PC and resolve its handler, by the decoded instruction
+    /// cache if the instruction has been decoded at the physical address. PC is
+    /// advanced to the next instruction.
+    fn fetch_decode(&mut self) -> Result<DecodedInstruction, Trap> {
+        let instruction_addr = self.pc;
+        let p_addr = match self.mmu.translate_fetch(instruction_addr, 2) {
+            Ok(p_addr) => p_addr,
+            Err(e) => return Err(e),
+        };
+        match self.decode_cache.get(p_addr) {
+            Some(decoded) => {
+                // PMP checks the whole 4-byte instruction at a 2-byte aligned address,
+                // and compressed instructions are illegal while C is disabled.
+                let executable = match decoded.size {
+                    4 => {
+                        (p_addr & 0x3) == 0 || self.mmu.translate_fetch(instruction_addr, 4).is_ok()
+                    }
+                    _ => self.csr.is_compressed_enabled(),
+                };
+                if executable {
+                    self.pc = instruction_addr.wrapping_add(decoded.size);
+                    return Ok(decoded);
+                }
+            }
+            None => {}
+        }
+
+        let word = match self.fetch() {
+            Ok(_word) => _word,
+            Err(e) => return Err(e),
+        };
+
+        // instruction decode.
+        let instruction = match self.decode(word) {
+            Ok(opecode) => match (opecode.operation)(self, instruction_addr, word) {
+                Ok(_instruction) => _instruction,
+                Err(()) => {
+                    return Err(Trap {
+                        exception: Exception::IllegalInstruction,
+                        value: word as u64,
+                    })
+                }
+            },
+            Err(e) => return Err(e),
+        };
+        let size = self.pc.wrapping_sub(instruction_addr);
+        let decoded = DecodedInstruction::new(word, size, instruction);
+        self.decode_cache.insert(p_addr, decoded);
+        Ok(decoded)
+    }
+
+    /// count the hardware performance events of the retired instruction. next_pc is
+    /// the address of the following instruction, which tells whether the branch is taken.
+    fn count_events(&mut self, word: u32, next_pc: u64) {
+        match word & 0x7f {
+            0x03 | 0x07 => self.csr.count_event(HPM_EVENT_LOAD),
+            0x23 | 0x27 => self.csr.count_event(HPM_EVENT_STORE),
+            // lr is a load, sc is a store, and AMOs are both.
+            0x2f => match word >> 27 {
+                0x02 => self.csr.count_event(HPM_EVENT_LOAD),
+                0x03 => self.csr.count_event(HPM_EVENT_STORE),
+                _ => {
+                    self.csr.count_event(HPM_EVENT_LOAD);
+                    self.csr.count_event(HPM_EVENT_STORE);
+                }
+            },
+            0x63 => {
+                self.csr.count_event(HPM_EVENT_BRANCH);
+                if self.pc != next_pc {
+                    self.csr.count_event(HPM_EVENT_BRANCH_TAKEN);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// reflect the interrupts routed to this hart by the bus after the peripherals
+    /// have been ticked.
+    pub fn tick_interrupt(&mut self) {
+        let hart_id = self.hart_id;
+        let mut bus = self.mmu.get_bus();
+        let irqs = bus.get_external_interrupts(hart_id);
 
         // set external interrupts to CSR register.
         if irqs[Privilege::Machine as usize] {
@@ -155,12 +488,6 @@ impl Cpu {
             self.csr.read_modify_write_direct(CSR_MIP, 0, CSR_IP_MEIP);
         }
 
-        if irqs[Privilege::Hypervisor as usize] {
-            self.csr.read_modify_write_direct(CSR_MIP, CSR_IP_HEIP, 0);
-        } else {
-            self.csr.read_modify_write_direct(CSR_MIP, 0, CSR_IP_HEIP);
-        }
-
         if irqs[Privilege::Supervisor as usize] {
             self.csr.read_modify_write_direct(CSR_SIP, CSR_IP_SEIP, 0);
         } else {
@@ -173,21 +500,41 @@ impl Cpu {
             self.csr.read_modify_write_direct(CSR_UIP, 0, CSR_IP_UEIP);
         }
 
+        // time CSR follows mtime of the CLINT, which also drives STIP by stimecmp.
+        self.csr.set_time(bus.get_mtime());
+
         // set timer interrupt.
-        if bus.is_pending_timer_interrupt(0) {
+        if bus.is_pending_timer_interrupt(hart_id) {
             self.csr.read_modify_write_direct(CSR_MIP, CSR_IP_MTIP, 0);
         } else {
             self.csr.read_modify_write_direct(CSR_MIP, 0, CSR_IP_MTIP);
         }
 
         // set software interrupt.
-        if bus.is_pending_software_interrupt(0) {
+        if bus.is_pending_software_interrupt(hart_id) {
             self.csr.read_modify_write_direct(CSR_MIP, CSR_IP_MSIP, 0);
         } else {
             self.csr.read_modify_write_direct(CSR_MIP, 0, CSR_IP_MSIP);
         }
     }
 
+    /// the hart waits for an interrupt, and no interrupt can be taken yet.
+    pub fn is_idle(&mut self) -> bool {
+        self.wfi && self.check_interrupts().is_none()
+    }
+
+    /// the number of cycles until an interrupt of the hart itself becomes pending
+    /// without the devices, which is the supervisor timer interrupt of Sstc.
+    pub fn get_cycles_to_interrupt(&self) -> Option<u64> {
+        self.csr.get_cycles_to_supervisor_timer()
+    }
+
+    /// advance the counters by the cycles in which the idle hart would do nothing.
+    pub fn skip_cycles(&mut self, cycles: u64) {
+        self.cycle = self.cycle.wrapping_add(cycles);
+        self.csr.skip(cycles);
+    }
+
     fn fetch(&mut self) -> Result<u32, Trap> {
         let fetch_word = match self.mmu.fetch32(self.pc) {
             Ok(word) => word,
@@ -202,12 +549,18 @@ impl Cpu {
             }
             // 16bit compressed instruction
             false => {
+                if !self.csr.is_compressed_enabled() {
+                    return Err(Trap {
+                        exception: Exception::IllegalInstruction,
+                        value: (fetch_word & 0xffff) as u64,
+                    });
+                }
                 self.pc = self.pc.wrapping_add(2);
                 return match instruction_decompress(self, self.pc.wrapping_sub(2), fetch_word) {
                     Ok(word) => Ok(word),
                     Err(()) => Err(Trap {
                         exception: Exception::IllegalInstruction,
-                        value: self.pc.wrapping_sub(2),
+                        value: (fetch_word & 0xffff) as u64,
                     }),
                 };
             }
@@ -217,7 +570,12 @@ impl Cpu {
     fn decode(&mut self, word: u32) -> Result<&Opecode, Trap> {
         match OPECODES.get(&((word & 0x7f) as u8)) {
             Some(opecode) => return Ok(&opecode),
-            None => panic!("Not found opecode: {:016x}", word),
+            None => {
+                return Err(Trap {
+                    exception: Exception::IllegalInstruction,
+                    value: word as u64,
+                })
+            }
         }
     }
 
@@ -231,10 +589,22 @@ impl Cpu {
 
         let trap_code = trap.exception as u8;
         let previous_privilege = self.privilege.clone();
-        let next_privilege = self.get_next_privilege(trap_code, false);
+        let previous_virtualization_mode = self.virtualization_mode;
+        let (next_privilege, next_virtualization_mode) = self.get_next_privilege(trap_code, false);
         self.change_privilege(next_privilege);
-        self.update_csr_trap_registers(addr, trap_code, trap.value, previous_privilege, false);
+        self.change_virtualization_mode(next_virtualization_mode);
+        self.update_csr_trap_registers(
+            addr,
+            trap_code,
+            trap.value,
+            previous_privilege,
+            previous_virtualization_mode,
+            false,
+        );
+        self.mmu.update_status(&self.csr);
         self.pc = self.get_trap_next_pc();
+        self.mmu.cancel_reservation();
+        self.csr.count_event(HPM_EVENT_TRAP);
     }
 
     fn check_interrupts(&mut self) -> Option<Interrupt> {
@@ -253,15 +623,6 @@ impl Cpu {
         if cause & CSR_IP_MTIP > 0 && self.select_handling_interrupt(Interrupt::MachineTimer) {
             return Some(Interrupt::MachineTimer);
         }
-        if cause & CSR_IP_HEIP > 0 {
-            panic!("Unexpected event happend!");
-        }
-        if cause & CSR_IP_HTIP > 0 {
-            panic!("Unexpected event happend!");
-        }
-        if cause & CSR_IP_HSIP > 0 {
-            panic!("Unexpected event happend!");
-        }
         if cause & CSR_IP_SEIP > 0 && self.select_handling_interrupt(Interrupt::SupervisorExternal)
         {
             return Some(Interrupt::SupervisorExternal);
@@ -273,6 +634,21 @@ impl Cpu {
         if cause & CSR_IP_STIP > 0 && self.select_handling_interrupt(Interrupt::SupervisorTimer) {
             return Some(Interrupt::SupervisorTimer);
         }
+        if cause & CSR_IP_VSEIP > 0
+            && self.select_handling_interrupt(Interrupt::VirtualSupervisorExternal)
+        {
+            return Some(Interrupt::VirtualSupervisorExternal);
+        }
+        if cause & CSR_IP_VSSIP > 0
+            && self.select_handling_interrupt(Interrupt::VirtualSupervisorSoftware)
+        {
+            return Some(Interrupt::VirtualSupervisorSoftware);
+        }
+        if cause & CSR_IP_VSTIP > 0
+            && self.select_handling_interrupt(Interrupt::VirtualSupervisorTimer)
+        {
+            return Some(Interrupt::VirtualSupervisorTimer);
+        }
         if cause & CSR_IP_UEIP > 0 && self.select_handling_interrupt(Interrupt::UserExternal) {
             return Some(Interrupt::UserExternal);
         }
@@ -298,11 +674,23 @@ impl Cpu {
 
         let trap_code = interrupt as u8;
         let previous_privilege = self.privilege.clone();
-        let next_privilege = self.get_next_privilege(trap_code, true);
+        let previous_virtualization_mode = self.virtualization_mode;
+        let (next_privilege, next_virtualization_mode) = self.get_next_privilege(trap_code, true);
 
         self.change_privilege(next_privilege);
-        self.update_csr_trap_registers(self.pc, trap_code, self.pc, previous_privilege, true);
+        self.change_virtualization_mode(next_virtualization_mode);
+        self.update_csr_trap_registers(
+            self.pc,
+            trap_code,
+            self.pc,
+            previous_privilege,
+            previous_virtualization_mode,
+            true,
+        );
+        self.mmu.update_status(&self.csr);
         self.pc = self.get_trap_next_pc();
+        self.mmu.cancel_reservation();
+        self.csr.count_event(HPM_EVENT_TRAP);
 
         self.wfi = false;
     }
@@ -312,202 +700,164 @@ impl Cpu {
         self.csr.write_direct(
             CSR_MIP,
             mip & !match interrupt {
+                Interrupt::SupervisorGuestExternal => 0x1000,
                 Interrupt::MachineExternal => 0x800,
+                Interrupt::VirtualSupervisorExternal => 0x400,
                 Interrupt::SupervisorExternal => 0x200,
                 Interrupt::UserExternal => 0x100,
                 Interrupt::MachineTimer => 0x080,
+                Interrupt::VirtualSupervisorTimer => 0x040,
                 Interrupt::SupervisorTimer => 0x020,
                 Interrupt::UserTimer => 0x010,
                 Interrupt::MachineSoftware => 0x008,
+                Interrupt::VirtualSupervisorSoftware => 0x004,
                 Interrupt::SupervisorSoftware => 0x002,
                 Interrupt::UserSoftware => 0x001,
             },
         );
     }
 
+    /// Interrupts for a more privileged mode are always enabled, and interrupts for a
+    /// less privileged mode are always disabled. Interrupts for the current mode are
+    /// enabled by the xIE bit of the status register. VS-level interrupts delegated to
+    /// VS-mode are taken only when V=1, and interrupts for HS-mode are always enabled
+    /// when V=1.
     fn select_handling_interrupt(&mut self, interrupt: Interrupt) -> bool {
         let trap_code = interrupt as u8;
-        let next_privilege = self.get_next_privilege(trap_code, true);
-        let ie = match next_privilege {
-            Privilege::User => self.csr.read_direct(CSR_UIE),
-            Privilege::Supervisor => self.csr.read_direct(CSR_SIE),
-            Privilege::Hypervisor => self.csr.read_direct(CSR_HIE),
-            Privilege::Machine => self.csr.read_direct(CSR_MIE),
-        };
-        let status = self.csr.read_direct(match self.privilege {
-            Privilege::User => CSR_USTATUS,
-            Privilege::Supervisor => CSR_SSTATUS,
-            Privilege::Hypervisor => CSR_HSTATUS,
-            Privilege::Machine => CSR_MSTATUS,
-        });
-
-        let next_privilege_level = next_privilege.clone() as u8;
-        let privilege_level = self.privilege.clone() as u8;
-        if next_privilege_level < privilege_level {
-            return false;
-        }
-
-        let uie = status & 1;
-        let sie = (status >> 1) & 1;
-        let hie = (status >> 2) & 1;
-        let mie = (status >> 3) & 1;
-        if privilege_level == next_privilege_level {
-            match self.privilege {
-                Privilege::User => {
-                    if uie == 0 {
-                        return false;
-                    }
-                }
-                Privilege::Supervisor => {
-                    if sie == 0 {
-                        return false;
-                    }
-                }
-                Privilege::Hypervisor => {
-                    if hie == 0 {
-                        return false;
-                    }
+        let (next_privilege, next_virtualization_mode) = self.get_next_privilege(trap_code, true);
+        match (next_privilege, next_virtualization_mode) {
+            (Privilege::Machine, _) => match self.privilege {
+                Privilege::Machine => (self.csr.read_direct(CSR_MSTATUS) & CSR_STATUS_MIE) != 0,
+                _ => true,
+            },
+            (Privilege::Supervisor, false) => match (&self.privilege, self.virtualization_mode) {
+                (Privilege::Machine, _) => false,
+                (Privilege::Supervisor, false) => {
+                    (self.csr.read_direct(CSR_SSTATUS) & CSR_STATUS_SIE) != 0
                 }
-                Privilege::Machine => {
-                    if mie == 0 {
-                        return false;
-                    }
+                _ => true,
+            },
+            (Privilege::Supervisor, true) => match (&self.privilege, self.virtualization_mode) {
+                (Privilege::Supervisor, true) => {
+                    (self.csr.read_direct(CSR_VSSTATUS) & CSR_STATUS_SIE) != 0
                 }
-            };
+                (Privilege::User, true) => true,
+                _ => false,
+            },
+            (Privilege::User, _) => match self.privilege {
+                Privilege::User => (self.csr.read_direct(CSR_USTATUS) & CSR_STATUS_UIE) != 0,
+                _ => false,
+            },
         }
-
-        match interrupt {
-            Interrupt::MachineExternal => {
-                let meie = (ie >> 11) & 1;
-                if meie == 0 {
-                    return false;
-                }
-            }
-            Interrupt::MachineSoftware => {
-                let msie = (ie >> 3) & 1;
-                if msie == 0 {
-                    return false;
-                }
-            }
-            Interrupt::MachineTimer => {
-                let mtie = (ie >> 7) & 1;
-                if mtie == 0 {
-                    return false;
-                }
-            }
-            // TODO: support Hypervisor interrupts.
-            Interrupt::SupervisorExternal => {
-                let seie = (ie >> 9) & 1;
-                if seie == 0 {
-                    return false;
-                }
-            }
-            Interrupt::SupervisorSoftware => {
-                let ssie = (ie >> 1) & 1;
-                if ssie == 0 {
-                    return false;
-                }
-            }
-            Interrupt::SupervisorTimer => {
-                let stie = (ie >> 5) & 1;
-                if stie == 0 {
-                    return false;
-                }
-            }
-            Interrupt::UserExternal => {
-                let ueie = (ie >> 8) & 1;
-                if ueie == 0 {
-                    return false;
-                }
-            }
-            Interrupt::UserSoftware => {
-                let usie = ie & 1;
-                if usie == 0 {
-                    return false;
-                }
-            }
-            Interrupt::UserTimer => {
-                let utie = (ie >> 4) & 1;
-                if utie == 0 {
-                    return false;
-                }
-            }
-        };
-        true
     }
 
     /// update CSR/xEPC, xCAUSE, xTVAL, xSTATUS registers by interrupts.
+    /// A trap into VS-mode updates the virtual supervisor CSRs instead of the
+    /// supervisor CSRs.
     fn update_csr_trap_registers(
         &mut self,
         exception_pc: u64,
         trap_code: u8,
         trap_value: u64,
         previous_privilege: Privilege,
+        previous_virtualization_mode: bool,
         is_interrupt: bool,
     ) {
-        self.csr.write_direct(
-            match self.privilege {
-                Privilege::User => CSR_UEPC,
-                Privilege::Supervisor => CSR_SEPC,
-                Privilege::Hypervisor => CSR_HEPC,
-                Privilege::Machine => CSR_MEPC,
-            },
-            exception_pc,
-        );
-
-        let cause = self.get_cause(trap_code, is_interrupt);
-        self.csr.write_direct(
-            match self.privilege {
-                Privilege::User => CSR_UCAUSE,
-                Privilege::Supervisor => CSR_SCAUSE,
-                Privilege::Hypervisor => CSR_HCAUSE,
-                Privilege::Machine => CSR_MCAUSE,
-            },
-            cause,
-        );
-
-        self.csr.write_direct(
-            match self.privilege {
-                Privilege::User => CSR_UTVAL,
-                Privilege::Supervisor => CSR_STVAL,
-                Privilege::Hypervisor => CSR_HTVAL,
-                Privilege::Machine => CSR_MTVAL,
-            },
-            trap_value,
-        );
+        let (epc_reg, cause_reg, tval_reg, status_reg) =
+            match (&self.privilege, self.virtualization_mode) {
+                (Privilege::User, _) => (CSR_UEPC, CSR_UCAUSE, CSR_UTVAL, CSR_USTATUS),
+                (Privilege::Supervisor, false) => (CSR_SEPC, CSR_SCAUSE, CSR_STVAL, CSR_SSTATUS),
+                (Privilege::Supervisor, true) => (CSR_VSEPC, CSR_VSCAUSE, CSR_VSTVAL, CSR_VSSTATUS),
+                (Privilege::Machine, _) => (CSR_MEPC, CSR_MCAUSE, CSR_MTVAL, CSR_MSTATUS),
+            };
 
-        let status_reg = match self.privilege {
-            Privilege::User => CSR_USTATUS,
-            Privilege::Supervisor => CSR_SSTATUS,
-            Privilege::Hypervisor => CSR_HSTATUS,
-            Privilege::Machine => CSR_MSTATUS,
+        // VS-level interrupts are seen as supervisor-level interrupts in VS-mode.
+        let cause = match self.virtualization_mode && is_interrupt {
+            true => self.get_cause(trap_code - 1, is_interrupt),
+            false => self.get_cause(trap_code, is_interrupt),
         };
+        self.csr.write_direct(epc_reg, exception_pc);
+        self.csr.write_direct(cause_reg, cause);
+        self.csr.write_direct(tval_reg, trap_value);
+
         let p = self.privilege.clone() as u8;
         let ie = ((self.csr.read_direct(status_reg) >> p) & 0x1) as u64;
+        let previous_level = previous_privilege as u64;
         self.csr.read_modify_write_direct(
             status_reg,
             match self.privilege {
-                Privilege::User => panic!("TODO"),
-                Privilege::Supervisor => (ie << 5) | ((previous_privilege as u64) << 8),
-                Privilege::Hypervisor => panic!("TODO"),
-                Privilege::Machine => (ie << 7) | ((previous_privilege as u64) << 11),
+                Privilege::User => ie << 4,
+                Privilege::Supervisor => (ie << 5) | (previous_level << 8),
+                Privilege::Machine => (ie << 7) | (previous_level << 11),
             },
             match self.privilege {
-                Privilege::User => panic!("TODO"),
+                Privilege::User => 0x11,
                 Privilege::Supervisor => 0x122,
-                Privilege::Hypervisor => panic!("TODO"),
                 Privilege::Machine => 0x1888,
             },
         );
+
+        // The hypervisor extension records the previous virtualization mode, whether
+        // xtval holds a guest virtual address, and the guest physical address of
+        // guest-page faults shifted right by 2 bits.
+        let gva = !is_interrupt
+            && matches!(trap_code, 0 | 1 | 4..=7 | 12 | 13 | 15 | 20 | 21 | 23)
+            && self.mmu.is_fault_guest_virtual_address();
+        let gpa = match !is_interrupt && matches!(trap_code, 20 | 21 | 23) {
+            true => self.mmu.get_fault_guest_physical_address() >> 2,
+            false => 0,
+        };
+        match (&self.privilege, self.virtualization_mode) {
+            (Privilege::Machine, _) => {
+                self.csr.read_modify_write_direct(
+                    CSR_MSTATUS,
+                    (match previous_virtualization_mode {
+                        true => CSR_STATUS_MPV,
+                        false => 0,
+                    }) | (match gva {
+                        true => CSR_STATUS_GVA,
+                        false => 0,
+                    }),
+                    CSR_STATUS_MPV | CSR_STATUS_GVA,
+                );
+                self.csr.write_direct(CSR_MTVAL2, gpa);
+                self.csr.write_direct(CSR_MTINST, 0);
+            }
+            (Privilege::Supervisor, false) => {
+                let mut set = match gva {
+                    true => CSR_HSTATUS_GVA,
+                    false => 0,
+                };
+                let mut clear = CSR_HSTATUS_SPV | CSR_HSTATUS_GVA;
+                if previous_virtualization_mode {
+                    set |= CSR_HSTATUS_SPV | (previous_level << 8);
+                    clear |= CSR_HSTATUS_SPVP;
+                }
+                self.csr.read_modify_write_direct(CSR_HSTATUS, set, clear);
+                self.csr.write_direct(CSR_HTVAL, gpa);
+                self.csr.write_direct(CSR_HTINST, 0);
+            }
+            _ => {}
+        };
     }
 
+    /// get the address of the trap handler from BASE and MODE of xtvec. In vectored
+    /// mode (MODE=1), interrupts jump to BASE + 4 * cause and exceptions jump to BASE.
     fn get_trap_next_pc(&mut self) -> u64 {
-        self.pc = self.csr.read_direct(match self.privilege {
-            Privilege::User => CSR_UTVEC,
-            Privilege::Supervisor => CSR_STVEC,
-            Privilege::Hypervisor => CSR_HTVEC,
-            Privilege::Machine => CSR_MTVEC,
-        });
+        let (tvec_reg, cause_reg) = match (&self.privilege, self.virtualization_mode) {
+            (Privilege::User, _) => (CSR_UTVEC, CSR_UCAUSE),
+            (Privilege::Supervisor, false) => (CSR_STVEC, CSR_SCAUSE),
+            (Privilege::Supervisor, true) => (CSR_VSTVEC, CSR_VSCAUSE),
+            (Privilege::Machine, _) => (CSR_MTVEC, CSR_MCAUSE),
+        };
+        let tvec = self.csr.read_direct(tvec_reg);
+        let cause = self.csr.read_direct(cause_reg);
+        let interrupt_bit = self.get_cause(0, true);
+        let base = tvec & !0x3;
+        self.pc = match (tvec & 0x3, (cause & interrupt_bit) != 0) {
+            (1, true) => base.wrapping_add((cause & !interrupt_bit) << 2),
+            _ => base,
+        };
         self.pc
     }
 
@@ -522,30 +872,38 @@ impl Cpu {
         cause
     }
 
-    fn get_next_privilege(&mut self, trap_code: u8, is_interrupt: bool) -> Privilege {
-        let cause = self.get_cause(trap_code, is_interrupt) & 0xf;
+    /// get the privilege level and the virtualization mode which handle the trap.
+    /// Exceptions in M-mode are never delegated. Traps delegated by medeleg/mideleg are
+    /// further delegated to VS-mode by hedeleg/hideleg, where exceptions are delegated
+    /// only when V=1.
+    fn get_next_privilege(&mut self, trap_code: u8, is_interrupt: bool) -> (Privilege, bool) {
+        let cause = trap_code as u64;
         let mdeleg = self.csr.read_direct(match is_interrupt {
             true => CSR_MIDELEG,
             _ => CSR_MEDELEG,
         }) & 0xffffffff_fffff777;
-        //let hdeleg = self.csr.read_direct(match is_interrupt {
-        //        true => CSR_HIDELEG,
-        //        _ => CSR_HEDELEG
-        //}) & 0xffffffff_fffff333;
+        let hdeleg = self.csr.read_direct(match is_interrupt {
+            true => CSR_HIDELEG,
+            _ => CSR_HEDELEG,
+        });
         let sdeleg = self.csr.read_direct(match is_interrupt {
             true => CSR_SIDELEG,
             _ => CSR_SEDELEG,
         }) & 0xffffffff_fffff111;
 
+        if let (Privilege::Machine, false) = (&self.privilege, is_interrupt) {
+            return (Privilege::Machine, false);
+        }
         match ((mdeleg >> cause) & 1) > 0 {
-            //true => match ((hdeleg >> cause) & 1) > 0 {
-            true => match ((sdeleg >> cause) & 1) > 0 {
-                true => Privilege::User,
-                false => Privilege::Supervisor,
+            true => match (is_interrupt || self.virtualization_mode) && ((hdeleg >> cause) & 1) > 0
+            {
+                true => (Privilege::Supervisor, true),
+                false => match ((sdeleg >> cause) & 1) > 0 {
+                    true => (Privilege::User, false),
+                    false => (Privilege::Supervisor, false),
+                },
             },
-            //    false => Privilege::Hypervisor,
-            //},
-            false => Privilege::Machine,
+            false => (Privilege::Machine, false),
         }
     }
 
@@ -553,4 +911,31 @@ impl Cpu {
         self.privilege = next_privilege;
         self.mmu.set_privilege(&self.privilege);
     }
+
+    pub fn change_virtualization_mode(&mut self, virtualization_mode: bool) {
+        self.virtualization_mode = virtualization_mode;
+        self.csr.set_virtualization_mode(virtualization_mode);
+        self.mmu.set_virtualization_mode(virtualization_mode);
+    }
+
+    /// reflect satp, vsatp and hgatp registers and the status fields to the address
+    /// translation, pmpcfg and pmpaddr registers to the physical memory protection,
+    /// and MXL of misa register to XLEN.
+    pub fn update_addressing_mode(&mut self) {
+        let xlen = self.csr.get_xlen();
+        if !matches!(
+            (&xlen, &self.xlen),
+            (Xlen::X32, Xlen::X32) | (Xlen::X64, Xlen::X64)
+        ) {
+            self.set_xlen(xlen);
+        }
+        let satp = self.csr.read_direct(CSR_SPTBR);
+        let vsatp = self.csr.read_direct(CSR_VSATP);
+        let hgatp = self.csr.read_direct(CSR_HGATP);
+        self.mmu.update_addressing_mode(satp);
+        self.mmu.update_vs_addressing_mode(vsatp);
+        self.mmu.update_g_stage_addressing_mode(hgatp);
+        self.mmu.update_status(&self.csr);
+        self.mmu.update_pmp(&self.csr);
+    }
 }
@@ -1,5 +1,6 @@
-use crate::cpu::cpu::Privilege;
+use crate::cpu::cpu::{Privilege, Xlen};
 use crate::cpu::trap::*;
+use std::collections::HashMap;
 
 pub const CSR_USTATUS: u16 = 0x000;
 pub const CSR_UIE: u16 = 0x004;
@@ -15,56 +16,76 @@ pub const CSR_FFLAGS: u16 = 0x001;
 pub const CSR_FRM: u16 = 0x002;
 pub const CSR_FCSR: u16 = 0x003;
 
+pub const CSR_VSTART: u16 = 0x008;
+pub const CSR_VXSAT: u16 = 0x009;
+pub const CSR_VXRM: u16 = 0x00A;
+pub const CSR_VCSR: u16 = 0x00F;
+pub const CSR_VL: u16 = 0xC20;
+pub const CSR_VTYPE: u16 = 0xC21;
+pub const CSR_VLENB: u16 = 0xC22;
+
 pub const CSR_CYCLE: u16 = 0xC00;
 pub const CSR_TIME: u16 = 0xC01;
 pub const CSR_INSTRET: u16 = 0xC02;
 pub const CSR_CYCLEH: u16 = 0xC80;
 pub const CSR_TIMEH: u16 = 0xC81;
 pub const CSR_INSTRETH: u16 = 0xC82;
+pub const CSR_HPMCOUNTER3: u16 = 0xC03;
+pub const CSR_HPMCOUNTER31: u16 = 0xC1F;
+pub const CSR_HPMCOUNTER3H: u16 = 0xC83;
+pub const CSR_HPMCOUNTER31H: u16 = 0xC9F;
 
 pub const CSR_SSTATUS: u16 = 0x100;
 pub const CSR_SEDELEG: u16 = 0x102;
 pub const CSR_SIDELEG: u16 = 0x103;
 pub const CSR_SIE: u16 = 0x104;
 pub const CSR_STVEC: u16 = 0x105;
+pub const CSR_SCOUNTEREN: u16 = 0x106;
 
 pub const CSR_SSCRATCH: u16 = 0x140;
 pub const CSR_SEPC: u16 = 0x141;
 pub const CSR_SCAUSE: u16 = 0x142;
 pub const CSR_STVAL: u16 = 0x143;
 pub const CSR_SIP: u16 = 0x144;
+pub const CSR_STIMECMP: u16 = 0x14D;
+pub const CSR_STIMECMPH: u16 = 0x15D;
 
 pub const CSR_SPTBR: u16 = 0x180;
 
-pub const CSR_SCYCLE: u16 = 0xD00;
-pub const CSR_STIME: u16 = 0xD01;
-pub const CSR_SINSTRET: u16 = 0xD02;
-pub const CSR_SCYCLEH: u16 = 0xD80;
-pub const CSR_STIMEH: u16 = 0xD81;
-pub const CSR_SINSTRETH: u16 = 0xD82;
-
-pub const CSR_HSTATUS: u16 = 0x200;
-pub const CSR_HEDELEG: u16 = 0x202;
-pub const CSR_HIDELEG: u16 = 0x203;
-pub const CSR_HIE: u16 = 0x204;
-pub const CSR_HTVEC: u16 = 0x205;
-
-pub const CSR_HSCRATCH: u16 = 0x240;
-pub const CSR_HEPC: u16 = 0x241;
-pub const CSR_HCAUSE: u16 = 0x242;
-pub const CSR_HTVAL: u16 = 0x243;
-
-pub const CSR_HCYCLE: u16 = 0xE00;
-pub const CSR_HTIME: u16 = 0xE01;
-pub const CSR_HINSTRET: u16 = 0xE02;
-pub const CSR_HCYCLEH: u16 = 0xE80;
-pub const CSR_HTIMEH: u16 = 0xE81;
-pub const CSR_HINSTRETH: u16 = 0xE82;
+pub const CSR_VSSTATUS: u16 = 0x200;
+pub const CSR_VSIE: u16 = 0x204;
+pub const CSR_VSTVEC: u16 = 0x205;
+
+pub const CSR_VSSCRATCH: u16 = 0x240;
+pub const CSR_VSEPC: u16 = 0x241;
+pub const CSR_VSCAUSE: u16 = 0x242;
+pub const CSR_VSTVAL: u16 = 0x243;
+pub const CSR_VSIP: u16 = 0x244;
+
+pub const CSR_VSATP: u16 = 0x280;
+
+pub const CSR_HSTATUS: u16 = 0x600;
+pub const CSR_HEDELEG: u16 = 0x602;
+pub const CSR_HIDELEG: u16 = 0x603;
+pub const CSR_HIE: u16 = 0x604;
+pub const CSR_HTIMEDELTA: u16 = 0x605;
+pub const CSR_HCOUNTEREN: u16 = 0x606;
+pub const CSR_HGEIE: u16 = 0x607;
+pub const CSR_HTIMEDELTAH: u16 = 0x615;
+
+pub const CSR_HTVAL: u16 = 0x643;
+pub const CSR_HIP: u16 = 0x644;
+pub const CSR_HVIP: u16 = 0x645;
+pub const CSR_HTINST: u16 = 0x64A;
+pub const CSR_HGEIP: u16 = 0xE12;
+
+pub const CSR_HGATP: u16 = 0x680;
 
 pub const CSR_MVENDORID: u16 = 0xF11;
 pub const CSR_MARCHID: u16 = 0xF12;
 pub const CSR_MIMPID: u16 = 0xF13;
 pub const CSR_MHARTID: u16 = 0xF14;
+pub const CSR_MCONFIGPTR: u16 = 0xF15;
 
 pub const CSR_MSTATUS: u16 = 0x300;
 pub const CSR_MISA: u16 = 0x301;
@@ -72,12 +93,23 @@ pub const CSR_MEDELEG: u16 = 0x302;
 pub const CSR_MIDELEG: u16 = 0x303;
 pub const CSR_MIE: u16 = 0x304;
 pub const CSR_MTVEC: u16 = 0x305;
+pub const CSR_MCOUNTEREN: u16 = 0x306;
 
 pub const CSR_MSCRATCH: u16 = 0x340;
 pub const CSR_MEPC: u16 = 0x341;
 pub const CSR_MCAUSE: u16 = 0x342;
 pub const CSR_MTVAL: u16 = 0x343;
 pub const CSR_MIP: u16 = 0x344;
+pub const CSR_MTINST: u16 = 0x34A;
+pub const CSR_MTVAL2: u16 = 0x34B;
+
+pub const CSR_MENVCFG: u16 = 0x30A;
+pub const CSR_MENVCFGH: u16 = 0x31A;
+
+pub const CSR_PMPCFG0: u16 = 0x3A0;
+pub const CSR_PMPCFG15: u16 = 0x3AF;
+pub const CSR_PMPADDR0: u16 = 0x3B0;
+pub const CSR_PMPADDR63: u16 = 0x3EF;
 
 pub const CSR_MBASE: u16 = 0x380;
 pub const CSR_MBOUND: u16 = 0x381;
@@ -86,16 +118,20 @@ pub const CSR_MIBOUND: u16 = 0x383;
 pub const CSR_MDBASE: u16 = 0x384;
 pub const CSR_MDBOUND: u16 = 0x385;
 
-pub const CSR_MCYCLE: u16 = 0xF00;
-pub const CSR_MTIME: u16 = 0xF01;
-pub const CSR_MINSTRET: u16 = 0xF02;
-pub const CSR_MCYCLEH: u16 = 0xF80;
-pub const CSR_MTIMEH: u16 = 0xF81;
-pub const CSR_MINSTRETH: u16 = 0xF82;
+pub const CSR_MCYCLE: u16 = 0xB00;
+pub const CSR_MINSTRET: u16 = 0xB02;
+pub const CSR_MHPMCOUNTER3: u16 = 0xB03;
+pub const CSR_MHPMCOUNTER31: u16 = 0xB1F;
+pub const CSR_MCYCLEH: u16 = 0xB80;
+pub const CSR_MINSTRETH: u16 = 0xB82;
+pub const CSR_MHPMCOUNTER3H: u16 = 0xB83;
+pub const CSR_MHPMCOUNTER31H: u16 = 0xB9F;
+
+pub const CSR_MCOUNTINHIBIT: u16 = 0x320;
+pub const CSR_MHPMEVENT3: u16 = 0x323;
+pub const CSR_MHPMEVENT31: u16 = 0x33F;
 
-pub const CSR_MUCONTEREN: u16 = 0x310;
-pub const CSR_MSCONTEREN: u16 = 0x311;
-pub const CSR_MHCONTEREN: u16 = 0x312;
+pub const CSR_MSTATUSH: u16 = 0x310;
 
 // register bit files
 pub const CSR_STATUS_UIE: u64 = 0x00000001;
@@ -108,73 +144,614 @@ pub const CSR_STATUS_HPIE: u64 = 0x00000040;
 pub const CSR_STATUS_MPIE: u64 = 0x00000080;
 pub const CSR_STATUS_SPP: u64 = 0x00000100;
 pub const CSR_STATUS_HPP: u64 = 0x00000600;
+pub const CSR_STATUS_VS: u64 = 0x00000600;
 pub const CSR_STATUS_MPP: u64 = 0x00001800;
 pub const CSR_STATUS_FS: u64 = 0x00006000;
 pub const CSR_STATUS_XS: u64 = 0x00018000;
 pub const CSR_STATUS_MPRV: u64 = 0x00020000;
 pub const CSR_STATUS_PUM: u64 = 0x00040000;
 pub const CSR_STATUS_MXR: u64 = 0x00080000;
+pub const CSR_STATUS_UXL: u64 = 0x00000003_00000000;
+pub const CSR_STATUS_SXL: u64 = 0x0000000c_00000000;
+pub const CSR_STATUS_SD32: u64 = 0x80000000;
+pub const CSR_STATUS_GVA: u64 = 0x00000040_00000000;
+pub const CSR_STATUS_MPV: u64 = 0x00000080_00000000;
+pub const CSR_STATUS_SD64: u64 = 0x80000000_00000000;
+
+// hstatus register bit files
+pub const CSR_HSTATUS_GVA: u64 = 0x00000040;
+pub const CSR_HSTATUS_SPV: u64 = 0x00000080;
+pub const CSR_HSTATUS_SPVP: u64 = 0x00000100;
+pub const CSR_HSTATUS_HU: u64 = 0x00000200;
+pub const CSR_HSTATUS_VTVM: u64 = 0x00100000;
+pub const CSR_HSTATUS_VTW: u64 = 0x00200000;
+pub const CSR_HSTATUS_VTSR: u64 = 0x00400000;
+pub const CSR_HSTATUS_VSXL: u64 = 0x00000003_00000000;
+
+// pmpcfg register bit files (8 bits for each entry)
+pub const CSR_PMPCFG_R: u8 = 0x01;
+pub const CSR_PMPCFG_W: u8 = 0x02;
+pub const CSR_PMPCFG_X: u8 = 0x04;
+pub const CSR_PMPCFG_A: u8 = 0x18;
+pub const CSR_PMPCFG_L: u8 = 0x80;
+
+// address-matching mode of pmpcfg.A
+pub const PMP_A_OFF: u8 = 0x00;
+pub const PMP_A_TOR: u8 = 0x08;
+pub const PMP_A_NA4: u8 = 0x10;
+pub const PMP_A_NAPOT: u8 = 0x18;
+
+pub const PMP_ENTRIES_DEFAULT: usize = 16;
+pub const PMP_ENTRIES_MAX: usize = 64;
+
+// misa register bit files
+pub const CSR_MISA_C: u64 = 0x00000004;
+// A, B, C, D, F, H, I, M, S, U and V extensions.
+const MISA_EXTENSIONS: u64 = 0x003411af;
+
+// menvcfg register bit files
+pub const CSR_MENVCFG_STCE: u64 = 0x80000000_00000000;
+
+// counter bits of mcounteren, scounteren, hcounteren and mcountinhibit
+pub const CSR_COUNTER_CY: u64 = 0x00000001;
+pub const CSR_COUNTER_TM: u64 = 0x00000002;
+pub const CSR_COUNTER_IR: u64 = 0x00000004;
+
+// events selected by mhpmevent3..mhpmevent31
+pub const HPM_EVENT_LOAD: u64 = 1;
+pub const HPM_EVENT_STORE: u64 = 2;
+pub const HPM_EVENT_BRANCH: u64 = 3;
+pub const HPM_EVENT_BRANCH_TAKEN: u64 = 4;
+pub const HPM_EVENT_TRAP: u64 = 5;
+pub const HPM_EVENT_TLB_MISS: u64 = 6;
+
+// vtype register bit files
+pub const CSR_VTYPE_VILL: u64 = 0x80000000_00000000;
 
 pub const CSR_IP_USIP: u64 = 0x00000001;
 pub const CSR_IP_SSIP: u64 = 0x00000002;
-pub const CSR_IP_HSIP: u64 = 0x00000004;
+pub const CSR_IP_VSSIP: u64 = 0x00000004;
 pub const CSR_IP_MSIP: u64 = 0x00000008;
 pub const CSR_IP_UTIP: u64 = 0x00000010;
 pub const CSR_IP_STIP: u64 = 0x00000020;
-pub const CSR_IP_HTIP: u64 = 0x00000040;
+pub const CSR_IP_VSTIP: u64 = 0x00000040;
 pub const CSR_IP_MTIP: u64 = 0x00000080;
 pub const CSR_IP_UEIP: u64 = 0x00000100;
 pub const CSR_IP_SEIP: u64 = 0x00000200;
-pub const CSR_IP_HEIP: u64 = 0x00000400;
+pub const CSR_IP_VSEIP: u64 = 0x00000400;
 pub const CSR_IP_MEIP: u64 = 0x00000800;
+pub const CSR_IP_SGEIP: u64 = 0x00001000;
 
 pub const CSR_IE_USIE: u64 = 0x00000001;
 pub const CSR_IE_SSIE: u64 = 0x00000002;
-pub const CSR_IE_HSIE: u64 = 0x00000004;
+pub const CSR_IE_VSSIE: u64 = 0x00000004;
 pub const CSR_IE_MSIE: u64 = 0x00000008;
 pub const CSR_IE_UTIE: u64 = 0x00000010;
 pub const CSR_IE_STIE: u64 = 0x00000020;
-pub const CSR_IE_HTIE: u64 = 0x00000040;
+pub const CSR_IE_VSTIE: u64 = 0x00000040;
 pub const CSR_IE_MTIE: u64 = 0x00000080;
 pub const CSR_IE_UEIE: u64 = 0x00000100;
 pub const CSR_IE_SEIE: u64 = 0x00000200;
-pub const CSR_IE_HEIE: u64 = 0x00000400;
+pub const CSR_IE_VSEIE: u64 = 0x00000400;
 pub const CSR_IE_MEIE: u64 = 0x00000800;
+pub const CSR_IE_SGEIE: u64 = 0x00001000;
+
+const SSTATUS_MASK: u64 = CSR_STATUS_MXR
+    | CSR_STATUS_PUM
+    | CSR_STATUS_XS
+    | CSR_STATUS_FS
+    | CSR_STATUS_VS
+    | CSR_STATUS_SPP
+    | CSR_STATUS_SPIE
+    | CSR_STATUS_UPIE
+    | CSR_STATUS_SIE
+    | CSR_STATUS_UIE;
+
+const VS_INTERRUPTS: u64 = CSR_IP_VSEIP | CSR_IP_VSTIP | CSR_IP_VSSIP;
+
+// The status fields which modify the address translation.
+const TRANSLATION_STATUS_MASK: u64 = CSR_STATUS_MPRV | CSR_STATUS_PUM | CSR_STATUS_MXR;
+
+/// description of an existing CSR. The CSRs which are not described do not exist,
+/// and accessing them raises illegal instruction exceptions. The CSRs whose address
+/// bits [11:10] are 0b11 are read-only.
+struct CsrDescription {
+    // the upper half of a 64-bit CSR, which exists only on RV32.
+    rv32_only: bool,
+    // the bits written by the CSR instructions. The other bits keep their values.
+    write_mask: u64,
+}
+
+const WRITE_ALL: u64 = 0xffffffff_ffffffff;
+
+lazy_static! {
+static ref CSR_DESCRIPTIONS: HashMap<u16, CsrDescription> = {
+    let mut m = HashMap::new();
+    let csr = |write_mask: u64| CsrDescription {rv32_only: false, write_mask};
+    let csr_rv32 = |write_mask: u64| CsrDescription {rv32_only: true, write_mask};
+
+    // User Floating-Point and Vector
+    for addr in [CSR_FFLAGS, CSR_FRM, CSR_FCSR] {
+        m.insert(addr, csr(WRITE_ALL));
+    }
+    for addr in [
+        CSR_VSTART, CSR_VXSAT, CSR_VXRM, CSR_VCSR, CSR_VL, CSR_VTYPE, CSR_VLENB,
+    ] {
+        m.insert(addr, csr(WRITE_ALL));
+    }
+
+    // User Counter/Timers
+    for addr in CSR_CYCLE..=CSR_HPMCOUNTER31 {
+        m.insert(addr, csr(WRITE_ALL));
+    }
+    for addr in CSR_CYCLEH..=CSR_HPMCOUNTER31H {
+        m.insert(addr, csr_rv32(WRITE_ALL));
+    }
+
+    // Supervisor
+    for addr in [
+        CSR_SSTATUS,
+        CSR_SIE,
+        CSR_STVEC,
+        CSR_SCOUNTEREN,
+        CSR_SSCRATCH,
+        CSR_SCAUSE,
+        CSR_STVAL,
+        CSR_SIP,
+        CSR_STIMECMP,
+        CSR_SPTBR,
+    ] {
+        m.insert(addr, csr(WRITE_ALL));
+    }
+    m.insert(CSR_SEPC, csr(!0x1));
+    m.insert(CSR_STIMECMPH, csr_rv32(WRITE_ALL));
+
+    // Hypervisor and Virtual Supervisor
+    for addr in [
+        CSR_HSTATUS,
+        CSR_HEDELEG,
+        CSR_HIDELEG,
+        CSR_HIE,
+        CSR_HTIMEDELTA,
+        CSR_HCOUNTEREN,
+        CSR_HGEIE,
+        CSR_HTVAL,
+        CSR_HIP,
+        CSR_HVIP,
+        CSR_HTINST,
+        CSR_HGATP,
+        CSR_HGEIP,
+    ] {
+        m.insert(addr, csr(WRITE_ALL));
+    }
+    m.insert(CSR_HTIMEDELTAH, csr_rv32(WRITE_ALL));
+    for addr in [
+        CSR_VSSTATUS,
+        CSR_VSIE,
+        CSR_VSTVEC,
+        CSR_VSSCRATCH,
+        CSR_VSCAUSE,
+        CSR_VSTVAL,
+        CSR_VSIP,
+        CSR_VSATP,
+    ] {
+        m.insert(addr, csr(WRITE_ALL));
+    }
+    m.insert(CSR_VSEPC, csr(!0x1));
+
+    // Machine Information Registers
+    for addr in [
+        CSR_MVENDORID,
+        CSR_MARCHID,
+        CSR_MIMPID,
+        CSR_MHARTID,
+        CSR_MCONFIGPTR,
+    ] {
+        m.insert(addr, csr(0));
+    }
+
+    // Machine Trap Setup and Handling
+    // ecall from M-mode can not be delegated, and only the supervisor-level
+    // interrupts are delegated by mideleg.
+    for addr in [
+        CSR_MSTATUS,
+        CSR_MISA,
+        CSR_MTVEC,
+        CSR_MCOUNTEREN,
+        CSR_MENVCFG,
+        CSR_MSCRATCH,
+        CSR_MCAUSE,
+        CSR_MTVAL,
+        CSR_MTINST,
+        CSR_MTVAL2,
+    ] {
+        m.insert(addr, csr(WRITE_ALL));
+    }
+    m.insert(CSR_MEDELEG, csr(0x00f0b7ff));
+    m.insert(CSR_MIDELEG, csr(CSR_IP_SSIP | CSR_IP_STIP | CSR_IP_SEIP));
+    m.insert(CSR_MIE, csr(CSR_IE_SSIE | CSR_IE_VSSIE | CSR_IE_MSIE | CSR_IE_STIE | CSR_IE_VSTIE | CSR_IE_MTIE | CSR_IE_SEIE | CSR_IE_VSEIE | CSR_IE_MEIE | CSR_IE_SGEIE));
+    m.insert(CSR_MIP, csr(CSR_IP_SSIP | CSR_IP_VSSIP | CSR_IP_STIP | CSR_IP_VSTIP | CSR_IP_SEIP | CSR_IP_VSEIP));
+    m.insert(CSR_MEPC, csr(!0x1));
+    for addr in [CSR_MSTATUSH, CSR_MENVCFGH] {
+        m.insert(addr, csr_rv32(WRITE_ALL));
+    }
+
+    // Machine Memory Protection
+    for addr in CSR_PMPCFG0..=CSR_PMPCFG15 {
+        m.insert(addr, csr(WRITE_ALL));
+    }
+    for addr in CSR_PMPADDR0..=CSR_PMPADDR63 {
+        m.insert(addr, csr(WRITE_ALL));
+    }
+
+    // Machine Counter/Timers
+    for addr in [CSR_MCYCLE, CSR_MINSTRET, CSR_MCOUNTINHIBIT] {
+        m.insert(addr, csr(WRITE_ALL));
+    }
+    for addr in CSR_MHPMCOUNTER3..=CSR_MHPMCOUNTER31 {
+        m.insert(addr, csr(WRITE_ALL));
+    }
+    for addr in CSR_MHPMEVENT3..=CSR_MHPMEVENT31 {
+        m.insert(addr, csr(WRITE_ALL));
+    }
+    for addr in [CSR_MCYCLEH, CSR_MINSTRETH] {
+        m.insert(addr, csr_rv32(WRITE_ALL));
+    }
+    for addr in CSR_MHPMCOUNTER3H..=CSR_MHPMCOUNTER31H {
+        m.insert(addr, csr_rv32(WRITE_ALL));
+    }
+    m
+};
+}
 
 pub struct Csr {
     csr: [u64; 4096],
+    xlen: Xlen,
+    virtualization_mode: bool,
+    pmp_entries: usize,
+    written_counters: u64,
+    hpm_events: u64,
 }
 
 impl Csr {
     pub fn new() -> Self {
-        let mut csr = Csr { csr: [0; 4096] };
+        let mut csr = Csr {
+            csr: [0; 4096],
+            xlen: Xlen::X64,
+            virtualization_mode: false,
+            pmp_entries: PMP_ENTRIES_DEFAULT,
+            written_counters: 0,
+            hpm_events: 0,
+        };
+        csr.csr[CSR_VTYPE as usize] = CSR_VTYPE_VILL;
+        csr.csr[CSR_MISA as usize] = MISA_EXTENSIONS;
+        csr
+    }
+
+    pub fn set_xlen(&mut self, xlen: &Xlen) {
+        self.xlen = xlen.clone();
+    }
+
+    /// get XLEN selected by MXL of misa.
+    pub fn get_xlen(&self) -> Xlen {
+        self.xlen.clone()
+    }
 
-        // this is actived when release mode for passing 
-        // "rv32mi-p-csr" test scenario of riscv-tests.
-        if cfg!(not(debug_assertions)) {
-            csr.csr[CSR_MISA as usize] = 0x800000008014312f;
+    /// check the C extension is enabled in misa.
+    pub fn is_compressed_enabled(&self) -> bool {
+        (self.csr[CSR_MISA as usize] & CSR_MISA_C) != 0
+    }
+
+    pub fn set_virtualization_mode(&mut self, virtualization_mode: bool) {
+        self.virtualization_mode = virtualization_mode;
+    }
+
+    /// set the number of implemented PMP entries. The pmpcfg fields and pmpaddr
+    /// registers of the unimplemented entries are hardwired to zero.
+    pub fn set_pmp_entries(&mut self, entries: usize) {
+        if entries > PMP_ENTRIES_MAX {
+            panic!("Unsupported number of PMP entries: {}", entries);
         }
-        csr
+        self.pmp_entries = entries;
+        for entry in entries..PMP_ENTRIES_MAX {
+            self.set_pmpcfg(entry, 0);
+            self.csr[(CSR_PMPADDR0 as usize) + entry] = 0;
+        }
+    }
+
+    pub fn get_pmp_entries(&self) -> usize {
+        self.pmp_entries
+    }
+
+    /// get the 8-bit configuration of the PMP entry. pmpcfg0..pmpcfg15 hold four
+    /// entries for each on RV32, and only even-numbered pmpcfg0..pmpcfg14 are used
+    /// and hold eight entries for each on RV64.
+    pub fn get_pmpcfg(&self, entry: usize) -> u8 {
+        let (addr, shift) = self.get_pmpcfg_position(entry);
+        ((self.csr[addr] >> shift) & 0xff) as u8
+    }
+
+    /// get the physical address bits [55:2] (RV64) or [33:2] (RV32) of the PMP entry.
+    pub fn get_pmpaddr(&self, entry: usize) -> u64 {
+        self.csr[(CSR_PMPADDR0 as usize) + entry]
+    }
+
+    fn set_pmpcfg(&mut self, entry: usize, cfg: u8) {
+        let (addr, shift) = self.get_pmpcfg_position(entry);
+        self.csr[addr] = (self.csr[addr] & !(0xff << shift)) | ((cfg as u64) << shift);
+    }
+
+    fn get_pmpcfg_position(&self, entry: usize) -> (usize, usize) {
+        let entries_per_csr = match self.xlen {
+            Xlen::X32 => 4,
+            Xlen::X64 => 8,
+        };
+        (
+            (CSR_PMPCFG0 as usize) + (entry / entries_per_csr) * (entries_per_csr / 4),
+            (entry % entries_per_csr) * 8,
+        )
+    }
+
+    /// A locked PMP entry ignores writes to its pmpcfg field and pmpaddr register.
+    /// pmpaddr is also locked when the next entry is locked and in TOR mode, because
+    /// it is the bottom of the range of the next entry.
+    fn is_pmpaddr_locked(&self, entry: usize) -> bool {
+        let next_cfg = match entry + 1 < self.pmp_entries {
+            true => self.get_pmpcfg(entry + 1),
+            false => 0,
+        };
+        (self.get_pmpcfg(entry) & CSR_PMPCFG_L) != 0
+            || ((next_cfg & CSR_PMPCFG_L) != 0 && (next_cfg & CSR_PMPCFG_A) == PMP_A_TOR)
     }
 
+    /// mcycle is not incremented in the cycle when it is written by an instruction,
+    /// so that the written value is visible to the next instruction.
     pub fn tick(&mut self) {
-        self.csr[CSR_TIME as usize] = self.csr[CSR_TIME as usize].wrapping_add(1);
+        if ((self.written_counters | self.csr[CSR_MCOUNTINHIBIT as usize]) & CSR_COUNTER_CY) == 0 {
+            self.csr[CSR_MCYCLE as usize] = self.csr[CSR_MCYCLE as usize].wrapping_add(1);
+        }
+        self.written_counters = 0;
     }
 
-    pub fn read(
-        &mut self,
-        addr: u16,
-        instruction_addr: u64,
-        cur_privilege: &Privilege,
-    ) -> Result<u64, Trap> {
+    /// advance mcycle by the cycles which the hart skips while it waits for an
+    /// interrupt.
+    pub fn skip(&mut self, cycles: u64) {
+        if (self.csr[CSR_MCOUNTINHIBIT as usize] & CSR_COUNTER_CY) == 0 {
+            self.csr[CSR_MCYCLE as usize] = self.csr[CSR_MCYCLE as usize].wrapping_add(cycles);
+        }
+    }
+
+    /// time CSR is a read-only shadow of mtime of the CLINT.
+    pub fn set_time(&mut self, time: u64) {
+        self.csr[CSR_TIME as usize] = time;
+        self.update_supervisor_timer_interrupt();
+    }
+
+    /// check the Sstc extension is enabled by menvcfg.STCE.
+    pub fn is_sstc_enabled(&self) -> bool {
+        (self.csr[CSR_MENVCFG as usize] & CSR_MENVCFG_STCE) != 0
+    }
+
+    /// the number of cycles until time reaches stimecmp while Sstc is enabled.
+    pub fn get_cycles_to_supervisor_timer(&self) -> Option<u64> {
+        let time = self.csr[CSR_TIME as usize];
+        let stimecmp = self.csr[CSR_STIMECMP as usize];
+        match self.is_sstc_enabled() && time < stimecmp {
+            true => Some(stimecmp - time),
+            false => None,
+        }
+    }
+
+    /// While menvcfg.STCE is set, STIP is read-only and pending whenever time is
+    /// greater than or equal to stimecmp.
+    fn update_supervisor_timer_interrupt(&mut self) {
+        if !self.is_sstc_enabled() {
+            return;
+        }
+        match self.csr[CSR_TIME as usize] >= self.csr[CSR_STIMECMP as usize] {
+            true => self.csr[CSR_MIP as usize] |= CSR_IP_STIP,
+            false => self.csr[CSR_MIP as usize] &= !CSR_IP_STIP,
+        }
+    }
+
+    /// count the retired instruction. minstret is not incremented by the instruction
+    /// which writes it.
+    pub fn retire(&mut self) {
+        if ((self.written_counters | self.csr[CSR_MCOUNTINHIBIT as usize]) & CSR_COUNTER_IR) == 0 {
+            self.csr[CSR_MINSTRET as usize] = self.csr[CSR_MINSTRET as usize].wrapping_add(1);
+        }
+    }
+
+    /// count the event on every mhpmcounter whose mhpmevent selects it.
+    pub fn count_event(&mut self, event: u64) {
+        if (self.hpm_events & (1 << event)) == 0 {
+            return;
+        }
+        for n in 3..32 {
+            if self.csr[(CSR_MHPMEVENT3 as usize) - 3 + n] == event
+                && (self.csr[CSR_MCOUNTINHIBIT as usize] & (1 << n)) == 0
+            {
+                let addr = (CSR_MHPMCOUNTER3 as usize) - 3 + n;
+                self.csr[addr] = self.csr[addr].wrapping_add(1);
+            }
+        }
+    }
+
+    pub fn read(&mut self, addr: u16, word: u32, cur_privilege: &Privilege) -> Result<u64, Trap> {
+        match self.check_privilege(addr, cur_privilege) {
+            Ok(()) => {}
+            Err(exception) => {
+                return Err(Trap {
+                    exception,
+                    value: word as u64,
+                })
+            }
+        };
+        let addr = self.substitute_virtual_supervisor(addr);
+        match addr {
+            // time CSR returns the sum of time and htimedelta when V=1. htimedeltah
+            // holds the upper 32 bits of htimedelta on RV32.
+            CSR_TIME | CSR_TIMEH if self.virtualization_mode => {
+                let delta = match self.xlen {
+                    Xlen::X32 => {
+                        (self.csr[CSR_HTIMEDELTAH as usize] << 32)
+                            | (self.csr[CSR_HTIMEDELTA as usize] & 0xffffffff)
+                    }
+                    Xlen::X64 => self.csr[CSR_HTIMEDELTA as usize],
+                };
+                let time = self.csr[CSR_TIME as usize].wrapping_add(delta);
+                match addr {
+                    CSR_TIMEH => Ok(time >> 32),
+                    _ => Ok(time),
+                }
+            }
+            _ => Ok(self.read_direct(addr)),
+        }
+    }
+
+    /// The privilege field of CSR address (bits [9:8]) is the lowest privilege level
+    /// that can access the CSR. The hypervisor and VS CSRs (field 2) are accessible
+    /// in HS-mode. When V=1, accesses which would be allowed in HS-mode raise virtual
+    /// instruction exceptions instead of illegal instruction exceptions.
+    fn check_privilege(&self, addr: u16, cur_privilege: &Privilege) -> Result<(), Exception> {
         let privilege = ((addr >> 8) & 0x3) as u8;
         let cur_level = cur_privilege.clone() as u8;
-        match privilege <= cur_level {
-            true => Ok(self.read_direct(addr)),
-            _ => Err(Trap {
-                exception: Exception::IllegalInstruction,
-                value: instruction_addr,
-            }),
+        let allowed = match (privilege, self.virtualization_mode) {
+            (3, _) => cur_level == Privilege::Machine as u8,
+            (2, true) => return Err(Exception::VirtualInstruction),
+            (1, true) if cur_level < Privilege::Supervisor as u8 => {
+                return Err(Exception::VirtualInstruction)
+            }
+            (p, _) => cur_level >= p.min(Privilege::Supervisor as u8),
+        };
+        match allowed && self.is_accessible(addr) {
+            true => match self.check_counter_enabled(addr, cur_level) {
+                Ok(()) => self.check_stimecmp_enabled(addr, cur_level),
+                Err(e) => Err(e),
+            },
+            false => Err(Exception::IllegalInstruction),
+        }
+    }
+
+    /// stimecmp can be accessed in S-mode only when both mcounteren.TM and menvcfg.STCE
+    /// are set. henvcfg is not implemented and its STCE is read-only zero, so the
+    /// accesses in VS-mode raise virtual instruction exceptions.
+    fn check_stimecmp_enabled(&self, addr: u16, cur_level: u8) -> Result<(), Exception> {
+        if !matches!(addr, CSR_STIMECMP | CSR_STIMECMPH) || cur_level == Privilege::Machine as u8 {
+            return Ok(());
+        }
+        if (self.csr[CSR_MCOUNTEREN as usize] & CSR_COUNTER_TM) == 0 || !self.is_sstc_enabled() {
+            return Err(Exception::IllegalInstruction);
+        }
+        match self.virtualization_mode {
+            true => Err(Exception::VirtualInstruction),
+            false => Ok(()),
+        }
+    }
+
+    /// The counters (cycle, time, instret and hpmcounter3..31) can be read in the
+    /// lower privilege modes only when the corresponding bits of mcounteren, and of
+    /// scounteren in U-mode, are set. When V=1, hcounteren also has to be set, and
+    /// the accesses allowed by mcounteren raise virtual instruction exceptions.
+    fn check_counter_enabled(&self, addr: u16, cur_level: u8) -> Result<(), Exception> {
+        if !matches!(addr, CSR_CYCLE..=CSR_HPMCOUNTER31 | CSR_CYCLEH..=CSR_HPMCOUNTER31H)
+            || cur_level == Privilege::Machine as u8
+        {
+            return Ok(());
+        }
+        let bit = 1 << (addr & 0x1f);
+        let user = cur_level == Privilege::User as u8;
+        if (self.csr[CSR_MCOUNTEREN as usize] & bit) == 0 {
+            return Err(Exception::IllegalInstruction);
+        }
+        match self.virtualization_mode {
+            true if (self.csr[CSR_HCOUNTEREN as usize] & bit) == 0
+                || (user && (self.csr[CSR_SCOUNTEREN as usize] & bit) == 0) =>
+            {
+                Err(Exception::VirtualInstruction)
+            }
+            false if user && (self.csr[CSR_SCOUNTEREN as usize] & bit) == 0 => {
+                Err(Exception::IllegalInstruction)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// When V=1, the virtual supervisor CSRs substitute for the supervisor CSRs.
+    fn substitute_virtual_supervisor(&self, addr: u16) -> u16 {
+        if !self.virtualization_mode {
+            return addr;
+        }
+        match addr {
+            CSR_SSTATUS => CSR_VSSTATUS,
+            CSR_SIE => CSR_VSIE,
+            CSR_STVEC => CSR_VSTVEC,
+            CSR_SSCRATCH => CSR_VSSCRATCH,
+            CSR_SEPC => CSR_VSEPC,
+            CSR_SCAUSE => CSR_VSCAUSE,
+            CSR_STVAL => CSR_VSTVAL,
+            CSR_SIP => CSR_VSIP,
+            CSR_SPTBR => CSR_VSATP,
+            _ => addr,
+        }
+    }
+
+    /// The floating-point and vector CSRs can not be accessed while mstatus.FS or
+    /// mstatus.VS is Off respectively.
+    fn is_accessible(&self, addr: u16) -> bool {
+        match addr {
+            CSR_FFLAGS | CSR_FRM | CSR_FCSR => self.is_status_enabled(CSR_STATUS_FS),
+            CSR_VSTART | CSR_VXSAT | CSR_VXRM | CSR_VCSR | CSR_VL | CSR_VTYPE | CSR_VLENB => {
+                self.is_status_enabled(CSR_STATUS_VS)
+            }
+            // Odd-numbered pmpcfg registers do not exist on RV64.
+            CSR_PMPCFG0..=CSR_PMPCFG15 => match self.xlen {
+                Xlen::X32 => true,
+                Xlen::X64 => (addr & 1) == 0,
+            },
+            // The upper halves of 64-bit CSRs do not exist on RV64.
+            _ => match CSR_DESCRIPTIONS.get(&addr) {
+                Some(description) => !description.rv32_only || matches!(self.xlen, Xlen::X32),
+                None => false,
+            },
+        }
+    }
+
+    /// check the FS or VS field is not Off. When V=1, the field of vsstatus also
+    /// has to be not Off.
+    pub fn is_status_enabled(&self, field: u64) -> bool {
+        (self.csr[CSR_MSTATUS as usize] & field) != 0
+            && (!self.virtualization_mode || (self.csr[CSR_VSSTATUS as usize] & field) != 0)
+    }
+
+    /// Modifying the floating-point or vector state sets the FS or VS field to Dirty.
+    /// When V=1, the field of vsstatus is also set to Dirty.
+    pub fn set_status_dirty(&mut self, field: u64) {
+        self.csr[CSR_MSTATUS as usize] |= field;
+        if self.virtualization_mode {
+            self.csr[CSR_VSSTATUS as usize] |= field;
+        }
+    }
+
+    /// SD bit summarizes whether either the FS, VS or XS fields signal the presence of dirty state.
+    /// UXL and SXL fields read as 2 (64 bits) on RV64, and do not exist on RV32.
+    fn get_status_xl(&self, fields: u64) -> u64 {
+        match self.xlen {
+            Xlen::X32 => 0,
+            Xlen::X64 => fields & 0x0000000a_00000000,
+        }
+    }
+
+    fn get_status_sd(&self, status: u64) -> u64 {
+        match (status & CSR_STATUS_FS) == CSR_STATUS_FS
+            || (status & CSR_STATUS_VS) == CSR_STATUS_VS
+            || (status & CSR_STATUS_XS) == CSR_STATUS_XS
+        {
+            true => match self.xlen {
+                Xlen::X32 => CSR_STATUS_SD32,
+                Xlen::X64 => CSR_STATUS_SD64,
+            },
+            false => 0,
         }
     }
 
@@ -184,19 +761,65 @@ impl Csr {
             CSR_FFLAGS => self.csr[CSR_FCSR as usize] & 0x1f,
             CSR_FRM => (self.csr[CSR_FCSR as usize] >> 5) & 0x7,
 
-            // Restricted views of the mstatus register appear as the hstatus and
-            // sstatus registers in the H and S privilege-level ISAs respectively.
-            CSR_HSTATUS => panic!("TODO: HSTATUS"),
+            // UXL and SXL are fixed to 64 bits on RV64. mstatush holds the upper 32 bits
+            // of mstatus on RV32.
+            CSR_MSTATUS => {
+                self.csr[CSR_MSTATUS as usize]
+                    | self.get_status_sd(self.csr[CSR_MSTATUS as usize])
+                    | self.get_status_xl(CSR_STATUS_UXL | CSR_STATUS_SXL)
+            }
+            CSR_MSTATUSH => self.csr[CSR_MSTATUS as usize] >> 32,
+
+            // MXL reflects the current XLEN.
+            CSR_MISA => match self.xlen {
+                Xlen::X32 => self.csr[CSR_MISA as usize] | (1 << 30),
+                Xlen::X64 => self.csr[CSR_MISA as usize] | (2 << 62),
+            },
+
+            // Vector (VCSR/VTYPE)
+            // vcsr mirrors the fixed-point rounding mode and saturation flag.
+            CSR_VCSR => (self.csr[CSR_VXRM as usize] << 1) | self.csr[CSR_VXSAT as usize],
+            // vill bit is held in the MSB regardless of XLEN.
+            CSR_VTYPE => {
+                let vtype = self.csr[CSR_VTYPE as usize];
+                match self.xlen {
+                    Xlen::X32 => (vtype & !CSR_VTYPE_VILL) | ((vtype & CSR_VTYPE_VILL) >> 32),
+                    Xlen::X64 => vtype,
+                }
+            }
+
+            // A restricted view of the mstatus register appears as the sstatus register
+            // in the S privilege-level ISA. vsstatus is a copy of sstatus for VS-mode.
             CSR_SSTATUS => {
-                let mask = CSR_STATUS_PUM
-                    | CSR_STATUS_XS
-                    | CSR_STATUS_FS
-                    | CSR_STATUS_SPP
-                    | CSR_STATUS_SPIE
-                    | CSR_STATUS_UPIE
-                    | CSR_STATUS_SIE
-                    | CSR_STATUS_UIE;
-                self.csr[CSR_MSTATUS as usize] & mask
+                (self.csr[CSR_MSTATUS as usize] & SSTATUS_MASK)
+                    | self.get_status_sd(self.csr[CSR_MSTATUS as usize])
+                    | self.get_status_xl(CSR_STATUS_UXL)
+            }
+            CSR_VSSTATUS => {
+                (self.csr[CSR_VSSTATUS as usize] & SSTATUS_MASK)
+                    | self.get_status_sd(self.csr[CSR_VSSTATUS as usize])
+                    | self.get_status_xl(CSR_STATUS_UXL)
+            }
+
+            // Hypervisor (HSTATUS/HIDELEG/HIE/HIP/HVIP)
+            // VSXL is fixed to 64 bits on RV64.
+            CSR_HSTATUS => match self.xlen {
+                Xlen::X32 => self.csr[CSR_HSTATUS as usize],
+                Xlen::X64 => self.csr[CSR_HSTATUS as usize] | (2 << 32),
+            },
+            // VS-level interrupts are always delegated to HS-mode.
+            CSR_MIDELEG => self.csr[CSR_MIDELEG as usize] | VS_INTERRUPTS,
+            CSR_HIP => self.csr[CSR_MIP as usize] & (VS_INTERRUPTS | CSR_IP_SGEIP),
+            CSR_HIE => self.csr[CSR_MIE as usize] & (VS_INTERRUPTS | CSR_IE_SGEIE),
+            CSR_HVIP => self.csr[CSR_MIP as usize] & VS_INTERRUPTS,
+
+            // VS-level interrupts delegated by hideleg appear as the supervisor-level
+            // interrupts in vsip and vsie.
+            CSR_VSIP => {
+                (self.csr[CSR_MIP as usize] & self.csr[CSR_HIDELEG as usize] & VS_INTERRUPTS) >> 1
+            }
+            CSR_VSIE => {
+                (self.csr[CSR_MIE as usize] & self.csr[CSR_HIDELEG as usize] & VS_INTERRUPTS) >> 1
             }
 
             // Restricted views of the mip and mie registers appear as the hip/hie,
@@ -220,19 +843,26 @@ impl Csr {
                 self.csr[CSR_MIE as usize] & mask
             }
 
-            // timer
-            CSR_MCYCLE | CSR_MTIME | CSR_MINSTRET | CSR_MCYCLEH | CSR_MTIMEH | CSR_MINSTRETH => {
-                panic!("TODO: CSR MTimer")
+            // Counters (CYCLE/INSTRET/HPMCOUNTER and their high halves)
+            // The user-level counters are read-only shadows of the machine-level
+            // counters, and the high halves hold the upper 32 bits on RV32.
+            CSR_CYCLE | CSR_INSTRET | CSR_HPMCOUNTER3..=CSR_HPMCOUNTER31 => {
+                self.csr[(CSR_MCYCLE | (addr & 0x1f)) as usize]
             }
-            CSR_HCYCLE | CSR_HTIME | CSR_HINSTRET | CSR_HCYCLEH | CSR_HTIMEH | CSR_HINSTRETH => {
-                panic!("TODO: CSR HTimer")
-            }
-            CSR_SCYCLE | CSR_STIME | CSR_SINSTRET | CSR_SCYCLEH | CSR_STIMEH | CSR_SINSTRETH => {
-                panic!("TODO: CSR STimer")
-            }
-            CSR_INSTRET | CSR_CYCLEH | CSR_TIMEH | CSR_INSTRETH => {
-                panic!("TODO: CSR Timer: {:x}", addr);
+            CSR_CYCLEH
+            | CSR_INSTRETH
+            | CSR_HPMCOUNTER3H..=CSR_HPMCOUNTER31H
+            | CSR_MCYCLEH
+            | CSR_MINSTRETH
+            | CSR_MHPMCOUNTER3H..=CSR_MHPMCOUNTER31H => {
+                self.csr[(CSR_MCYCLE | (addr & 0x1f)) as usize] >> 32
             }
+            CSR_TIMEH => self.csr[CSR_TIME as usize] >> 32,
+
+            // Supervisor Timer Compare (STIMECMPH/MENVCFGH)
+            // The high halves hold the upper 32 bits on RV32.
+            CSR_STIMECMPH => self.csr[CSR_STIMECMP as usize] >> 32,
+            CSR_MENVCFGH => self.csr[CSR_MENVCFG as usize] >> 32,
 
             _ => self.csr[addr as usize],
         }
@@ -248,51 +878,247 @@ impl Csr {
         addr: u16,
         data: u64,
         instruction_addr: u64,
+        word: u32,
         cur_privilege: &Privilege,
     ) -> Result<bool, Trap> {
-        let privilege = ((addr >> 8) & 0x3) as u8;
-        let cur_level = cur_privilege.clone() as u8;
-        match privilege <= cur_level {
-            true => {
-                self.write_direct(addr, data);
-                Ok(match addr {
-                    CSR_SPTBR => true,
-                    _ => false,
+        match self.check_privilege(addr, cur_privilege) {
+            Ok(()) => {}
+            Err(exception) => {
+                return Err(Trap {
+                    exception,
+                    value: word as u64,
                 })
             }
-            _ => Err(Trap {
+        };
+        // The CSRs whose address bits [11:10] are 0b11 are read-only.
+        if (addr >> 10) == 0x3 {
+            return Err(Trap {
                 exception: Exception::IllegalInstruction,
-                value: instruction_addr,
-            }),
+                value: word as u64,
+            });
+        }
+        let addr = self.substitute_virtual_supervisor(addr);
+        let translation_status = self.get_translation_status();
+        // The bits out of the write mask keep their values.
+        let data = match CSR_DESCRIPTIONS.get(&addr) {
+            Some(description) if description.write_mask != WRITE_ALL => {
+                (self.csr[addr as usize] & !description.write_mask)
+                    | (data & description.write_mask)
+            }
+            _ => data,
+        };
+        match addr {
+            // vl, vtype and vlenb are read-only and only updated by vset{i}vl{i}.
+            CSR_VL | CSR_VTYPE | CSR_VLENB => {}
+            // C extension can not be disabled when the next instruction is not
+            // aligned to a four-byte boundary.
+            CSR_MISA if (instruction_addr.wrapping_add(4) & 0x3) != 0 => {
+                self.write_direct(addr, data | (self.csr[CSR_MISA as usize] & CSR_MISA_C))
+            }
+            _ => self.write_direct(addr, data),
         }
+        Ok(matches!(
+            addr,
+            CSR_SPTBR | CSR_VSATP | CSR_HGATP | CSR_PMPCFG0..=CSR_PMPADDR63 | CSR_MISA
+        ) || translation_status != self.get_translation_status())
+    }
+
+    /// MPRV, SUM and MXR fields of mstatus and vsstatus, and MPP and MPV fields of
+    /// mstatus which take effect only when MPRV=1.
+    pub fn get_translation_status(&self) -> (u64, u64) {
+        let mstatus = self.csr[CSR_MSTATUS as usize];
+        let mask = match (mstatus & CSR_STATUS_MPRV) != 0 {
+            true => TRANSLATION_STATUS_MASK | CSR_STATUS_MPP | CSR_STATUS_MPV,
+            false => TRANSLATION_STATUS_MASK,
+        };
+        (
+            mstatus & mask,
+            self.csr[CSR_VSSTATUS as usize] & TRANSLATION_STATUS_MASK,
+        )
     }
 
     pub fn write_direct(&mut self, addr: u16, data: u64) {
         match addr {
             // User Floating-Point (FFLAGS/FRM/FCSR)
+            // Modifying the floating-point state sets mstatus.FS to Dirty.
             CSR_FFLAGS => {
                 self.csr[CSR_FCSR as usize] &= !0x1f;
                 self.csr[CSR_FCSR as usize] |= data & 0x1f;
+                self.set_status_dirty(CSR_STATUS_FS);
             }
             CSR_FRM => {
                 self.csr[CSR_FCSR as usize] &= !0xe0;
                 self.csr[CSR_FCSR as usize] |= (data << 5) & 0xe0;
+                self.set_status_dirty(CSR_STATUS_FS);
+            }
+            CSR_FCSR => {
+                self.csr[CSR_FCSR as usize] = data & 0xff;
+                self.set_status_dirty(CSR_STATUS_FS);
+            }
+
+            // Vector (VSTART/VXSAT/VXRM/VCSR)
+            // Modifying the vector state sets mstatus.VS to Dirty.
+            CSR_VSTART => {
+                self.csr[CSR_VSTART as usize] = data & 0xffff;
+                self.set_status_dirty(CSR_STATUS_VS);
+            }
+            CSR_VXSAT => {
+                self.csr[CSR_VXSAT as usize] = data & 0x1;
+                self.set_status_dirty(CSR_STATUS_VS);
+            }
+            CSR_VXRM => {
+                self.csr[CSR_VXRM as usize] = data & 0x3;
+                self.set_status_dirty(CSR_STATUS_VS);
+            }
+            CSR_VCSR => {
+                self.csr[CSR_VXSAT as usize] = data & 0x1;
+                self.csr[CSR_VXRM as usize] = (data >> 1) & 0x3;
+                self.set_status_dirty(CSR_STATUS_VS);
             }
 
-            // Restricted views of the mstatus register appear as the hstatus and
-            // sstatus registers in the H and S privilege-level ISAs respectively.
-            CSR_HSTATUS => panic!("TODO: HSTATUS"),
+            // SD bit is read-only. MPP is a WARL field which does not hold the
+            // reserved privilege level 2.
+            CSR_MSTATUS => {
+                let data = match data & CSR_STATUS_MPP {
+                    0x1000 => {
+                        (data & !CSR_STATUS_MPP) | (self.csr[CSR_MSTATUS as usize] & CSR_STATUS_MPP)
+                    }
+                    _ => data,
+                };
+                self.csr[CSR_MSTATUS as usize] =
+                    data & !(CSR_STATUS_SD32 | CSR_STATUS_SD64 | CSR_STATUS_UXL | CSR_STATUS_SXL);
+            }
+            CSR_MSTATUSH => {
+                self.csr[CSR_MSTATUS as usize] =
+                    (self.csr[CSR_MSTATUS as usize] & 0xffffffff) | ((data & 0xffffffff) << 32);
+            }
+
+            // Only C extension can be disabled. MXL selects XLEN of 32 (1) or 64 (2),
+            // and the other values are ignored.
+            CSR_MISA => {
+                self.csr[CSR_MISA as usize] = (MISA_EXTENSIONS & !CSR_MISA_C) | (data & CSR_MISA_C);
+                let mxl = match self.xlen {
+                    Xlen::X32 => (data >> 30) & 0x3,
+                    Xlen::X64 => data >> 62,
+                };
+                match mxl {
+                    1 => self.xlen = Xlen::X32,
+                    2 => self.xlen = Xlen::X64,
+                    _ => {}
+                };
+            }
+
+            // A restricted view of the mstatus register appears as the sstatus register
+            // in the S privilege-level ISA. vsstatus is a copy of sstatus for VS-mode.
             CSR_SSTATUS => {
-                let mask = CSR_STATUS_PUM
-                    | CSR_STATUS_XS
-                    | CSR_STATUS_FS
-                    | CSR_STATUS_SPP
-                    | CSR_STATUS_SPIE
-                    | CSR_STATUS_UPIE
-                    | CSR_STATUS_SIE
-                    | CSR_STATUS_UIE;
                 self.csr[CSR_MSTATUS as usize] =
-                    (self.csr[CSR_MSTATUS as usize] & !mask) | (data & mask);
+                    (self.csr[CSR_MSTATUS as usize] & !SSTATUS_MASK) | (data & SSTATUS_MASK);
+            }
+            CSR_VSSTATUS => {
+                self.csr[CSR_VSSTATUS as usize] = data & SSTATUS_MASK;
+            }
+
+            // Hypervisor (HSTATUS/HEDELEG/HIDELEG/HIE/HIP/HVIP/HGATP)
+            CSR_HSTATUS => {
+                let mask = CSR_HSTATUS_GVA
+                    | CSR_HSTATUS_SPV
+                    | CSR_HSTATUS_SPVP
+                    | CSR_HSTATUS_HU
+                    | CSR_HSTATUS_VTVM
+                    | CSR_HSTATUS_VTW
+                    | CSR_HSTATUS_VTSR;
+                self.csr[CSR_HSTATUS as usize] = data & mask;
+            }
+            // Environment calls from HS-mode, VS-mode and M-mode, guest-page faults and
+            // virtual instruction exceptions can not be delegated to VS-mode.
+            CSR_HEDELEG => self.csr[CSR_HEDELEG as usize] = data & !0x00f00e00,
+            CSR_HIDELEG => self.csr[CSR_HIDELEG as usize] = data & VS_INTERRUPTS,
+            CSR_HIE => {
+                self.csr[CSR_MIE as usize] =
+                    (self.csr[CSR_MIE as usize] & !VS_INTERRUPTS) | (data & VS_INTERRUPTS);
+            }
+            CSR_HIP => {
+                self.csr[CSR_MIP as usize] =
+                    (self.csr[CSR_MIP as usize] & !CSR_IP_VSSIP) | (data & CSR_IP_VSSIP);
+            }
+            CSR_HVIP => {
+                self.csr[CSR_MIP as usize] =
+                    (self.csr[CSR_MIP as usize] & !VS_INTERRUPTS) | (data & VS_INTERRUPTS);
+            }
+            CSR_VSIP => {
+                let mask = self.csr[CSR_HIDELEG as usize] & CSR_IP_VSSIP;
+                self.csr[CSR_MIP as usize] =
+                    (self.csr[CSR_MIP as usize] & !mask) | ((data << 1) & mask);
+            }
+            CSR_VSIE => {
+                let mask = self.csr[CSR_HIDELEG as usize] & VS_INTERRUPTS;
+                self.csr[CSR_MIE as usize] =
+                    (self.csr[CSR_MIE as usize] & !mask) | ((data << 1) & mask);
+            }
+            // No guest external interrupt is implemented (GEILEN=0).
+            CSR_HGEIE | CSR_HGEIP => {}
+            // Only Bare and Sv32x4/Sv39x4/Sv48x4/Sv57x4 are supported, and a write with
+            // an unsupported MODE has no effect. VMID is not implemented and the root
+            // page table is 16 KiB aligned.
+            CSR_HGATP => match self.xlen {
+                Xlen::X32 => self.csr[CSR_HGATP as usize] = data & 0x803ffffc,
+                Xlen::X64 => match data >> 60 {
+                    0 | 8 | 9 | 10 => self.csr[CSR_HGATP as usize] = data & 0xf0000fff_fffffffc,
+                    _ => {}
+                },
+            },
+
+            // Trap-Vector Base-Address (MTVEC/STVEC/VSTVEC)
+            // MODE is either 0 (direct) or 1 (vectored). A write with a reserved MODE
+            // keeps the previous MODE.
+            CSR_MTVEC | CSR_STVEC | CSR_VSTVEC => {
+                let mode = match data & 0x3 {
+                    0 | 1 => data & 0x3,
+                    _ => self.csr[addr as usize] & 0x3,
+                };
+                self.csr[addr as usize] = (data & !0x3) | mode;
+            }
+
+            // Supervisor Protection and Translation (SATP/VSATP)
+            // Only Bare and Sv39/Sv48/Sv57 are supported on RV64, and a write with an
+            // unsupported MODE has no effect.
+            CSR_SPTBR | CSR_VSATP => match self.xlen {
+                Xlen::X32 => self.csr[addr as usize] = data & 0xffffffff,
+                Xlen::X64 => match data >> 60 {
+                    0 | 8 | 9 | 10 => self.csr[addr as usize] = data,
+                    _ => {}
+                },
+            },
+
+            // Physical Memory Protection (PMPCFG/PMPADDR)
+            // Bits 5 and 6 of pmpcfg are reserved, and the reserved combination of
+            // R=0 and W=1 is not held.
+            CSR_PMPCFG0..=CSR_PMPCFG15 => {
+                let entries_per_csr = match self.xlen {
+                    Xlen::X32 => 4,
+                    Xlen::X64 => 8,
+                };
+                let base = ((addr - CSR_PMPCFG0) as usize) * 4;
+                for i in 0..entries_per_csr {
+                    let entry = base + i;
+                    if entry >= self.pmp_entries || (self.get_pmpcfg(entry) & CSR_PMPCFG_L) != 0 {
+                        continue;
+                    }
+                    let mut cfg = ((data >> (i * 8)) & 0x9f) as u8;
+                    if (cfg & CSR_PMPCFG_R) == 0 {
+                        cfg &= !CSR_PMPCFG_W;
+                    }
+                    self.set_pmpcfg(entry, cfg);
+                }
+            }
+            CSR_PMPADDR0..=CSR_PMPADDR63 => {
+                let entry = (addr - CSR_PMPADDR0) as usize;
+                if entry < self.pmp_entries && !self.is_pmpaddr_locked(entry) {
+                    self.csr[addr as usize] = match self.xlen {
+                        Xlen::X32 => data & 0xffffffff,
+                        Xlen::X64 => data & 0x003fffff_ffffffff,
+                    };
+                }
             }
 
             // Restricted views of the mip and mie registers appear as the hip/hie,
@@ -304,6 +1130,10 @@ impl Csr {
                     | CSR_IP_UTIP
                     | CSR_IP_SSIP
                     | CSR_IP_USIP;
+                let mask = match self.is_sstc_enabled() {
+                    true => mask & !CSR_IP_STIP,
+                    false => mask,
+                };
                 self.csr[CSR_MIP as usize] = (self.csr[CSR_MIP as usize] & !mask) | (data & mask);
             }
             CSR_SIE => {
@@ -316,17 +1146,76 @@ impl Csr {
                 self.csr[CSR_MIE as usize] = (self.csr[CSR_MIE as usize] & !mask) | (data & mask);
             }
 
-            // timer
-            CSR_MCYCLE | CSR_MTIME | CSR_MINSTRET | CSR_MCYCLEH | CSR_MTIMEH | CSR_MINSTRETH => {
-                panic!("TODO: CSR MTimer")
+            // Counters (MCYCLE/MINSTRET/MHPMCOUNTER/MHPMEVENT/MCOUNTINHIBIT)
+            // A write to the lower half keeps the upper 32 bits on RV32. The written
+            // counter is not incremented by the writing instruction.
+            CSR_MCYCLE | CSR_MINSTRET | CSR_MHPMCOUNTER3..=CSR_MHPMCOUNTER31 => {
+                self.csr[addr as usize] = match self.xlen {
+                    Xlen::X32 => (self.csr[addr as usize] & !0xffffffff) | (data & 0xffffffff),
+                    Xlen::X64 => data,
+                };
+                self.written_counters |= 1 << (addr & 0x1f);
+            }
+            CSR_MCYCLEH | CSR_MINSTRETH | CSR_MHPMCOUNTER3H..=CSR_MHPMCOUNTER31H => {
+                let counter = (CSR_MCYCLE | (addr & 0x1f)) as usize;
+                self.csr[counter] = (self.csr[counter] & 0xffffffff) | ((data & 0xffffffff) << 32);
+                self.written_counters |= 1 << (addr & 0x1f);
+            }
+            // The time bit of mcountinhibit is hardwired to zero.
+            CSR_MCOUNTINHIBIT => {
+                self.csr[CSR_MCOUNTINHIBIT as usize] = data & 0xffffffff & !CSR_COUNTER_TM;
+            }
+            CSR_MHPMEVENT3..=CSR_MHPMEVENT31 => {
+                self.csr[addr as usize] = data;
+                self.hpm_events = 0;
+                for event in CSR_MHPMEVENT3..=CSR_MHPMEVENT31 {
+                    if self.csr[event as usize] < 64 {
+                        self.hpm_events |= 1 << self.csr[event as usize];
+                    }
+                }
+            }
+            CSR_MCOUNTEREN | CSR_SCOUNTEREN | CSR_HCOUNTEREN => {
+                self.csr[addr as usize] = data & 0xffffffff;
+            }
+            // The user-level counters are read-only.
+            CSR_CYCLE..=CSR_HPMCOUNTER31 | CSR_CYCLEH..=CSR_HPMCOUNTER31H => {}
+
+            // Supervisor Timer Compare (STIMECMP/STIMECMPH/MENVCFG/MENVCFGH)
+            // Only STCE of menvcfg is implemented. A write to the lower half keeps the
+            // upper 32 bits on RV32.
+            CSR_STIMECMP => {
+                self.csr[CSR_STIMECMP as usize] = match self.xlen {
+                    Xlen::X32 => {
+                        (self.csr[CSR_STIMECMP as usize] & !0xffffffff) | (data & 0xffffffff)
+                    }
+                    Xlen::X64 => data,
+                };
+                self.update_supervisor_timer_interrupt();
+            }
+            CSR_STIMECMPH => {
+                self.csr[CSR_STIMECMP as usize] =
+                    (self.csr[CSR_STIMECMP as usize] & 0xffffffff) | ((data & 0xffffffff) << 32);
+                self.update_supervisor_timer_interrupt();
             }
-            CSR_HCYCLE | CSR_HTIME | CSR_HINSTRET | CSR_HCYCLEH | CSR_HTIMEH | CSR_HINSTRETH => {
-                panic!("TODO: CSR HTimer")
+            CSR_MENVCFG => match self.xlen {
+                Xlen::X32 => {}
+                Xlen::X64 => {
+                    self.csr[CSR_MENVCFG as usize] = data & CSR_MENVCFG_STCE;
+                    self.update_supervisor_timer_interrupt();
+                }
+            },
+            CSR_MENVCFGH => {
+                self.csr[CSR_MENVCFG as usize] = ((data & 0xffffffff) << 32) & CSR_MENVCFG_STCE;
+                self.update_supervisor_timer_interrupt();
             }
-            CSR_SCYCLE | CSR_STIME | CSR_SINSTRET | CSR_SCYCLEH | CSR_STIMEH | CSR_SINSTRETH => {
-                panic!("TODO: CSR STimer")
+            // STIP is read-only while menvcfg.STCE is set.
+            CSR_MIP => {
+                let mask = match self.is_sstc_enabled() {
+                    true => CSR_IP_STIP,
+                    false => 0,
+                };
+                self.csr[CSR_MIP as usize] = (self.csr[CSR_MIP as usize] & mask) | (data & !mask);
             }
-            CSR_INSTRET | CSR_CYCLEH | CSR_TIMEH | CSR_INSTRETH => panic!("TODO: CSR Timer"),
 
             _ => self.csr[addr as usize] = data,
         }
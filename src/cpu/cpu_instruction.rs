@@ -2,10 +2,14 @@ use std::collections::HashMap;
 
 use crate::cpu::cpu::{Cpu, Privilege, Xlen};
 use crate::cpu::cpu_csr::*;
+use crate::cpu::cpu_instruction_vector::{opecode_57, opecode_vector_load, opecode_vector_store};
+use crate::cpu::fpu;
+use crate::cpu::fpu::{FloatFormat, RoundingMode};
+use crate::cpu::mmu::Mmu;
 use crate::cpu::trap::*;
 
 pub struct Opecode {
-    pub operation: fn(cpu: &Cpu, addr: u64, word: u32) -> Result<&Instruction, ()>,
+    pub operation: fn(cpu: &Cpu, addr: u64, word: u32) -> Result<&'static Instruction, ()>,
 }
 
 pub struct Instruction {
@@ -14,6 +18,92 @@ pub struct Instruction {
     pub disassemble: fn(cpu: &Cpu, mnemonic: &str, word: u32) -> String,
 }
 
+/// The operands extracted from an instruction word. rd, rs1 and rs2 are the register
+/// fields, and imm is the sign-extended immediate of the format of the opcode, or 0
+/// if the format has no immediate.
+#[derive(Clone, Copy, Default)]
+pub struct Operands {
+    pub rd: usize,
+    pub rs1: usize,
+    pub rs2: usize,
+    pub imm: i64,
+}
+
+/// The handler of an instruction which takes the operands decoded in advance.
+pub type OperandsOperation = fn(cpu: &mut Cpu, addr: u64, o: &Operands) -> Result<(), Trap>;
+
+impl Operands {
+    pub fn new(word: u32) -> Self {
+        let r = parse_type_r(word);
+        let imm = match word & 0x7f {
+            0x03 | 0x07 | 0x0f | 0x13 | 0x1b | 0x67 | 0x73 => parse_type_i(word).imm,
+            0x23 | 0x27 => parse_type_s(word).imm,
+            0x63 => parse_type_b(word).imm as i64,
+            0x17 | 0x37 => parse_type_u(word).imm as i64,
+            0x6f => parse_type_j(word).imm as i64,
+            _ => 0,
+        };
+        Operands {
+            rd: r.rd as usize,
+            rs1: r.rs1 as usize,
+            rs2: r.rs2 as usize,
+            imm,
+        }
+    }
+}
+
+/// the handler taking the decoded operands of the instruction, which is provided
+/// for the common integer instructions, so that the decoded instruction cache and
+/// the block engine run them without extracting the operands again.
+pub fn get_operands_operation(instruction: &Instruction) -> Option<OperandsOperation> {
+    let operation: OperandsOperation = match instruction.mnemonic {
+        "lui" => lui_operands,
+        "auipc" => auipc_operands,
+        "jal" => jal_operands,
+        "jalr" => jalr_operands,
+        "beq" => beq_operands,
+        "bne" => bne_operands,
+        "blt" => blt_operands,
+        "bge" => bge_operands,
+        "bltu" => bltu_operands,
+        "bgeu" => bgeu_operands,
+        "lb" => lb_operands,
+        "lh" => lh_operands,
+        "lw" => lw_operands,
+        "lbu" => lbu_operands,
+        "lhu" => lhu_operands,
+        "lwu" => lwu_operands,
+        "ld" => ld_operands,
+        "sb" => sb_operands,
+        "sh" => sh_operands,
+        "sw" => sw_operands,
+        "sd" => sd_operands,
+        "addi" => addi_operands,
+        "slti" => slti_operands,
+        "sltiu" => sltiu_operands,
+        "xori" => xori_operands,
+        "ori" => ori_operands,
+        "andi" => andi_operands,
+        "slli" => slli_operands,
+        "srli" => srli_operands,
+        "srai" => srai_operands,
+        "add" => add_operands,
+        "sub" => sub_operands,
+        "sll" => sll_operands,
+        "slt" => slt_operands,
+        "sltu" => sltu_operands,
+        "xor" => xor_operands,
+        "srl" => srl_operands,
+        "sra" => sra_operands,
+        "or" => or_operands,
+        "and" => and_operands,
+        "addiw" => addiw_operands,
+        "addw" => addw_operands,
+        _ => return None,
+    };
+    Some(operation)
+}
+
 struct InstructionTypeB {
     rs1: u8,
     rs2: u8,
@@ -26,6 +116,13 @@ struct InstructionTypeR {
     rs2: u8,
 }
 
+struct InstructionTypeR4 {
+    rd: u8,
+    rs1: u8,
+    rs2: u8,
+    rs3: u8,
+}
+
 struct InstructionTypeI {
     rd: u8,
     rs1: u8,
@@ -56,7 +153,7 @@ struct InstructionTypeCSR {
 
 lazy_static! {
     // ABI name
-    static ref REGISTERS: HashMap<u8, &'static str> = {
+    pub static ref REGISTERS: HashMap<u8, &'static str> = {
         let mut m = HashMap::new();
         m.insert(0, "zero");  // Hard-wired zero
         m.insert(1, "ra");    // Return address
@@ -93,6 +190,44 @@ lazy_static! {
         m
     };
 
+    // ABI name (floating-point)
+    static ref FP_REGISTERS: HashMap<u8, &'static str> = {
+        let mut m = HashMap::new();
+        m.insert(0, "ft0");   // FP temporaries
+        m.insert(1, "ft1");   // FP temporaries
+        m.insert(2, "ft2");   // FP temporaries
+        m.insert(3, "ft3");   // FP temporaries
+        m.insert(4, "ft4");   // FP temporaries
+        m.insert(5, "ft5");   // FP temporaries
+        m.insert(6, "ft6");   // FP temporaries
+        m.insert(7, "ft7");   // FP temporaries
+        m.insert(8, "fs0");   // FP saved registers
+        m.insert(9, "fs1");   // FP saved registers
+        m.insert(10, "fa0");  // FP arguments/return values
+        m.insert(11, "fa1");  // FP arguments/return values
+        m.insert(12, "fa2");  // FP arguments
+        m.insert(13, "fa3");  // FP arguments
+        m.insert(14, "fa4");  // FP arguments
+        m.insert(15, "fa5");  // FP arguments
+        m.insert(16, "fa6");  // FP arguments
+        m.insert(17, "fa7");  // FP arguments
+        m.insert(18, "fs2");  // FP saved registers
+        m.insert(19, "fs3");  // FP saved registers
+        m.insert(20, "fs4");  // FP saved registers
+        m.insert(21, "fs5");  // FP saved registers
+        m.insert(22, "fs6");  // FP saved registers
+        m.insert(23, "fs7");  // FP saved registers
+        m.insert(24, "fs8");  // FP saved registers
+        m.insert(25, "fs9");  // FP saved registers
+        m.insert(26, "fs10"); // FP saved registers
+        m.insert(27, "fs11"); // FP saved registers
+        m.insert(28, "ft8");  // FP temporaries
+        m.insert(29, "ft9");  // FP temporaries
+        m.insert(30, "ft10"); // FP temporaries
+        m.insert(31, "ft11"); // FP temporaries
+        m
+    };
+
     pub static ref OPECODES: HashMap<u8, Opecode> = {
         let mut m = HashMap::new();
         m.insert(0x03, Opecode {operation: opecode_03});
@@ -107,7 +242,12 @@ lazy_static! {
         m.insert(0x33, Opecode {operation: opecode_33});
         m.insert(0x37, Opecode {operation: opecode_37});
         m.insert(0x3b, Opecode {operation: opecode_3b});
+        m.insert(0x43, Opecode {operation: opecode_43});
+        m.insert(0x47, Opecode {operation: opecode_47});
+        m.insert(0x4b, Opecode {operation: opecode_4b});
+        m.insert(0x4f, Opecode {operation: opecode_4f});
         m.insert(0x53, Opecode {operation: opecode_53});
+        m.insert(0x57, Opecode {operation: opecode_57});
         m.insert(0x63, Opecode {operation: opecode_63});
         m.insert(0x67, Opecode {operation: opecode_67});
         m.insert(0x6F, Opecode {operation: opecode_6f});
@@ -159,15 +299,15 @@ lazy_static! {
     // RV32F/RV64F Single/Double-Precision Load Instructions.
     static ref INSTRUCTIONS_GROUP07: HashMap<u8, Instruction> = {
         let mut m = HashMap::new();
-        m.insert(0, Instruction{
+        m.insert(2, Instruction{
             mnemonic: "flw",
             operation: flw,
-            disassemble: disassemble_precision_load,
+            disassemble: disassemble_fp_load,
         });
         m.insert(3, Instruction{
             mnemonic: "fld",
             operation: fld,
-            disassemble: disassemble_precision_load,
+            disassemble: disassemble_fp_load,
         });
         /* TODO: support 128-bit
         m.insert(4, Instruction{
@@ -183,12 +323,12 @@ lazy_static! {
         m.insert(2, Instruction{
             mnemonic: "fsw",
             operation: fsw,
-            disassemble: disassemble_s,
+            disassemble: disassemble_precision_store,
         });
         m.insert(3, Instruction{
             mnemonic: "fsd",
             operation: fsd,
-            disassemble: disassemble_s,
+            disassemble: disassemble_precision_store,
         });
         m
     };
@@ -203,7 +343,7 @@ lazy_static! {
         });
         m.insert(1, Instruction{
             mnemonic: "fence.i",
-            operation: fence,
+            operation: fence_i,
             disassemble: disassemble_mnemonic,
         });
         m
@@ -217,11 +357,6 @@ lazy_static! {
             operation: addi,
             disassemble: disassemble_precision_load,
         });
-        m.insert(1, Instruction{
-            mnemonic: "slli",
-            operation: slli,
-            disassemble: disassemble_computation_shamt,
-        });
         m.insert(2, Instruction{
             mnemonic: "slti",
             operation: slti,
@@ -261,6 +396,82 @@ lazy_static! {
             operation: srai,
             disassemble: disassemble_computation_shamt,
         });
+        m.insert((0, 1), Instruction{
+            mnemonic: "slli",
+            operation: slli,
+            disassemble: disassemble_computation_shamt,
+        });
+        m.insert((0x14, 1), Instruction{
+            mnemonic: "bseti",
+            operation: bseti,
+            disassemble: disassemble_computation_shamt,
+        });
+        m.insert((0x24, 1), Instruction{
+            mnemonic: "bclri",
+            operation: bclri,
+            disassemble: disassemble_computation_shamt,
+        });
+        m.insert((0x34, 1), Instruction{
+            mnemonic: "binvi",
+            operation: binvi,
+            disassemble: disassemble_computation_shamt,
+        });
+        m.insert((0x24, 5), Instruction{
+            mnemonic: "bexti",
+            operation: bexti,
+            disassemble: disassemble_computation_shamt,
+        });
+        m.insert((0x30, 5), Instruction{
+            mnemonic: "rori",
+            operation: rori,
+            disassemble: disassemble_computation_shamt,
+        });
+        m
+    };
+
+    // Zbb unary instructions encoded as the immediate of shift instructions.
+    static ref INSTRUCTIONS_GROUP13_UNARY: HashMap<(u16, u8), Instruction> = {
+        let mut m = HashMap::new();
+        m.insert((0x600, 1), Instruction{
+            mnemonic: "clz",
+            operation: clz,
+            disassemble: disassemble_r2,
+        });
+        m.insert((0x601, 1), Instruction{
+            mnemonic: "ctz",
+            operation: ctz,
+            disassemble: disassemble_r2,
+        });
+        m.insert((0x602, 1), Instruction{
+            mnemonic: "cpop",
+            operation: cpop,
+            disassemble: disassemble_r2,
+        });
+        m.insert((0x604, 1), Instruction{
+            mnemonic: "sext.b",
+            operation: sext_b,
+            disassemble: disassemble_r2,
+        });
+        m.insert((0x605, 1), Instruction{
+            mnemonic: "sext.h",
+            operation: sext_h,
+            disassemble: disassemble_r2,
+        });
+        m.insert((0x287, 5), Instruction{
+            mnemonic: "orc.b",
+            operation: orc_b,
+            disassemble: disassemble_r2,
+        });
+        m.insert((0x698, 5), Instruction{
+            mnemonic: "rev8",
+            operation: rev8,
+            disassemble: disassemble_r2,
+        });
+        m.insert((0x6b8, 5), Instruction{
+            mnemonic: "rev8",
+            operation: rev8,
+            disassemble: disassemble_r2,
+        });
         m
     };
 
@@ -282,11 +493,6 @@ lazy_static! {
             operation: addiw,
             disassemble: disassemble_precision_load,
         });
-        m.insert(1, Instruction{
-            mnemonic: "slliw",
-            operation: slliw,
-            disassemble: disassemble_precision_load,
-        });
         m
     };
 
@@ -302,6 +508,41 @@ lazy_static! {
             operation: sraiw,
             disassemble: disassemble_precision_load,
         });
+        m.insert((0, 1), Instruction{
+            mnemonic: "slliw",
+            operation: slliw,
+            disassemble: disassemble_precision_load,
+        });
+        m.insert((0x04, 1), Instruction{
+            mnemonic: "slli.uw",
+            operation: slli_uw,
+            disassemble: disassemble_computation_shamt,
+        });
+        m.insert((0x30, 5), Instruction{
+            mnemonic: "roriw",
+            operation: roriw,
+            disassemble: disassemble_precision_load,
+        });
+        m
+    };
+
+    static ref INSTRUCTIONS_GROUP1B_UNARY: HashMap<(u16, u8), Instruction> = {
+        let mut m = HashMap::new();
+        m.insert((0x600, 1), Instruction{
+            mnemonic: "clzw",
+            operation: clzw,
+            disassemble: disassemble_r2,
+        });
+        m.insert((0x601, 1), Instruction{
+            mnemonic: "ctzw",
+            operation: ctzw,
+            disassemble: disassemble_r2,
+        });
+        m.insert((0x602, 1), Instruction{
+            mnemonic: "cpopw",
+            operation: cpopw,
+            disassemble: disassemble_r2,
+        });
         m
     };
 
@@ -538,6 +779,106 @@ lazy_static! {
             operation: remu,
             disassemble: disassemble_r,
         });
+        m.insert((0x10, 2), Instruction{
+            mnemonic: "sh1add",
+            operation: sh1add,
+            disassemble: disassemble_r,
+        });
+        m.insert((0x10, 4), Instruction{
+            mnemonic: "sh2add",
+            operation: sh2add,
+            disassemble: disassemble_r,
+        });
+        m.insert((0x10, 6), Instruction{
+            mnemonic: "sh3add",
+            operation: sh3add,
+            disassemble: disassemble_r,
+        });
+        m.insert((0x20, 7), Instruction{
+            mnemonic: "andn",
+            operation: andn,
+            disassemble: disassemble_r,
+        });
+        m.insert((0x20, 6), Instruction{
+            mnemonic: "orn",
+            operation: orn,
+            disassemble: disassemble_r,
+        });
+        m.insert((0x20, 4), Instruction{
+            mnemonic: "xnor",
+            operation: xnor,
+            disassemble: disassemble_r,
+        });
+        m.insert((0x05, 6), Instruction{
+            mnemonic: "max",
+            operation: max,
+            disassemble: disassemble_r,
+        });
+        m.insert((0x05, 7), Instruction{
+            mnemonic: "maxu",
+            operation: maxu,
+            disassemble: disassemble_r,
+        });
+        m.insert((0x05, 4), Instruction{
+            mnemonic: "min",
+            operation: min,
+            disassemble: disassemble_r,
+        });
+        m.insert((0x05, 5), Instruction{
+            mnemonic: "minu",
+            operation: minu,
+            disassemble: disassemble_r,
+        });
+        m.insert((0x04, 4), Instruction{
+            mnemonic: "zext.h",
+            operation: zext_h,
+            disassemble: disassemble_r2,
+        });
+        m.insert((0x30, 1), Instruction{
+            mnemonic: "rol",
+            operation: rol,
+            disassemble: disassemble_r,
+        });
+        m.insert((0x30, 5), Instruction{
+            mnemonic: "ror",
+            operation: ror,
+            disassemble: disassemble_r,
+        });
+        m.insert((0x05, 1), Instruction{
+            mnemonic: "clmul",
+            operation: clmul,
+            disassemble: disassemble_r,
+        });
+        m.insert((0x05, 2), Instruction{
+            mnemonic: "clmulr",
+            operation: clmulr,
+            disassemble: disassemble_r,
+        });
+        m.insert((0x05, 3), Instruction{
+            mnemonic: "clmulh",
+            operation: clmulh,
+            disassemble: disassemble_r,
+        });
+        m.insert((0x24, 1), Instruction{
+            mnemonic: "bclr",
+            operation: bclr,
+            disassemble: disassemble_r,
+        });
+        m.insert((0x24, 5), Instruction{
+            mnemonic: "bext",
+            operation: bext,
+            disassemble: disassemble_r,
+        });
+        m.insert((0x34, 1), Instruction{
+            mnemonic: "binv",
+            operation: binv,
+            disassemble: disassemble_r,
+        });
+        m.insert((0x14, 1), Instruction{
+            mnemonic: "bset",
+            operation: bset,
+            disassemble: disassemble_r,
+        });
         m
     };
 
@@ -593,15 +934,366 @@ lazy_static! {
             operation: remuw,
             disassemble: disassemble_r,
         });
+        m.insert((0x04, 0), Instruction{
+            mnemonic: "add.uw",
+            operation: add_uw,
+            disassemble: disassemble_r,
+        });
+        m.insert((0x10, 2), Instruction{
+            mnemonic: "sh1add.uw",
+            operation: sh1add_uw,
+            disassemble: disassemble_r,
+        });
+        m.insert((0x10, 4), Instruction{
+            mnemonic: "sh2add.uw",
+            operation: sh2add_uw,
+            disassemble: disassemble_r,
+        });
+        m.insert((0x10, 6), Instruction{
+            mnemonic: "sh3add.uw",
+            operation: sh3add_uw,
+            disassemble: disassemble_r,
+        });
+        m.insert((0x04, 4), Instruction{
+            mnemonic: "zext.h",
+            operation: zext_h,
+            disassemble: disassemble_r2,
+        });
+        m.insert((0x30, 1), Instruction{
+            mnemonic: "rolw",
+            operation: rolw,
+            disassemble: disassemble_r,
+        });
+        m.insert((0x30, 5), Instruction{
+            mnemonic: "rorw",
+            operation: rorw,
+            disassemble: disassemble_r,
+        });
         m
     };
 
-    static ref INSTRUCTIONS_GROUP53: HashMap<(u8, u8), Instruction> = {
+    // Floating-Point Computational Instructions.
+    static ref INSTRUCTIONS_GROUP53: HashMap<u8, Instruction> = {
+        let mut m = HashMap::new();
+        m.insert(0x00, Instruction{
+            mnemonic: "fadd.s",
+            operation: fadd_s,
+            disassemble: disassemble_fp_r,
+        });
+        m.insert(0x04, Instruction{
+            mnemonic: "fsub.s",
+            operation: fsub_s,
+            disassemble: disassemble_fp_r,
+        });
+        m.insert(0x08, Instruction{
+            mnemonic: "fmul.s",
+            operation: fmul_s,
+            disassemble: disassemble_fp_r,
+        });
+        m.insert(0x0c, Instruction{
+            mnemonic: "fdiv.s",
+            operation: fdiv_s,
+            disassemble: disassemble_fp_r,
+        });
+        m.insert(0x2c, Instruction{
+            mnemonic: "fsqrt.s",
+            operation: fsqrt_s,
+            disassemble: disassemble_fp_r2,
+        });
+        m.insert(0x01, Instruction{
+            mnemonic: "fadd.d",
+            operation: fadd_d,
+            disassemble: disassemble_fp_r,
+        });
+        m.insert(0x05, Instruction{
+            mnemonic: "fsub.d",
+            operation: fsub_d,
+            disassemble: disassemble_fp_r,
+        });
+        m.insert(0x09, Instruction{
+            mnemonic: "fmul.d",
+            operation: fmul_d,
+            disassemble: disassemble_fp_r,
+        });
+        m.insert(0x0d, Instruction{
+            mnemonic: "fdiv.d",
+            operation: fdiv_d,
+            disassemble: disassemble_fp_r,
+        });
+        m.insert(0x2d, Instruction{
+            mnemonic: "fsqrt.d",
+            operation: fsqrt_d,
+            disassemble: disassemble_fp_r2,
+        });
+        m
+    };
+    // Floating-Point Sign-Injection, Min/Max, Compare, Move and Classify Instructions.
+    static ref INSTRUCTIONS_GROUP53_SUB: HashMap<(u8, u8), Instruction> = {
         let mut m = HashMap::new();
+        m.insert((0x10, 0), Instruction{
+            mnemonic: "fsgnj.s",
+            operation: fsgnj_s,
+            disassemble: disassemble_fp_r,
+        });
+        m.insert((0x10, 1), Instruction{
+            mnemonic: "fsgnjn.s",
+            operation: fsgnjn_s,
+            disassemble: disassemble_fp_r,
+        });
+        m.insert((0x10, 2), Instruction{
+            mnemonic: "fsgnjx.s",
+            operation: fsgnjx_s,
+            disassemble: disassemble_fp_r,
+        });
+        m.insert((0x14, 0), Instruction{
+            mnemonic: "fmin.s",
+            operation: fmin_s,
+            disassemble: disassemble_fp_r,
+        });
+        m.insert((0x14, 1), Instruction{
+            mnemonic: "fmax.s",
+            operation: fmax_s,
+            disassemble: disassemble_fp_r,
+        });
+        m.insert((0x50, 0), Instruction{
+            mnemonic: "fle.s",
+            operation: fle_s,
+            disassemble: disassemble_fp_cmp,
+        });
+        m.insert((0x50, 1), Instruction{
+            mnemonic: "flt.s",
+            operation: flt_s,
+            disassemble: disassemble_fp_cmp,
+        });
+        m.insert((0x50, 2), Instruction{
+            mnemonic: "feq.s",
+            operation: feq_s,
+            disassemble: disassemble_fp_cmp,
+        });
+        m.insert((0x70, 0), Instruction{
+            mnemonic: "fmv.x.w",
+            operation: fmv_x_w,
+            disassemble: disassemble_fp_to_x,
+        });
+        m.insert((0x70, 1), Instruction{
+            mnemonic: "fclass.s",
+            operation: fclass_s,
+            disassemble: disassemble_fp_to_x,
+        });
         m.insert((0x78, 0), Instruction{
             mnemonic: "fmv.w.x",
             operation: fmv_w_x,
-            disassemble: disassemble_r,
+            disassemble: disassemble_x_to_fp,
+        });
+        m.insert((0x11, 0), Instruction{
+            mnemonic: "fsgnj.d",
+            operation: fsgnj_d,
+            disassemble: disassemble_fp_r,
+        });
+        m.insert((0x11, 1), Instruction{
+            mnemonic: "fsgnjn.d",
+            operation: fsgnjn_d,
+            disassemble: disassemble_fp_r,
+        });
+        m.insert((0x11, 2), Instruction{
+            mnemonic: "fsgnjx.d",
+            operation: fsgnjx_d,
+            disassemble: disassemble_fp_r,
+        });
+        m.insert((0x15, 0), Instruction{
+            mnemonic: "fmin.d",
+            operation: fmin_d,
+            disassemble: disassemble_fp_r,
+        });
+        m.insert((0x15, 1), Instruction{
+            mnemonic: "fmax.d",
+            operation: fmax_d,
+            disassemble: disassemble_fp_r,
+        });
+        m.insert((0x51, 0), Instruction{
+            mnemonic: "fle.d",
+            operation: fle_d,
+            disassemble: disassemble_fp_cmp,
+        });
+        m.insert((0x51, 1), Instruction{
+            mnemonic: "flt.d",
+            operation: flt_d,
+            disassemble: disassemble_fp_cmp,
+        });
+        m.insert((0x51, 2), Instruction{
+            mnemonic: "feq.d",
+            operation: feq_d,
+            disassemble: disassemble_fp_cmp,
+        });
+        m.insert((0x71, 0), Instruction{
+            mnemonic: "fmv.x.d",
+            operation: fmv_x_d,
+            disassemble: disassemble_fp_to_x,
+        });
+        m.insert((0x71, 1), Instruction{
+            mnemonic: "fclass.d",
+            operation: fclass_d,
+            disassemble: disassemble_fp_to_x,
+        });
+        m.insert((0x79, 0), Instruction{
+            mnemonic: "fmv.d.x",
+            operation: fmv_d_x,
+            disassemble: disassemble_x_to_fp,
+        });
+        m
+    };
+    // Floating-Point Conversion Instructions.
+    static ref INSTRUCTIONS_GROUP53_CVT: HashMap<(u8, u8), Instruction> = {
+        let mut m = HashMap::new();
+        m.insert((0x60, 0), Instruction{
+            mnemonic: "fcvt.w.s",
+            operation: fcvt_w_s,
+            disassemble: disassemble_fp_to_x,
+        });
+        m.insert((0x60, 1), Instruction{
+            mnemonic: "fcvt.wu.s",
+            operation: fcvt_wu_s,
+            disassemble: disassemble_fp_to_x,
+        });
+        m.insert((0x60, 2), Instruction{
+            mnemonic: "fcvt.l.s",
+            operation: fcvt_l_s,
+            disassemble: disassemble_fp_to_x,
+        });
+        m.insert((0x60, 3), Instruction{
+            mnemonic: "fcvt.lu.s",
+            operation: fcvt_lu_s,
+            disassemble: disassemble_fp_to_x,
+        });
+        m.insert((0x68, 0), Instruction{
+            mnemonic: "fcvt.s.w",
+            operation: fcvt_s_w,
+            disassemble: disassemble_x_to_fp,
+        });
+        m.insert((0x68, 1), Instruction{
+            mnemonic: "fcvt.s.wu",
+            operation: fcvt_s_wu,
+            disassemble: disassemble_x_to_fp,
+        });
+        m.insert((0x68, 2), Instruction{
+            mnemonic: "fcvt.s.l",
+            operation: fcvt_s_l,
+            disassemble: disassemble_x_to_fp,
+        });
+        m.insert((0x68, 3), Instruction{
+            mnemonic: "fcvt.s.lu",
+            operation: fcvt_s_lu,
+            disassemble: disassemble_x_to_fp,
+        });
+        m.insert((0x20, 1), Instruction{
+            mnemonic: "fcvt.s.d",
+            operation: fcvt_s_d,
+            disassemble: disassemble_fp_r2,
+        });
+        m.insert((0x21, 0), Instruction{
+            mnemonic: "fcvt.d.s",
+            operation: fcvt_d_s,
+            disassemble: disassemble_fp_r2,
+        });
+        m.insert((0x61, 0), Instruction{
+            mnemonic: "fcvt.w.d",
+            operation: fcvt_w_d,
+            disassemble: disassemble_fp_to_x,
+        });
+        m.insert((0x61, 1), Instruction{
+            mnemonic: "fcvt.wu.d",
+            operation: fcvt_wu_d,
+            disassemble: disassemble_fp_to_x,
+        });
+        m.insert((0x61, 2), Instruction{
+            mnemonic: "fcvt.l.d",
+            operation: fcvt_l_d,
+            disassemble: disassemble_fp_to_x,
+        });
+        m.insert((0x61, 3), Instruction{
+            mnemonic: "fcvt.lu.d",
+            operation: fcvt_lu_d,
+            disassemble: disassemble_fp_to_x,
+        });
+        m.insert((0x69, 0), Instruction{
+            mnemonic: "fcvt.d.w",
+            operation: fcvt_d_w,
+            disassemble: disassemble_x_to_fp,
+        });
+        m.insert((0x69, 1), Instruction{
+            mnemonic: "fcvt.d.wu",
+            operation: fcvt_d_wu,
+            disassemble: disassemble_x_to_fp,
+        });
+        m.insert((0x69, 2), Instruction{
+            mnemonic: "fcvt.d.l",
+            operation: fcvt_d_l,
+            disassemble: disassemble_x_to_fp,
+        });
+        m.insert((0x69, 3), Instruction{
+            mnemonic: "fcvt.d.lu",
+            operation: fcvt_d_lu,
+            disassemble: disassemble_x_to_fp,
+        });
+        m
+    };
+    // Fused Multiply-Add Instructions.
+    static ref INSTRUCTIONS_GROUP43: HashMap<u8, Instruction> = {
+        let mut m = HashMap::new();
+        m.insert(0, Instruction{
+            mnemonic: "fmadd.s",
+            operation: fmadd_s,
+            disassemble: disassemble_fp_r4,
+        });
+        m.insert(1, Instruction{
+            mnemonic: "fmadd.d",
+            operation: fmadd_d,
+            disassemble: disassemble_fp_r4,
+        });
+        m
+    };
+    // Fused Multiply-Subtract Instructions.
+    static ref INSTRUCTIONS_GROUP47: HashMap<u8, Instruction> = {
+        let mut m = HashMap::new();
+        m.insert(0, Instruction{
+            mnemonic: "fmsub.s",
+            operation: fmsub_s,
+            disassemble: disassemble_fp_r4,
+        });
+        m.insert(1, Instruction{
+            mnemonic: "fmsub.d",
+            operation: fmsub_d,
+            disassemble: disassemble_fp_r4,
+        });
+        m
+    };
+    // Fused Negative Multiply-Subtract Instructions.
+    static ref INSTRUCTIONS_GROUP4B: HashMap<u8, Instruction> = {
+        let mut m = HashMap::new();
+        m.insert(0, Instruction{
+            mnemonic: "fnmsub.s",
+            operation: fnmsub_s,
+            disassemble: disassemble_fp_r4,
+        });
+        m.insert(1, Instruction{
+            mnemonic: "fnmsub.d",
+            operation: fnmsub_d,
+            disassemble: disassemble_fp_r4,
+        });
+        m
+    };
+    // Fused Negative Multiply-Add Instructions.
+    static ref INSTRUCTIONS_GROUP4F: HashMap<u8, Instruction> = {
+        let mut m = HashMap::new();
+        m.insert(0, Instruction{
+            mnemonic: "fnmadd.s",
+            operation: fnmadd_s,
+            disassemble: disassemble_fp_r4,
+        });
+        m.insert(1, Instruction{
+            mnemonic: "fnmadd.d",
+            operation: fnmadd_d,
+            disassemble: disassemble_fp_r4,
         });
         m
     };
@@ -711,109 +1403,218 @@ lazy_static! {
         });
         m
     };
+
+    // Hypervisor Virtual-Machine Load and Store Instructions.
+    static ref INSTRUCTIONS_GROUP73_HYPERVISOR: HashMap<(u8, u8), Instruction> = {
+        let mut m = HashMap::new();
+        m.insert((0x30, 0), Instruction{
+            mnemonic: "hlv.b",
+            operation: hlv_b,
+            disassemble: disassemble_r,
+        });
+        m.insert((0x30, 1), Instruction{
+            mnemonic: "hlv.bu",
+            operation: hlv_bu,
+            disassemble: disassemble_r,
+        });
+        m.insert((0x32, 0), Instruction{
+            mnemonic: "hlv.h",
+            operation: hlv_h,
+            disassemble: disassemble_r,
+        });
+        m.insert((0x32, 1), Instruction{
+            mnemonic: "hlv.hu",
+            operation: hlv_hu,
+            disassemble: disassemble_r,
+        });
+        m.insert((0x32, 3), Instruction{
+            mnemonic: "hlvx.hu",
+            operation: hlvx_hu,
+            disassemble: disassemble_r,
+        });
+        m.insert((0x34, 0), Instruction{
+            mnemonic: "hlv.w",
+            operation: hlv_w,
+            disassemble: disassemble_r,
+        });
+        m.insert((0x34, 1), Instruction{
+            mnemonic: "hlv.wu",
+            operation: hlv_wu,
+            disassemble: disassemble_r,
+        });
+        m.insert((0x34, 3), Instruction{
+            mnemonic: "hlvx.wu",
+            operation: hlvx_wu,
+            disassemble: disassemble_r,
+        });
+        m.insert((0x36, 0), Instruction{
+            mnemonic: "hlv.d",
+            operation: hlv_d,
+            disassemble: disassemble_r,
+        });
+        m.insert((0x31, 0), Instruction{
+            mnemonic: "hsv.b",
+            operation: hsv_b,
+            disassemble: disassemble_r,
+        });
+        m.insert((0x33, 0), Instruction{
+            mnemonic: "hsv.h",
+            operation: hsv_h,
+            disassemble: disassemble_r,
+        });
+        m.insert((0x35, 0), Instruction{
+            mnemonic: "hsv.w",
+            operation: hsv_w,
+            disassemble: disassemble_r,
+        });
+        m.insert((0x37, 0), Instruction{
+            mnemonic: "hsv.d",
+            operation: hsv_d,
+            disassemble: disassemble_r,
+        });
+        m
+    };
 }
 
-fn opecode_03(_cpu: &Cpu, _addr: u64, word: u32) -> Result<&Instruction, ()> {
+fn opecode_03(_cpu: &Cpu, _addr: u64, word: u32) -> Result<&'static Instruction, ()> {
     let funct3 = ((word & 0x00007000) >> 12) as u8;
     match INSTRUCTIONS_GROUP03.get(&funct3) {
         Some(instruction) => Ok(&instruction),
-        None => panic!("Not found instruction!"),
+        None => Err(()),
     }
 }
 
-fn opecode_07(_cpu: &Cpu, _addr: u64, word: u32) -> Result<&Instruction, ()> {
+fn opecode_07(_cpu: &Cpu, _addr: u64, word: u32) -> Result<&'static Instruction, ()> {
     let funct3 = ((word & 0x00007000) >> 12) as u8;
+    // vector loads are encoded with the width field of 0, 5, 6 and 7.
+    match funct3 {
+        0 | 5 | 6 | 7 => return opecode_vector_load(_cpu, _addr, word),
+        _ => {}
+    };
     match INSTRUCTIONS_GROUP07.get(&funct3) {
         Some(instruction) => Ok(&instruction),
-        None => panic!("Not found instruction!"),
+        None => Err(()),
     }
 }
 
-fn opecode_0f(_cpu: &Cpu, _addr: u64, word: u32) -> Result<&Instruction, ()> {
+fn opecode_0f(_cpu: &Cpu, _addr: u64, word: u32) -> Result<&'static Instruction, ()> {
     let funct3 = ((word & 0x00007000) >> 12) as u8;
     match INSTRUCTIONS_GROUP0F.get(&funct3) {
         Some(instruction) => Ok(&instruction),
-        None => panic!("Not found instruction!"),
+        None => Err(()),
     }
 }
 
-fn opecode_13(_cpu: &Cpu, _addr: u64, word: u32) -> Result<&Instruction, ()> {
+fn opecode_13(cpu: &Cpu, _addr: u64, word: u32) -> Result<&'static Instruction, ()> {
     let funct3 = ((word & 0x00007000) >> 12) as u8;
     match funct3 {
-        5 => {
+        1 | 5 => {
+            // the shift amount is 5-bit on RV32.
+            if matches!(cpu.xlen, Xlen::X32) && (word & 0x02000000) != 0 {
+                return Err(());
+            }
             let funct7 = ((word & 0xfc000000) >> 25) as u8;
-            match INSTRUCTIONS_GROUP13_SUB.get(&(funct7, funct3)) {
+            let imm = ((word & 0xfff00000) >> 20) as u16;
+            let instruction = match (funct7, funct3) {
+                // rev8 is encoded with the shift amount of XLEN - 8.
+                (0x34, 5) => match (imm, &cpu.xlen) {
+                    (0x698, Xlen::X32) | (0x6b8, Xlen::X64) => {
+                        INSTRUCTIONS_GROUP13_UNARY.get(&(imm, funct3))
+                    }
+                    _ => None,
+                },
+                (0x30, 1) | (0x14, 5) => INSTRUCTIONS_GROUP13_UNARY.get(&(imm, funct3)),
+                _ => INSTRUCTIONS_GROUP13_SUB.get(&(funct7, funct3)),
+            };
+            match instruction {
                 Some(instruction) => Ok(&instruction),
-                None => panic!("Not found instruction!",),
+                None => Err(()),
             }
         }
         _ => match INSTRUCTIONS_GROUP13.get(&funct3) {
             Some(instruction) => Ok(&instruction),
-            None => panic!("Not found instruction!"),
+            None => Err(()),
         },
     }
 }
 
-fn opecode_17(_cpu: &Cpu, _addr: u64, _word: u32) -> Result<&Instruction, ()> {
+fn opecode_17(_cpu: &Cpu, _addr: u64, _word: u32) -> Result<&'static Instruction, ()> {
     let idx = 0;
     match INSTRUCTIONS_GROUP17.get(&idx) {
         Some(instruction) => Ok(&instruction),
-        None => panic!("Not found instruction!"),
+        None => Err(()),
     }
 }
 
-fn opecode_1b(_cpu: &Cpu, _addr: u64, word: u32) -> Result<&Instruction, ()> {
+fn opecode_1b(_cpu: &Cpu, _addr: u64, word: u32) -> Result<&'static Instruction, ()> {
     let funct3 = ((word & 0x00007000) >> 12) as u8;
     match funct3 {
-        5 => {
+        1 | 5 => {
+            // the W forms take a 5-bit shift amount, but slli.uw takes a 6-bit one.
             let funct7 = ((word & 0xfe000000) >> 25) as u8;
-            match INSTRUCTIONS_GROUP1B_SUB.get(&(funct7, funct3)) {
+            let imm = ((word & 0xfff00000) >> 20) as u16;
+            let instruction = match (funct7, funct3) {
+                (0x30, 1) => INSTRUCTIONS_GROUP1B_UNARY.get(&(imm, funct3)),
+                (0x05, 1) => INSTRUCTIONS_GROUP1B_SUB.get(&(0x04, funct3)),
+                _ => INSTRUCTIONS_GROUP1B_SUB.get(&(funct7, funct3)),
+            };
+            match instruction {
                 Some(instruction) => Ok(&instruction),
-                None => panic!("Not found instruction!"),
+                None => Err(()),
             }
         }
         _ => match INSTRUCTIONS_GROUP1B.get(&funct3) {
             Some(instruction) => Ok(&instruction),
-            None => panic!("Not found instruction!"),
+            None => Err(()),
         },
     }
 }
 
-fn opecode_23(_cpu: &Cpu, _addr: u64, word: u32) -> Result<&Instruction, ()> {
+fn opecode_23(_cpu: &Cpu, _addr: u64, word: u32) -> Result<&'static Instruction, ()> {
     let funct3 = ((word & 0x00007000) >> 12) as u8;
     match INSTRUCTIONS_GROUP23.get(&funct3) {
         Some(instruction) => Ok(&instruction),
-        None => panic!("Not found instruction!"),
+        None => Err(()),
     }
 }
 
-fn opecode_27(_cpu: &Cpu, _addr: u64, word: u32) -> Result<&Instruction, ()> {
+fn opecode_27(_cpu: &Cpu, _addr: u64, word: u32) -> Result<&'static Instruction, ()> {
     let funct3 = ((word & 0x00007000) >> 12) as u8;
+    // vector stores are encoded with the width field of 0, 5, 6 and 7.
+    match funct3 {
+        0 | 5 | 6 | 7 => return opecode_vector_store(_cpu, _addr, word),
+        _ => {}
+    };
     match INSTRUCTIONS_GROUP27.get(&funct3) {
         Some(instruction) => Ok(&instruction),
-        None => panic!("Not found instruction!"),
+        None => Err(()),
     }
 }
 
-fn opecode_2f(_cpu: &Cpu, _addr: u64, word: u32) -> Result<&Instruction, ()> {
+fn opecode_2f(_cpu: &Cpu, _addr: u64, word: u32) -> Result<&'static Instruction, ()> {
     let funct3 = ((word & 0x00007000) >> 12) as u8;
     let funct7 = ((word & 0xf8000000) >> 27) as u8;
     match INSTRUCTIONS_GROUP2F.get(&(funct7, funct3)) {
         Some(instruction) => Ok(&instruction),
-        None => panic!("Not found instruction!"),
+        None => Err(()),
     }
 }
 
-fn opecode_33(_cpu: &Cpu, _addr: u64, word: u32) -> Result<&Instruction, ()> {
+fn opecode_33(cpu: &Cpu, _addr: u64, word: u32) -> Result<&'static Instruction, ()> {
     let funct3 = ((word & 0x00007000) >> 12) as u8;
     let funct7 = ((word & 0xfe000000) >> 25) as u8;
+    match (funct7, funct3) {
+        // zext.h is in OP on RV32 and in OP-32 on RV64, and its rs2 is 0.
+        (0x04, 4) if !is_zext_h(cpu, word, Xlen::X32) => return Err(()),
+        _ => {}
+    };
     match INSTRUCTIONS_GROUP33.get(&(funct7, funct3)) {
         Some(instruction) => Ok(&instruction),
-        None => panic!("Not found instruction!"),
+        None => Err(()),
     }
 }
 
-fn opecode_37(_cpu: &Cpu, _addr: u64, _word: u32) -> Result<&Instruction, ()> {
+fn opecode_37(_cpu: &Cpu, _addr: u64, _word: u32) -> Result<&'static Instruction, ()> {
     Ok(&Instruction {
         mnemonic: "lui",
         operation: lui,
@@ -821,33 +1622,83 @@ fn opecode_37(_cpu: &Cpu, _addr: u64, _word: u32) -> Result<&Instruction, ()> {
     })
 }
 
-fn opecode_3b(_cpu: &Cpu, _addr: u64, word: u32) -> Result<&Instruction, ()> {
+fn opecode_3b(cpu: &Cpu, _addr: u64, word: u32) -> Result<&'static Instruction, ()> {
     let funct3 = ((word & 0x00007000) >> 12) as u8;
     let funct7 = ((word & 0xfe000000) >> 25) as u8;
+    match (funct7, funct3) {
+        (0x04, 4) if !is_zext_h(cpu, word, Xlen::X64) => return Err(()),
+        _ => {}
+    };
     match INSTRUCTIONS_GROUP3B.get(&(funct7, funct3)) {
         Some(instruction) => Ok(&instruction),
-        None => panic!("Not found instruction!"),
+        None => Err(()),
     }
 }
 
-fn opecode_53(_cpu: &Cpu, _addr: u64, word: u32) -> Result<&Instruction, ()> {
-    let funct3 = ((word & 0x00007000) >> 12) as u8;
+/// the encoding of zext.h is the one of the XLEN with rs2 of 0. The others are pack
+/// and packw of Zbkb.
+fn is_zext_h(cpu: &Cpu, word: u32, xlen: Xlen) -> bool {
+    let rs2 = (word >> 20) & 0x1f;
+    rs2 == 0 && matches!((&cpu.xlen, xlen), (Xlen::X32, Xlen::X32) | (Xlen::X64, Xlen::X64))
+}
+
+fn opecode_43(_cpu: &Cpu, _addr: u64, word: u32) -> Result<&'static Instruction, ()> {
+    let fmt = ((word & 0x06000000) >> 25) as u8;
+    match INSTRUCTIONS_GROUP43.get(&fmt) {
+        Some(instruction) => Ok(&instruction),
+        None => Err(()),
+    }
+}
+
+fn opecode_47(_cpu: &Cpu, _addr: u64, word: u32) -> Result<&'static Instruction, ()> {
+    let fmt = ((word & 0x06000000) >> 25) as u8;
+    match INSTRUCTIONS_GROUP47.get(&fmt) {
+        Some(instruction) => Ok(&instruction),
+        None => Err(()),
+    }
+}
+
+fn opecode_4b(_cpu: &Cpu, _addr: u64, word: u32) -> Result<&'static Instruction, ()> {
+    let fmt = ((word & 0x06000000) >> 25) as u8;
+    match INSTRUCTIONS_GROUP4B.get(&fmt) {
+        Some(instruction) => Ok(&instruction),
+        None => Err(()),
+    }
+}
+
+fn opecode_4f(_cpu: &Cpu, _addr: u64, word: u32) -> Result<&'static Instruction, ()> {
+    let fmt = ((word & 0x06000000) >> 25) as u8;
+    match INSTRUCTIONS_GROUP4F.get(&fmt) {
+        Some(instruction) => Ok(&instruction),
+        None => Err(()),
+    }
+}
+
+fn opecode_53(_cpu: &Cpu, _addr: u64, word: u32) -> Result<&'static Instruction, ()> {
+    let funct3 = ((word & 0x00007000) >> 12) as u8;
     let funct7 = ((word & 0xfe000000) >> 25) as u8;
-    match INSTRUCTIONS_GROUP53.get(&(funct7, funct3)) {
+    let rs2 = ((word & 0x01f00000) >> 20) as u8;
+    // funct7 consists of funct5 and fmt.
+    let instruction = match funct7 & 0x7c {
+        0x10 | 0x14 | 0x50 | 0x70 | 0x78 => INSTRUCTIONS_GROUP53_SUB.get(&(funct7, funct3)),
+        0x20 | 0x60 | 0x68 => INSTRUCTIONS_GROUP53_CVT.get(&(funct7, rs2)),
+        _ => INSTRUCTIONS_GROUP53.get(&funct7),
+    };
+    match instruction {
         Some(instruction) => Ok(&instruction),
-        None => panic!("Not found instruction!"),
+        None => Err(()),
     }
 }
 
-fn opecode_63(_cpu: &Cpu, _addr: u64, word: u32) -> Result<&Instruction, ()> {
+fn opecode_63(_cpu: &Cpu, _addr: u64, word: u32) -> Result<&'static Instruction, ()> {
     let funct3 = ((word & 0x00007000) >> 12) as u8;
     match INSTRUCTIONS_GROUP63.get(&funct3) {
         Some(instruction) => Ok(&instruction),
-        None => panic!("Not found instruction!"),
+        None => Err(()),
     }
 }
 
-fn opecode_67(_cpu: &Cpu, _addr: u64, _word: u32) -> Result<&Instruction, ()> {
+fn opecode_67(_cpu: &Cpu, _addr: u64, _word: u32) -> Result<&'static Instruction, ()> {
     Ok(&Instruction {
         mnemonic: "jalr",
         operation: jalr,
@@ -855,7 +1706,7 @@ fn opecode_67(_cpu: &Cpu, _addr: u64, _word: u32) -> Result<&Instruction, ()> {
     })
 }
 
-fn opecode_6f(_cpu: &Cpu, _addr: u64, _word: u32) -> Result<&Instruction, ()> {
+fn opecode_6f(_cpu: &Cpu, _addr: u64, _word: u32) -> Result<&'static Instruction, ()> {
     Ok(&Instruction {
         mnemonic: "jal",
         operation: jal,
@@ -863,26 +1714,48 @@ fn opecode_6f(_cpu: &Cpu, _addr: u64, _word: u32) -> Result<&Instruction, ()> {
     })
 }
 
-fn opecode_73(_cpu: &Cpu, _addr: u64, word: u32) -> Result<&Instruction, ()> {
+fn opecode_73(_cpu: &Cpu, _addr: u64, word: u32) -> Result<&'static Instruction, ()> {
     let funct3 = ((word & 0x00007000) >> 12) as u8;
     match funct3 {
         0 => {
             let funct12 = ((word & 0xfff00000) >> 20) as u16;
-            match funct12 & 0x120 {
-                0x120 => Ok(&Instruction {
+            match funct12 >> 5 {
+                0x09 => Ok(&Instruction {
                     mnemonic: "sfence.vma",
                     operation: sfence,
                     disassemble: disassemble_mnemonic,
                 }),
+                0x11 => Ok(&Instruction {
+                    mnemonic: "hfence.vvma",
+                    operation: hfence_vvma,
+                    disassemble: disassemble_mnemonic,
+                }),
+                0x31 => Ok(&Instruction {
+                    mnemonic: "hfence.gvma",
+                    operation: hfence_gvma,
+                    disassemble: disassemble_mnemonic,
+                }),
                 _ => match INSTRUCTIONS_GROUP73_EXTEND.get(&funct12) {
                     Some(instruction) => Ok(&instruction),
-                    None => panic!("Not found instruction!"),
+                    None => Err(()),
                 },
             }
         }
+        // The virtual-machine load instructions are distinguished by rs2 field.
+        4 => {
+            let funct7 = ((word & 0xfe000000) >> 25) as u8;
+            let rs2 = match funct7 & 1 {
+                0 => ((word & 0x01f00000) >> 20) as u8,
+                _ => 0,
+            };
+            match INSTRUCTIONS_GROUP73_HYPERVISOR.get(&(funct7, rs2)) {
+                Some(instruction) => Ok(&instruction),
+                None => Err(()),
+            }
+        }
         _ => match INSTRUCTIONS_GROUP73.get(&funct3) {
             Some(instruction) => Ok(&instruction),
-            None => panic!("Not found instruction!"),
+            None => Err(()),
         },
     }
 }
@@ -949,6 +1822,15 @@ fn parse_type_r(word: u32) -> InstructionTypeR {
     }
 }
 
+fn parse_type_r4(word: u32) -> InstructionTypeR4 {
+    InstructionTypeR4 {
+        rd: ((word & 0x00000f80) >> 7) as u8,
+        rs1: ((word & 0x000f8000) >> 15) as u8,
+        rs2: ((word & 0x01f00000) >> 20) as u8,
+        rs3: ((word & 0xf8000000) >> 27) as u8,
+    }
+}
+
 fn parse_type_csr(word: u32) -> InstructionTypeCSR {
     InstructionTypeCSR {
         rd: ((word & 0x00000f80) >> 7) as u8,
@@ -973,6 +1855,96 @@ pub fn signed(cpu: &Cpu, data: i64) -> i64 {
     }
 }
 
+/// read a single-precision value from the floating-point register.
+/// Any value that is not a legal NaN-boxed value is treated as the canonical NaN.
+fn read_f32(cpu: &Cpu, reg: u8) -> u64 {
+    let data = cpu.f[reg as usize];
+    match data >> 32 {
+        0xffffffff => data & 0xffffffff,
+        _ => fpu::BINARY32.canonical_nan(),
+    }
+}
+
+/// write a single-precision value to the floating-point register with NaN-boxing,
+/// i.e. the upper 32 bits of the register are all set to 1.
+fn write_f32(cpu: &mut Cpu, reg: u8, data: u64) {
+    cpu.f[reg as usize] = 0xffffffff_00000000 | (data & 0xffffffff);
+    cpu.csr.set_status_dirty(CSR_STATUS_FS);
+}
+
+/// write a double-precision value to the floating-point register.
+fn write_f64(cpu: &mut Cpu, reg: u8, data: u64) {
+    cpu.f[reg as usize] = data;
+    cpu.csr.set_status_dirty(CSR_STATUS_FS);
+}
+
+/// read a double-precision value from the floating-point register.
+fn read_f64(cpu: &Cpu, reg: u8) -> u64 {
+    cpu.f[reg as usize]
+}
+
+/// read a value of the floating-point format from the floating-point register.
+fn read_fp(cpu: &Cpu, fmt: &FloatFormat, reg: u8) -> u64 {
+    match fmt.width() {
+        32 => read_f32(cpu, reg),
+        _ => read_f64(cpu, reg),
+    }
+}
+
+/// write a value of the floating-point format to the floating-point register.
+fn write_fp(cpu: &mut Cpu, fmt: &FloatFormat, reg: u8, data: u64) {
+    match fmt.width() {
+        32 => write_f32(cpu, reg, data),
+        _ => write_f64(cpu, reg, data),
+    }
+}
+
+/// floating-point instructions are illegal while mstatus.FS (or vsstatus.FS when V=1)
+/// is Off.
+fn check_fp_enabled(cpu: &mut Cpu, word: u32) -> Result<(), Trap> {
+    match cpu.csr.is_status_enabled(CSR_STATUS_FS) {
+        false => Err(Trap {
+            exception: Exception::IllegalInstruction,
+            value: word as u64,
+        }),
+        true => Ok(()),
+    }
+}
+
+/// get the rounding mode from the rm field of instruction. 7 selects the dynamic
+/// rounding mode held in frm, and reserved rounding modes are illegal.
+fn get_rounding_mode(cpu: &mut Cpu, word: u32) -> Result<RoundingMode, Trap> {
+    let rm = match ((word & 0x00007000) >> 12) as u64 {
+        7 => cpu.csr.read_direct(CSR_FRM),
+        rm => rm,
+    };
+    match RoundingMode::from_bits(rm) {
+        Some(rm) => Ok(rm),
+        None => Err(Trap {
+            exception: Exception::IllegalInstruction,
+            value: word as u64,
+        }),
+    }
+}
+
+/// accrue the exception flags to fflags.
+fn accrue_fflags(cpu: &mut Cpu, flags: u64) {
+    if flags != 0 {
+        cpu.csr.read_modify_write_direct(CSR_FFLAGS, flags, 0);
+    }
+}
+
+/// RV64 only instructions are illegal in RV32.
+fn check_rv64(cpu: &Cpu, word: u32) -> Result<(), Trap> {
+    match cpu.xlen {
+        Xlen::X64 => Ok(()),
+        Xlen::X32 => Err(Trap {
+            exception: Exception::IllegalInstruction,
+            value: word as u64,
+        }),
+    }
+}
+
 //==============================================================================
 // DisAssembling functions for debug.
 //==============================================================================
@@ -1069,6 +2041,17 @@ fn disassemble_r(_cpu: &Cpu, mnemonic: &str, word: u32) -> String {
     s
 }
 
+fn disassemble_r2(_cpu: &Cpu, mnemonic: &str, word: u32) -> String {
+    let o = parse_type_r(word);
+    let mut s = String::new();
+    s += &format!("{0: <10} ", mnemonic);
+    s += &format!("{:}", REGISTERS.get(&o.rd).unwrap());
+    s += &format!(":{:x}", _cpu.x[o.rd as usize]);
+    s += &format!(",{:}", REGISTERS.get(&o.rs1).unwrap());
+    s += &format!(":{:x}", _cpu.x[o.rs1 as usize]);
+    s
+}
+
 fn disassemble_j(_cpu: &Cpu, mnemonic: &str, word: u32) -> String {
     let o = parse_type_j(word);
     let mut s = String::new();
@@ -1091,6 +2074,104 @@ fn disassemble_b(_cpu: &Cpu, mnemonic: &str, word: u32) -> String {
     s
 }
 
+fn disassemble_fp_load(_cpu: &Cpu, mnemonic: &str, word: u32) -> String {
+    let o = parse_type_i(word);
+    let mut s = String::new();
+    s += &format!("{0: <10} ", mnemonic);
+    s += &format!("{:}", FP_REGISTERS.get(&o.rd).unwrap());
+    s += &format!(":{:x}", _cpu.f[o.rd as usize]);
+    s += &format!(",{:x}", o.imm);
+    s += &format!("({:}", REGISTERS.get(&o.rs1).unwrap());
+    s += &format!(":{:x})", _cpu.x[o.rs1 as usize]);
+    s
+}
+
+fn disassemble_precision_store(_cpu: &Cpu, mnemonic: &str, word: u32) -> String {
+    let o = parse_type_s(word);
+    let mut s = String::new();
+    s += &format!("{0: <10} ", mnemonic);
+    s += &format!("{:}", FP_REGISTERS.get(&o.rs2).unwrap());
+    s += &format!(":{:x}", _cpu.f[o.rs2 as usize]);
+    s += &format!(",{:x}", o.imm);
+    s += &format!("({:}", REGISTERS.get(&o.rs1).unwrap());
+    s += &format!(":{:x})", _cpu.x[o.rs1 as usize]);
+    s
+}
+
+fn disassemble_fp_r(_cpu: &Cpu, mnemonic: &str, word: u32) -> String {
+    let o = parse_type_r(word);
+    let mut s = String::new();
+    s += &format!("{0: <10} ", mnemonic);
+    s += &format!("{:}", FP_REGISTERS.get(&o.rd).unwrap());
+    s += &format!(":{:x}", _cpu.f[o.rd as usize]);
+    s += &format!(",{:}", FP_REGISTERS.get(&o.rs1).unwrap());
+    s += &format!(":{:x}", _cpu.f[o.rs1 as usize]);
+    s += &format!(",{:}", FP_REGISTERS.get(&o.rs2).unwrap());
+    s += &format!(":{:x}", _cpu.f[o.rs2 as usize]);
+    s
+}
+
+fn disassemble_fp_r2(_cpu: &Cpu, mnemonic: &str, word: u32) -> String {
+    let o = parse_type_r(word);
+    let mut s = String::new();
+    s += &format!("{0: <10} ", mnemonic);
+    s += &format!("{:}", FP_REGISTERS.get(&o.rd).unwrap());
+    s += &format!(":{:x}", _cpu.f[o.rd as usize]);
+    s += &format!(",{:}", FP_REGISTERS.get(&o.rs1).unwrap());
+    s += &format!(":{:x}", _cpu.f[o.rs1 as usize]);
+    s
+}
+
+fn disassemble_fp_r4(_cpu: &Cpu, mnemonic: &str, word: u32) -> String {
+    let o = parse_type_r4(word);
+    let mut s = String::new();
+    s += &format!("{0: <10} ", mnemonic);
+    s += &format!("{:}", FP_REGISTERS.get(&o.rd).unwrap());
+    s += &format!(":{:x}", _cpu.f[o.rd as usize]);
+    s += &format!(",{:}", FP_REGISTERS.get(&o.rs1).unwrap());
+    s += &format!(":{:x}", _cpu.f[o.rs1 as usize]);
+    s += &format!(",{:}", FP_REGISTERS.get(&o.rs2).unwrap());
+    s += &format!(":{:x}", _cpu.f[o.rs2 as usize]);
+    s += &format!(",{:}", FP_REGISTERS.get(&o.rs3).unwrap());
+    s += &format!(":{:x}", _cpu.f[o.rs3 as usize]);
+    s
+}
+
+fn disassemble_fp_cmp(_cpu: &Cpu, mnemonic: &str, word: u32) -> String {
+    let o = parse_type_r(word);
+    let mut s = String::new();
+    s += &format!("{0: <10} ", mnemonic);
+    s += &format!("{:}", REGISTERS.get(&o.rd).unwrap());
+    s += &format!(":{:x}", _cpu.x[o.rd as usize]);
+    s += &format!(",{:}", FP_REGISTERS.get(&o.rs1).unwrap());
+    s += &format!(":{:x}", _cpu.f[o.rs1 as usize]);
+    s += &format!(",{:}", FP_REGISTERS.get(&o.rs2).unwrap());
+    s += &format!(":{:x}", _cpu.f[o.rs2 as usize]);
+    s
+}
+
+fn disassemble_fp_to_x(_cpu: &Cpu, mnemonic: &str, word: u32) -> String {
+    let o = parse_type_r(word);
+    let mut s = String::new();
+    s += &format!("{0: <10} ", mnemonic);
+    s += &format!("{:}", REGISTERS.get(&o.rd).unwrap());
+    s += &format!(":{:x}", _cpu.x[o.rd as usize]);
+    s += &format!(",{:}", FP_REGISTERS.get(&o.rs1).unwrap());
+    s += &format!(":{:x}", _cpu.f[o.rs1 as usize]);
+    s
+}
+
+fn disassemble_x_to_fp(_cpu: &Cpu, mnemonic: &str, word: u32) -> String {
+    let o = parse_type_r(word);
+    let mut s = String::new();
+    s += &format!("{0: <10} ", mnemonic);
+    s += &format!("{:}", FP_REGISTERS.get(&o.rd).unwrap());
+    s += &format!(":{:x}", _cpu.f[o.rd as usize]);
+    s += &format!(",{:}", REGISTERS.get(&o.rs1).unwrap());
+    s += &format!(":{:x}", _cpu.x[o.rs1 as usize]);
+    s
+}
+
 //==============================================================================
 // Load Instructions (RV32I/RV64I)
 //==============================================================================
@@ -1101,107 +2182,110 @@ fn disassemble_b(_cpu: &Cpu, mnemonic: &str, word: u32) -> String {
 // rs2 to memory.
 
 /// lb rd,offset(rs1)
-fn lb(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
-    let o = parse_type_i(word);
-    let data = match cpu
-        .mmu
-        .read8(cpu.x[o.rs1 as usize].wrapping_add(o.imm) as u64)
-    {
+fn lb(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    lb_operands(cpu, addr, &Operands::new(word))
+}
+
+fn lb_operands(cpu: &mut Cpu, _addr: u64, o: &Operands) -> Result<(), Trap> {
+    let data = match cpu.mmu.read8(cpu.x[o.rs1].wrapping_add(o.imm) as u64) {
         Ok(d) => d as i8 as i64,
         Err(e) => return Err(e),
     };
-    cpu.x[o.rd as usize] = data;
+    cpu.x[o.rd] = data;
     Ok(())
 }
 
 /// lh rd,offset(rs1)
-fn lh(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
-    let o = parse_type_i(word);
-    let data = match cpu
-        .mmu
-        .read16(cpu.x[o.rs1 as usize].wrapping_add(o.imm) as u64)
-    {
+fn lh(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    lh_operands(cpu, addr, &Operands::new(word))
+}
+
+fn lh_operands(cpu: &mut Cpu, _addr: u64, o: &Operands) -> Result<(), Trap> {
+    let data = match cpu.mmu.read16(cpu.x[o.rs1].wrapping_add(o.imm) as u64) {
         Ok(d) => d as i16 as i64,
         Err(e) => return Err(e),
     };
-    cpu.x[o.rd as usize] = data;
+    cpu.x[o.rd] = data;
     Ok(())
 }
 
 /// lw rd,offset(rs1)
-fn lw(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
-    let o = parse_type_i(word);
-    let data = match cpu
-        .mmu
-        .read32(cpu.x[o.rs1 as usize].wrapping_add(o.imm) as u64)
-    {
+fn lw(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    lw_operands(cpu, addr, &Operands::new(word))
+}
+
+fn lw_operands(cpu: &mut Cpu, _addr: u64, o: &Operands) -> Result<(), Trap> {
+    let data = match cpu.mmu.read32(cpu.x[o.rs1].wrapping_add(o.imm) as u64) {
         Ok(d) => d as i32 as i64,
         Err(e) => return Err(e),
     };
-    cpu.x[o.rd as usize] = data;
+    cpu.x[o.rd] = data;
     Ok(())
 }
 
 /// ld rd,offset(rs1)
-fn ld(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
-    let o = parse_type_i(word);
-    let data = match cpu
-        .mmu
-        .read64(cpu.x[o.rs1 as usize].wrapping_add(o.imm) as u64)
-    {
+fn ld(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    ld_operands(cpu, addr, &Operands::new(word))
+}
+
+fn ld_operands(cpu: &mut Cpu, _addr: u64, o: &Operands) -> Result<(), Trap> {
+    let data = match cpu.mmu.read64(cpu.x[o.rs1].wrapping_add(o.imm) as u64) {
         Ok(d) => d as i64,
         Err(e) => return Err(e),
     };
-    cpu.x[o.rd as usize] = data;
+    cpu.x[o.rd] = data;
     Ok(())
 }
 
 /// lbu rd,offset(rs1)
-fn lbu(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
-    let o = parse_type_i(word);
-    let data = match cpu
-        .mmu
-        .read8(cpu.x[o.rs1 as usize].wrapping_add(o.imm) as u64)
-    {
+fn lbu(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    lbu_operands(cpu, addr, &Operands::new(word))
+}
+
+fn lbu_operands(cpu: &mut Cpu, _addr: u64, o: &Operands) -> Result<(), Trap> {
+    let data = match cpu.mmu.read8(cpu.x[o.rs1].wrapping_add(o.imm) as u64) {
         Ok(d) => d as i64,
         Err(e) => return Err(e),
     };
-    cpu.x[o.rd as usize] = data;
+    cpu.x[o.rd] = data;
     Ok(())
 }
 
 /// lhu rd,offset(rs1)
-fn lhu(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
-    let o = parse_type_i(word);
-    let data = match cpu
-        .mmu
-        .read16(cpu.x[o.rs1 as usize].wrapping_add(o.imm) as u64)
-    {
+fn lhu(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    lhu_operands(cpu, addr, &Operands::new(word))
+}
+
+fn lhu_operands(cpu: &mut Cpu, _addr: u64, o: &Operands) -> Result<(), Trap> {
+    let data = match cpu.mmu.read16(cpu.x[o.rs1].wrapping_add(o.imm) as u64) {
         Ok(d) => d as i64,
         Err(e) => return Err(e),
     };
-    cpu.x[o.rd as usize] = data;
+    cpu.x[o.rd] = data;
     Ok(())
 }
 
 /// lwu rd,offset(rs1)
-fn lwu(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
-    let o = parse_type_i(word);
-    let data = match cpu
-        .mmu
-        .read32(cpu.x[o.rs1 as usize].wrapping_add(o.imm) as u64)
-    {
+fn lwu(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    lwu_operands(cpu, addr, &Operands::new(word))
+}
+
+fn lwu_operands(cpu: &mut Cpu, _addr: u64, o: &Operands) -> Result<(), Trap> {
+    let data = match cpu.mmu.read32(cpu.x[o.rs1].wrapping_add(o.imm) as u64) {
         Ok(d) => d as i64,
         Err(e) => return Err(e),
     };
-    cpu.x[o.rd as usize] = data;
+    cpu.x[o.rd] = data;
     Ok(())
 }
 
 /// [lui rd,imm]
-fn lui(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
-    let o = parse_type_u(word);
-    cpu.x[o.rd as usize] = o.imm as i64;
+fn lui(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    lui_operands(cpu, addr, &Operands::new(word))
+}
+
+fn lui_operands(cpu: &mut Cpu, _addr: u64, o: &Operands) -> Result<(), Trap> {
+    cpu.x[o.rd] = o.imm;
     Ok(())
 }
 
@@ -1212,34 +2296,46 @@ fn lui(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
 // from the low bits of register rs2 to memory.
 
 /// [sb rs2,offset(rs1)]
-fn sb(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
-    let o = parse_type_s(word);
-    let addr = cpu.x[o.rs1 as usize].wrapping_add(o.imm) as u64;
-    let data = cpu.x[o.rs2 as usize] as u8;
+fn sb(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    sb_operands(cpu, addr, &Operands::new(word))
+}
+
+fn sb_operands(cpu: &mut Cpu, _addr: u64, o: &Operands) -> Result<(), Trap> {
+    let addr = cpu.x[o.rs1].wrapping_add(o.imm) as u64;
+    let data = cpu.x[o.rs2] as u8;
     cpu.mmu.write8(addr, data)
 }
 
 /// [sh rs2,offset(rs1)]
-fn sh(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
-    let o = parse_type_s(word);
-    let addr = cpu.x[o.rs1 as usize].wrapping_add(o.imm) as u64;
-    let data = cpu.x[o.rs2 as usize] as u16;
+fn sh(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    sh_operands(cpu, addr, &Operands::new(word))
+}
+
+fn sh_operands(cpu: &mut Cpu, _addr: u64, o: &Operands) -> Result<(), Trap> {
+    let addr = cpu.x[o.rs1].wrapping_add(o.imm) as u64;
+    let data = cpu.x[o.rs2] as u16;
     cpu.mmu.write16(addr, data)
 }
 
 /// [sw rs2,offset(rs1)]
-fn sw(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
-    let o = parse_type_s(word);
-    let addr = cpu.x[o.rs1 as usize].wrapping_add(o.imm) as u64;
-    let data = cpu.x[o.rs2 as usize] as u32;
+fn sw(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    sw_operands(cpu, addr, &Operands::new(word))
+}
+
+fn sw_operands(cpu: &mut Cpu, _addr: u64, o: &Operands) -> Result<(), Trap> {
+    let addr = cpu.x[o.rs1].wrapping_add(o.imm) as u64;
+    let data = cpu.x[o.rs2] as u32;
     cpu.mmu.write32(addr, data)
 }
 
 /// [sd rs2,offset(rs1)]
-fn sd(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
-    let o = parse_type_s(word);
-    let addr = cpu.x[o.rs1 as usize].wrapping_add(o.imm) as u64;
-    let data = cpu.x[o.rs2 as usize] as u64;
+fn sd(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    sd_operands(cpu, addr, &Operands::new(word))
+}
+
+fn sd_operands(cpu: &mut Cpu, _addr: u64, o: &Operands) -> Result<(), Trap> {
+    let addr = cpu.x[o.rs1].wrapping_add(o.imm) as u64;
+    let data = cpu.x[o.rs2] as u64;
     cpu.mmu.write64(addr, data)
 }
 
@@ -1253,15 +2349,19 @@ fn sd(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
 /// The FLW instruction loads a single-precision floating-point value
 /// from memory into floating-point register rd.
 fn flw(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    match check_fp_enabled(cpu, word) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
     let o = parse_type_i(word);
     let data = match cpu
         .mmu
         .read32(cpu.x[o.rs1 as usize].wrapping_add(o.imm) as u64)
     {
-        Ok(d) => f64::from_bits(d as i32 as i64 as u64),
+        Ok(d) => d as u64,
         Err(e) => return Err(e),
     };
-    cpu.f[o.rd as usize] = data;
+    write_f32(cpu, o.rd, data);
     Ok(())
 }
 
@@ -1269,31 +2369,43 @@ fn flw(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
 /// The FLD instruction loads a double-precision floating-point value
 /// from memory into floating-point register rd.
 fn fld(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    match check_fp_enabled(cpu, word) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
     let o = parse_type_i(word);
     let data = match cpu
         .mmu
         .read64(cpu.x[o.rs1 as usize].wrapping_add(o.imm) as u64)
     {
-        Ok(d) => f64::from_bits(d),
+        Ok(d) => d,
         Err(e) => return Err(e),
     };
-    cpu.f[o.rd as usize] = data;
+    write_f64(cpu, o.rd, data);
     Ok(())
 }
 
 /// [fsw rs2,offset(rs1)]
+/// FSW stores the lower 32 bits of the floating-point register without checking NaN-boxing.
 fn fsw(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    match check_fp_enabled(cpu, word) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
     let o = parse_type_s(word);
     let addr = cpu.x[o.rs1 as usize].wrapping_add(o.imm) as u64;
-    cpu.mmu
-        .write32(addr, cpu.f[o.rs2 as usize].to_bits() as u32)
+    cpu.mmu.write32(addr, cpu.f[o.rs2 as usize] as u32)
 }
 
 /// [fsd rs2,offset(rs1)]
 fn fsd(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    match check_fp_enabled(cpu, word) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
     let o = parse_type_s(word);
     let addr = cpu.x[o.rs1 as usize].wrapping_add(o.imm) as u64;
-    cpu.mmu.write64(addr, cpu.f[o.rs2 as usize].to_bits())
+    cpu.mmu.write64(addr, cpu.f[o.rs2 as usize])
 }
 
 //==============================================================================
@@ -1307,6 +2419,14 @@ fn fence(_cpu: &mut Cpu, _addr: u64, _word: u32) -> Result<(), Trap> {
     Ok(())
 }
 
+/// [fence.i]
+/// The decoded instruction cache and the block cache already follow the stores into
+/// the cached pages, and are flushed here as well.
+fn fence_i(cpu: &mut Cpu, _addr: u64, _word: u32) -> Result<(), Trap> {
+    cpu.flush_instruction_caches();
+    Ok(())
+}
+
 //==============================================================================
 // Integer Register-Immediate Instructions (RV32I/RV64I)
 //==============================================================================
@@ -1314,27 +2434,36 @@ fn fence(_cpu: &mut Cpu, _addr: u64, _word: u32) -> Result<(), Trap> {
 /// ADDI adds the sign-extended 12-bit immediate to register rs1. Arithmetic overfl ow is ignored and
 /// the result is simply the low XLEN bits of the result. ADDI rd, rs1, 0 is used to implement the MV
 /// rd, rs1 assembler pseudoinstruction.
-fn addi(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
-    let o = parse_type_i(word);
-    cpu.x[o.rd as usize] = signed(cpu, cpu.x[o.rs1 as usize].wrapping_add(o.imm));
+fn addi(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    addi_operands(cpu, addr, &Operands::new(word))
+}
+
+fn addi_operands(cpu: &mut Cpu, _addr: u64, o: &Operands) -> Result<(), Trap> {
+    cpu.x[o.rd] = signed(cpu, cpu.x[o.rs1].wrapping_add(o.imm));
     Ok(())
 }
 
 /// [slli rd,rs1,shamt]
-fn slli(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
-    let o = parse_type_i(word);
+fn slli(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    slli_operands(cpu, addr, &Operands::new(word))
+}
+
+fn slli_operands(cpu: &mut Cpu, _addr: u64, o: &Operands) -> Result<(), Trap> {
     let shamt = match cpu.xlen {
-        Xlen::X64 => (word >> 20) & 0x3f,
-        Xlen::X32 => (word >> 20) & 0x1f,
+        Xlen::X64 => o.imm & 0x3f,
+        Xlen::X32 => o.imm & 0x1f,
     };
-    cpu.x[o.rd as usize] = signed(cpu, cpu.x[o.rs1 as usize] << shamt);
+    cpu.x[o.rd] = signed(cpu, cpu.x[o.rs1] << shamt);
     Ok(())
 }
 
 /// [slti rd,rs1,imm]
-fn slti(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
-    let o = parse_type_i(word);
-    cpu.x[o.rd as usize] = match cpu.x[o.rs1 as usize] < o.imm {
+fn slti(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    slti_operands(cpu, addr, &Operands::new(word))
+}
+
+fn slti_operands(cpu: &mut Cpu, _addr: u64, o: &Operands) -> Result<(), Trap> {
+    cpu.x[o.rd] = match cpu.x[o.rs1] < o.imm {
         true => 1,
         false => 0,
     };
@@ -1342,9 +2471,12 @@ fn slti(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
 }
 
 /// [sltiu rd,rs1,imm]
-fn sltiu(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
-    let o = parse_type_i(word);
-    cpu.x[o.rd as usize] = match unsigned(cpu, cpu.x[o.rs1 as usize]) < unsigned(cpu, o.imm) {
+fn sltiu(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    sltiu_operands(cpu, addr, &Operands::new(word))
+}
+
+fn sltiu_operands(cpu: &mut Cpu, _addr: u64, o: &Operands) -> Result<(), Trap> {
+    cpu.x[o.rd] = match unsigned(cpu, cpu.x[o.rs1]) < unsigned(cpu, o.imm) {
         true => 1,
         false => 0,
     };
@@ -1352,45 +2484,60 @@ fn sltiu(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
 }
 
 /// [xori rd,rs1,imm]
-fn xori(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
-    let o = parse_type_i(word);
-    cpu.x[o.rd as usize] = cpu.x[o.rs1 as usize] ^ o.imm;
+fn xori(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    xori_operands(cpu, addr, &Operands::new(word))
+}
+
+fn xori_operands(cpu: &mut Cpu, _addr: u64, o: &Operands) -> Result<(), Trap> {
+    cpu.x[o.rd] = cpu.x[o.rs1] ^ o.imm;
     Ok(())
 }
 
 /// [srli rd,rs1,shamt]
-fn srli(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
-    let o = parse_type_i(word);
+fn srli(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    srli_operands(cpu, addr, &Operands::new(word))
+}
+
+fn srli_operands(cpu: &mut Cpu, _addr: u64, o: &Operands) -> Result<(), Trap> {
     let shamt = match cpu.xlen {
-        Xlen::X64 => (word >> 20) & 0x3f,
-        Xlen::X32 => (word >> 20) & 0x1f,
+        Xlen::X64 => o.imm & 0x3f,
+        Xlen::X32 => o.imm & 0x1f,
     };
-    cpu.x[o.rd as usize] = signed(cpu, (unsigned(cpu, cpu.x[o.rs1 as usize]) >> shamt) as i64);
+    cpu.x[o.rd] = signed(cpu, (unsigned(cpu, cpu.x[o.rs1]) >> shamt) as i64);
     Ok(())
 }
 
 /// [srai rd,rs1,shamt]
-fn srai(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
-    let o = parse_type_i(word);
+fn srai(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    srai_operands(cpu, addr, &Operands::new(word))
+}
+
+fn srai_operands(cpu: &mut Cpu, _addr: u64, o: &Operands) -> Result<(), Trap> {
     let shamt = match cpu.xlen {
-        Xlen::X64 => (word >> 20) & 0x3f,
-        Xlen::X32 => (word >> 20) & 0x1f,
+        Xlen::X64 => o.imm & 0x3f,
+        Xlen::X32 => o.imm & 0x1f,
     };
-    cpu.x[o.rd as usize] = signed(cpu, (cpu.x[o.rs1 as usize] >> shamt) as i64);
+    cpu.x[o.rd] = signed(cpu, cpu.x[o.rs1] >> shamt);
     Ok(())
 }
 
 /// [ori rd,rs1,imm]
-fn ori(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
-    let o = parse_type_i(word);
-    cpu.x[o.rd as usize] = cpu.x[o.rs1 as usize] | o.imm;
+fn ori(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    ori_operands(cpu, addr, &Operands::new(word))
+}
+
+fn ori_operands(cpu: &mut Cpu, _addr: u64, o: &Operands) -> Result<(), Trap> {
+    cpu.x[o.rd] = cpu.x[o.rs1] | o.imm;
     Ok(())
 }
 
 /// [andi rd,rs1,imm]
-fn andi(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
-    let o = parse_type_i(word);
-    cpu.x[o.rd as usize] = cpu.x[o.rs1 as usize] & o.imm;
+fn andi(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    andi_operands(cpu, addr, &Operands::new(word))
+}
+
+fn andi_operands(cpu: &mut Cpu, _addr: u64, o: &Operands) -> Result<(), Trap> {
+    cpu.x[o.rd] = cpu.x[o.rs1] & o.imm;
     Ok(())
 }
 
@@ -1398,48 +2545,57 @@ fn andi(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
 /// AUIPC (add upper immediate to pc) is used to build pc-relative
 /// addresses and uses the U-type format.
 fn auipc(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
-    let o = parse_type_u(word);
-    cpu.x[o.rd as usize] = signed(cpu, addr.wrapping_add(o.imm) as i64);
+    auipc_operands(cpu, addr, &Operands::new(word))
+}
+
+fn auipc_operands(cpu: &mut Cpu, addr: u64, o: &Operands) -> Result<(), Trap> {
+    cpu.x[o.rd] = signed(cpu, addr.wrapping_add(o.imm as u64) as i64);
     Ok(())
 }
 
 /// [add rd,rs1,rs2]
-fn add(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
-    let o = parse_type_r(word);
-    cpu.x[o.rd as usize] = signed(
-        cpu,
-        cpu.x[o.rs1 as usize].wrapping_add(cpu.x[o.rs2 as usize]),
-    );
+fn add(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    add_operands(cpu, addr, &Operands::new(word))
+}
+
+fn add_operands(cpu: &mut Cpu, _addr: u64, o: &Operands) -> Result<(), Trap> {
+    cpu.x[o.rd] = signed(cpu, cpu.x[o.rs1].wrapping_add(cpu.x[o.rs2]));
     Ok(())
 }
 
 /// [sub rd,rs1,rs2]
-fn sub(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
-    let o = parse_type_r(word);
-    cpu.x[o.rd as usize] = signed(
-        cpu,
-        cpu.x[o.rs1 as usize].wrapping_sub(cpu.x[o.rs2 as usize]),
-    );
+fn sub(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    sub_operands(cpu, addr, &Operands::new(word))
+}
+
+fn sub_operands(cpu: &mut Cpu, _addr: u64, o: &Operands) -> Result<(), Trap> {
+    cpu.x[o.rd] = signed(cpu, cpu.x[o.rs1].wrapping_sub(cpu.x[o.rs2]));
     Ok(())
 }
 
 /// [sll rd,rs1,rs2]
 /// SLL, SRL, and SRA perform logical left, logical right, and arithmetic right shifts on the value in
 /// register rs1 by the shift amount held in the lower 5 bits of register rs2.
-fn sll(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
-    let o = parse_type_r(word);
+fn sll(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    sll_operands(cpu, addr, &Operands::new(word))
+}
+
+fn sll_operands(cpu: &mut Cpu, _addr: u64, o: &Operands) -> Result<(), Trap> {
     let shamt = match cpu.xlen {
-        Xlen::X64 => cpu.x[o.rs2 as usize] & 0x3f,
-        Xlen::X32 => cpu.x[o.rs2 as usize] & 0x1f,
+        Xlen::X64 => cpu.x[o.rs2] & 0x3f,
+        Xlen::X32 => cpu.x[o.rs2] & 0x1f,
     };
-    cpu.x[o.rd as usize] = signed(cpu, cpu.x[o.rs1 as usize] << shamt);
+    cpu.x[o.rd] = signed(cpu, cpu.x[o.rs1] << shamt);
     Ok(())
 }
 
 /// [slt rd,rs1,rs2]
-fn slt(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
-    let o = parse_type_r(word);
-    cpu.x[o.rd as usize] = match cpu.x[o.rs1 as usize] < cpu.x[o.rs2 as usize] {
+fn slt(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    slt_operands(cpu, addr, &Operands::new(word))
+}
+
+fn slt_operands(cpu: &mut Cpu, _addr: u64, o: &Operands) -> Result<(), Trap> {
+    cpu.x[o.rd] = match cpu.x[o.rs1] < cpu.x[o.rs2] {
         true => 1,
         false => 0,
     };
@@ -1447,66 +2603,83 @@ fn slt(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
 }
 
 /// [sltu rd,rs1,rs2]
-fn sltu(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
-    let o = parse_type_r(word);
-    cpu.x[o.rd as usize] =
-        match unsigned(cpu, cpu.x[o.rs1 as usize]) < unsigned(cpu, cpu.x[o.rs2 as usize]) {
-            true => 1,
-            false => 0,
-        };
+fn sltu(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    sltu_operands(cpu, addr, &Operands::new(word))
+}
+
+fn sltu_operands(cpu: &mut Cpu, _addr: u64, o: &Operands) -> Result<(), Trap> {
+    cpu.x[o.rd] = match unsigned(cpu, cpu.x[o.rs1]) < unsigned(cpu, cpu.x[o.rs2]) {
+        true => 1,
+        false => 0,
+    };
     Ok(())
 }
 
 /// [xor rd,rs1,rs2]
-fn xor(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
-    let o = parse_type_r(word);
-    cpu.x[o.rd as usize] = cpu.x[o.rs1 as usize] ^ cpu.x[o.rs2 as usize];
+fn xor(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    xor_operands(cpu, addr, &Operands::new(word))
+}
+
+fn xor_operands(cpu: &mut Cpu, _addr: u64, o: &Operands) -> Result<(), Trap> {
+    cpu.x[o.rd] = cpu.x[o.rs1] ^ cpu.x[o.rs2];
     Ok(())
 }
 
 /// [srl rd,rs1,rs2]
-fn srl(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
-    let o = parse_type_r(word);
+fn srl(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    srl_operands(cpu, addr, &Operands::new(word))
+}
+
+fn srl_operands(cpu: &mut Cpu, _addr: u64, o: &Operands) -> Result<(), Trap> {
     let shamt = match cpu.xlen {
-        Xlen::X64 => cpu.x[o.rs2 as usize] & 0x3f,
-        Xlen::X32 => cpu.x[o.rs2 as usize] & 0x1f,
+        Xlen::X64 => cpu.x[o.rs2] & 0x3f,
+        Xlen::X32 => cpu.x[o.rs2] & 0x1f,
     };
-    cpu.x[o.rd as usize] = signed(cpu, (unsigned(cpu, cpu.x[o.rs1 as usize]) >> shamt) as i64);
+    cpu.x[o.rd] = signed(cpu, (unsigned(cpu, cpu.x[o.rs1]) >> shamt) as i64);
     Ok(())
 }
 
 /// [sra rd,rs1,rs2]
-fn sra(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
-    let o = parse_type_r(word);
+fn sra(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    sra_operands(cpu, addr, &Operands::new(word))
+}
+
+fn sra_operands(cpu: &mut Cpu, _addr: u64, o: &Operands) -> Result<(), Trap> {
     let shamt = match cpu.xlen {
-        Xlen::X64 => cpu.x[o.rs2 as usize] & 0x3f,
-        Xlen::X32 => cpu.x[o.rs2 as usize] & 0x1f,
+        Xlen::X64 => cpu.x[o.rs2] & 0x3f,
+        Xlen::X32 => cpu.x[o.rs2] & 0x1f,
     };
-    cpu.x[o.rd as usize] = signed(cpu, (signed(cpu, cpu.x[o.rs1 as usize]) >> shamt) as i64);
+    cpu.x[o.rd] = signed(cpu, signed(cpu, cpu.x[o.rs1]) >> shamt);
     Ok(())
 }
 
 /// [or rd,rs1,rs2]
-fn or(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
-    let o = parse_type_r(word);
-    cpu.x[o.rd as usize] = cpu.x[o.rs1 as usize] | cpu.x[o.rs2 as usize];
+fn or(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    or_operands(cpu, addr, &Operands::new(word))
+}
+
+fn or_operands(cpu: &mut Cpu, _addr: u64, o: &Operands) -> Result<(), Trap> {
+    cpu.x[o.rd] = cpu.x[o.rs1] | cpu.x[o.rs2];
     Ok(())
 }
 
 /// [and rd,rs1,rs2]
-fn and(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
-    let o = parse_type_r(word);
-    cpu.x[o.rd as usize] = cpu.x[o.rs1 as usize] & cpu.x[o.rs2 as usize];
+fn and(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    and_operands(cpu, addr, &Operands::new(word))
+}
+
+fn and_operands(cpu: &mut Cpu, _addr: u64, o: &Operands) -> Result<(), Trap> {
+    cpu.x[o.rd] = cpu.x[o.rs1] & cpu.x[o.rs2];
     Ok(())
 }
 
 /// [addw rd,rs1,rs2]
-fn addw(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
-    let o = parse_type_r(word);
-    cpu.x[o.rd as usize] = signed(
-        cpu,
-        cpu.x[o.rs1 as usize].wrapping_add(cpu.x[o.rs2 as usize]),
-    ) as i32 as i64;
+fn addw(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    addw_operands(cpu, addr, &Operands::new(word))
+}
+
+fn addw_operands(cpu: &mut Cpu, _addr: u64, o: &Operands) -> Result<(), Trap> {
+    cpu.x[o.rd] = signed(cpu, cpu.x[o.rs1].wrapping_add(cpu.x[o.rs2])) as i32 as i64;
     Ok(())
 }
 
@@ -1556,9 +2729,12 @@ fn sraw(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
 /// to register rs1 and produces the proper sign-extension of a 32-bit result
 /// in rd. Overflows are ignored and the result is the low 32 bits of the result
 /// sign-extended to 64 bits
-fn addiw(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
-    let o = parse_type_i(word);
-    cpu.x[o.rd as usize] = cpu.x[o.rs1 as usize].wrapping_add(o.imm) as i32 as i64;
+fn addiw(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    addiw_operands(cpu, addr, &Operands::new(word))
+}
+
+fn addiw_operands(cpu: &mut Cpu, _addr: u64, o: &Operands) -> Result<(), Trap> {
+    cpu.x[o.rd] = cpu.x[o.rs1].wrapping_add(o.imm) as i32 as i64;
     Ok(())
 }
 
@@ -1597,27 +2773,36 @@ fn sraiw(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
 /// The standard software calling convention uses x1 as the return address register and
 /// x5 as an alternate link register.
 fn jal(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
-    let o = parse_type_j(word);
-    cpu.x[o.rd as usize] = signed(cpu, cpu.pc as i64);
-    cpu.pc = addr.wrapping_add(o.imm);
+    jal_operands(cpu, addr, &Operands::new(word))
+}
+
+fn jal_operands(cpu: &mut Cpu, addr: u64, o: &Operands) -> Result<(), Trap> {
+    cpu.x[o.rd] = signed(cpu, cpu.pc as i64);
+    cpu.pc = addr.wrapping_add(o.imm as u64);
     Ok(())
 }
 
 /// [jalr rd,rs1,offset]
-fn jalr(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
-    let o = parse_type_i(word);
+fn jalr(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    jalr_operands(cpu, addr, &Operands::new(word))
+}
+
+fn jalr_operands(cpu: &mut Cpu, _addr: u64, o: &Operands) -> Result<(), Trap> {
     let t = signed(cpu, cpu.pc as i64);
-    cpu.pc = (cpu.x[o.rs1 as usize] as u64).wrapping_add(o.imm as u64);
-    cpu.x[o.rd as usize] = t;
+    cpu.pc = (cpu.x[o.rs1] as u64).wrapping_add(o.imm as u64);
+    cpu.x[o.rd] = t;
     Ok(())
 }
 
 /// [beq rs1,rs2,offset]
 /// BEQ and BNE take the branch if registers rs1 and rs2 are equal or unequal respectively.
 fn beq(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
-    let o = parse_type_b(word);
-    match cpu.x[o.rs1 as usize] == cpu.x[o.rs2 as usize] {
-        true => cpu.pc = addr.wrapping_add(o.imm),
+    beq_operands(cpu, addr, &Operands::new(word))
+}
+
+fn beq_operands(cpu: &mut Cpu, addr: u64, o: &Operands) -> Result<(), Trap> {
+    match cpu.x[o.rs1] == cpu.x[o.rs2] {
+        true => cpu.pc = addr.wrapping_add(o.imm as u64),
         _ => {}
     }
     Ok(())
@@ -1625,9 +2810,12 @@ fn beq(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
 
 /// [bne rs1,rs2,offset]
 fn bne(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
-    let o = parse_type_b(word);
-    match cpu.x[o.rs1 as usize] != cpu.x[o.rs2 as usize] {
-        true => cpu.pc = addr.wrapping_add(o.imm),
+    bne_operands(cpu, addr, &Operands::new(word))
+}
+
+fn bne_operands(cpu: &mut Cpu, addr: u64, o: &Operands) -> Result<(), Trap> {
+    match cpu.x[o.rs1] != cpu.x[o.rs2] {
+        true => cpu.pc = addr.wrapping_add(o.imm as u64),
         _ => {}
     }
     Ok(())
@@ -1637,9 +2825,12 @@ fn bne(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
 /// BLT and BLTU take the branch if rs1 is less than rs2, using signed and unsigned
 /// comparison respectively.
 fn blt(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
-    let o = parse_type_b(word);
-    match signed(&cpu, cpu.x[o.rs1 as usize]) < signed(&cpu, cpu.x[o.rs2 as usize]) {
-        true => cpu.pc = addr.wrapping_add(o.imm),
+    blt_operands(cpu, addr, &Operands::new(word))
+}
+
+fn blt_operands(cpu: &mut Cpu, addr: u64, o: &Operands) -> Result<(), Trap> {
+    match signed(cpu, cpu.x[o.rs1]) < signed(cpu, cpu.x[o.rs2]) {
+        true => cpu.pc = addr.wrapping_add(o.imm as u64),
         _ => {}
     }
     Ok(())
@@ -1647,9 +2838,12 @@ fn blt(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
 
 /// [bltu rs1,rs2,offset]
 fn bltu(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
-    let o = parse_type_b(word);
-    match unsigned(cpu, cpu.x[o.rs1 as usize]) < unsigned(cpu, cpu.x[o.rs2 as usize]) {
-        true => cpu.pc = addr.wrapping_add(o.imm),
+    bltu_operands(cpu, addr, &Operands::new(word))
+}
+
+fn bltu_operands(cpu: &mut Cpu, addr: u64, o: &Operands) -> Result<(), Trap> {
+    match unsigned(cpu, cpu.x[o.rs1]) < unsigned(cpu, cpu.x[o.rs2]) {
+        true => cpu.pc = addr.wrapping_add(o.imm as u64),
         _ => {}
     }
     Ok(())
@@ -1659,9 +2853,12 @@ fn bltu(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
 /// BGE and BGEU take the branch if rs1 is greater than or equal to rs2,
 /// using signed and unsigned comparison respectively.
 fn bge(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
-    let o = parse_type_b(word);
-    match signed(&cpu, cpu.x[o.rs1 as usize]) >= signed(&cpu, cpu.x[o.rs2 as usize]) {
-        true => cpu.pc = addr.wrapping_add(o.imm),
+    bge_operands(cpu, addr, &Operands::new(word))
+}
+
+fn bge_operands(cpu: &mut Cpu, addr: u64, o: &Operands) -> Result<(), Trap> {
+    match signed(cpu, cpu.x[o.rs1]) >= signed(cpu, cpu.x[o.rs2]) {
+        true => cpu.pc = addr.wrapping_add(o.imm as u64),
         _ => {}
     }
     Ok(())
@@ -1669,9 +2866,12 @@ fn bge(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
 
 /// [bgeu rs1,rs2,offset]
 fn bgeu(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
-    let o = parse_type_b(word);
-    match unsigned(cpu, cpu.x[o.rs1 as usize]) >= unsigned(cpu, cpu.x[o.rs2 as usize]) {
-        true => cpu.pc = addr.wrapping_add(o.imm),
+    bgeu_operands(cpu, addr, &Operands::new(word))
+}
+
+fn bgeu_operands(cpu: &mut Cpu, addr: u64, o: &Operands) -> Result<(), Trap> {
+    match unsigned(cpu, cpu.x[o.rs1]) >= unsigned(cpu, cpu.x[o.rs2]) {
+        true => cpu.pc = addr.wrapping_add(o.imm as u64),
         _ => {}
     }
     Ok(())
@@ -1688,15 +2888,15 @@ fn bgeu(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
 /// read the CSR and shall not cause any of the side effects that might occur on a CSR read.
 fn csrrw(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
     let o = parse_type_csr(word);
-    let t = match cpu.csr.read(o.csr, addr, &cpu.privilege) {
+    let t = match cpu.csr.read(o.csr, word, &cpu.privilege) {
         Ok(data) => data as i64,
         Err(e) => return Err(e),
     };
     let data = unsigned(cpu, cpu.x[o.rs1 as usize]);
-    match cpu.csr.write(o.csr, data, addr, &cpu.privilege) {
+    match cpu.csr.write(o.csr, data, addr, word, &cpu.privilege) {
         Ok(need_update_mmu_addressing_mode) => {
             if need_update_mmu_addressing_mode {
-                cpu.mmu.update_addressing_mode(data);
+                cpu.update_addressing_mode();
             }
             cpu.x[o.rd as usize] = signed(cpu, t);
             Ok(())
@@ -1709,14 +2909,14 @@ fn csrrw(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
 fn csrrwi(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
     let o = parse_type_csr(word);
     let t = o.rs1 as u64; // uimm field
-    match cpu.csr.read(o.csr, addr, &cpu.privilege) {
+    match cpu.csr.read(o.csr, word, &cpu.privilege) {
         Ok(data) => cpu.x[o.rd as usize] = signed(cpu, data as i64),
         Err(e) => return Err(e),
     };
-    match cpu.csr.write(o.csr, t, addr, &cpu.privilege) {
+    match cpu.csr.write(o.csr, t, addr, word, &cpu.privilege) {
         Ok(need_update_mmu_addressing_mode) => {
             if need_update_mmu_addressing_mode {
-                cpu.mmu.update_addressing_mode(t);
+                cpu.update_addressing_mode();
             }
             Ok(())
         }
@@ -1731,17 +2931,22 @@ fn csrrwi(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
 /// bit positions to be set in the CSR. Any bit that is high in rs1 will cause
 /// the corresponding bit to be set in the CSR, if that CSR bit is writable.
 /// Other bits in the CSR are unaffected (though CSRs might have side effects when written).
+/// If rs1=x0, the instruction does not write to the CSR at all.
 fn csrrs(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
     let o = parse_type_csr(word);
-    let t = match cpu.csr.read(o.csr, addr, &cpu.privilege) {
+    let t = match cpu.csr.read(o.csr, word, &cpu.privilege) {
         Ok(data) => data as i64,
         Err(e) => return Err(e),
     };
+    if o.rs1 == 0 {
+        cpu.x[o.rd as usize] = signed(cpu, t);
+        return Ok(());
+    }
     let data = unsigned(cpu, t | cpu.x[o.rs1 as usize]);
-    match cpu.csr.write(o.csr, data, addr, &cpu.privilege) {
+    match cpu.csr.write(o.csr, data, addr, word, &cpu.privilege) {
         Ok(need_update_mmu_addressing_mode) => {
             if need_update_mmu_addressing_mode {
-                cpu.mmu.update_addressing_mode(data);
+                cpu.update_addressing_mode();
             }
             cpu.x[o.rd as usize] = signed(cpu, t);
             Ok(())
@@ -1751,17 +2956,22 @@ fn csrrs(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
 }
 
 /// [csrrsi rd,offset,uimm]
+/// If uimm=0, the instruction does not write to the CSR at all.
 fn csrrsi(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
     let o = parse_type_csr(word);
-    let t = match cpu.csr.read(o.csr, addr, &cpu.privilege) {
+    let t = match cpu.csr.read(o.csr, word, &cpu.privilege) {
         Ok(data) => data as i64,
         Err(e) => return Err(e),
     };
+    if o.rs1 == 0 {
+        cpu.x[o.rd as usize] = signed(cpu, t);
+        return Ok(());
+    }
     let data = unsigned(cpu, t | o.rs1 as i64);
-    match cpu.csr.write(o.csr, data, addr, &cpu.privilege) {
+    match cpu.csr.write(o.csr, data, addr, word, &cpu.privilege) {
         Ok(need_update_mmu_addressing_mode) => {
             if need_update_mmu_addressing_mode {
-                cpu.mmu.update_addressing_mode(data);
+                cpu.update_addressing_mode();
             }
             cpu.x[o.rd as usize] = signed(cpu, t);
             Ok(())
@@ -1776,17 +2986,22 @@ fn csrrsi(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
 /// value in integer register rs1 is treated as a bit mask that specifies bit positions to
 /// be cleared in the CSR. Any bit that is high in rs1 will cause the corresponding bit to
 /// be cleared in the CSR, if that CSR bit is writable. Other bits in the CSR are unaffected.
+/// If rs1=x0, the instruction does not write to the CSR at all.
 fn csrrc(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
     let o = parse_type_csr(word);
-    let t = match cpu.csr.read(o.csr, addr, &cpu.privilege) {
+    let t = match cpu.csr.read(o.csr, word, &cpu.privilege) {
         Ok(data) => data as i64,
         Err(e) => return Err(e),
     };
+    if o.rs1 == 0 {
+        cpu.x[o.rd as usize] = signed(cpu, t);
+        return Ok(());
+    }
     let data = (signed(cpu, t) & !cpu.x[o.rs1 as usize]) as u64;
-    match cpu.csr.write(o.csr, data, addr, &cpu.privilege) {
+    match cpu.csr.write(o.csr, data, addr, word, &cpu.privilege) {
         Ok(need_update_mmu_addressing_mode) => {
             if need_update_mmu_addressing_mode {
-                cpu.mmu.update_addressing_mode(data);
+                cpu.update_addressing_mode();
             }
             cpu.x[o.rd as usize] = signed(cpu, t);
             Ok(())
@@ -1796,17 +3011,22 @@ fn csrrc(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
 }
 
 /// [csrrci rd,offset,uimm]
+/// If uimm=0, the instruction does not write to the CSR at all.
 fn csrrci(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
     let o = parse_type_csr(word);
-    let t = match cpu.csr.read(o.csr, addr, &cpu.privilege) {
+    let t = match cpu.csr.read(o.csr, word, &cpu.privilege) {
         Ok(data) => data as i64,
         Err(e) => return Err(e),
     };
+    if o.rs1 == 0 {
+        cpu.x[o.rd as usize] = signed(cpu, t);
+        return Ok(());
+    }
     let data = (signed(cpu, t) & !(o.rs1 as i64)) as u64;
-    match cpu.csr.write(o.csr, data, addr, &cpu.privilege) {
+    match cpu.csr.write(o.csr, data, addr, word, &cpu.privilege) {
         Ok(need_update_mmu_addressing_mode) => {
             if need_update_mmu_addressing_mode {
-                cpu.mmu.update_addressing_mode(data);
+                cpu.update_addressing_mode();
             }
             cpu.x[o.rd as usize] = signed(cpu, t);
             Ok(())
@@ -1821,11 +3041,11 @@ fn csrrci(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
 /// [ecall]
 fn ecall(cpu: &mut Cpu, addr: u64, _word: u32) -> Result<(), Trap> {
     Err(Trap {
-        exception: match cpu.privilege {
-            Privilege::User => Exception::EnvironmentCallFromUMode,
-            Privilege::Supervisor => Exception::EnvironmentCallFromSMode,
-            Privilege::Hypervisor => panic!("Hypervisor is not supported!"),
-            Privilege::Machine => Exception::EnvironmentCallFromMMode,
+        exception: match (&cpu.privilege, cpu.virtualization_mode) {
+            (Privilege::User, _) => Exception::EnvironmentCallFromUMode,
+            (Privilege::Supervisor, false) => Exception::EnvironmentCallFromSMode,
+            (Privilege::Supervisor, true) => Exception::EnvironmentCallFromVSMode,
+            (Privilege::Machine, _) => Exception::EnvironmentCallFromMMode,
         },
         value: addr,
     })
@@ -1843,27 +3063,57 @@ fn ebreak(_cpu: &mut Cpu, addr: u64, _word: u32) -> Result<(), Trap> {
 // Trap-Return Instructions
 //==============================================================================
 /// [uret]
-fn uret(_cpu: &mut Cpu, _addr: u64, _word: u32) -> Result<(), Trap> {
-    panic!("TODO!!");
+/// User-level interrupts are not supported, so uret is an illegal instruction.
+fn uret(_cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    Err(Trap {
+        exception: Exception::IllegalInstruction,
+        value: word as u64,
+    })
 }
 
 /// [sret]
-fn sret(cpu: &mut Cpu, addr: u64, _word: u32) -> Result<(), Trap> {
-    cpu.pc = match cpu.csr.read(CSR_SEPC, addr, &cpu.privilege) {
+/// In VS-mode, sret returns with vsepc and vsstatus, and raises a virtual instruction
+/// exception when hstatus.VTSR=1. In HS-mode, the virtualization mode is restored
+/// from hstatus.SPV.
+fn sret(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    if cpu.virtualization_mode && (cpu.csr.read_direct(CSR_HSTATUS) & CSR_HSTATUS_VTSR) != 0 {
+        return Err(Trap {
+            exception: Exception::VirtualInstruction,
+            value: word as u64,
+        });
+    }
+    cpu.pc = match cpu.csr.read(CSR_SEPC, word, &cpu.privilege) {
         Ok(data) => data,
         Err(e) => return Err(e),
     };
 
-    // update SSTATUS register.
-    let sstatus = cpu.csr.read_direct(CSR_SSTATUS);
+    // update SSTATUS (VSSTATUS) register.
+    let status_reg = match cpu.virtualization_mode {
+        true => CSR_VSSTATUS,
+        false => CSR_SSTATUS,
+    };
+    let sstatus = cpu.csr.read_direct(status_reg);
     let spp = (sstatus >> 8) & 1;
     let spie = (sstatus >> 5) & 1;
     cpu.csr.write_direct(
-        CSR_SSTATUS,
+        status_reg,
         (sstatus & !0x122) | // set 0 to SPP, SPIE, SIE
               (spie << 1) |   // set SPIE to SIE.
               (1 << 5), // set 1 to SPIE
     );
+    // sret returns to a privilege mode less privileged than M-mode, so it clears MPRV.
+    if !cpu.virtualization_mode {
+        let mstatus = cpu.csr.read_direct(CSR_MSTATUS);
+        cpu.csr.write_direct(CSR_MSTATUS, mstatus & !CSR_STATUS_MPRV);
+    }
+
+    // update virtualization mode by SPV.
+    if !cpu.virtualization_mode {
+        let hstatus = cpu.csr.read_direct(CSR_HSTATUS);
+        cpu.csr
+            .write_direct(CSR_HSTATUS, hstatus & !CSR_HSTATUS_SPV);
+        cpu.change_virtualization_mode((hstatus & CSR_HSTATUS_SPV) != 0);
+    }
 
     // update privilege by SPP.
     // TODO: refactoring.
@@ -1873,12 +3123,14 @@ fn sret(cpu: &mut Cpu, addr: u64, _word: u32) -> Result<(), Trap> {
         _ => panic!("Unexpected Error!!"),
     };
     cpu.mmu.set_privilege(&cpu.privilege);
+    cpu.mmu.update_status(&cpu.csr);
+    cpu.mmu.cancel_reservation();
     Ok(())
 }
 
 /// [mret]
-fn mret(cpu: &mut Cpu, addr: u64, _word: u32) -> Result<(), Trap> {
-    cpu.pc = match cpu.csr.read(CSR_MEPC, addr, &cpu.privilege) {
+fn mret(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    cpu.pc = match cpu.csr.read(CSR_MEPC, word, &cpu.privilege) {
         Ok(data) => data,
         Err(e) => return Err(e),
     };
@@ -1887,23 +3139,31 @@ fn mret(cpu: &mut Cpu, addr: u64, _word: u32) -> Result<(), Trap> {
     let mstatus = cpu.csr.read_direct(CSR_MSTATUS);
     let mpp = (mstatus >> 11) & 0x3;
     let mpie = (mstatus >> 7) & 1;
+    let mpv = (mstatus & CSR_STATUS_MPV) != 0;
+    // mret clears MPRV unless it returns to M-mode.
+    let mprv = match mpp {
+        3 => 0,
+        _ => CSR_STATUS_MPRV,
+    };
     cpu.csr.write_direct(
         CSR_MSTATUS,
-        (mstatus & !0x1800) | // set 0 to MPP.
+        (mstatus & !(0x1800 | CSR_STATUS_MPV | mprv)) | // set 0 to MPP and MPV.
               (mpie << 3) |         // set MPIE to MIE.
               (1 << 7), // set 1 to MPIE
     );
 
-    // update privilege by MPP.
+    // update privilege by MPP, and virtualization mode by MPV.
     // TODO: refactoring.
     cpu.privilege = match mpp {
         0 => Privilege::User,
         1 => Privilege::Supervisor,
-        2 => Privilege::Hypervisor,
         3 => Privilege::Machine,
         _ => panic!("Unexpected Error!!"),
     };
     cpu.mmu.set_privilege(&cpu.privilege);
+    cpu.change_virtualization_mode(mpv && mpp != 3);
+    cpu.mmu.update_status(&cpu.csr);
+    cpu.mmu.cancel_reservation();
     Ok(())
 }
 
@@ -1918,11 +3178,259 @@ fn wfi(cpu: &mut Cpu, _addr: u64, _word: u32) -> Result<(), Trap> {
     Ok(())
 }
 
-/// [sfence.vma]
-fn sfence(_cpu: &mut Cpu, _addr: u64, _word: u32) -> Result<(), Trap> {
+/// [sfence.vma rs1,rs2]
+/// sfence.vma raises a virtual instruction exception in VU-mode, or in VS-mode when
+/// hstatus.VTVM=1.
+fn sfence(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    if cpu.virtualization_mode
+        && (matches!(cpu.privilege, Privilege::User)
+            || (cpu.csr.read_direct(CSR_HSTATUS) & CSR_HSTATUS_VTVM) != 0)
+    {
+        return Err(Trap {
+            exception: Exception::VirtualInstruction,
+            value: word as u64,
+        });
+    }
+    flush_tlb(cpu, word, cpu.virtualization_mode);
+    Ok(())
+}
+
+/// flush the TLB entries selected by the virtual address in rs1 and the ASID in
+/// rs2. x0 selects all virtual addresses or all address spaces.
+fn flush_tlb(cpu: &mut Cpu, word: u32, virtualization_mode: bool) {
+    let o = parse_type_r(word);
+    let v_addr = match o.rs1 {
+        0 => None,
+        rs1 => Some(cpu.x[rs1 as usize] as u64),
+    };
+    let asid = match o.rs2 {
+        0 => None,
+        rs2 => Some(cpu.x[rs2 as usize] as u64),
+    };
+    cpu.mmu.flush_tlb(v_addr, asid, virtualization_mode);
+}
+
+//==============================================================================
+// Hypervisor Instructions (H)
+//==============================================================================
+/// hypervisor instructions are valid in M-mode and HS-mode, and the virtual-machine
+/// load/store instructions are also valid in U-mode when hstatus.HU=1.
+/// They raise virtual instruction exceptions in VS-mode and VU-mode.
+fn check_hypervisor_instruction(cpu: &mut Cpu, word: u32, allow_user: bool) -> Result<(), Trap> {
+    let allowed = match (&cpu.privilege, cpu.virtualization_mode) {
+        (_, true) => {
+            return Err(Trap {
+                exception: Exception::VirtualInstruction,
+                value: word as u64,
+            })
+        }
+        (Privilege::User, false) => {
+            allow_user && (cpu.csr.read_direct(CSR_HSTATUS) & CSR_HSTATUS_HU) != 0
+        }
+        _ => true,
+    };
+    match allowed {
+        true => Ok(()),
+        false => Err(Trap {
+            exception: Exception::IllegalInstruction,
+            value: word as u64,
+        }),
+    }
+}
+
+/// the virtual-machine load/store instructions access the memory as though V=1 and
+/// the privilege mode were hstatus.SPVP, with the two-stage address translation.
+fn hypervisor_access<T>(
+    cpu: &mut Cpu,
+    word: u32,
+    executable: bool,
+    access: impl FnOnce(&mut Mmu) -> Result<T, Trap>,
+) -> Result<T, Trap> {
+    match check_hypervisor_instruction(cpu, word, true) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    let privilege = match cpu.csr.read_direct(CSR_HSTATUS) & CSR_HSTATUS_SPVP {
+        0 => Privilege::User,
+        _ => Privilege::Supervisor,
+    };
+    cpu.mmu.set_privilege(&privilege);
+    cpu.mmu.set_virtualization_mode(true);
+    cpu.mmu.set_executable_read(executable);
+    let result = access(&mut cpu.mmu);
+    cpu.mmu.set_privilege(&cpu.privilege);
+    cpu.mmu.set_virtualization_mode(cpu.virtualization_mode);
+    cpu.mmu.set_executable_read(false);
+    result
+}
+
+/// [hfence.vvma rs1,rs2]
+fn hfence_vvma(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    match check_hypervisor_instruction(cpu, word, false) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    flush_tlb(cpu, word, true);
+    Ok(())
+}
+
+/// [hfence.gvma rs1,rs2]
+/// The TLB caches the translations from guest virtual addresses, so all entries
+/// of the virtual machine are flushed regardless of the guest physical address.
+fn hfence_gvma(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    match check_hypervisor_instruction(cpu, word, false) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    cpu.mmu.flush_tlb(None, None, true);
+    Ok(())
+}
+
+/// [hlv.b rd,(rs1)]
+fn hlv_b(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    let o = parse_type_r(word);
+    let v_addr = cpu.x[o.rs1 as usize] as u64;
+    match hypervisor_access(cpu, word, false, |mmu| mmu.read8(v_addr)) {
+        Ok(data) => cpu.x[o.rd as usize] = data as i8 as i64,
+        Err(e) => return Err(e),
+    };
+    Ok(())
+}
+
+/// [hlv.bu rd,(rs1)]
+fn hlv_bu(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    let o = parse_type_r(word);
+    let v_addr = cpu.x[o.rs1 as usize] as u64;
+    match hypervisor_access(cpu, word, false, |mmu| mmu.read8(v_addr)) {
+        Ok(data) => cpu.x[o.rd as usize] = data as i64,
+        Err(e) => return Err(e),
+    };
+    Ok(())
+}
+
+/// [hlv.h rd,(rs1)]
+fn hlv_h(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    let o = parse_type_r(word);
+    let v_addr = cpu.x[o.rs1 as usize] as u64;
+    match hypervisor_access(cpu, word, false, |mmu| mmu.read16(v_addr)) {
+        Ok(data) => cpu.x[o.rd as usize] = data as i16 as i64,
+        Err(e) => return Err(e),
+    };
+    Ok(())
+}
+
+/// [hlv.hu rd,(rs1)]
+fn hlv_hu(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    let o = parse_type_r(word);
+    let v_addr = cpu.x[o.rs1 as usize] as u64;
+    match hypervisor_access(cpu, word, false, |mmu| mmu.read16(v_addr)) {
+        Ok(data) => cpu.x[o.rd as usize] = data as i64,
+        Err(e) => return Err(e),
+    };
+    Ok(())
+}
+
+/// [hlvx.hu rd,(rs1)]
+/// hlvx.hu reads the memory with the execute permission instead of the read permission.
+fn hlvx_hu(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    let o = parse_type_r(word);
+    let v_addr = cpu.x[o.rs1 as usize] as u64;
+    match hypervisor_access(cpu, word, true, |mmu| mmu.read16(v_addr)) {
+        Ok(data) => cpu.x[o.rd as usize] = data as i64,
+        Err(e) => return Err(e),
+    };
+    Ok(())
+}
+
+/// [hlv.w rd,(rs1)]
+fn hlv_w(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    let o = parse_type_r(word);
+    let v_addr = cpu.x[o.rs1 as usize] as u64;
+    match hypervisor_access(cpu, word, false, |mmu| mmu.read32(v_addr)) {
+        Ok(data) => cpu.x[o.rd as usize] = data as i32 as i64,
+        Err(e) => return Err(e),
+    };
+    Ok(())
+}
+
+/// [hlv.wu rd,(rs1)]
+fn hlv_wu(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    match check_rv64(cpu, word) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    let o = parse_type_r(word);
+    let v_addr = cpu.x[o.rs1 as usize] as u64;
+    match hypervisor_access(cpu, word, false, |mmu| mmu.read32(v_addr)) {
+        Ok(data) => cpu.x[o.rd as usize] = data as i64,
+        Err(e) => return Err(e),
+    };
+    Ok(())
+}
+
+/// [hlvx.wu rd,(rs1)]
+/// hlvx.wu reads the memory with the execute permission instead of the read permission.
+fn hlvx_wu(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    let o = parse_type_r(word);
+    let v_addr = cpu.x[o.rs1 as usize] as u64;
+    match hypervisor_access(cpu, word, true, |mmu| mmu.read32(v_addr)) {
+        Ok(data) => cpu.x[o.rd as usize] = signed(cpu, data as i64),
+        Err(e) => return Err(e),
+    };
+    Ok(())
+}
+
+/// [hlv.d rd,(rs1)]
+fn hlv_d(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    match check_rv64(cpu, word) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    let o = parse_type_r(word);
+    let v_addr = cpu.x[o.rs1 as usize] as u64;
+    match hypervisor_access(cpu, word, false, |mmu| mmu.read64(v_addr)) {
+        Ok(data) => cpu.x[o.rd as usize] = data as i64,
+        Err(e) => return Err(e),
+    };
     Ok(())
 }
 
+/// [hsv.b rs2,(rs1)]
+fn hsv_b(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    let o = parse_type_r(word);
+    let v_addr = cpu.x[o.rs1 as usize] as u64;
+    let data = cpu.x[o.rs2 as usize] as u8;
+    hypervisor_access(cpu, word, false, |mmu| mmu.write8(v_addr, data))
+}
+
+/// [hsv.h rs2,(rs1)]
+fn hsv_h(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    let o = parse_type_r(word);
+    let v_addr = cpu.x[o.rs1 as usize] as u64;
+    let data = cpu.x[o.rs2 as usize] as u16;
+    hypervisor_access(cpu, word, false, |mmu| mmu.write16(v_addr, data))
+}
+
+/// [hsv.w rs2,(rs1)]
+fn hsv_w(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    let o = parse_type_r(word);
+    let v_addr = cpu.x[o.rs1 as usize] as u64;
+    let data = cpu.x[o.rs2 as usize] as u32;
+    hypervisor_access(cpu, word, false, |mmu| mmu.write32(v_addr, data))
+}
+
+/// [hsv.d rs2,(rs1)]
+fn hsv_d(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    match check_rv64(cpu, word) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    let o = parse_type_r(word);
+    let v_addr = cpu.x[o.rs1 as usize] as u64;
+    let data = cpu.x[o.rs2 as usize] as u64;
+    hypervisor_access(cpu, word, false, |mmu| mmu.write64(v_addr, data))
+}
+
 //==============================================================================
 // Multiplication Instructions (RV32M/RV64M)
 //==============================================================================
@@ -2092,361 +3600,552 @@ fn remuw(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
 }
 
 //==============================================================================
-// Specifying Ordering of Atomic Instructions (RV32A/RV64A)
+// Bit-Manipulation Instructions (Zba/Zbb/Zbc/Zbs)
 //==============================================================================
 
-/// [lr.w rd,rs1]
-fn lr_w(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+/// number of bits in the integer register.
+fn xlen_bits(cpu: &Cpu) -> u32 {
+    match cpu.xlen {
+        Xlen::X32 => 32,
+        Xlen::X64 => 64,
+    }
+}
+
+/// the shift amount of immediate shift instructions, which is 6 bits in RV64.
+fn get_shamt(cpu: &Cpu, word: u32) -> u32 {
+    match cpu.xlen {
+        Xlen::X64 => (word >> 20) & 0x3f,
+        Xlen::X32 => (word >> 20) & 0x1f,
+    }
+}
+
+/// [sh1add rd,rs1,rs2]
+fn sh1add(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
     let o = parse_type_r(word);
-    let addr = cpu.x[o.rs1 as usize] as u64;
-    let data = match cpu.mmu.read32(addr) {
-        Ok(d) => d as i32 as i64,
-        Err(e) => return Err(e),
-    };
-    cpu.x[o.rd as usize] = data;
-    cpu.mmu.set_address_reserve(addr, true);
+    cpu.x[o.rd as usize] = signed(
+        cpu,
+        (cpu.x[o.rs1 as usize] << 1).wrapping_add(cpu.x[o.rs2 as usize]),
+    );
     Ok(())
 }
 
-/// [sc.w rd,rs1,rs2]
-fn sc_w(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+/// [sh2add rd,rs1,rs2]
+fn sh2add(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
     let o = parse_type_r(word);
-    let addr = cpu.x[o.rs1 as usize] as u64;
-    let data = cpu.x[o.rs2 as usize] as u32;
-    cpu.x[o.rd as usize] = match cpu.mmu.is_address_reserved(addr) {
-        true => match cpu.mmu.write32(addr, data) {
-            Ok(()) => {
-                cpu.mmu.set_address_reserve(addr, false);
-                0
-            }
-            Err(e) => return Err(e),
-        },
-        false => 1,
-    };
+    cpu.x[o.rd as usize] = signed(
+        cpu,
+        (cpu.x[o.rs1 as usize] << 2).wrapping_add(cpu.x[o.rs2 as usize]),
+    );
     Ok(())
 }
 
-/// [amoswap.w rd,rs2,(rs1)]
-fn amoswap_w(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+/// [sh3add rd,rs1,rs2]
+fn sh3add(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
     let o = parse_type_r(word);
-    let t = match cpu.mmu.read32(cpu.x[o.rs1 as usize] as u64) {
-        Ok(data) => data,
+    cpu.x[o.rd as usize] = signed(
+        cpu,
+        (cpu.x[o.rs1 as usize] << 3).wrapping_add(cpu.x[o.rs2 as usize]),
+    );
+    Ok(())
+}
+
+/// [add.uw rd,rs1,rs2]
+/// The *.uw instructions zero-extend the lower 32 bits of rs1 before the operation.
+fn add_uw(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    match check_rv64(cpu, word) {
+        Ok(()) => {}
         Err(e) => return Err(e),
     };
-    let x = cpu.x[o.rs2 as usize] as u32;
-    match cpu.mmu.write32(cpu.x[o.rs1 as usize] as u64, x) {
+    let o = parse_type_r(word);
+    let index = cpu.x[o.rs1 as usize] as u32 as i64;
+    cpu.x[o.rd as usize] = cpu.x[o.rs2 as usize].wrapping_add(index);
+    Ok(())
+}
+
+/// [sh1add.uw rd,rs1,rs2]
+fn sh1add_uw(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    match check_rv64(cpu, word) {
         Ok(()) => {}
         Err(e) => return Err(e),
     };
-    cpu.x[o.rd as usize] = t as i32 as i64;
+    let o = parse_type_r(word);
+    let index = cpu.x[o.rs1 as usize] as u32 as i64;
+    cpu.x[o.rd as usize] = cpu.x[o.rs2 as usize].wrapping_add(index << 1);
     Ok(())
 }
 
-/// [amoadd.w rd,rs2,(rs1)]
-fn amoadd_w(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
-    let o = parse_type_r(word);
-    let t = match cpu.mmu.read32(cpu.x[o.rs1 as usize] as u64) {
-        Ok(data) => data as i32 as i64,
-        Err(e) => return Err(e),
-    };
-    let x = cpu.x[o.rs2 as usize];
-    match cpu.mmu.write32(
-        cpu.x[o.rs1 as usize] as u64,
-        signed(cpu, t.wrapping_add(x)) as u32,
-    ) {
+/// [sh2add.uw rd,rs1,rs2]
+fn sh2add_uw(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    match check_rv64(cpu, word) {
         Ok(()) => {}
         Err(e) => return Err(e),
     };
-    cpu.x[o.rd as usize] = t;
+    let o = parse_type_r(word);
+    let index = cpu.x[o.rs1 as usize] as u32 as i64;
+    cpu.x[o.rd as usize] = cpu.x[o.rs2 as usize].wrapping_add(index << 2);
     Ok(())
 }
 
-/// [amoxor.w rd,rs2,(rs1)]
-fn amoxor_w(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
-    let o = parse_type_r(word);
-    let t = match cpu.mmu.read32(cpu.x[o.rs1 as usize] as u64) {
-        Ok(data) => data as i32 as i64,
-        Err(e) => return Err(e),
-    };
-    let x = cpu.x[o.rs2 as usize];
-    match cpu
-        .mmu
-        .write32(cpu.x[o.rs1 as usize] as u64, signed(cpu, t ^ x) as u32)
-    {
+/// [sh3add.uw rd,rs1,rs2]
+fn sh3add_uw(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    match check_rv64(cpu, word) {
         Ok(()) => {}
         Err(e) => return Err(e),
     };
-    cpu.x[o.rd as usize] = t;
+    let o = parse_type_r(word);
+    let index = cpu.x[o.rs1 as usize] as u32 as i64;
+    cpu.x[o.rd as usize] = cpu.x[o.rs2 as usize].wrapping_add(index << 3);
     Ok(())
 }
 
-/// [amoand.w rd,rs2,(rs1)]
-fn amoand_w(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
-    let o = parse_type_r(word);
-    let t = match cpu.mmu.read32(cpu.x[o.rs1 as usize] as u64) {
-        Ok(data) => data as i32 as i64,
-        Err(e) => return Err(e),
-    };
-    let x = cpu.x[o.rs2 as usize];
-    match cpu
-        .mmu
-        .write32(cpu.x[o.rs1 as usize] as u64, signed(cpu, t & x) as u32)
-    {
+/// [slli.uw rd,rs1,shamt]
+fn slli_uw(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    match check_rv64(cpu, word) {
         Ok(()) => {}
         Err(e) => return Err(e),
     };
-    cpu.x[o.rd as usize] = t;
+    let o = parse_type_i(word);
+    let shamt = (word >> 20) & 0x3f;
+    cpu.x[o.rd as usize] = ((cpu.x[o.rs1 as usize] as u32 as u64) << shamt) as i64;
     Ok(())
 }
 
-/// [amoor.w rd,rs2,(rs1)]
-fn amoor_w(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+/// [andn rd,rs1,rs2]
+fn andn(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
     let o = parse_type_r(word);
-    let t = match cpu.mmu.read32(cpu.x[o.rs1 as usize] as u64) {
-        Ok(data) => data as i32 as i64,
-        Err(e) => return Err(e),
-    };
-    let x = cpu.x[o.rs2 as usize];
-    match cpu
-        .mmu
-        .write32(cpu.x[o.rs1 as usize] as u64, signed(cpu, t | x) as u32)
-    {
-        Ok(()) => {}
-        Err(e) => return Err(e),
-    };
-    cpu.x[o.rd as usize] = t;
+    cpu.x[o.rd as usize] = cpu.x[o.rs1 as usize] & !cpu.x[o.rs2 as usize];
     Ok(())
 }
 
-/// [amomin.w rd,rs2,(rs1)]
-fn amomin_w(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+/// [orn rd,rs1,rs2]
+fn orn(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
     let o = parse_type_r(word);
-    let t = match cpu.mmu.read32(cpu.x[o.rs1 as usize] as u64) {
-        Ok(data) => data as i32 as i64,
-        Err(e) => return Err(e),
-    };
-    let x = cpu.x[o.rs2 as usize];
-    match cpu
-        .mmu
-        .write32(cpu.x[o.rs1 as usize] as u64, std::cmp::min(t, x) as u32)
-    {
-        Ok(()) => {}
-        Err(e) => return Err(e),
-    };
-    cpu.x[o.rd as usize] = t;
+    cpu.x[o.rd as usize] = cpu.x[o.rs1 as usize] | !cpu.x[o.rs2 as usize];
     Ok(())
 }
 
-/// [amomax.w rd,rs2,(rs1)]
-fn amomax_w(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+/// [xnor rd,rs1,rs2]
+fn xnor(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
     let o = parse_type_r(word);
-    let t = match cpu.mmu.read32(cpu.x[o.rs1 as usize] as u64) {
-        Ok(data) => data as i32 as i64,
-        Err(e) => return Err(e),
-    };
-    let x = cpu.x[o.rs2 as usize];
-    match cpu
-        .mmu
-        .write32(cpu.x[o.rs1 as usize] as u64, std::cmp::max(t, x) as u32)
-    {
+    cpu.x[o.rd as usize] = !(cpu.x[o.rs1 as usize] ^ cpu.x[o.rs2 as usize]);
+    Ok(())
+}
+
+/// [clz rd,rs1]
+fn clz(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    let o = parse_type_r(word);
+    cpu.x[o.rd as usize] = match cpu.xlen {
+        Xlen::X64 => (cpu.x[o.rs1 as usize] as u64).leading_zeros(),
+        Xlen::X32 => (cpu.x[o.rs1 as usize] as u32).leading_zeros(),
+    } as i64;
+    Ok(())
+}
+
+/// [clzw rd,rs1]
+fn clzw(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    match check_rv64(cpu, word) {
         Ok(()) => {}
         Err(e) => return Err(e),
     };
-    cpu.x[o.rd as usize] = t;
+    let o = parse_type_r(word);
+    cpu.x[o.rd as usize] = (cpu.x[o.rs1 as usize] as u32).leading_zeros() as i64;
     Ok(())
 }
 
-/// [amominu.w rd,rs2,(rs1)]
-fn amominu_w(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+/// [ctz rd,rs1]
+fn ctz(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
     let o = parse_type_r(word);
-    let t = match cpu.mmu.read32(cpu.x[o.rs1 as usize] as u64) {
-        Ok(data) => data,
-        Err(e) => return Err(e),
-    };
-    let x = cpu.x[o.rs2 as usize] as u32;
-    match cpu
-        .mmu
-        .write32(cpu.x[o.rs1 as usize] as u64, std::cmp::min(t, x))
-    {
+    cpu.x[o.rd as usize] = match cpu.xlen {
+        Xlen::X64 => (cpu.x[o.rs1 as usize] as u64).trailing_zeros(),
+        Xlen::X32 => (cpu.x[o.rs1 as usize] as u32).trailing_zeros(),
+    } as i64;
+    Ok(())
+}
+
+/// [ctzw rd,rs1]
+fn ctzw(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    match check_rv64(cpu, word) {
         Ok(()) => {}
         Err(e) => return Err(e),
     };
-    cpu.x[o.rd as usize] = t as i32 as i64;
+    let o = parse_type_r(word);
+    cpu.x[o.rd as usize] = (cpu.x[o.rs1 as usize] as u32).trailing_zeros() as i64;
     Ok(())
 }
 
-/// [amomaxu.w rd,rs2,(rs1)]
-fn amomaxu_w(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+/// [cpop rd,rs1]
+fn cpop(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
     let o = parse_type_r(word);
-    let t = match cpu.mmu.read32(cpu.x[o.rs1 as usize] as u64) {
-        Ok(data) => data,
-        Err(e) => return Err(e),
-    };
-    let x = cpu.x[o.rs2 as usize] as u32;
-    match cpu
-        .mmu
-        .write32(cpu.x[o.rs1 as usize] as u64, std::cmp::max(t, x))
-    {
+    cpu.x[o.rd as usize] = unsigned(cpu, cpu.x[o.rs1 as usize]).count_ones() as i64;
+    Ok(())
+}
+
+/// [cpopw rd,rs1]
+fn cpopw(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    match check_rv64(cpu, word) {
         Ok(()) => {}
         Err(e) => return Err(e),
     };
-    cpu.x[o.rd as usize] = t as i32 as i64;
+    let o = parse_type_r(word);
+    cpu.x[o.rd as usize] = (cpu.x[o.rs1 as usize] as u32).count_ones() as i64;
     Ok(())
 }
 
-/// [lr.d rd,rs1]
-fn lr_d(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+/// [max rd,rs1,rs2]
+fn max(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
     let o = parse_type_r(word);
-    let addr = cpu.x[o.rs1 as usize] as u64;
-    let data = match cpu.mmu.read64(addr) {
-        Ok(d) => d as i64,
-        Err(e) => return Err(e),
-    };
-    cpu.x[o.rd as usize] = data;
-    cpu.mmu.set_address_reserve(addr, true);
+    cpu.x[o.rd as usize] = cpu.x[o.rs1 as usize].max(cpu.x[o.rs2 as usize]);
     Ok(())
 }
 
-/// [sc.d rd,rs1,rs2]
-fn sc_d(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+/// [maxu rd,rs1,rs2]
+fn maxu(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
     let o = parse_type_r(word);
-    let addr = cpu.x[o.rs1 as usize] as u64;
-    let data = cpu.x[o.rs2 as usize] as u64;
-    cpu.x[o.rd as usize] = match cpu.mmu.is_address_reserved(addr) {
-        true => match cpu.mmu.write64(addr, data) {
-            Ok(()) => {
-                cpu.mmu.set_address_reserve(addr, false);
-                0
-            }
-            Err(e) => return Err(e),
-        },
-        false => 1,
-    };
+    let rs1 = unsigned(cpu, cpu.x[o.rs1 as usize]);
+    let rs2 = unsigned(cpu, cpu.x[o.rs2 as usize]);
+    cpu.x[o.rd as usize] = signed(cpu, rs1.max(rs2) as i64);
     Ok(())
 }
 
-/// [amoswap.d rd,rs2,(rs1)]
-fn amoswap_d(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+/// [min rd,rs1,rs2]
+fn min(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
     let o = parse_type_r(word);
-    let t = match cpu.mmu.read64(cpu.x[o.rs1 as usize] as u64) {
-        Ok(data) => data,
-        Err(e) => return Err(e),
-    };
-    let x = cpu.x[o.rs2 as usize] as u64;
-    match cpu.mmu.write64(cpu.x[o.rs1 as usize] as u64, x) {
-        Ok(()) => {}
-        Err(e) => return Err(e),
-    };
-    cpu.x[o.rd as usize] = t as i64;
+    cpu.x[o.rd as usize] = cpu.x[o.rs1 as usize].min(cpu.x[o.rs2 as usize]);
     Ok(())
 }
 
-/// [amoadd.d rd,rs2,(rs1)]
-fn amoadd_d(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+/// [minu rd,rs1,rs2]
+fn minu(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
     let o = parse_type_r(word);
-    let t = match cpu.mmu.read64(cpu.x[o.rs1 as usize] as u64) {
-        Ok(data) => data as i64,
-        Err(e) => return Err(e),
-    };
-    let x = cpu.x[o.rs2 as usize];
-    match cpu
-        .mmu
-        .write64(cpu.x[o.rs1 as usize] as u64, t.wrapping_add(x) as u64)
-    {
-        Ok(()) => {}
-        Err(e) => return Err(e),
-    };
-    cpu.x[o.rd as usize] = t;
+    let rs1 = unsigned(cpu, cpu.x[o.rs1 as usize]);
+    let rs2 = unsigned(cpu, cpu.x[o.rs2 as usize]);
+    cpu.x[o.rd as usize] = signed(cpu, rs1.min(rs2) as i64);
     Ok(())
 }
 
-/// [amoxor.d rd,rs2,(rs1)]
-fn amoxor_d(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+/// [sext.b rd,rs1]
+fn sext_b(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
     let o = parse_type_r(word);
-    let t = match cpu.mmu.read64(cpu.x[o.rs1 as usize] as u64) {
-        Ok(data) => data as i64,
-        Err(e) => return Err(e),
-    };
-    let x = cpu.x[o.rs2 as usize];
-    match cpu
-        .mmu
-        .write64(cpu.x[o.rs1 as usize] as u64, (t ^ x) as u64)
-    {
-        Ok(()) => {}
-        Err(e) => return Err(e),
-    };
-    cpu.x[o.rd as usize] = t;
+    cpu.x[o.rd as usize] = cpu.x[o.rs1 as usize] as i8 as i64;
     Ok(())
 }
 
-/// [amoand.d rd,rs2,(rs1)]
-fn amoand_d(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+/// [sext.h rd,rs1]
+fn sext_h(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
     let o = parse_type_r(word);
-    let t = match cpu.mmu.read64(cpu.x[o.rs1 as usize] as u64) {
-        Ok(data) => data as i64,
-        Err(e) => return Err(e),
+    cpu.x[o.rd as usize] = cpu.x[o.rs1 as usize] as i16 as i64;
+    Ok(())
+}
+
+/// [zext.h rd,rs1]
+fn zext_h(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    let o = parse_type_r(word);
+    cpu.x[o.rd as usize] = cpu.x[o.rs1 as usize] as u16 as i64;
+    Ok(())
+}
+
+/// [rol rd,rs1,rs2]
+fn rol(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    let o = parse_type_r(word);
+    let shamt = cpu.x[o.rs2 as usize] as u32;
+    cpu.x[o.rd as usize] = match cpu.xlen {
+        Xlen::X64 => (cpu.x[o.rs1 as usize] as u64).rotate_left(shamt & 0x3f) as i64,
+        Xlen::X32 => (cpu.x[o.rs1 as usize] as u32).rotate_left(shamt & 0x1f) as i32 as i64,
     };
-    let x = cpu.x[o.rs2 as usize];
-    match cpu
-        .mmu
-        .write64(cpu.x[o.rs1 as usize] as u64, (t & x) as u64)
-    {
+    Ok(())
+}
+
+/// [rolw rd,rs1,rs2]
+fn rolw(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    match check_rv64(cpu, word) {
         Ok(()) => {}
         Err(e) => return Err(e),
     };
-    cpu.x[o.rd as usize] = t;
+    let o = parse_type_r(word);
+    let shamt = cpu.x[o.rs2 as usize] as u32 & 0x1f;
+    cpu.x[o.rd as usize] = (cpu.x[o.rs1 as usize] as u32).rotate_left(shamt) as i32 as i64;
     Ok(())
 }
 
-/// [amoor.d rd,rs2,(rs1)]
-fn amoor_d(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+/// [ror rd,rs1,rs2]
+fn ror(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
     let o = parse_type_r(word);
-    let t = match cpu.mmu.read64(cpu.x[o.rs1 as usize] as u64) {
-        Ok(data) => data as i64,
-        Err(e) => return Err(e),
+    let shamt = cpu.x[o.rs2 as usize] as u32;
+    cpu.x[o.rd as usize] = match cpu.xlen {
+        Xlen::X64 => (cpu.x[o.rs1 as usize] as u64).rotate_right(shamt & 0x3f) as i64,
+        Xlen::X32 => (cpu.x[o.rs1 as usize] as u32).rotate_right(shamt & 0x1f) as i32 as i64,
     };
-    let x = cpu.x[o.rs2 as usize];
-    match cpu
-        .mmu
-        .write64(cpu.x[o.rs1 as usize] as u64, (t | x) as u64)
-    {
-        Ok(()) => {}
-        Err(e) => return Err(e),
+    Ok(())
+}
+
+/// [rori rd,rs1,shamt]
+fn rori(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    let o = parse_type_i(word);
+    let shamt = get_shamt(cpu, word);
+    cpu.x[o.rd as usize] = match cpu.xlen {
+        Xlen::X64 => (cpu.x[o.rs1 as usize] as u64).rotate_right(shamt) as i64,
+        Xlen::X32 => (cpu.x[o.rs1 as usize] as u32).rotate_right(shamt) as i32 as i64,
     };
-    cpu.x[o.rd as usize] = t;
     Ok(())
 }
 
-/// [amomin.d rd,rs2,(rs1)]
-fn amomin_d(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
-    let o = parse_type_r(word);
-    let t = match cpu.mmu.read64(cpu.x[o.rs1 as usize] as u64) {
-        Ok(data) => data as i64,
+/// [rorw rd,rs1,rs2]
+fn rorw(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    match check_rv64(cpu, word) {
+        Ok(()) => {}
         Err(e) => return Err(e),
     };
-    let x = cpu.x[o.rs2 as usize];
-    match cpu
-        .mmu
-        .write64(cpu.x[o.rs1 as usize] as u64, std::cmp::min(t, x) as u64)
-    {
+    let o = parse_type_r(word);
+    let shamt = cpu.x[o.rs2 as usize] as u32 & 0x1f;
+    cpu.x[o.rd as usize] = (cpu.x[o.rs1 as usize] as u32).rotate_right(shamt) as i32 as i64;
+    Ok(())
+}
+
+/// [roriw rd,rs1,shamt]
+fn roriw(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    match check_rv64(cpu, word) {
         Ok(()) => {}
         Err(e) => return Err(e),
     };
-    cpu.x[o.rd as usize] = t;
+    let o = parse_type_i(word);
+    let shamt = (word >> 20) & 0x1f;
+    cpu.x[o.rd as usize] = (cpu.x[o.rs1 as usize] as u32).rotate_right(shamt) as i32 as i64;
     Ok(())
 }
 
-/// [amomax.d rd,rs2,(rs1)]
-fn amomax_d(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+/// [orc.b rd,rs1]
+/// Each byte of the result is all ones if the byte of rs1 is non-zero, otherwise zero.
+fn orc_b(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
     let o = parse_type_r(word);
-    let t = match cpu.mmu.read64(cpu.x[o.rs1 as usize] as u64) {
-        Ok(data) => data as i64,
+    let data = cpu.x[o.rs1 as usize] as u64;
+    let mut result: u64 = 0;
+    for i in 0..8 {
+        if (data >> (i * 8)) & 0xff != 0 {
+            result |= 0xff << (i * 8);
+        }
+    }
+    cpu.x[o.rd as usize] = signed(cpu, result as i64);
+    Ok(())
+}
+
+/// [rev8 rd,rs1]
+fn rev8(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    let o = parse_type_r(word);
+    cpu.x[o.rd as usize] = match cpu.xlen {
+        Xlen::X64 => (cpu.x[o.rs1 as usize] as u64).swap_bytes() as i64,
+        Xlen::X32 => (cpu.x[o.rs1 as usize] as u32).swap_bytes() as i32 as i64,
+    };
+    Ok(())
+}
+
+/// carry-less multiplication of XLEN-bit values, which produces 2*XLEN-bit product.
+fn carryless_multiply(cpu: &Cpu, rs1: i64, rs2: i64) -> u128 {
+    let a = unsigned(cpu, rs1) as u128;
+    let b = unsigned(cpu, rs2);
+    let mut product = 0;
+    for i in 0..xlen_bits(cpu) {
+        if (b >> i) & 1 == 1 {
+            product ^= a << i;
+        }
+    }
+    product
+}
+
+/// [clmul rd,rs1,rs2]
+fn clmul(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    let o = parse_type_r(word);
+    let product = carryless_multiply(cpu, cpu.x[o.rs1 as usize], cpu.x[o.rs2 as usize]);
+    cpu.x[o.rd as usize] = signed(cpu, product as i64);
+    Ok(())
+}
+
+/// [clmulh rd,rs1,rs2]
+fn clmulh(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    let o = parse_type_r(word);
+    let product = carryless_multiply(cpu, cpu.x[o.rs1 as usize], cpu.x[o.rs2 as usize]);
+    cpu.x[o.rd as usize] = signed(cpu, (product >> xlen_bits(cpu)) as i64);
+    Ok(())
+}
+
+/// [clmulr rd,rs1,rs2]
+fn clmulr(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    let o = parse_type_r(word);
+    let product = carryless_multiply(cpu, cpu.x[o.rs1 as usize], cpu.x[o.rs2 as usize]);
+    cpu.x[o.rd as usize] = signed(cpu, (product >> (xlen_bits(cpu) - 1)) as i64);
+    Ok(())
+}
+
+/// [bclr rd,rs1,rs2]
+fn bclr(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    let o = parse_type_r(word);
+    let index = cpu.x[o.rs2 as usize] as u32 & (xlen_bits(cpu) - 1);
+    cpu.x[o.rd as usize] = signed(cpu, cpu.x[o.rs1 as usize] & !(1 << index));
+    Ok(())
+}
+
+/// [bclri rd,rs1,shamt]
+fn bclri(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    let o = parse_type_i(word);
+    let index = get_shamt(cpu, word);
+    cpu.x[o.rd as usize] = signed(cpu, cpu.x[o.rs1 as usize] & !(1 << index));
+    Ok(())
+}
+
+/// [bext rd,rs1,rs2]
+fn bext(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    let o = parse_type_r(word);
+    let index = cpu.x[o.rs2 as usize] as u32 & (xlen_bits(cpu) - 1);
+    cpu.x[o.rd as usize] = (cpu.x[o.rs1 as usize] >> index) & 1;
+    Ok(())
+}
+
+/// [bexti rd,rs1,shamt]
+fn bexti(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    let o = parse_type_i(word);
+    let index = get_shamt(cpu, word);
+    cpu.x[o.rd as usize] = (cpu.x[o.rs1 as usize] >> index) & 1;
+    Ok(())
+}
+
+/// [binv rd,rs1,rs2]
+fn binv(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    let o = parse_type_r(word);
+    let index = cpu.x[o.rs2 as usize] as u32 & (xlen_bits(cpu) - 1);
+    cpu.x[o.rd as usize] = signed(cpu, cpu.x[o.rs1 as usize] ^ (1 << index));
+    Ok(())
+}
+
+/// [binvi rd,rs1,shamt]
+fn binvi(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    let o = parse_type_i(word);
+    let index = get_shamt(cpu, word);
+    cpu.x[o.rd as usize] = signed(cpu, cpu.x[o.rs1 as usize] ^ (1 << index));
+    Ok(())
+}
+
+/// [bset rd,rs1,rs2]
+fn bset(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    let o = parse_type_r(word);
+    let index = cpu.x[o.rs2 as usize] as u32 & (xlen_bits(cpu) - 1);
+    cpu.x[o.rd as usize] = signed(cpu, cpu.x[o.rs1 as usize] | (1 << index));
+    Ok(())
+}
+
+/// [bseti rd,rs1,shamt]
+fn bseti(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    let o = parse_type_i(word);
+    let index = get_shamt(cpu, word);
+    cpu.x[o.rd as usize] = signed(cpu, cpu.x[o.rs1 as usize] | (1 << index));
+    Ok(())
+}
+
+//==============================================================================
+// Specifying Ordering of Atomic Instructions (RV32A/RV64A)
+//==============================================================================
+
+/// [lr.w rd,rs1]
+fn lr_w(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    let o = parse_type_r(word);
+    let addr = cpu.x[o.rs1 as usize] as u64;
+    match cpu
+        .mmu
+        .check_atomic_alignment(addr, 4, Exception::LoadAddressMisaligned)
+    {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    let data = match cpu.mmu.read32(addr) {
+        Ok(d) => d as i32 as i64,
+        Err(e) => return Err(e),
+    };
+    cpu.x[o.rd as usize] = data;
+    cpu.mmu.reserve_address(addr, cpu.cycle);
+    Ok(())
+}
+
+/// [sc.w rd,rs1,rs2]
+/// sc invalidates the reservation of the hart regardless of success or failure.
+fn sc_w(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    let o = parse_type_r(word);
+    let addr = cpu.x[o.rs1 as usize] as u64;
+    match cpu
+        .mmu
+        .check_atomic_alignment(addr, 4, Exception::StoreAddressMisaligned)
+    {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    let data = cpu.x[o.rs2 as usize] as u32;
+    let reserved = cpu.mmu.is_address_reserved(addr, cpu.cycle);
+    cpu.mmu.cancel_reservation();
+    cpu.x[o.rd as usize] = match reserved {
+        true => match cpu.mmu.write32(addr, data) {
+            Ok(()) => 0,
+            Err(e) => return Err(e),
+        },
+        false => 1,
+    };
+    Ok(())
+}
+
+/// [amoswap.w rd,rs2,(rs1)]
+fn amoswap_w(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    let o = parse_type_r(word);
+    match cpu.mmu.check_amo_address(cpu.x[o.rs1 as usize] as u64, 4) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    let t = match cpu.mmu.read32(cpu.x[o.rs1 as usize] as u64) {
+        Ok(data) => data,
+        Err(e) => return Err(e),
+    };
+    let x = cpu.x[o.rs2 as usize] as u32;
+    match cpu.mmu.write32(cpu.x[o.rs1 as usize] as u64, x) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    cpu.x[o.rd as usize] = t as i32 as i64;
+    Ok(())
+}
+
+/// [amoadd.w rd,rs2,(rs1)]
+fn amoadd_w(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    let o = parse_type_r(word);
+    match cpu.mmu.check_amo_address(cpu.x[o.rs1 as usize] as u64, 4) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    let t = match cpu.mmu.read32(cpu.x[o.rs1 as usize] as u64) {
+        Ok(data) => data as i32 as i64,
+        Err(e) => return Err(e),
+    };
+    let x = cpu.x[o.rs2 as usize];
+    match cpu.mmu.write32(
+        cpu.x[o.rs1 as usize] as u64,
+        signed(cpu, t.wrapping_add(x)) as u32,
+    ) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    cpu.x[o.rd as usize] = t;
+    Ok(())
+}
+
+/// [amoxor.w rd,rs2,(rs1)]
+fn amoxor_w(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    let o = parse_type_r(word);
+    match cpu.mmu.check_amo_address(cpu.x[o.rs1 as usize] as u64, 4) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    let t = match cpu.mmu.read32(cpu.x[o.rs1 as usize] as u64) {
+        Ok(data) => data as i32 as i64,
         Err(e) => return Err(e),
     };
     let x = cpu.x[o.rs2 as usize];
     match cpu
         .mmu
-        .write64(cpu.x[o.rs1 as usize] as u64, std::cmp::max(t, x) as u64)
+        .write32(cpu.x[o.rs1 as usize] as u64, signed(cpu, t ^ x) as u32)
     {
         Ok(()) => {}
         Err(e) => return Err(e),
@@ -2455,55 +4154,1059 @@ fn amomax_d(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
     Ok(())
 }
 
-/// [amominu.d rd,rs2,(rs1)]
-fn amominu_d(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+/// [amoand.w rd,rs2,(rs1)]
+fn amoand_w(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
     let o = parse_type_r(word);
-    let t = match cpu.mmu.read64(cpu.x[o.rs1 as usize] as u64) {
-        Ok(data) => data as u64,
+    match cpu.mmu.check_amo_address(cpu.x[o.rs1 as usize] as u64, 4) {
+        Ok(()) => {}
         Err(e) => return Err(e),
     };
-    let x = cpu.x[o.rs2 as usize] as u64;
+    let t = match cpu.mmu.read32(cpu.x[o.rs1 as usize] as u64) {
+        Ok(data) => data as i32 as i64,
+        Err(e) => return Err(e),
+    };
+    let x = cpu.x[o.rs2 as usize];
     match cpu
         .mmu
-        .write64(cpu.x[o.rs1 as usize] as u64, std::cmp::min(t, x))
+        .write32(cpu.x[o.rs1 as usize] as u64, signed(cpu, t & x) as u32)
     {
         Ok(()) => {}
         Err(e) => return Err(e),
     };
-    cpu.x[o.rd as usize] = t as i64;
+    cpu.x[o.rd as usize] = t;
     Ok(())
 }
 
-/// [amomaxu.d rd,rs2,(rs1)]
-fn amomaxu_d(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+/// [amoor.w rd,rs2,(rs1)]
+fn amoor_w(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
     let o = parse_type_r(word);
-    let t = match cpu.mmu.read64(cpu.x[o.rs1 as usize] as u64) {
-        Ok(data) => data as u64,
+    match cpu.mmu.check_amo_address(cpu.x[o.rs1 as usize] as u64, 4) {
+        Ok(()) => {}
         Err(e) => return Err(e),
     };
-    let x = cpu.x[o.rs2 as usize] as u64;
+    let t = match cpu.mmu.read32(cpu.x[o.rs1 as usize] as u64) {
+        Ok(data) => data as i32 as i64,
+        Err(e) => return Err(e),
+    };
+    let x = cpu.x[o.rs2 as usize];
     match cpu
         .mmu
-        .write64(cpu.x[o.rs1 as usize] as u64, std::cmp::max(t, x))
+        .write32(cpu.x[o.rs1 as usize] as u64, signed(cpu, t | x) as u32)
     {
         Ok(()) => {}
         Err(e) => return Err(e),
     };
-    cpu.x[o.rd as usize] = t as i64;
+    cpu.x[o.rd as usize] = t;
     Ok(())
 }
 
-//==============================================================================
-// Single-Precision Load and Store Instructions (RV32F/RV64D)
-//==============================================================================
+/// [amomin.w rd,rs2,(rs1)]
+fn amomin_w(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    let o = parse_type_r(word);
+    match cpu.mmu.check_amo_address(cpu.x[o.rs1 as usize] as u64, 4) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    let t = match cpu.mmu.read32(cpu.x[o.rs1 as usize] as u64) {
+        Ok(data) => data as i32 as i64,
+        Err(e) => return Err(e),
+    };
+    let x = cpu.x[o.rs2 as usize];
+    match cpu
+        .mmu
+        .write32(cpu.x[o.rs1 as usize] as u64, std::cmp::min(t, x) as u32)
+    {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    cpu.x[o.rd as usize] = t;
+    Ok(())
+}
 
-/// [fmv.w.x rd,rs1]
-/// FMV.W.X moves the single-precision value encoded in IEEE 754-2008 standard encoding
-/// from the lower 32 bits of integer register rs1 to the floating-point register rd.
-/// The bits are not modified in the transfer, and in particular, the payloads of
-/// non-canonical NaNs are preserved.
-fn fmv_w_x(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+/// [amomax.w rd,rs2,(rs1)]
+fn amomax_w(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    let o = parse_type_r(word);
+    match cpu.mmu.check_amo_address(cpu.x[o.rs1 as usize] as u64, 4) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    let t = match cpu.mmu.read32(cpu.x[o.rs1 as usize] as u64) {
+        Ok(data) => data as i32 as i64,
+        Err(e) => return Err(e),
+    };
+    let x = cpu.x[o.rs2 as usize];
+    match cpu
+        .mmu
+        .write32(cpu.x[o.rs1 as usize] as u64, std::cmp::max(t, x) as u32)
+    {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    cpu.x[o.rd as usize] = t;
+    Ok(())
+}
+
+/// [amominu.w rd,rs2,(rs1)]
+fn amominu_w(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
     let o = parse_type_r(word);
-    cpu.f[o.rd as usize] = f64::from_bits(cpu.x[o.rs1 as usize] as u32 as u64);
+    match cpu.mmu.check_amo_address(cpu.x[o.rs1 as usize] as u64, 4) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    let t = match cpu.mmu.read32(cpu.x[o.rs1 as usize] as u64) {
+        Ok(data) => data,
+        Err(e) => return Err(e),
+    };
+    let x = cpu.x[o.rs2 as usize] as u32;
+    match cpu
+        .mmu
+        .write32(cpu.x[o.rs1 as usize] as u64, std::cmp::min(t, x))
+    {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    cpu.x[o.rd as usize] = t as i32 as i64;
     Ok(())
 }
+
+/// [amomaxu.w rd,rs2,(rs1)]
+fn amomaxu_w(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    let o = parse_type_r(word);
+    match cpu.mmu.check_amo_address(cpu.x[o.rs1 as usize] as u64, 4) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    let t = match cpu.mmu.read32(cpu.x[o.rs1 as usize] as u64) {
+        Ok(data) => data,
+        Err(e) => return Err(e),
+    };
+    let x = cpu.x[o.rs2 as usize] as u32;
+    match cpu
+        .mmu
+        .write32(cpu.x[o.rs1 as usize] as u64, std::cmp::max(t, x))
+    {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    cpu.x[o.rd as usize] = t as i32 as i64;
+    Ok(())
+}
+
+/// [lr.d rd,rs1]
+fn lr_d(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    let o = parse_type_r(word);
+    let addr = cpu.x[o.rs1 as usize] as u64;
+    match cpu
+        .mmu
+        .check_atomic_alignment(addr, 8, Exception::LoadAddressMisaligned)
+    {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    let data = match cpu.mmu.read64(addr) {
+        Ok(d) => d as i64,
+        Err(e) => return Err(e),
+    };
+    cpu.x[o.rd as usize] = data;
+    cpu.mmu.reserve_address(addr, cpu.cycle);
+    Ok(())
+}
+
+/// [sc.d rd,rs1,rs2]
+/// sc invalidates the reservation of the hart regardless of success or failure.
+fn sc_d(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    let o = parse_type_r(word);
+    let addr = cpu.x[o.rs1 as usize] as u64;
+    match cpu
+        .mmu
+        .check_atomic_alignment(addr, 8, Exception::StoreAddressMisaligned)
+    {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    let data = cpu.x[o.rs2 as usize] as u64;
+    let reserved = cpu.mmu.is_address_reserved(addr, cpu.cycle);
+    cpu.mmu.cancel_reservation();
+    cpu.x[o.rd as usize] = match reserved {
+        true => match cpu.mmu.write64(addr, data) {
+            Ok(()) => 0,
+            Err(e) => return Err(e),
+        },
+        false => 1,
+    };
+    Ok(())
+}
+
+/// [amoswap.d rd,rs2,(rs1)]
+fn amoswap_d(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    let o = parse_type_r(word);
+    match cpu.mmu.check_amo_address(cpu.x[o.rs1 as usize] as u64, 8) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    let t = match cpu.mmu.read64(cpu.x[o.rs1 as usize] as u64) {
+        Ok(data) => data,
+        Err(e) => return Err(e),
+    };
+    let x = cpu.x[o.rs2 as usize] as u64;
+    match cpu.mmu.write64(cpu.x[o.rs1 as usize] as u64, x) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    cpu.x[o.rd as usize] = t as i64;
+    Ok(())
+}
+
+/// [amoadd.d rd,rs2,(rs1)]
+fn amoadd_d(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    let o = parse_type_r(word);
+    match cpu.mmu.check_amo_address(cpu.x[o.rs1 as usize] as u64, 8) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    let t = match cpu.mmu.read64(cpu.x[o.rs1 as usize] as u64) {
+        Ok(data) => data as i64,
+        Err(e) => return Err(e),
+    };
+    let x = cpu.x[o.rs2 as usize];
+    match cpu
+        .mmu
+        .write64(cpu.x[o.rs1 as usize] as u64, t.wrapping_add(x) as u64)
+    {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    cpu.x[o.rd as usize] = t;
+    Ok(())
+}
+
+/// [amoxor.d rd,rs2,(rs1)]
+fn amoxor_d(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    let o = parse_type_r(word);
+    match cpu.mmu.check_amo_address(cpu.x[o.rs1 as usize] as u64, 8) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    let t = match cpu.mmu.read64(cpu.x[o.rs1 as usize] as u64) {
+        Ok(data) => data as i64,
+        Err(e) => return Err(e),
+    };
+    let x = cpu.x[o.rs2 as usize];
+    match cpu
+        .mmu
+        .write64(cpu.x[o.rs1 as usize] as u64, (t ^ x) as u64)
+    {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    cpu.x[o.rd as usize] = t;
+    Ok(())
+}
+
+/// [amoand.d rd,rs2,(rs1)]
+fn amoand_d(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    let o = parse_type_r(word);
+    match cpu.mmu.check_amo_address(cpu.x[o.rs1 as usize] as u64, 8) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    let t = match cpu.mmu.read64(cpu.x[o.rs1 as usize] as u64) {
+        Ok(data) => data as i64,
+        Err(e) => return Err(e),
+    };
+    let x = cpu.x[o.rs2 as usize];
+    match cpu
+        .mmu
+        .write64(cpu.x[o.rs1 as usize] as u64, (t & x) as u64)
+    {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    cpu.x[o.rd as usize] = t;
+    Ok(())
+}
+
+/// [amoor.d rd,rs2,(rs1)]
+fn amoor_d(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    let o = parse_type_r(word);
+    match cpu.mmu.check_amo_address(cpu.x[o.rs1 as usize] as u64, 8) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    let t = match cpu.mmu.read64(cpu.x[o.rs1 as usize] as u64) {
+        Ok(data) => data as i64,
+        Err(e) => return Err(e),
+    };
+    let x = cpu.x[o.rs2 as usize];
+    match cpu
+        .mmu
+        .write64(cpu.x[o.rs1 as usize] as u64, (t | x) as u64)
+    {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    cpu.x[o.rd as usize] = t;
+    Ok(())
+}
+
+/// [amomin.d rd,rs2,(rs1)]
+fn amomin_d(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    let o = parse_type_r(word);
+    match cpu.mmu.check_amo_address(cpu.x[o.rs1 as usize] as u64, 8) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    let t = match cpu.mmu.read64(cpu.x[o.rs1 as usize] as u64) {
+        Ok(data) => data as i64,
+        Err(e) => return Err(e),
+    };
+    let x = cpu.x[o.rs2 as usize];
+    match cpu
+        .mmu
+        .write64(cpu.x[o.rs1 as usize] as u64, std::cmp::min(t, x) as u64)
+    {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    cpu.x[o.rd as usize] = t;
+    Ok(())
+}
+
+/// [amomax.d rd,rs2,(rs1)]
+fn amomax_d(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    let o = parse_type_r(word);
+    match cpu.mmu.check_amo_address(cpu.x[o.rs1 as usize] as u64, 8) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    let t = match cpu.mmu.read64(cpu.x[o.rs1 as usize] as u64) {
+        Ok(data) => data as i64,
+        Err(e) => return Err(e),
+    };
+    let x = cpu.x[o.rs2 as usize];
+    match cpu
+        .mmu
+        .write64(cpu.x[o.rs1 as usize] as u64, std::cmp::max(t, x) as u64)
+    {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    cpu.x[o.rd as usize] = t;
+    Ok(())
+}
+
+/// [amominu.d rd,rs2,(rs1)]
+fn amominu_d(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    let o = parse_type_r(word);
+    match cpu.mmu.check_amo_address(cpu.x[o.rs1 as usize] as u64, 8) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    let t = match cpu.mmu.read64(cpu.x[o.rs1 as usize] as u64) {
+        Ok(data) => data as u64,
+        Err(e) => return Err(e),
+    };
+    let x = cpu.x[o.rs2 as usize] as u64;
+    match cpu
+        .mmu
+        .write64(cpu.x[o.rs1 as usize] as u64, std::cmp::min(t, x))
+    {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    cpu.x[o.rd as usize] = t as i64;
+    Ok(())
+}
+
+/// [amomaxu.d rd,rs2,(rs1)]
+fn amomaxu_d(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    let o = parse_type_r(word);
+    match cpu.mmu.check_amo_address(cpu.x[o.rs1 as usize] as u64, 8) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    let t = match cpu.mmu.read64(cpu.x[o.rs1 as usize] as u64) {
+        Ok(data) => data as u64,
+        Err(e) => return Err(e),
+    };
+    let x = cpu.x[o.rs2 as usize] as u64;
+    match cpu
+        .mmu
+        .write64(cpu.x[o.rs1 as usize] as u64, std::cmp::max(t, x))
+    {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    cpu.x[o.rd as usize] = t as i64;
+    Ok(())
+}
+
+//==============================================================================
+// Floating-Point Computational Instructions (RV32F/RV64F, RV32D/RV64D)
+//==============================================================================
+// Floating-point operations that can raise an exception accrue the flags to fflags,
+// and the rounding mode is selected by the rm field or the dynamic rounding mode in frm.
+
+fn fp_arithmetic(
+    cpu: &mut Cpu,
+    _addr: u64,
+    word: u32,
+    fmt: &FloatFormat,
+    op: fn(&FloatFormat, u64, u64, RoundingMode, &mut u64) -> u64,
+) -> Result<(), Trap> {
+    match check_fp_enabled(cpu, word) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    let rm = match get_rounding_mode(cpu, word) {
+        Ok(rm) => rm,
+        Err(e) => return Err(e),
+    };
+    let o = parse_type_r(word);
+    let mut flags = 0;
+    let data = op(
+        fmt,
+        read_fp(cpu, fmt, o.rs1),
+        read_fp(cpu, fmt, o.rs2),
+        rm,
+        &mut flags,
+    );
+    accrue_fflags(cpu, flags);
+    write_fp(cpu, fmt, o.rd, data);
+    Ok(())
+}
+
+fn fp_sqrt(cpu: &mut Cpu, _addr: u64, word: u32, fmt: &FloatFormat) -> Result<(), Trap> {
+    match check_fp_enabled(cpu, word) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    let rm = match get_rounding_mode(cpu, word) {
+        Ok(rm) => rm,
+        Err(e) => return Err(e),
+    };
+    let o = parse_type_r(word);
+    let mut flags = 0;
+    let data = fpu::sqrt(fmt, read_fp(cpu, fmt, o.rs1), rm, &mut flags);
+    accrue_fflags(cpu, flags);
+    write_fp(cpu, fmt, o.rd, data);
+    Ok(())
+}
+
+/// If only one operand is a NaN, the result is the non-NaN operand.
+/// If both operands are NaNs, the result is the canonical NaN.
+fn fp_min_max(
+    cpu: &mut Cpu,
+    _addr: u64,
+    word: u32,
+    fmt: &FloatFormat,
+    is_max: bool,
+) -> Result<(), Trap> {
+    match check_fp_enabled(cpu, word) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    let o = parse_type_r(word);
+    let mut flags = 0;
+    let (a, b) = (read_fp(cpu, fmt, o.rs1), read_fp(cpu, fmt, o.rs2));
+    let data = match is_max {
+        true => fpu::max(fmt, a, b, &mut flags),
+        false => fpu::min(fmt, a, b, &mut flags),
+    };
+    accrue_fflags(cpu, flags);
+    write_fp(cpu, fmt, o.rd, data);
+    Ok(())
+}
+
+fn fp_fused_mul_add(
+    cpu: &mut Cpu,
+    _addr: u64,
+    word: u32,
+    fmt: &FloatFormat,
+    negate_product: bool,
+    negate_addend: bool,
+) -> Result<(), Trap> {
+    match check_fp_enabled(cpu, word) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    let rm = match get_rounding_mode(cpu, word) {
+        Ok(rm) => rm,
+        Err(e) => return Err(e),
+    };
+    let o = parse_type_r4(word);
+    let (mut a, mut c) = (read_fp(cpu, fmt, o.rs1), read_fp(cpu, fmt, o.rs3));
+    if negate_product {
+        a = fpu::negate(fmt, a);
+    }
+    if negate_addend {
+        c = fpu::negate(fmt, c);
+    }
+    let mut flags = 0;
+    let data = fpu::fused_mul_add(fmt, a, read_fp(cpu, fmt, o.rs2), c, rm, &mut flags);
+    accrue_fflags(cpu, flags);
+    write_fp(cpu, fmt, o.rd, data);
+    Ok(())
+}
+
+/// [fadd.s rd,rs1,rs2]
+fn fadd_s(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    fp_arithmetic(cpu, addr, word, &fpu::BINARY32, fpu::add)
+}
+
+/// [fsub.s rd,rs1,rs2]
+fn fsub_s(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    fp_arithmetic(cpu, addr, word, &fpu::BINARY32, fpu::sub)
+}
+
+/// [fmul.s rd,rs1,rs2]
+fn fmul_s(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    fp_arithmetic(cpu, addr, word, &fpu::BINARY32, fpu::mul)
+}
+
+/// [fdiv.s rd,rs1,rs2]
+fn fdiv_s(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    fp_arithmetic(cpu, addr, word, &fpu::BINARY32, fpu::div)
+}
+
+/// [fsqrt.s rd,rs1]
+fn fsqrt_s(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    fp_sqrt(cpu, addr, word, &fpu::BINARY32)
+}
+
+/// [fmin.s rd,rs1,rs2]
+fn fmin_s(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    fp_min_max(cpu, addr, word, &fpu::BINARY32, false)
+}
+
+/// [fmax.s rd,rs1,rs2]
+fn fmax_s(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    fp_min_max(cpu, addr, word, &fpu::BINARY32, true)
+}
+
+/// [fmadd.s rd,rs1,rs2,rs3]
+/// FMADD.S multiplies the values in rs1 and rs2, adds the value in rs3,
+/// and writes the final result to rd. (rs1×rs2)+rs3
+fn fmadd_s(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    fp_fused_mul_add(cpu, addr, word, &fpu::BINARY32, false, false)
+}
+
+/// [fmsub.s rd,rs1,rs2,rs3]
+/// FMSUB.S multiplies the values in rs1 and rs2, subtracts the value in rs3,
+/// and writes the final result to rd. (rs1×rs2)-rs3
+fn fmsub_s(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    fp_fused_mul_add(cpu, addr, word, &fpu::BINARY32, false, true)
+}
+
+/// [fnmsub.s rd,rs1,rs2,rs3]
+/// FNMSUB.S multiplies the values in rs1 and rs2, negates the product, adds the value
+/// in rs3, and writes the final result to rd. -(rs1×rs2)+rs3
+fn fnmsub_s(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    fp_fused_mul_add(cpu, addr, word, &fpu::BINARY32, true, false)
+}
+
+/// [fnmadd.s rd,rs1,rs2,rs3]
+/// FNMADD.S multiplies the values in rs1 and rs2, negates the product, subtracts the value
+/// in rs3, and writes the final result to rd. -(rs1×rs2)-rs3
+fn fnmadd_s(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    fp_fused_mul_add(cpu, addr, word, &fpu::BINARY32, true, true)
+}
+
+/// [fadd.d rd,rs1,rs2]
+fn fadd_d(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    fp_arithmetic(cpu, addr, word, &fpu::BINARY64, fpu::add)
+}
+
+/// [fsub.d rd,rs1,rs2]
+fn fsub_d(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    fp_arithmetic(cpu, addr, word, &fpu::BINARY64, fpu::sub)
+}
+
+/// [fmul.d rd,rs1,rs2]
+fn fmul_d(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    fp_arithmetic(cpu, addr, word, &fpu::BINARY64, fpu::mul)
+}
+
+/// [fdiv.d rd,rs1,rs2]
+fn fdiv_d(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    fp_arithmetic(cpu, addr, word, &fpu::BINARY64, fpu::div)
+}
+
+/// [fsqrt.d rd,rs1]
+fn fsqrt_d(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    fp_sqrt(cpu, addr, word, &fpu::BINARY64)
+}
+
+/// [fmin.d rd,rs1,rs2]
+fn fmin_d(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    fp_min_max(cpu, addr, word, &fpu::BINARY64, false)
+}
+
+/// [fmax.d rd,rs1,rs2]
+fn fmax_d(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    fp_min_max(cpu, addr, word, &fpu::BINARY64, true)
+}
+
+/// [fmadd.d rd,rs1,rs2,rs3]
+/// FMADD.D multiplies the values in rs1 and rs2, adds the value in rs3,
+/// and writes the final result to rd. (rs1×rs2)+rs3
+fn fmadd_d(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    fp_fused_mul_add(cpu, addr, word, &fpu::BINARY64, false, false)
+}
+
+/// [fmsub.d rd,rs1,rs2,rs3]
+/// FMSUB.D multiplies the values in rs1 and rs2, subtracts the value in rs3,
+/// and writes the final result to rd. (rs1×rs2)-rs3
+fn fmsub_d(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    fp_fused_mul_add(cpu, addr, word, &fpu::BINARY64, false, true)
+}
+
+/// [fnmsub.d rd,rs1,rs2,rs3]
+/// FNMSUB.D multiplies the values in rs1 and rs2, negates the product, adds the value
+/// in rs3, and writes the final result to rd. -(rs1×rs2)+rs3
+fn fnmsub_d(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    fp_fused_mul_add(cpu, addr, word, &fpu::BINARY64, true, false)
+}
+
+/// [fnmadd.d rd,rs1,rs2,rs3]
+/// FNMADD.D multiplies the values in rs1 and rs2, negates the product, subtracts the value
+/// in rs3, and writes the final result to rd. -(rs1×rs2)-rs3
+fn fnmadd_d(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    fp_fused_mul_add(cpu, addr, word, &fpu::BINARY64, true, true)
+}
+
+//==============================================================================
+// Floating-Point Conversion and Move Instructions (RV32F/RV64F, RV32D/RV64D)
+//==============================================================================
+
+fn fp_to_int(
+    cpu: &mut Cpu,
+    _addr: u64,
+    word: u32,
+    fmt: &FloatFormat,
+    signed: bool,
+    width: u32,
+) -> Result<(), Trap> {
+    match check_fp_enabled(cpu, word) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    let rm = match get_rounding_mode(cpu, word) {
+        Ok(rm) => rm,
+        Err(e) => return Err(e),
+    };
+    let o = parse_type_r(word);
+    let mut flags = 0;
+    let data = fpu::to_int(fmt, read_fp(cpu, fmt, o.rs1), signed, width, rm, &mut flags);
+    accrue_fflags(cpu, flags);
+    // 32-bit integer results are sign-extended, even for the unsigned conversions.
+    cpu.x[o.rd as usize] = match width {
+        32 => data as i32 as i64,
+        _ => data as i64,
+    };
+    Ok(())
+}
+
+fn int_to_fp(
+    cpu: &mut Cpu,
+    _addr: u64,
+    word: u32,
+    fmt: &FloatFormat,
+    signed: bool,
+    width: u32,
+) -> Result<(), Trap> {
+    match check_fp_enabled(cpu, word) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    let rm = match get_rounding_mode(cpu, word) {
+        Ok(rm) => rm,
+        Err(e) => return Err(e),
+    };
+    let o = parse_type_r(word);
+    let mut flags = 0;
+    let data = fpu::from_int(
+        fmt,
+        cpu.x[o.rs1 as usize] as u64,
+        signed,
+        width,
+        rm,
+        &mut flags,
+    );
+    accrue_fflags(cpu, flags);
+    write_fp(cpu, fmt, o.rd, data);
+    Ok(())
+}
+
+fn fp_sign_inject(
+    cpu: &mut Cpu,
+    _addr: u64,
+    word: u32,
+    fmt: &FloatFormat,
+    mode: u32,
+) -> Result<(), Trap> {
+    match check_fp_enabled(cpu, word) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    let o = parse_type_r(word);
+    let data = fpu::sign_inject(
+        fmt,
+        read_fp(cpu, fmt, o.rs1),
+        read_fp(cpu, fmt, o.rs2),
+        mode,
+    );
+    write_fp(cpu, fmt, o.rd, data);
+    Ok(())
+}
+
+/// [fcvt.w.s rd,rs1]
+fn fcvt_w_s(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    fp_to_int(cpu, addr, word, &fpu::BINARY32, true, 32)
+}
+
+/// [fcvt.wu.s rd,rs1]
+fn fcvt_wu_s(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    fp_to_int(cpu, addr, word, &fpu::BINARY32, false, 32)
+}
+
+/// [fcvt.l.s rd,rs1]
+fn fcvt_l_s(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    match check_rv64(cpu, word) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    fp_to_int(cpu, addr, word, &fpu::BINARY32, true, 64)
+}
+
+/// [fcvt.lu.s rd,rs1]
+fn fcvt_lu_s(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    match check_rv64(cpu, word) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    fp_to_int(cpu, addr, word, &fpu::BINARY32, false, 64)
+}
+
+/// [fcvt.s.w rd,rs1]
+fn fcvt_s_w(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    int_to_fp(cpu, addr, word, &fpu::BINARY32, true, 32)
+}
+
+/// [fcvt.s.wu rd,rs1]
+fn fcvt_s_wu(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    int_to_fp(cpu, addr, word, &fpu::BINARY32, false, 32)
+}
+
+/// [fcvt.s.l rd,rs1]
+fn fcvt_s_l(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    match check_rv64(cpu, word) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    int_to_fp(cpu, addr, word, &fpu::BINARY32, true, 64)
+}
+
+/// [fcvt.s.lu rd,rs1]
+fn fcvt_s_lu(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    match check_rv64(cpu, word) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    int_to_fp(cpu, addr, word, &fpu::BINARY32, false, 64)
+}
+
+/// [fsgnj.s rd,rs1,rs2]
+/// The result's sign bit is rs2's sign bit.
+fn fsgnj_s(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    fp_sign_inject(cpu, addr, word, &fpu::BINARY32, 0)
+}
+
+/// [fsgnjn.s rd,rs1,rs2]
+/// The result's sign bit is the opposite of rs2's sign bit.
+fn fsgnjn_s(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    fp_sign_inject(cpu, addr, word, &fpu::BINARY32, 1)
+}
+
+/// [fsgnjx.s rd,rs1,rs2]
+/// The result's sign bit is the XOR of the sign bits of rs1 and rs2.
+fn fsgnjx_s(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    fp_sign_inject(cpu, addr, word, &fpu::BINARY32, 2)
+}
+
+/// [fmv.x.w rd,rs1]
+/// FMV.X.W moves the single-precision value in floating-point register rs1 represented
+/// in IEEE 754-2008 encoding to the lower 32 bits of integer register rd.
+/// The higher 32 bits of the destination register are filled with copies of the sign bit.
+fn fmv_x_w(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    match check_fp_enabled(cpu, word) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    let o = parse_type_r(word);
+    cpu.x[o.rd as usize] = cpu.f[o.rs1 as usize] as i32 as i64;
+    Ok(())
+}
+
+/// [fmv.w.x rd,rs1]
+/// FMV.W.X moves the single-precision value encoded in IEEE 754-2008 standard encoding
+/// from the lower 32 bits of integer register rs1 to the floating-point register rd.
+/// The bits are not modified in the transfer, and in particular, the payloads of
+/// non-canonical NaNs are preserved.
+fn fmv_w_x(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    match check_fp_enabled(cpu, word) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    let o = parse_type_r(word);
+    write_f32(cpu, o.rd, cpu.x[o.rs1 as usize] as u64);
+    Ok(())
+}
+
+/// [fcvt.w.d rd,rs1]
+fn fcvt_w_d(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    fp_to_int(cpu, addr, word, &fpu::BINARY64, true, 32)
+}
+
+/// [fcvt.wu.d rd,rs1]
+fn fcvt_wu_d(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    fp_to_int(cpu, addr, word, &fpu::BINARY64, false, 32)
+}
+
+/// [fcvt.l.d rd,rs1]
+fn fcvt_l_d(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    match check_rv64(cpu, word) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    fp_to_int(cpu, addr, word, &fpu::BINARY64, true, 64)
+}
+
+/// [fcvt.lu.d rd,rs1]
+fn fcvt_lu_d(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    match check_rv64(cpu, word) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    fp_to_int(cpu, addr, word, &fpu::BINARY64, false, 64)
+}
+
+/// [fcvt.d.w rd,rs1]
+fn fcvt_d_w(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    int_to_fp(cpu, addr, word, &fpu::BINARY64, true, 32)
+}
+
+/// [fcvt.d.wu rd,rs1]
+fn fcvt_d_wu(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    int_to_fp(cpu, addr, word, &fpu::BINARY64, false, 32)
+}
+
+/// [fcvt.d.l rd,rs1]
+fn fcvt_d_l(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    match check_rv64(cpu, word) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    int_to_fp(cpu, addr, word, &fpu::BINARY64, true, 64)
+}
+
+/// [fcvt.d.lu rd,rs1]
+fn fcvt_d_lu(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    match check_rv64(cpu, word) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    int_to_fp(cpu, addr, word, &fpu::BINARY64, false, 64)
+}
+
+/// [fsgnj.d rd,rs1,rs2]
+/// The result's sign bit is rs2's sign bit.
+fn fsgnj_d(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    fp_sign_inject(cpu, addr, word, &fpu::BINARY64, 0)
+}
+
+/// [fsgnjn.d rd,rs1,rs2]
+/// The result's sign bit is the opposite of rs2's sign bit.
+fn fsgnjn_d(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    fp_sign_inject(cpu, addr, word, &fpu::BINARY64, 1)
+}
+
+/// [fsgnjx.d rd,rs1,rs2]
+/// The result's sign bit is the XOR of the sign bits of rs1 and rs2.
+fn fsgnjx_d(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    fp_sign_inject(cpu, addr, word, &fpu::BINARY64, 2)
+}
+
+/// [fcvt.s.d rd,rs1]
+/// FCVT.S.D converts double-precision float to single-precision float, rounding according
+/// to the dynamic rounding mode.
+fn fcvt_s_d(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    match check_fp_enabled(cpu, word) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    let rm = match get_rounding_mode(cpu, word) {
+        Ok(rm) => rm,
+        Err(e) => return Err(e),
+    };
+    let o = parse_type_r(word);
+    let mut flags = 0;
+    let data = fpu::convert(
+        &fpu::BINARY64,
+        &fpu::BINARY32,
+        read_f64(cpu, o.rs1),
+        rm,
+        &mut flags,
+    );
+    accrue_fflags(cpu, flags);
+    write_f32(cpu, o.rd, data);
+    Ok(())
+}
+
+/// [fcvt.d.s rd,rs1]
+/// FCVT.D.S converts single-precision float to double-precision float. The conversion is
+/// always exact, but a signaling NaN input still raises the invalid operation exception.
+fn fcvt_d_s(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    match check_fp_enabled(cpu, word) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    let rm = match get_rounding_mode(cpu, word) {
+        Ok(rm) => rm,
+        Err(e) => return Err(e),
+    };
+    let o = parse_type_r(word);
+    let mut flags = 0;
+    let data = fpu::convert(
+        &fpu::BINARY32,
+        &fpu::BINARY64,
+        read_f32(cpu, o.rs1),
+        rm,
+        &mut flags,
+    );
+    accrue_fflags(cpu, flags);
+    write_f64(cpu, o.rd, data);
+    Ok(())
+}
+
+/// [fmv.x.d rd,rs1]
+/// FMV.X.D moves the double-precision value in floating-point register rs1 to a
+/// representation in IEEE 754-2008 standard encoding in integer register rd.
+fn fmv_x_d(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    match check_rv64(cpu, word) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    match check_fp_enabled(cpu, word) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    let o = parse_type_r(word);
+    cpu.x[o.rd as usize] = cpu.f[o.rs1 as usize] as i64;
+    Ok(())
+}
+
+/// [fmv.d.x rd,rs1]
+/// FMV.D.X moves the double-precision value encoded in IEEE 754-2008 standard encoding
+/// from the integer register rs1 to the floating-point register rd.
+fn fmv_d_x(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    match check_rv64(cpu, word) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    match check_fp_enabled(cpu, word) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    let o = parse_type_r(word);
+    write_f64(cpu, o.rd, cpu.x[o.rs1 as usize] as u64);
+    Ok(())
+}
+
+//==============================================================================
+// Floating-Point Compare and Classify Instructions (RV32F/RV64F, RV32D/RV64D)
+//==============================================================================
+
+fn fp_compare(
+    cpu: &mut Cpu,
+    _addr: u64,
+    word: u32,
+    fmt: &FloatFormat,
+    op: fn(&FloatFormat, u64, u64, &mut u64) -> bool,
+) -> Result<(), Trap> {
+    match check_fp_enabled(cpu, word) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    let o = parse_type_r(word);
+    let mut flags = 0;
+    let result = op(
+        fmt,
+        read_fp(cpu, fmt, o.rs1),
+        read_fp(cpu, fmt, o.rs2),
+        &mut flags,
+    );
+    accrue_fflags(cpu, flags);
+    cpu.x[o.rd as usize] = result as i64;
+    Ok(())
+}
+
+fn fp_classify(cpu: &mut Cpu, _addr: u64, word: u32, fmt: &FloatFormat) -> Result<(), Trap> {
+    match check_fp_enabled(cpu, word) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    let o = parse_type_r(word);
+    cpu.x[o.rd as usize] = fpu::classify(fmt, read_fp(cpu, fmt, o.rs1)) as i64;
+    Ok(())
+}
+
+/// [feq.s rd,rs1,rs2]
+/// FEQ.S performs a quiet comparison: only signaling NaN inputs cause an Invalid Operation exception.
+fn feq_s(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    fp_compare(cpu, addr, word, &fpu::BINARY32, fpu::eq)
+}
+
+/// [flt.s rd,rs1,rs2]
+/// FLT.S performs a signaling comparison: any NaN input causes an Invalid Operation exception.
+fn flt_s(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    fp_compare(cpu, addr, word, &fpu::BINARY32, fpu::lt)
+}
+
+/// [fle.s rd,rs1,rs2]
+/// FLE.S performs a signaling comparison: any NaN input causes an Invalid Operation exception.
+fn fle_s(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    fp_compare(cpu, addr, word, &fpu::BINARY32, fpu::le)
+}
+
+/// [fclass.s rd,rs1]
+/// The FCLASS.S instruction examines the value in floating-point register rs1 and writes
+/// to integer register rd a 10-bit mask that indicates the class of the floating-point number.
+fn fclass_s(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    fp_classify(cpu, addr, word, &fpu::BINARY32)
+}
+
+/// [feq.d rd,rs1,rs2]
+/// FEQ.D performs a quiet comparison: only signaling NaN inputs cause an Invalid Operation exception.
+fn feq_d(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    fp_compare(cpu, addr, word, &fpu::BINARY64, fpu::eq)
+}
+
+/// [flt.d rd,rs1,rs2]
+/// FLT.D performs a signaling comparison: any NaN input causes an Invalid Operation exception.
+fn flt_d(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    fp_compare(cpu, addr, word, &fpu::BINARY64, fpu::lt)
+}
+
+/// [fle.d rd,rs1,rs2]
+/// FLE.D performs a signaling comparison: any NaN input causes an Invalid Operation exception.
+fn fle_d(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    fp_compare(cpu, addr, word, &fpu::BINARY64, fpu::le)
+}
+
+/// [fclass.d rd,rs1]
+/// The FCLASS.D instruction examines the value in floating-point register rs1 and writes
+/// to integer register rd a 10-bit mask that indicates the class of the floating-point number.
+fn fclass_d(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    fp_classify(cpu, addr, word, &fpu::BINARY64)
+}
@@ -135,11 +135,11 @@ fn opecode_0(cpu: &Cpu, word: u16) -> Result<&'static CompressedInstruction, ()>
     match funct3 {
         3 | 7 => match COMPRESSED_INSTRUCTIONS_GROUP0_SUB.get(&(cpu.xlen.clone() as u8, funct3)) {
             Some(instruction) => Ok(&instruction),
-            None => panic!("Not found instruction!"),
+            None => Err(()),
         },
         _ => match COMPRESSED_INSTRUCTIONS_GROUP0.get(&funct3) {
             Some(instruction) => Ok(&instruction),
-            None => panic!("Not found instruction!"),
+            None => Err(()),
         },
     }
 }
@@ -238,7 +238,7 @@ fn opecode_1(cpu: &Cpu, word: u16) -> Result<&'static CompressedInstruction, ()>
         },
         _ => match COMPRESSED_INSTRUCTIONS_GROUP1.get(&funct3) {
             Some(instruction) => Ok(&instruction),
-            None => panic!("Not found instruction!"),
+            None => Err(()),
         },
     }
 }
@@ -309,22 +309,22 @@ fn opecode_2(cpu: &Cpu, word: u16) -> Result<&'static CompressedInstruction, ()>
         },
         _ => match COMPRESSED_INSTRUCTIONS_GROUP2.get(&funct3) {
             Some(instruction) => Ok(&instruction),
-            None => panic!("Not found instruction!"),
+            None => Err(()),
         },
     }
 }
 
-pub fn instruction_decompress(cpu: &Cpu, instruction_addr: u64, word: u32) -> Result<u32, ()> {
+pub fn instruction_decompress(cpu: &Cpu, _instruction_addr: u64, word: u32) -> Result<u32, ()> {
     let compressed_word = (word & 0xffff) as u16;
 
     let opecodes = match COMPRESSED_OPECODES.get(&((word & 0x3) as u8)) {
         Some(ops) => ops,
-        None => panic!("Not found opecode: {:016x}", word),
+        None => return Err(()),
     };
 
     match (opecodes.operation)(cpu, compressed_word) {
         Ok(instruction) => (instruction.decompress)(compressed_word),
-        Err(()) => panic!("Not found instruction: {:016x}", instruction_addr),
+        Err(()) => Err(()),
     }
 }
 
@@ -402,11 +402,11 @@ fn c_flw(word: u16) -> Result<u32, ()> {
     let uimm = (((word >> 7) & 0x38) | ((word >> 4) & 0x4) | ((word << 1) & 0x40)) as u32;
 
     // flw rd,uimm(rs1)
-    let op = 0x3 as u32;
+    let op = 0x7 as u32;
     let rd = (rd_ + 8) << 7;
     let rs1 = (rs1_ + 8) << 15;
     let offset = uimm << 20;
-    Ok(offset | rs1 | 3 << 12 | rd | op)
+    Ok(offset | rs1 | 2 << 12 | rd | op)
 }
 
 /// [c.sd rd’,uimm(rs1’)]
@@ -461,12 +461,12 @@ fn c_fsw(word: u16) -> Result<u32, ()> {
     let uimm = (((word >> 7) & 0x38) | ((word >> 4) & 0x4) | ((word << 1) & 0x40)) as u32;
 
     // fsw rd2,uimm(rs1)
-    let op = 0x23 as u32;
+    let op = 0x27 as u32;
     let rs1 = (rs1_ + 8) << 15;
     let rs2 = (rs2_ + 8) << 20;
     let offset_h = ((uimm >> 5) & 0x7f) << 25;
     let offset_l = (uimm & 0x1f) << 7;
-    Ok(offset_h | rs2 | rs1 | 3 << 12 | offset_l | op)
+    Ok(offset_h | rs2 | rs1 | 2 << 12 | offset_l | op)
 }
 
 /// [c.nop]
@@ -801,8 +801,16 @@ fn c_slli(word: u16) -> Result<u32, ()> {
 }
 
 /// [c.fldsp rd,uimm(x2)]
-fn c_fldsp(_word: u16) -> Result<u32, ()> {
-    panic!("TODO");
+fn c_fldsp(word: u16) -> Result<u32, ()> {
+    let rd_ = ((word >> 7) & 0x1f) as u32;
+    let uimm = (((word >> 7) & 0x20) | ((word >> 2) & 0x18) | ((word << 4) & 0x1c0)) as u32;
+
+    // fld rd,offset(rs1)
+    let op = 0x7 as u32;
+    let rd = rd_ << 7;
+    let rs1 = 2/* x2 */ << 15;
+    let offset = uimm << 20;
+    Ok(offset | rs1 | 3 << 12 | rd | op)
 }
 
 /// [c.lwsp rd,uimm(x2)]
@@ -826,8 +834,16 @@ fn c_lwsp(word: u16) -> Result<u32, ()> {
 }
 
 /// [c.flwsp rd,uimm(x2)]
-fn c_flwsp(_word: u16) -> Result<u32, ()> {
-    panic!("TODO");
+fn c_flwsp(word: u16) -> Result<u32, ()> {
+    let rd_ = ((word >> 7) & 0x1f) as u32;
+    let uimm = (((word >> 7) & 0x20) | ((word >> 2) & 0x1c) | ((word << 4) & 0xc0)) as u32;
+
+    // flw rd,offset(rs1)
+    let op = 0x7 as u32;
+    let rd = rd_ << 7;
+    let rs1 = 2/* x2 */ << 15;
+    let offset = uimm << 20;
+    Ok(offset | rs1 | 2 << 12 | rd | op)
 }
 
 /// [c.ldsp rd,uimm(x2)]
@@ -924,8 +940,17 @@ fn c_add(word: u16) -> Result<u32, ()> {
 }
 
 /// [c.fsdsp rs2,uimm(x2)]
-fn c_fsdsp(_word: u16) -> Result<u32, ()> {
-    panic!("TODO");
+fn c_fsdsp(word: u16) -> Result<u32, ()> {
+    let rs2_ = ((word >> 2) & 0x1f) as u32;
+    let uimm = (((word >> 7) & 0x38) | ((word >> 1) & 0x1c0)) as u32;
+
+    // fsd rs2,offset(rs1)
+    let op = 0x27 as u32;
+    let rs1 = 2 << 15; // x2
+    let rs2 = rs2_ << 20;
+    let offset_h = ((uimm >> 5) & 0x7f) << 25;
+    let offset_l = (uimm & 0x1f) << 7;
+    Ok(offset_h | rs2 | rs1 | 3 << 12 | offset_l | op)
 }
 
 /// [c.swsp rs2,uimm(x2)]
@@ -943,8 +968,17 @@ fn c_swsp(word: u16) -> Result<u32, ()> {
 }
 
 /// [c.fswsp rs2,uimm(rs2)]
-fn c_fswsp(_word: u16) -> Result<u32, ()> {
-    panic!("TODO");
+fn c_fswsp(word: u16) -> Result<u32, ()> {
+    let rs2_ = ((word >> 2) & 0x1f) as u32;
+    let uimm = (((word >> 7) & 0x3c) | ((word >> 1) & 0xc0)) as u32;
+
+    // fsw rs2,offset(rs1)
+    let op = 0x27 as u32;
+    let rs2 = rs2_ << 20;
+    let rs1 = 2/* x2 */ << 15;
+    let offset_h = ((uimm >> 5) & 0x7f) << 25;
+    let offset_l = (uimm & 0x1f) << 7;
+    Ok(offset_h | rs2 | rs1 | 2 << 12 | offset_l | op)
 }
 
 /// [c.sdsp rs2,uimm(x2)]
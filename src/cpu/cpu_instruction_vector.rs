@@ -0,0 +1,4359 @@
+use std::collections::HashMap;
+
+use crate::cpu::cpu::{Cpu, Xlen};
+use crate::cpu::cpu_csr::*;
+use crate::cpu::cpu_instruction::{signed, unsigned, Instruction, REGISTERS};
+use crate::cpu::trap::*;
+
+/// default number of bits in a single vector register (VLEN).
+pub const VLEN_DEFAULT: usize = 128;
+
+/// maximum size in bits of a vector element (ELEN).
+const ELEN: usize = 64;
+
+struct InstructionTypeV {
+    vd: u8,
+    vs1: u8,
+    vs2: u8,
+    vm: bool,
+}
+
+struct InstructionTypeVMem {
+    vd: u8,
+    rs1: u8,
+    rs2: u8,
+    vm: bool,
+    eew: usize,
+    nf: usize,
+}
+
+/// source of the second operand of vector-vector, vector-scalar and vector-immediate forms.
+#[derive(Clone, Copy, PartialEq)]
+enum Operand {
+    Vector,
+    Scalar,
+    Immediate,
+    UnsignedImmediate,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Access {
+    UnitStride,
+    Strided,
+    Indexed,
+}
+
+struct VectorConfig {
+    sew: usize,
+    lmul_log2: i32,
+    vl: usize,
+    vstart: usize,
+}
+
+/// state shared by element operations of a single instruction.
+struct ElementContext {
+    sew: usize,
+    vxrm: u64,
+    vxsat: bool,
+}
+
+type ElementOp = fn(ctx: &mut ElementContext, a: u64, b: u64) -> u64;
+type CarryOp = fn(ctx: &mut ElementContext, a: u64, b: u64, c: u64) -> u64;
+
+lazy_static! {
+    static ref INSTRUCTIONS_GROUP57: HashMap<(u8, u8), Instruction> = {
+        let mut m = HashMap::new();
+        m.insert((0x00, 0), Instruction{
+            mnemonic: "vadd.vv",
+            operation: vadd_vv,
+            disassemble: disassemble_vv,
+        });
+        m.insert((0x00, 2), Instruction{
+            mnemonic: "vredsum.vs",
+            operation: vredsum_vs,
+            disassemble: disassemble_vv,
+        });
+        m.insert((0x00, 3), Instruction{
+            mnemonic: "vadd.vi",
+            operation: vadd_vi,
+            disassemble: disassemble_vi,
+        });
+        m.insert((0x00, 4), Instruction{
+            mnemonic: "vadd.vx",
+            operation: vadd_vx,
+            disassemble: disassemble_vx,
+        });
+        m.insert((0x01, 2), Instruction{
+            mnemonic: "vredand.vs",
+            operation: vredand_vs,
+            disassemble: disassemble_vv,
+        });
+        m.insert((0x02, 0), Instruction{
+            mnemonic: "vsub.vv",
+            operation: vsub_vv,
+            disassemble: disassemble_vv,
+        });
+        m.insert((0x02, 2), Instruction{
+            mnemonic: "vredor.vs",
+            operation: vredor_vs,
+            disassemble: disassemble_vv,
+        });
+        m.insert((0x02, 4), Instruction{
+            mnemonic: "vsub.vx",
+            operation: vsub_vx,
+            disassemble: disassemble_vx,
+        });
+        m.insert((0x03, 2), Instruction{
+            mnemonic: "vredxor.vs",
+            operation: vredxor_vs,
+            disassemble: disassemble_vv,
+        });
+        m.insert((0x03, 3), Instruction{
+            mnemonic: "vrsub.vi",
+            operation: vrsub_vi,
+            disassemble: disassemble_vi,
+        });
+        m.insert((0x03, 4), Instruction{
+            mnemonic: "vrsub.vx",
+            operation: vrsub_vx,
+            disassemble: disassemble_vx,
+        });
+        m.insert((0x04, 0), Instruction{
+            mnemonic: "vminu.vv",
+            operation: vminu_vv,
+            disassemble: disassemble_vv,
+        });
+        m.insert((0x04, 2), Instruction{
+            mnemonic: "vredminu.vs",
+            operation: vredminu_vs,
+            disassemble: disassemble_vv,
+        });
+        m.insert((0x04, 4), Instruction{
+            mnemonic: "vminu.vx",
+            operation: vminu_vx,
+            disassemble: disassemble_vx,
+        });
+        m.insert((0x05, 0), Instruction{
+            mnemonic: "vmin.vv",
+            operation: vmin_vv,
+            disassemble: disassemble_vv,
+        });
+        m.insert((0x05, 2), Instruction{
+            mnemonic: "vredmin.vs",
+            operation: vredmin_vs,
+            disassemble: disassemble_vv,
+        });
+        m.insert((0x05, 4), Instruction{
+            mnemonic: "vmin.vx",
+            operation: vmin_vx,
+            disassemble: disassemble_vx,
+        });
+        m.insert((0x06, 0), Instruction{
+            mnemonic: "vmaxu.vv",
+            operation: vmaxu_vv,
+            disassemble: disassemble_vv,
+        });
+        m.insert((0x06, 2), Instruction{
+            mnemonic: "vredmaxu.vs",
+            operation: vredmaxu_vs,
+            disassemble: disassemble_vv,
+        });
+        m.insert((0x06, 4), Instruction{
+            mnemonic: "vmaxu.vx",
+            operation: vmaxu_vx,
+            disassemble: disassemble_vx,
+        });
+        m.insert((0x07, 0), Instruction{
+            mnemonic: "vmax.vv",
+            operation: vmax_vv,
+            disassemble: disassemble_vv,
+        });
+        m.insert((0x07, 2), Instruction{
+            mnemonic: "vredmax.vs",
+            operation: vredmax_vs,
+            disassemble: disassemble_vv,
+        });
+        m.insert((0x07, 4), Instruction{
+            mnemonic: "vmax.vx",
+            operation: vmax_vx,
+            disassemble: disassemble_vx,
+        });
+        m.insert((0x08, 2), Instruction{
+            mnemonic: "vaaddu.vv",
+            operation: vaaddu_vv,
+            disassemble: disassemble_vv,
+        });
+        m.insert((0x08, 6), Instruction{
+            mnemonic: "vaaddu.vx",
+            operation: vaaddu_vx,
+            disassemble: disassemble_vx,
+        });
+        m.insert((0x09, 0), Instruction{
+            mnemonic: "vand.vv",
+            operation: vand_vv,
+            disassemble: disassemble_vv,
+        });
+        m.insert((0x09, 2), Instruction{
+            mnemonic: "vaadd.vv",
+            operation: vaadd_vv,
+            disassemble: disassemble_vv,
+        });
+        m.insert((0x09, 3), Instruction{
+            mnemonic: "vand.vi",
+            operation: vand_vi,
+            disassemble: disassemble_vi,
+        });
+        m.insert((0x09, 4), Instruction{
+            mnemonic: "vand.vx",
+            operation: vand_vx,
+            disassemble: disassemble_vx,
+        });
+        m.insert((0x09, 6), Instruction{
+            mnemonic: "vaadd.vx",
+            operation: vaadd_vx,
+            disassemble: disassemble_vx,
+        });
+        m.insert((0x0a, 0), Instruction{
+            mnemonic: "vor.vv",
+            operation: vor_vv,
+            disassemble: disassemble_vv,
+        });
+        m.insert((0x0a, 2), Instruction{
+            mnemonic: "vasubu.vv",
+            operation: vasubu_vv,
+            disassemble: disassemble_vv,
+        });
+        m.insert((0x0a, 3), Instruction{
+            mnemonic: "vor.vi",
+            operation: vor_vi,
+            disassemble: disassemble_vi,
+        });
+        m.insert((0x0a, 4), Instruction{
+            mnemonic: "vor.vx",
+            operation: vor_vx,
+            disassemble: disassemble_vx,
+        });
+        m.insert((0x0a, 6), Instruction{
+            mnemonic: "vasubu.vx",
+            operation: vasubu_vx,
+            disassemble: disassemble_vx,
+        });
+        m.insert((0x0b, 0), Instruction{
+            mnemonic: "vxor.vv",
+            operation: vxor_vv,
+            disassemble: disassemble_vv,
+        });
+        m.insert((0x0b, 2), Instruction{
+            mnemonic: "vasub.vv",
+            operation: vasub_vv,
+            disassemble: disassemble_vv,
+        });
+        m.insert((0x0b, 3), Instruction{
+            mnemonic: "vxor.vi",
+            operation: vxor_vi,
+            disassemble: disassemble_vi,
+        });
+        m.insert((0x0b, 4), Instruction{
+            mnemonic: "vxor.vx",
+            operation: vxor_vx,
+            disassemble: disassemble_vx,
+        });
+        m.insert((0x0b, 6), Instruction{
+            mnemonic: "vasub.vx",
+            operation: vasub_vx,
+            disassemble: disassemble_vx,
+        });
+        m.insert((0x0c, 0), Instruction{
+            mnemonic: "vrgather.vv",
+            operation: vrgather_vv,
+            disassemble: disassemble_vv,
+        });
+        m.insert((0x0c, 3), Instruction{
+            mnemonic: "vrgather.vi",
+            operation: vrgather_vi,
+            disassemble: disassemble_vi,
+        });
+        m.insert((0x0c, 4), Instruction{
+            mnemonic: "vrgather.vx",
+            operation: vrgather_vx,
+            disassemble: disassemble_vx,
+        });
+        m.insert((0x0e, 0), Instruction{
+            mnemonic: "vrgatherei16.vv",
+            operation: vrgatherei16_vv,
+            disassemble: disassemble_vv,
+        });
+        m.insert((0x0e, 3), Instruction{
+            mnemonic: "vslideup.vi",
+            operation: vslideup_vi,
+            disassemble: disassemble_vi,
+        });
+        m.insert((0x0e, 4), Instruction{
+            mnemonic: "vslideup.vx",
+            operation: vslideup_vx,
+            disassemble: disassemble_vx,
+        });
+        m.insert((0x0e, 6), Instruction{
+            mnemonic: "vslide1up.vx",
+            operation: vslide1up_vx,
+            disassemble: disassemble_vx,
+        });
+        m.insert((0x0f, 3), Instruction{
+            mnemonic: "vslidedown.vi",
+            operation: vslidedown_vi,
+            disassemble: disassemble_vi,
+        });
+        m.insert((0x0f, 4), Instruction{
+            mnemonic: "vslidedown.vx",
+            operation: vslidedown_vx,
+            disassemble: disassemble_vx,
+        });
+        m.insert((0x0f, 6), Instruction{
+            mnemonic: "vslide1down.vx",
+            operation: vslide1down_vx,
+            disassemble: disassemble_vx,
+        });
+        m.insert((0x10, 0), Instruction{
+            mnemonic: "vadc.vvm",
+            operation: vadc_vvm,
+            disassemble: disassemble_vvm,
+        });
+        m.insert((0x10, 3), Instruction{
+            mnemonic: "vadc.vim",
+            operation: vadc_vim,
+            disassemble: disassemble_vim,
+        });
+        m.insert((0x10, 4), Instruction{
+            mnemonic: "vadc.vxm",
+            operation: vadc_vxm,
+            disassemble: disassemble_vxm,
+        });
+        m.insert((0x12, 0), Instruction{
+            mnemonic: "vsbc.vvm",
+            operation: vsbc_vvm,
+            disassemble: disassemble_vvm,
+        });
+        m.insert((0x12, 4), Instruction{
+            mnemonic: "vsbc.vxm",
+            operation: vsbc_vxm,
+            disassemble: disassemble_vxm,
+        });
+        m.insert((0x17, 2), Instruction{
+            mnemonic: "vcompress.vm",
+            operation: vcompress_vm,
+            disassemble: disassemble_vv,
+        });
+        m.insert((0x18, 0), Instruction{
+            mnemonic: "vmseq.vv",
+            operation: vmseq_vv,
+            disassemble: disassemble_vv,
+        });
+        m.insert((0x18, 2), Instruction{
+            mnemonic: "vmandn.mm",
+            operation: vmandn_mm,
+            disassemble: disassemble_vv,
+        });
+        m.insert((0x18, 3), Instruction{
+            mnemonic: "vmseq.vi",
+            operation: vmseq_vi,
+            disassemble: disassemble_vi,
+        });
+        m.insert((0x18, 4), Instruction{
+            mnemonic: "vmseq.vx",
+            operation: vmseq_vx,
+            disassemble: disassemble_vx,
+        });
+        m.insert((0x19, 0), Instruction{
+            mnemonic: "vmsne.vv",
+            operation: vmsne_vv,
+            disassemble: disassemble_vv,
+        });
+        m.insert((0x19, 2), Instruction{
+            mnemonic: "vmand.mm",
+            operation: vmand_mm,
+            disassemble: disassemble_vv,
+        });
+        m.insert((0x19, 3), Instruction{
+            mnemonic: "vmsne.vi",
+            operation: vmsne_vi,
+            disassemble: disassemble_vi,
+        });
+        m.insert((0x19, 4), Instruction{
+            mnemonic: "vmsne.vx",
+            operation: vmsne_vx,
+            disassemble: disassemble_vx,
+        });
+        m.insert((0x1a, 0), Instruction{
+            mnemonic: "vmsltu.vv",
+            operation: vmsltu_vv,
+            disassemble: disassemble_vv,
+        });
+        m.insert((0x1a, 2), Instruction{
+            mnemonic: "vmor.mm",
+            operation: vmor_mm,
+            disassemble: disassemble_vv,
+        });
+        m.insert((0x1a, 4), Instruction{
+            mnemonic: "vmsltu.vx",
+            operation: vmsltu_vx,
+            disassemble: disassemble_vx,
+        });
+        m.insert((0x1b, 0), Instruction{
+            mnemonic: "vmslt.vv",
+            operation: vmslt_vv,
+            disassemble: disassemble_vv,
+        });
+        m.insert((0x1b, 2), Instruction{
+            mnemonic: "vmxor.mm",
+            operation: vmxor_mm,
+            disassemble: disassemble_vv,
+        });
+        m.insert((0x1b, 4), Instruction{
+            mnemonic: "vmslt.vx",
+            operation: vmslt_vx,
+            disassemble: disassemble_vx,
+        });
+        m.insert((0x1c, 0), Instruction{
+            mnemonic: "vmsleu.vv",
+            operation: vmsleu_vv,
+            disassemble: disassemble_vv,
+        });
+        m.insert((0x1c, 2), Instruction{
+            mnemonic: "vmorn.mm",
+            operation: vmorn_mm,
+            disassemble: disassemble_vv,
+        });
+        m.insert((0x1c, 3), Instruction{
+            mnemonic: "vmsleu.vi",
+            operation: vmsleu_vi,
+            disassemble: disassemble_vi,
+        });
+        m.insert((0x1c, 4), Instruction{
+            mnemonic: "vmsleu.vx",
+            operation: vmsleu_vx,
+            disassemble: disassemble_vx,
+        });
+        m.insert((0x1d, 0), Instruction{
+            mnemonic: "vmsle.vv",
+            operation: vmsle_vv,
+            disassemble: disassemble_vv,
+        });
+        m.insert((0x1d, 2), Instruction{
+            mnemonic: "vmnand.mm",
+            operation: vmnand_mm,
+            disassemble: disassemble_vv,
+        });
+        m.insert((0x1d, 3), Instruction{
+            mnemonic: "vmsle.vi",
+            operation: vmsle_vi,
+            disassemble: disassemble_vi,
+        });
+        m.insert((0x1d, 4), Instruction{
+            mnemonic: "vmsle.vx",
+            operation: vmsle_vx,
+            disassemble: disassemble_vx,
+        });
+        m.insert((0x1e, 2), Instruction{
+            mnemonic: "vmnor.mm",
+            operation: vmnor_mm,
+            disassemble: disassemble_vv,
+        });
+        m.insert((0x1e, 3), Instruction{
+            mnemonic: "vmsgtu.vi",
+            operation: vmsgtu_vi,
+            disassemble: disassemble_vi,
+        });
+        m.insert((0x1e, 4), Instruction{
+            mnemonic: "vmsgtu.vx",
+            operation: vmsgtu_vx,
+            disassemble: disassemble_vx,
+        });
+        m.insert((0x1f, 2), Instruction{
+            mnemonic: "vmxnor.mm",
+            operation: vmxnor_mm,
+            disassemble: disassemble_vv,
+        });
+        m.insert((0x1f, 3), Instruction{
+            mnemonic: "vmsgt.vi",
+            operation: vmsgt_vi,
+            disassemble: disassemble_vi,
+        });
+        m.insert((0x1f, 4), Instruction{
+            mnemonic: "vmsgt.vx",
+            operation: vmsgt_vx,
+            disassemble: disassemble_vx,
+        });
+        m.insert((0x20, 0), Instruction{
+            mnemonic: "vsaddu.vv",
+            operation: vsaddu_vv,
+            disassemble: disassemble_vv,
+        });
+        m.insert((0x20, 2), Instruction{
+            mnemonic: "vdivu.vv",
+            operation: vdivu_vv,
+            disassemble: disassemble_vv,
+        });
+        m.insert((0x20, 3), Instruction{
+            mnemonic: "vsaddu.vi",
+            operation: vsaddu_vi,
+            disassemble: disassemble_vi,
+        });
+        m.insert((0x20, 4), Instruction{
+            mnemonic: "vsaddu.vx",
+            operation: vsaddu_vx,
+            disassemble: disassemble_vx,
+        });
+        m.insert((0x20, 6), Instruction{
+            mnemonic: "vdivu.vx",
+            operation: vdivu_vx,
+            disassemble: disassemble_vx,
+        });
+        m.insert((0x21, 0), Instruction{
+            mnemonic: "vsadd.vv",
+            operation: vsadd_vv,
+            disassemble: disassemble_vv,
+        });
+        m.insert((0x21, 2), Instruction{
+            mnemonic: "vdiv.vv",
+            operation: vdiv_vv,
+            disassemble: disassemble_vv,
+        });
+        m.insert((0x21, 3), Instruction{
+            mnemonic: "vsadd.vi",
+            operation: vsadd_vi,
+            disassemble: disassemble_vi,
+        });
+        m.insert((0x21, 4), Instruction{
+            mnemonic: "vsadd.vx",
+            operation: vsadd_vx,
+            disassemble: disassemble_vx,
+        });
+        m.insert((0x21, 6), Instruction{
+            mnemonic: "vdiv.vx",
+            operation: vdiv_vx,
+            disassemble: disassemble_vx,
+        });
+        m.insert((0x22, 0), Instruction{
+            mnemonic: "vssubu.vv",
+            operation: vssubu_vv,
+            disassemble: disassemble_vv,
+        });
+        m.insert((0x22, 2), Instruction{
+            mnemonic: "vremu.vv",
+            operation: vremu_vv,
+            disassemble: disassemble_vv,
+        });
+        m.insert((0x22, 4), Instruction{
+            mnemonic: "vssubu.vx",
+            operation: vssubu_vx,
+            disassemble: disassemble_vx,
+        });
+        m.insert((0x22, 6), Instruction{
+            mnemonic: "vremu.vx",
+            operation: vremu_vx,
+            disassemble: disassemble_vx,
+        });
+        m.insert((0x23, 0), Instruction{
+            mnemonic: "vssub.vv",
+            operation: vssub_vv,
+            disassemble: disassemble_vv,
+        });
+        m.insert((0x23, 2), Instruction{
+            mnemonic: "vrem.vv",
+            operation: vrem_vv,
+            disassemble: disassemble_vv,
+        });
+        m.insert((0x23, 4), Instruction{
+            mnemonic: "vssub.vx",
+            operation: vssub_vx,
+            disassemble: disassemble_vx,
+        });
+        m.insert((0x23, 6), Instruction{
+            mnemonic: "vrem.vx",
+            operation: vrem_vx,
+            disassemble: disassemble_vx,
+        });
+        m.insert((0x24, 2), Instruction{
+            mnemonic: "vmulhu.vv",
+            operation: vmulhu_vv,
+            disassemble: disassemble_vv,
+        });
+        m.insert((0x24, 6), Instruction{
+            mnemonic: "vmulhu.vx",
+            operation: vmulhu_vx,
+            disassemble: disassemble_vx,
+        });
+        m.insert((0x25, 0), Instruction{
+            mnemonic: "vsll.vv",
+            operation: vsll_vv,
+            disassemble: disassemble_vv,
+        });
+        m.insert((0x25, 2), Instruction{
+            mnemonic: "vmul.vv",
+            operation: vmul_vv,
+            disassemble: disassemble_vv,
+        });
+        m.insert((0x25, 3), Instruction{
+            mnemonic: "vsll.vi",
+            operation: vsll_vi,
+            disassemble: disassemble_vi,
+        });
+        m.insert((0x25, 4), Instruction{
+            mnemonic: "vsll.vx",
+            operation: vsll_vx,
+            disassemble: disassemble_vx,
+        });
+        m.insert((0x25, 6), Instruction{
+            mnemonic: "vmul.vx",
+            operation: vmul_vx,
+            disassemble: disassemble_vx,
+        });
+        m.insert((0x26, 2), Instruction{
+            mnemonic: "vmulhsu.vv",
+            operation: vmulhsu_vv,
+            disassemble: disassemble_vv,
+        });
+        m.insert((0x26, 6), Instruction{
+            mnemonic: "vmulhsu.vx",
+            operation: vmulhsu_vx,
+            disassemble: disassemble_vx,
+        });
+        m.insert((0x27, 0), Instruction{
+            mnemonic: "vsmul.vv",
+            operation: vsmul_vv,
+            disassemble: disassemble_vv,
+        });
+        m.insert((0x27, 2), Instruction{
+            mnemonic: "vmulh.vv",
+            operation: vmulh_vv,
+            disassemble: disassemble_vv,
+        });
+        m.insert((0x27, 4), Instruction{
+            mnemonic: "vsmul.vx",
+            operation: vsmul_vx,
+            disassemble: disassemble_vx,
+        });
+        m.insert((0x27, 6), Instruction{
+            mnemonic: "vmulh.vx",
+            operation: vmulh_vx,
+            disassemble: disassemble_vx,
+        });
+        m.insert((0x28, 0), Instruction{
+            mnemonic: "vsrl.vv",
+            operation: vsrl_vv,
+            disassemble: disassemble_vv,
+        });
+        m.insert((0x28, 3), Instruction{
+            mnemonic: "vsrl.vi",
+            operation: vsrl_vi,
+            disassemble: disassemble_vi,
+        });
+        m.insert((0x28, 4), Instruction{
+            mnemonic: "vsrl.vx",
+            operation: vsrl_vx,
+            disassemble: disassemble_vx,
+        });
+        m.insert((0x29, 0), Instruction{
+            mnemonic: "vsra.vv",
+            operation: vsra_vv,
+            disassemble: disassemble_vv,
+        });
+        m.insert((0x29, 2), Instruction{
+            mnemonic: "vmadd.vv",
+            operation: vmadd_vv,
+            disassemble: disassemble_vv_multiply_add,
+        });
+        m.insert((0x29, 3), Instruction{
+            mnemonic: "vsra.vi",
+            operation: vsra_vi,
+            disassemble: disassemble_vi,
+        });
+        m.insert((0x29, 4), Instruction{
+            mnemonic: "vsra.vx",
+            operation: vsra_vx,
+            disassemble: disassemble_vx,
+        });
+        m.insert((0x29, 6), Instruction{
+            mnemonic: "vmadd.vx",
+            operation: vmadd_vx,
+            disassemble: disassemble_vx_multiply_add,
+        });
+        m.insert((0x2a, 0), Instruction{
+            mnemonic: "vssrl.vv",
+            operation: vssrl_vv,
+            disassemble: disassemble_vv,
+        });
+        m.insert((0x2a, 3), Instruction{
+            mnemonic: "vssrl.vi",
+            operation: vssrl_vi,
+            disassemble: disassemble_vi,
+        });
+        m.insert((0x2a, 4), Instruction{
+            mnemonic: "vssrl.vx",
+            operation: vssrl_vx,
+            disassemble: disassemble_vx,
+        });
+        m.insert((0x2b, 0), Instruction{
+            mnemonic: "vssra.vv",
+            operation: vssra_vv,
+            disassemble: disassemble_vv,
+        });
+        m.insert((0x2b, 2), Instruction{
+            mnemonic: "vnmsub.vv",
+            operation: vnmsub_vv,
+            disassemble: disassemble_vv_multiply_add,
+        });
+        m.insert((0x2b, 3), Instruction{
+            mnemonic: "vssra.vi",
+            operation: vssra_vi,
+            disassemble: disassemble_vi,
+        });
+        m.insert((0x2b, 4), Instruction{
+            mnemonic: "vssra.vx",
+            operation: vssra_vx,
+            disassemble: disassemble_vx,
+        });
+        m.insert((0x2b, 6), Instruction{
+            mnemonic: "vnmsub.vx",
+            operation: vnmsub_vx,
+            disassemble: disassemble_vx_multiply_add,
+        });
+        m.insert((0x2c, 0), Instruction{
+            mnemonic: "vnsrl.wv",
+            operation: vnsrl_wv,
+            disassemble: disassemble_vv,
+        });
+        m.insert((0x2c, 3), Instruction{
+            mnemonic: "vnsrl.wi",
+            operation: vnsrl_wi,
+            disassemble: disassemble_vi,
+        });
+        m.insert((0x2c, 4), Instruction{
+            mnemonic: "vnsrl.wx",
+            operation: vnsrl_wx,
+            disassemble: disassemble_vx,
+        });
+        m.insert((0x2d, 0), Instruction{
+            mnemonic: "vnsra.wv",
+            operation: vnsra_wv,
+            disassemble: disassemble_vv,
+        });
+        m.insert((0x2d, 2), Instruction{
+            mnemonic: "vmacc.vv",
+            operation: vmacc_vv,
+            disassemble: disassemble_vv_multiply_add,
+        });
+        m.insert((0x2d, 3), Instruction{
+            mnemonic: "vnsra.wi",
+            operation: vnsra_wi,
+            disassemble: disassemble_vi,
+        });
+        m.insert((0x2d, 4), Instruction{
+            mnemonic: "vnsra.wx",
+            operation: vnsra_wx,
+            disassemble: disassemble_vx,
+        });
+        m.insert((0x2d, 6), Instruction{
+            mnemonic: "vmacc.vx",
+            operation: vmacc_vx,
+            disassemble: disassemble_vx_multiply_add,
+        });
+        m.insert((0x2e, 0), Instruction{
+            mnemonic: "vnclipu.wv",
+            operation: vnclipu_wv,
+            disassemble: disassemble_vv,
+        });
+        m.insert((0x2e, 3), Instruction{
+            mnemonic: "vnclipu.wi",
+            operation: vnclipu_wi,
+            disassemble: disassemble_vi,
+        });
+        m.insert((0x2e, 4), Instruction{
+            mnemonic: "vnclipu.wx",
+            operation: vnclipu_wx,
+            disassemble: disassemble_vx,
+        });
+        m.insert((0x2f, 0), Instruction{
+            mnemonic: "vnclip.wv",
+            operation: vnclip_wv,
+            disassemble: disassemble_vv,
+        });
+        m.insert((0x2f, 2), Instruction{
+            mnemonic: "vnmsac.vv",
+            operation: vnmsac_vv,
+            disassemble: disassemble_vv_multiply_add,
+        });
+        m.insert((0x2f, 3), Instruction{
+            mnemonic: "vnclip.wi",
+            operation: vnclip_wi,
+            disassemble: disassemble_vi,
+        });
+        m.insert((0x2f, 4), Instruction{
+            mnemonic: "vnclip.wx",
+            operation: vnclip_wx,
+            disassemble: disassemble_vx,
+        });
+        m.insert((0x2f, 6), Instruction{
+            mnemonic: "vnmsac.vx",
+            operation: vnmsac_vx,
+            disassemble: disassemble_vx_multiply_add,
+        });
+        m.insert((0x30, 0), Instruction{
+            mnemonic: "vwredsumu.vs",
+            operation: vwredsumu_vs,
+            disassemble: disassemble_vv,
+        });
+        m.insert((0x30, 2), Instruction{
+            mnemonic: "vwaddu.vv",
+            operation: vwaddu_vv,
+            disassemble: disassemble_vv,
+        });
+        m.insert((0x30, 6), Instruction{
+            mnemonic: "vwaddu.vx",
+            operation: vwaddu_vx,
+            disassemble: disassemble_vx,
+        });
+        m.insert((0x31, 0), Instruction{
+            mnemonic: "vwredsum.vs",
+            operation: vwredsum_vs,
+            disassemble: disassemble_vv,
+        });
+        m.insert((0x31, 2), Instruction{
+            mnemonic: "vwadd.vv",
+            operation: vwadd_vv,
+            disassemble: disassemble_vv,
+        });
+        m.insert((0x31, 6), Instruction{
+            mnemonic: "vwadd.vx",
+            operation: vwadd_vx,
+            disassemble: disassemble_vx,
+        });
+        m.insert((0x32, 2), Instruction{
+            mnemonic: "vwsubu.vv",
+            operation: vwsubu_vv,
+            disassemble: disassemble_vv,
+        });
+        m.insert((0x32, 6), Instruction{
+            mnemonic: "vwsubu.vx",
+            operation: vwsubu_vx,
+            disassemble: disassemble_vx,
+        });
+        m.insert((0x33, 2), Instruction{
+            mnemonic: "vwsub.vv",
+            operation: vwsub_vv,
+            disassemble: disassemble_vv,
+        });
+        m.insert((0x33, 6), Instruction{
+            mnemonic: "vwsub.vx",
+            operation: vwsub_vx,
+            disassemble: disassemble_vx,
+        });
+        m.insert((0x34, 2), Instruction{
+            mnemonic: "vwaddu.wv",
+            operation: vwaddu_wv,
+            disassemble: disassemble_vv,
+        });
+        m.insert((0x34, 6), Instruction{
+            mnemonic: "vwaddu.wx",
+            operation: vwaddu_wx,
+            disassemble: disassemble_vx,
+        });
+        m.insert((0x35, 2), Instruction{
+            mnemonic: "vwadd.wv",
+            operation: vwadd_wv,
+            disassemble: disassemble_vv,
+        });
+        m.insert((0x35, 6), Instruction{
+            mnemonic: "vwadd.wx",
+            operation: vwadd_wx,
+            disassemble: disassemble_vx,
+        });
+        m.insert((0x36, 2), Instruction{
+            mnemonic: "vwsubu.wv",
+            operation: vwsubu_wv,
+            disassemble: disassemble_vv,
+        });
+        m.insert((0x36, 6), Instruction{
+            mnemonic: "vwsubu.wx",
+            operation: vwsubu_wx,
+            disassemble: disassemble_vx,
+        });
+        m.insert((0x37, 2), Instruction{
+            mnemonic: "vwsub.wv",
+            operation: vwsub_wv,
+            disassemble: disassemble_vv,
+        });
+        m.insert((0x37, 6), Instruction{
+            mnemonic: "vwsub.wx",
+            operation: vwsub_wx,
+            disassemble: disassemble_vx,
+        });
+        m.insert((0x38, 2), Instruction{
+            mnemonic: "vwmulu.vv",
+            operation: vwmulu_vv,
+            disassemble: disassemble_vv,
+        });
+        m.insert((0x38, 6), Instruction{
+            mnemonic: "vwmulu.vx",
+            operation: vwmulu_vx,
+            disassemble: disassemble_vx,
+        });
+        m.insert((0x3a, 2), Instruction{
+            mnemonic: "vwmulsu.vv",
+            operation: vwmulsu_vv,
+            disassemble: disassemble_vv,
+        });
+        m.insert((0x3a, 6), Instruction{
+            mnemonic: "vwmulsu.vx",
+            operation: vwmulsu_vx,
+            disassemble: disassemble_vx,
+        });
+        m.insert((0x3b, 2), Instruction{
+            mnemonic: "vwmul.vv",
+            operation: vwmul_vv,
+            disassemble: disassemble_vv,
+        });
+        m.insert((0x3b, 6), Instruction{
+            mnemonic: "vwmul.vx",
+            operation: vwmul_vx,
+            disassemble: disassemble_vx,
+        });
+        m.insert((0x3c, 2), Instruction{
+            mnemonic: "vwmaccu.vv",
+            operation: vwmaccu_vv,
+            disassemble: disassemble_vv_multiply_add,
+        });
+        m.insert((0x3c, 6), Instruction{
+            mnemonic: "vwmaccu.vx",
+            operation: vwmaccu_vx,
+            disassemble: disassemble_vx_multiply_add,
+        });
+        m.insert((0x3d, 2), Instruction{
+            mnemonic: "vwmacc.vv",
+            operation: vwmacc_vv,
+            disassemble: disassemble_vv_multiply_add,
+        });
+        m.insert((0x3d, 6), Instruction{
+            mnemonic: "vwmacc.vx",
+            operation: vwmacc_vx,
+            disassemble: disassemble_vx_multiply_add,
+        });
+        m.insert((0x3e, 6), Instruction{
+            mnemonic: "vwmaccus.vx",
+            operation: vwmaccus_vx,
+            disassemble: disassemble_vx_multiply_add,
+        });
+        m.insert((0x3f, 2), Instruction{
+            mnemonic: "vwmaccsu.vv",
+            operation: vwmaccsu_vv,
+            disassemble: disassemble_vv_multiply_add,
+        });
+        m.insert((0x3f, 6), Instruction{
+            mnemonic: "vwmaccsu.vx",
+            operation: vwmaccsu_vx,
+            disassemble: disassemble_vx_multiply_add,
+        });
+        m
+    };
+
+    static ref INSTRUCTIONS_GROUP57_SUB: HashMap<(u8, u8, u8), Instruction> = {
+        let mut m = HashMap::new();
+        m.insert((0x10, 2, 0x00), Instruction{
+            mnemonic: "vmv.x.s",
+            operation: vmv_x_s,
+            disassemble: disassemble_x_v,
+        });
+        m.insert((0x10, 2, 0x10), Instruction{
+            mnemonic: "vcpop.m",
+            operation: vcpop_m,
+            disassemble: disassemble_x_v,
+        });
+        m.insert((0x10, 2, 0x11), Instruction{
+            mnemonic: "vfirst.m",
+            operation: vfirst_m,
+            disassemble: disassemble_x_v,
+        });
+        m.insert((0x10, 6, 0x00), Instruction{
+            mnemonic: "vmv.s.x",
+            operation: vmv_s_x,
+            disassemble: disassemble_v_x,
+        });
+        m.insert((0x12, 2, 0x02), Instruction{
+            mnemonic: "vzext.vf8",
+            operation: vzext_vf8,
+            disassemble: disassemble_v_v,
+        });
+        m.insert((0x12, 2, 0x03), Instruction{
+            mnemonic: "vsext.vf8",
+            operation: vsext_vf8,
+            disassemble: disassemble_v_v,
+        });
+        m.insert((0x12, 2, 0x04), Instruction{
+            mnemonic: "vzext.vf4",
+            operation: vzext_vf4,
+            disassemble: disassemble_v_v,
+        });
+        m.insert((0x12, 2, 0x05), Instruction{
+            mnemonic: "vsext.vf4",
+            operation: vsext_vf4,
+            disassemble: disassemble_v_v,
+        });
+        m.insert((0x12, 2, 0x06), Instruction{
+            mnemonic: "vzext.vf2",
+            operation: vzext_vf2,
+            disassemble: disassemble_v_v,
+        });
+        m.insert((0x12, 2, 0x07), Instruction{
+            mnemonic: "vsext.vf2",
+            operation: vsext_vf2,
+            disassemble: disassemble_v_v,
+        });
+        m.insert((0x14, 2, 0x01), Instruction{
+            mnemonic: "vmsbf.m",
+            operation: vmsbf_m,
+            disassemble: disassemble_v_v,
+        });
+        m.insert((0x14, 2, 0x02), Instruction{
+            mnemonic: "vmsof.m",
+            operation: vmsof_m,
+            disassemble: disassemble_v_v,
+        });
+        m.insert((0x14, 2, 0x03), Instruction{
+            mnemonic: "vmsif.m",
+            operation: vmsif_m,
+            disassemble: disassemble_v_v,
+        });
+        m.insert((0x14, 2, 0x10), Instruction{
+            mnemonic: "viota.m",
+            operation: viota_m,
+            disassemble: disassemble_v_v,
+        });
+        m.insert((0x14, 2, 0x11), Instruction{
+            mnemonic: "vid.v",
+            operation: vid_v,
+            disassemble: disassemble_v,
+        });
+        m.insert((0x17, 0, 0x00), Instruction{
+            mnemonic: "vmerge.vvm",
+            operation: vmerge_vvm,
+            disassemble: disassemble_vvm,
+        });
+        m.insert((0x17, 4, 0x00), Instruction{
+            mnemonic: "vmerge.vxm",
+            operation: vmerge_vxm,
+            disassemble: disassemble_vxm,
+        });
+        m.insert((0x17, 3, 0x00), Instruction{
+            mnemonic: "vmerge.vim",
+            operation: vmerge_vim,
+            disassemble: disassemble_vim,
+        });
+        m.insert((0x17, 0, 0x01), Instruction{
+            mnemonic: "vmv.v.v",
+            operation: vmv_v_v,
+            disassemble: disassemble_v_v1,
+        });
+        m.insert((0x17, 4, 0x01), Instruction{
+            mnemonic: "vmv.v.x",
+            operation: vmv_v_x,
+            disassemble: disassemble_v_x,
+        });
+        m.insert((0x17, 3, 0x01), Instruction{
+            mnemonic: "vmv.v.i",
+            operation: vmv_v_i,
+            disassemble: disassemble_v_i,
+        });
+        m.insert((0x11, 0, 0x00), Instruction{
+            mnemonic: "vmadc.vvm",
+            operation: vmadc_vvm,
+            disassemble: disassemble_vvm,
+        });
+        m.insert((0x11, 4, 0x00), Instruction{
+            mnemonic: "vmadc.vxm",
+            operation: vmadc_vxm,
+            disassemble: disassemble_vxm,
+        });
+        m.insert((0x11, 3, 0x00), Instruction{
+            mnemonic: "vmadc.vim",
+            operation: vmadc_vim,
+            disassemble: disassemble_vim,
+        });
+        m.insert((0x11, 0, 0x01), Instruction{
+            mnemonic: "vmadc.vv",
+            operation: vmadc_vv,
+            disassemble: disassemble_vv,
+        });
+        m.insert((0x11, 4, 0x01), Instruction{
+            mnemonic: "vmadc.vx",
+            operation: vmadc_vx,
+            disassemble: disassemble_vx,
+        });
+        m.insert((0x11, 3, 0x01), Instruction{
+            mnemonic: "vmadc.vi",
+            operation: vmadc_vi,
+            disassemble: disassemble_vi,
+        });
+        m.insert((0x13, 0, 0x00), Instruction{
+            mnemonic: "vmsbc.vvm",
+            operation: vmsbc_vvm,
+            disassemble: disassemble_vvm,
+        });
+        m.insert((0x13, 4, 0x00), Instruction{
+            mnemonic: "vmsbc.vxm",
+            operation: vmsbc_vxm,
+            disassemble: disassemble_vxm,
+        });
+        m.insert((0x13, 0, 0x01), Instruction{
+            mnemonic: "vmsbc.vv",
+            operation: vmsbc_vv,
+            disassemble: disassemble_vv,
+        });
+        m.insert((0x13, 4, 0x01), Instruction{
+            mnemonic: "vmsbc.vx",
+            operation: vmsbc_vx,
+            disassemble: disassemble_vx,
+        });
+        m.insert((0x27, 3, 0x00), Instruction{
+            mnemonic: "vmv1r.v",
+            operation: vmv1r_v,
+            disassemble: disassemble_v_v,
+        });
+        m.insert((0x27, 3, 0x01), Instruction{
+            mnemonic: "vmv2r.v",
+            operation: vmv2r_v,
+            disassemble: disassemble_v_v,
+        });
+        m.insert((0x27, 3, 0x03), Instruction{
+            mnemonic: "vmv4r.v",
+            operation: vmv4r_v,
+            disassemble: disassemble_v_v,
+        });
+        m.insert((0x27, 3, 0x07), Instruction{
+            mnemonic: "vmv8r.v",
+            operation: vmv8r_v,
+            disassemble: disassemble_v_v,
+        });
+        m
+    };
+
+    static ref INSTRUCTIONS_VECTOR_LOAD: HashMap<(u8, u8), Instruction> = {
+        let mut m = HashMap::new();
+        m.insert((0, 0x00), Instruction{
+            mnemonic: "vle.v",
+            operation: vle_v,
+            disassemble: disassemble_vector_load_store,
+        });
+        m.insert((0, 0x08), Instruction{
+            mnemonic: "vlre.v",
+            operation: vlre_v,
+            disassemble: disassemble_vector_load_store,
+        });
+        m.insert((0, 0x0b), Instruction{
+            mnemonic: "vlm.v",
+            operation: vlm_v,
+            disassemble: disassemble_vector_load_store,
+        });
+        m.insert((0, 0x10), Instruction{
+            mnemonic: "vleff.v",
+            operation: vleff_v,
+            disassemble: disassemble_vector_load_store,
+        });
+        m.insert((1, 0x00), Instruction{
+            mnemonic: "vluxei.v",
+            operation: vluxei_v,
+            disassemble: disassemble_vector_load_store,
+        });
+        m.insert((2, 0x00), Instruction{
+            mnemonic: "vlse.v",
+            operation: vlse_v,
+            disassemble: disassemble_vector_load_store,
+        });
+        m.insert((3, 0x00), Instruction{
+            mnemonic: "vloxei.v",
+            operation: vloxei_v,
+            disassemble: disassemble_vector_load_store,
+        });
+        m
+    };
+    static ref INSTRUCTIONS_VECTOR_STORE: HashMap<(u8, u8), Instruction> = {
+        let mut m = HashMap::new();
+        m.insert((0, 0x00), Instruction{
+            mnemonic: "vse.v",
+            operation: vse_v,
+            disassemble: disassemble_vector_load_store,
+        });
+        m.insert((0, 0x08), Instruction{
+            mnemonic: "vsr.v",
+            operation: vsr_v,
+            disassemble: disassemble_vector_load_store,
+        });
+        m.insert((0, 0x0b), Instruction{
+            mnemonic: "vsm.v",
+            operation: vsm_v,
+            disassemble: disassemble_vector_load_store,
+        });
+        m.insert((1, 0x00), Instruction{
+            mnemonic: "vsuxei.v",
+            operation: vsuxei_v,
+            disassemble: disassemble_vector_load_store,
+        });
+        m.insert((2, 0x00), Instruction{
+            mnemonic: "vsse.v",
+            operation: vsse_v,
+            disassemble: disassemble_vector_load_store,
+        });
+        m.insert((3, 0x00), Instruction{
+            mnemonic: "vsoxei.v",
+            operation: vsoxei_v,
+            disassemble: disassemble_vector_load_store,
+        });
+        m
+    };
+}
+
+pub fn opecode_57(_cpu: &Cpu, _addr: u64, word: u32) -> Result<&'static Instruction, ()> {
+    let funct3 = ((word & 0x00007000) >> 12) as u8;
+    let funct6 = ((word & 0xfc000000) >> 26) as u8;
+    let vm = ((word & 0x02000000) >> 25) as u8;
+    let vs1 = ((word & 0x000f8000) >> 15) as u8;
+    let vs2 = ((word & 0x01f00000) >> 20) as u8;
+    let instruction = match (funct6, funct3) {
+        (_, 7) => {
+            return match (word & 0xc0000000) >> 30 {
+                0 | 1 => Ok(&Instruction {
+                    mnemonic: "vsetvli",
+                    operation: vsetvli,
+                    disassemble: disassemble_vsetvli,
+                }),
+                3 => Ok(&Instruction {
+                    mnemonic: "vsetivli",
+                    operation: vsetivli,
+                    disassemble: disassemble_vsetivli,
+                }),
+                _ => Ok(&Instruction {
+                    mnemonic: "vsetvl",
+                    operation: vsetvl,
+                    disassemble: disassemble_vsetvl,
+                }),
+            }
+        }
+        (0x10, 2) | (0x12, 2) | (0x14, 2) | (0x27, 3) => {
+            INSTRUCTIONS_GROUP57_SUB.get(&(funct6, funct3, vs1))
+        }
+        (0x10, 6) => INSTRUCTIONS_GROUP57_SUB.get(&(funct6, funct3, vs2)),
+        (0x11, _) | (0x13, _) | (0x17, 0) | (0x17, 3) | (0x17, 4) => {
+            INSTRUCTIONS_GROUP57_SUB.get(&(funct6, funct3, vm))
+        }
+        _ => INSTRUCTIONS_GROUP57.get(&(funct6, funct3)),
+    };
+    match instruction {
+        Some(instruction) => Ok(&instruction),
+        None => Err(()),
+    }
+}
+
+/// vector loads share the LOAD-FP major opcode, where the width field selects the
+/// element width. They are decoded by mop and lumop (unit-stride only).
+pub fn opecode_vector_load(_cpu: &Cpu, _addr: u64, word: u32) -> Result<&'static Instruction, ()> {
+    let mop = ((word & 0x0c000000) >> 26) as u8;
+    let lumop = match mop {
+        0 => ((word & 0x01f00000) >> 20) as u8,
+        _ => 0,
+    };
+    match INSTRUCTIONS_VECTOR_LOAD.get(&(mop, lumop)) {
+        Some(instruction) => Ok(&instruction),
+        None => Err(()),
+    }
+}
+
+/// vector stores share the STORE-FP major opcode.
+pub fn opecode_vector_store(_cpu: &Cpu, _addr: u64, word: u32) -> Result<&'static Instruction, ()> {
+    let mop = ((word & 0x0c000000) >> 26) as u8;
+    let sumop = match mop {
+        0 => ((word & 0x01f00000) >> 20) as u8,
+        _ => 0,
+    };
+    match INSTRUCTIONS_VECTOR_STORE.get(&(mop, sumop)) {
+        Some(instruction) => Ok(&instruction),
+        None => Err(()),
+    }
+}
+
+fn parse_type_v(word: u32) -> InstructionTypeV {
+    InstructionTypeV {
+        vd: ((word & 0x00000f80) >> 7) as u8,
+        vs1: ((word & 0x000f8000) >> 15) as u8,
+        vs2: ((word & 0x01f00000) >> 20) as u8,
+        vm: (word & 0x02000000) != 0,
+    }
+}
+
+fn parse_type_vmem(word: u32) -> InstructionTypeVMem {
+    InstructionTypeVMem {
+        vd: ((word & 0x00000f80) >> 7) as u8,
+        rs1: ((word & 0x000f8000) >> 15) as u8,
+        rs2: ((word & 0x01f00000) >> 20) as u8,
+        vm: (word & 0x02000000) != 0,
+        eew: match (word & 0x00007000) >> 12 {
+            0 => 8,
+            5 => 16,
+            6 => 32,
+            _ => 64,
+        },
+        nf: (((word & 0xe0000000) >> 29) + 1) as usize,
+    }
+}
+
+fn illegal_instruction(word: u32) -> Trap {
+    Trap {
+        exception: Exception::IllegalInstruction,
+        value: word as u64,
+    }
+}
+
+/// bit mask of the lower bits.
+fn mask_bits(bits: usize) -> u64 {
+    match bits {
+        64 => 0xffffffff_ffffffff,
+        _ => (1 << bits) - 1,
+    }
+}
+
+/// sign extension of the lower bits.
+fn sext(data: u64, bits: usize) -> i64 {
+    let shift = 64 - bits;
+    ((data << shift) as i64) >> shift
+}
+
+fn log2(data: usize) -> i32 {
+    data.trailing_zeros() as i32
+}
+
+/// read the element of the register group. Element index may exceed the size of
+/// a single register, in which case the element is held in the following registers.
+fn read_element(v: &[u8], reg: u8, index: usize, eew: usize) -> u64 {
+    let bytes = eew / 8;
+    let offset = reg as usize * (v.len() / 32) + index * bytes;
+    let mut data = 0;
+    for i in 0..bytes {
+        data |= (v[offset + i] as u64) << (i * 8);
+    }
+    data
+}
+
+fn write_element(v: &mut [u8], reg: u8, index: usize, eew: usize, data: u64) {
+    let bytes = eew / 8;
+    let offset = reg as usize * (v.len() / 32) + index * bytes;
+    for i in 0..bytes {
+        v[offset + i] = (data >> (i * 8)) as u8;
+    }
+}
+
+/// read the mask bit. Mask elements are packed one bit per element in a single register.
+fn read_mask(v: &[u8], reg: u8, index: usize) -> bool {
+    let offset = reg as usize * (v.len() / 32) + index / 8;
+    ((v[offset] >> (index % 8)) & 1) == 1
+}
+
+fn write_mask(v: &mut [u8], reg: u8, index: usize, value: bool) {
+    let offset = reg as usize * (v.len() / 32) + index / 8;
+    match value {
+        true => v[offset] |= 1 << (index % 8),
+        false => v[offset] &= !(1 << (index % 8)),
+    }
+}
+
+/// masked-off elements are left undisturbed. vm=1 means unmasked.
+fn is_active(v: &[u8], vm: bool, index: usize) -> bool {
+    vm || read_mask(v, 0, index)
+}
+
+/// vector instructions are illegal while mstatus.VS (or vsstatus.VS when V=1) is Off.
+fn check_vector_enabled(cpu: &mut Cpu, word: u32) -> Result<(), Trap> {
+    match cpu.csr.is_status_enabled(CSR_STATUS_VS) {
+        false => Err(illegal_instruction(word)),
+        true => Ok(()),
+    }
+}
+
+/// maximum number of elements for the element width and register group multiplier.
+fn get_vlmax(cpu: &Cpu, sew: usize, lmul_log2: i32) -> usize {
+    let vlen = cpu.v.len() / 32 * 8;
+    match lmul_log2 >= 0 {
+        true => (vlen / sew) << lmul_log2,
+        false => (vlen / sew) >> -lmul_log2,
+    }
+}
+
+/// get the current vector configuration. Vector instructions that depend on vtype
+/// are illegal while vtype.vill is set.
+fn get_config(cpu: &mut Cpu, word: u32) -> Result<VectorConfig, Trap> {
+    match check_vector_enabled(cpu, word) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    let vtype = cpu.csr.read_direct(CSR_VTYPE);
+    // Any bits other than vma, vta, vsew and vlmul means vill is set.
+    if (vtype >> 8) != 0 {
+        return Err(illegal_instruction(word));
+    }
+    let sew = 8 << ((vtype >> 3) & 0x7);
+    let lmul_log2 = match vtype & 0x7 {
+        lmul if lmul < 4 => lmul as i32,
+        lmul => lmul as i32 - 8,
+    };
+    Ok(VectorConfig {
+        sew,
+        lmul_log2,
+        vl: cpu.csr.read_direct(CSR_VL) as usize,
+        vstart: cpu.csr.read_direct(CSR_VSTART) as usize,
+    })
+}
+
+/// a register group must be aligned to its size, and EMUL must be within 1/8 to 8.
+fn check_register_group(word: u32, reg: u8, emul_log2: i32) -> Result<(), Trap> {
+    match emul_log2 {
+        emul_log2 if !(-3..=3).contains(&emul_log2) => Err(illegal_instruction(word)),
+        emul_log2 if emul_log2 > 0 && (reg as usize) & ((1 << emul_log2) - 1) != 0 => {
+            Err(illegal_instruction(word))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// a masked instruction can not overwrite v0 with non-mask destination.
+fn check_mask_overlap(word: u32, o: &InstructionTypeV) -> Result<(), Trap> {
+    match !o.vm && o.vd == 0 {
+        true => Err(illegal_instruction(word)),
+        false => Ok(()),
+    }
+}
+
+fn check_operands(
+    word: u32,
+    o: &InstructionTypeV,
+    operand: Operand,
+    vd_emul_log2: i32,
+    vs2_emul_log2: i32,
+) -> Result<(), Trap> {
+    match check_register_group(word, o.vd, vd_emul_log2) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    match check_register_group(word, o.vs2, vs2_emul_log2) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    match operand {
+        Operand::Vector => check_register_group(word, o.vs1, vs2_emul_log2),
+        _ => Ok(()),
+    }
+}
+
+/// get the second source operand: vs1 element, x register or 5-bit immediate.
+fn get_operand(cpu: &Cpu, v: &[u8], operand: Operand, vs1: u8, index: usize, eew: usize) -> u64 {
+    match operand {
+        Operand::Vector => read_element(v, vs1, index, eew),
+        Operand::Scalar => cpu.x[vs1 as usize] as u64 & mask_bits(eew),
+        Operand::Immediate => sext(vs1 as u64, 5) as u64 & mask_bits(eew),
+        Operand::UnsignedImmediate => vs1 as u64,
+    }
+}
+
+impl ElementContext {
+    fn new(cpu: &mut Cpu, sew: usize) -> ElementContext {
+        ElementContext {
+            sew,
+            vxrm: cpu.csr.read_direct(CSR_VXRM),
+            vxsat: false,
+        }
+    }
+}
+
+/// complete the vector instruction. vstart is reset to zero, the saturation is accrued
+/// to vxsat and mstatus.VS is set to Dirty.
+fn finish(cpu: &mut Cpu, ctx: &ElementContext) -> Result<(), Trap> {
+    if ctx.vxsat {
+        cpu.csr.write_direct(CSR_VXSAT, 1);
+    }
+    cpu.csr.write_direct(CSR_VSTART, 0);
+    Ok(())
+}
+
+/// rounding increment of fixed-point operations, which depends on vxrm and
+/// the bits to be shifted out.
+fn rounding_increment(data: u128, shift: usize, vxrm: u64) -> u128 {
+    if shift == 0 {
+        return 0;
+    }
+    let lsb = (data >> shift) & 1;
+    let half = (data >> (shift - 1)) & 1;
+    let rest = (data & ((1 << (shift - 1)) - 1)) != 0;
+    match vxrm {
+        // round-to-nearest-up
+        0 => half,
+        // round-to-nearest-even
+        1 => half & (rest as u128 | lsb),
+        // round-down (truncate)
+        2 => 0,
+        // round-to-odd (OR bits into LSB)
+        _ => (lsb == 0 && (half == 1 || rest)) as u128,
+    }
+}
+
+fn roundoff_unsigned(data: u128, shift: usize, vxrm: u64) -> u128 {
+    (data >> shift) + rounding_increment(data, shift, vxrm)
+}
+
+fn roundoff_signed(data: i128, shift: usize, vxrm: u64) -> i128 {
+    (data >> shift) + rounding_increment(data as u128, shift, vxrm) as i128
+}
+
+/// saturate the signed value to the element width.
+fn clip_signed(ctx: &mut ElementContext, data: i128, bits: usize) -> u64 {
+    let max = (1i128 << (bits - 1)) - 1;
+    let min = -(1i128 << (bits - 1));
+    let result = match data {
+        data if data > max => {
+            ctx.vxsat = true;
+            max
+        }
+        data if data < min => {
+            ctx.vxsat = true;
+            min
+        }
+        data => data,
+    };
+    result as u64 & mask_bits(bits)
+}
+
+/// saturate the unsigned value to the element width.
+fn clip_unsigned(ctx: &mut ElementContext, data: u128, bits: usize) -> u64 {
+    let max = mask_bits(bits) as u128;
+    match data > max {
+        true => {
+            ctx.vxsat = true;
+            max as u64
+        }
+        false => data as u64,
+    }
+}
+
+//==============================================================================
+// Generic vector operations
+//==============================================================================
+
+/// vd[i] = op(vs2[i], vs1[i] / x[rs1] / imm)
+fn vector_binary(
+    cpu: &mut Cpu,
+    _addr: u64,
+    word: u32,
+    operand: Operand,
+    op: ElementOp,
+) -> Result<(), Trap> {
+    let config = match get_config(cpu, word) {
+        Ok(config) => config,
+        Err(e) => return Err(e),
+    };
+    let o = parse_type_v(word);
+    match check_operands(word, &o, operand, config.lmul_log2, config.lmul_log2) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    match check_mask_overlap(word, &o) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    let mut ctx = ElementContext::new(cpu, config.sew);
+    let v = cpu.v.clone();
+    for i in config.vstart..config.vl {
+        if !is_active(&v, o.vm, i) {
+            continue;
+        }
+        let a = read_element(&v, o.vs2, i, config.sew);
+        let b = get_operand(cpu, &v, operand, o.vs1, i, config.sew);
+        let data = op(&mut ctx, a, b) & mask_bits(config.sew);
+        write_element(&mut cpu.v, o.vd, i, config.sew, data);
+    }
+    finish(cpu, &ctx)
+}
+
+/// 2*SEW = op(SEW or 2*SEW vs2[i], SEW vs1[i] / x[rs1])
+fn vector_widening(
+    cpu: &mut Cpu,
+    _addr: u64,
+    word: u32,
+    operand: Operand,
+    wide_vs2: bool,
+    op: ElementOp,
+) -> Result<(), Trap> {
+    let config = match get_config(cpu, word) {
+        Ok(config) => config,
+        Err(e) => return Err(e),
+    };
+    if config.sew * 2 > ELEN {
+        return Err(illegal_instruction(word));
+    }
+    let o = parse_type_v(word);
+    let wide_emul_log2 = config.lmul_log2 + 1;
+    let (vs2_eew, vs2_emul_log2) = match wide_vs2 {
+        true => (config.sew * 2, wide_emul_log2),
+        false => (config.sew, config.lmul_log2),
+    };
+    match check_register_group(word, o.vd, wide_emul_log2) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    match check_register_group(word, o.vs2, vs2_emul_log2) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    if operand == Operand::Vector {
+        match check_register_group(word, o.vs1, config.lmul_log2) {
+            Ok(()) => {}
+            Err(e) => return Err(e),
+        };
+    }
+    match check_mask_overlap(word, &o) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    let mut ctx = ElementContext::new(cpu, config.sew);
+    let v = cpu.v.clone();
+    for i in config.vstart..config.vl {
+        if !is_active(&v, o.vm, i) {
+            continue;
+        }
+        let a = read_element(&v, o.vs2, i, vs2_eew);
+        let b = get_operand(cpu, &v, operand, o.vs1, i, config.sew);
+        let data = op(&mut ctx, a, b) & mask_bits(config.sew * 2);
+        write_element(&mut cpu.v, o.vd, i, config.sew * 2, data);
+    }
+    finish(cpu, &ctx)
+}
+
+/// SEW vd[i] = op(2*SEW vs2[i], SEW vs1[i] / x[rs1] / imm)
+fn vector_narrowing(
+    cpu: &mut Cpu,
+    _addr: u64,
+    word: u32,
+    operand: Operand,
+    op: ElementOp,
+) -> Result<(), Trap> {
+    let config = match get_config(cpu, word) {
+        Ok(config) => config,
+        Err(e) => return Err(e),
+    };
+    if config.sew * 2 > ELEN {
+        return Err(illegal_instruction(word));
+    }
+    let o = parse_type_v(word);
+    match check_register_group(word, o.vd, config.lmul_log2) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    match check_register_group(word, o.vs2, config.lmul_log2 + 1) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    if operand == Operand::Vector {
+        match check_register_group(word, o.vs1, config.lmul_log2) {
+            Ok(()) => {}
+            Err(e) => return Err(e),
+        };
+    }
+    match check_mask_overlap(word, &o) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    let mut ctx = ElementContext::new(cpu, config.sew);
+    let v = cpu.v.clone();
+    for i in config.vstart..config.vl {
+        if !is_active(&v, o.vm, i) {
+            continue;
+        }
+        let a = read_element(&v, o.vs2, i, config.sew * 2);
+        let b = get_operand(cpu, &v, operand, o.vs1, i, config.sew);
+        let data = op(&mut ctx, a, b) & mask_bits(config.sew);
+        write_element(&mut cpu.v, o.vd, i, config.sew, data);
+    }
+    finish(cpu, &ctx)
+}
+
+/// vd.mask[i] = op(vs2[i], vs1[i] / x[rs1] / imm) != 0
+fn vector_compare(
+    cpu: &mut Cpu,
+    _addr: u64,
+    word: u32,
+    operand: Operand,
+    op: ElementOp,
+) -> Result<(), Trap> {
+    let config = match get_config(cpu, word) {
+        Ok(config) => config,
+        Err(e) => return Err(e),
+    };
+    let o = parse_type_v(word);
+    match check_operands(word, &o, operand, 0, config.lmul_log2) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    let mut ctx = ElementContext::new(cpu, config.sew);
+    let v = cpu.v.clone();
+    for i in config.vstart..config.vl {
+        if !is_active(&v, o.vm, i) {
+            continue;
+        }
+        let a = read_element(&v, o.vs2, i, config.sew);
+        let b = get_operand(cpu, &v, operand, o.vs1, i, config.sew);
+        let data = op(&mut ctx, a, b) != 0;
+        write_mask(&mut cpu.v, o.vd, i, data);
+    }
+    finish(cpu, &ctx)
+}
+
+/// vd[i] = op(vs2[i], vs1[i] / x[rs1] / imm, v0.mask[i])
+/// vadc and vsbc always take the carry input from v0, so that vm=1 is reserved.
+fn vector_carry(
+    cpu: &mut Cpu,
+    _addr: u64,
+    word: u32,
+    operand: Operand,
+    op: CarryOp,
+) -> Result<(), Trap> {
+    let config = match get_config(cpu, word) {
+        Ok(config) => config,
+        Err(e) => return Err(e),
+    };
+    let o = parse_type_v(word);
+    if o.vm || o.vd == 0 {
+        return Err(illegal_instruction(word));
+    }
+    match check_operands(word, &o, operand, config.lmul_log2, config.lmul_log2) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    let mut ctx = ElementContext::new(cpu, config.sew);
+    let v = cpu.v.clone();
+    for i in config.vstart..config.vl {
+        let a = read_element(&v, o.vs2, i, config.sew);
+        let b = get_operand(cpu, &v, operand, o.vs1, i, config.sew);
+        let carry = read_mask(&v, 0, i) as u64;
+        let data = op(&mut ctx, a, b, carry) & mask_bits(config.sew);
+        write_element(&mut cpu.v, o.vd, i, config.sew, data);
+    }
+    finish(cpu, &ctx)
+}
+
+/// vd.mask[i] = carry/borrow out of op(vs2[i], vs1[i] / x[rs1] / imm, v0.mask[i])
+/// The carry input is zero if vm=1.
+fn vector_carry_out(
+    cpu: &mut Cpu,
+    _addr: u64,
+    word: u32,
+    operand: Operand,
+    op: CarryOp,
+) -> Result<(), Trap> {
+    let config = match get_config(cpu, word) {
+        Ok(config) => config,
+        Err(e) => return Err(e),
+    };
+    let o = parse_type_v(word);
+    match check_operands(word, &o, operand, 0, config.lmul_log2) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    let mut ctx = ElementContext::new(cpu, config.sew);
+    let v = cpu.v.clone();
+    for i in config.vstart..config.vl {
+        let a = read_element(&v, o.vs2, i, config.sew);
+        let b = get_operand(cpu, &v, operand, o.vs1, i, config.sew);
+        let carry = match o.vm {
+            true => 0,
+            false => read_mask(&v, 0, i) as u64,
+        };
+        let data = op(&mut ctx, a, b, carry) != 0;
+        write_mask(&mut cpu.v, o.vd, i, data);
+    }
+    finish(cpu, &ctx)
+}
+
+/// vd[i] = v0.mask[i] ? vs1[i] / x[rs1] / imm : vs2[i]
+/// vmv.v.* shares the encoding with vm=1, where vs2 must be v0.
+fn vector_merge(cpu: &mut Cpu, _addr: u64, word: u32, operand: Operand) -> Result<(), Trap> {
+    let config = match get_config(cpu, word) {
+        Ok(config) => config,
+        Err(e) => return Err(e),
+    };
+    let o = parse_type_v(word);
+    if (o.vm && o.vs2 != 0) || (!o.vm && o.vd == 0) {
+        return Err(illegal_instruction(word));
+    }
+    match check_operands(word, &o, operand, config.lmul_log2, config.lmul_log2) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    let ctx = ElementContext::new(cpu, config.sew);
+    let v = cpu.v.clone();
+    for i in config.vstart..config.vl {
+        let data = match is_active(&v, o.vm, i) {
+            true => get_operand(cpu, &v, operand, o.vs1, i, config.sew),
+            false => read_element(&v, o.vs2, i, config.sew),
+        };
+        write_element(&mut cpu.v, o.vd, i, config.sew, data);
+    }
+    finish(cpu, &ctx)
+}
+
+/// vd[i] = op(vd[i], vs2[i], vs1[i] / x[rs1])
+/// The widening variant accumulates into 2*SEW vd.
+fn vector_multiply_add(
+    cpu: &mut Cpu,
+    _addr: u64,
+    word: u32,
+    operand: Operand,
+    widening: bool,
+    op: CarryOp,
+) -> Result<(), Trap> {
+    let config = match get_config(cpu, word) {
+        Ok(config) => config,
+        Err(e) => return Err(e),
+    };
+    let o = parse_type_v(word);
+    let (vd_eew, vd_emul_log2) = match widening {
+        true => (config.sew * 2, config.lmul_log2 + 1),
+        false => (config.sew, config.lmul_log2),
+    };
+    if vd_eew > ELEN {
+        return Err(illegal_instruction(word));
+    }
+    match check_register_group(word, o.vd, vd_emul_log2) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    match check_operands(word, &o, operand, 0, config.lmul_log2) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    match check_mask_overlap(word, &o) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    let mut ctx = ElementContext::new(cpu, config.sew);
+    let v = cpu.v.clone();
+    for i in config.vstart..config.vl {
+        if !is_active(&v, o.vm, i) {
+            continue;
+        }
+        let d = read_element(&v, o.vd, i, vd_eew);
+        let a = read_element(&v, o.vs2, i, config.sew);
+        let b = get_operand(cpu, &v, operand, o.vs1, i, config.sew);
+        let data = op(&mut ctx, d, a, b) & mask_bits(vd_eew);
+        write_element(&mut cpu.v, o.vd, i, vd_eew, data);
+    }
+    finish(cpu, &ctx)
+}
+
+/// vd[0] = op(... op(op(vs1[0], vs2[0]), vs2[1]) ..., vs2[vl-1])
+/// The widening variant sign or zero extends vs2 elements and accumulates in 2*SEW.
+fn vector_reduction(
+    cpu: &mut Cpu,
+    _addr: u64,
+    word: u32,
+    widening: bool,
+    op: ElementOp,
+) -> Result<(), Trap> {
+    let config = match get_config(cpu, word) {
+        Ok(config) => config,
+        Err(e) => return Err(e),
+    };
+    // Reduction instructions raise an illegal instruction exception if vstart is non-zero.
+    if config.vstart != 0 {
+        return Err(illegal_instruction(word));
+    }
+    let o = parse_type_v(word);
+    let eew = match widening {
+        true => config.sew * 2,
+        false => config.sew,
+    };
+    if eew > ELEN {
+        return Err(illegal_instruction(word));
+    }
+    match check_register_group(word, o.vs2, config.lmul_log2) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    let mut ctx = ElementContext::new(cpu, config.sew);
+    if config.vl == 0 {
+        return finish(cpu, &ctx);
+    }
+    let v = cpu.v.clone();
+    let mut acc = read_element(&v, o.vs1, 0, eew);
+    for i in 0..config.vl {
+        if !is_active(&v, o.vm, i) {
+            continue;
+        }
+        let data = read_element(&v, o.vs2, i, config.sew);
+        acc = op(&mut ctx, acc, data) & mask_bits(eew);
+    }
+    write_element(&mut cpu.v, o.vd, 0, eew, acc);
+    finish(cpu, &ctx)
+}
+
+/// vd.mask[i] = op(vs2.mask[i], vs1.mask[i])
+fn vector_mask_logical(
+    cpu: &mut Cpu,
+    _addr: u64,
+    word: u32,
+    op: fn(a: bool, b: bool) -> bool,
+) -> Result<(), Trap> {
+    let config = match get_config(cpu, word) {
+        Ok(config) => config,
+        Err(e) => return Err(e),
+    };
+    let o = parse_type_v(word);
+    if !o.vm {
+        return Err(illegal_instruction(word));
+    }
+    let ctx = ElementContext::new(cpu, config.sew);
+    let v = cpu.v.clone();
+    for i in config.vstart..config.vl {
+        let data = op(read_mask(&v, o.vs2, i), read_mask(&v, o.vs1, i));
+        write_mask(&mut cpu.v, o.vd, i, data);
+    }
+    finish(cpu, &ctx)
+}
+
+/// vd[i] = zero or sign extension of vs2[i] with EEW = SEW / factor.
+fn vector_extension(
+    cpu: &mut Cpu,
+    _addr: u64,
+    word: u32,
+    factor: usize,
+    signed: bool,
+) -> Result<(), Trap> {
+    let config = match get_config(cpu, word) {
+        Ok(config) => config,
+        Err(e) => return Err(e),
+    };
+    let o = parse_type_v(word);
+    let eew = config.sew / factor;
+    if eew < 8 {
+        return Err(illegal_instruction(word));
+    }
+    match check_register_group(word, o.vd, config.lmul_log2) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    match check_register_group(word, o.vs2, config.lmul_log2 - log2(factor)) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    match check_mask_overlap(word, &o) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    let ctx = ElementContext::new(cpu, config.sew);
+    let v = cpu.v.clone();
+    for i in config.vstart..config.vl {
+        if !is_active(&v, o.vm, i) {
+            continue;
+        }
+        let data = read_element(&v, o.vs2, i, eew);
+        let data = match signed {
+            true => sext(data, eew) as u64 & mask_bits(config.sew),
+            false => data,
+        };
+        write_element(&mut cpu.v, o.vd, i, config.sew, data);
+    }
+    finish(cpu, &ctx)
+}
+/// masked instructions are suffixed with v0.t, and instructions taking v0 as
+/// the carry input or merge selector are suffixed with v0.
+fn disassemble_vector_mask(vm: bool, mask: &str) -> String {
+    match vm {
+        true => String::new(),
+        false => format!(",{:}", mask),
+    }
+}
+
+fn disassemble_vector(
+    cpu: &Cpu,
+    mnemonic: &str,
+    word: u32,
+    operand: Operand,
+    mask: &str,
+) -> String {
+    let o = parse_type_v(word);
+    let mut s = String::new();
+    s += &format!("{0: <10} ", mnemonic);
+    s += &format!("v{:}", o.vd);
+    s += &format!(",v{:}", o.vs2);
+    match operand {
+        Operand::Vector => s += &format!(",v{:}", o.vs1),
+        Operand::Scalar => {
+            s += &format!(",{:}", REGISTERS.get(&o.vs1).unwrap());
+            s += &format!(":{:x}", cpu.x[o.vs1 as usize]);
+        }
+        _ => s += &format!(",{:x}", sext(o.vs1 as u64, 5)),
+    };
+    s += &disassemble_vector_mask(o.vm, mask);
+    s
+}
+
+fn disassemble_vv(cpu: &Cpu, mnemonic: &str, word: u32) -> String {
+    disassemble_vector(cpu, mnemonic, word, Operand::Vector, "v0.t")
+}
+
+fn disassemble_vx(cpu: &Cpu, mnemonic: &str, word: u32) -> String {
+    disassemble_vector(cpu, mnemonic, word, Operand::Scalar, "v0.t")
+}
+
+fn disassemble_vi(cpu: &Cpu, mnemonic: &str, word: u32) -> String {
+    disassemble_vector(cpu, mnemonic, word, Operand::Immediate, "v0.t")
+}
+
+fn disassemble_vvm(cpu: &Cpu, mnemonic: &str, word: u32) -> String {
+    disassemble_vector(cpu, mnemonic, word, Operand::Vector, "v0")
+}
+
+fn disassemble_vxm(cpu: &Cpu, mnemonic: &str, word: u32) -> String {
+    disassemble_vector(cpu, mnemonic, word, Operand::Scalar, "v0")
+}
+
+fn disassemble_vim(cpu: &Cpu, mnemonic: &str, word: u32) -> String {
+    disassemble_vector(cpu, mnemonic, word, Operand::Immediate, "v0")
+}
+
+fn disassemble_vv_multiply_add(_cpu: &Cpu, mnemonic: &str, word: u32) -> String {
+    let o = parse_type_v(word);
+    let mut s = String::new();
+    s += &format!("{0: <10} ", mnemonic);
+    s += &format!("v{:}", o.vd);
+    s += &format!(",v{:}", o.vs1);
+    s += &format!(",v{:}", o.vs2);
+    s += &disassemble_vector_mask(o.vm, "v0.t");
+    s
+}
+
+fn disassemble_vx_multiply_add(_cpu: &Cpu, mnemonic: &str, word: u32) -> String {
+    let o = parse_type_v(word);
+    let mut s = String::new();
+    s += &format!("{0: <10} ", mnemonic);
+    s += &format!("v{:}", o.vd);
+    s += &format!(",{:}", REGISTERS.get(&o.vs1).unwrap());
+    s += &format!(":{:x}", _cpu.x[o.vs1 as usize]);
+    s += &format!(",v{:}", o.vs2);
+    s += &disassemble_vector_mask(o.vm, "v0.t");
+    s
+}
+
+fn disassemble_v(_cpu: &Cpu, mnemonic: &str, word: u32) -> String {
+    let o = parse_type_v(word);
+    let mut s = String::new();
+    s += &format!("{0: <10} ", mnemonic);
+    s += &format!("v{:}", o.vd);
+    s += &disassemble_vector_mask(o.vm, "v0.t");
+    s
+}
+
+fn disassemble_v_v(_cpu: &Cpu, mnemonic: &str, word: u32) -> String {
+    let o = parse_type_v(word);
+    let mut s = String::new();
+    s += &format!("{0: <10} ", mnemonic);
+    s += &format!("v{:}", o.vd);
+    s += &format!(",v{:}", o.vs2);
+    s += &disassemble_vector_mask(o.vm, "v0.t");
+    s
+}
+
+fn disassemble_v_v1(_cpu: &Cpu, mnemonic: &str, word: u32) -> String {
+    let o = parse_type_v(word);
+    let mut s = String::new();
+    s += &format!("{0: <10} ", mnemonic);
+    s += &format!("v{:}", o.vd);
+    s += &format!(",v{:}", o.vs1);
+    s
+}
+
+fn disassemble_v_x(_cpu: &Cpu, mnemonic: &str, word: u32) -> String {
+    let o = parse_type_v(word);
+    let mut s = String::new();
+    s += &format!("{0: <10} ", mnemonic);
+    s += &format!("v{:}", o.vd);
+    s += &format!(",{:}", REGISTERS.get(&o.vs1).unwrap());
+    s += &format!(":{:x}", _cpu.x[o.vs1 as usize]);
+    s
+}
+
+fn disassemble_v_i(_cpu: &Cpu, mnemonic: &str, word: u32) -> String {
+    let o = parse_type_v(word);
+    let mut s = String::new();
+    s += &format!("{0: <10} ", mnemonic);
+    s += &format!("v{:}", o.vd);
+    s += &format!(",{:x}", sext(o.vs1 as u64, 5));
+    s
+}
+
+fn disassemble_x_v(_cpu: &Cpu, mnemonic: &str, word: u32) -> String {
+    let o = parse_type_v(word);
+    let mut s = String::new();
+    s += &format!("{0: <10} ", mnemonic);
+    s += &format!("{:}", REGISTERS.get(&o.vd).unwrap());
+    s += &format!(":{:x}", _cpu.x[o.vd as usize]);
+    s += &format!(",v{:}", o.vs2);
+    s += &disassemble_vector_mask(o.vm, "v0.t");
+    s
+}
+
+/// vtype is shown as e<SEW>,m<LMUL>,t{a,u},m{a,u} in the assembler syntax.
+fn disassemble_vtype(vtype: u64) -> String {
+    let lmul = match vtype & 0x7 {
+        0 => "m1",
+        1 => "m2",
+        2 => "m4",
+        3 => "m8",
+        5 => "mf8",
+        6 => "mf4",
+        7 => "mf2",
+        _ => "reserved",
+    };
+    let ta = match (vtype >> 6) & 1 {
+        0 => "tu",
+        _ => "ta",
+    };
+    let ma = match (vtype >> 7) & 1 {
+        0 => "mu",
+        _ => "ma",
+    };
+    format!("e{:},{:},{:},{:}", 8 << ((vtype >> 3) & 0x7), lmul, ta, ma)
+}
+
+fn disassemble_vsetvli(_cpu: &Cpu, mnemonic: &str, word: u32) -> String {
+    let o = parse_type_v(word);
+    let mut s = String::new();
+    s += &format!("{0: <10} ", mnemonic);
+    s += &format!("{:}", REGISTERS.get(&o.vd).unwrap());
+    s += &format!(":{:x}", _cpu.x[o.vd as usize]);
+    s += &format!(",{:}", REGISTERS.get(&o.vs1).unwrap());
+    s += &format!(":{:x}", _cpu.x[o.vs1 as usize]);
+    s += &format!(
+        ",{:}",
+        disassemble_vtype(((word & 0x7ff00000) >> 20) as u64)
+    );
+    s
+}
+
+fn disassemble_vsetivli(_cpu: &Cpu, mnemonic: &str, word: u32) -> String {
+    let o = parse_type_v(word);
+    let mut s = String::new();
+    s += &format!("{0: <10} ", mnemonic);
+    s += &format!("{:}", REGISTERS.get(&o.vd).unwrap());
+    s += &format!(":{:x}", _cpu.x[o.vd as usize]);
+    s += &format!(",{:x}", o.vs1);
+    s += &format!(
+        ",{:}",
+        disassemble_vtype(((word & 0x3ff00000) >> 20) as u64)
+    );
+    s
+}
+
+fn disassemble_vsetvl(_cpu: &Cpu, mnemonic: &str, word: u32) -> String {
+    let o = parse_type_v(word);
+    let mut s = String::new();
+    s += &format!("{0: <10} ", mnemonic);
+    s += &format!("{:}", REGISTERS.get(&o.vd).unwrap());
+    s += &format!(":{:x}", _cpu.x[o.vd as usize]);
+    s += &format!(",{:}", REGISTERS.get(&o.vs1).unwrap());
+    s += &format!(":{:x}", _cpu.x[o.vs1 as usize]);
+    s += &format!(",{:}", REGISTERS.get(&o.vs2).unwrap());
+    s += &format!(":{:x}", _cpu.x[o.vs2 as usize]);
+    s
+}
+
+/// the mnemonic of vector loads and stores is completed with the element width
+/// and the number of fields in a segment.
+fn disassemble_vector_memory_mnemonic(mnemonic: &str, word: u32) -> String {
+    let o = parse_type_vmem(word);
+    let seg = match o.nf {
+        1 => String::new(),
+        nf => format!("seg{:}", nf),
+    };
+    match mnemonic {
+        "vle.v" => format!("vl{:}e{:}.v", seg, o.eew),
+        "vleff.v" => format!("vl{:}e{:}ff.v", seg, o.eew),
+        "vlse.v" => format!("vls{:}e{:}.v", seg, o.eew),
+        "vluxei.v" => format!("vlux{:}ei{:}.v", seg, o.eew),
+        "vloxei.v" => format!("vlox{:}ei{:}.v", seg, o.eew),
+        "vlre.v" => format!("vl{:}re{:}.v", o.nf, o.eew),
+        "vse.v" => format!("vs{:}e{:}.v", seg, o.eew),
+        "vsse.v" => format!("vss{:}e{:}.v", seg, o.eew),
+        "vsuxei.v" => format!("vsux{:}ei{:}.v", seg, o.eew),
+        "vsoxei.v" => format!("vsox{:}ei{:}.v", seg, o.eew),
+        "vsr.v" => format!("vs{:}r.v", o.nf),
+        _ => mnemonic.to_string(),
+    }
+}
+
+fn disassemble_vector_load_store(_cpu: &Cpu, mnemonic: &str, word: u32) -> String {
+    let o = parse_type_vmem(word);
+    let mut s = String::new();
+    s += &format!(
+        "{0: <10} ",
+        disassemble_vector_memory_mnemonic(mnemonic, word)
+    );
+    s += &format!("v{:}", o.vd);
+    s += &format!(",({:}", REGISTERS.get(&o.rs1).unwrap());
+    s += &format!(":{:x})", _cpu.x[o.rs1 as usize]);
+    match (word & 0x0c000000) >> 26 {
+        0 => {}
+        2 => {
+            s += &format!(",{:}", REGISTERS.get(&o.rs2).unwrap());
+            s += &format!(":{:x}", _cpu.x[o.rs2 as usize]);
+        }
+        _ => s += &format!(",v{:}", o.rs2),
+    };
+    s += &disassemble_vector_mask(o.vm, "v0.t");
+    s
+}
+
+//==============================================================================
+// Element operations
+//==============================================================================
+
+fn op_add(_ctx: &mut ElementContext, a: u64, b: u64) -> u64 {
+    a.wrapping_add(b)
+}
+
+fn op_sub(_ctx: &mut ElementContext, a: u64, b: u64) -> u64 {
+    a.wrapping_sub(b)
+}
+
+fn op_rsub(_ctx: &mut ElementContext, a: u64, b: u64) -> u64 {
+    b.wrapping_sub(a)
+}
+
+fn op_minu(_ctx: &mut ElementContext, a: u64, b: u64) -> u64 {
+    a.min(b)
+}
+
+fn op_min(ctx: &mut ElementContext, a: u64, b: u64) -> u64 {
+    sext(a, ctx.sew).min(sext(b, ctx.sew)) as u64
+}
+
+fn op_maxu(_ctx: &mut ElementContext, a: u64, b: u64) -> u64 {
+    a.max(b)
+}
+
+fn op_max(ctx: &mut ElementContext, a: u64, b: u64) -> u64 {
+    sext(a, ctx.sew).max(sext(b, ctx.sew)) as u64
+}
+
+fn op_and(_ctx: &mut ElementContext, a: u64, b: u64) -> u64 {
+    a & b
+}
+
+fn op_or(_ctx: &mut ElementContext, a: u64, b: u64) -> u64 {
+    a | b
+}
+
+fn op_xor(_ctx: &mut ElementContext, a: u64, b: u64) -> u64 {
+    a ^ b
+}
+
+/// only the low lg2(SEW) bits of the shift amount are used.
+fn op_sll(ctx: &mut ElementContext, a: u64, b: u64) -> u64 {
+    a << (b & (ctx.sew as u64 - 1))
+}
+
+fn op_srl(ctx: &mut ElementContext, a: u64, b: u64) -> u64 {
+    a >> (b & (ctx.sew as u64 - 1))
+}
+
+fn op_sra(ctx: &mut ElementContext, a: u64, b: u64) -> u64 {
+    (sext(a, ctx.sew) >> (b & (ctx.sew as u64 - 1))) as u64
+}
+
+fn op_mul(_ctx: &mut ElementContext, a: u64, b: u64) -> u64 {
+    a.wrapping_mul(b)
+}
+
+fn op_mulh(ctx: &mut ElementContext, a: u64, b: u64) -> u64 {
+    ((sext(a, ctx.sew) as i128 * sext(b, ctx.sew) as i128) >> ctx.sew) as u64
+}
+
+fn op_mulhu(ctx: &mut ElementContext, a: u64, b: u64) -> u64 {
+    ((a as u128 * b as u128) >> ctx.sew) as u64
+}
+
+fn op_mulhsu(ctx: &mut ElementContext, a: u64, b: u64) -> u64 {
+    ((sext(a, ctx.sew) as i128 * b as i128) >> ctx.sew) as u64
+}
+
+/// division by zero returns all ones as the scalar divu.
+fn op_divu(_ctx: &mut ElementContext, a: u64, b: u64) -> u64 {
+    match b {
+        0 => 0xffffffff_ffffffff,
+        _ => a / b,
+    }
+}
+
+/// division by zero returns -1, and overflow returns the dividend.
+fn op_div(ctx: &mut ElementContext, a: u64, b: u64) -> u64 {
+    let a = sext(a, ctx.sew);
+    let b = sext(b, ctx.sew);
+    match b {
+        0 => 0xffffffff_ffffffff,
+        -1 => a.wrapping_neg() as u64,
+        _ => (a / b) as u64,
+    }
+}
+
+fn op_remu(_ctx: &mut ElementContext, a: u64, b: u64) -> u64 {
+    match b {
+        0 => a,
+        _ => a % b,
+    }
+}
+
+fn op_rem(ctx: &mut ElementContext, a: u64, b: u64) -> u64 {
+    let a = sext(a, ctx.sew);
+    let b = sext(b, ctx.sew);
+    match b {
+        0 => a as u64,
+        -1 => 0,
+        _ => (a % b) as u64,
+    }
+}
+
+fn op_seq(_ctx: &mut ElementContext, a: u64, b: u64) -> u64 {
+    (a == b) as u64
+}
+
+fn op_sne(_ctx: &mut ElementContext, a: u64, b: u64) -> u64 {
+    (a != b) as u64
+}
+
+fn op_sltu(_ctx: &mut ElementContext, a: u64, b: u64) -> u64 {
+    (a < b) as u64
+}
+
+fn op_slt(ctx: &mut ElementContext, a: u64, b: u64) -> u64 {
+    (sext(a, ctx.sew) < sext(b, ctx.sew)) as u64
+}
+
+fn op_sleu(_ctx: &mut ElementContext, a: u64, b: u64) -> u64 {
+    (a <= b) as u64
+}
+
+fn op_sle(ctx: &mut ElementContext, a: u64, b: u64) -> u64 {
+    (sext(a, ctx.sew) <= sext(b, ctx.sew)) as u64
+}
+
+fn op_sgtu(_ctx: &mut ElementContext, a: u64, b: u64) -> u64 {
+    (a > b) as u64
+}
+
+fn op_sgt(ctx: &mut ElementContext, a: u64, b: u64) -> u64 {
+    (sext(a, ctx.sew) > sext(b, ctx.sew)) as u64
+}
+
+fn op_adc(_ctx: &mut ElementContext, a: u64, b: u64, carry: u64) -> u64 {
+    a.wrapping_add(b).wrapping_add(carry)
+}
+
+fn op_madc(ctx: &mut ElementContext, a: u64, b: u64, carry: u64) -> u64 {
+    ((a as u128 + b as u128 + carry as u128) >> ctx.sew) as u64
+}
+
+fn op_sbc(_ctx: &mut ElementContext, a: u64, b: u64, borrow: u64) -> u64 {
+    a.wrapping_sub(b).wrapping_sub(borrow)
+}
+
+fn op_msbc(_ctx: &mut ElementContext, a: u64, b: u64, borrow: u64) -> u64 {
+    ((a as u128) < (b as u128 + borrow as u128)) as u64
+}
+
+fn op_macc(_ctx: &mut ElementContext, d: u64, a: u64, b: u64) -> u64 {
+    d.wrapping_add(b.wrapping_mul(a))
+}
+
+fn op_nmsac(_ctx: &mut ElementContext, d: u64, a: u64, b: u64) -> u64 {
+    d.wrapping_sub(b.wrapping_mul(a))
+}
+
+fn op_madd(_ctx: &mut ElementContext, d: u64, a: u64, b: u64) -> u64 {
+    b.wrapping_mul(d).wrapping_add(a)
+}
+
+fn op_nmsub(_ctx: &mut ElementContext, d: u64, a: u64, b: u64) -> u64 {
+    a.wrapping_sub(b.wrapping_mul(d))
+}
+
+fn op_waddu(_ctx: &mut ElementContext, a: u64, b: u64) -> u64 {
+    a.wrapping_add(b)
+}
+
+fn op_wadd(ctx: &mut ElementContext, a: u64, b: u64) -> u64 {
+    sext(a, ctx.sew).wrapping_add(sext(b, ctx.sew)) as u64
+}
+
+fn op_wsubu(_ctx: &mut ElementContext, a: u64, b: u64) -> u64 {
+    a.wrapping_sub(b)
+}
+
+fn op_wsub(ctx: &mut ElementContext, a: u64, b: u64) -> u64 {
+    sext(a, ctx.sew).wrapping_sub(sext(b, ctx.sew)) as u64
+}
+
+/// vs2 of the .w form is already 2*SEW.
+fn op_wadd_w(ctx: &mut ElementContext, a: u64, b: u64) -> u64 {
+    a.wrapping_add(sext(b, ctx.sew) as u64)
+}
+
+fn op_wsub_w(ctx: &mut ElementContext, a: u64, b: u64) -> u64 {
+    a.wrapping_sub(sext(b, ctx.sew) as u64)
+}
+
+fn op_wmulu(_ctx: &mut ElementContext, a: u64, b: u64) -> u64 {
+    a.wrapping_mul(b)
+}
+
+fn op_wmul(ctx: &mut ElementContext, a: u64, b: u64) -> u64 {
+    sext(a, ctx.sew).wrapping_mul(sext(b, ctx.sew)) as u64
+}
+
+fn op_wmulsu(ctx: &mut ElementContext, a: u64, b: u64) -> u64 {
+    sext(a, ctx.sew).wrapping_mul(b as i64) as u64
+}
+
+fn op_wmaccu(_ctx: &mut ElementContext, d: u64, a: u64, b: u64) -> u64 {
+    d.wrapping_add(a.wrapping_mul(b))
+}
+
+fn op_wmacc(ctx: &mut ElementContext, d: u64, a: u64, b: u64) -> u64 {
+    d.wrapping_add(sext(a, ctx.sew).wrapping_mul(sext(b, ctx.sew)) as u64)
+}
+
+/// signed vs1/rs1 * unsigned vs2.
+fn op_wmaccsu(ctx: &mut ElementContext, d: u64, a: u64, b: u64) -> u64 {
+    d.wrapping_add(sext(b, ctx.sew).wrapping_mul(a as i64) as u64)
+}
+
+/// unsigned rs1 * signed vs2.
+fn op_wmaccus(ctx: &mut ElementContext, d: u64, a: u64, b: u64) -> u64 {
+    d.wrapping_add(sext(a, ctx.sew).wrapping_mul(b as i64) as u64)
+}
+
+fn op_wredsum(ctx: &mut ElementContext, acc: u64, data: u64) -> u64 {
+    acc.wrapping_add(sext(data, ctx.sew) as u64)
+}
+
+fn op_saddu(ctx: &mut ElementContext, a: u64, b: u64) -> u64 {
+    let sew = ctx.sew;
+    clip_unsigned(ctx, a as u128 + b as u128, sew)
+}
+
+fn op_sadd(ctx: &mut ElementContext, a: u64, b: u64) -> u64 {
+    let sew = ctx.sew;
+    clip_signed(ctx, sext(a, sew) as i128 + sext(b, sew) as i128, sew)
+}
+
+fn op_ssubu(ctx: &mut ElementContext, a: u64, b: u64) -> u64 {
+    match a < b {
+        true => {
+            ctx.vxsat = true;
+            0
+        }
+        false => a - b,
+    }
+}
+
+fn op_ssub(ctx: &mut ElementContext, a: u64, b: u64) -> u64 {
+    let sew = ctx.sew;
+    clip_signed(ctx, sext(a, sew) as i128 - sext(b, sew) as i128, sew)
+}
+
+fn op_aaddu(ctx: &mut ElementContext, a: u64, b: u64) -> u64 {
+    roundoff_unsigned(a as u128 + b as u128, 1, ctx.vxrm) as u64
+}
+
+fn op_aadd(ctx: &mut ElementContext, a: u64, b: u64) -> u64 {
+    let data = sext(a, ctx.sew) as i128 + sext(b, ctx.sew) as i128;
+    roundoff_signed(data, 1, ctx.vxrm) as u64
+}
+
+fn op_asubu(ctx: &mut ElementContext, a: u64, b: u64) -> u64 {
+    roundoff_signed(a as i128 - b as i128, 1, ctx.vxrm) as u64
+}
+
+fn op_asub(ctx: &mut ElementContext, a: u64, b: u64) -> u64 {
+    let data = sext(a, ctx.sew) as i128 - sext(b, ctx.sew) as i128;
+    roundoff_signed(data, 1, ctx.vxrm) as u64
+}
+
+/// signed fractional multiply with rounding and saturation.
+fn op_smul(ctx: &mut ElementContext, a: u64, b: u64) -> u64 {
+    let sew = ctx.sew;
+    let data = sext(a, sew) as i128 * sext(b, sew) as i128;
+    let data = roundoff_signed(data, sew - 1, ctx.vxrm);
+    clip_signed(ctx, data, sew)
+}
+
+fn op_ssrl(ctx: &mut ElementContext, a: u64, b: u64) -> u64 {
+    let shift = (b & (ctx.sew as u64 - 1)) as usize;
+    roundoff_unsigned(a as u128, shift, ctx.vxrm) as u64
+}
+
+fn op_ssra(ctx: &mut ElementContext, a: u64, b: u64) -> u64 {
+    let shift = (b & (ctx.sew as u64 - 1)) as usize;
+    roundoff_signed(sext(a, ctx.sew) as i128, shift, ctx.vxrm) as u64
+}
+
+/// vs2 of narrowing shifts is 2*SEW, so that the low lg2(2*SEW) bits of the shift
+/// amount are used.
+fn op_nsrl(ctx: &mut ElementContext, a: u64, b: u64) -> u64 {
+    a >> (b & (ctx.sew as u64 * 2 - 1))
+}
+
+fn op_nsra(ctx: &mut ElementContext, a: u64, b: u64) -> u64 {
+    (sext(a, ctx.sew * 2) >> (b & (ctx.sew as u64 * 2 - 1))) as u64
+}
+
+fn op_nclipu(ctx: &mut ElementContext, a: u64, b: u64) -> u64 {
+    let sew = ctx.sew;
+    let shift = (b & (sew as u64 * 2 - 1)) as usize;
+    let data = roundoff_unsigned(a as u128, shift, ctx.vxrm);
+    clip_unsigned(ctx, data, sew)
+}
+
+fn op_nclip(ctx: &mut ElementContext, a: u64, b: u64) -> u64 {
+    let sew = ctx.sew;
+    let shift = (b & (sew as u64 * 2 - 1)) as usize;
+    let data = roundoff_signed(sext(a, sew * 2) as i128, shift, ctx.vxrm);
+    clip_signed(ctx, data, sew)
+}
+
+fn mask_andn(a: bool, b: bool) -> bool {
+    a & !b
+}
+
+fn mask_and(a: bool, b: bool) -> bool {
+    a & b
+}
+
+fn mask_or(a: bool, b: bool) -> bool {
+    a | b
+}
+
+fn mask_xor(a: bool, b: bool) -> bool {
+    a ^ b
+}
+
+fn mask_orn(a: bool, b: bool) -> bool {
+    a | !b
+}
+
+fn mask_nand(a: bool, b: bool) -> bool {
+    !(a & b)
+}
+
+fn mask_nor(a: bool, b: bool) -> bool {
+    !(a | b)
+}
+
+fn mask_xnor(a: bool, b: bool) -> bool {
+    !(a ^ b)
+}
+//==============================================================================
+// Configuration-Setting Instructions
+//==============================================================================
+
+/// set vtype and vl from the application vector length (AVL).
+/// Unsupported vtype sets vill and clears the other bits of vtype and vl.
+fn set_vector_config(cpu: &mut Cpu, word: u32, rd: u8, avl: u64, vtype: u64) -> Result<(), Trap> {
+    match check_vector_enabled(cpu, word) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    let xlen = match cpu.xlen {
+        Xlen::X32 => 32,
+        Xlen::X64 => 64,
+    };
+    let sew = 8 << ((vtype >> 3) & 0x7);
+    let lmul_log2 = match vtype & 0x7 {
+        lmul if lmul < 4 => lmul as i32,
+        lmul => lmul as i32 - 8,
+    };
+    let vill = (vtype & mask_bits(xlen)) >> 8 != 0
+        || (vtype & 0x7) == 4
+        || sew > ELEN
+        || (lmul_log2 < 0 && sew > (ELEN >> -lmul_log2));
+    let vl = match vill {
+        true => {
+            cpu.csr.write_direct(CSR_VTYPE, CSR_VTYPE_VILL);
+            0
+        }
+        false => {
+            let vlmax = get_vlmax(cpu, sew, lmul_log2) as u64;
+            cpu.csr.write_direct(CSR_VTYPE, vtype & 0xff);
+            avl.min(vlmax)
+        }
+    };
+    cpu.csr.write_direct(CSR_VL, vl);
+    cpu.csr.write_direct(CSR_VSTART, 0);
+    cpu.x[rd as usize] = vl as i64;
+    Ok(())
+}
+
+/// AVL is x[rs1], or VLMAX if rs1=x0 and rd!=x0. If both are x0, the current vl is kept.
+fn get_avl(cpu: &mut Cpu, rd: u8, rs1: u8) -> u64 {
+    match (rd, rs1) {
+        (0, 0) => cpu.csr.read_direct(CSR_VL),
+        (_, 0) => 0xffffffff_ffffffff,
+        (_, rs1) => unsigned(cpu, cpu.x[rs1 as usize]),
+    }
+}
+
+/// [vsetvli rd,rs1,vtypei]
+fn vsetvli(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    let o = parse_type_v(word);
+    let avl = get_avl(cpu, o.vd, o.vs1);
+    let vtype = ((word & 0x7ff00000) >> 20) as u64;
+    set_vector_config(cpu, word, o.vd, avl, vtype)
+}
+
+/// [vsetivli rd,uimm,vtypei]
+fn vsetivli(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    let o = parse_type_v(word);
+    let vtype = ((word & 0x3ff00000) >> 20) as u64;
+    set_vector_config(cpu, word, o.vd, o.vs1 as u64, vtype)
+}
+
+/// [vsetvl rd,rs1,rs2]
+fn vsetvl(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    let o = parse_type_v(word);
+    let avl = get_avl(cpu, o.vd, o.vs1);
+    let vtype = unsigned(cpu, cpu.x[o.vs2 as usize]);
+    set_vector_config(cpu, word, o.vd, avl, vtype)
+}
+
+//==============================================================================
+// Vector Loads and Stores
+//==============================================================================
+
+fn load_element(cpu: &mut Cpu, v_addr: u64, eew: usize) -> Result<u64, Trap> {
+    match eew {
+        8 => match cpu.mmu.read8(v_addr) {
+            Ok(data) => Ok(data as u64),
+            Err(e) => Err(e),
+        },
+        16 => match cpu.mmu.read16(v_addr) {
+            Ok(data) => Ok(data as u64),
+            Err(e) => Err(e),
+        },
+        32 => match cpu.mmu.read32(v_addr) {
+            Ok(data) => Ok(data as u64),
+            Err(e) => Err(e),
+        },
+        _ => cpu.mmu.read64(v_addr),
+    }
+}
+
+fn store_element(cpu: &mut Cpu, v_addr: u64, eew: usize, data: u64) -> Result<(), Trap> {
+    match eew {
+        8 => cpu.mmu.write8(v_addr, data as u8),
+        16 => cpu.mmu.write16(v_addr, data as u16),
+        32 => cpu.mmu.write32(v_addr, data as u32),
+        _ => cpu.mmu.write64(v_addr, data),
+    }
+}
+
+/// unit-stride, strided and indexed loads and stores with nf fields per segment.
+/// Indexed accesses take the data EEW from SEW and the index EEW from the width field.
+/// A trap on an element records the element index to vstart so that the instruction
+/// can be resumed. Fault-only-first loads instead trim vl if the faulting element is
+/// not the first.
+fn vector_load_store(
+    cpu: &mut Cpu,
+    _addr: u64,
+    word: u32,
+    access: Access,
+    store: bool,
+    fault_only_first: bool,
+) -> Result<(), Trap> {
+    let config = match get_config(cpu, word) {
+        Ok(config) => config,
+        Err(e) => return Err(e),
+    };
+    let o = parse_type_vmem(word);
+    let log2_sew = log2(config.sew);
+    let (data_eew, data_emul_log2) = match access {
+        Access::Indexed => (config.sew, config.lmul_log2),
+        _ => (o.eew, config.lmul_log2 + log2(o.eew) - log2_sew),
+    };
+    match check_register_group(word, o.vd, data_emul_log2) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    let regs = 1 << data_emul_log2.max(0);
+    if o.nf * regs > 8 || o.vd as usize + o.nf * regs > 32 {
+        return Err(illegal_instruction(word));
+    }
+    if access == Access::Indexed {
+        let index_emul_log2 = config.lmul_log2 + log2(o.eew) - log2_sew;
+        match check_register_group(word, o.rs2, index_emul_log2) {
+            Ok(()) => {}
+            Err(e) => return Err(e),
+        };
+    }
+    if !store && !o.vm && o.vd == 0 {
+        return Err(illegal_instruction(word));
+    }
+    let bytes = (data_eew / 8) as u64;
+    let base = cpu.x[o.rs1 as usize] as u64;
+    let stride = match access {
+        Access::UnitStride => o.nf as u64 * bytes,
+        Access::Strided => cpu.x[o.rs2 as usize] as u64,
+        Access::Indexed => 0,
+    };
+    let ctx = ElementContext::new(cpu, config.sew);
+    let v = cpu.v.clone();
+    for i in config.vstart..config.vl {
+        if !is_active(&v, o.vm, i) {
+            continue;
+        }
+        let element_addr = match access {
+            Access::Indexed => base.wrapping_add(read_element(&v, o.rs2, i, o.eew)),
+            _ => base.wrapping_add(stride.wrapping_mul(i as u64)),
+        };
+        for field in 0..o.nf {
+            let v_addr = element_addr.wrapping_add(field as u64 * bytes);
+            let reg = o.vd + (field * regs) as u8;
+            let result = match store {
+                true => {
+                    let data = read_element(&v, reg, i, data_eew);
+                    store_element(cpu, v_addr, data_eew, data)
+                }
+                false => match load_element(cpu, v_addr, data_eew) {
+                    Ok(data) => {
+                        write_element(&mut cpu.v, reg, i, data_eew, data);
+                        Ok(())
+                    }
+                    Err(e) => Err(e),
+                },
+            };
+            match result {
+                Ok(()) => {}
+                Err(e) => {
+                    if fault_only_first && i != 0 {
+                        cpu.csr.write_direct(CSR_VL, i as u64);
+                        return finish(cpu, &ctx);
+                    }
+                    cpu.csr.write_direct(CSR_VSTART, i as u64);
+                    return Err(e);
+                }
+            };
+        }
+    }
+    finish(cpu, &ctx)
+}
+
+/// whole register loads and stores, and mask loads and stores. They access the
+/// bytes ignoring vtype, where evl is the number of elements to be transferred.
+fn vector_load_store_bytes(
+    cpu: &mut Cpu,
+    word: u32,
+    store: bool,
+    evl: usize,
+    eew: usize,
+) -> Result<(), Trap> {
+    let o = parse_type_vmem(word);
+    let vstart = cpu.csr.read_direct(CSR_VSTART) as usize;
+    let base = cpu.x[o.rs1 as usize] as u64;
+    let bytes = (eew / 8) as u64;
+    let ctx = ElementContext::new(cpu, eew);
+    for i in vstart..evl {
+        let v_addr = base.wrapping_add(i as u64 * bytes);
+        let result = match store {
+            true => {
+                let data = read_element(&cpu.v, o.vd, i, eew);
+                store_element(cpu, v_addr, eew, data)
+            }
+            false => match load_element(cpu, v_addr, eew) {
+                Ok(data) => {
+                    write_element(&mut cpu.v, o.vd, i, eew, data);
+                    Ok(())
+                }
+                Err(e) => Err(e),
+            },
+        };
+        match result {
+            Ok(()) => {}
+            Err(e) => {
+                cpu.csr.write_direct(CSR_VSTART, i as u64);
+                return Err(e);
+            }
+        };
+    }
+    finish(cpu, &ctx)
+}
+
+/// whole register accesses of nf registers, where nf must be 1, 2, 4 or 8.
+fn vector_whole_register(cpu: &mut Cpu, _addr: u64, word: u32, store: bool) -> Result<(), Trap> {
+    match check_vector_enabled(cpu, word) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    let o = parse_type_vmem(word);
+    if !o.vm || !o.nf.is_power_of_two() || (o.vd as usize) & (o.nf - 1) != 0 {
+        return Err(illegal_instruction(word));
+    }
+    let evl = o.nf * (cpu.v.len() / 32) / (o.eew / 8);
+    vector_load_store_bytes(cpu, word, store, evl, o.eew)
+}
+
+/// mask accesses of ceil(vl/8) bytes.
+fn vector_mask_load_store(cpu: &mut Cpu, _addr: u64, word: u32, store: bool) -> Result<(), Trap> {
+    let config = match get_config(cpu, word) {
+        Ok(config) => config,
+        Err(e) => return Err(e),
+    };
+    let o = parse_type_vmem(word);
+    if !o.vm || o.eew != 8 || o.nf != 1 {
+        return Err(illegal_instruction(word));
+    }
+    let evl = config.vl.div_ceil(8);
+    vector_load_store_bytes(cpu, word, store, evl, 8)
+}
+
+/// [vle<eew>.v vd,(rs1),vm]
+fn vle_v(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_load_store(cpu, addr, word, Access::UnitStride, false, false)
+}
+
+/// [vle<eew>ff.v vd,(rs1),vm]
+fn vleff_v(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_load_store(cpu, addr, word, Access::UnitStride, false, true)
+}
+
+/// [vlse<eew>.v vd,(rs1),rs2,vm]
+fn vlse_v(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_load_store(cpu, addr, word, Access::Strided, false, false)
+}
+
+/// [vluxei<eew>.v vd,(rs1),vs2,vm]
+fn vluxei_v(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_load_store(cpu, addr, word, Access::Indexed, false, false)
+}
+
+/// [vloxei<eew>.v vd,(rs1),vs2,vm]
+fn vloxei_v(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_load_store(cpu, addr, word, Access::Indexed, false, false)
+}
+
+/// [vlm.v vd,(rs1)]
+fn vlm_v(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_mask_load_store(cpu, addr, word, false)
+}
+
+/// [vl<nf>re<eew>.v vd,(rs1)]
+fn vlre_v(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_whole_register(cpu, addr, word, false)
+}
+
+/// [vse<eew>.v vs3,(rs1),vm]
+fn vse_v(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_load_store(cpu, addr, word, Access::UnitStride, true, false)
+}
+
+/// [vsse<eew>.v vs3,(rs1),rs2,vm]
+fn vsse_v(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_load_store(cpu, addr, word, Access::Strided, true, false)
+}
+
+/// [vsuxei<eew>.v vs3,(rs1),vs2,vm]
+fn vsuxei_v(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_load_store(cpu, addr, word, Access::Indexed, true, false)
+}
+
+/// [vsoxei<eew>.v vs3,(rs1),vs2,vm]
+fn vsoxei_v(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_load_store(cpu, addr, word, Access::Indexed, true, false)
+}
+
+/// [vsm.v vs3,(rs1)]
+fn vsm_v(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_mask_load_store(cpu, addr, word, true)
+}
+
+/// [vs<nf>r.v vs3,(rs1)]
+fn vsr_v(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    match (word & 0x00007000) >> 12 {
+        0 => vector_whole_register(cpu, addr, word, true),
+        _ => Err(illegal_instruction(word)),
+    }
+}
+
+//==============================================================================
+// Vector Mask Instructions
+//==============================================================================
+
+/// [vcpop.m rd,vs2,vm]
+fn vcpop_m(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    let config = match get_config(cpu, word) {
+        Ok(config) => config,
+        Err(e) => return Err(e),
+    };
+    if config.vstart != 0 {
+        return Err(illegal_instruction(word));
+    }
+    let o = parse_type_v(word);
+    let mut count = 0;
+    for i in 0..config.vl {
+        if is_active(&cpu.v, o.vm, i) && read_mask(&cpu.v, o.vs2, i) {
+            count += 1;
+        }
+    }
+    cpu.x[o.vd as usize] = count;
+    let ctx = ElementContext::new(cpu, config.sew);
+    finish(cpu, &ctx)
+}
+
+/// [vfirst.m rd,vs2,vm]
+fn vfirst_m(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    let config = match get_config(cpu, word) {
+        Ok(config) => config,
+        Err(e) => return Err(e),
+    };
+    if config.vstart != 0 {
+        return Err(illegal_instruction(word));
+    }
+    let o = parse_type_v(word);
+    let mut first = -1;
+    for i in 0..config.vl {
+        if is_active(&cpu.v, o.vm, i) && read_mask(&cpu.v, o.vs2, i) {
+            first = i as i64;
+            break;
+        }
+    }
+    cpu.x[o.vd as usize] = first;
+    let ctx = ElementContext::new(cpu, config.sew);
+    finish(cpu, &ctx)
+}
+
+/// set-before-first, set-including-first and set-only-first mask bits.
+/// The destination can not overlap the source or v0 of masked instruction.
+fn vector_set_first(
+    cpu: &mut Cpu,
+    _addr: u64,
+    word: u32,
+    before: bool,
+    including: bool,
+    after: bool,
+) -> Result<(), Trap> {
+    let config = match get_config(cpu, word) {
+        Ok(config) => config,
+        Err(e) => return Err(e),
+    };
+    let o = parse_type_v(word);
+    if config.vstart != 0 || o.vd == o.vs2 || (!o.vm && o.vd == 0) {
+        return Err(illegal_instruction(word));
+    }
+    let v = cpu.v.clone();
+    let mut found = false;
+    for i in 0..config.vl {
+        if !is_active(&v, o.vm, i) {
+            continue;
+        }
+        let data = match found {
+            true => after,
+            false => match read_mask(&v, o.vs2, i) {
+                true => {
+                    found = true;
+                    including
+                }
+                false => before,
+            },
+        };
+        write_mask(&mut cpu.v, o.vd, i, data);
+    }
+    let ctx = ElementContext::new(cpu, config.sew);
+    finish(cpu, &ctx)
+}
+
+/// [vmsbf.m vd,vs2,vm]
+fn vmsbf_m(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_set_first(cpu, addr, word, true, false, false)
+}
+
+/// [vmsif.m vd,vs2,vm]
+fn vmsif_m(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_set_first(cpu, addr, word, true, true, false)
+}
+
+/// [vmsof.m vd,vs2,vm]
+fn vmsof_m(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_set_first(cpu, addr, word, false, true, false)
+}
+
+/// [viota.m vd,vs2,vm]
+fn viota_m(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    let config = match get_config(cpu, word) {
+        Ok(config) => config,
+        Err(e) => return Err(e),
+    };
+    let o = parse_type_v(word);
+    if config.vstart != 0 {
+        return Err(illegal_instruction(word));
+    }
+    match check_register_group(word, o.vd, config.lmul_log2) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    match check_mask_overlap(word, &o) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    let v = cpu.v.clone();
+    let mut count = 0;
+    for i in 0..config.vl {
+        if !is_active(&v, o.vm, i) {
+            continue;
+        }
+        write_element(
+            &mut cpu.v,
+            o.vd,
+            i,
+            config.sew,
+            count & mask_bits(config.sew),
+        );
+        if read_mask(&v, o.vs2, i) {
+            count += 1;
+        }
+    }
+    let ctx = ElementContext::new(cpu, config.sew);
+    finish(cpu, &ctx)
+}
+
+/// [vid.v vd,vm]
+fn vid_v(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    let config = match get_config(cpu, word) {
+        Ok(config) => config,
+        Err(e) => return Err(e),
+    };
+    let o = parse_type_v(word);
+    match check_register_group(word, o.vd, config.lmul_log2) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    match check_mask_overlap(word, &o) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    for i in config.vstart..config.vl {
+        if is_active(&cpu.v, o.vm, i) {
+            write_element(
+                &mut cpu.v,
+                o.vd,
+                i,
+                config.sew,
+                i as u64 & mask_bits(config.sew),
+            );
+        }
+    }
+    let ctx = ElementContext::new(cpu, config.sew);
+    finish(cpu, &ctx)
+}
+
+//==============================================================================
+// Vector Permutation Instructions
+//==============================================================================
+
+/// [vmv.x.s rd,vs2]
+fn vmv_x_s(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    let config = match get_config(cpu, word) {
+        Ok(config) => config,
+        Err(e) => return Err(e),
+    };
+    let o = parse_type_v(word);
+    if !o.vm {
+        return Err(illegal_instruction(word));
+    }
+    let data = read_element(&cpu.v, o.vs2, 0, config.sew);
+    cpu.x[o.vd as usize] = signed(cpu, sext(data, config.sew));
+    let ctx = ElementContext::new(cpu, config.sew);
+    finish(cpu, &ctx)
+}
+
+/// [vmv.s.x vd,rs1]
+fn vmv_s_x(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    let config = match get_config(cpu, word) {
+        Ok(config) => config,
+        Err(e) => return Err(e),
+    };
+    let o = parse_type_v(word);
+    if !o.vm {
+        return Err(illegal_instruction(word));
+    }
+    if config.vstart < config.vl {
+        let data = cpu.x[o.vs1 as usize] as u64 & mask_bits(config.sew);
+        write_element(&mut cpu.v, o.vd, 0, config.sew, data);
+    }
+    let ctx = ElementContext::new(cpu, config.sew);
+    finish(cpu, &ctx)
+}
+
+/// vd[i+offset] = vs2[i] for slide up, and vd[i] = vs2[i+offset] for slide down,
+/// where the source elements beyond VLMAX read as zero.
+fn vector_slide(
+    cpu: &mut Cpu,
+    _addr: u64,
+    word: u32,
+    operand: Operand,
+    up: bool,
+) -> Result<(), Trap> {
+    let config = match get_config(cpu, word) {
+        Ok(config) => config,
+        Err(e) => return Err(e),
+    };
+    let o = parse_type_v(word);
+    match check_operands(word, &o, operand, config.lmul_log2, config.lmul_log2) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    match check_mask_overlap(word, &o) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    // The destination of slide up can not overlap the source.
+    if up && o.vd == o.vs2 {
+        return Err(illegal_instruction(word));
+    }
+    let offset = match operand {
+        Operand::Scalar => unsigned(cpu, cpu.x[o.vs1 as usize]),
+        _ => o.vs1 as u64,
+    };
+    let vlmax = get_vlmax(cpu, config.sew, config.lmul_log2) as u64;
+    let v = cpu.v.clone();
+    let start = match up {
+        true => (config.vstart as u64).max(offset),
+        false => config.vstart as u64,
+    };
+    for i in start..config.vl as u64 {
+        if !is_active(&v, o.vm, i as usize) {
+            continue;
+        }
+        let data = match up {
+            true => read_element(&v, o.vs2, (i - offset) as usize, config.sew),
+            false => match i.checked_add(offset) {
+                Some(index) if index < vlmax => read_element(&v, o.vs2, index as usize, config.sew),
+                _ => 0,
+            },
+        };
+        write_element(&mut cpu.v, o.vd, i as usize, config.sew, data);
+    }
+    let ctx = ElementContext::new(cpu, config.sew);
+    finish(cpu, &ctx)
+}
+
+/// vd[0] = x[rs1], vd[i+1] = vs2[i] for slide1up, and vd[i] = vs2[i+1],
+/// vd[vl-1] = x[rs1] for slide1down.
+fn vector_slide1(cpu: &mut Cpu, _addr: u64, word: u32, up: bool) -> Result<(), Trap> {
+    let config = match get_config(cpu, word) {
+        Ok(config) => config,
+        Err(e) => return Err(e),
+    };
+    let o = parse_type_v(word);
+    match check_operands(
+        word,
+        &o,
+        Operand::Scalar,
+        config.lmul_log2,
+        config.lmul_log2,
+    ) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    match check_mask_overlap(word, &o) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    if up && o.vd == o.vs2 {
+        return Err(illegal_instruction(word));
+    }
+    let scalar = cpu.x[o.vs1 as usize] as u64 & mask_bits(config.sew);
+    let v = cpu.v.clone();
+    for i in config.vstart..config.vl {
+        if !is_active(&v, o.vm, i) {
+            continue;
+        }
+        let data = match up {
+            true => match i {
+                0 => scalar,
+                _ => read_element(&v, o.vs2, i - 1, config.sew),
+            },
+            false => match i + 1 == config.vl {
+                true => scalar,
+                false => read_element(&v, o.vs2, i + 1, config.sew),
+            },
+        };
+        write_element(&mut cpu.v, o.vd, i, config.sew, data);
+    }
+    let ctx = ElementContext::new(cpu, config.sew);
+    finish(cpu, &ctx)
+}
+
+/// vd[i] = (index >= VLMAX) ? 0 : vs2[index], where index is vs1[i], x[rs1] or uimm.
+/// vrgatherei16 always takes 16-bit indices from vs1.
+fn vector_gather(
+    cpu: &mut Cpu,
+    _addr: u64,
+    word: u32,
+    operand: Operand,
+    index_eew: usize,
+) -> Result<(), Trap> {
+    let config = match get_config(cpu, word) {
+        Ok(config) => config,
+        Err(e) => return Err(e),
+    };
+    let o = parse_type_v(word);
+    match check_register_group(word, o.vd, config.lmul_log2) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    match check_register_group(word, o.vs2, config.lmul_log2) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    if operand == Operand::Vector {
+        let index_emul_log2 = config.lmul_log2 + log2(index_eew) - log2(config.sew);
+        match check_register_group(word, o.vs1, index_emul_log2) {
+            Ok(()) => {}
+            Err(e) => return Err(e),
+        };
+        if o.vd == o.vs1 {
+            return Err(illegal_instruction(word));
+        }
+    }
+    match check_mask_overlap(word, &o) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    if o.vd == o.vs2 {
+        return Err(illegal_instruction(word));
+    }
+    let vlmax = get_vlmax(cpu, config.sew, config.lmul_log2) as u64;
+    let v = cpu.v.clone();
+    for i in config.vstart..config.vl {
+        if !is_active(&v, o.vm, i) {
+            continue;
+        }
+        let index = match operand {
+            Operand::Vector => read_element(&v, o.vs1, i, index_eew),
+            Operand::Scalar => unsigned(cpu, cpu.x[o.vs1 as usize]),
+            _ => o.vs1 as u64,
+        };
+        let data = match index < vlmax {
+            true => read_element(&v, o.vs2, index as usize, config.sew),
+            false => 0,
+        };
+        write_element(&mut cpu.v, o.vd, i, config.sew, data);
+    }
+    let ctx = ElementContext::new(cpu, config.sew);
+    finish(cpu, &ctx)
+}
+
+/// [vrgather.vv vd,vs2,vs1,vm]
+fn vrgather_vv(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    let sew = match get_config(cpu, word) {
+        Ok(config) => config.sew,
+        Err(e) => return Err(e),
+    };
+    vector_gather(cpu, addr, word, Operand::Vector, sew)
+}
+
+/// [vrgather.vx vd,vs2,rs1,vm]
+fn vrgather_vx(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_gather(cpu, addr, word, Operand::Scalar, 0)
+}
+
+/// [vrgather.vi vd,vs2,uimm,vm]
+fn vrgather_vi(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_gather(cpu, addr, word, Operand::UnsignedImmediate, 0)
+}
+
+/// [vrgatherei16.vv vd,vs2,vs1,vm]
+fn vrgatherei16_vv(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_gather(cpu, addr, word, Operand::Vector, 16)
+}
+
+/// [vslideup.vx vd,vs2,rs1,vm]
+fn vslideup_vx(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_slide(cpu, addr, word, Operand::Scalar, true)
+}
+
+/// [vslideup.vi vd,vs2,uimm,vm]
+fn vslideup_vi(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_slide(cpu, addr, word, Operand::UnsignedImmediate, true)
+}
+
+/// [vslidedown.vx vd,vs2,rs1,vm]
+fn vslidedown_vx(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_slide(cpu, addr, word, Operand::Scalar, false)
+}
+
+/// [vslidedown.vi vd,vs2,uimm,vm]
+fn vslidedown_vi(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_slide(cpu, addr, word, Operand::UnsignedImmediate, false)
+}
+
+/// [vslide1up.vx vd,vs2,rs1,vm]
+fn vslide1up_vx(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_slide1(cpu, addr, word, true)
+}
+
+/// [vslide1down.vx vd,vs2,rs1,vm]
+fn vslide1down_vx(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_slide1(cpu, addr, word, false)
+}
+
+/// [vcompress.vm vd,vs2,vs1]
+fn vcompress_vm(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    let config = match get_config(cpu, word) {
+        Ok(config) => config,
+        Err(e) => return Err(e),
+    };
+    let o = parse_type_v(word);
+    if config.vstart != 0 || !o.vm || o.vd == o.vs2 || o.vd == o.vs1 {
+        return Err(illegal_instruction(word));
+    }
+    match check_operands(
+        word,
+        &o,
+        Operand::Scalar,
+        config.lmul_log2,
+        config.lmul_log2,
+    ) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    let v = cpu.v.clone();
+    let mut count = 0;
+    for i in 0..config.vl {
+        if read_mask(&v, o.vs1, i) {
+            let data = read_element(&v, o.vs2, i, config.sew);
+            write_element(&mut cpu.v, o.vd, count, config.sew, data);
+            count += 1;
+        }
+    }
+    let ctx = ElementContext::new(cpu, config.sew);
+    finish(cpu, &ctx)
+}
+
+/// copy whole registers regardless of vtype, where the number of registers is
+/// encoded in the simm field as nr-1.
+fn vector_move_whole(cpu: &mut Cpu, _addr: u64, word: u32) -> Result<(), Trap> {
+    match check_vector_enabled(cpu, word) {
+        Ok(()) => {}
+        Err(e) => return Err(e),
+    };
+    let o = parse_type_v(word);
+    let nr = o.vs1 as usize + 1;
+    if !o.vm || !nr.is_power_of_two() || (o.vd as usize | o.vs2 as usize) & (nr - 1) != 0 {
+        return Err(illegal_instruction(word));
+    }
+    let vlenb = cpu.v.len() / 32;
+    let vstart = cpu.csr.read_direct(CSR_VSTART) as usize;
+    let src = o.vs2 as usize * vlenb;
+    let dst = o.vd as usize * vlenb;
+    if vstart < nr * vlenb {
+        cpu.v
+            .copy_within(src + vstart..src + nr * vlenb, dst + vstart);
+    }
+    let ctx = ElementContext::new(cpu, 8);
+    finish(cpu, &ctx)
+}
+//==============================================================================
+// Vector Integer Arithmetic Instructions
+//==============================================================================
+
+/// [vadd.vv vd,vs2,vs1,vm]
+fn vadd_vv(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_binary(cpu, addr, word, Operand::Vector, op_add)
+}
+
+/// [vadd.vx vd,vs2,rs1,vm]
+fn vadd_vx(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_binary(cpu, addr, word, Operand::Scalar, op_add)
+}
+
+/// [vadd.vi vd,vs2,simm,vm]
+fn vadd_vi(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_binary(cpu, addr, word, Operand::Immediate, op_add)
+}
+
+/// [vsub.vv vd,vs2,vs1,vm]
+fn vsub_vv(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_binary(cpu, addr, word, Operand::Vector, op_sub)
+}
+
+/// [vsub.vx vd,vs2,rs1,vm]
+fn vsub_vx(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_binary(cpu, addr, word, Operand::Scalar, op_sub)
+}
+
+/// [vrsub.vx vd,vs2,rs1,vm]
+fn vrsub_vx(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_binary(cpu, addr, word, Operand::Scalar, op_rsub)
+}
+
+/// [vrsub.vi vd,vs2,simm,vm]
+fn vrsub_vi(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_binary(cpu, addr, word, Operand::Immediate, op_rsub)
+}
+
+/// [vminu.vv vd,vs2,vs1,vm]
+fn vminu_vv(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_binary(cpu, addr, word, Operand::Vector, op_minu)
+}
+
+/// [vminu.vx vd,vs2,rs1,vm]
+fn vminu_vx(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_binary(cpu, addr, word, Operand::Scalar, op_minu)
+}
+
+/// [vmin.vv vd,vs2,vs1,vm]
+fn vmin_vv(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_binary(cpu, addr, word, Operand::Vector, op_min)
+}
+
+/// [vmin.vx vd,vs2,rs1,vm]
+fn vmin_vx(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_binary(cpu, addr, word, Operand::Scalar, op_min)
+}
+
+/// [vmaxu.vv vd,vs2,vs1,vm]
+fn vmaxu_vv(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_binary(cpu, addr, word, Operand::Vector, op_maxu)
+}
+
+/// [vmaxu.vx vd,vs2,rs1,vm]
+fn vmaxu_vx(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_binary(cpu, addr, word, Operand::Scalar, op_maxu)
+}
+
+/// [vmax.vv vd,vs2,vs1,vm]
+fn vmax_vv(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_binary(cpu, addr, word, Operand::Vector, op_max)
+}
+
+/// [vmax.vx vd,vs2,rs1,vm]
+fn vmax_vx(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_binary(cpu, addr, word, Operand::Scalar, op_max)
+}
+
+/// [vand.vv vd,vs2,vs1,vm]
+fn vand_vv(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_binary(cpu, addr, word, Operand::Vector, op_and)
+}
+
+/// [vand.vx vd,vs2,rs1,vm]
+fn vand_vx(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_binary(cpu, addr, word, Operand::Scalar, op_and)
+}
+
+/// [vand.vi vd,vs2,simm,vm]
+fn vand_vi(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_binary(cpu, addr, word, Operand::Immediate, op_and)
+}
+
+/// [vor.vv vd,vs2,vs1,vm]
+fn vor_vv(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_binary(cpu, addr, word, Operand::Vector, op_or)
+}
+
+/// [vor.vx vd,vs2,rs1,vm]
+fn vor_vx(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_binary(cpu, addr, word, Operand::Scalar, op_or)
+}
+
+/// [vor.vi vd,vs2,simm,vm]
+fn vor_vi(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_binary(cpu, addr, word, Operand::Immediate, op_or)
+}
+
+/// [vxor.vv vd,vs2,vs1,vm]
+fn vxor_vv(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_binary(cpu, addr, word, Operand::Vector, op_xor)
+}
+
+/// [vxor.vx vd,vs2,rs1,vm]
+fn vxor_vx(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_binary(cpu, addr, word, Operand::Scalar, op_xor)
+}
+
+/// [vxor.vi vd,vs2,simm,vm]
+fn vxor_vi(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_binary(cpu, addr, word, Operand::Immediate, op_xor)
+}
+
+/// [vadc.vvm vd,vs2,vs1,v0]
+fn vadc_vvm(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_carry(cpu, addr, word, Operand::Vector, op_adc)
+}
+
+/// [vadc.vxm vd,vs2,rs1,v0]
+fn vadc_vxm(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_carry(cpu, addr, word, Operand::Scalar, op_adc)
+}
+
+/// [vadc.vim vd,vs2,simm,v0]
+fn vadc_vim(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_carry(cpu, addr, word, Operand::Immediate, op_adc)
+}
+
+/// [vsbc.vvm vd,vs2,vs1,v0]
+fn vsbc_vvm(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_carry(cpu, addr, word, Operand::Vector, op_sbc)
+}
+
+/// [vsbc.vxm vd,vs2,rs1,v0]
+fn vsbc_vxm(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_carry(cpu, addr, word, Operand::Scalar, op_sbc)
+}
+
+/// [vmseq.vv vd,vs2,vs1,vm]
+fn vmseq_vv(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_compare(cpu, addr, word, Operand::Vector, op_seq)
+}
+
+/// [vmseq.vx vd,vs2,rs1,vm]
+fn vmseq_vx(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_compare(cpu, addr, word, Operand::Scalar, op_seq)
+}
+
+/// [vmseq.vi vd,vs2,simm,vm]
+fn vmseq_vi(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_compare(cpu, addr, word, Operand::Immediate, op_seq)
+}
+
+/// [vmsne.vv vd,vs2,vs1,vm]
+fn vmsne_vv(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_compare(cpu, addr, word, Operand::Vector, op_sne)
+}
+
+/// [vmsne.vx vd,vs2,rs1,vm]
+fn vmsne_vx(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_compare(cpu, addr, word, Operand::Scalar, op_sne)
+}
+
+/// [vmsne.vi vd,vs2,simm,vm]
+fn vmsne_vi(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_compare(cpu, addr, word, Operand::Immediate, op_sne)
+}
+
+/// [vmsltu.vv vd,vs2,vs1,vm]
+fn vmsltu_vv(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_compare(cpu, addr, word, Operand::Vector, op_sltu)
+}
+
+/// [vmsltu.vx vd,vs2,rs1,vm]
+fn vmsltu_vx(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_compare(cpu, addr, word, Operand::Scalar, op_sltu)
+}
+
+/// [vmslt.vv vd,vs2,vs1,vm]
+fn vmslt_vv(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_compare(cpu, addr, word, Operand::Vector, op_slt)
+}
+
+/// [vmslt.vx vd,vs2,rs1,vm]
+fn vmslt_vx(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_compare(cpu, addr, word, Operand::Scalar, op_slt)
+}
+
+/// [vmsleu.vv vd,vs2,vs1,vm]
+fn vmsleu_vv(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_compare(cpu, addr, word, Operand::Vector, op_sleu)
+}
+
+/// [vmsleu.vx vd,vs2,rs1,vm]
+fn vmsleu_vx(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_compare(cpu, addr, word, Operand::Scalar, op_sleu)
+}
+
+/// [vmsleu.vi vd,vs2,simm,vm]
+fn vmsleu_vi(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_compare(cpu, addr, word, Operand::Immediate, op_sleu)
+}
+
+/// [vmsle.vv vd,vs2,vs1,vm]
+fn vmsle_vv(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_compare(cpu, addr, word, Operand::Vector, op_sle)
+}
+
+/// [vmsle.vx vd,vs2,rs1,vm]
+fn vmsle_vx(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_compare(cpu, addr, word, Operand::Scalar, op_sle)
+}
+
+/// [vmsle.vi vd,vs2,simm,vm]
+fn vmsle_vi(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_compare(cpu, addr, word, Operand::Immediate, op_sle)
+}
+
+/// [vmsgtu.vx vd,vs2,rs1,vm]
+fn vmsgtu_vx(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_compare(cpu, addr, word, Operand::Scalar, op_sgtu)
+}
+
+/// [vmsgtu.vi vd,vs2,simm,vm]
+fn vmsgtu_vi(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_compare(cpu, addr, word, Operand::Immediate, op_sgtu)
+}
+
+/// [vmsgt.vx vd,vs2,rs1,vm]
+fn vmsgt_vx(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_compare(cpu, addr, word, Operand::Scalar, op_sgt)
+}
+
+/// [vmsgt.vi vd,vs2,simm,vm]
+fn vmsgt_vi(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_compare(cpu, addr, word, Operand::Immediate, op_sgt)
+}
+
+/// [vsll.vv vd,vs2,vs1,vm]
+fn vsll_vv(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_binary(cpu, addr, word, Operand::Vector, op_sll)
+}
+
+/// [vsll.vx vd,vs2,rs1,vm]
+fn vsll_vx(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_binary(cpu, addr, word, Operand::Scalar, op_sll)
+}
+
+/// [vsll.vi vd,vs2,uimm,vm]
+fn vsll_vi(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_binary(cpu, addr, word, Operand::UnsignedImmediate, op_sll)
+}
+
+/// [vsrl.vv vd,vs2,vs1,vm]
+fn vsrl_vv(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_binary(cpu, addr, word, Operand::Vector, op_srl)
+}
+
+/// [vsrl.vx vd,vs2,rs1,vm]
+fn vsrl_vx(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_binary(cpu, addr, word, Operand::Scalar, op_srl)
+}
+
+/// [vsrl.vi vd,vs2,uimm,vm]
+fn vsrl_vi(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_binary(cpu, addr, word, Operand::UnsignedImmediate, op_srl)
+}
+
+/// [vsra.vv vd,vs2,vs1,vm]
+fn vsra_vv(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_binary(cpu, addr, word, Operand::Vector, op_sra)
+}
+
+/// [vsra.vx vd,vs2,rs1,vm]
+fn vsra_vx(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_binary(cpu, addr, word, Operand::Scalar, op_sra)
+}
+
+/// [vsra.vi vd,vs2,uimm,vm]
+fn vsra_vi(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_binary(cpu, addr, word, Operand::UnsignedImmediate, op_sra)
+}
+
+/// [vnsrl.wv vd,vs2,vs1,vm]
+fn vnsrl_wv(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_narrowing(cpu, addr, word, Operand::Vector, op_nsrl)
+}
+
+/// [vnsrl.wx vd,vs2,rs1,vm]
+fn vnsrl_wx(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_narrowing(cpu, addr, word, Operand::Scalar, op_nsrl)
+}
+
+/// [vnsrl.wi vd,vs2,uimm,vm]
+fn vnsrl_wi(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_narrowing(cpu, addr, word, Operand::UnsignedImmediate, op_nsrl)
+}
+
+/// [vnsra.wv vd,vs2,vs1,vm]
+fn vnsra_wv(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_narrowing(cpu, addr, word, Operand::Vector, op_nsra)
+}
+
+/// [vnsra.wx vd,vs2,rs1,vm]
+fn vnsra_wx(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_narrowing(cpu, addr, word, Operand::Scalar, op_nsra)
+}
+
+/// [vnsra.wi vd,vs2,uimm,vm]
+fn vnsra_wi(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_narrowing(cpu, addr, word, Operand::UnsignedImmediate, op_nsra)
+}
+
+/// [vmulhu.vv vd,vs2,vs1,vm]
+fn vmulhu_vv(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_binary(cpu, addr, word, Operand::Vector, op_mulhu)
+}
+
+/// [vmulhu.vx vd,vs2,rs1,vm]
+fn vmulhu_vx(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_binary(cpu, addr, word, Operand::Scalar, op_mulhu)
+}
+
+/// [vmul.vv vd,vs2,vs1,vm]
+fn vmul_vv(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_binary(cpu, addr, word, Operand::Vector, op_mul)
+}
+
+/// [vmul.vx vd,vs2,rs1,vm]
+fn vmul_vx(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_binary(cpu, addr, word, Operand::Scalar, op_mul)
+}
+
+/// [vmulhsu.vv vd,vs2,vs1,vm]
+fn vmulhsu_vv(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_binary(cpu, addr, word, Operand::Vector, op_mulhsu)
+}
+
+/// [vmulhsu.vx vd,vs2,rs1,vm]
+fn vmulhsu_vx(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_binary(cpu, addr, word, Operand::Scalar, op_mulhsu)
+}
+
+/// [vmulh.vv vd,vs2,vs1,vm]
+fn vmulh_vv(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_binary(cpu, addr, word, Operand::Vector, op_mulh)
+}
+
+/// [vmulh.vx vd,vs2,rs1,vm]
+fn vmulh_vx(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_binary(cpu, addr, word, Operand::Scalar, op_mulh)
+}
+
+/// [vdivu.vv vd,vs2,vs1,vm]
+fn vdivu_vv(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_binary(cpu, addr, word, Operand::Vector, op_divu)
+}
+
+/// [vdivu.vx vd,vs2,rs1,vm]
+fn vdivu_vx(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_binary(cpu, addr, word, Operand::Scalar, op_divu)
+}
+
+/// [vdiv.vv vd,vs2,vs1,vm]
+fn vdiv_vv(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_binary(cpu, addr, word, Operand::Vector, op_div)
+}
+
+/// [vdiv.vx vd,vs2,rs1,vm]
+fn vdiv_vx(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_binary(cpu, addr, word, Operand::Scalar, op_div)
+}
+
+/// [vremu.vv vd,vs2,vs1,vm]
+fn vremu_vv(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_binary(cpu, addr, word, Operand::Vector, op_remu)
+}
+
+/// [vremu.vx vd,vs2,rs1,vm]
+fn vremu_vx(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_binary(cpu, addr, word, Operand::Scalar, op_remu)
+}
+
+/// [vrem.vv vd,vs2,vs1,vm]
+fn vrem_vv(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_binary(cpu, addr, word, Operand::Vector, op_rem)
+}
+
+/// [vrem.vx vd,vs2,rs1,vm]
+fn vrem_vx(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_binary(cpu, addr, word, Operand::Scalar, op_rem)
+}
+
+/// [vmadd.vv vd,vs1,vs2,vm]
+fn vmadd_vv(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_multiply_add(cpu, addr, word, Operand::Vector, false, op_madd)
+}
+
+/// [vmadd.vx vd,rs1,vs2,vm]
+fn vmadd_vx(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_multiply_add(cpu, addr, word, Operand::Scalar, false, op_madd)
+}
+
+/// [vnmsub.vv vd,vs1,vs2,vm]
+fn vnmsub_vv(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_multiply_add(cpu, addr, word, Operand::Vector, false, op_nmsub)
+}
+
+/// [vnmsub.vx vd,rs1,vs2,vm]
+fn vnmsub_vx(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_multiply_add(cpu, addr, word, Operand::Scalar, false, op_nmsub)
+}
+
+/// [vmacc.vv vd,vs1,vs2,vm]
+fn vmacc_vv(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_multiply_add(cpu, addr, word, Operand::Vector, false, op_macc)
+}
+
+/// [vmacc.vx vd,rs1,vs2,vm]
+fn vmacc_vx(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_multiply_add(cpu, addr, word, Operand::Scalar, false, op_macc)
+}
+
+/// [vnmsac.vv vd,vs1,vs2,vm]
+fn vnmsac_vv(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_multiply_add(cpu, addr, word, Operand::Vector, false, op_nmsac)
+}
+
+/// [vnmsac.vx vd,rs1,vs2,vm]
+fn vnmsac_vx(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_multiply_add(cpu, addr, word, Operand::Scalar, false, op_nmsac)
+}
+
+/// [vwaddu.vv vd,vs2,vs1,vm]
+fn vwaddu_vv(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_widening(cpu, addr, word, Operand::Vector, false, op_waddu)
+}
+
+/// [vwaddu.vx vd,vs2,rs1,vm]
+fn vwaddu_vx(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_widening(cpu, addr, word, Operand::Scalar, false, op_waddu)
+}
+
+/// [vwadd.vv vd,vs2,vs1,vm]
+fn vwadd_vv(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_widening(cpu, addr, word, Operand::Vector, false, op_wadd)
+}
+
+/// [vwadd.vx vd,vs2,rs1,vm]
+fn vwadd_vx(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_widening(cpu, addr, word, Operand::Scalar, false, op_wadd)
+}
+
+/// [vwsubu.vv vd,vs2,vs1,vm]
+fn vwsubu_vv(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_widening(cpu, addr, word, Operand::Vector, false, op_wsubu)
+}
+
+/// [vwsubu.vx vd,vs2,rs1,vm]
+fn vwsubu_vx(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_widening(cpu, addr, word, Operand::Scalar, false, op_wsubu)
+}
+
+/// [vwsub.vv vd,vs2,vs1,vm]
+fn vwsub_vv(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_widening(cpu, addr, word, Operand::Vector, false, op_wsub)
+}
+
+/// [vwsub.vx vd,vs2,rs1,vm]
+fn vwsub_vx(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_widening(cpu, addr, word, Operand::Scalar, false, op_wsub)
+}
+
+/// [vwaddu.wv vd,vs2,vs1,vm]
+fn vwaddu_wv(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_widening(cpu, addr, word, Operand::Vector, true, op_waddu)
+}
+
+/// [vwaddu.wx vd,vs2,rs1,vm]
+fn vwaddu_wx(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_widening(cpu, addr, word, Operand::Scalar, true, op_waddu)
+}
+
+/// [vwadd.wv vd,vs2,vs1,vm]
+fn vwadd_wv(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_widening(cpu, addr, word, Operand::Vector, true, op_wadd_w)
+}
+
+/// [vwadd.wx vd,vs2,rs1,vm]
+fn vwadd_wx(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_widening(cpu, addr, word, Operand::Scalar, true, op_wadd_w)
+}
+
+/// [vwsubu.wv vd,vs2,vs1,vm]
+fn vwsubu_wv(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_widening(cpu, addr, word, Operand::Vector, true, op_wsubu)
+}
+
+/// [vwsubu.wx vd,vs2,rs1,vm]
+fn vwsubu_wx(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_widening(cpu, addr, word, Operand::Scalar, true, op_wsubu)
+}
+
+/// [vwsub.wv vd,vs2,vs1,vm]
+fn vwsub_wv(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_widening(cpu, addr, word, Operand::Vector, true, op_wsub_w)
+}
+
+/// [vwsub.wx vd,vs2,rs1,vm]
+fn vwsub_wx(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_widening(cpu, addr, word, Operand::Scalar, true, op_wsub_w)
+}
+
+/// [vwmulu.vv vd,vs2,vs1,vm]
+fn vwmulu_vv(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_widening(cpu, addr, word, Operand::Vector, false, op_wmulu)
+}
+
+/// [vwmulu.vx vd,vs2,rs1,vm]
+fn vwmulu_vx(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_widening(cpu, addr, word, Operand::Scalar, false, op_wmulu)
+}
+
+/// [vwmulsu.vv vd,vs2,vs1,vm]
+fn vwmulsu_vv(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_widening(cpu, addr, word, Operand::Vector, false, op_wmulsu)
+}
+
+/// [vwmulsu.vx vd,vs2,rs1,vm]
+fn vwmulsu_vx(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_widening(cpu, addr, word, Operand::Scalar, false, op_wmulsu)
+}
+
+/// [vwmul.vv vd,vs2,vs1,vm]
+fn vwmul_vv(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_widening(cpu, addr, word, Operand::Vector, false, op_wmul)
+}
+
+/// [vwmul.vx vd,vs2,rs1,vm]
+fn vwmul_vx(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_widening(cpu, addr, word, Operand::Scalar, false, op_wmul)
+}
+
+/// [vwmaccu.vv vd,vs1,vs2,vm]
+fn vwmaccu_vv(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_multiply_add(cpu, addr, word, Operand::Vector, true, op_wmaccu)
+}
+
+/// [vwmaccu.vx vd,rs1,vs2,vm]
+fn vwmaccu_vx(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_multiply_add(cpu, addr, word, Operand::Scalar, true, op_wmaccu)
+}
+
+/// [vwmacc.vv vd,vs1,vs2,vm]
+fn vwmacc_vv(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_multiply_add(cpu, addr, word, Operand::Vector, true, op_wmacc)
+}
+
+/// [vwmacc.vx vd,rs1,vs2,vm]
+fn vwmacc_vx(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_multiply_add(cpu, addr, word, Operand::Scalar, true, op_wmacc)
+}
+
+/// [vwmaccus.vx vd,rs1,vs2,vm]
+fn vwmaccus_vx(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_multiply_add(cpu, addr, word, Operand::Scalar, true, op_wmaccus)
+}
+
+/// [vwmaccsu.vv vd,vs1,vs2,vm]
+fn vwmaccsu_vv(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_multiply_add(cpu, addr, word, Operand::Vector, true, op_wmaccsu)
+}
+
+/// [vwmaccsu.vx vd,rs1,vs2,vm]
+fn vwmaccsu_vx(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_multiply_add(cpu, addr, word, Operand::Scalar, true, op_wmaccsu)
+}
+
+//==============================================================================
+// Vector Fixed-Point Arithmetic Instructions
+//==============================================================================
+
+/// [vsaddu.vv vd,vs2,vs1,vm]
+fn vsaddu_vv(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_binary(cpu, addr, word, Operand::Vector, op_saddu)
+}
+
+/// [vsaddu.vx vd,vs2,rs1,vm]
+fn vsaddu_vx(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_binary(cpu, addr, word, Operand::Scalar, op_saddu)
+}
+
+/// [vsaddu.vi vd,vs2,simm,vm]
+fn vsaddu_vi(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_binary(cpu, addr, word, Operand::Immediate, op_saddu)
+}
+
+/// [vsadd.vv vd,vs2,vs1,vm]
+fn vsadd_vv(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_binary(cpu, addr, word, Operand::Vector, op_sadd)
+}
+
+/// [vsadd.vx vd,vs2,rs1,vm]
+fn vsadd_vx(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_binary(cpu, addr, word, Operand::Scalar, op_sadd)
+}
+
+/// [vsadd.vi vd,vs2,simm,vm]
+fn vsadd_vi(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_binary(cpu, addr, word, Operand::Immediate, op_sadd)
+}
+
+/// [vssubu.vv vd,vs2,vs1,vm]
+fn vssubu_vv(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_binary(cpu, addr, word, Operand::Vector, op_ssubu)
+}
+
+/// [vssubu.vx vd,vs2,rs1,vm]
+fn vssubu_vx(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_binary(cpu, addr, word, Operand::Scalar, op_ssubu)
+}
+
+/// [vssub.vv vd,vs2,vs1,vm]
+fn vssub_vv(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_binary(cpu, addr, word, Operand::Vector, op_ssub)
+}
+
+/// [vssub.vx vd,vs2,rs1,vm]
+fn vssub_vx(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_binary(cpu, addr, word, Operand::Scalar, op_ssub)
+}
+
+/// [vaaddu.vv vd,vs2,vs1,vm]
+fn vaaddu_vv(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_binary(cpu, addr, word, Operand::Vector, op_aaddu)
+}
+
+/// [vaaddu.vx vd,vs2,rs1,vm]
+fn vaaddu_vx(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_binary(cpu, addr, word, Operand::Scalar, op_aaddu)
+}
+
+/// [vaadd.vv vd,vs2,vs1,vm]
+fn vaadd_vv(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_binary(cpu, addr, word, Operand::Vector, op_aadd)
+}
+
+/// [vaadd.vx vd,vs2,rs1,vm]
+fn vaadd_vx(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_binary(cpu, addr, word, Operand::Scalar, op_aadd)
+}
+
+/// [vasubu.vv vd,vs2,vs1,vm]
+fn vasubu_vv(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_binary(cpu, addr, word, Operand::Vector, op_asubu)
+}
+
+/// [vasubu.vx vd,vs2,rs1,vm]
+fn vasubu_vx(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_binary(cpu, addr, word, Operand::Scalar, op_asubu)
+}
+
+/// [vasub.vv vd,vs2,vs1,vm]
+fn vasub_vv(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_binary(cpu, addr, word, Operand::Vector, op_asub)
+}
+
+/// [vasub.vx vd,vs2,rs1,vm]
+fn vasub_vx(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_binary(cpu, addr, word, Operand::Scalar, op_asub)
+}
+
+/// [vsmul.vv vd,vs2,vs1,vm]
+fn vsmul_vv(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_binary(cpu, addr, word, Operand::Vector, op_smul)
+}
+
+/// [vsmul.vx vd,vs2,rs1,vm]
+fn vsmul_vx(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_binary(cpu, addr, word, Operand::Scalar, op_smul)
+}
+
+/// [vssrl.vv vd,vs2,vs1,vm]
+fn vssrl_vv(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_binary(cpu, addr, word, Operand::Vector, op_ssrl)
+}
+
+/// [vssrl.vx vd,vs2,rs1,vm]
+fn vssrl_vx(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_binary(cpu, addr, word, Operand::Scalar, op_ssrl)
+}
+
+/// [vssrl.vi vd,vs2,uimm,vm]
+fn vssrl_vi(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_binary(cpu, addr, word, Operand::UnsignedImmediate, op_ssrl)
+}
+
+/// [vssra.vv vd,vs2,vs1,vm]
+fn vssra_vv(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_binary(cpu, addr, word, Operand::Vector, op_ssra)
+}
+
+/// [vssra.vx vd,vs2,rs1,vm]
+fn vssra_vx(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_binary(cpu, addr, word, Operand::Scalar, op_ssra)
+}
+
+/// [vssra.vi vd,vs2,uimm,vm]
+fn vssra_vi(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_binary(cpu, addr, word, Operand::UnsignedImmediate, op_ssra)
+}
+
+/// [vnclipu.wv vd,vs2,vs1,vm]
+fn vnclipu_wv(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_narrowing(cpu, addr, word, Operand::Vector, op_nclipu)
+}
+
+/// [vnclipu.wx vd,vs2,rs1,vm]
+fn vnclipu_wx(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_narrowing(cpu, addr, word, Operand::Scalar, op_nclipu)
+}
+
+/// [vnclipu.wi vd,vs2,uimm,vm]
+fn vnclipu_wi(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_narrowing(cpu, addr, word, Operand::UnsignedImmediate, op_nclipu)
+}
+
+/// [vnclip.wv vd,vs2,vs1,vm]
+fn vnclip_wv(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_narrowing(cpu, addr, word, Operand::Vector, op_nclip)
+}
+
+/// [vnclip.wx vd,vs2,rs1,vm]
+fn vnclip_wx(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_narrowing(cpu, addr, word, Operand::Scalar, op_nclip)
+}
+
+/// [vnclip.wi vd,vs2,uimm,vm]
+fn vnclip_wi(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_narrowing(cpu, addr, word, Operand::UnsignedImmediate, op_nclip)
+}
+
+//==============================================================================
+// Vector Reduction Instructions
+//==============================================================================
+
+/// [vredsum.vs vd,vs2,vs1,vm]
+fn vredsum_vs(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_reduction(cpu, addr, word, false, op_add)
+}
+
+/// [vredand.vs vd,vs2,vs1,vm]
+fn vredand_vs(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_reduction(cpu, addr, word, false, op_and)
+}
+
+/// [vredor.vs vd,vs2,vs1,vm]
+fn vredor_vs(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_reduction(cpu, addr, word, false, op_or)
+}
+
+/// [vredxor.vs vd,vs2,vs1,vm]
+fn vredxor_vs(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_reduction(cpu, addr, word, false, op_xor)
+}
+
+/// [vredminu.vs vd,vs2,vs1,vm]
+fn vredminu_vs(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_reduction(cpu, addr, word, false, op_minu)
+}
+
+/// [vredmin.vs vd,vs2,vs1,vm]
+fn vredmin_vs(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_reduction(cpu, addr, word, false, op_min)
+}
+
+/// [vredmaxu.vs vd,vs2,vs1,vm]
+fn vredmaxu_vs(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_reduction(cpu, addr, word, false, op_maxu)
+}
+
+/// [vredmax.vs vd,vs2,vs1,vm]
+fn vredmax_vs(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_reduction(cpu, addr, word, false, op_max)
+}
+
+/// [vwredsumu.vs vd,vs2,vs1,vm]
+fn vwredsumu_vs(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_reduction(cpu, addr, word, true, op_add)
+}
+
+/// [vwredsum.vs vd,vs2,vs1,vm]
+fn vwredsum_vs(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_reduction(cpu, addr, word, true, op_wredsum)
+}
+
+//==============================================================================
+// Vector Mask-Register Logical Instructions
+//==============================================================================
+
+/// [vmandn.mm vd,vs2,vs1]
+fn vmandn_mm(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_mask_logical(cpu, addr, word, mask_andn)
+}
+
+/// [vmand.mm vd,vs2,vs1]
+fn vmand_mm(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_mask_logical(cpu, addr, word, mask_and)
+}
+
+/// [vmor.mm vd,vs2,vs1]
+fn vmor_mm(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_mask_logical(cpu, addr, word, mask_or)
+}
+
+/// [vmxor.mm vd,vs2,vs1]
+fn vmxor_mm(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_mask_logical(cpu, addr, word, mask_xor)
+}
+
+/// [vmorn.mm vd,vs2,vs1]
+fn vmorn_mm(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_mask_logical(cpu, addr, word, mask_orn)
+}
+
+/// [vmnand.mm vd,vs2,vs1]
+fn vmnand_mm(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_mask_logical(cpu, addr, word, mask_nand)
+}
+
+/// [vmnor.mm vd,vs2,vs1]
+fn vmnor_mm(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_mask_logical(cpu, addr, word, mask_nor)
+}
+
+/// [vmxnor.mm vd,vs2,vs1]
+fn vmxnor_mm(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_mask_logical(cpu, addr, word, mask_xnor)
+}
+
+//==============================================================================
+// Vector Integer Move, Merge and Extension Instructions
+//==============================================================================
+
+/// [vzext.vf8 vd,vs2,vm]
+fn vzext_vf8(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_extension(cpu, addr, word, 8, false)
+}
+
+/// [vsext.vf8 vd,vs2,vm]
+fn vsext_vf8(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_extension(cpu, addr, word, 8, true)
+}
+
+/// [vzext.vf4 vd,vs2,vm]
+fn vzext_vf4(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_extension(cpu, addr, word, 4, false)
+}
+
+/// [vsext.vf4 vd,vs2,vm]
+fn vsext_vf4(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_extension(cpu, addr, word, 4, true)
+}
+
+/// [vzext.vf2 vd,vs2,vm]
+fn vzext_vf2(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_extension(cpu, addr, word, 2, false)
+}
+
+/// [vsext.vf2 vd,vs2,vm]
+fn vsext_vf2(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_extension(cpu, addr, word, 2, true)
+}
+
+/// [vmerge.vvm vd,vs2,vs1,v0]
+fn vmerge_vvm(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_merge(cpu, addr, word, Operand::Vector)
+}
+
+/// [vmerge.vxm vd,vs2,rs1,v0]
+fn vmerge_vxm(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_merge(cpu, addr, word, Operand::Scalar)
+}
+
+/// [vmerge.vim vd,vs2,simm,v0]
+fn vmerge_vim(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_merge(cpu, addr, word, Operand::Immediate)
+}
+
+/// [vmv.v.v vd,vs1]
+fn vmv_v_v(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_merge(cpu, addr, word, Operand::Vector)
+}
+
+/// [vmv.v.x vd,rs1]
+fn vmv_v_x(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_merge(cpu, addr, word, Operand::Scalar)
+}
+
+/// [vmv.v.i vd,simm]
+fn vmv_v_i(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_merge(cpu, addr, word, Operand::Immediate)
+}
+
+/// [vmadc.vvm vd,vs2,vs1,v0]
+fn vmadc_vvm(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_carry_out(cpu, addr, word, Operand::Vector, op_madc)
+}
+
+/// [vmadc.vxm vd,vs2,rs1,v0]
+fn vmadc_vxm(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_carry_out(cpu, addr, word, Operand::Scalar, op_madc)
+}
+
+/// [vmadc.vim vd,vs2,simm,v0]
+fn vmadc_vim(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_carry_out(cpu, addr, word, Operand::Immediate, op_madc)
+}
+
+/// [vmadc.vv vd,vs2,vs1]
+fn vmadc_vv(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_carry_out(cpu, addr, word, Operand::Vector, op_madc)
+}
+
+/// [vmadc.vx vd,vs2,rs1]
+fn vmadc_vx(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_carry_out(cpu, addr, word, Operand::Scalar, op_madc)
+}
+
+/// [vmadc.vi vd,vs2,simm]
+fn vmadc_vi(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_carry_out(cpu, addr, word, Operand::Immediate, op_madc)
+}
+
+/// [vmsbc.vvm vd,vs2,vs1,v0]
+fn vmsbc_vvm(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_carry_out(cpu, addr, word, Operand::Vector, op_msbc)
+}
+
+/// [vmsbc.vxm vd,vs2,rs1,v0]
+fn vmsbc_vxm(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_carry_out(cpu, addr, word, Operand::Scalar, op_msbc)
+}
+
+/// [vmsbc.vv vd,vs2,vs1]
+fn vmsbc_vv(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_carry_out(cpu, addr, word, Operand::Vector, op_msbc)
+}
+
+/// [vmsbc.vx vd,vs2,rs1]
+fn vmsbc_vx(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_carry_out(cpu, addr, word, Operand::Scalar, op_msbc)
+}
+
+/// [vmv1r.v vd,vs2]
+fn vmv1r_v(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_move_whole(cpu, addr, word)
+}
+
+/// [vmv2r.v vd,vs2]
+fn vmv2r_v(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_move_whole(cpu, addr, word)
+}
+
+/// [vmv4r.v vd,vs2]
+fn vmv4r_v(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_move_whole(cpu, addr, word)
+}
+
+/// [vmv8r.v vd,vs2]
+fn vmv8r_v(cpu: &mut Cpu, addr: u64, word: u32) -> Result<(), Trap> {
+    vector_move_whole(cpu, addr, word)
+}
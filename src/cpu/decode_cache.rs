@@ -0,0 +1,144 @@
+use crate::cpu::cpu_instruction::{
+    get_operands_operation, Instruction, Operands, OperandsOperation,
+};
+use crate::peripherals::memory::PageWrites;
+
+const DECODE_CACHE_ENTRIES: usize = 16384;
+const PAGE_SIZE: u64 = 4096;
+
+/// A decoded instruction. word is the 32-bit instruction, which is the decompressed
+/// one for a compressed instruction, and size is the length of the fetched instruction
+/// in bytes. operands are extracted from word, and operation is the handler taking
+/// them if the instruction has one.
+#[derive(Clone, Copy)]
+pub struct DecodedInstruction {
+    pub word: u32,
+    pub size: u64,
+    pub instruction: &'static Instruction,
+    pub operands: Operands,
+    pub operation: Option<OperandsOperation>,
+}
+
+impl DecodedInstruction {
+    pub fn new(word: u32, size: u64, instruction: &'static Instruction) -> Self {
+        DecodedInstruction {
+            word,
+            size,
+            instruction,
+            operands: Operands::new(word),
+            operation: get_operands_operation(instruction),
+        }
+    }
+}
+
+/// An instruction decoded at a physical address, and the write counter of the page
+/// when it was decoded.
+#[derive(Clone, Copy)]
+struct DecodeCacheEntry {
+    p_addr: u64,
+    page_writes: u64,
+    decoded: DecodedInstruction,
+}
+
+/// physical address range [start, end) of a memory and the write counters of its pages.
+struct CacheableMemory {
+    start: u64,
+    end: u64,
+    page_writes: PageWrites,
+}
+
+/// The memories of the bus, from which the decoded instructions can be cached.
+pub struct CacheableMemories {
+    memories: Vec<CacheableMemory>,
+}
+
+impl CacheableMemories {
+    pub fn new(memories: Vec<(u64, PageWrites)>) -> Self {
+        CacheableMemories {
+            memories: memories
+                .into_iter()
+                .map(|(start, page_writes)| CacheableMemory {
+                    start,
+                    end: start + page_writes.len() as u64 * PAGE_SIZE,
+                    page_writes,
+                })
+                .collect(),
+        }
+    }
+
+    /// the write counter of the page at the physical address, or None if the address
+    /// is not in the memories.
+    pub fn get_page_writes(&self, p_addr: u64) -> Option<u64> {
+        for memory in self.memories.iter() {
+            if memory.start <= p_addr && p_addr < memory.end {
+                let page = ((p_addr - memory.start) / PAGE_SIZE) as usize;
+                return Some(memory.page_writes[page].get());
+            }
+        }
+        None
+    }
+
+    pub fn contains(&self, p_addr: u64) -> bool {
+        self.memories
+            .iter()
+            .any(|memory| memory.start <= p_addr && p_addr < memory.end)
+    }
+}
+
+/// The decoded instruction cache skips the fetch from the bus and the lookups of the
+/// opecode tables for the instructions executed again. It is direct-mapped by the
+/// physical address, so the translation of PC is still checked at every fetch, and
+/// caches only the instructions which are in a page of the memories. An entry is
+/// valid while the write counter of the page is unchanged, so the stores of any
+/// hart and DMA writes into the page invalidate it.
+pub struct DecodeCache {
+    entries: Vec<Option<DecodeCacheEntry>>,
+    memories: CacheableMemories,
+}
+
+impl DecodeCache {
+    pub fn new(memories: Vec<(u64, PageWrites)>) -> Self {
+        DecodeCache {
+            entries: vec![None; DECODE_CACHE_ENTRIES],
+            memories: CacheableMemories::new(memories),
+        }
+    }
+
+    pub fn get(&self, p_addr: u64) -> Option<DecodedInstruction> {
+        let entry = match self.entries[Self::index(p_addr)] {
+            Some(entry) if entry.p_addr == p_addr => entry,
+            _ => return None,
+        };
+        match self.memories.get_page_writes(p_addr) {
+            Some(page_writes) if page_writes == entry.page_writes => Some(entry.decoded),
+            _ => None,
+        }
+    }
+
+    pub fn insert(&mut self, p_addr: u64, decoded: DecodedInstruction) {
+        if (p_addr & (PAGE_SIZE - 1)) + decoded.size > PAGE_SIZE {
+            return;
+        }
+        let page_writes = match self.memories.get_page_writes(p_addr) {
+            Some(page_writes) => page_writes,
+            None => return,
+        };
+        self.entries[Self::index(p_addr)] = Some(DecodeCacheEntry {
+            p_addr,
+            page_writes,
+            decoded,
+        });
+    }
+
+    /// fence.i and the change of XLEN, which selects the compressed instructions,
+    /// drop all entries.
+    pub fn flush(&mut self) {
+        for entry in self.entries.iter_mut() {
+            *entry = None;
+        }
+    }
+
+    fn index(p_addr: u64) -> usize {
+        ((p_addr >> 1) as usize) & (DECODE_CACHE_ENTRIES - 1)
+    }
+}
@@ -0,0 +1,675 @@
+// Floating-Point Unit
+// IEEE 754-2008 binary32/binary64 arithmetic implemented in software, so that every
+// rounding mode and accrued exception flag required by the F/D extensions is exact.
+// https://riscv.org/technical/specifications/ (Chapter 11 "F" Standard Extension)
+
+use std::cmp;
+
+// fflags (Accrued Exceptions)
+pub const FFLAGS_NX: u64 = 0x01; // Inexact
+pub const FFLAGS_UF: u64 = 0x02; // Underflow
+pub const FFLAGS_OF: u64 = 0x04; // Overflow
+pub const FFLAGS_DZ: u64 = 0x08; // Divide by Zero
+pub const FFLAGS_NV: u64 = 0x10; // Invalid Operation
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RoundingMode {
+    /// Round to Nearest, ties to Even
+    Rne = 0,
+    /// Round towards Zero
+    Rtz = 1,
+    /// Round Down (towards -inf)
+    Rdn = 2,
+    /// Round Up (towards +inf)
+    Rup = 3,
+    /// Round to Nearest, ties to Max Magnitude
+    Rmm = 4,
+}
+
+impl RoundingMode {
+    pub fn from_bits(rm: u64) -> Option<RoundingMode> {
+        match rm {
+            0 => Some(RoundingMode::Rne),
+            1 => Some(RoundingMode::Rtz),
+            2 => Some(RoundingMode::Rdn),
+            3 => Some(RoundingMode::Rup),
+            4 => Some(RoundingMode::Rmm),
+            _ => None,
+        }
+    }
+}
+
+pub struct FloatFormat {
+    exp_bits: u32,
+    frac_bits: u32,
+}
+
+pub const BINARY32: FloatFormat = FloatFormat {
+    exp_bits: 8,
+    frac_bits: 23,
+};
+
+pub const BINARY64: FloatFormat = FloatFormat {
+    exp_bits: 11,
+    frac_bits: 52,
+};
+
+impl FloatFormat {
+    pub fn width(&self) -> u32 {
+        1 + self.exp_bits + self.frac_bits
+    }
+
+    fn bias(&self) -> i32 {
+        (1 << (self.exp_bits - 1)) - 1
+    }
+
+    fn sign_mask(&self) -> u64 {
+        1 << (self.exp_bits + self.frac_bits)
+    }
+
+    fn frac_mask(&self) -> u64 {
+        (1 << self.frac_bits) - 1
+    }
+
+    fn exp_max(&self) -> u64 {
+        (1 << self.exp_bits) - 1
+    }
+
+    fn sign(&self, sign: bool) -> u64 {
+        match sign {
+            true => self.sign_mask(),
+            false => 0,
+        }
+    }
+
+    fn zero(&self, sign: bool) -> u64 {
+        self.sign(sign)
+    }
+
+    fn infinity(&self, sign: bool) -> u64 {
+        self.sign(sign) | (self.exp_max() << self.frac_bits)
+    }
+
+    fn max_finite(&self, sign: bool) -> u64 {
+        self.sign(sign) | ((self.exp_max() - 1) << self.frac_bits) | self.frac_mask()
+    }
+
+    /// The canonical NaN has a positive sign and all significand bits clear except the MSB.
+    pub fn canonical_nan(&self) -> u64 {
+        (self.exp_max() << self.frac_bits) | (1 << (self.frac_bits - 1))
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Class {
+    Zero,
+    Normal,
+    Infinite,
+    QuietNaN,
+    SignalingNaN,
+}
+
+/// Decoded floating-point value. A finite value is `sig * 2^exp`.
+struct Unpacked {
+    class: Class,
+    sign: bool,
+    exp: i32,
+    sig: u128,
+}
+
+impl Unpacked {
+    fn is_nan(&self) -> bool {
+        self.class == Class::QuietNaN || self.class == Class::SignalingNaN
+    }
+
+    fn is_signaling(&self) -> bool {
+        self.class == Class::SignalingNaN
+    }
+}
+
+fn unpack(fmt: &FloatFormat, bits: u64) -> Unpacked {
+    let sign = bits & fmt.sign_mask() != 0;
+    let exp = (bits >> fmt.frac_bits) & fmt.exp_max();
+    let frac = bits & fmt.frac_mask();
+    let (class, e, sig) = match exp {
+        0 => match frac {
+            0 => (Class::Zero, 0, 0),
+            // subnormal
+            _ => (Class::Normal, 1 - fmt.bias(), frac),
+        },
+        e if e == fmt.exp_max() => match frac {
+            0 => (Class::Infinite, 0, 0),
+            _ => match frac >> (fmt.frac_bits - 1) {
+                1 => (Class::QuietNaN, 0, 0),
+                _ => (Class::SignalingNaN, 0, 0),
+            },
+        },
+        e => (
+            Class::Normal,
+            e as i32 - fmt.bias(),
+            frac | (1 << fmt.frac_bits),
+        ),
+    };
+    Unpacked {
+        class,
+        sign,
+        exp: e - fmt.frac_bits as i32,
+        sig: sig as u128,
+    }
+}
+
+/// Shifts `sig` right by `shift` bits, ORing all discarded bits into the LSB.
+fn shift_right_jam(sig: u128, shift: i32) -> u128 {
+    match shift {
+        s if s <= 0 => sig,
+        s if s >= 128 => (sig != 0) as u128,
+        s => (sig >> s) | ((sig & ((1 << s) - 1)) != 0) as u128,
+    }
+}
+
+/// Shifts `sig` so that its most significant bit is placed at `msb`.
+fn normalize(sig: u128, exp: i32, msb: i32) -> (u128, i32) {
+    let shift = msb - (127 - sig.leading_zeros() as i32);
+    match shift >= 0 {
+        true => (sig << shift, exp - shift),
+        false => (shift_right_jam(sig, -shift), exp - shift),
+    }
+}
+
+/// Shifts `sig` right by `shift` bits and rounds the result with `rm`.
+/// Returns the rounded value and whether any non-zero bits were discarded.
+fn round_shift(sig: u128, shift: i32, sign: bool, rm: RoundingMode) -> (u128, bool) {
+    if shift <= 0 {
+        return (sig << -shift, false);
+    }
+    let (m, half, rest) = match shift {
+        s if s > 128 => (0, false, sig != 0),
+        128 => (0, (sig >> 127) != 0, (sig & !(1 << 127)) != 0),
+        s => (
+            sig >> s,
+            ((sig >> (s - 1)) & 1) != 0,
+            (sig & ((1 << (s - 1)) - 1)) != 0,
+        ),
+    };
+    let inexact = half || rest;
+    let increment = match rm {
+        RoundingMode::Rne => half && (rest || (m & 1) == 1),
+        RoundingMode::Rtz => false,
+        RoundingMode::Rdn => inexact && sign,
+        RoundingMode::Rup => inexact && !sign,
+        RoundingMode::Rmm => half,
+    };
+    (m + increment as u128, inexact)
+}
+
+/// Rounds the exact value `(-1)^sign * sig * 2^exp` to the format.
+/// The LSB of `sig` may be a sticky bit as long as it is far below the rounding position.
+fn round_pack(
+    fmt: &FloatFormat,
+    sign: bool,
+    exp: i32,
+    sig: u128,
+    rm: RoundingMode,
+    flags: &mut u64,
+) -> u64 {
+    if sig == 0 {
+        return fmt.zero(sign);
+    }
+
+    let frac_bits = fmt.frac_bits as i32;
+    let emin = 1 - fmt.bias();
+    // unbiased exponent of the leading bit.
+    let e = exp + 127 - sig.leading_zeros() as i32;
+
+    let mut lsb_exp = cmp::max(e, emin) - frac_bits;
+    let (mut m, inexact) = round_shift(sig, lsb_exp - exp, sign, rm);
+    if m >> (frac_bits + 1) != 0 {
+        m >>= 1;
+        lsb_exp += 1;
+    }
+
+    if lsb_exp + frac_bits > fmt.bias() {
+        *flags |= FFLAGS_OF | FFLAGS_NX;
+        return match rm {
+            RoundingMode::Rne | RoundingMode::Rmm => fmt.infinity(sign),
+            RoundingMode::Rtz => fmt.max_finite(sign),
+            RoundingMode::Rdn => match sign {
+                true => fmt.infinity(sign),
+                false => fmt.max_finite(sign),
+            },
+            RoundingMode::Rup => match sign {
+                true => fmt.max_finite(sign),
+                false => fmt.infinity(sign),
+            },
+        };
+    }
+
+    if inexact {
+        *flags |= FFLAGS_NX;
+        // RISC-V detects tininess after rounding.
+        let tiny = match e {
+            e if e >= emin => false,
+            e if e < emin - 1 => true,
+            e => {
+                let (m, _) = round_shift(sig, e - frac_bits - exp, sign, rm);
+                m >> (frac_bits + 1) == 0
+            }
+        };
+        if tiny {
+            *flags |= FFLAGS_UF;
+        }
+    }
+
+    let m = m as u64;
+    match m >> frac_bits {
+        0 => fmt.sign(sign) | m,
+        _ => {
+            let biased_exp = (lsb_exp + frac_bits + fmt.bias()) as u64;
+            fmt.sign(sign) | (biased_exp << frac_bits) | (m & fmt.frac_mask())
+        }
+    }
+}
+
+/// Returns the canonical NaN and raises the invalid flag if any operand is a signaling NaN.
+fn propagate_nan(fmt: &FloatFormat, operands: &[&Unpacked], flags: &mut u64) -> u64 {
+    if operands.iter().any(|o| o.is_signaling()) {
+        *flags |= FFLAGS_NV;
+    }
+    fmt.canonical_nan()
+}
+
+fn add_unpacked(
+    fmt: &FloatFormat,
+    a: &Unpacked,
+    b: &Unpacked,
+    rm: RoundingMode,
+    flags: &mut u64,
+) -> u64 {
+    if a.is_nan() || b.is_nan() {
+        return propagate_nan(fmt, &[a, b], flags);
+    }
+    match (a.class, b.class) {
+        (Class::Infinite, Class::Infinite) => {
+            return match a.sign == b.sign {
+                true => fmt.infinity(a.sign),
+                false => {
+                    *flags |= FFLAGS_NV;
+                    fmt.canonical_nan()
+                }
+            }
+        }
+        (Class::Infinite, _) => return fmt.infinity(a.sign),
+        (_, Class::Infinite) => return fmt.infinity(b.sign),
+        (Class::Zero, Class::Zero) => {
+            return fmt.zero(match a.sign == b.sign {
+                true => a.sign,
+                false => rm == RoundingMode::Rdn,
+            })
+        }
+        (Class::Zero, _) => return round_pack(fmt, b.sign, b.exp, b.sig, rm, flags),
+        (_, Class::Zero) => return round_pack(fmt, a.sign, a.exp, a.sig, rm, flags),
+        _ => {}
+    }
+
+    // Both operands are placed at bit 120 so that there are enough guard bits below
+    // the rounding position, even for the 106-bit product of a fused multiply-add.
+    let (sig_a, exp_a) = normalize(a.sig, a.exp, 120);
+    let (sig_b, exp_b) = normalize(b.sig, b.exp, 120);
+    let ((big_sig, big_exp, big_sign), (small_sig, small_exp)) =
+        match (exp_a, sig_a) >= (exp_b, sig_b) {
+            true => ((sig_a, exp_a, a.sign), (sig_b, exp_b)),
+            false => ((sig_b, exp_b, b.sign), (sig_a, exp_a)),
+        };
+    let small_sig = shift_right_jam(small_sig, big_exp - small_exp);
+
+    match a.sign == b.sign {
+        true => round_pack(fmt, big_sign, big_exp, big_sig + small_sig, rm, flags),
+        false => match big_sig - small_sig {
+            0 => fmt.zero(rm == RoundingMode::Rdn),
+            sig => round_pack(fmt, big_sign, big_exp, sig, rm, flags),
+        },
+    }
+}
+
+/// a + b
+pub fn add(fmt: &FloatFormat, a: u64, b: u64, rm: RoundingMode, flags: &mut u64) -> u64 {
+    add_unpacked(fmt, &unpack(fmt, a), &unpack(fmt, b), rm, flags)
+}
+
+/// a - b
+pub fn sub(fmt: &FloatFormat, a: u64, b: u64, rm: RoundingMode, flags: &mut u64) -> u64 {
+    add(fmt, a, b ^ fmt.sign_mask(), rm, flags)
+}
+
+/// a * b
+pub fn mul(fmt: &FloatFormat, a: u64, b: u64, rm: RoundingMode, flags: &mut u64) -> u64 {
+    let a = unpack(fmt, a);
+    let b = unpack(fmt, b);
+    let sign = a.sign ^ b.sign;
+    if a.is_nan() || b.is_nan() {
+        return propagate_nan(fmt, &[&a, &b], flags);
+    }
+    match (a.class, b.class) {
+        (Class::Infinite, Class::Zero) | (Class::Zero, Class::Infinite) => {
+            *flags |= FFLAGS_NV;
+            fmt.canonical_nan()
+        }
+        (Class::Infinite, _) | (_, Class::Infinite) => fmt.infinity(sign),
+        (Class::Zero, _) | (_, Class::Zero) => fmt.zero(sign),
+        _ => round_pack(fmt, sign, a.exp + b.exp, a.sig * b.sig, rm, flags),
+    }
+}
+
+/// a / b
+pub fn div(fmt: &FloatFormat, a: u64, b: u64, rm: RoundingMode, flags: &mut u64) -> u64 {
+    let a = unpack(fmt, a);
+    let b = unpack(fmt, b);
+    let sign = a.sign ^ b.sign;
+    if a.is_nan() || b.is_nan() {
+        return propagate_nan(fmt, &[&a, &b], flags);
+    }
+    match (a.class, b.class) {
+        (Class::Infinite, Class::Infinite) | (Class::Zero, Class::Zero) => {
+            *flags |= FFLAGS_NV;
+            fmt.canonical_nan()
+        }
+        (Class::Infinite, _) => fmt.infinity(sign),
+        (_, Class::Infinite) => fmt.zero(sign),
+        (Class::Zero, _) => fmt.zero(sign),
+        (_, Class::Zero) => {
+            *flags |= FFLAGS_DZ;
+            fmt.infinity(sign)
+        }
+        _ => {
+            // The quotient has at least 63 bits, which leaves enough guard bits for rounding.
+            let (sig_a, exp_a) = normalize(a.sig, a.exp, 125);
+            let (sig_b, exp_b) = normalize(b.sig, b.exp, 62);
+            let q = sig_a / sig_b;
+            let sticky = (sig_a % sig_b != 0) as u128;
+            round_pack(fmt, sign, exp_a - exp_b, q | sticky, rm, flags)
+        }
+    }
+}
+
+/// sqrt(a)
+pub fn sqrt(fmt: &FloatFormat, a: u64, rm: RoundingMode, flags: &mut u64) -> u64 {
+    let a = unpack(fmt, a);
+    if a.is_nan() {
+        return propagate_nan(fmt, &[&a], flags);
+    }
+    match a.class {
+        Class::Zero => fmt.zero(a.sign),
+        _ if a.sign => {
+            *flags |= FFLAGS_NV;
+            fmt.canonical_nan()
+        }
+        Class::Infinite => fmt.infinity(false),
+        _ => {
+            let (mut sig, mut exp) = normalize(a.sig, a.exp, 124);
+            if exp & 1 != 0 {
+                sig <<= 1;
+                exp -= 1;
+            }
+            // bit-by-bit integer square root.
+            let mut root: u128 = 0;
+            let mut rem = sig;
+            let mut bit: u128 = 1 << 124;
+            while bit != 0 {
+                if rem >= root + bit {
+                    rem -= root + bit;
+                    root = (root >> 1) + bit;
+                } else {
+                    root >>= 1;
+                }
+                bit >>= 2;
+            }
+            round_pack(fmt, false, exp / 2, root | (rem != 0) as u128, rm, flags)
+        }
+    }
+}
+
+/// (a * b) + c, rounded only once.
+pub fn fused_mul_add(
+    fmt: &FloatFormat,
+    a: u64,
+    b: u64,
+    c: u64,
+    rm: RoundingMode,
+    flags: &mut u64,
+) -> u64 {
+    let a = unpack(fmt, a);
+    let b = unpack(fmt, b);
+    let c = unpack(fmt, c);
+    let sign = a.sign ^ b.sign;
+
+    // The invalid flag is raised for inf * 0 even if the addend is a quiet NaN.
+    let invalid_product = matches!(
+        (a.class, b.class),
+        (Class::Infinite, Class::Zero) | (Class::Zero, Class::Infinite)
+    );
+    if a.is_nan() || b.is_nan() || c.is_nan() || invalid_product {
+        if invalid_product {
+            *flags |= FFLAGS_NV;
+        }
+        return propagate_nan(fmt, &[&a, &b, &c], flags);
+    }
+
+    let product = match (a.class, b.class) {
+        (Class::Infinite, _) | (_, Class::Infinite) => Unpacked {
+            class: Class::Infinite,
+            sign,
+            exp: 0,
+            sig: 0,
+        },
+        (Class::Zero, _) | (_, Class::Zero) => Unpacked {
+            class: Class::Zero,
+            sign,
+            exp: 0,
+            sig: 0,
+        },
+        _ => Unpacked {
+            class: Class::Normal,
+            sign,
+            exp: a.exp + b.exp,
+            sig: a.sig * b.sig,
+        },
+    };
+    add_unpacked(fmt, &product, &c, rm, flags)
+}
+
+/// Orders non-NaN values. Both zeros compare equal.
+fn order_key(fmt: &FloatFormat, bits: u64) -> i128 {
+    let magnitude = (bits & !fmt.sign_mask()) as i128;
+    match bits & fmt.sign_mask() {
+        0 => magnitude,
+        _ => -magnitude,
+    }
+}
+
+/// Quiet comparison: only signaling NaNs raise the invalid flag.
+pub fn eq(fmt: &FloatFormat, a: u64, b: u64, flags: &mut u64) -> bool {
+    let ua = unpack(fmt, a);
+    let ub = unpack(fmt, b);
+    if ua.is_nan() || ub.is_nan() {
+        if ua.is_signaling() || ub.is_signaling() {
+            *flags |= FFLAGS_NV;
+        }
+        return false;
+    }
+    order_key(fmt, a) == order_key(fmt, b)
+}
+
+/// Signaling comparison: any NaN raises the invalid flag.
+pub fn lt(fmt: &FloatFormat, a: u64, b: u64, flags: &mut u64) -> bool {
+    if unpack(fmt, a).is_nan() || unpack(fmt, b).is_nan() {
+        *flags |= FFLAGS_NV;
+        return false;
+    }
+    order_key(fmt, a) < order_key(fmt, b)
+}
+
+/// Signaling comparison: any NaN raises the invalid flag.
+pub fn le(fmt: &FloatFormat, a: u64, b: u64, flags: &mut u64) -> bool {
+    if unpack(fmt, a).is_nan() || unpack(fmt, b).is_nan() {
+        *flags |= FFLAGS_NV;
+        return false;
+    }
+    order_key(fmt, a) <= order_key(fmt, b)
+}
+
+/// IEEE 754-2019 minimumNumber/maximumNumber. -0.0 is considered to be less than +0.0.
+fn min_max(fmt: &FloatFormat, a: u64, b: u64, is_max: bool, flags: &mut u64) -> u64 {
+    let ua = unpack(fmt, a);
+    let ub = unpack(fmt, b);
+    if ua.is_signaling() || ub.is_signaling() {
+        *flags |= FFLAGS_NV;
+    }
+    match (ua.is_nan(), ub.is_nan()) {
+        (true, true) => fmt.canonical_nan(),
+        (true, false) => b,
+        (false, true) => a,
+        (false, false) => {
+            let key_a = (order_key(fmt, a), !ua.sign);
+            let key_b = (order_key(fmt, b), !ub.sign);
+            match (key_a < key_b) ^ is_max {
+                true => a,
+                false => b,
+            }
+        }
+    }
+}
+
+pub fn min(fmt: &FloatFormat, a: u64, b: u64, flags: &mut u64) -> u64 {
+    min_max(fmt, a, b, false, flags)
+}
+
+pub fn max(fmt: &FloatFormat, a: u64, b: u64, flags: &mut u64) -> u64 {
+    min_max(fmt, a, b, true, flags)
+}
+
+/// Flips the sign bit. The payload of a NaN is not modified.
+pub fn negate(fmt: &FloatFormat, a: u64) -> u64 {
+    a ^ fmt.sign_mask()
+}
+
+/// FSGNJ (mode 0), FSGNJN (mode 1) and FSGNJX (mode 2).
+pub fn sign_inject(fmt: &FloatFormat, a: u64, b: u64, mode: u32) -> u64 {
+    let sign = match mode {
+        0 => b & fmt.sign_mask(),
+        1 => !b & fmt.sign_mask(),
+        _ => (a ^ b) & fmt.sign_mask(),
+    };
+    (a & !fmt.sign_mask()) | sign
+}
+
+/// Returns the 10-bit mask used by FCLASS.
+pub fn classify(fmt: &FloatFormat, a: u64) -> u64 {
+    let u = unpack(fmt, a);
+    let subnormal = (a >> fmt.frac_bits) & fmt.exp_max() == 0;
+    match (u.class, u.sign) {
+        (Class::Infinite, true) => 1 << 0,
+        (Class::Normal, true) if !subnormal => 1 << 1,
+        (Class::Normal, true) => 1 << 2,
+        (Class::Zero, true) => 1 << 3,
+        (Class::Zero, false) => 1 << 4,
+        (Class::Normal, false) if subnormal => 1 << 5,
+        (Class::Normal, false) => 1 << 6,
+        (Class::Infinite, false) => 1 << 7,
+        (Class::SignalingNaN, _) => 1 << 8,
+        (Class::QuietNaN, _) => 1 << 9,
+    }
+}
+
+/// Converts a floating-point value to a `width`-bit integer.
+/// Out-of-range values and NaNs raise the invalid flag and saturate.
+pub fn to_int(
+    fmt: &FloatFormat,
+    a: u64,
+    signed: bool,
+    width: u32,
+    rm: RoundingMode,
+    flags: &mut u64,
+) -> u64 {
+    let u = unpack(fmt, a);
+    let (max_positive, max_negative): (u128, u128) = match signed {
+        true => ((1 << (width - 1)) - 1, 1 << (width - 1)),
+        false => ((1 << width) - 1, 0),
+    };
+    let saturate = |negative: bool| -> u64 {
+        match negative {
+            true => (max_negative as u64).wrapping_neg(),
+            false => max_positive as u64,
+        }
+    };
+
+    let (magnitude, inexact) = match u.class {
+        Class::QuietNaN | Class::SignalingNaN => {
+            *flags |= FFLAGS_NV;
+            return saturate(false);
+        }
+        Class::Infinite => {
+            *flags |= FFLAGS_NV;
+            return saturate(u.sign);
+        }
+        Class::Zero => return 0,
+        // too large for any integer format.
+        Class::Normal if u.exp > 64 => (u128::MAX, false),
+        Class::Normal => round_shift(u.sig, -u.exp, u.sign, rm),
+    };
+
+    let limit = match u.sign {
+        true => max_negative,
+        false => max_positive,
+    };
+    if magnitude > limit {
+        *flags |= FFLAGS_NV;
+        return saturate(u.sign);
+    }
+    if inexact {
+        *flags |= FFLAGS_NX;
+    }
+    match u.sign {
+        true => (magnitude as u64).wrapping_neg(),
+        false => magnitude as u64,
+    }
+}
+
+/// Converts the low `width` bits of an integer to a floating-point value.
+pub fn from_int(
+    fmt: &FloatFormat,
+    data: u64,
+    signed: bool,
+    width: u32,
+    rm: RoundingMode,
+    flags: &mut u64,
+) -> u64 {
+    let data = match width {
+        32 => match signed {
+            true => data as i32 as i64 as u64,
+            false => data & 0xffffffff,
+        },
+        _ => data,
+    };
+    let (sign, magnitude) = match signed && (data as i64) < 0 {
+        true => (true, (data as i64).unsigned_abs()),
+        false => (false, data),
+    };
+    round_pack(fmt, sign, 0, magnitude as u128, rm, flags)
+}
+
+/// Converts a value between floating-point formats.
+pub fn convert(
+    from: &FloatFormat,
+    to: &FloatFormat,
+    a: u64,
+    rm: RoundingMode,
+    flags: &mut u64,
+) -> u64 {
+    let u = unpack(from, a);
+    match u.class {
+        Class::QuietNaN | Class::SignalingNaN => propagate_nan(to, &[&u], flags),
+        Class::Infinite => to.infinity(u.sign),
+        Class::Zero => to.zero(u.sign),
+        Class::Normal => round_pack(to, u.sign, u.exp, u.sig, rm, flags),
+    }
+}
@@ -1,16 +1,16 @@
 use crate::bus::bus::Bus;
-use crate::bus::bus_fe310::BusFe310;
-use crate::bus::bus_fu540::BusFu540;
-use crate::bus::bus_qemu_virt::BusQemuVirt;
-use crate::console::Console;
 use crate::cpu::cpu::{Privilege, Xlen};
+use crate::cpu::cpu_csr::*;
+use crate::cpu::decode_cache::CacheableMemories;
 use crate::cpu::trap::*;
-use crate::machine::Machine;
-use std::collections::HashMap;
+use std::cell::{RefCell, RefMut};
+use std::rc::Rc;
 
 const PAGE_SIZE: u64 = 4096;
+const TLB_ENTRIES: usize = 1024;
+const RESERVATION_SET_SIZE: u64 = 64;
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub enum AddressingMode {
     Bare,
     Sv32,
@@ -20,49 +20,174 @@ pub enum AddressingMode {
     Sv64,
 }
 
+/// How the loads and stores which are not naturally aligned are handled.
+/// Trap raises address-misaligned exceptions. Hardware performs the access at once
+/// as the hardware supporting misaligned accesses does, translating both pages if
+/// the access crosses a page boundary. Emulate performs the access byte by byte as
+/// the misaligned trap handler of OpenSBI does, so a fault may occur after a part
+/// of a store is written. LR/SC and AMOs always raise the exceptions.
+#[derive(Clone, Debug)]
+pub enum MisalignedAccess {
+    Trap,
+    Hardware,
+    Emulate,
+}
+
+/// LR/SC reservations of all harts sharing the bus, indexed by hart ID.
+/// The reserved addresses are physical addresses, so that a store by another
+/// hart can clear them regardless of its address translation.
+pub type Reservations = Rc<RefCell<Vec<Option<Reservation>>>>;
+
+/// A hart holds at most one reservation, on the naturally aligned reservation set
+/// containing the address of LR. cycle is when LR registered the reservation.
+#[derive(Clone, Copy)]
+pub struct Reservation {
+    address: u64,
+    cycle: u64,
+}
+
 pub struct Mmu {
-    pub bus: Box<dyn Bus>,
+    bus: Rc<RefCell<dyn Bus>>,
+    hart_id: usize,
     xlen: Xlen,
     ppn: u64,
+    asid: u64,
     addressing_mode: AddressingMode,
+    vs_ppn: u64,
+    vs_asid: u64,
+    vs_addressing_mode: AddressingMode,
+    g_ppn: u64,
+    g_addressing_mode: AddressingMode,
     privilege: Privilege,
-    reserved_address: HashMap<u64, bool>,
+    virtualization_mode: bool,
+    status: u64,
+    vs_status: u64,
+    executable_read: bool,
+    fault_guest_virtual_address: bool,
+    fault_guest_physical_address: u64,
+    pmp_entries: usize,
+    pmp_regions: Vec<PmpRegion>,
+    tlb: [Vec<TlbEntry>; 3],
+    walk_page_shift: usize,
+    walk_global: bool,
+    reserved_address: Reservations,
+    reservation_timeout: u64,
+    tlb_misses: u64,
+    misaligned_access: MisalignedAccess,
+    memories: CacheableMemories,
+    mmio_access: bool,
+    bus_ticks: u64,
+    bus_ticks_due: u64,
+}
+
+/// The software TLB is direct-mapped for each access type, and caches the
+/// translation for each 4 KiB virtual page. The translation also depends on the
+/// privilege mode and the virtualization mode, so they are a part of the tag
+/// instead of flushing the TLB at every trap. page_shift is the size of the page
+/// (or superpage) mapped by the leaf PTE, which is used to flush all 4 KiB pages
+/// of a superpage by the address.
+#[derive(Clone)]
+struct TlbEntry {
+    valid: bool,
+    vpn: u64,
+    asid: u64,
+    privilege: u8,
+    virtualization_mode: bool,
+    ppn: u64,
+    page_shift: usize,
+    global: bool,
+}
+
+const TLB_ENTRY_INVALID: TlbEntry = TlbEntry {
+    valid: false,
+    vpn: 0,
+    asid: 0,
+    privilege: 0,
+    virtualization_mode: false,
+    ppn: 0,
+    page_shift: 0,
+    global: false,
+};
+
+/// physical address range [start, end) and permissions of an active PMP entry.
+struct PmpRegion {
+    cfg: u8,
+    start: u64,
+    end: u64,
 }
 
 struct Pte {
     ppn: u64, // physical page number
-    ppns: [u64; 3],
     _rsw: u8, // reserved for use by supervisor software
     d: u8,    // dirty
     a: u8,    // accessed
-    _g: u8,   // global mapping
-    _u: u8,   // page is accessible to user mode
+    g: u8,    // global mapping
+    u: u8,    // page is accessible to user mode
     x: u8,    // execute permission
     w: u8,    // write permission
     r: u8,    // read permission
     v: u8,    // PTE is valid
 }
 
+#[derive(Clone, Copy)]
 enum MemoryAccessType {
     Fetch,
     Read,
     Write,
 }
 
+/// When V=1, VS-stage translates a guest virtual address into a guest physical
+/// address, and G-stage translates it into a supervisor physical address.
+enum TranslationStage {
+    Supervisor,
+    VirtualSupervisor,
+    Guest,
+}
+
 impl Mmu {
-    pub fn new(_xlen: Xlen, machine: Machine, console: Box<dyn Console>) -> Self {
-        let machine_bus: Box<dyn Bus> = match machine {
-            Machine::SiFiveE => Box::new(BusFe310::new(console)),
-            Machine::SiFiveU => Box::new(BusFu540::new(console)),
-            Machine::QemuVirt => Box::new(BusQemuVirt::new(console)),
-        };
+    pub fn new(
+        _xlen: Xlen,
+        hart_id_: usize,
+        bus_: Rc<RefCell<dyn Bus>>,
+        reserved_address_: Reservations,
+    ) -> Self {
+        let memories = CacheableMemories::new(bus_.borrow_mut().get_page_writes());
         Mmu {
-            bus: machine_bus,
+            bus: bus_,
+            hart_id: hart_id_,
             xlen: _xlen,
             ppn: 0,
+            asid: 0,
             addressing_mode: AddressingMode::Bare,
+            vs_ppn: 0,
+            vs_asid: 0,
+            vs_addressing_mode: AddressingMode::Bare,
+            g_ppn: 0,
+            g_addressing_mode: AddressingMode::Bare,
             privilege: Privilege::Machine,
-            reserved_address: HashMap::new(),
+            virtualization_mode: false,
+            status: 0,
+            vs_status: 0,
+            executable_read: false,
+            fault_guest_virtual_address: false,
+            fault_guest_physical_address: 0,
+            pmp_entries: 0,
+            pmp_regions: vec![],
+            tlb: [
+                vec![TLB_ENTRY_INVALID; TLB_ENTRIES],
+                vec![TLB_ENTRY_INVALID; TLB_ENTRIES],
+                vec![TLB_ENTRY_INVALID; TLB_ENTRIES],
+            ],
+            walk_page_shift: 0,
+            walk_global: false,
+            reserved_address: reserved_address_,
+            reservation_timeout: 0,
+            tlb_misses: 0,
+            misaligned_access: MisalignedAccess::Hardware,
+            memories,
+            mmio_access: false,
+            bus_ticks: 0,
+            bus_ticks_due: 0,
         }
     }
 
@@ -74,131 +199,326 @@ impl Mmu {
         self.xlen = xlen.clone();
     }
 
+    pub fn set_virtualization_mode(&mut self, virtualization_mode: bool) {
+        self.virtualization_mode = virtualization_mode;
+    }
+
+    /// MPRV, MPP and MPV of mstatus select the privilege mode of the loads and stores
+    /// in M-mode, and SUM and MXR of mstatus and vsstatus modify the permissions of
+    /// the pages. The TLB has to be flushed when SUM or MXR changes.
+    pub fn update_status(&mut self, csr: &Csr) {
+        let (status, vs_status) = csr.get_translation_status();
+        self.status = status;
+        self.vs_status = vs_status;
+    }
+
+    /// hlvx.hu and hlvx.wu read the memory with the execute permission instead of
+    /// the read permission.
+    pub fn set_executable_read(&mut self, executable_read: bool) {
+        self.executable_read = executable_read;
+    }
+
+    /// whether the faulting address of the last memory access is a guest virtual address.
+    pub fn is_fault_guest_virtual_address(&self) -> bool {
+        self.fault_guest_virtual_address
+    }
+
+    /// guest physical address which caused the last guest-page fault.
+    pub fn get_fault_guest_physical_address(&self) -> u64 {
+        self.fault_guest_physical_address
+    }
+
     pub fn update_addressing_mode(&mut self, data: u64) {
-        self.ppn = match self.xlen {
+        let (ppn, asid, addressing_mode) = self.parse_atp(data);
+        self.ppn = ppn;
+        self.asid = asid;
+        self.addressing_mode = addressing_mode;
+        self.clear_tlb();
+    }
+
+    pub fn update_vs_addressing_mode(&mut self, data: u64) {
+        let (ppn, asid, addressing_mode) = self.parse_atp(data);
+        self.vs_ppn = ppn;
+        self.vs_asid = asid;
+        self.vs_addressing_mode = addressing_mode;
+        self.clear_tlb();
+    }
+
+    /// hgatp selects Sv32x4, Sv39x4, Sv48x4 or Sv57x4, which are the variants of
+    /// Sv32, Sv39, Sv48 and Sv57 with the root page table widened by 2 bits.
+    pub fn update_g_stage_addressing_mode(&mut self, data: u64) {
+        let (ppn, _vmid, addressing_mode) = self.parse_atp(data);
+        self.g_ppn = ppn;
+        self.g_addressing_mode = addressing_mode;
+        self.clear_tlb();
+    }
+
+    /// parse the address translation and protection register (satp/vsatp/hgatp).
+    fn parse_atp(&self, data: u64) -> (u64, u64, AddressingMode) {
+        let ppn = match self.xlen {
             Xlen::X64 => data & 0xfffffffffff,
             Xlen::X32 => data & 0x3fffff,
         };
+        let asid = match self.xlen {
+            Xlen::X64 => (data >> 44) & 0xffff,
+            Xlen::X32 => (data >> 22) & 0x1ff,
+        };
 
-        self.addressing_mode = match self.xlen {
+        let addressing_mode = match self.xlen {
             Xlen::X64 => match data >> 60 {
                 0 => AddressingMode::Bare,
                 8 => AddressingMode::Sv39,
                 9 => AddressingMode::Sv48,
-                n => panic!(" {:x} is not implemented yet.", n),
+                10 => AddressingMode::Sv57,
+                // The CSR writes with the other modes have no effect, and any other
+                // value is taken as Bare.
+                _ => AddressingMode::Bare,
             },
             Xlen::X32 => match data & 0x80000000 {
                 0 => AddressingMode::Bare,
                 _ => AddressingMode::Sv32,
             },
         };
-        //println!("update mode => {:?}", self.addressing_mode);
+        (ppn, asid, addressing_mode)
     }
 
-    pub fn set_address_reserve(&mut self, addr: u64, request_reserve: bool) {
-        match request_reserve {
-            true => self.reserved_address.insert(addr, true),
-            false => self.reserved_address.remove(&addr),
+    /// flush the TLB entries for the virtualization mode. v_addr selects the entries
+    /// which map the address, and asid selects the entries of the address space
+    /// except for the global mappings. None selects all entries.
+    pub fn flush_tlb(&mut self, v_addr: Option<u64>, asid: Option<u64>, virtualization_mode: bool) {
+        let vpn = v_addr.map(|v_addr| self.to_effective_address(v_addr) >> 12);
+        for entry in self.tlb.iter_mut().flatten() {
+            if !entry.valid {
+                continue;
+            }
+            let shift = entry.page_shift - 12;
+            let selected_by_vpn = match vpn {
+                Some(vpn) => (entry.vpn >> shift) == (vpn >> shift),
+                None => true,
+            };
+            let selected_by_asid = match asid {
+                Some(asid) => entry.asid == asid && !entry.global,
+                None => true,
+            };
+            if entry.virtualization_mode == virtualization_mode
+                && selected_by_vpn
+                && selected_by_asid
+            {
+                entry.valid = false;
+            }
+        }
+    }
+
+    fn clear_tlb(&mut self) {
+        for entry in self.tlb.iter_mut().flatten() {
+            entry.valid = false;
+        }
+    }
+
+    /// decode the pmpcfg and pmpaddr registers into the address ranges. The entries
+    /// whose A field is OFF are skipped, and the order of the entries is kept because
+    /// the lowest-numbered matching entry determines the result of the access.
+    pub fn update_pmp(&mut self, csr: &Csr) {
+        self.pmp_entries = csr.get_pmp_entries();
+        self.pmp_regions.clear();
+        self.clear_tlb();
+        for entry in 0..self.pmp_entries {
+            let cfg = csr.get_pmpcfg(entry);
+            let pmpaddr = csr.get_pmpaddr(entry);
+            let (start, end) = match cfg & CSR_PMPCFG_A {
+                PMP_A_TOR => match entry {
+                    0 => (0, pmpaddr << 2),
+                    _ => (csr.get_pmpaddr(entry - 1) << 2, pmpaddr << 2),
+                },
+                PMP_A_NA4 => (pmpaddr << 2, (pmpaddr << 2) + 4),
+                PMP_A_NAPOT => {
+                    let size = 1 << (pmpaddr.trailing_ones() + 3);
+                    let start = (pmpaddr << 2) & !(size - 1);
+                    (start, start + size)
+                }
+                _ => continue,
+            };
+            // TOR entry matches nothing if the bottom of the range is not below the top.
+            if start < end {
+                self.pmp_regions.push(PmpRegion { cfg, start, end });
+            }
+        }
+    }
+
+    /// set the number of cycles after which a reservation expires (0: never).
+    pub fn set_reservation_timeout(&mut self, cycles: u64) {
+        self.reservation_timeout = cycles;
+    }
+
+    pub fn get_reservation_timeout(&self) -> u64 {
+        self.reservation_timeout
+    }
+
+    pub fn set_misaligned_access(&mut self, misaligned_access: MisalignedAccess) {
+        self.misaligned_access = misaligned_access;
+    }
+
+    pub fn get_misaligned_access(&self) -> MisalignedAccess {
+        self.misaligned_access.clone()
+    }
+
+    /// LR/SC and AMOs raise address-misaligned exceptions regardless of the
+    /// misaligned access mode if the address is not naturally aligned.
+    pub fn check_atomic_alignment(
+        &self,
+        v_addr: u64,
+        size: u64,
+        exception: Exception,
+    ) -> Result<(), Trap> {
+        let ev_addr = self.to_effective_address(v_addr);
+        match (ev_addr & (size - 1)) == 0 {
+            true => Ok(()),
+            false => Err(Trap {
+                exception,
+                value: ev_addr,
+            }),
+        }
+    }
+
+    /// AMOs raise store/AMO exceptions also for the faults on the read, so the
+    /// address is checked for the write before the read.
+    pub fn check_amo_address(&mut self, v_addr: u64, size: u64) -> Result<(), Trap> {
+        match self.check_atomic_alignment(v_addr, size, Exception::StoreAddressMisaligned) {
+            Ok(()) => {}
+            Err(e) => return Err(e),
+        };
+        let ev_addr = self.to_effective_address(v_addr);
+        match self.to_physical_address(ev_addr, size, MemoryAccessType::Write) {
+            Ok(_) => Ok(()),
+            Err(exception) => Err(Trap {
+                exception,
+                value: ev_addr,
+            }),
+        }
+    }
+
+    /// LR registers a reservation on the reservation set containing the address,
+    /// and drops the previous reservation of the hart.
+    pub fn reserve_address(&mut self, v_addr: u64, cycle: u64) {
+        let reservation = self
+            .translate_reserved_address(v_addr)
+            .map(|p_addr| Reservation {
+                address: p_addr & !(RESERVATION_SET_SIZE - 1),
+                cycle,
+            });
+        self.reserved_address.borrow_mut()[self.hart_id] = reservation;
+    }
+
+    pub fn is_address_reserved(&mut self, v_addr: u64, cycle: u64) -> bool {
+        let p_addr = match self.translate_reserved_address(v_addr) {
+            Some(p_addr) => p_addr,
+            None => return false,
         };
+        match self.reserved_address.borrow()[self.hart_id] {
+            Some(reservation) => {
+                reservation.address == p_addr & !(RESERVATION_SET_SIZE - 1)
+                    && (self.reservation_timeout == 0
+                        || cycle.wrapping_sub(reservation.cycle) <= self.reservation_timeout)
+            }
+            None => false,
+        }
+    }
+
+    /// SC, trap entry and trap return invalidate the reservation of the hart.
+    pub fn cancel_reservation(&mut self) {
+        self.reserved_address.borrow_mut()[self.hart_id] = None;
     }
 
-    pub fn is_address_reserved(&mut self, addr: u64) -> bool {
-        match self.reserved_address.get_mut(&addr) {
-            Some(_v) => true,
-            _ => false,
+    fn translate_reserved_address(&mut self, v_addr: u64) -> Option<u64> {
+        let ev_addr = self.to_effective_address(v_addr);
+        self.to_physical_address(ev_addr, 1, MemoryAccessType::Read)
+            .ok()
+    }
+
+    /// A store by any hart invalidates the reservations on the reservation sets
+    /// it overlaps.
+    fn clear_reserved_address(&self, p_addr: u64, size: u64) {
+        let first = p_addr & !(RESERVATION_SET_SIZE - 1);
+        let last = p_addr.wrapping_add(size - 1) & !(RESERVATION_SET_SIZE - 1);
+        for reservation in self.reserved_address.borrow_mut().iter_mut() {
+            match reservation {
+                Some(r) if r.address == first || r.address == last => *reservation = None,
+                _ => {}
+            }
         }
     }
 
-    pub fn get_bus(&mut self) -> &mut Box<dyn Bus> {
-        &mut self.bus
+    /// get the number of TLB misses since the last call.
+    pub fn take_tlb_misses(&mut self) -> u64 {
+        std::mem::replace(&mut self.tlb_misses, 0)
+    }
+
+    /// check whether a load or store has accessed a device other than the memories
+    /// since the last call.
+    pub fn take_mmio_access(&mut self) -> bool {
+        std::mem::replace(&mut self.mmio_access, false)
+    }
+
+    /// The block engine runs the peripherals after the block, so the bus is run up
+    /// to the current instruction before a load or store to a device. ticks is the
+    /// number of ticks of the bus since the block started, and due is the number
+    /// of the instructions executed before the current one.
+    pub fn set_bus_ticks(&mut self, ticks: u64, due: u64) {
+        self.bus_ticks = ticks;
+        self.bus_ticks_due = due;
+    }
+
+    pub fn get_bus_ticks(&self) -> u64 {
+        self.bus_ticks
+    }
+
+    /// run the bus up to the current instruction of the block engine.
+    pub fn tick_bus(&mut self) {
+        if self.bus_ticks < self.bus_ticks_due {
+            let ticks = self.bus_ticks_due - self.bus_ticks;
+            self.bus.borrow_mut().tick(ticks);
+            self.bus_ticks = self.bus_ticks_due;
+        }
+    }
+
+    pub fn get_bus(&mut self) -> RefMut<'_, dyn Bus> {
+        self.bus.borrow_mut()
     }
 
     pub fn read8(&mut self, v_addr: u64) -> Result<u8, Trap> {
         let ev_addr = self.to_effective_address(v_addr);
-        match self.to_physical_address(ev_addr, MemoryAccessType::Read) {
-            Ok(p_addr) => match self.bus.read8(p_addr) {
+        match self.to_physical_address(ev_addr, 1, MemoryAccessType::Read) {
+            Ok(p_addr) => match self.bus.borrow_mut().read8(p_addr) {
                 Ok(data) => Ok(data),
                 Err(()) => Err(Trap {
                     exception: Exception::LoadPageFault,
                     value: ev_addr,
                 }),
             },
-            Err(()) => Err(Trap {
-                exception: Exception::LoadPageFault,
+            Err(exception) => Err(Trap {
+                exception,
                 value: ev_addr,
             }),
         }
     }
 
     pub fn read16(&mut self, v_addr: u64) -> Result<u16, Trap> {
-        // sometimes access to unaliggned acccess.
-        // If it exceeds the page size, it is necessary to refer to another page table.
-        match v_addr & (PAGE_SIZE - 1) <= (PAGE_SIZE - 2) {
-            true => {
-                let ev_addr = self.to_effective_address(v_addr);
-                match self.to_physical_address(ev_addr, MemoryAccessType::Read) {
-                    Ok(p_addr) => match self.bus.read16(p_addr) {
-                        Ok(data) => Ok(data),
-                        Err(()) => Err(Trap {
-                            exception: Exception::LoadPageFault,
-                            value: ev_addr,
-                        }),
-                    },
-                    Err(()) => Err(Trap {
-                        exception: Exception::LoadPageFault,
-                        value: ev_addr,
-                    }),
-                }
-            }
-            _ => {
-                let mut data = 0 as u16;
-                for i in 0..2 {
-                    match self.read8(v_addr.wrapping_add(i)) {
-                        Ok(d) => data |= (d as u16) << (i * 8),
-                        Err(e) => return Err(e),
-                    }
-                }
-                Ok(data)
-            }
+        match self.read(v_addr, 2) {
+            Ok(data) => Ok(data as u16),
+            Err(e) => Err(e),
         }
     }
 
     pub fn read32(&mut self, v_addr: u64) -> Result<u32, Trap> {
-        // sometimes access to unaliggned acccess.
-        // If it exceeds the page size, it is necessary to refer to another page table.
-        match v_addr & (PAGE_SIZE - 1) <= (PAGE_SIZE - 4) {
-            true => {
-                let ev_addr = self.to_effective_address(v_addr);
-                match self.to_physical_address(ev_addr, MemoryAccessType::Read) {
-                    Ok(p_addr) => match self.bus.read32(p_addr) {
-                        Ok(data) => Ok(data),
-                        Err(()) => Err(Trap {
-                            exception: Exception::LoadPageFault,
-                            value: ev_addr,
-                        }),
-                    },
-                    Err(()) => Err(Trap {
-                        exception: Exception::LoadPageFault,
-                        value: ev_addr,
-                    }),
-                }
-            }
-            _ => {
-                let mut data = 0 as u32;
-                for i in 0..4 {
-                    match self.read8(v_addr.wrapping_add(i)) {
-                        Ok(d) => data |= (d as u32) << (i * 8),
-                        Err(e) => return Err(e),
-                    }
-                }
-                Ok(data)
-            }
+        match self.read(v_addr, 4) {
+            Ok(data) => Ok(data as u32),
+            Err(e) => Err(e),
         }
     }
 
     pub fn read32_direct(&mut self, p_addr: u64) -> Result<u32, Trap> {
         let ep_addr = self.to_effective_address(p_addr);
-        match self.bus.read32(p_addr) {
+        match self.bus.borrow_mut().read32(p_addr) {
             Ok(data) => Ok(data),
             Err(()) => Err(Trap {
                 exception: Exception::LoadPageFault,
@@ -208,149 +528,246 @@ impl Mmu {
     }
 
     pub fn read64(&mut self, v_addr: u64) -> Result<u64, Trap> {
-        // sometimes access to unaliggned acccess.
-        // If it exceeds the page size, it is necessary to refer to another page table.
-        match v_addr & (PAGE_SIZE - 1) <= (PAGE_SIZE - 8) {
-            true => {
-                let ev_addr = self.to_effective_address(v_addr);
-                match self.to_physical_address(ev_addr, MemoryAccessType::Read) {
-                    Ok(p_addr) => match self.bus.read64(p_addr) {
-                        Ok(data) => Ok(data),
-                        Err(()) => Err(Trap {
-                            exception: Exception::LoadPageFault,
-                            value: ev_addr,
-                        }),
-                    },
-                    Err(()) => Err(Trap {
-                        exception: Exception::LoadPageFault,
+        self.read(v_addr, 8)
+    }
+
+    /// load size bytes. A misaligned load is handled by the misaligned access mode.
+    fn read(&mut self, v_addr: u64, size: u64) -> Result<u64, Trap> {
+        let ev_addr = self.to_effective_address(v_addr);
+        if (ev_addr & (size - 1)) != 0 {
+            match self.misaligned_access {
+                MisalignedAccess::Trap => {
+                    return Err(Trap {
+                        exception: Exception::LoadAddressMisaligned,
                         value: ev_addr,
-                    }),
+                    })
                 }
-            }
-            _ => {
-                let mut data = 0 as u64;
-                for i in 0..8 {
-                    match self.read8(v_addr.wrapping_add(i)) {
-                        Ok(d) => data |= (d as u64) << (i * 8),
-                        Err(e) => return Err(e),
+                MisalignedAccess::Emulate => return self.read_bytes(ev_addr, size),
+                MisalignedAccess::Hardware => {
+                    if (ev_addr & (PAGE_SIZE - 1)) > PAGE_SIZE - size {
+                        return self.read_page_crossing(ev_addr, size);
                     }
                 }
-                Ok(data)
             }
         }
+        match self.to_physical_address(ev_addr, size, MemoryAccessType::Read) {
+            Ok(p_addr) => match self.read_bus(p_addr, size) {
+                Ok(data) => Ok(data),
+                Err(()) => Err(Trap {
+                    exception: Exception::LoadPageFault,
+                    value: ev_addr,
+                }),
+            },
+            Err(exception) => Err(Trap {
+                exception,
+                value: ev_addr,
+            }),
+        }
+    }
+
+    fn read_bus(&mut self, p_addr: u64, size: u64) -> Result<u64, ()> {
+        let mut bus = self.bus.borrow_mut();
+        match size {
+            1 => match bus.read8(p_addr) {
+                Ok(data) => Ok(data as u64),
+                Err(()) => Err(()),
+            },
+            2 => match bus.read16(p_addr) {
+                Ok(data) => Ok(data as u64),
+                Err(()) => Err(()),
+            },
+            4 => match bus.read32(p_addr) {
+                Ok(data) => Ok(data as u64),
+                Err(()) => Err(()),
+            },
+            _ => bus.read64(p_addr),
+        }
+    }
+
+    /// load byte by byte, and each byte is translated separately.
+    fn read_bytes(&mut self, ev_addr: u64, size: u64) -> Result<u64, Trap> {
+        let mut data = 0;
+        for i in 0..size {
+            match self.read8(ev_addr.wrapping_add(i)) {
+                Ok(d) => data |= (d as u64) << (i * 8),
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(data)
+    }
+
+    fn read_page_crossing(&mut self, ev_addr: u64, size: u64) -> Result<u64, Trap> {
+        let (p_addrs, first_size) =
+            match self.translate_page_crossing(ev_addr, size, MemoryAccessType::Read) {
+                Ok(translation) => translation,
+                Err(e) => return Err(e),
+            };
+        let mut data = 0;
+        for i in 0..size {
+            let p_addr = match i < first_size {
+                true => p_addrs.0 + i,
+                false => p_addrs.1 + (i - first_size),
+            };
+            match self.bus.borrow_mut().read8(p_addr) {
+                Ok(d) => data |= (d as u64) << (i * 8),
+                Err(()) => {
+                    return Err(Trap {
+                        exception: Exception::LoadPageFault,
+                        value: ev_addr,
+                    })
+                }
+            }
+        }
+        Ok(data)
+    }
+
+    /// translate both pages of a misaligned access crossing a page boundary before
+    /// the access, so that the access is not partially performed. The physical
+    /// addresses of the both parts and the size of the first part are returned.
+    fn translate_page_crossing(
+        &mut self,
+        ev_addr: u64,
+        size: u64,
+        access_type: MemoryAccessType,
+    ) -> Result<((u64, u64), u64), Trap> {
+        let first_size = PAGE_SIZE - (ev_addr & (PAGE_SIZE - 1));
+        let second_addr = self.to_effective_address(ev_addr.wrapping_add(first_size));
+        let first = match self.to_physical_address(ev_addr, first_size, access_type) {
+            Ok(p_addr) => p_addr,
+            Err(exception) => {
+                return Err(Trap {
+                    exception,
+                    value: ev_addr,
+                })
+            }
+        };
+        match self.to_physical_address(second_addr, size - first_size, access_type) {
+            Ok(second) => Ok(((first, second), first_size)),
+            Err(exception) => Err(Trap {
+                exception,
+                value: second_addr,
+            }),
+        }
     }
 
     pub fn write8(&mut self, v_addr: u64, val: u8) -> Result<(), Trap> {
         let ev_addr = self.to_effective_address(v_addr);
-        match self.to_physical_address(ev_addr, MemoryAccessType::Write) {
-            Ok(p_addr) => match self.bus.write8(p_addr, val) {
-                Ok(()) => Ok(()),
+        match self.to_physical_address(ev_addr, 1, MemoryAccessType::Write) {
+            Ok(p_addr) => match self.bus.borrow_mut().write8(p_addr, val) {
+                Ok(()) => {
+                    self.clear_reserved_address(p_addr, 1);
+                    Ok(())
+                }
                 Err(()) => Err(Trap {
                     exception: Exception::StorePageFault,
                     value: ev_addr,
                 }),
             },
-            Err(()) => Err(Trap {
-                exception: Exception::StorePageFault,
+            Err(exception) => Err(Trap {
+                exception,
                 value: ev_addr,
             }),
         }
     }
 
     pub fn write16(&mut self, v_addr: u64, data: u16) -> Result<(), Trap> {
-        // sometimes access to unaliggned acccess.
-        // If it exceeds the page size, it is necessary to refer to another page table.
-        match v_addr & (PAGE_SIZE - 1) <= (PAGE_SIZE - 2) {
-            true => {
-                let ev_addr = self.to_effective_address(v_addr);
-                match self.to_physical_address(ev_addr, MemoryAccessType::Write) {
-                    Ok(p_addr) => match self.bus.write16(p_addr, data) {
-                        Ok(()) => Ok(()),
-                        Err(()) => Err(Trap {
-                            exception: Exception::StorePageFault,
-                            value: ev_addr,
-                        }),
-                    },
-                    Err(()) => Err(Trap {
-                        exception: Exception::StorePageFault,
+        self.write(v_addr, data as u64, 2)
+    }
+
+    pub fn write32(&mut self, v_addr: u64, data: u32) -> Result<(), Trap> {
+        self.write(v_addr, data as u64, 4)
+    }
+
+    pub fn write64(&mut self, v_addr: u64, data: u64) -> Result<(), Trap> {
+        self.write(v_addr, data, 8)
+    }
+
+    /// store size bytes. A misaligned store is handled by the misaligned access mode.
+    fn write(&mut self, v_addr: u64, data: u64, size: u64) -> Result<(), Trap> {
+        let ev_addr = self.to_effective_address(v_addr);
+        if (ev_addr & (size - 1)) != 0 {
+            match self.misaligned_access {
+                MisalignedAccess::Trap => {
+                    return Err(Trap {
+                        exception: Exception::StoreAddressMisaligned,
                         value: ev_addr,
-                    }),
+                    })
                 }
-            }
-            _ => {
-                for i in 0..2 {
-                    match self.write8(v_addr.wrapping_add(i), ((data >> (i * 8)) & 0xff) as u8) {
-                        Err(e) => return Err(e),
-                        _ => {}
+                MisalignedAccess::Emulate => return self.write_bytes(ev_addr, data, size),
+                MisalignedAccess::Hardware => {
+                    if (ev_addr & (PAGE_SIZE - 1)) > PAGE_SIZE - size {
+                        return self.write_page_crossing(ev_addr, data, size);
                     }
                 }
-                Ok(())
             }
         }
+        match self.to_physical_address(ev_addr, size, MemoryAccessType::Write) {
+            Ok(p_addr) => match self.write_bus(p_addr, data, size) {
+                Ok(()) => {
+                    self.clear_reserved_address(p_addr, size);
+                    Ok(())
+                }
+                Err(()) => Err(Trap {
+                    exception: Exception::StorePageFault,
+                    value: ev_addr,
+                }),
+            },
+            Err(exception) => Err(Trap {
+                exception,
+                value: ev_addr,
+            }),
+        }
     }
 
-    pub fn write32(&mut self, v_addr: u64, data: u32) -> Result<(), Trap> {
-        // sometimes access to unaliggned acccess.
-        // If it exceeds the page size, it is necessary to refer to another page table.
-        match v_addr & (PAGE_SIZE - 1) <= (PAGE_SIZE - 4) {
-            true => {
-                let ev_addr = self.to_effective_address(v_addr);
-                match self.to_physical_address(ev_addr, MemoryAccessType::Write) {
-                    Ok(p_addr) => match self.bus.write32(p_addr, data) {
-                        Ok(()) => Ok(()),
-                        Err(()) => Err(Trap {
-                            exception: Exception::StorePageFault,
-                            value: ev_addr,
-                        }),
-                    },
-                    Err(()) => Err(Trap {
-                        exception: Exception::StorePageFault,
-                        value: ev_addr,
-                    }),
-                }
-            }
-            _ => {
-                for i in 0..4 {
-                    match self.write8(v_addr.wrapping_add(i), ((data >> (i * 8)) & 0xff) as u8) {
-                        Err(e) => return Err(e),
-                        _ => {}
-                    }
-                }
-                Ok(())
+    fn write_bus(&mut self, p_addr: u64, data: u64, size: u64) -> Result<(), ()> {
+        let mut bus = self.bus.borrow_mut();
+        match size {
+            1 => bus.write8(p_addr, data as u8),
+            2 => bus.write16(p_addr, data as u16),
+            4 => bus.write32(p_addr, data as u32),
+            _ => bus.write64(p_addr, data),
+        }
+    }
+
+    /// store byte by byte, and each byte is translated separately.
+    fn write_bytes(&mut self, ev_addr: u64, data: u64, size: u64) -> Result<(), Trap> {
+        for i in 0..size {
+            match self.write8(ev_addr.wrapping_add(i), (data >> (i * 8)) as u8) {
+                Ok(()) => {}
+                Err(e) => return Err(e),
             }
         }
+        Ok(())
     }
 
-    pub fn write64(&mut self, v_addr: u64, data: u64) -> Result<(), Trap> {
-        // sometimes access to unaliggned acccess.
-        // If it exceeds the page size, it is necessary to refer to another page table.
-        match v_addr & (PAGE_SIZE - 1) <= (PAGE_SIZE - 8) {
-            true => {
-                let ev_addr = self.to_effective_address(v_addr);
-                match self.to_physical_address(ev_addr, MemoryAccessType::Write) {
-                    Ok(p_addr) => match self.bus.write64(p_addr, data) {
-                        Ok(()) => Ok(()),
-                        Err(()) => Err(Trap {
-                            exception: Exception::StorePageFault,
-                            value: ev_addr,
-                        }),
-                    },
-                    Err(()) => Err(Trap {
+    fn write_page_crossing(&mut self, ev_addr: u64, data: u64, size: u64) -> Result<(), Trap> {
+        let (p_addrs, first_size) =
+            match self.translate_page_crossing(ev_addr, size, MemoryAccessType::Write) {
+                Ok(translation) => translation,
+                Err(e) => return Err(e),
+            };
+        for i in 0..size {
+            let p_addr = match i < first_size {
+                true => p_addrs.0 + i,
+                false => p_addrs.1 + (i - first_size),
+            };
+            match self
+                .bus
+                .borrow_mut()
+                .write8(p_addr, (data >> (i * 8)) as u8)
+            {
+                Ok(()) => {}
+                Err(()) => {
+                    return Err(Trap {
                         exception: Exception::StorePageFault,
                         value: ev_addr,
-                    }),
-                }
-            }
-            _ => {
-                for i in 0..8 {
-                    match self.write8(v_addr.wrapping_add(i), ((data >> (i * 8)) & 0xff) as u8) {
-                        Err(e) => return Err(e),
-                        _ => {}
-                    }
+                    })
                 }
-                Ok(())
             }
         }
+        self.clear_reserved_address(p_addrs.0, first_size);
+        self.clear_reserved_address(p_addrs.1, size - first_size);
+        Ok(())
     }
 
     pub fn fetch32(&mut self, v_addr: u64) -> Result<u32, Trap> {
@@ -359,16 +776,16 @@ impl Mmu {
         match v_addr & (PAGE_SIZE - 1) <= (PAGE_SIZE - 4) {
             true => {
                 let ev_addr = self.to_effective_address(v_addr);
-                match self.to_physical_address(ev_addr, MemoryAccessType::Fetch) {
-                    Ok(p_addr) => match self.bus.read32(p_addr) {
+                match self.to_physical_address(ev_addr, 4, MemoryAccessType::Fetch) {
+                    Ok(p_addr) => match self.bus.borrow_mut().read32(p_addr) {
                         Ok(data) => Ok(data),
                         Err(()) => Err(Trap {
                             exception: Exception::InstructionPageFault,
                             value: ev_addr,
                         }),
                     },
-                    Err(()) => Err(Trap {
-                        exception: Exception::InstructionPageFault,
+                    Err(exception) => Err(Trap {
+                        exception,
                         value: ev_addr,
                     }),
                 }
@@ -386,19 +803,32 @@ impl Mmu {
         }
     }
 
+    /// translate the address of an instruction of size bytes for the decoded
+    /// instruction cache, which is indexed by the physical address.
+    pub fn translate_fetch(&mut self, v_addr: u64, size: u64) -> Result<u64, Trap> {
+        let ev_addr = self.to_effective_address(v_addr);
+        match self.to_physical_address(ev_addr, size, MemoryAccessType::Fetch) {
+            Ok(p_addr) => Ok(p_addr),
+            Err(exception) => Err(Trap {
+                exception,
+                value: ev_addr,
+            }),
+        }
+    }
+
     /// Instruction fetch for unaliggned acccess when virtual addressing mode.
     fn fetch8(&mut self, v_addr: u64) -> Result<u8, Trap> {
         let ev_addr = self.to_effective_address(v_addr);
-        match self.to_physical_address(ev_addr, MemoryAccessType::Fetch) {
-            Ok(p_addr) => match self.bus.read8(p_addr) {
+        match self.to_physical_address(ev_addr, 1, MemoryAccessType::Fetch) {
+            Ok(p_addr) => match self.bus.borrow_mut().read8(p_addr) {
                 Ok(data) => Ok(data),
                 Err(()) => Err(Trap {
                     exception: Exception::InstructionPageFault,
                     value: ev_addr,
                 }),
             },
-            Err(()) => Err(Trap {
-                exception: Exception::InstructionPageFault,
+            Err(exception) => Err(Trap {
+                exception,
                 value: ev_addr,
             }),
         }
@@ -407,38 +837,252 @@ impl Mmu {
     fn to_physical_address(
         &mut self,
         v_addr: u64,
+        size: u64,
         access_type: MemoryAccessType,
-    ) -> Result<u64, ()> {
-        //println!("AddressingMode = {:?}", self.addressing_mode);
-        match self.addressing_mode {
-            AddressingMode::Bare => Ok(v_addr),
-            AddressingMode::Sv32 => match self.privilege {
-                Privilege::User | Privilege::Supervisor => {
-                    let vpns = [(v_addr >> 12) & 0x3ff, (v_addr >> 22) & 0x3ff];
-                    self.page_waking(v_addr, 1, self.ppn, &vpns, &access_type)
+    ) -> Result<u64, Exception> {
+        // The loads and stores in M-mode with MPRV=1 are translated and protected as
+        // in the privilege mode of MPP and the virtualization mode of MPV.
+        if matches!(self.privilege, Privilege::Machine)
+            && !matches!(access_type, MemoryAccessType::Fetch)
+            && (self.status & CSR_STATUS_MPRV) != 0
+            && (self.status & CSR_STATUS_MPP) != CSR_STATUS_MPP
+        {
+            let previous_virtualization_mode = self.virtualization_mode;
+            self.privilege = match (self.status & CSR_STATUS_MPP) >> 11 {
+                0 => Privilege::User,
+                _ => Privilege::Supervisor,
+            };
+            self.virtualization_mode = (self.status & CSR_STATUS_MPV) != 0;
+            let p_addr = self.to_physical_address(v_addr, size, access_type);
+            self.privilege = Privilege::Machine;
+            self.virtualization_mode = previous_virtualization_mode;
+            return p_addr;
+        }
+        self.fault_guest_virtual_address = self.virtualization_mode;
+        self.fault_guest_physical_address = 0;
+        let p_addr = match self.privilege {
+            Privilege::Machine => Ok(v_addr),
+            _ => self.translate_with_tlb(v_addr, access_type),
+        };
+        match p_addr {
+            Ok(p_addr) => match self.check_pmp(p_addr, size, &access_type, &self.privilege) {
+                true => {
+                    if !matches!(access_type, MemoryAccessType::Fetch)
+                        && !self.memories.contains(p_addr)
+                    {
+                        self.mmio_access = true;
+                        self.tick_bus();
+                    }
+                    Ok(p_addr)
                 }
-                _ => Ok(v_addr),
+                false => Err(self.get_access_fault(&access_type)),
             },
-            AddressingMode::Sv39 => match self.privilege {
-                Privilege::User | Privilege::Supervisor => {
-                    let vpns = [
-                        (v_addr >> 12) & 0x1ff,
-                        (v_addr >> 21) & 0x1ff,
-                        (v_addr >> 30) & 0x1ff,
-                    ];
-                    self.page_waking(v_addr, 2, self.ppn, &vpns, &access_type)
-                }
-                _ => Ok(v_addr),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// translate the address by the TLB, or by the page-table walk if the TLB misses.
+    /// A write is cached only after the page-table walk sets the D bit, so the A/D
+    /// bits of PTE are updated as well as without the TLB. The translations by hlvx
+    /// are not cached since they are checked with the execute permission.
+    fn translate_with_tlb(
+        &mut self,
+        v_addr: u64,
+        access_type: MemoryAccessType,
+    ) -> Result<u64, Exception> {
+        let vpn = v_addr >> 12;
+        let asid = match self.virtualization_mode {
+            true => self.vs_asid,
+            false => self.asid,
+        };
+        let privilege = self.privilege.clone() as u8;
+        let index = (vpn as usize) & (TLB_ENTRIES - 1);
+        if !self.executable_read {
+            let entry = &self.tlb[access_type as usize][index];
+            if entry.valid
+                && entry.vpn == vpn
+                && entry.asid == asid
+                && entry.privilege == privilege
+                && entry.virtualization_mode == self.virtualization_mode
+            {
+                return Ok((entry.ppn << 12) | (v_addr & 0xfff));
+            }
+        }
+
+        // The translation in Bare mode is not cached. The page of two-stage
+        // translation is the smaller one of both stages.
+        if self.virtualization_mode || !matches!(self.addressing_mode, AddressingMode::Bare) {
+            self.tlb_misses = self.tlb_misses.wrapping_add(1);
+        }
+        let p_addr = match self.virtualization_mode {
+            false => match self.addressing_mode {
+                AddressingMode::Bare => return Ok(v_addr),
+                _ => self.translate(v_addr, &access_type, &TranslationStage::Supervisor),
             },
-            AddressingMode::Sv48 => {
-                panic!("AddressingMode SV48 is not implemented yet.");
+            true => {
+                match self.translate(v_addr, &access_type, &TranslationStage::VirtualSupervisor) {
+                    Ok(g_addr) => {
+                        let (page_shift, global) = (self.walk_page_shift, self.walk_global);
+                        let p_addr = self.translate(g_addr, &access_type, &TranslationStage::Guest);
+                        self.walk_page_shift = self.walk_page_shift.min(page_shift);
+                        self.walk_global = global;
+                        p_addr
+                    }
+                    Err(e) => Err(e),
+                }
+            }
+        };
+        if let Ok(p_addr) = p_addr {
+            if !self.executable_read {
+                self.tlb[access_type as usize][index] = TlbEntry {
+                    valid: true,
+                    vpn,
+                    asid,
+                    privilege,
+                    virtualization_mode: self.virtualization_mode,
+                    ppn: p_addr >> 12,
+                    page_shift: self.walk_page_shift,
+                    global: self.walk_global,
+                };
+            }
+        }
+        p_addr
+    }
+
+    /// check the physical memory protection. The lowest-numbered entry that matches
+    /// any byte of the access determines whether the access succeeds, and the access
+    /// fails if the entry does not match all bytes of the access. The permissions of
+    /// the entry are enforced in M-mode only when the entry is locked. If no entry
+    /// matches, only the M-mode access succeeds unless no entry is implemented.
+    fn check_pmp(
+        &self,
+        p_addr: u64,
+        size: u64,
+        access_type: &MemoryAccessType,
+        privilege: &Privilege,
+    ) -> bool {
+        let p_end = p_addr.wrapping_add(size);
+        for region in self.pmp_regions.iter() {
+            if p_end <= region.start || region.end <= p_addr {
+                continue;
+            }
+            if p_addr < region.start || region.end < p_end {
+                return false;
+            }
+            if let Privilege::Machine = privilege {
+                if (region.cfg & CSR_PMPCFG_L) == 0 {
+                    return true;
+                }
             }
-            AddressingMode::Sv57 => {
-                panic!("AddressingMode SV57 is not implemented yet.");
+            let permission = match access_type {
+                MemoryAccessType::Fetch => CSR_PMPCFG_X,
+                MemoryAccessType::Read => CSR_PMPCFG_R,
+                MemoryAccessType::Write => CSR_PMPCFG_W,
+            };
+            return (region.cfg & permission) != 0;
+        }
+        match privilege {
+            Privilege::Machine => true,
+            _ => self.pmp_entries == 0,
+        }
+    }
+
+    fn get_access_fault(&self, access_type: &MemoryAccessType) -> Exception {
+        match access_type {
+            MemoryAccessType::Fetch => Exception::InstructionAccessFault,
+            MemoryAccessType::Read => Exception::LoadAccessFault,
+            MemoryAccessType::Write => Exception::StoreAccessFault,
+        }
+    }
+
+    fn get_addressing_mode(&self, stage: &TranslationStage) -> AddressingMode {
+        match stage {
+            TranslationStage::Supervisor => self.addressing_mode.clone(),
+            TranslationStage::VirtualSupervisor => self.vs_addressing_mode.clone(),
+            TranslationStage::Guest => self.g_addressing_mode.clone(),
+        }
+    }
+
+    /// translate an address by a single stage of the address translation.
+    fn translate(
+        &mut self,
+        v_addr: u64,
+        access_type: &MemoryAccessType,
+        stage: &TranslationStage,
+    ) -> Result<u64, Exception> {
+        let ppn = match stage {
+            TranslationStage::Supervisor => self.ppn,
+            TranslationStage::VirtualSupervisor => self.vs_ppn,
+            TranslationStage::Guest => self.g_ppn,
+        };
+        // The guest physical address is 2 bits wider than the virtual address.
+        let widen = match stage {
+            TranslationStage::Guest => 2,
+            _ => 0,
+        };
+        let (levels, vpn_bits) = match self.get_addressing_mode(stage) {
+            AddressingMode::Bare => {
+                self.walk_page_shift = 64;
+                self.walk_global = true;
+                return Ok(v_addr);
             }
+            AddressingMode::Sv32 => (2, 10),
+            AddressingMode::Sv39 => (3, 9),
+            AddressingMode::Sv48 => (4, 9),
+            AddressingMode::Sv57 => (5, 9),
             AddressingMode::Sv64 => {
                 panic!("AddressingMode SV64 is not implemented yet.");
             }
+        };
+
+        // The bits above the virtual address have to be equal to its most significant
+        // bit, and the bits above the guest physical address have to be zero.
+        let va_bits = 12 + levels * vpn_bits;
+        let canonical = match (stage, levels) {
+            (TranslationStage::Guest, _) => (v_addr >> (va_bits + widen)) == 0,
+            (_, 2) => true,
+            _ => {
+                let upper = (v_addr as i64) >> (va_bits - 1);
+                upper == 0 || upper == -1
+            }
+        };
+        if !canonical {
+            return Err(self.get_page_fault(v_addr, access_type, stage));
+        }
+
+        let mut vpns = [0; 5];
+        for (i, vpn) in vpns.iter_mut().enumerate().take(levels) {
+            let bits = match i == levels - 1 {
+                true => vpn_bits + widen,
+                false => vpn_bits,
+            };
+            *vpn = (v_addr >> (12 + i * vpn_bits)) & ((1 << bits) - 1);
+        }
+        self.page_waking(v_addr, (levels - 1) as u8, ppn, &vpns, access_type, stage)
+    }
+
+    /// page-fault exception for the access type. Faults in G-stage are guest-page
+    /// faults, whose guest physical address is reported to htval or mtval2.
+    fn get_page_fault(
+        &mut self,
+        v_addr: u64,
+        access_type: &MemoryAccessType,
+        stage: &TranslationStage,
+    ) -> Exception {
+        match stage {
+            TranslationStage::Guest => {
+                self.fault_guest_physical_address = v_addr;
+                match access_type {
+                    MemoryAccessType::Fetch => Exception::InstructionGuestPageFault,
+                    MemoryAccessType::Read => Exception::LoadGuestPageFault,
+                    MemoryAccessType::Write => Exception::StoreGuestPageFault,
+                }
+            }
+            _ => match access_type {
+                MemoryAccessType::Fetch => Exception::InstructionPageFault,
+                MemoryAccessType::Read => Exception::LoadPageFault,
+                MemoryAccessType::Write => Exception::StorePageFault,
+            },
         }
     }
 
@@ -449,37 +1093,99 @@ impl Mmu {
         parent_ppn: u64,
         vpns: &[u64],
         access_type: &MemoryAccessType,
-    ) -> Result<u64, ()> {
+        stage: &TranslationStage,
+    ) -> Result<u64, Exception> {
+        let addressing_mode = self.get_addressing_mode(stage);
+
         // 1. calc PTE address.
-        let pte_size = match self.addressing_mode {
+        // The page tables of VS-stage are placed in the guest physical address space.
+        let pte_size = match addressing_mode {
             AddressingMode::Sv32 => 4,
             _ => 8,
         };
         let pte_addr = parent_ppn * PAGE_SIZE + vpns[level as usize] * pte_size;
+        let pte_addr = match stage {
+            TranslationStage::VirtualSupervisor => {
+                match self.translate(pte_addr, &MemoryAccessType::Read, &TranslationStage::Guest) {
+                    Ok(addr) => addr,
+                    Err(_) => {
+                        return Err(self.get_page_fault(
+                            pte_addr,
+                            access_type,
+                            &TranslationStage::Guest,
+                        ))
+                    }
+                }
+            }
+            _ => pte_addr,
+        };
 
         // 2. get PTE (Page Table Entry).
-        let pte = match self.addressing_mode {
+        // Implicit accesses to the page table are checked by PMP as S-mode accesses.
+        if !self.check_pmp(
+            pte_addr,
+            pte_size,
+            &MemoryAccessType::Read,
+            &Privilege::Supervisor,
+        ) {
+            return Err(self.get_access_fault(access_type));
+        }
+        let pte = match addressing_mode {
             AddressingMode::Sv32 => self.pte_read32(pte_addr) as u64,
             _ => self.pte_read64(pte_addr),
         };
 
         // 3. check PTE.
-        let pte_d = self.parse_pte(pte);
+        let pte_d = self.parse_pte(pte, &addressing_mode);
 
         // 4. validate page-table. (PTE.V / PTE.R / PTE.W)
         if pte_d.v == 0 || (pte_d.r == 0 && pte_d.w == 1) {
-            return Err(());
+            return Err(self.get_page_fault(v_addr, access_type, stage));
         }
 
         // 5. check last entry or not.
         if pte_d.r == 0 && pte_d.x == 0 {
             return match level {
-                0 => Err(()),
-                _ => self.page_waking(v_addr, level - 1, pte_d.ppn, vpns, access_type),
+                0 => Err(self.get_page_fault(v_addr, access_type, stage)),
+                _ => self.page_waking(v_addr, level - 1, pte_d.ppn, vpns, access_type, stage),
             };
         }
 
-        // 6. check page-fault.
+        // 6. check access permission.
+        // All accesses in G-stage are treated as U-mode accesses. U-mode can access
+        // only the pages with U=1, and S-mode can not execute them and can load and
+        // store them only if SUM=1. MXR makes the executable pages readable, where
+        // vsstatus.MXR affects only VS-stage.
+        let (sum, mxr) = match stage {
+            TranslationStage::Supervisor => (
+                (self.status & CSR_STATUS_PUM) != 0,
+                (self.status & CSR_STATUS_MXR) != 0,
+            ),
+            TranslationStage::VirtualSupervisor => (
+                (self.vs_status & CSR_STATUS_PUM) != 0,
+                ((self.status | self.vs_status) & CSR_STATUS_MXR) != 0,
+            ),
+            TranslationStage::Guest => (false, (self.status & CSR_STATUS_MXR) != 0),
+        };
+        let permitted = match access_type {
+            MemoryAccessType::Fetch => pte_d.x != 0,
+            MemoryAccessType::Read => match self.executable_read {
+                true => pte_d.x != 0,
+                false => pte_d.r != 0 || (mxr && pte_d.x != 0),
+            },
+            MemoryAccessType::Write => pte_d.w != 0,
+        } && (match (stage, &self.privilege) {
+            (TranslationStage::Guest, _) | (_, Privilege::User) => pte_d.u != 0,
+            _ => match access_type {
+                MemoryAccessType::Fetch => pte_d.u == 0,
+                _ => pte_d.u == 0 || sum,
+            },
+        });
+        if !permitted {
+            return Err(self.get_page_fault(v_addr, access_type, stage));
+        }
+
+        // 7. update A and D bits.
         if pte_d.a == 0
             || (match access_type {
                 MemoryAccessType::Write => pte_d.d == 0,
@@ -492,86 +1198,53 @@ impl Mmu {
                     MemoryAccessType::Write => 1 << 7,
                     _ => 0,
                 });
-            match self.addressing_mode {
+            if !self.check_pmp(
+                pte_addr,
+                pte_size,
+                &MemoryAccessType::Write,
+                &Privilege::Supervisor,
+            ) {
+                return Err(self.get_access_fault(access_type));
+            }
+            match addressing_mode {
                 AddressingMode::Sv32 => self.pte_write32(pte_addr, new_pte as u32),
                 _ => self.pte_write64(pte_addr, new_pte),
             };
             // return Err(()); need page-fault exception?
         }
 
-        // 7. check access permission.
-        match access_type {
-            MemoryAccessType::Fetch => {
-                if pte_d.x == 0 {
-                    return Err(());
-                }
-            }
-            MemoryAccessType::Read => {
-                if pte_d.r == 0 {
-                    return Err(());
-                }
-            }
-            _ => {
-                if pte_d.w == 0 {
-                    return Err(());
-                }
-            }
-        };
-
         // 8. calculate physical address.
-        let offset = v_addr & 0xfff;
-        let p_addr = match self.addressing_mode {
-            AddressingMode::Sv32 => match level {
-                1 => {
-                    if pte_d.ppns[0] != 0 {
-                        return Err(());
-                    }
-                    (pte_d.ppns[1] << 22) | (vpns[0] << 12) | offset
-                }
-                0 => (pte_d.ppn << 12) | offset,
-                _ => panic!(),
-            },
-            _ => match level {
-                2 => {
-                    if pte_d.ppns[1] != 0 || pte_d.ppns[0] != 0 {
-                        return Err(());
-                    }
-                    (pte_d.ppns[2] << 30) | (vpns[1] << 21) | (vpns[0] << 12) | offset
-                }
-                1 => {
-                    if pte_d.ppns[0] != 0 {
-                        return Err(());
-                    }
-                    (pte_d.ppns[2] << 30) | (pte_d.ppns[1] << 21) | (vpns[0] << 12) | offset
-                }
-                0 => (pte_d.ppn << 12) | offset,
-                _ => panic!(),
-            },
+        // A superpage has to be aligned to its size, and the lower fields of the
+        // physical page number are taken from the virtual page number.
+        let vpn_bits = match addressing_mode {
+            AddressingMode::Sv32 => 10,
+            _ => 9,
         };
-        Ok(p_addr)
+        let superpage_bits = vpn_bits * level as usize;
+        if (pte_d.ppn & ((1 << superpage_bits) - 1)) != 0 {
+            return Err(self.get_page_fault(v_addr, access_type, stage));
+        }
+        let mut ppn = pte_d.ppn;
+        for (i, vpn) in vpns.iter().enumerate().take(level as usize) {
+            ppn |= vpn << (vpn_bits * i);
+        }
+        self.walk_page_shift = 12 + superpage_bits;
+        self.walk_global = pte_d.g != 0;
+        Ok((ppn << 12) | (v_addr & 0xfff))
     }
 
-    fn parse_pte(&self, pte: u64) -> Pte {
-        let _ppn = match self.addressing_mode {
+    fn parse_pte(&self, pte: u64, addressing_mode: &AddressingMode) -> Pte {
+        let _ppn = match addressing_mode {
             AddressingMode::Sv32 => (pte >> 10) & 0x3fffff,
             _ => (pte >> 10) & 0xfff_ffffffff,
         };
-        let _ppns = match self.addressing_mode {
-            AddressingMode::Sv32 => [(pte >> 10) & 0x3ff, (pte >> 20) & 0xfff, 0],
-            _ => [
-                (pte >> 10) & 0x1ff,
-                (pte >> 19) & 0x1ff,
-                (pte >> 28) & 0x3ffffff,
-            ],
-        };
         Pte {
             ppn: _ppn,
-            ppns: _ppns,
             _rsw: ((pte >> 8) & 0x3) as u8,
             d: ((pte >> 7) & 1) as u8,
             a: ((pte >> 6) & 1) as u8,
-            _g: ((pte >> 5) & 1) as u8,
-            _u: ((pte >> 4) & 1) as u8,
+            g: ((pte >> 5) & 1) as u8,
+            u: ((pte >> 4) & 1) as u8,
             x: ((pte >> 3) & 1) as u8,
             w: ((pte >> 2) & 1) as u8,
             r: ((pte >> 1) & 1) as u8,
@@ -581,7 +1254,7 @@ impl Mmu {
 
     fn pte_read32(&mut self, addr: u64) -> u32 {
         let effective_addr = self.to_effective_address(addr);
-        match self.bus.read32(effective_addr) {
+        match self.bus.borrow_mut().read32(effective_addr) {
             Ok(data) => data,
             Err(e) => panic!(e),
         }
@@ -589,7 +1262,7 @@ impl Mmu {
 
     fn pte_read64(&mut self, addr: u64) -> u64 {
         let effective_addr = self.to_effective_address(addr);
-        match self.bus.read64(effective_addr) {
+        match self.bus.borrow_mut().read64(effective_addr) {
             Ok(data) => data,
             Err(e) => panic!(e),
         }
@@ -597,7 +1270,7 @@ impl Mmu {
 
     fn pte_write32(&mut self, addr: u64, data: u32) {
         let effective_addr = self.to_effective_address(addr);
-        match self.bus.write32(effective_addr, data) {
+        match self.bus.borrow_mut().write32(effective_addr, data) {
             Ok(()) => (),
             Err(e) => panic!(e),
         }
@@ -605,7 +1278,7 @@ impl Mmu {
 
     fn pte_write64(&mut self, addr: u64, data: u64) {
         let effective_addr = self.to_effective_address(addr);
-        match self.bus.write64(effective_addr, data) {
+        match self.bus.borrow_mut().write64(effective_addr, data) {
             Ok(()) => (),
             Err(e) => panic!(e),
         }
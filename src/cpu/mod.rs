@@ -1,6 +1,10 @@
 pub mod cpu;
 pub mod cpu_instruction;
 pub mod cpu_instruction_comp;
+pub mod cpu_instruction_vector;
 pub mod cpu_csr;
+pub mod fpu;
 pub mod trap;
 pub mod mmu;
+pub mod decode_cache;
+pub mod block_cache;
@@ -16,12 +16,17 @@ pub enum Exception {
     StoreAccessFault = 7,
     EnvironmentCallFromUMode = 8,
     EnvironmentCallFromSMode = 9,
-    /* Reserved */
+    EnvironmentCallFromVSMode = 10,
     EnvironmentCallFromMMode = 11,
     InstructionPageFault = 12,
     LoadPageFault = 13,
     /* Reserved for future standart use */
     StorePageFault = 15,
+    /* Reserved for future standart use */
+    InstructionGuestPageFault = 20,
+    LoadGuestPageFault = 21,
+    VirtualInstruction = 22,
+    StoreGuestPageFault = 23,
 }
 
 #[allow(dead_code)]
@@ -30,15 +35,15 @@ pub enum Interrupt {
     // Interrupts
     UserSoftware = 0,
     SupervisorSoftware = 1,
-    /* Reserved for future standart use */
+    VirtualSupervisorSoftware = 2,
     MachineSoftware = 3,
     UserTimer = 4,
-    /* Reserved for future standart use */
     SupervisorTimer = 5,
-    /* Reserved for future standart use */
+    VirtualSupervisorTimer = 6,
     MachineTimer = 7,
     UserExternal = 8,
     SupervisorExternal = 9,
-    /* Reserved for future standart use */
+    VirtualSupervisorExternal = 10,
     MachineExternal = 11,
+    SupervisorGuestExternal = 12,
 }
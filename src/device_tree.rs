@@ -0,0 +1,190 @@
+// Flattened device tree (DTB)
+// https://devicetree-specification.readthedocs.io/en/stable/flattened-format.html
+
+const FDT_MAGIC: u32 = 0xd00dfeed;
+const FDT_BEGIN_NODE: u32 = 0x1;
+const FDT_END_NODE: u32 = 0x2;
+const FDT_PROP: u32 = 0x3;
+const FDT_NOP: u32 = 0x4;
+const FDT_END: u32 = 0x9;
+
+/// enable the cpu nodes of the harts in the device tree. The device tree lists the
+/// cpu nodes of all harts the machine supports, and the ones other than cpu@0 are
+/// disabled, so that the kernel only brings up the harts which exist. A status of
+/// "disabled" is rewritten to "okay" in place, and the bytes left are filled with
+/// FDT_NOP.
+pub fn enable_harts(dtb: &mut [u8], harts: usize) -> Result<(), String> {
+    if dtb.len() < 40 || read32(dtb, 0) != FDT_MAGIC {
+        return Err("The device tree has no FDT header".to_string());
+    }
+    let struct_offset = read32(dtb, 8) as usize;
+    let strings_offset = read32(dtb, 12) as usize;
+    let mut found = vec![false; harts];
+
+    let mut offset = struct_offset;
+    // the names of the nodes from the root to the current one.
+    let mut path: Vec<String> = vec![];
+    loop {
+        if offset + 4 > dtb.len() {
+            return Err("The device tree has no FDT_END".to_string());
+        }
+        let token = read32(dtb, offset);
+        offset += 4;
+        match token {
+            FDT_BEGIN_NODE => {
+                let name = read_string(dtb, offset);
+                offset = align4(offset + name.len() + 1);
+                path.push(name);
+                match get_hart(&path) {
+                    Some(hart) if hart < harts => found[hart] = true,
+                    _ => {}
+                }
+            }
+            FDT_END_NODE => {
+                path.pop();
+            }
+            FDT_PROP => {
+                let len = read32(dtb, offset) as usize;
+                let name = read_string(dtb, strings_offset + read32(dtb, offset + 4) as usize);
+                let value = offset + 8;
+                offset = align4(value + len);
+                if offset > dtb.len() {
+                    return Err("The device tree has a truncated property".to_string());
+                }
+                let enable = match get_hart(&path) {
+                    Some(hart) => hart < harts && name == "status",
+                    None => false,
+                };
+                if enable && &dtb[value..value + len] == b"disabled\0" {
+                    write32(dtb, value - 8, 5);
+                    dtb[value..value + 8].copy_from_slice(b"okay\0\0\0\0");
+                    write32(dtb, value + 8, FDT_NOP);
+                }
+            }
+            FDT_NOP => {}
+            FDT_END => break,
+            _ => return Err(format!("Unknown token {:x} in the device tree", token)),
+        }
+    }
+
+    match found.iter().position(|found| !found) {
+        Some(hart) => Err(format!("The device tree has no node of cpu@{:x}", hart)),
+        None => Ok(()),
+    }
+}
+
+/// the hart of the node at the path /cpus/cpu@<hart id>.
+fn get_hart(path: &[String]) -> Option<usize> {
+    match path {
+        [root, cpus, cpu] if root.is_empty() && cpus == "cpus" => match cpu.strip_prefix("cpu@") {
+            Some(hart) => usize::from_str_radix(hart, 16).ok(),
+            None => None,
+        },
+        _ => None,
+    }
+}
+
+fn read32(dtb: &[u8], offset: usize) -> u32 {
+    let mut bytes = [0; 4];
+    bytes.copy_from_slice(&dtb[offset..offset + 4]);
+    u32::from_be_bytes(bytes)
+}
+
+fn write32(dtb: &mut [u8], offset: usize, data: u32) {
+    dtb[offset..offset + 4].copy_from_slice(&data.to_be_bytes());
+}
+
+fn read_string(dtb: &[u8], offset: usize) -> String {
+    let bytes = dtb.get(offset..).unwrap_or(&[]);
+    let len = bytes.iter().position(|c| *c == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..len]).to_string()
+}
+
+fn align4(offset: usize) -> usize {
+    (offset + 3) & !3
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const QEMU_VIRTIO_DTB: &[u8] = include_bytes!("../artifacts/linux/dtb/qemu_virtio.dtb");
+
+    /// the status of the cpu nodes, or None for a node without it.
+    fn get_statuses(dtb: &[u8]) -> Vec<Option<String>> {
+        let struct_offset = read32(dtb, 8) as usize;
+        let strings_offset = read32(dtb, 12) as usize;
+        let mut statuses = vec![];
+        let mut path: Vec<String> = vec![];
+        let mut offset = struct_offset;
+        loop {
+            let token = read32(dtb, offset);
+            offset += 4;
+            match token {
+                FDT_BEGIN_NODE => {
+                    let name = read_string(dtb, offset);
+                    offset = align4(offset + name.len() + 1);
+                    path.push(name);
+                    if get_hart(&path).is_some() {
+                        statuses.push(None);
+                    }
+                }
+                FDT_END_NODE => {
+                    path.pop();
+                }
+                FDT_PROP => {
+                    let len = read32(dtb, offset) as usize;
+                    let name = read_string(dtb, strings_offset + read32(dtb, offset + 4) as usize);
+                    if get_hart(&path).is_some() && name == "status" {
+                        let status = read_string(dtb, offset + 8);
+                        assert_eq!(status.len() + 1, len);
+                        *statuses.last_mut().unwrap() = Some(status);
+                    }
+                    offset = align4(offset + 8 + len);
+                }
+                FDT_NOP => {}
+                FDT_END => return statuses,
+                _ => panic!("Unknown token {:x}", token),
+            }
+        }
+    }
+
+    /// the statuses of the 5 cpu nodes with the first harts enabled.
+    fn get_enabled_statuses(harts: usize) -> Vec<Option<String>> {
+        (0..5)
+            .map(|hart| match hart < harts {
+                true => Some("okay".to_string()),
+                false => Some("disabled".to_string()),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn enables_the_cpu_nodes_of_the_harts() {
+        let mut dtb = QEMU_VIRTIO_DTB.to_vec();
+        assert_eq!(get_statuses(&dtb), get_enabled_statuses(1));
+        assert_eq!(enable_harts(&mut dtb, 1), Ok(()));
+        assert_eq!(dtb, QEMU_VIRTIO_DTB);
+
+        assert_eq!(enable_harts(&mut dtb, 3), Ok(()));
+        assert_eq!(dtb.len(), QEMU_VIRTIO_DTB.len());
+        assert_eq!(get_statuses(&dtb), get_enabled_statuses(3));
+
+        // the enabled nodes are kept.
+        assert_eq!(enable_harts(&mut dtb, 5), Ok(()));
+        assert_eq!(get_statuses(&dtb), get_enabled_statuses(5));
+    }
+
+    #[test]
+    fn harts_without_cpu_nodes_are_errors() {
+        let mut dtb = QEMU_VIRTIO_DTB.to_vec();
+        assert_eq!(
+            enable_harts(&mut dtb, 6),
+            Err("The device tree has no node of cpu@5".to_string())
+        );
+        assert_eq!(
+            enable_harts(&mut vec![0; 64], 1),
+            Err("The device tree has no FDT header".to_string())
+        );
+    }
+}
@@ -1,40 +1,204 @@
+use std::cell::{RefCell, RefMut};
 use std::fs::File;
 use std::io::Read;
 use std::path::Path;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
 
-use crate::bus::bus::Device;
+use crate::bus::bus::{Bus, Device};
+use crate::bus::bus_fe310::BusFe310;
+use crate::bus::bus_fu540::BusFu540;
+use crate::bus::bus_qemu_virt::BusQemuVirt;
 use crate::console::Console;
 use crate::cpu::cpu::{Cpu, Xlen};
+use crate::cpu::mmu::{MisalignedAccess, Reservations};
 use crate::elf_loader::{EMachine, EiClass, ElfLoader, ShType};
 use crate::machine::Machine;
+use crate::network::Network;
+use crate::peripherals::virtio_rng::EntropySource;
+
+/// CLINT and PLIC have the registers for up to 5 harts.
+pub const HARTS_MAX: usize = 5;
+
+/// the maximum number of cycles that a hart runs the blocks without the interrupts
+/// from the devices.
+const BLOCK_CYCLES_MAX: u64 = 0x10000;
+
+/// The interpreter runs one instruction per tick, and is the reference of the
+/// block engine, which runs a basic block of the translated instructions per tick.
+#[derive(Clone, Debug)]
+pub enum ExecutionEngine {
+    Interpreter,
+    Block,
+}
+
+/// While every hart waits for an interrupt, the harts and the devices skip to the
+/// next event instead of running cycle by cycle. The guest sees the same cycles in
+/// both modes.
+#[derive(Clone, Debug)]
+pub enum IdleMode {
+    /// skip without sleeping, so that a run only depends on the instruction stream.
+    Skip,
+    /// sleep the host until the wall-clock time of the next event in the timebase of
+    /// the CLINT. An input of the console ends the sleep, and the harts skip without
+    /// sleeping until the UART polls the input.
+    RealTime,
+}
 
 pub struct Emulator {
-    cpu: Cpu,
+    harts: Vec<Cpu>,
+    bus: Rc<RefCell<dyn Bus>>,
+    reserved_address: Reservations,
     machine: Machine,
+    engine: ExecutionEngine,
+    idle_mode: IdleMode,
+    /// the wall-clock time and mtime when the real-time idle mode last synchronized.
+    real_time_origin: Option<(Instant, u64)>,
     testmode: bool,
     tohost: u64,
 }
 
 impl Emulator {
     pub fn new(machine_: Machine, tty: Box<dyn Console>, testmode_: bool) -> Emulator {
-        Self {
-            cpu: Cpu::new(machine_.clone(), tty, testmode_),
+        let bus_: Rc<RefCell<dyn Bus>> = match machine_ {
+            Machine::SiFiveE => Rc::new(RefCell::new(BusFe310::new(tty))),
+            Machine::SiFiveU => Rc::new(RefCell::new(BusFu540::new(tty))),
+            Machine::QemuVirt => Rc::new(RefCell::new(BusQemuVirt::new(tty))),
+        };
+        let mut emulator = Self {
+            harts: vec![],
+            bus: bus_,
+            reserved_address: Rc::new(RefCell::new(vec![])),
             machine: machine_,
+            engine: ExecutionEngine::Interpreter,
+            idle_mode: IdleMode::Skip,
+            real_time_origin: None,
             testmode: testmode_,
             tohost: 0,
-        }
+        };
+        emulator.set_harts(1);
+        emulator
     }
 
     pub fn reset(&mut self) {
-        self.cpu.reset()
+        for hart in self.harts.iter_mut() {
+            hart.reset()
+        }
     }
 
     pub fn set_pc(&mut self, addr: u64) {
-        self.cpu.set_pc(addr)
+        for hart in self.harts.iter_mut() {
+            hart.set_pc(addr)
+        }
     }
 
-    pub fn get_console(&mut self) -> &mut Box<dyn Console> {
-        self.cpu.mmu.get_bus().get_console()
+    /// set the number of harts sharing the bus (1 to 5). Added harts take over
+    /// the PC, XLEN, VLEN, PMP entries, reservation timeout and misaligned access
+    /// mode of hart 0.
+    pub fn set_harts(&mut self, harts: usize) {
+        if !(1..=HARTS_MAX).contains(&harts) {
+            panic!("Unsupported number of harts: {}", harts);
+        }
+        self.harts.truncate(harts);
+        self.reserved_address
+            .borrow_mut()
+            .resize(harts, Default::default());
+        for hart_id in self.harts.len()..harts {
+            let mut hart = Cpu::new(
+                hart_id,
+                self.bus.clone(),
+                self.reserved_address.clone(),
+                self.testmode,
+            );
+            if hart_id > 0 {
+                let hart0 = &self.harts[0];
+                hart.set_pc(hart0.pc);
+                hart.set_xlen(hart0.xlen.clone());
+                hart.set_vlen(hart0.v.len() / 32 * 8);
+                hart.set_pmp_entries(hart0.csr.get_pmp_entries());
+                hart.mmu
+                    .set_reservation_timeout(hart0.mmu.get_reservation_timeout());
+                hart.mmu
+                    .set_misaligned_access(hart0.mmu.get_misaligned_access());
+            }
+            self.harts.push(hart);
+        }
+    }
+
+    pub fn set_vlen(&mut self, vlen: usize) {
+        for hart in self.harts.iter_mut() {
+            hart.set_vlen(vlen)
+        }
+    }
+
+    pub fn set_pmp_entries(&mut self, entries: usize) {
+        for hart in self.harts.iter_mut() {
+            hart.set_pmp_entries(entries)
+        }
+    }
+
+    /// set the number of cycles after which an LR reservation expires, so that
+    /// an LR/SC sequence without forward progress fails (0: never expires).
+    pub fn set_reservation_timeout(&mut self, cycles: u64) {
+        for hart in self.harts.iter_mut() {
+            hart.mmu.set_reservation_timeout(cycles)
+        }
+    }
+
+    /// select how the misaligned loads and stores are handled.
+    pub fn set_misaligned_access(&mut self, misaligned_access: MisalignedAccess) {
+        for hart in self.harts.iter_mut() {
+            hart.mmu.set_misaligned_access(misaligned_access.clone())
+        }
+    }
+
+    /// select the execution engine of the harts.
+    pub fn set_execution_engine(&mut self, engine: ExecutionEngine) {
+        self.engine = engine;
+    }
+
+    /// select how the cycles are run while every hart waits for an interrupt.
+    pub fn set_idle_mode(&mut self, idle_mode: IdleMode) {
+        self.idle_mode = idle_mode;
+    }
+
+    pub fn get_console(&mut self) -> RefMut<'_, Box<dyn Console>> {
+        RefMut::map(self.bus.borrow_mut(), |bus| bus.get_console())
+    }
+
+    /// connect the network device of the machine to the network.
+    pub fn set_network(&mut self, network: Box<dyn Network>) {
+        self.bus.borrow_mut().set_network(network);
+    }
+
+    /// connect the port of the virtio console of the machine to the console. Port 0
+    /// is the console port, and the other ports are named. Err if the virtio console
+    /// does not have the port.
+    pub fn set_console_port(
+        &mut self,
+        id: u32,
+        name: &str,
+        console: Box<dyn Console>,
+    ) -> Result<(), ()> {
+        self.bus.borrow_mut().set_console_port(id, name, console)
+    }
+
+    /// set the source of the entropy of the virtio entropy device of the machine.
+    /// Err if the source is not available.
+    pub fn set_entropy_source(&mut self, source: EntropySource) -> Result<(), String> {
+        self.bus.borrow_mut().set_entropy_source(source)
+    }
+
+    /// forbid the writes of the guest to the disk of the virtio block device of the
+    /// machine.
+    pub fn set_disk_read_only(&mut self, read_only: bool) {
+        self.bus.borrow_mut().set_disk_read_only(read_only);
+    }
+
+    /// export the directory of the host by the virtio 9P device of the machine. The
+    /// guest cannot change the files if read_only is true.
+    pub fn set_shared_directory(&mut self, path: &Path, read_only: bool) {
+        self.bus.borrow_mut().set_shared_directory(path, read_only);
     }
 
     pub fn set_data_from_file(&mut self, device: Device, filename: &Path) {
@@ -45,21 +209,18 @@ impl Emulator {
                     Err(why) => panic!("Failed to read {}: {}", filename.display(), why),
                     _ => {}
                 };
-                let bus = self.cpu.mmu.get_bus();
-                bus.set_device_data(device, data);
+                self.bus.borrow_mut().set_device_data(device, data);
             }
             Err(why) => panic!("Falied to open {}: {}", filename.display(), why),
         };
     }
 
     pub fn set_data_from_binary(&mut self, device: Device, data: Vec<u8>) {
-        let bus = self.cpu.mmu.get_bus();
-        bus.set_device_data(device, data);
+        self.bus.borrow_mut().set_device_data(device, data);
     }
 
     pub fn set_dram_data(&mut self, data: Vec<u8>) {
-        let bus = self.cpu.mmu.get_bus();
-        bus.set_device_data(Device::Dram, data);
+        self.bus.borrow_mut().set_device_data(Device::Dram, data);
     }
 
     pub fn load_program_from_file(&mut self, filename: &Path) {
@@ -110,12 +271,15 @@ impl Emulator {
 
     fn load_program(&mut self, loader: ElfLoader) {
         let elf_header = loader.get_elf_header();
-        self.cpu.set_pc(elf_header.e_entry);
-        self.cpu.set_xlen(match elf_header.e_indent.ei_classs {
+        let xlen = match elf_header.e_indent.ei_classs {
             EiClass::Class32 => Xlen::X32,
             EiClass::Class64 => Xlen::X64,
             _ => panic!("Unexpected class size: {:?}", elf_header.e_indent.ei_classs),
-        });
+        };
+        for hart in self.harts.iter_mut() {
+            hart.set_pc(elf_header.e_entry);
+            hart.set_xlen(xlen.clone());
+        }
 
         let sec_headers = loader.get_section_header(&elf_header);
         let mut progbits_sec_headers = vec![];
@@ -131,9 +295,9 @@ impl Emulator {
         let target_device_addr;
         match self.machine {
             Machine::QemuVirt => {
-                target_device_addr = self.cpu.mmu.get_bus().get_base_address(Device::Dram)
+                target_device_addr = self.bus.borrow_mut().get_base_address(Device::Dram)
             }
-            _ => target_device_addr = self.cpu.mmu.get_bus().get_base_address(Device::SpiFlash),
+            _ => target_device_addr = self.bus.borrow_mut().get_base_address(Device::SpiFlash),
         }
 
         let program_headers = loader.get_program_header(&elf_header);
@@ -156,7 +320,7 @@ impl Emulator {
 
             for j in 0..p_size {
                 let data = loader.read8((progbits_sec_headers[i].sh_offset + j) as usize);
-                match self.cpu.mmu.write8(p_addr + j as u64, data) {
+                match self.harts[0].mmu.write8(p_addr + j as u64, data) {
                     Err(e) => panic!("{:?}", e.exception),
                     _ => {}
                 }
@@ -171,11 +335,113 @@ impl Emulator {
         }
     }
 
+    /// run one instruction (or one basic block by the block engine) on each hart in
+    /// round-robin order, then run the peripherals shared by the harts for as many
+    /// cycles as the longest run. The block engine may have run the peripherals for
+    /// some of the cycles before a load or store to a device.
+    fn tick(&mut self) {
+        let mut cycles = 1;
+        let mut bus_ticks = 0;
+        match self.engine {
+            ExecutionEngine::Interpreter => {
+                for hart in self.harts.iter_mut() {
+                    hart.tick();
+                }
+            }
+            ExecutionEngine::Block => {
+                // a single hart runs the blocks up to the next event of the devices,
+                // and the harts of SMP run a block in turn.
+                let cycles_max = match self.harts.len() {
+                    1 => match self.bus.borrow_mut().get_cycles_to_next_event() {
+                        Some(cycles) => cycles.min(BLOCK_CYCLES_MAX),
+                        None => BLOCK_CYCLES_MAX,
+                    },
+                    _ => 1,
+                };
+                for hart in self.harts.iter_mut() {
+                    cycles = cycles.max(hart.tick_block(&mut bus_ticks, cycles_max));
+                }
+            }
+        }
+
+        // run peripherals.
+        self.bus.borrow_mut().tick(cycles - bus_ticks);
+
+        // handle interrupt.
+        for hart in self.harts.iter_mut() {
+            hart.tick_interrupt();
+        }
+
+        self.skip_idle_cycles();
+    }
+
+    /// skip the cycles up to the next event of the devices or the harts while every
+    /// hart waits for an interrupt which cannot be taken yet. Nothing but the time
+    /// changes in the skipped cycles, so the harts and the devices are advanced at
+    /// once as if they ran cycle by cycle.
+    fn skip_idle_cycles(&mut self) {
+        for hart in self.harts.iter_mut() {
+            if !hart.is_idle() {
+                return;
+            }
+        }
+        let mut cycles = self
+            .bus
+            .borrow_mut()
+            .get_cycles_to_next_event()
+            .unwrap_or(u64::MAX);
+        for hart in self.harts.iter() {
+            match hart.get_cycles_to_interrupt() {
+                Some(hart_cycles) => cycles = cycles.min(hart_cycles),
+                None => {}
+            }
+        }
+        if cycles == 0 || cycles == u64::MAX {
+            return;
+        }
+
+        match self.idle_mode {
+            IdleMode::Skip => {}
+            IdleMode::RealTime => {
+                let mtime = self.bus.borrow_mut().get_mtime();
+                let deadline = self.bus.borrow_mut().get_mtime_after(cycles);
+                self.sleep_until(mtime, deadline);
+            }
+        }
+        for hart in self.harts.iter_mut() {
+            hart.skip_cycles(cycles);
+        }
+        self.bus.borrow_mut().tick(cycles);
+        for hart in self.harts.iter_mut() {
+            hart.tick_interrupt();
+        }
+    }
+
+    /// sleep the host until the wall-clock time of the deadline in mtime, or until an
+    /// input of the console. The clock is synchronized again at the deadline when the
+    /// guest is behind the wall clock or an input ends the sleep, and at mtime when
+    /// mtime has been written.
+    fn sleep_until(&mut self, mtime: u64, deadline: u64) {
+        let now = Instant::now();
+        let (origin, origin_mtime) = match self.real_time_origin {
+            Some((origin, origin_mtime)) if mtime >= origin_mtime => (origin, origin_mtime),
+            _ => (now, mtime),
+        };
+        let frequency = self.bus.borrow_mut().get_timebase_frequency();
+        let nanos =
+            (deadline.wrapping_sub(origin_mtime) as u128) * 1_000_000_000 / (frequency as u128);
+        let wakeup = origin + Duration::from_nanos(nanos as u64);
+        match wakeup > now && !self.get_console().wait_input(wakeup - now) {
+            true => self.real_time_origin = Some((origin, origin_mtime)),
+            false => self.real_time_origin = Some((Instant::now(), deadline)),
+        }
+    }
+
     pub fn run(&mut self) -> Result<u32, u32> {
         loop {
-            self.cpu.tick();
+            self.tick();
             if self.testmode && self.tohost != 0 {
-                match self.cpu.mmu.read32_direct(self.tohost) {
+                match self.harts[0].mmu.read32_direct(self.tohost) {
                     Ok(data) => match data {
                         0 => {}
                         1 => return Ok(1),
@@ -189,7 +455,7 @@ impl Emulator {
 
     pub fn run_steps(&mut self, steps: u32) {
         for _i in 0..steps {
-            self.cpu.tick();
+            self.tick();
         }
     }
 }
@@ -4,7 +4,10 @@ extern crate lazy_static;
 pub mod bus;
 pub mod console;
 pub mod cpu;
+pub mod device_tree;
 pub mod elf_loader;
 pub mod emulator;
 pub mod machine;
+pub mod network;
+pub mod p9;
 pub mod peripherals;
@@ -0,0 +1,51 @@
+// Loopback network: the frames sent by the guest are received by the guest.
+
+use std::collections::VecDeque;
+
+use crate::network::Network;
+
+pub struct LoopbackNetwork {
+    frames: VecDeque<Vec<u8>>,
+}
+
+impl LoopbackNetwork {
+    pub fn new() -> Self {
+        LoopbackNetwork {
+            frames: VecDeque::new(),
+        }
+    }
+}
+
+impl Default for LoopbackNetwork {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Network for LoopbackNetwork {
+    fn send(&mut self, frame: &[u8]) {
+        self.frames.push_back(frame.to_vec());
+    }
+
+    fn receive(&mut self) -> Option<Vec<u8>> {
+        self.frames.pop_front()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames_are_received_in_sent_order() {
+        let mut network = LoopbackNetwork::new();
+        assert_eq!(None, network.receive());
+        network.send(&[1, 2, 3]);
+        network.send(&[4, 5]);
+        assert_eq!(Some(vec![1, 2, 3]), network.receive());
+        network.send(&[6]);
+        assert_eq!(Some(vec![4, 5]), network.receive());
+        assert_eq!(Some(vec![6]), network.receive());
+        assert_eq!(None, network.receive());
+    }
+}
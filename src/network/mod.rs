@@ -0,0 +1,11 @@
+pub mod loopback;
+pub mod pcap;
+pub mod user;
+
+/// The backend of a network device, which carries the Ethernet frames of the guest.
+pub trait Network {
+    /// send a frame from the guest.
+    fn send(&mut self, frame: &[u8]);
+    /// take a frame to the guest, or None if there is no frame yet.
+    fn receive(&mut self) -> Option<Vec<u8>>;
+}
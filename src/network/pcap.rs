@@ -0,0 +1,259 @@
+// Pcap-file network: the frames sent by the guest are captured into a pcap file,
+// and the frames of another pcap file are replayed to the guest.
+// https://wiki.wireshark.org/Development/LibpcapFileFormat
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+
+use crate::network::Network;
+
+const PCAP_MAGIC: u32 = 0xa1b2c3d4;
+const PCAP_MAGIC_NANOSECONDS: u32 = 0xa1b23c4d;
+const PCAP_VERSION_MAJOR: u16 = 2;
+const PCAP_VERSION_MINOR: u16 = 4;
+const PCAP_SNAPLEN: u32 = 0xffff;
+const LINKTYPE_ETHERNET: u32 = 1;
+const GLOBAL_HEADER_SIZE: usize = 24;
+const RECORD_HEADER_SIZE: usize = 16;
+
+pub struct PcapNetwork {
+    capture: File,
+    /// the number of the captured frames, which is the timestamp of the next frame
+    /// in microseconds so that a capture only depends on the guest.
+    captured: u32,
+    replay: VecDeque<Vec<u8>>,
+}
+
+impl PcapNetwork {
+    /// capture the frames into the file of capture_path, and replay the frames of the
+    /// file of replay_path in order. The error is the message of the file which
+    /// cannot be used.
+    pub fn new(capture_path: &Path, replay_path: Option<&Path>) -> Result<Self, String> {
+        let replay = match replay_path {
+            Some(path) => match read_frames(path) {
+                Ok(frames) => frames,
+                Err(why) => return Err(why),
+            },
+            None => VecDeque::new(),
+        };
+        let mut capture = match File::create(capture_path) {
+            Ok(file) => file,
+            Err(why) => {
+                return Err(format!(
+                    "Failed to create {}: {}",
+                    capture_path.display(),
+                    why
+                ))
+            }
+        };
+        let mut header = vec![];
+        header.extend_from_slice(&PCAP_MAGIC.to_le_bytes());
+        header.extend_from_slice(&PCAP_VERSION_MAJOR.to_le_bytes());
+        header.extend_from_slice(&PCAP_VERSION_MINOR.to_le_bytes());
+        header.extend_from_slice(&0_i32.to_le_bytes()); // thiszone
+        header.extend_from_slice(&0_u32.to_le_bytes()); // sigfigs
+        header.extend_from_slice(&PCAP_SNAPLEN.to_le_bytes());
+        header.extend_from_slice(&LINKTYPE_ETHERNET.to_le_bytes());
+        match capture.write_all(&header) {
+            Ok(()) => Ok(PcapNetwork {
+                capture,
+                captured: 0,
+                replay,
+            }),
+            Err(why) => Err(format!(
+                "Failed to write {}: {}",
+                capture_path.display(),
+                why
+            )),
+        }
+    }
+}
+
+impl Network for PcapNetwork {
+    fn send(&mut self, frame: &[u8]) {
+        let mut record = vec![];
+        record.extend_from_slice(&(self.captured / 1_000_000).to_le_bytes());
+        record.extend_from_slice(&(self.captured % 1_000_000).to_le_bytes());
+        record.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+        record.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+        record.extend_from_slice(frame);
+        // the frame is dropped from the capture if the host fails to write it, as a
+        // link drops a frame.
+        if self.capture.write_all(&record).is_ok() {
+            self.captured = self.captured.wrapping_add(1);
+        }
+    }
+
+    fn receive(&mut self) -> Option<Vec<u8>> {
+        self.replay.pop_front()
+    }
+}
+
+/// read the frames of a pcap file of Ethernet in either byte order.
+fn read_frames(path: &Path) -> Result<VecDeque<Vec<u8>>, String> {
+    let mut data = vec![];
+    match File::open(path) {
+        Ok(mut file) => match file.read_to_end(&mut data) {
+            Err(why) => return Err(format!("Failed to read {}: {}", path.display(), why)),
+            _ => {}
+        },
+        Err(why) => return Err(format!("Failed to open {}: {}", path.display(), why)),
+    };
+    if data.len() < GLOBAL_HEADER_SIZE {
+        return Err(format!("{} is not a pcap file.", path.display()));
+    }
+    let magic = u32::from_le_bytes([data[0], data[1], data[2], data[3]]);
+    let big_endian = match magic {
+        PCAP_MAGIC | PCAP_MAGIC_NANOSECONDS => false,
+        _ if magic.swap_bytes() == PCAP_MAGIC || magic.swap_bytes() == PCAP_MAGIC_NANOSECONDS => {
+            true
+        }
+        _ => return Err(format!("{} is not a pcap file.", path.display())),
+    };
+    let read32 = |offset: usize| {
+        let bytes = [
+            data[offset],
+            data[offset + 1],
+            data[offset + 2],
+            data[offset + 3],
+        ];
+        match big_endian {
+            true => u32::from_be_bytes(bytes),
+            false => u32::from_le_bytes(bytes),
+        }
+    };
+    if read32(20) != LINKTYPE_ETHERNET {
+        return Err(format!("{} is not a capture of Ethernet.", path.display()));
+    }
+
+    let mut frames = VecDeque::new();
+    let mut offset = GLOBAL_HEADER_SIZE;
+    while offset + RECORD_HEADER_SIZE <= data.len() {
+        let len = read32(offset + 8) as usize;
+        let start = offset + RECORD_HEADER_SIZE;
+        if start + len > data.len() {
+            break;
+        }
+        frames.push_back(data[start..start + len].to_vec());
+        offset = start + len;
+    }
+    Ok(frames)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn temp_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("riscv_emu_pcap_{}_{}", std::process::id(), name))
+    }
+
+    fn pcap_header(big_endian: bool, linktype: u32) -> Vec<u8> {
+        let bytes16 = |value: u16| match big_endian {
+            true => value.to_be_bytes(),
+            false => value.to_le_bytes(),
+        };
+        let bytes32 = |value: u32| match big_endian {
+            true => value.to_be_bytes(),
+            false => value.to_le_bytes(),
+        };
+        let mut header = vec![];
+        header.extend_from_slice(&bytes32(PCAP_MAGIC));
+        header.extend_from_slice(&bytes16(PCAP_VERSION_MAJOR));
+        header.extend_from_slice(&bytes16(PCAP_VERSION_MINOR));
+        header.extend_from_slice(&bytes32(0));
+        header.extend_from_slice(&bytes32(0));
+        header.extend_from_slice(&bytes32(PCAP_SNAPLEN));
+        header.extend_from_slice(&bytes32(linktype));
+        header
+    }
+
+    #[test]
+    fn captured_frames_are_replayed() {
+        let capture_path = temp_path("capture");
+        let replay_capture_path = temp_path("replay_capture");
+        let mut network = PcapNetwork::new(&capture_path, None).unwrap();
+        assert_eq!(None, network.receive());
+        network.send(&[1, 2, 3]);
+        network.send(&[4, 5]);
+        drop(network);
+
+        let data = fs::read(&capture_path).unwrap();
+        assert_eq!(
+            pcap_header(false, LINKTYPE_ETHERNET),
+            data[..GLOBAL_HEADER_SIZE]
+        );
+        // the timestamps count the frames in microseconds.
+        let record = &data[GLOBAL_HEADER_SIZE..];
+        assert_eq!(
+            [0, 0, 0, 0, 0, 0, 0, 0, 3, 0, 0, 0, 3, 0, 0, 0, 1, 2, 3],
+            record[..19]
+        );
+        assert_eq!(
+            [0, 0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0, 2, 0, 0, 0, 4, 5],
+            record[19..]
+        );
+
+        let mut network = PcapNetwork::new(&replay_capture_path, Some(&capture_path)).unwrap();
+        assert_eq!(Some(vec![1, 2, 3]), network.receive());
+        assert_eq!(Some(vec![4, 5]), network.receive());
+        assert_eq!(None, network.receive());
+        let _ = fs::remove_file(&capture_path);
+        let _ = fs::remove_file(&replay_capture_path);
+    }
+
+    #[test]
+    fn big_endian_file_is_replayed_without_the_truncated_frame() {
+        let replay_path = temp_path("big_endian");
+        let capture_path = temp_path("big_endian_capture");
+        let mut data = pcap_header(true, LINKTYPE_ETHERNET);
+        data.extend_from_slice(&[0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 2, 7, 8]);
+        data.extend_from_slice(&[0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 4, 0, 0, 0, 4, 9]);
+        fs::write(&replay_path, &data).unwrap();
+
+        let mut network = PcapNetwork::new(&capture_path, Some(&replay_path)).unwrap();
+        assert_eq!(Some(vec![7, 8]), network.receive());
+        assert_eq!(None, network.receive());
+        let _ = fs::remove_file(&replay_path);
+        let _ = fs::remove_file(&capture_path);
+    }
+
+    #[test]
+    fn files_which_cannot_be_used_are_errors() {
+        let capture_path = temp_path("error_capture");
+        let replay_path = temp_path("error_replay");
+
+        let missing = temp_path("missing");
+        match PcapNetwork::new(&capture_path, Some(&missing)) {
+            Err(why) => assert!(why.starts_with("Failed to open")),
+            Ok(_) => panic!("the missing replay file is accepted"),
+        }
+        match PcapNetwork::new(&missing.join("capture"), None) {
+            Err(why) => assert!(why.starts_with("Failed to create")),
+            Ok(_) => panic!("the capture in the missing directory is accepted"),
+        }
+
+        fs::write(&replay_path, [0; GLOBAL_HEADER_SIZE - 1]).unwrap();
+        match PcapNetwork::new(&capture_path, Some(&replay_path)) {
+            Err(why) => assert!(why.ends_with("is not a pcap file.")),
+            Ok(_) => panic!("the short replay file is accepted"),
+        }
+        fs::write(&replay_path, [0; GLOBAL_HEADER_SIZE]).unwrap();
+        match PcapNetwork::new(&capture_path, Some(&replay_path)) {
+            Err(why) => assert!(why.ends_with("is not a pcap file.")),
+            Ok(_) => panic!("the replay file without the magic is accepted"),
+        }
+        fs::write(&replay_path, pcap_header(false, 101)).unwrap();
+        match PcapNetwork::new(&capture_path, Some(&replay_path)) {
+            Err(why) => assert!(why.ends_with("is not a capture of Ethernet.")),
+            Ok(_) => panic!("the replay file of raw IP is accepted"),
+        }
+        let _ = fs::remove_file(&replay_path);
+        let _ = fs::remove_file(&capture_path);
+    }
+}
@@ -0,0 +1,962 @@
+// User-mode network: a NAT from the guest to the sockets of the host
+//
+// The guest is on 10.0.2.0/24 like the user networking of QEMU. The gateway
+// 10.0.2.2 answers ARP, ICMP echo and DHCP, and the TCP connections and the UDP
+// datagrams to the gateway are forwarded to the same ports on localhost of the
+// host. The other addresses are unreachable.
+
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, VecDeque};
+use std::io::{ErrorKind, Read, Write};
+use std::net::{Ipv4Addr, Shutdown, SocketAddr, TcpStream, UdpSocket};
+use std::time::Duration;
+
+use crate::network::Network;
+
+const GATEWAY_MAC: [u8; 6] = [0x52, 0x55, 0x0a, 0x00, 0x02, 0x02];
+const BROADCAST_MAC: [u8; 6] = [0xff; 6];
+const GATEWAY_IP: [u8; 4] = [10, 0, 2, 2];
+const GUEST_IP: [u8; 4] = [10, 0, 2, 15];
+const BROADCAST_IP: [u8; 4] = [255, 255, 255, 255];
+const NETMASK: [u8; 4] = [255, 255, 255, 0];
+
+const ETHERNET_HEADER_SIZE: usize = 14;
+const ETHERTYPE_IPV4: u16 = 0x0800;
+const ETHERTYPE_ARP: u16 = 0x0806;
+
+const ARP_SIZE: usize = 28;
+const ARP_REQUEST: u16 = 1;
+const ARP_REPLY: u16 = 2;
+
+const IPV4_HEADER_SIZE: usize = 20;
+const IP_PROTOCOL_ICMP: u8 = 1;
+const IP_PROTOCOL_TCP: u8 = 6;
+const IP_PROTOCOL_UDP: u8 = 17;
+const IP_TTL: u8 = 64;
+
+const ICMP_ECHO_REPLY: u8 = 0;
+const ICMP_ECHO_REQUEST: u8 = 8;
+
+const UDP_HEADER_SIZE: usize = 8;
+
+const TCP_HEADER_SIZE: usize = 20;
+const TCP_FIN: u8 = 0x01;
+const TCP_SYN: u8 = 0x02;
+const TCP_RST: u8 = 0x04;
+const TCP_PSH: u8 = 0x08;
+const TCP_ACK: u8 = 0x10;
+const TCP_OPTION_MSS: u8 = 2;
+const TCP_MSS: usize = 1460;
+const TCP_WINDOW: usize = 0xffff;
+const TCP_CONNECT_TIMEOUT: Duration = Duration::from_secs(1);
+
+const DHCP_SERVER_PORT: u16 = 67;
+const DHCP_CLIENT_PORT: u16 = 68;
+const DHCP_MAGIC_COOKIE: [u8; 4] = [99, 130, 83, 99];
+const DHCP_OPTIONS: usize = 240;
+const DHCP_DISCOVER: u8 = 1;
+const DHCP_OFFER: u8 = 2;
+const DHCP_REQUEST: u8 = 3;
+const DHCP_ACK: u8 = 5;
+const DHCP_OPTION_SUBNET_MASK: u8 = 1;
+const DHCP_OPTION_ROUTER: u8 = 3;
+const DHCP_OPTION_LEASE_TIME: u8 = 51;
+const DHCP_OPTION_MESSAGE_TYPE: u8 = 53;
+const DHCP_OPTION_SERVER_ID: u8 = 54;
+const DHCP_OPTION_END: u8 = 255;
+const DHCP_LEASE_TIME: u32 = 86400;
+
+/// the sockets are not read while the guest has this number of the frames to take.
+const FRAMES_MAX: usize = 64;
+
+/// A TCP connection from the guest to localhost. The link to the guest does not
+/// lose the frames, so the segments to the guest are never retransmitted.
+struct TcpConnection {
+    stream: TcpStream,
+    /// the next sequence number to the guest.
+    seq: u32,
+    /// the oldest sequence number to the guest which is not acknowledged.
+    acked: u32,
+    /// the next sequence number from the guest.
+    ack: u32,
+    /// the receive window of the guest.
+    window: u32,
+    established: bool,
+    /// the data from the guest which the socket has not taken yet.
+    to_host: Vec<u8>,
+    /// the guest has sent FIN.
+    guest_fin: bool,
+    /// the host has closed the socket, and FIN has been sent to the guest.
+    host_fin: bool,
+    /// the socket is shut down for writing after the FIN from the guest.
+    shutdown: bool,
+}
+
+pub struct UserNetwork {
+    guest_mac: [u8; 6],
+    /// the frames to the guest.
+    frames: VecDeque<Vec<u8>>,
+    ip_id: u16,
+    /// the initial sequence number of the next TCP connection.
+    isn: u32,
+    /// the TCP connections by the ports of the guest and the host.
+    tcp: HashMap<(u16, u16), TcpConnection>,
+    /// the UDP sockets by the ports of the guest and the host.
+    udp: HashMap<(u16, u16), UdpSocket>,
+}
+
+impl UserNetwork {
+    pub fn new() -> Self {
+        UserNetwork {
+            guest_mac: BROADCAST_MAC,
+            frames: VecDeque::new(),
+            ip_id: 0,
+            isn: 0x1000_0000,
+            tcp: HashMap::new(),
+            udp: HashMap::new(),
+        }
+    }
+
+    fn handle_arp(&mut self, arp: &[u8]) {
+        if arp.len() < ARP_SIZE
+            || read16(arp, 0) != 1
+            || read16(arp, 2) != ETHERTYPE_IPV4
+            || read16(arp, 6) != ARP_REQUEST
+        {
+            return;
+        }
+        let sender_mac = &arp[8..14];
+        let sender_ip = &arp[14..18];
+        let target_ip = &arp[24..28];
+        // the gateway answers for every other address of the subnet.
+        if !is_in_subnet(target_ip) || target_ip == GUEST_IP || target_ip == sender_ip {
+            return;
+        }
+        let mut reply = vec![];
+        reply.extend_from_slice(&arp[0..6]);
+        reply.extend_from_slice(&ARP_REPLY.to_be_bytes());
+        reply.extend_from_slice(&GATEWAY_MAC);
+        reply.extend_from_slice(target_ip);
+        reply.extend_from_slice(sender_mac);
+        reply.extend_from_slice(sender_ip);
+        let mut dst_mac = [0; 6];
+        dst_mac.copy_from_slice(sender_mac);
+        self.send_ethernet(dst_mac, ETHERTYPE_ARP, &reply);
+    }
+
+    fn handle_ipv4(&mut self, packet: &[u8]) {
+        if packet.len() < IPV4_HEADER_SIZE || (packet[0] >> 4) != 4 {
+            return;
+        }
+        let header_size = ((packet[0] & 0xf) as usize) * 4;
+        let total_size = read16(packet, 2) as usize;
+        // the fragments are not reassembled.
+        let fragment = read16(packet, 6) & 0x3fff;
+        if header_size < IPV4_HEADER_SIZE
+            || total_size < header_size
+            || total_size > packet.len()
+            || fragment != 0
+        {
+            return;
+        }
+        let protocol = packet[9];
+        let mut src = [0; 4];
+        let mut dst = [0; 4];
+        src.copy_from_slice(&packet[12..16]);
+        dst.copy_from_slice(&packet[16..20]);
+        let payload = &packet[header_size..total_size];
+        match protocol {
+            IP_PROTOCOL_ICMP => self.handle_icmp(src, dst, payload),
+            IP_PROTOCOL_TCP => self.handle_tcp(src, dst, payload),
+            IP_PROTOCOL_UDP => self.handle_udp(src, dst, payload),
+            _ => {}
+        }
+    }
+
+    fn handle_icmp(&mut self, src: [u8; 4], dst: [u8; 4], icmp: &[u8]) {
+        if icmp.len() < 8 || icmp[0] != ICMP_ECHO_REQUEST || dst != GATEWAY_IP {
+            return;
+        }
+        let mut reply = icmp.to_vec();
+        reply[0] = ICMP_ECHO_REPLY;
+        reply[2] = 0;
+        reply[3] = 0;
+        let checksum = checksum(&reply, 0);
+        reply[2..4].copy_from_slice(&checksum.to_be_bytes());
+        self.send_ipv4(GATEWAY_IP, src, IP_PROTOCOL_ICMP, &reply);
+    }
+
+    fn handle_udp(&mut self, src: [u8; 4], dst: [u8; 4], udp: &[u8]) {
+        if udp.len() < UDP_HEADER_SIZE {
+            return;
+        }
+        let src_port = read16(udp, 0);
+        let dst_port = read16(udp, 2);
+        let size = (read16(udp, 4) as usize)
+            .max(UDP_HEADER_SIZE)
+            .min(udp.len());
+        let payload = &udp[UDP_HEADER_SIZE..size];
+        if dst_port == DHCP_SERVER_PORT && (dst == BROADCAST_IP || dst == GATEWAY_IP) {
+            self.handle_dhcp(payload);
+            return;
+        }
+        if dst != GATEWAY_IP || src != GUEST_IP {
+            return;
+        }
+        let key = (src_port, dst_port);
+        if let Entry::Vacant(entry) = self.udp.entry(key) {
+            let socket = match UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)) {
+                Ok(socket) => socket,
+                Err(_) => return,
+            };
+            if socket.connect((Ipv4Addr::LOCALHOST, dst_port)).is_err()
+                || socket.set_nonblocking(true).is_err()
+            {
+                return;
+            }
+            entry.insert(socket);
+        }
+        let _ = self.udp[&key].send(payload);
+    }
+
+    /// assign the address of the guest by DHCP.
+    fn handle_dhcp(&mut self, dhcp: &[u8]) {
+        if dhcp.len() < DHCP_OPTIONS || dhcp[0] != 1 || dhcp[236..240] != DHCP_MAGIC_COOKIE {
+            return;
+        }
+        let message_type = match find_dhcp_option(&dhcp[DHCP_OPTIONS..], DHCP_OPTION_MESSAGE_TYPE) {
+            Some(option) if !option.is_empty() => option[0],
+            _ => return,
+        };
+        let reply_type = match message_type {
+            DHCP_DISCOVER => DHCP_OFFER,
+            DHCP_REQUEST => DHCP_ACK,
+            _ => return,
+        };
+        let mut reply = vec![0; DHCP_OPTIONS];
+        reply[0] = 2; // BOOTREPLY
+        reply[1] = 1; // Ethernet
+        reply[2] = 6;
+        reply[4..8].copy_from_slice(&dhcp[4..8]); // xid
+        reply[10..12].copy_from_slice(&dhcp[10..12]); // flags
+        reply[16..20].copy_from_slice(&GUEST_IP); // yiaddr
+        reply[20..24].copy_from_slice(&GATEWAY_IP); // siaddr
+        reply[28..44].copy_from_slice(&dhcp[28..44]); // chaddr
+        reply[236..240].copy_from_slice(&DHCP_MAGIC_COOKIE);
+        reply.extend_from_slice(&[DHCP_OPTION_MESSAGE_TYPE, 1, reply_type]);
+        reply.extend_from_slice(&[DHCP_OPTION_SERVER_ID, 4]);
+        reply.extend_from_slice(&GATEWAY_IP);
+        reply.extend_from_slice(&[DHCP_OPTION_LEASE_TIME, 4]);
+        reply.extend_from_slice(&DHCP_LEASE_TIME.to_be_bytes());
+        reply.extend_from_slice(&[DHCP_OPTION_SUBNET_MASK, 4]);
+        reply.extend_from_slice(&NETMASK);
+        reply.extend_from_slice(&[DHCP_OPTION_ROUTER, 4]);
+        reply.extend_from_slice(&GATEWAY_IP);
+        reply.push(DHCP_OPTION_END);
+        // BOOTP messages are at least 300 bytes.
+        if reply.len() < 300 {
+            reply.resize(300, 0);
+        }
+        let udp = build_udp(
+            GATEWAY_IP,
+            BROADCAST_IP,
+            DHCP_SERVER_PORT,
+            DHCP_CLIENT_PORT,
+            &reply,
+        );
+        let packet = self.build_ipv4(GATEWAY_IP, BROADCAST_IP, IP_PROTOCOL_UDP, &udp);
+        self.send_ethernet(BROADCAST_MAC, ETHERTYPE_IPV4, &packet);
+    }
+
+    fn handle_tcp(&mut self, src: [u8; 4], dst: [u8; 4], tcp: &[u8]) {
+        if tcp.len() < TCP_HEADER_SIZE {
+            return;
+        }
+        let src_port = read16(tcp, 0);
+        let dst_port = read16(tcp, 2);
+        let seq = read32(tcp, 4);
+        let ack = read32(tcp, 8);
+        let header_size = ((tcp[12] >> 4) as usize) * 4;
+        let flags = tcp[13];
+        let window = read16(tcp, 14) as u32;
+        if header_size < TCP_HEADER_SIZE || header_size > tcp.len() {
+            return;
+        }
+        let payload = &tcp[header_size..];
+        let key = (src_port, dst_port);
+        if (flags & TCP_RST) != 0 {
+            self.tcp.remove(&key);
+            return;
+        }
+
+        if !self.tcp.contains_key(&key) {
+            if (flags & (TCP_SYN | TCP_ACK)) != TCP_SYN || dst != GATEWAY_IP || src != GUEST_IP {
+                self.reset_tcp(src, dst, src_port, dst_port, seq, ack, flags, payload.len());
+                return;
+            }
+            let addr = SocketAddr::from((Ipv4Addr::LOCALHOST, dst_port));
+            let stream = match TcpStream::connect_timeout(&addr, TCP_CONNECT_TIMEOUT) {
+                Ok(stream) => stream,
+                Err(_) => {
+                    self.reset_tcp(src, dst, src_port, dst_port, seq, ack, flags, 0);
+                    return;
+                }
+            };
+            if stream.set_nonblocking(true).is_err() {
+                return;
+            }
+            let _ = stream.set_nodelay(true);
+            let isn = self.isn;
+            self.isn = self.isn.wrapping_add(0x0100_0000);
+            self.tcp.insert(
+                key,
+                TcpConnection {
+                    stream,
+                    seq: isn.wrapping_add(1),
+                    acked: isn,
+                    ack: seq.wrapping_add(1),
+                    window,
+                    established: false,
+                    to_host: vec![],
+                    guest_fin: false,
+                    host_fin: false,
+                    shutdown: false,
+                },
+            );
+            let mut options = vec![TCP_OPTION_MSS, 4];
+            options.extend_from_slice(&(TCP_MSS as u16).to_be_bytes());
+            self.send_tcp(key, isn, TCP_SYN | TCP_ACK, &options, &[]);
+            return;
+        }
+
+        let conn = self.tcp.get_mut(&key).unwrap();
+        if (flags & TCP_ACK) != 0
+            && ack.wrapping_sub(conn.acked) <= conn.seq.wrapping_sub(conn.acked)
+        {
+            conn.acked = ack;
+            conn.window = window;
+            if !conn.established && ack == conn.seq {
+                conn.established = true;
+            }
+        }
+        let mut reply = false;
+        if !payload.is_empty() || (flags & TCP_FIN) != 0 {
+            reply = true;
+            // the segments out of order are dropped, and the guest sends them again.
+            if seq == conn.ack && !conn.guest_fin {
+                conn.to_host.extend_from_slice(payload);
+                conn.ack = conn.ack.wrapping_add(payload.len() as u32);
+                if (flags & TCP_FIN) != 0 {
+                    conn.ack = conn.ack.wrapping_add(1);
+                    conn.guest_fin = true;
+                }
+                conn.flush();
+            }
+        }
+        if reply {
+            let seq = conn.seq;
+            self.send_tcp(key, seq, TCP_ACK, &[], &[]);
+        }
+        self.close_tcp(key);
+    }
+
+    /// answer the segment which does not belong to a connection by RST.
+    #[allow(clippy::too_many_arguments)]
+    fn reset_tcp(
+        &mut self,
+        src: [u8; 4],
+        dst: [u8; 4],
+        src_port: u16,
+        dst_port: u16,
+        seq: u32,
+        ack: u32,
+        flags: u8,
+        size: usize,
+    ) {
+        let (seq, ack, flags) = match (flags & TCP_ACK) != 0 {
+            true => (ack, 0, TCP_RST),
+            false => {
+                let mut ack = seq.wrapping_add(size as u32);
+                if (flags & (TCP_SYN | TCP_FIN)) != 0 {
+                    ack = ack.wrapping_add(1);
+                }
+                (0, ack, TCP_RST | TCP_ACK)
+            }
+        };
+        let tcp = build_tcp(dst, src, dst_port, src_port, seq, ack, flags, 0, &[], &[]);
+        self.send_ipv4(dst, src, IP_PROTOCOL_TCP, &tcp);
+    }
+
+    /// forget the connection closed in both directions.
+    fn close_tcp(&mut self, key: (u16, u16)) {
+        let closed = match self.tcp.get(&key) {
+            Some(conn) => conn.guest_fin && conn.host_fin && conn.acked == conn.seq,
+            None => false,
+        };
+        if closed {
+            self.tcp.remove(&key);
+        }
+    }
+
+    fn send_tcp(&mut self, key: (u16, u16), seq: u32, flags: u8, options: &[u8], payload: &[u8]) {
+        let (ack, window) = match self.tcp.get(&key) {
+            Some(conn) => (conn.ack, TCP_WINDOW - conn.to_host.len().min(TCP_WINDOW)),
+            None => return,
+        };
+        let tcp = build_tcp(
+            GATEWAY_IP,
+            GUEST_IP,
+            key.1,
+            key.0,
+            seq,
+            ack,
+            flags,
+            window as u16,
+            options,
+            payload,
+        );
+        self.send_ipv4(GATEWAY_IP, GUEST_IP, IP_PROTOCOL_TCP, &tcp);
+    }
+
+    /// read the sockets of the host into the frames to the guest.
+    fn poll(&mut self) {
+        let mut buffer = vec![0; 0x10000];
+        let keys: Vec<(u16, u16)> = self.udp.keys().cloned().collect();
+        for key in keys {
+            while self.frames.len() < FRAMES_MAX {
+                let size = match self.udp[&key].recv(&mut buffer) {
+                    Ok(size) => size,
+                    Err(_) => break,
+                };
+                let udp = build_udp(GATEWAY_IP, GUEST_IP, key.1, key.0, &buffer[..size]);
+                self.send_ipv4(GATEWAY_IP, GUEST_IP, IP_PROTOCOL_UDP, &udp);
+            }
+        }
+
+        let keys: Vec<(u16, u16)> = self.tcp.keys().cloned().collect();
+        for key in keys {
+            let mut reset = false;
+            loop {
+                if self.frames.len() >= FRAMES_MAX {
+                    break;
+                }
+                let conn = self.tcp.get_mut(&key).unwrap();
+                conn.flush();
+                if !conn.established || conn.host_fin {
+                    break;
+                }
+                let in_flight = conn.seq.wrapping_sub(conn.acked);
+                if in_flight >= conn.window {
+                    break;
+                }
+                let size = (TCP_MSS as u32).min(conn.window - in_flight) as usize;
+                let seq = conn.seq;
+                match conn.stream.read(&mut buffer[..size]) {
+                    Ok(0) => {
+                        conn.seq = conn.seq.wrapping_add(1);
+                        conn.host_fin = true;
+                        self.send_tcp(key, seq, TCP_FIN | TCP_ACK, &[], &[]);
+                    }
+                    Ok(size) => {
+                        conn.seq = conn.seq.wrapping_add(size as u32);
+                        let data = buffer[..size].to_vec();
+                        self.send_tcp(key, seq, TCP_PSH | TCP_ACK, &[], &data);
+                    }
+                    Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                    Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                    Err(_) => {
+                        reset = true;
+                        break;
+                    }
+                }
+            }
+            if reset {
+                let seq = self.tcp[&key].seq;
+                self.send_tcp(key, seq, TCP_RST | TCP_ACK, &[], &[]);
+                self.tcp.remove(&key);
+            }
+        }
+    }
+
+    fn build_ipv4(&mut self, src: [u8; 4], dst: [u8; 4], protocol: u8, payload: &[u8]) -> Vec<u8> {
+        let mut packet = vec![0; IPV4_HEADER_SIZE];
+        packet[0] = 0x45;
+        packet[2..4].copy_from_slice(&((IPV4_HEADER_SIZE + payload.len()) as u16).to_be_bytes());
+        packet[4..6].copy_from_slice(&self.ip_id.to_be_bytes());
+        packet[6] = 0x40; // Don't Fragment
+        packet[8] = IP_TTL;
+        packet[9] = protocol;
+        packet[12..16].copy_from_slice(&src);
+        packet[16..20].copy_from_slice(&dst);
+        let checksum = checksum(&packet, 0);
+        packet[10..12].copy_from_slice(&checksum.to_be_bytes());
+        packet.extend_from_slice(payload);
+        self.ip_id = self.ip_id.wrapping_add(1);
+        packet
+    }
+
+    fn send_ipv4(&mut self, src: [u8; 4], dst: [u8; 4], protocol: u8, payload: &[u8]) {
+        let packet = self.build_ipv4(src, dst, protocol, payload);
+        let guest_mac = self.guest_mac;
+        self.send_ethernet(guest_mac, ETHERTYPE_IPV4, &packet);
+    }
+
+    fn send_ethernet(&mut self, dst: [u8; 6], ethertype: u16, payload: &[u8]) {
+        let mut frame = vec![];
+        frame.extend_from_slice(&dst);
+        frame.extend_from_slice(&GATEWAY_MAC);
+        frame.extend_from_slice(&ethertype.to_be_bytes());
+        frame.extend_from_slice(payload);
+        self.frames.push_back(frame);
+    }
+}
+
+impl Default for UserNetwork {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TcpConnection {
+    /// write the data from the guest to the socket as much as it takes, and shut
+    /// down the socket for writing after the FIN from the guest.
+    fn flush(&mut self) {
+        while !self.to_host.is_empty() {
+            match self.stream.write(&self.to_host) {
+                Ok(0) => break,
+                Ok(size) => {
+                    self.to_host.drain(..size);
+                }
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(_) => break,
+            }
+        }
+        if self.guest_fin && self.to_host.is_empty() && !self.shutdown {
+            let _ = self.stream.shutdown(Shutdown::Write);
+            self.shutdown = true;
+        }
+    }
+}
+
+impl Network for UserNetwork {
+    fn send(&mut self, frame: &[u8]) {
+        if frame.len() < ETHERNET_HEADER_SIZE {
+            return;
+        }
+        self.guest_mac.copy_from_slice(&frame[6..12]);
+        let payload = &frame[ETHERNET_HEADER_SIZE..];
+        match read16(frame, 12) {
+            ETHERTYPE_ARP => self.handle_arp(payload),
+            ETHERTYPE_IPV4 => self.handle_ipv4(payload),
+            _ => {}
+        }
+    }
+
+    fn receive(&mut self) -> Option<Vec<u8>> {
+        if self.frames.is_empty() {
+            self.poll();
+        }
+        self.frames.pop_front()
+    }
+}
+
+fn read16(data: &[u8], offset: usize) -> u16 {
+    u16::from_be_bytes([data[offset], data[offset + 1]])
+}
+
+fn read32(data: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes([
+        data[offset],
+        data[offset + 1],
+        data[offset + 2],
+        data[offset + 3],
+    ])
+}
+
+fn is_in_subnet(ip: &[u8]) -> bool {
+    (0..4).all(|i| (ip[i] & NETMASK[i]) == (GUEST_IP[i] & NETMASK[i]))
+}
+
+fn find_dhcp_option(options: &[u8], code: u8) -> Option<&[u8]> {
+    let mut offset = 0;
+    while offset < options.len() {
+        match options[offset] {
+            0 => offset += 1, // Pad
+            DHCP_OPTION_END => return None,
+            option => {
+                if offset + 1 >= options.len() {
+                    return None;
+                }
+                let size = options[offset + 1] as usize;
+                let start = offset + 2;
+                if start + size > options.len() {
+                    return None;
+                }
+                if option == code {
+                    return Some(&options[start..start + size]);
+                }
+                offset = start + size;
+            }
+        }
+    }
+    None
+}
+
+/// the one's complement sum of data added to sum.
+fn checksum(data: &[u8], sum: u32) -> u16 {
+    let mut sum = sum;
+    for chunk in data.chunks(2) {
+        let word = match chunk.len() {
+            2 => u16::from_be_bytes([chunk[0], chunk[1]]),
+            _ => (chunk[0] as u16) << 8,
+        };
+        sum += word as u32;
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+/// the checksum of TCP or UDP with the pseudo header of IPv4.
+fn transport_checksum(src: [u8; 4], dst: [u8; 4], protocol: u8, segment: &[u8]) -> u16 {
+    let mut pseudo_header = vec![];
+    pseudo_header.extend_from_slice(&src);
+    pseudo_header.extend_from_slice(&dst);
+    pseudo_header.push(0);
+    pseudo_header.push(protocol);
+    pseudo_header.extend_from_slice(&(segment.len() as u16).to_be_bytes());
+    let sum = !checksum(&pseudo_header, 0) as u32;
+    checksum(segment, sum)
+}
+
+fn build_udp(src: [u8; 4], dst: [u8; 4], src_port: u16, dst_port: u16, payload: &[u8]) -> Vec<u8> {
+    let mut udp = vec![];
+    udp.extend_from_slice(&src_port.to_be_bytes());
+    udp.extend_from_slice(&dst_port.to_be_bytes());
+    udp.extend_from_slice(&((UDP_HEADER_SIZE + payload.len()) as u16).to_be_bytes());
+    udp.extend_from_slice(&[0, 0]);
+    udp.extend_from_slice(payload);
+    let checksum = match transport_checksum(src, dst, IP_PROTOCOL_UDP, &udp) {
+        // zero means no checksum in UDP.
+        0 => 0xffff,
+        checksum => checksum,
+    };
+    udp[6..8].copy_from_slice(&checksum.to_be_bytes());
+    udp
+}
+
+#[allow(clippy::too_many_arguments)]
+fn build_tcp(
+    src: [u8; 4],
+    dst: [u8; 4],
+    src_port: u16,
+    dst_port: u16,
+    seq: u32,
+    ack: u32,
+    flags: u8,
+    window: u16,
+    options: &[u8],
+    payload: &[u8],
+) -> Vec<u8> {
+    let mut tcp = vec![];
+    tcp.extend_from_slice(&src_port.to_be_bytes());
+    tcp.extend_from_slice(&dst_port.to_be_bytes());
+    tcp.extend_from_slice(&seq.to_be_bytes());
+    tcp.extend_from_slice(&ack.to_be_bytes());
+    tcp.push((((TCP_HEADER_SIZE + options.len()) / 4) as u8) << 4);
+    tcp.push(flags);
+    tcp.extend_from_slice(&window.to_be_bytes());
+    tcp.extend_from_slice(&[0, 0, 0, 0]); // checksum, urgent pointer
+    tcp.extend_from_slice(options);
+    tcp.extend_from_slice(payload);
+    let checksum = transport_checksum(src, dst, IP_PROTOCOL_TCP, &tcp);
+    tcp[16..18].copy_from_slice(&checksum.to_be_bytes());
+    tcp
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::thread;
+
+    const GUEST_MAC: [u8; 6] = [0x52, 0x54, 0x00, 0x12, 0x34, 0x56];
+    const ANY_IP: [u8; 4] = [0, 0, 0, 0];
+
+    fn guest_frame(dst_mac: [u8; 6], ethertype: u16, payload: &[u8]) -> Vec<u8> {
+        let mut frame = dst_mac.to_vec();
+        frame.extend_from_slice(&GUEST_MAC);
+        frame.extend_from_slice(&ethertype.to_be_bytes());
+        frame.extend_from_slice(payload);
+        frame
+    }
+
+    fn guest_ipv4(src: [u8; 4], dst: [u8; 4], protocol: u8, payload: &[u8]) -> Vec<u8> {
+        let mut packet = vec![0; IPV4_HEADER_SIZE];
+        packet[0] = 0x45;
+        packet[2..4].copy_from_slice(&((IPV4_HEADER_SIZE + payload.len()) as u16).to_be_bytes());
+        packet[8] = IP_TTL;
+        packet[9] = protocol;
+        packet[12..16].copy_from_slice(&src);
+        packet[16..20].copy_from_slice(&dst);
+        let checksum = checksum(&packet, 0);
+        packet[10..12].copy_from_slice(&checksum.to_be_bytes());
+        packet.extend_from_slice(payload);
+        guest_frame(GATEWAY_MAC, ETHERTYPE_IPV4, &packet)
+    }
+
+    fn guest_tcp(port: u16, seq: u32, ack: u32, flags: u8, payload: &[u8]) -> Vec<u8> {
+        let tcp = build_tcp(
+            GUEST_IP,
+            GATEWAY_IP,
+            6000,
+            port,
+            seq,
+            ack,
+            flags,
+            0xffff,
+            &[],
+            payload,
+        );
+        guest_ipv4(GUEST_IP, GATEWAY_IP, IP_PROTOCOL_TCP, &tcp)
+    }
+
+    /// the frame to the guest, which the sockets of the host may send later.
+    fn receive(network: &mut UserNetwork) -> Vec<u8> {
+        for _ in 0..500 {
+            match network.receive() {
+                Some(frame) => return frame,
+                None => thread::sleep(Duration::from_millis(10)),
+            }
+        }
+        panic!("no frame to the guest");
+    }
+
+    /// the payload of the IPv4 packet of protocol from the gateway to dst.
+    fn ipv4_payload(frame: &[u8], dst_mac: [u8; 6], dst: [u8; 4], protocol: u8) -> Vec<u8> {
+        assert_eq!(dst_mac, frame[0..6]);
+        assert_eq!(GATEWAY_MAC, frame[6..12]);
+        assert_eq!(ETHERTYPE_IPV4, read16(frame, 12));
+        let packet = &frame[ETHERNET_HEADER_SIZE..];
+        assert_eq!(0, checksum(&packet[..IPV4_HEADER_SIZE], 0));
+        assert_eq!(packet.len(), read16(packet, 2) as usize);
+        assert_eq!(protocol, packet[9]);
+        assert_eq!(GATEWAY_IP, packet[12..16]);
+        assert_eq!(dst, packet[16..20]);
+        let payload = &packet[IPV4_HEADER_SIZE..];
+        if protocol != IP_PROTOCOL_ICMP {
+            assert_eq!(0, transport_checksum(GATEWAY_IP, dst, protocol, payload));
+        }
+        payload.to_vec()
+    }
+
+    /// the sequence number, the acknowledgment number, the flags and the payload of
+    /// the TCP segment to the guest.
+    fn tcp_segment(frame: &[u8], port: u16) -> (u32, u32, u8, Vec<u8>) {
+        let tcp = ipv4_payload(frame, GUEST_MAC, GUEST_IP, IP_PROTOCOL_TCP);
+        assert_eq!(port, read16(&tcp, 0));
+        assert_eq!(6000, read16(&tcp, 2));
+        let header_size = ((tcp[12] >> 4) as usize) * 4;
+        (
+            read32(&tcp, 4),
+            read32(&tcp, 8),
+            tcp[13],
+            tcp[header_size..].to_vec(),
+        )
+    }
+
+    #[test]
+    fn gateway_answers_arp() {
+        let mut network = UserNetwork::new();
+        let mut request = vec![0, 1, 0x08, 0x00, 6, 4, 0, 1];
+        request.extend_from_slice(&GUEST_MAC);
+        request.extend_from_slice(&GUEST_IP);
+        request.extend_from_slice(&[0; 6]);
+        request.extend_from_slice(&GATEWAY_IP);
+        network.send(&guest_frame(BROADCAST_MAC, ETHERTYPE_ARP, &request));
+
+        let frame = network.receive().unwrap();
+        assert_eq!(GUEST_MAC, frame[0..6]);
+        assert_eq!(GATEWAY_MAC, frame[6..12]);
+        assert_eq!(ETHERTYPE_ARP, read16(&frame, 12));
+        let arp = &frame[ETHERNET_HEADER_SIZE..];
+        assert_eq!(ARP_REPLY, read16(arp, 6));
+        assert_eq!(GATEWAY_MAC, arp[8..14]);
+        assert_eq!(GATEWAY_IP, arp[14..18]);
+        assert_eq!(GUEST_MAC, arp[18..24]);
+        assert_eq!(GUEST_IP, arp[24..28]);
+        assert_eq!(None, network.receive());
+
+        // the address of the guest is not answered.
+        request[24..28].copy_from_slice(&GUEST_IP);
+        network.send(&guest_frame(BROADCAST_MAC, ETHERTYPE_ARP, &request));
+        assert_eq!(None, network.receive());
+    }
+
+    #[test]
+    fn gateway_answers_icmp_echo() {
+        let mut network = UserNetwork::new();
+        let mut icmp = vec![
+            ICMP_ECHO_REQUEST,
+            0,
+            0,
+            0,
+            0x12,
+            0x34,
+            0,
+            1,
+            b'p',
+            b'i',
+            b'n',
+            b'g',
+        ];
+        let sum = checksum(&icmp, 0);
+        icmp[2..4].copy_from_slice(&sum.to_be_bytes());
+        network.send(&guest_ipv4(GUEST_IP, GATEWAY_IP, IP_PROTOCOL_ICMP, &icmp));
+
+        let frame = network.receive().unwrap();
+        let reply = ipv4_payload(&frame, GUEST_MAC, GUEST_IP, IP_PROTOCOL_ICMP);
+        assert_eq!(ICMP_ECHO_REPLY, reply[0]);
+        assert_eq!(icmp[4..], reply[4..]);
+        assert_eq!(0, checksum(&reply, 0));
+
+        // the other addresses are unreachable.
+        network.send(&guest_ipv4(
+            GUEST_IP,
+            [10, 0, 2, 3],
+            IP_PROTOCOL_ICMP,
+            &icmp,
+        ));
+        assert_eq!(None, network.receive());
+    }
+
+    #[test]
+    fn gateway_assigns_the_address_by_dhcp() {
+        let mut network = UserNetwork::new();
+        for (message_type, reply_type) in [(DHCP_DISCOVER, DHCP_OFFER), (DHCP_REQUEST, DHCP_ACK)] {
+            let mut dhcp = vec![0; DHCP_OPTIONS];
+            dhcp[0] = 1;
+            dhcp[1] = 1;
+            dhcp[2] = 6;
+            dhcp[4..8].copy_from_slice(&[0xde, 0xad, 0xbe, 0xef]);
+            dhcp[28..34].copy_from_slice(&GUEST_MAC);
+            dhcp[236..240].copy_from_slice(&DHCP_MAGIC_COOKIE);
+            dhcp.extend_from_slice(&[DHCP_OPTION_MESSAGE_TYPE, 1, message_type, DHCP_OPTION_END]);
+            let udp = build_udp(
+                ANY_IP,
+                BROADCAST_IP,
+                DHCP_CLIENT_PORT,
+                DHCP_SERVER_PORT,
+                &dhcp,
+            );
+            network.send(&guest_ipv4(ANY_IP, BROADCAST_IP, IP_PROTOCOL_UDP, &udp));
+
+            let frame = network.receive().unwrap();
+            let udp = ipv4_payload(&frame, BROADCAST_MAC, BROADCAST_IP, IP_PROTOCOL_UDP);
+            assert_eq!(DHCP_SERVER_PORT, read16(&udp, 0));
+            assert_eq!(DHCP_CLIENT_PORT, read16(&udp, 2));
+            let reply = &udp[UDP_HEADER_SIZE..];
+            assert_eq!(2, reply[0]);
+            assert_eq!(dhcp[4..8], reply[4..8]);
+            assert_eq!(GUEST_IP, reply[16..20]);
+            assert_eq!(GUEST_MAC, reply[28..34]);
+            let options = &reply[DHCP_OPTIONS..];
+            assert_eq!(
+                Some(&[reply_type][..]),
+                find_dhcp_option(options, DHCP_OPTION_MESSAGE_TYPE)
+            );
+            assert_eq!(
+                Some(&GATEWAY_IP[..]),
+                find_dhcp_option(options, DHCP_OPTION_ROUTER)
+            );
+            assert_eq!(
+                Some(&NETMASK[..]),
+                find_dhcp_option(options, DHCP_OPTION_SUBNET_MASK)
+            );
+        }
+    }
+
+    #[test]
+    fn udp_to_the_gateway_is_forwarded_to_localhost() {
+        let mut network = UserNetwork::new();
+        let host = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        host.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let port = host.local_addr().unwrap().port();
+        let udp = build_udp(GUEST_IP, GATEWAY_IP, 5000, port, b"hello");
+        network.send(&guest_ipv4(GUEST_IP, GATEWAY_IP, IP_PROTOCOL_UDP, &udp));
+
+        let mut buffer = [0; 16];
+        let (size, addr) = host.recv_from(&mut buffer).unwrap();
+        assert_eq!(b"hello", &buffer[..size]);
+        host.send_to(b"world", addr).unwrap();
+        let frame = receive(&mut network);
+        let udp = ipv4_payload(&frame, GUEST_MAC, GUEST_IP, IP_PROTOCOL_UDP);
+        assert_eq!(port, read16(&udp, 0));
+        assert_eq!(5000, read16(&udp, 2));
+        assert_eq!(b"world", &udp[UDP_HEADER_SIZE..]);
+    }
+
+    #[test]
+    fn tcp_to_the_gateway_is_forwarded_to_localhost() {
+        let mut network = UserNetwork::new();
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        network.send(&guest_tcp(port, 100, 0, TCP_SYN, &[]));
+        let (isn, ack, flags, payload) = tcp_segment(&network.receive().unwrap(), port);
+        assert_eq!((101, TCP_SYN | TCP_ACK), (ack, flags));
+        assert!(payload.is_empty());
+        network.send(&guest_tcp(port, 101, isn.wrapping_add(1), TCP_ACK, &[]));
+        assert_eq!(None, network.receive());
+
+        network.send(&guest_tcp(
+            port,
+            101,
+            isn.wrapping_add(1),
+            TCP_PSH | TCP_ACK,
+            b"hello",
+        ));
+        let (_, ack, flags, _) = tcp_segment(&network.receive().unwrap(), port);
+        assert_eq!((106, TCP_ACK), (ack, flags));
+        let (mut host, _) = listener.accept().unwrap();
+        host.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let mut buffer = [0; 5];
+        host.read_exact(&mut buffer).unwrap();
+        assert_eq!(b"hello", &buffer);
+
+        host.write_all(b"world").unwrap();
+        let (seq, ack, flags, payload) = tcp_segment(&receive(&mut network), port);
+        assert_eq!(
+            (isn.wrapping_add(1), 106, TCP_PSH | TCP_ACK),
+            (seq, ack, flags)
+        );
+        assert_eq!(b"world", &payload[..]);
+
+        // the host closes first, and the guest closes after it.
+        drop(host);
+        let (seq, _, flags, _) = tcp_segment(&receive(&mut network), port);
+        assert_eq!((isn.wrapping_add(6), TCP_FIN | TCP_ACK), (seq, flags));
+        network.send(&guest_tcp(
+            port,
+            106,
+            isn.wrapping_add(7),
+            TCP_FIN | TCP_ACK,
+            &[],
+        ));
+        let (_, ack, flags, _) = tcp_segment(&network.receive().unwrap(), port);
+        assert_eq!((107, TCP_ACK), (ack, flags));
+        assert!(network.tcp.is_empty());
+    }
+
+    #[test]
+    fn tcp_to_a_closed_port_is_reset() {
+        let mut network = UserNetwork::new();
+        let port = {
+            let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+            listener.local_addr().unwrap().port()
+        };
+        network.send(&guest_tcp(port, 100, 0, TCP_SYN, &[]));
+        let (seq, ack, flags, _) = tcp_segment(&network.receive().unwrap(), port);
+        assert_eq!((0, 101, TCP_RST | TCP_ACK), (seq, ack, flags));
+        assert!(network.tcp.is_empty());
+    }
+}
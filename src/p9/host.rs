@@ -0,0 +1,243 @@
+// The files of the host in the form of Linux for the 9P server. The attributes
+// come from the host OS where it has them, and they are made up elsewhere.
+
+#[cfg(unix)]
+use std::ffi::CString;
+#[cfg(not(unix))]
+use std::fs::{self, File};
+use std::fs::{Metadata, OpenOptions};
+use std::io;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const S_IFMT: u32 = 0o170000;
+pub const S_IFDIR: u32 = 0o040000;
+pub const S_IFREG: u32 = 0o100000;
+pub const S_IFLNK: u32 = 0o120000;
+
+// errno of Linux
+pub const EPERM: u32 = 1;
+pub const ENOENT: u32 = 2;
+pub const EIO: u32 = 5;
+pub const EBADF: u32 = 9;
+pub const EACCES: u32 = 13;
+pub const EEXIST: u32 = 17;
+pub const ENOTDIR: u32 = 20;
+pub const EISDIR: u32 = 21;
+pub const EINVAL: u32 = 22;
+pub const EROFS: u32 = 30;
+pub const ENOTEMPTY: u32 = 39;
+pub const ELOOP: u32 = 40;
+pub const EPROTO: u32 = 71;
+pub const EOPNOTSUPP: u32 = 95;
+
+/// The attributes of a file as struct stat of Linux. The times are the seconds
+/// and the nanoseconds.
+pub struct Stat {
+    pub ino: u64,
+    pub mode: u32,
+    pub uid: u32,
+    pub gid: u32,
+    pub nlink: u64,
+    pub rdev: u64,
+    pub size: u64,
+    pub blksize: u64,
+    pub blocks: u64,
+    pub atime: (u64, u64),
+    pub mtime: (u64, u64),
+    pub ctime: (u64, u64),
+}
+
+#[cfg(unix)]
+pub fn get_stat(metadata: &Metadata, _path: &Path) -> Stat {
+    use std::os::unix::fs::MetadataExt;
+    Stat {
+        ino: metadata.ino(),
+        mode: metadata.mode(),
+        uid: metadata.uid(),
+        gid: metadata.gid(),
+        nlink: metadata.nlink(),
+        rdev: metadata.rdev(),
+        size: metadata.size(),
+        blksize: metadata.blksize(),
+        blocks: metadata.blocks(),
+        atime: (metadata.atime() as u64, metadata.atime_nsec() as u64),
+        mtime: (metadata.mtime() as u64, metadata.mtime_nsec() as u64),
+        ctime: (metadata.ctime() as u64, metadata.ctime_nsec() as u64),
+    }
+}
+
+#[cfg(not(unix))]
+pub fn get_stat(metadata: &Metadata, path: &Path) -> Stat {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+    let mut hasher = DefaultHasher::new();
+    path.hash(&mut hasher);
+    let file_type = metadata.file_type();
+    let permission = match metadata.permissions().readonly() {
+        true => 0o555,
+        false => 0o755,
+    };
+    let mode = match () {
+        _ if file_type.is_dir() => S_IFDIR | permission,
+        _ if file_type.is_symlink() => S_IFLNK | 0o777,
+        _ => S_IFREG | (permission & !0o111),
+    };
+    let get_time = |time: io::Result<SystemTime>| match time {
+        Ok(time) => get_seconds(time),
+        Err(_e) => (0, 0),
+    };
+    Stat {
+        ino: hasher.finish(),
+        mode,
+        uid: 0,
+        gid: 0,
+        nlink: 1,
+        rdev: 0,
+        size: metadata.len(),
+        blksize: 4096,
+        blocks: (metadata.len() + 511) / 512,
+        atime: get_time(metadata.accessed()),
+        mtime: get_time(metadata.modified()),
+        ctime: get_time(metadata.modified()),
+    }
+}
+
+/// the seconds and the nanoseconds since the epoch.
+pub fn get_seconds(time: SystemTime) -> (u64, u64) {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(duration) => (duration.as_secs(), duration.subsec_nanos() as u64),
+        Err(_e) => (0, 0),
+    }
+}
+
+/// set the permission bits of the file. A symbolic link is not followed.
+#[cfg(unix)]
+pub fn set_mode(path: &Path, mode: u32) -> io::Result<()> {
+    let path = match get_c_path(path) {
+        Ok(path) => path,
+        Err(why) => return Err(why),
+    };
+    let mode = (mode & 0o7777) as libc::mode_t;
+    match unsafe {
+        libc::fchmodat(libc::AT_FDCWD, path.as_ptr(), mode, libc::AT_SYMLINK_NOFOLLOW)
+    } {
+        0 => Ok(()),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
+
+/// set the permission bits of the file. Only the write permission is kept.
+#[cfg(not(unix))]
+pub fn set_mode(path: &Path, mode: u32) -> io::Result<()> {
+    match fs::symlink_metadata(path) {
+        Ok(metadata) => {
+            let mut permissions = metadata.permissions();
+            permissions.set_readonly((mode & 0o222) == 0);
+            fs::set_permissions(path, permissions)
+        }
+        Err(why) => Err(why),
+    }
+}
+
+/// set the access and the modification times of the file. None keeps the time. A
+/// symbolic link is not followed.
+#[cfg(unix)]
+pub fn set_times(
+    path: &Path,
+    atime: Option<SystemTime>,
+    mtime: Option<SystemTime>,
+) -> io::Result<()> {
+    let path = match get_c_path(path) {
+        Ok(path) => path,
+        Err(why) => return Err(why),
+    };
+    let get_timespec = |time: Option<SystemTime>| match time {
+        Some(time) => {
+            let (sec, nsec) = get_seconds(time);
+            libc::timespec {
+                tv_sec: sec as libc::time_t,
+                tv_nsec: nsec as _,
+            }
+        }
+        None => libc::timespec {
+            tv_sec: 0,
+            tv_nsec: libc::UTIME_OMIT,
+        },
+    };
+    let times = [get_timespec(atime), get_timespec(mtime)];
+    match unsafe {
+        libc::utimensat(libc::AT_FDCWD, path.as_ptr(), times.as_ptr(), libc::AT_SYMLINK_NOFOLLOW)
+    } {
+        0 => Ok(()),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
+
+/// set the access and the modification times of the file. None keeps the time.
+#[cfg(not(unix))]
+pub fn set_times(
+    path: &Path,
+    atime: Option<SystemTime>,
+    mtime: Option<SystemTime>,
+) -> io::Result<()> {
+    let mut times = fs::FileTimes::new();
+    if let Some(atime) = atime {
+        times = times.set_accessed(atime);
+    }
+    if let Some(mtime) = mtime {
+        times = times.set_modified(mtime);
+    }
+    File::open(path).and_then(|file| file.set_times(times))
+}
+
+#[cfg(unix)]
+fn get_c_path(path: &Path) -> io::Result<CString> {
+    use std::os::unix::ffi::OsStrExt;
+    match CString::new(path.as_os_str().as_bytes()) {
+        Ok(path) => Ok(path),
+        Err(_e) => Err(io::Error::from(io::ErrorKind::InvalidInput)),
+    }
+}
+
+/// open the symbolic link of the last component as an error instead of its target.
+#[cfg(unix)]
+pub fn set_no_follow(options: &mut OpenOptions) {
+    use std::os::unix::fs::OpenOptionsExt;
+    options.custom_flags(libc::O_NOFOLLOW);
+}
+
+#[cfg(not(unix))]
+pub fn set_no_follow(_options: &mut OpenOptions) {}
+
+#[cfg(unix)]
+pub fn symlink(target: &str, path: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(target, path)
+}
+
+#[cfg(not(unix))]
+pub fn symlink(_target: &str, _path: &Path) -> io::Result<()> {
+    Err(io::Error::from(io::ErrorKind::Unsupported))
+}
+
+/// the errno of Linux for the error of the host.
+pub fn get_errno(error: &io::Error) -> u32 {
+    if cfg!(target_os = "linux") {
+        match error.raw_os_error() {
+            Some(errno) => return errno as u32,
+            None => {}
+        }
+    }
+    match error.kind() {
+        io::ErrorKind::NotFound => ENOENT,
+        io::ErrorKind::PermissionDenied => EACCES,
+        io::ErrorKind::AlreadyExists => EEXIST,
+        io::ErrorKind::NotADirectory => ENOTDIR,
+        io::ErrorKind::IsADirectory => EISDIR,
+        io::ErrorKind::DirectoryNotEmpty => ENOTEMPTY,
+        io::ErrorKind::ReadOnlyFilesystem => EROFS,
+        io::ErrorKind::InvalidInput => EINVAL,
+        io::ErrorKind::Unsupported => EOPNOTSUPP,
+        _ => EIO,
+    }
+}
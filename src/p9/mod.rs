@@ -0,0 +1,1461 @@
+// 9P2000.L server which exports a directory of the host
+// https://github.com/chaos/diod/blob/master/protocol.md
+
+pub mod host;
+
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::p9::host::*;
+
+const P9_VERSION: &str = "9P2000.L";
+/// the largest message which the server takes.
+const MSIZE_MAX: u32 = 0x80000;
+/// size[4] type[1] tag[2]
+const HEADER_SIZE: usize = 7;
+/// size[4] type[1] tag[2] count[4] of Rread and Rreaddir
+const IO_HEADER_SIZE: usize = 11;
+
+// T-messages. The R-message of each is the next number.
+const P9_RLERROR: u8 = 7;
+const P9_TSTATFS: u8 = 8;
+const P9_TLOPEN: u8 = 12;
+const P9_TLCREATE: u8 = 14;
+const P9_TSYMLINK: u8 = 16;
+const P9_TRENAME: u8 = 20;
+const P9_TREADLINK: u8 = 22;
+const P9_TGETATTR: u8 = 24;
+const P9_TSETATTR: u8 = 26;
+const P9_TREADDIR: u8 = 40;
+const P9_TFSYNC: u8 = 50;
+const P9_TLOCK: u8 = 52;
+const P9_TGETLOCK: u8 = 54;
+const P9_TLINK: u8 = 70;
+const P9_TMKDIR: u8 = 72;
+const P9_TRENAMEAT: u8 = 74;
+const P9_TUNLINKAT: u8 = 76;
+const P9_TVERSION: u8 = 100;
+const P9_TATTACH: u8 = 104;
+const P9_TFLUSH: u8 = 108;
+const P9_TWALK: u8 = 110;
+const P9_TREAD: u8 = 116;
+const P9_TWRITE: u8 = 118;
+const P9_TCLUNK: u8 = 120;
+const P9_TREMOVE: u8 = 122;
+
+// Qid types
+const P9_QTDIR: u8 = 0x80;
+const P9_QTSYMLINK: u8 = 0x02;
+const P9_QTFILE: u8 = 0x00;
+
+// Flags of Tlopen and Tlcreate, which are the flags of open(2) of Linux.
+const O_ACCMODE: u32 = 0o3;
+const O_RDONLY: u32 = 0o0;
+const O_WRONLY: u32 = 0o1;
+const O_EXCL: u32 = 0o200;
+const O_TRUNC: u32 = 0o1000;
+const O_APPEND: u32 = 0o2000;
+
+// Attributes of Tsetattr
+const P9_SETATTR_MODE: u32 = 0x1;
+const P9_SETATTR_SIZE: u32 = 0x8;
+const P9_SETATTR_ATIME: u32 = 0x10;
+const P9_SETATTR_MTIME: u32 = 0x20;
+const P9_SETATTR_ATIME_SET: u32 = 0x80;
+const P9_SETATTR_MTIME_SET: u32 = 0x100;
+
+/// the attributes of Rgetattr from mode to blocks.
+const P9_GETATTR_BASIC: u64 = 0x7ff;
+const AT_REMOVEDIR: u32 = 0x200;
+const P9_LOCK_SUCCESS: u8 = 0;
+const F_UNLCK: u8 = 2;
+
+// Rstatfs, which is made up since the host has no portable statfs.
+const V9FS_MAGIC: u32 = 0x01021997;
+const STATFS_BLOCK_SIZE: u32 = 4096;
+const STATFS_BLOCKS: u64 = 1 << 24;
+const STATFS_FILES: u64 = 1 << 20;
+const NAME_MAX: u32 = 255;
+
+// d_type of the directory entries
+const DT_UNKNOWN: u8 = 0;
+const DT_DIR: u8 = 4;
+const DT_REG: u8 = 8;
+const DT_LNK: u8 = 10;
+
+/// The fields of a T-message in order. Reading beyond the end gives zeros, and
+/// the message is invalid then.
+struct Reader<'a> {
+    data: &'a [u8],
+    offset: usize,
+    valid: bool,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data_: &'a [u8]) -> Self {
+        Reader {
+            data: data_,
+            offset: 0,
+            valid: true,
+        }
+    }
+
+    fn read_bytes(&mut self, size: usize) -> &'a [u8] {
+        if self.offset + size > self.data.len() {
+            self.valid = false;
+            self.offset = self.data.len();
+            return &[];
+        }
+        let bytes = &self.data[self.offset..self.offset + size];
+        self.offset += size;
+        bytes
+    }
+
+    fn read8(&mut self) -> u8 {
+        match self.read_bytes(1) {
+            [byte] => *byte,
+            _ => 0,
+        }
+    }
+
+    fn read16(&mut self) -> u16 {
+        let mut bytes = [0; 2];
+        bytes.copy_from_slice(match self.read_bytes(2) {
+            [] => &[0; 2],
+            data => data,
+        });
+        u16::from_le_bytes(bytes)
+    }
+
+    fn read32(&mut self) -> u32 {
+        let mut bytes = [0; 4];
+        bytes.copy_from_slice(match self.read_bytes(4) {
+            [] => &[0; 4],
+            data => data,
+        });
+        u32::from_le_bytes(bytes)
+    }
+
+    fn read64(&mut self) -> u64 {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(match self.read_bytes(8) {
+            [] => &[0; 8],
+            data => data,
+        });
+        u64::from_le_bytes(bytes)
+    }
+
+    fn read_string(&mut self) -> String {
+        let size = self.read16() as usize;
+        String::from_utf8_lossy(self.read_bytes(size)).to_string()
+    }
+}
+
+/// The fields of an R-message in order.
+struct Writer {
+    data: Vec<u8>,
+}
+
+impl Writer {
+    fn new() -> Self {
+        Writer { data: vec![] }
+    }
+
+    fn write8(&mut self, data: u8) {
+        self.data.push(data);
+    }
+
+    fn write16(&mut self, data: u16) {
+        self.data.extend_from_slice(&data.to_le_bytes());
+    }
+
+    fn write32(&mut self, data: u32) {
+        self.data.extend_from_slice(&data.to_le_bytes());
+    }
+
+    fn write64(&mut self, data: u64) {
+        self.data.extend_from_slice(&data.to_le_bytes());
+    }
+
+    fn write_string(&mut self, data: &str) {
+        self.write16(data.len() as u16);
+        self.data.extend_from_slice(data.as_bytes());
+    }
+
+    fn write_qid(&mut self, qid: &Qid) {
+        self.write8(qid.kind);
+        self.write32(qid.version);
+        self.write64(qid.path);
+    }
+}
+
+/// The unique identifier of a file in the server.
+#[derive(Clone)]
+struct Qid {
+    kind: u8,
+    version: u32,
+    path: u64,
+}
+
+impl Qid {
+    fn new(stat: &Stat) -> Self {
+        let kind = match stat.mode & S_IFMT {
+            S_IFDIR => P9_QTDIR,
+            S_IFLNK => P9_QTSYMLINK,
+            _ => P9_QTFILE,
+        };
+        Qid {
+            kind,
+            version: 0,
+            path: stat.ino,
+        }
+    }
+}
+
+struct DirectoryEntry {
+    qid: Qid,
+    kind: u8,
+    name: String,
+}
+
+/// A file which the client refers to by the fid.
+struct Fid {
+    /// the path from the root. It has no "." or "..", so the client cannot go
+    /// outside the root.
+    path: PathBuf,
+    /// the file opened by Tlopen or Tlcreate.
+    file: Option<File>,
+    /// the entries of the directory which Treaddir from offset 0 reads.
+    entries: Vec<DirectoryEntry>,
+}
+
+impl Fid {
+    fn new(path_: PathBuf) -> Self {
+        Fid {
+            path: path_,
+            file: None,
+            entries: vec![],
+        }
+    }
+}
+
+pub struct P9Server {
+    /// the canonical path of the exported directory.
+    root: PathBuf,
+    /// the client cannot change the files.
+    read_only: bool,
+    /// the maximum size of the messages negotiated by Tversion.
+    msize: u32,
+    fids: HashMap<u32, Fid>,
+}
+
+impl P9Server {
+    pub fn new(root_: &Path, read_only_: bool) -> Self {
+        let root = match fs::canonicalize(root_) {
+            Ok(root) if root.is_dir() => root,
+            _ => panic!("{} is not a directory.", root_.display()),
+        };
+        P9Server {
+            root,
+            read_only: read_only_,
+            msize: MSIZE_MAX,
+            fids: HashMap::new(),
+        }
+    }
+
+    /// handle the T-message, and return the R-message which fits in max_size.
+    pub fn handle(&mut self, request: &[u8], max_size: usize) -> Vec<u8> {
+        let mut reader = Reader::new(request);
+        let _size = reader.read32();
+        let kind = reader.read8();
+        let tag = reader.read16();
+        let max_size = max_size.min(self.msize as usize);
+        let result = match kind {
+            P9_TSTATFS => self.statfs(&mut reader),
+            P9_TLOPEN => self.lopen(&mut reader),
+            P9_TLCREATE => self.lcreate(&mut reader),
+            P9_TSYMLINK => self.symlink(&mut reader),
+            P9_TRENAME => self.rename(&mut reader),
+            P9_TREADLINK => self.readlink(&mut reader),
+            P9_TGETATTR => self.getattr(&mut reader),
+            P9_TSETATTR => self.setattr(&mut reader),
+            P9_TREADDIR => self.readdir(&mut reader, max_size),
+            P9_TFSYNC => self.fsync(&mut reader),
+            P9_TLOCK => self.lock(&mut reader),
+            P9_TGETLOCK => self.getlock(&mut reader),
+            P9_TLINK => self.link(&mut reader),
+            P9_TMKDIR => self.mkdir(&mut reader),
+            P9_TRENAMEAT => self.renameat(&mut reader),
+            P9_TUNLINKAT => self.unlinkat(&mut reader),
+            P9_TVERSION => self.version(&mut reader),
+            P9_TATTACH => self.attach(&mut reader),
+            P9_TFLUSH => Ok(Writer::new()),
+            P9_TWALK => self.walk(&mut reader),
+            P9_TREAD => self.read(&mut reader, max_size),
+            P9_TWRITE => self.write(&mut reader),
+            P9_TCLUNK => self.clunk(&mut reader),
+            P9_TREMOVE => self.remove(&mut reader),
+            // Txattrwalk, Tmknod and the others.
+            _ => Err(EOPNOTSUPP),
+        };
+        let (kind, body) = match result {
+            Ok(writer) => (kind + 1, writer.data),
+            Err(errno) => (P9_RLERROR, errno.to_le_bytes().to_vec()),
+        };
+        let mut response = Writer::new();
+        response.write32((HEADER_SIZE + body.len()) as u32);
+        response.write8(kind);
+        response.write16(tag);
+        response.data.extend_from_slice(&body);
+        response.data
+    }
+
+    fn get_fid(&mut self, fid: u32) -> Result<&mut Fid, u32> {
+        match self.fids.get_mut(&fid) {
+            Some(fid) => Ok(fid),
+            None => Err(EBADF),
+        }
+    }
+
+    /// the path of the host for the path from the root. Its parent is resolved with
+    /// the symbolic links, and it must be in the root. The last component is not
+    /// resolved, and the operations do not follow it: the files are opened with
+    /// O_NOFOLLOW, and their attributes are changed with AT_SYMLINK_NOFOLLOW.
+    fn get_host_path(&self, path: &Path) -> Result<PathBuf, u32> {
+        let (parent, name) = match (path.parent(), path.file_name()) {
+            (Some(parent), Some(name)) => (parent, name),
+            _ => return Ok(self.root.clone()),
+        };
+        match fs::canonicalize(self.root.join(parent)) {
+            Ok(parent) if parent.starts_with(&self.root) => Ok(parent.join(name)),
+            Ok(_) => Err(EACCES),
+            Err(why) => Err(get_errno(&why)),
+        }
+    }
+
+    /// the path of the host of the fid.
+    fn get_fid_host_path(&mut self, fid: u32) -> Result<PathBuf, u32> {
+        let path = match self.get_fid(fid) {
+            Ok(fid) => fid.path.clone(),
+            Err(errno) => return Err(errno),
+        };
+        self.get_host_path(&path)
+    }
+
+    /// the path from the root and the path of the host of the name in the
+    /// directory of the fid. The name is a single component.
+    fn get_child_path(&mut self, fid: u32, name: &str) -> Result<(PathBuf, PathBuf), u32> {
+        if name.is_empty() || name == "." || name == ".." || name.contains('/') || name.contains('\0')
+        {
+            return Err(EINVAL);
+        }
+        let path = match self.get_fid(fid) {
+            Ok(fid) => fid.path.join(name),
+            Err(errno) => return Err(errno),
+        };
+        match self.get_host_path(&path) {
+            Ok(host_path) => Ok((path, host_path)),
+            Err(errno) => Err(errno),
+        }
+    }
+
+    fn check_writable(&self) -> Result<(), u32> {
+        match self.read_only {
+            true => Err(EROFS),
+            false => Ok(()),
+        }
+    }
+
+    /// version[4] msize[s] -> msize[4] version[s]
+    fn version(&mut self, reader: &mut Reader) -> Result<Writer, u32> {
+        let msize = reader.read32();
+        let version = reader.read_string();
+        if !reader.valid {
+            return Err(EPROTO);
+        }
+        // the session starts again.
+        self.fids.clear();
+        self.msize = msize.min(MSIZE_MAX);
+        let mut writer = Writer::new();
+        writer.write32(self.msize);
+        match version == P9_VERSION {
+            true => writer.write_string(P9_VERSION),
+            false => writer.write_string("unknown"),
+        }
+        Ok(writer)
+    }
+
+    /// fid[4] afid[4] uname[s] aname[s] n_uname[4] -> qid[13]
+    fn attach(&mut self, reader: &mut Reader) -> Result<Writer, u32> {
+        let fid = reader.read32();
+        let _afid = reader.read32();
+        let _uname = reader.read_string();
+        let _aname = reader.read_string();
+        let _n_uname = reader.read32();
+        if !reader.valid {
+            return Err(EPROTO);
+        }
+        let stat = match get_lstat(&self.root) {
+            Ok(stat) => stat,
+            Err(errno) => return Err(errno),
+        };
+        self.fids.insert(fid, Fid::new(PathBuf::new()));
+        let mut writer = Writer::new();
+        writer.write_qid(&Qid::new(&stat));
+        Ok(writer)
+    }
+
+    /// fid[4] newfid[4] nwname[2] nwname*(wname[s]) -> nwqid[2] nwqid*(qid[13])
+    /// The walk stops at the first name which does not exist, and newfid is made
+    /// only if the walk reaches the last name.
+    fn walk(&mut self, reader: &mut Reader) -> Result<Writer, u32> {
+        let fid = reader.read32();
+        let newfid = reader.read32();
+        let nwname = reader.read16();
+        let names: Vec<String> = (0..nwname).map(|_| reader.read_string()).collect();
+        if !reader.valid {
+            return Err(EPROTO);
+        }
+        let mut path = match self.get_fid(fid) {
+            Ok(fid) => fid.path.clone(),
+            Err(errno) => return Err(errno),
+        };
+        let mut qids = vec![];
+        for name in names.iter() {
+            match name.as_str() {
+                "." => {}
+                // the parent of the root is the root.
+                ".." => {
+                    path.pop();
+                }
+                _ if name.is_empty() || name.contains('/') || name.contains('\0') => {
+                    return Err(ENOENT)
+                }
+                _ => path.push(name),
+            }
+            let stat = match self.get_host_path(&path).and_then(|p| get_lstat(&p)) {
+                Ok(stat) => stat,
+                Err(errno) if qids.is_empty() => return Err(errno),
+                Err(_errno) => break,
+            };
+            qids.push(Qid::new(&stat));
+        }
+        if qids.len() == names.len() {
+            self.fids.insert(newfid, Fid::new(path));
+        }
+        let mut writer = Writer::new();
+        writer.write16(qids.len() as u16);
+        for qid in qids.iter() {
+            writer.write_qid(qid);
+        }
+        Ok(writer)
+    }
+
+    /// fid[4] request_mask[8] -> valid[8] qid[13] mode[4] uid[4] gid[4] nlink[8]
+    /// rdev[8] size[8] blksize[8] blocks[8] atime_sec[8] atime_nsec[8] mtime_sec[8]
+    /// mtime_nsec[8] ctime_sec[8] ctime_nsec[8] btime_sec[8] btime_nsec[8] gen[8]
+    /// data_version[8]
+    fn getattr(&mut self, reader: &mut Reader) -> Result<Writer, u32> {
+        let fid = reader.read32();
+        let _request_mask = reader.read64();
+        if !reader.valid {
+            return Err(EPROTO);
+        }
+        let stat = match self.get_fid_host_path(fid).and_then(|p| get_lstat(&p)) {
+            Ok(stat) => stat,
+            Err(errno) => return Err(errno),
+        };
+        let mut writer = Writer::new();
+        writer.write64(P9_GETATTR_BASIC);
+        writer.write_qid(&Qid::new(&stat));
+        writer.write32(stat.mode);
+        writer.write32(stat.uid);
+        writer.write32(stat.gid);
+        writer.write64(stat.nlink);
+        writer.write64(stat.rdev);
+        writer.write64(stat.size);
+        writer.write64(stat.blksize);
+        writer.write64(stat.blocks);
+        for time in [stat.atime, stat.mtime, stat.ctime, (0, 0)].iter() {
+            writer.write64(time.0);
+            writer.write64(time.1);
+        }
+        writer.write64(0); // gen
+        writer.write64(0); // data_version
+        Ok(writer)
+    }
+
+    /// fid[4] valid[4] mode[4] uid[4] gid[4] size[8] atime_sec[8] atime_nsec[8]
+    /// mtime_sec[8] mtime_nsec[8] -> (empty)
+    /// The owner stays since the files of the host belong to the emulator.
+    fn setattr(&mut self, reader: &mut Reader) -> Result<Writer, u32> {
+        let fid = reader.read32();
+        let valid = reader.read32();
+        let mode = reader.read32();
+        let _uid = reader.read32();
+        let _gid = reader.read32();
+        let size = reader.read64();
+        let atime = (reader.read64(), reader.read64());
+        let mtime = (reader.read64(), reader.read64());
+        if !reader.valid {
+            return Err(EPROTO);
+        }
+        let host_path = match self.check_writable().and_then(|_| self.get_fid_host_path(fid)) {
+            Ok(host_path) => host_path,
+            Err(errno) => return Err(errno),
+        };
+        match get_lstat(&host_path) {
+            // the attributes of a symbolic link are the ones of the link itself.
+            Ok(stat) if (stat.mode & S_IFMT) == S_IFLNK => return Ok(Writer::new()),
+            Ok(_) => {}
+            Err(errno) => return Err(errno),
+        }
+        // the file may be replaced by a symbolic link since lstat, so the mode and
+        // the times are set without following it, and the file is opened with
+        // O_NOFOLLOW to be truncated.
+        if (valid & P9_SETATTR_MODE) != 0 {
+            match set_mode(&host_path, mode) {
+                Err(why) => return Err(get_errno(&why)),
+                _ => {}
+            }
+        }
+        if (valid & P9_SETATTR_SIZE) != 0 {
+            match open_file(&host_path, O_WRONLY, false) {
+                Ok(file) => match file.set_len(size) {
+                    Err(why) => return Err(get_errno(&why)),
+                    _ => {}
+                },
+                Err(errno) => return Err(errno),
+            }
+        }
+        if (valid & (P9_SETATTR_ATIME | P9_SETATTR_MTIME)) != 0 {
+            let get_time = |time: (u64, u64), valid_time: u32, valid_set: u32| {
+                match ((valid & valid_time) != 0, (valid & valid_set) != 0) {
+                    (true, true) => Some(UNIX_EPOCH + Duration::new(time.0, time.1 as u32)),
+                    (true, false) => Some(SystemTime::now()),
+                    (false, _) => None,
+                }
+            };
+            let atime = get_time(atime, P9_SETATTR_ATIME, P9_SETATTR_ATIME_SET);
+            let mtime = get_time(mtime, P9_SETATTR_MTIME, P9_SETATTR_MTIME_SET);
+            match set_times(&host_path, atime, mtime) {
+                Err(why) => return Err(get_errno(&why)),
+                _ => {}
+            }
+        }
+        Ok(Writer::new())
+    }
+
+    /// fid[4] flags[4] -> qid[13] iounit[4]
+    fn lopen(&mut self, reader: &mut Reader) -> Result<Writer, u32> {
+        let fid = reader.read32();
+        let flags = reader.read32();
+        if !reader.valid {
+            return Err(EPROTO);
+        }
+        if (flags & O_ACCMODE) != O_RDONLY || (flags & O_TRUNC) != 0 {
+            match self.check_writable() {
+                Err(errno) => return Err(errno),
+                _ => {}
+            }
+        }
+        let host_path = match self.get_fid_host_path(fid) {
+            Ok(host_path) => host_path,
+            Err(errno) => return Err(errno),
+        };
+        let mut stat = match get_lstat(&host_path) {
+            Ok(stat) => stat,
+            Err(errno) => return Err(errno),
+        };
+        let file = match stat.mode & S_IFMT {
+            // the client follows the symbolic links by itself.
+            S_IFLNK => return Err(ELOOP),
+            // Treaddir reads the directory by the path.
+            S_IFDIR => None,
+            // the file may have been replaced since lstat.
+            _ => match open_file(&host_path, flags, false).and_then(|file| {
+                get_file_stat(&file, &host_path).map(|file_stat| (file, file_stat))
+            }) {
+                Ok((file, file_stat)) => {
+                    stat = file_stat;
+                    Some(file)
+                }
+                Err(errno) => return Err(errno),
+            },
+        };
+        match self.get_fid(fid) {
+            Ok(fid) => {
+                fid.file = file;
+                fid.entries.clear();
+            }
+            Err(errno) => return Err(errno),
+        }
+        let mut writer = Writer::new();
+        writer.write_qid(&Qid::new(&stat));
+        writer.write32(0); // iounit: the client decides it from msize.
+        Ok(writer)
+    }
+
+    /// fid[4] name[s] flags[4] mode[4] gid[4] -> qid[13] iounit[4]
+    /// The fid becomes the new file.
+    fn lcreate(&mut self, reader: &mut Reader) -> Result<Writer, u32> {
+        let fid = reader.read32();
+        let name = reader.read_string();
+        let flags = reader.read32();
+        let mode = reader.read32();
+        let _gid = reader.read32();
+        if !reader.valid {
+            return Err(EPROTO);
+        }
+        let (path, host_path) = match self
+            .check_writable()
+            .and_then(|_| self.get_child_path(fid, &name))
+        {
+            Ok(paths) => paths,
+            Err(errno) => return Err(errno),
+        };
+        let exists = fs::symlink_metadata(&host_path).is_ok();
+        let file = match open_file(&host_path, flags, true) {
+            Ok(file) => file,
+            Err(errno) => return Err(errno),
+        };
+        if !exists {
+            match set_mode(&host_path, mode) {
+                Err(why) => return Err(get_errno(&why)),
+                _ => {}
+            }
+        }
+        let stat = match get_file_stat(&file, &host_path) {
+            Ok(stat) => stat,
+            Err(errno) => return Err(errno),
+        };
+        match self.get_fid(fid) {
+            Ok(fid) => {
+                fid.path = path;
+                fid.file = Some(file);
+                fid.entries.clear();
+            }
+            Err(errno) => return Err(errno),
+        }
+        let mut writer = Writer::new();
+        writer.write_qid(&Qid::new(&stat));
+        writer.write32(0);
+        Ok(writer)
+    }
+
+    /// fid[4] name[s] symtgt[s] gid[4] -> qid[13]
+    fn symlink(&mut self, reader: &mut Reader) -> Result<Writer, u32> {
+        let fid = reader.read32();
+        let name = reader.read_string();
+        let target = reader.read_string();
+        let _gid = reader.read32();
+        if !reader.valid {
+            return Err(EPROTO);
+        }
+        let (_, host_path) = match self
+            .check_writable()
+            .and_then(|_| self.get_child_path(fid, &name))
+        {
+            Ok(paths) => paths,
+            Err(errno) => return Err(errno),
+        };
+        let stat = match host::symlink(&target, &host_path) {
+            Ok(_) => match get_lstat(&host_path) {
+                Ok(stat) => stat,
+                Err(errno) => return Err(errno),
+            },
+            Err(why) => return Err(get_errno(&why)),
+        };
+        let mut writer = Writer::new();
+        writer.write_qid(&Qid::new(&stat));
+        Ok(writer)
+    }
+
+    /// dfid[4] name[s] mode[4] gid[4] -> qid[13]
+    fn mkdir(&mut self, reader: &mut Reader) -> Result<Writer, u32> {
+        let fid = reader.read32();
+        let name = reader.read_string();
+        let mode = reader.read32();
+        let _gid = reader.read32();
+        if !reader.valid {
+            return Err(EPROTO);
+        }
+        let (_, host_path) = match self
+            .check_writable()
+            .and_then(|_| self.get_child_path(fid, &name))
+        {
+            Ok(paths) => paths,
+            Err(errno) => return Err(errno),
+        };
+        match fs::create_dir(&host_path).and_then(|_| set_mode(&host_path, mode)) {
+            Err(why) => return Err(get_errno(&why)),
+            _ => {}
+        }
+        let stat = match get_lstat(&host_path) {
+            Ok(stat) => stat,
+            Err(errno) => return Err(errno),
+        };
+        let mut writer = Writer::new();
+        writer.write_qid(&Qid::new(&stat));
+        Ok(writer)
+    }
+
+    /// dfid[4] fid[4] name[s] -> (empty)
+    fn link(&mut self, reader: &mut Reader) -> Result<Writer, u32> {
+        let dfid = reader.read32();
+        let fid = reader.read32();
+        let name = reader.read_string();
+        if !reader.valid {
+            return Err(EPROTO);
+        }
+        let host_path = match self
+            .check_writable()
+            .and_then(|_| self.get_fid_host_path(fid))
+        {
+            Ok(host_path) => host_path,
+            Err(errno) => return Err(errno),
+        };
+        let (_, new_host_path) = match self.get_child_path(dfid, &name) {
+            Ok(paths) => paths,
+            Err(errno) => return Err(errno),
+        };
+        match fs::hard_link(&host_path, &new_host_path) {
+            Ok(_) => Ok(Writer::new()),
+            Err(why) => Err(get_errno(&why)),
+        }
+    }
+
+    /// fid[4] dfid[4] name[s] -> (empty)
+    fn rename(&mut self, reader: &mut Reader) -> Result<Writer, u32> {
+        let fid = reader.read32();
+        let dfid = reader.read32();
+        let name = reader.read_string();
+        if !reader.valid {
+            return Err(EPROTO);
+        }
+        let host_path = match self
+            .check_writable()
+            .and_then(|_| self.get_fid_host_path(fid))
+        {
+            Ok(host_path) => host_path,
+            Err(errno) => return Err(errno),
+        };
+        let (new_path, new_host_path) = match self.get_child_path(dfid, &name) {
+            Ok(paths) => paths,
+            Err(errno) => return Err(errno),
+        };
+        match fs::rename(&host_path, &new_host_path) {
+            Err(why) => return Err(get_errno(&why)),
+            _ => {}
+        }
+        match self.get_fid(fid) {
+            Ok(fid) => fid.path = new_path,
+            Err(errno) => return Err(errno),
+        }
+        Ok(Writer::new())
+    }
+
+    /// olddirfid[4] oldname[s] newdirfid[4] newname[s] -> (empty)
+    fn renameat(&mut self, reader: &mut Reader) -> Result<Writer, u32> {
+        let old_fid = reader.read32();
+        let old_name = reader.read_string();
+        let new_fid = reader.read32();
+        let new_name = reader.read_string();
+        if !reader.valid {
+            return Err(EPROTO);
+        }
+        let (_, host_path) = match self
+            .check_writable()
+            .and_then(|_| self.get_child_path(old_fid, &old_name))
+        {
+            Ok(paths) => paths,
+            Err(errno) => return Err(errno),
+        };
+        let (_, new_host_path) = match self.get_child_path(new_fid, &new_name) {
+            Ok(paths) => paths,
+            Err(errno) => return Err(errno),
+        };
+        match fs::rename(&host_path, &new_host_path) {
+            Ok(_) => Ok(Writer::new()),
+            Err(why) => Err(get_errno(&why)),
+        }
+    }
+
+    /// dirfd[4] name[s] flags[4] -> (empty)
+    fn unlinkat(&mut self, reader: &mut Reader) -> Result<Writer, u32> {
+        let fid = reader.read32();
+        let name = reader.read_string();
+        let flags = reader.read32();
+        if !reader.valid {
+            return Err(EPROTO);
+        }
+        let (_, host_path) = match self
+            .check_writable()
+            .and_then(|_| self.get_child_path(fid, &name))
+        {
+            Ok(paths) => paths,
+            Err(errno) => return Err(errno),
+        };
+        remove_path(&host_path, (flags & AT_REMOVEDIR) != 0)
+    }
+
+    /// fid[4] -> (empty)
+    /// The fid is clunked even if the file is not removed.
+    fn remove(&mut self, reader: &mut Reader) -> Result<Writer, u32> {
+        let fid = reader.read32();
+        if !reader.valid {
+            return Err(EPROTO);
+        }
+        let result = match self
+            .check_writable()
+            .and_then(|_| self.get_fid_host_path(fid))
+        {
+            Ok(host_path) => match get_lstat(&host_path) {
+                Ok(stat) => remove_path(&host_path, (stat.mode & S_IFMT) == S_IFDIR),
+                Err(errno) => Err(errno),
+            },
+            Err(errno) => Err(errno),
+        };
+        self.fids.remove(&fid);
+        result
+    }
+
+    /// fid[4] -> target[s]
+    fn readlink(&mut self, reader: &mut Reader) -> Result<Writer, u32> {
+        let fid = reader.read32();
+        if !reader.valid {
+            return Err(EPROTO);
+        }
+        let target = match self.get_fid_host_path(fid) {
+            Ok(host_path) => match fs::read_link(&host_path) {
+                Ok(target) => target,
+                Err(why) => return Err(get_errno(&why)),
+            },
+            Err(errno) => return Err(errno),
+        };
+        let mut writer = Writer::new();
+        writer.write_string(&target.to_string_lossy());
+        Ok(writer)
+    }
+
+    /// fid[4] offset[8] count[4] -> count[4] data[count]
+    /// The offset of an entry is the index of the next entry.
+    fn readdir(&mut self, reader: &mut Reader, max_size: usize) -> Result<Writer, u32> {
+        let fid = reader.read32();
+        let offset = reader.read64();
+        let count = reader.read32();
+        if !reader.valid {
+            return Err(EPROTO);
+        }
+        let read_entries = match self.get_fid(fid) {
+            Ok(fid) => offset == 0 || fid.entries.is_empty(),
+            Err(errno) => return Err(errno),
+        };
+        if read_entries {
+            let entries = match self.read_entries(fid) {
+                Ok(entries) => entries,
+                Err(errno) => return Err(errno),
+            };
+            match self.get_fid(fid) {
+                Ok(fid) => fid.entries = entries,
+                Err(errno) => return Err(errno),
+            }
+        }
+        let size = (count as usize).min(max_size.saturating_sub(IO_HEADER_SIZE));
+        let mut data = Writer::new();
+        let entries = &self.fids[&fid].entries;
+        for (i, entry) in entries.iter().enumerate().skip(offset as usize) {
+            // qid[13] offset[8] type[1] name[s]
+            if data.data.len() + 24 + entry.name.len() > size {
+                break;
+            }
+            data.write_qid(&entry.qid);
+            data.write64(i as u64 + 1);
+            data.write8(entry.kind);
+            data.write_string(&entry.name);
+        }
+        let mut writer = Writer::new();
+        writer.write32(data.data.len() as u32);
+        writer.data.extend_from_slice(&data.data);
+        Ok(writer)
+    }
+
+    /// the entries of the directory of the fid in the order of the names after "."
+    /// and "..".
+    fn read_entries(&mut self, fid: u32) -> Result<Vec<DirectoryEntry>, u32> {
+        let path = match self.get_fid(fid) {
+            Ok(fid) => fid.path.clone(),
+            Err(errno) => return Err(errno),
+        };
+        // read_dir follows the symbolic link, so the directory itself is resolved.
+        let host_path = match self.get_host_path(&path).map(fs::canonicalize) {
+            Ok(Ok(host_path)) if host_path.starts_with(&self.root) => host_path,
+            Ok(Ok(_)) => return Err(EACCES),
+            Ok(Err(why)) => return Err(get_errno(&why)),
+            Err(errno) => return Err(errno),
+        };
+        let mut parent = path.clone();
+        parent.pop();
+        let mut entries = vec![];
+        for (name, path) in [(".", path), ("..", parent)].iter() {
+            match self.get_host_path(path).and_then(|p| get_lstat(&p)) {
+                Ok(stat) => entries.push(DirectoryEntry {
+                    qid: Qid::new(&stat),
+                    kind: DT_DIR,
+                    name: name.to_string(),
+                }),
+                Err(errno) => return Err(errno),
+            }
+        }
+        let mut children = vec![];
+        match fs::read_dir(&host_path) {
+            Ok(directory) => {
+                for entry in directory.flatten() {
+                    let name = entry.file_name().to_string_lossy().to_string();
+                    let stat = match entry.metadata() {
+                        Ok(metadata) => get_stat(&metadata, &entry.path()),
+                        Err(_e) => continue,
+                    };
+                    let kind = match stat.mode & S_IFMT {
+                        S_IFDIR => DT_DIR,
+                        S_IFREG => DT_REG,
+                        S_IFLNK => DT_LNK,
+                        _ => DT_UNKNOWN,
+                    };
+                    children.push(DirectoryEntry {
+                        qid: Qid::new(&stat),
+                        kind,
+                        name,
+                    });
+                }
+            }
+            Err(why) => return Err(get_errno(&why)),
+        }
+        children.sort_by(|a, b| a.name.cmp(&b.name));
+        entries.append(&mut children);
+        Ok(entries)
+    }
+
+    /// fid[4] offset[8] count[4] -> count[4] data[count]
+    fn read(&mut self, reader: &mut Reader, max_size: usize) -> Result<Writer, u32> {
+        let fid = reader.read32();
+        let offset = reader.read64();
+        let count = reader.read32();
+        if !reader.valid {
+            return Err(EPROTO);
+        }
+        let file = match self.get_fid(fid) {
+            Ok(Fid {
+                file: Some(file), ..
+            }) => file,
+            Ok(_) => return Err(EBADF),
+            Err(errno) => return Err(errno),
+        };
+        let size = (count as usize).min(max_size.saturating_sub(IO_HEADER_SIZE));
+        let mut data = vec![];
+        match file
+            .seek(SeekFrom::Start(offset))
+            .and_then(|_| file.take(size as u64).read_to_end(&mut data))
+        {
+            Err(why) => return Err(get_errno(&why)),
+            _ => {}
+        }
+        let mut writer = Writer::new();
+        writer.write32(data.len() as u32);
+        writer.data.extend_from_slice(&data);
+        Ok(writer)
+    }
+
+    /// fid[4] offset[8] count[4] data[count] -> count[4]
+    fn write(&mut self, reader: &mut Reader) -> Result<Writer, u32> {
+        let fid = reader.read32();
+        let offset = reader.read64();
+        let count = reader.read32();
+        let data = reader.read_bytes(count as usize);
+        if !reader.valid {
+            return Err(EPROTO);
+        }
+        let file = match self.get_fid(fid) {
+            Ok(Fid {
+                file: Some(file), ..
+            }) => file,
+            Ok(_) => return Err(EBADF),
+            Err(errno) => return Err(errno),
+        };
+        match file
+            .seek(SeekFrom::Start(offset))
+            .and_then(|_| file.write_all(data))
+        {
+            Err(why) => return Err(get_errno(&why)),
+            _ => {}
+        }
+        let mut writer = Writer::new();
+        writer.write32(count);
+        Ok(writer)
+    }
+
+    /// fid[4] datasync[4] -> (empty)
+    fn fsync(&mut self, reader: &mut Reader) -> Result<Writer, u32> {
+        let fid = reader.read32();
+        let datasync = reader.read32();
+        if !reader.valid {
+            return Err(EPROTO);
+        }
+        let result = match self.get_fid(fid) {
+            Ok(Fid {
+                file: Some(file), ..
+            }) => match datasync {
+                0 => file.sync_all(),
+                _ => file.sync_data(),
+            },
+            Ok(_) => Ok(()),
+            Err(errno) => return Err(errno),
+        };
+        match result {
+            Ok(_) => Ok(Writer::new()),
+            Err(why) => Err(get_errno(&why)),
+        }
+    }
+
+    /// fid[4] type[1] flags[4] start[8] length[8] proc_id[4] client_id[s] -> status[1]
+    /// The client is the only user of the files, so a lock always succeeds.
+    fn lock(&mut self, reader: &mut Reader) -> Result<Writer, u32> {
+        let fid = reader.read32();
+        let _kind = reader.read8();
+        let _flags = reader.read32();
+        let _start = reader.read64();
+        let _length = reader.read64();
+        let _proc_id = reader.read32();
+        let _client_id = reader.read_string();
+        if !reader.valid {
+            return Err(EPROTO);
+        }
+        match self.get_fid(fid) {
+            Ok(_) => {
+                let mut writer = Writer::new();
+                writer.write8(P9_LOCK_SUCCESS);
+                Ok(writer)
+            }
+            Err(errno) => Err(errno),
+        }
+    }
+
+    /// fid[4] type[1] start[8] length[8] proc_id[4] client_id[s]
+    /// -> type[1] start[8] length[8] proc_id[4] client_id[s]
+    fn getlock(&mut self, reader: &mut Reader) -> Result<Writer, u32> {
+        let fid = reader.read32();
+        let _kind = reader.read8();
+        let start = reader.read64();
+        let length = reader.read64();
+        let proc_id = reader.read32();
+        let client_id = reader.read_string();
+        if !reader.valid {
+            return Err(EPROTO);
+        }
+        match self.get_fid(fid) {
+            Ok(_) => {
+                let mut writer = Writer::new();
+                writer.write8(F_UNLCK);
+                writer.write64(start);
+                writer.write64(length);
+                writer.write32(proc_id);
+                writer.write_string(&client_id);
+                Ok(writer)
+            }
+            Err(errno) => Err(errno),
+        }
+    }
+
+    /// fid[4] -> type[4] bsize[4] blocks[8] bfree[8] bavail[8] files[8] ffree[8]
+    /// fsid[8] namelen[4]
+    fn statfs(&mut self, reader: &mut Reader) -> Result<Writer, u32> {
+        let fid = reader.read32();
+        if !reader.valid {
+            return Err(EPROTO);
+        }
+        match self.get_fid(fid) {
+            Ok(_) => {
+                let mut writer = Writer::new();
+                writer.write32(V9FS_MAGIC);
+                writer.write32(STATFS_BLOCK_SIZE);
+                writer.write64(STATFS_BLOCKS);
+                writer.write64(STATFS_BLOCKS);
+                writer.write64(STATFS_BLOCKS);
+                writer.write64(STATFS_FILES);
+                writer.write64(STATFS_FILES);
+                writer.write64(0);
+                writer.write32(NAME_MAX);
+                Ok(writer)
+            }
+            Err(errno) => Err(errno),
+        }
+    }
+
+    /// fid[4] -> (empty)
+    fn clunk(&mut self, reader: &mut Reader) -> Result<Writer, u32> {
+        let fid = reader.read32();
+        if !reader.valid {
+            return Err(EPROTO);
+        }
+        match self.fids.remove(&fid) {
+            Some(_) => Ok(Writer::new()),
+            None => Err(EBADF),
+        }
+    }
+}
+
+/// the attributes of the file, or the symbolic link itself.
+fn get_lstat(host_path: &Path) -> Result<Stat, u32> {
+    match fs::symlink_metadata(host_path) {
+        Ok(metadata) => Ok(get_stat(&metadata, host_path)),
+        Err(why) => Err(get_errno(&why)),
+    }
+}
+
+/// the attributes of the opened file.
+fn get_file_stat(file: &File, host_path: &Path) -> Result<Stat, u32> {
+    match file.metadata() {
+        Ok(metadata) => Ok(get_stat(&metadata, host_path)),
+        Err(why) => Err(get_errno(&why)),
+    }
+}
+
+/// open the file by the flags of open(2) of Linux. A symbolic link is not
+/// followed, and opening it is ELOOP.
+fn open_file(host_path: &Path, flags: u32, create: bool) -> Result<File, u32> {
+    let mut options = OpenOptions::new();
+    set_no_follow(&mut options);
+    match flags & O_ACCMODE {
+        O_RDONLY => options.read(true),
+        O_WRONLY => options.write(true),
+        _ => options.read(true).write(true),
+    };
+    if create {
+        // a new file is written even if it is opened for reading only.
+        options.write(true);
+        match (flags & O_EXCL) != 0 {
+            true => options.create_new(true),
+            false => options.create(true),
+        };
+    }
+    if (flags & O_TRUNC) != 0 {
+        options.truncate(true);
+    }
+    if (flags & O_APPEND) != 0 {
+        options.append(true);
+    }
+    match options.open(host_path) {
+        Ok(file) => Ok(file),
+        Err(why) => Err(get_errno(&why)),
+    }
+}
+
+fn remove_path(host_path: &Path, directory: bool) -> Result<Writer, u32> {
+    let result = match directory {
+        true => fs::remove_dir(host_path),
+        false => match fs::symlink_metadata(host_path) {
+            Ok(metadata) if metadata.is_dir() => return Err(EISDIR),
+            _ => fs::remove_file(host_path),
+        },
+    };
+    match result {
+        Ok(_) => Ok(Writer::new()),
+        Err(why) => Err(get_errno(&why)),
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::os::unix::fs::{symlink, PermissionsExt};
+
+    const ROOT_FID: u32 = 0;
+    const O_RDWR: u32 = 0o2;
+
+    /// the exported directory root and the directory outside of it.
+    struct Directories {
+        base: PathBuf,
+        root: PathBuf,
+        outside: PathBuf,
+    }
+
+    impl Directories {
+        fn new(name: &str) -> Self {
+            let base =
+                env::temp_dir().join(format!("riscv_emu_p9_{}_{}", std::process::id(), name));
+            let _ = fs::remove_dir_all(&base);
+            let root = base.join("root");
+            let outside = base.join("outside");
+            fs::create_dir_all(&root).unwrap();
+            fs::create_dir_all(&outside).unwrap();
+            let secret = outside.join("secret");
+            fs::write(&secret, b"secret").unwrap();
+            fs::set_permissions(&secret, fs::Permissions::from_mode(0o644)).unwrap();
+            Directories {
+                base,
+                root,
+                outside,
+            }
+        }
+
+        /// the content and the permission bits of the secret file outside the root.
+        fn get_secret(&self) -> (Vec<u8>, u32) {
+            let path = self.outside.join("secret");
+            let mode = fs::metadata(&path).unwrap().permissions().mode() & 0o7777;
+            (fs::read(&path).unwrap(), mode)
+        }
+    }
+
+    impl Drop for Directories {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.base);
+        }
+    }
+
+    /// send the T-message, and return the body of the R-message or the errno.
+    fn call(server: &mut P9Server, kind: u8, body: Writer) -> Result<Vec<u8>, u32> {
+        let mut request = Writer::new();
+        request.write32((HEADER_SIZE + body.data.len()) as u32);
+        request.write8(kind);
+        request.write16(1);
+        request.data.extend_from_slice(&body.data);
+        let response = server.handle(&request.data, MSIZE_MAX as usize);
+        assert_eq!(
+            response.len(),
+            u32::from_le_bytes([response[0], response[1], response[2], response[3]]) as usize
+        );
+        match response[4] {
+            P9_RLERROR => Err(u32::from_le_bytes([
+                response[7],
+                response[8],
+                response[9],
+                response[10],
+            ])),
+            reply => {
+                assert_eq!(kind + 1, reply);
+                Ok(response[HEADER_SIZE..].to_vec())
+            }
+        }
+    }
+
+    fn attach(directories: &Directories) -> P9Server {
+        let mut server = P9Server::new(&directories.root, false);
+        let mut body = Writer::new();
+        body.write32(ROOT_FID);
+        body.write32(!0);
+        body.write_string("");
+        body.write_string("");
+        body.write32(0);
+        assert!(call(&mut server, P9_TATTACH, body).is_ok());
+        server
+    }
+
+    /// the number of the qids of the walk.
+    fn walk(server: &mut P9Server, fid: u32, newfid: u32, names: &[&str]) -> Result<u16, u32> {
+        let mut body = Writer::new();
+        body.write32(fid);
+        body.write32(newfid);
+        body.write16(names.len() as u16);
+        for name in names.iter() {
+            body.write_string(name);
+        }
+        call(server, P9_TWALK, body).map(|data| u16::from_le_bytes([data[0], data[1]]))
+    }
+
+    fn lopen(server: &mut P9Server, fid: u32, flags: u32) -> Result<Vec<u8>, u32> {
+        let mut body = Writer::new();
+        body.write32(fid);
+        body.write32(flags);
+        call(server, P9_TLOPEN, body)
+    }
+
+    fn lcreate(server: &mut P9Server, fid: u32, name: &str) -> Result<Vec<u8>, u32> {
+        let mut body = Writer::new();
+        body.write32(fid);
+        body.write_string(name);
+        body.write32(O_WRONLY);
+        body.write32(0o666);
+        body.write32(0);
+        call(server, P9_TLCREATE, body)
+    }
+
+    /// set the mode, the size and the modification time.
+    fn setattr(server: &mut P9Server, fid: u32, mode: u32, size: u64) -> Result<Vec<u8>, u32> {
+        let mut body = Writer::new();
+        body.write32(fid);
+        body.write32(P9_SETATTR_MODE | P9_SETATTR_SIZE | P9_SETATTR_MTIME | P9_SETATTR_MTIME_SET);
+        body.write32(mode);
+        body.write32(0);
+        body.write32(0);
+        body.write64(size);
+        for time in [0, 0, 1_000_000, 0].iter() {
+            body.write64(*time);
+        }
+        call(server, P9_TSETATTR, body)
+    }
+
+    fn chmod(server: &mut P9Server, fid: u32, mode: u32) -> Result<Vec<u8>, u32> {
+        let mut body = Writer::new();
+        body.write32(fid);
+        body.write32(P9_SETATTR_MODE);
+        body.write32(mode);
+        for _ in 0..2 {
+            body.write32(0);
+        }
+        for _ in 0..5 {
+            body.write64(0);
+        }
+        call(server, P9_TSETATTR, body)
+    }
+
+    fn readdir(server: &mut P9Server, fid: u32) -> Result<Vec<u8>, u32> {
+        let mut body = Writer::new();
+        body.write32(fid);
+        body.write64(0);
+        body.write32(0x1000);
+        call(server, P9_TREADDIR, body)
+    }
+
+    fn getattr_qid(server: &mut P9Server, fid: u32) -> Vec<u8> {
+        let mut body = Writer::new();
+        body.write32(fid);
+        body.write64(P9_GETATTR_BASIC);
+        call(server, P9_TGETATTR, body).unwrap()[8..21].to_vec()
+    }
+
+    #[test]
+    fn dot_dot_does_not_leave_the_root() {
+        let directories = Directories::new("dot_dot");
+        fs::create_dir(directories.root.join("dir")).unwrap();
+        symlink("../outside", directories.root.join("up")).unwrap();
+        let mut server = attach(&directories);
+        let root_qid = getattr_qid(&mut server, ROOT_FID);
+
+        // the parent of the root is the root.
+        assert_eq!(Ok(2), walk(&mut server, ROOT_FID, 1, &["..", ".."]));
+        assert_eq!(root_qid, getattr_qid(&mut server, 1));
+        assert_eq!(
+            Ok(4),
+            walk(&mut server, ROOT_FID, 2, &["dir", "..", "..", "dir"])
+        );
+        assert_ne!(root_qid, getattr_qid(&mut server, 2));
+        assert_eq!(Ok(2), walk(&mut server, 2, 3, &["..", ".."]));
+        assert_eq!(root_qid, getattr_qid(&mut server, 3));
+
+        // a name is a single component.
+        assert_eq!(Err(EINVAL), lcreate(&mut server, ROOT_FID, ".."));
+        assert_eq!(Err(EINVAL), lcreate(&mut server, ROOT_FID, "../escape"));
+        assert_eq!(Err(ENOENT), walk(&mut server, ROOT_FID, 4, &["../outside"]));
+        assert!(!directories.base.join("escape").exists());
+
+        // the relative symbolic link to the parent of the root is not followed.
+        assert_eq!(Ok(1), walk(&mut server, ROOT_FID, 4, &["up", "secret"]));
+        assert_eq!(Err(EBADF), lopen(&mut server, 4, O_RDONLY));
+        assert_eq!(Ok(1), walk(&mut server, ROOT_FID, 4, &["up"]));
+        assert_eq!(Err(ELOOP), lopen(&mut server, 4, O_RDONLY));
+        assert_eq!(Err(EACCES), readdir(&mut server, 4));
+    }
+
+    #[test]
+    fn absolute_symbolic_links_are_not_followed() {
+        let directories = Directories::new("absolute");
+        symlink(&directories.outside, directories.root.join("dir")).unwrap();
+        symlink(
+            directories.outside.join("secret"),
+            directories.root.join("file"),
+        )
+        .unwrap();
+        symlink(
+            directories.outside.join("created"),
+            directories.root.join("dangling"),
+        )
+        .unwrap();
+        let mut server = attach(&directories);
+
+        assert_eq!(Ok(1), walk(&mut server, ROOT_FID, 1, &["dir", "secret"]));
+        assert_eq!(Ok(1), walk(&mut server, ROOT_FID, 1, &["dir"]));
+        assert_eq!(Err(EACCES), readdir(&mut server, 1));
+        assert_eq!(Err(EACCES), lcreate(&mut server, 1, "created"));
+
+        assert_eq!(Ok(1), walk(&mut server, ROOT_FID, 2, &["file"]));
+        assert_eq!(Err(ELOOP), lopen(&mut server, 2, O_RDWR | O_TRUNC));
+        // the attributes of a symbolic link are the ones of the link itself.
+        assert!(setattr(&mut server, 2, 0o777, 0).is_ok());
+        assert_eq!((b"secret".to_vec(), 0o644), directories.get_secret());
+
+        // creating the file of a dangling symbolic link does not create its target.
+        assert_eq!(Ok(0), walk(&mut server, ROOT_FID, 3, &[]));
+        assert_eq!(Err(ELOOP), lcreate(&mut server, 3, "dangling"));
+        assert!(!directories.outside.join("created").exists());
+    }
+
+    #[test]
+    fn swapped_symbolic_links_are_not_followed() {
+        let directories = Directories::new("swap");
+        let file = directories.root.join("file");
+        let dir = directories.root.join("dir");
+        fs::write(&file, b"file").unwrap();
+        fs::create_dir(&dir).unwrap();
+        fs::write(dir.join("secret"), b"inner").unwrap();
+        let mut server = attach(&directories);
+        assert_eq!(Ok(1), walk(&mut server, ROOT_FID, 1, &["file"]));
+        assert_eq!(Ok(1), walk(&mut server, ROOT_FID, 2, &["dir"]));
+        assert_eq!(Ok(2), walk(&mut server, ROOT_FID, 3, &["dir", "secret"]));
+
+        // the attributes are changed on the file in the root.
+        assert!(setattr(&mut server, 1, 0o600, 2).is_ok());
+        let metadata = fs::metadata(&file).unwrap();
+        assert_eq!(
+            (0o600, 2),
+            (metadata.permissions().mode() & 0o7777, metadata.len())
+        );
+        assert_eq!(
+            UNIX_EPOCH + Duration::from_secs(1_000_000),
+            metadata.modified().unwrap()
+        );
+
+        // the host replaces the file and the directory with the symbolic links to
+        // the outside of the root after the walks.
+        fs::remove_file(&file).unwrap();
+        symlink(directories.outside.join("secret"), &file).unwrap();
+        fs::rename(&dir, directories.root.join("old")).unwrap();
+        symlink(&directories.outside, &dir).unwrap();
+
+        assert_eq!(Err(ELOOP), lopen(&mut server, 1, O_RDWR | O_TRUNC));
+        assert!(setattr(&mut server, 1, 0o777, 0).is_ok());
+        assert_eq!(Err(EACCES), lopen(&mut server, 3, O_RDWR | O_TRUNC));
+        assert_eq!(Err(EACCES), setattr(&mut server, 3, 0o777, 0));
+        assert_eq!(Err(EACCES), lcreate(&mut server, 2, "created"));
+        assert_eq!(Err(EACCES), readdir(&mut server, 2));
+        assert_eq!((b"secret".to_vec(), 0o644), directories.get_secret());
+        assert!(!directories.outside.join("created").exists());
+    }
+
+    #[test]
+    fn chmod_restores_the_permissions() {
+        let directories = Directories::new("chmod");
+        let file = directories.root.join("file");
+        let dir = directories.root.join("dir");
+        fs::write(&file, b"file").unwrap();
+        fs::create_dir(&dir).unwrap();
+        fs::set_permissions(&file, fs::Permissions::from_mode(0o000)).unwrap();
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o000)).unwrap();
+        let mut server = attach(&directories);
+        assert_eq!(Ok(1), walk(&mut server, ROOT_FID, 1, &["file"]));
+        assert_eq!(Ok(1), walk(&mut server, ROOT_FID, 2, &["dir"]));
+
+        // the files which can be neither read nor written can be changed back.
+        assert!(chmod(&mut server, 1, 0o644).is_ok());
+        assert!(chmod(&mut server, 2, 0o644).is_ok());
+        let get_mode = |path: &Path| fs::metadata(path).unwrap().permissions().mode() & 0o7777;
+        assert_eq!(0o644, get_mode(&file));
+        assert_eq!(0o644, get_mode(&dir));
+        assert!(chmod(&mut server, 2, 0o755).is_ok());
+        assert_eq!(0o755, get_mode(&dir));
+        assert_eq!(b"file".to_vec(), fs::read(&file).unwrap());
+    }
+}
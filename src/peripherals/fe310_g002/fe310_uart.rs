@@ -2,6 +2,7 @@
 // https://static.dev.sifive.com/FE310-G000.pdf
 
 use crate::console::Console;
+use crate::peripherals::scheduler::{Event, SharedScheduler};
 
 const UART_TXEN: u32 = 0x1;
 const UART_RXEN: u32 = 0x1;
@@ -9,6 +10,13 @@ const UART_RXEN: u32 = 0x1;
 const UART_TXWM: u32 = 0x1;
 const UART_RXWM: u32 = 0x2;
 
+// TODO: Correctly care for the clock frequency.
+// The current settings have no reason.
+/// cycles between the polls of the console.
+const RECEIVE_INTERVAL: u64 = 0xffff;
+/// a character is sent at a multiple of these cycles.
+const TRANSMIT_INTERVAL: u64 = 0xf;
+
 pub struct Fe310Uart {
     // /Transmit data register
     txdata: u32,
@@ -30,12 +38,17 @@ pub struct Fe310Uart {
     t_fifo: Vec<u8>,
     /// Terminal for serial console.
     console: Box<dyn Console>,
-    /// current clock cycle.
-    cycle: u64,
+    /// scheduler of the bus.
+    scheduler: SharedScheduler,
+    /// index of the UART in the bus, which tells the events of this UART.
+    index: usize,
 }
 
 impl Fe310Uart {
-    pub fn new(console_: Box<dyn Console>) -> Self {
+    pub fn new(console_: Box<dyn Console>, scheduler_: SharedScheduler, index_: usize) -> Self {
+        scheduler_
+            .borrow_mut()
+            .schedule(RECEIVE_INTERVAL, Event::UartReceive(index_));
         Fe310Uart {
             txdata: 0,
             rxdata: 0x8000_0000,
@@ -47,7 +60,8 @@ impl Fe310Uart {
             r_fifo: Vec::new(),
             t_fifo: Vec::new(),
             console: console_,
-            cycle: 0,
+            scheduler: scheduler_,
+            index: index_,
         }
     }
 
@@ -55,29 +69,44 @@ impl Fe310Uart {
         &mut self.console
     }
 
-    pub fn tick(&mut self) {
-        self.cycle = self.cycle.wrapping_add(1);
-
-        // TODO: Correctly care for the clock frequency.
-        // The current settings have no reason.
-
-        // receiver
-        if (self.cycle % 0xffff) == 0 {
-            if self.rxctrl & UART_RXEN > 0 {
-                match self.console.getchar() {
-                    0 => {}
-                    c => self.r_fifo.push(c),
-                }
+    /// [Event::UartReceive] poll the console, and schedule the next poll.
+    pub fn receive(&mut self) {
+        if self.rxctrl & UART_RXEN > 0 {
+            match self.console.getchar() {
+                0 => {}
+                c => self.r_fifo.push(c),
             }
-            self.update_recieve_interrupt_status();
         }
+        self.update_recieve_interrupt_status();
+
+        let mut scheduler = self.scheduler.borrow_mut();
+        let time = scheduler.get_time();
+        scheduler.schedule(time + RECEIVE_INTERVAL, Event::UartReceive(self.index));
+    }
 
-        // transmitter
-        if (self.cycle % 0xf) == 0 && (self.txctrl & UART_TXEN > 0) && self.t_fifo.len() > 0 {
+    /// [Event::UartTransmit] send a character of the transmitter FIFO, and schedule
+    /// the next one while the FIFO is not empty.
+    pub fn transmit(&mut self) {
+        if (self.txctrl & UART_TXEN > 0) && !self.t_fifo.is_empty() {
             self.console.putchar(self.t_fifo[0] as u8);
             self.t_fifo.remove(0);
             self.update_transmit_interrupt_status();
         }
+        if !self.t_fifo.is_empty() {
+            self.schedule_transmit();
+        }
+    }
+
+    /// schedule the transmit at the next multiple of TRANSMIT_INTERVAL.
+    fn schedule_transmit(&mut self) {
+        let mut scheduler = self.scheduler.borrow_mut();
+        if !scheduler.is_scheduled(Event::UartTransmit(self.index)) {
+            let time = scheduler.get_time();
+            scheduler.schedule(
+                (time / TRANSMIT_INTERVAL + 1) * TRANSMIT_INTERVAL,
+                Event::UartTransmit(self.index),
+            );
+        }
     }
 
     pub fn read(&mut self, addr: u64) -> u32 {
@@ -109,6 +138,7 @@ impl Fe310Uart {
                 let push_data = (data & 0xff) as u8;
                 self.t_fifo.push(push_data);
                 self.txdata = push_data as u32;
+                self.schedule_transmit();
             }
             0x08 => self.txctrl = data & 0x7_0003,
             0x0C => self.rxctrl = data & 0x7_0001,
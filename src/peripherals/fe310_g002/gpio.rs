@@ -62,10 +62,6 @@ impl Gpio {
         }
     }
 
-    pub fn tick(&mut self) {
-        // do nothing.
-    }
-
     pub fn is_irq(&mut self) -> bool {
         self.rise_ip != 0 || self.fall_ip != 0 || self.high_ip != 0 || self.low_ip != 0
     }
@@ -21,10 +21,6 @@ impl Prci {
         }
     }
 
-    pub fn tick(&mut self) {
-        // do nothing.
-    }
-
     pub fn read(&mut self, addr: u64) -> u32 {
         match addr & 0xff {
             0x00 => self.hfrosccfg | 0x8000_0000 /* OSC ready */,
@@ -1,11 +1,12 @@
 // Core Local Interruptor (CLINT)
 // https://static.dev.sifive.com/FU540-C000-v1.0.pdf
 
+use crate::peripherals::scheduler::{Event, SharedScheduler};
 use crate::peripherals::timer::Timer;
 
 pub struct Clint {
-    /// current clock cycle.
-    cycle: u64,
+    /// scheduler of the bus, whose time drives mtime.
+    scheduler: SharedScheduler,
     // Machine-mode software interrupts are generated by writing to the memory-mapped control register msip.
     // Each msip register is a 32-bit wide WARL register where the upper 31 bits are tied to
     // 0. The least significant bit is reflected in the MSIP bit of the mip CSR. Other bits in the msip
@@ -17,36 +18,68 @@ pub struct Clint {
     // equal to the value in the mtimecmp register. The timer interrupt is reflected in the mtip bit of the
     // mip register described in Chapter 8.
     mtimecmp: [u64; 5],
-    mtime: u64,
+    /// mtime is the time of the scheduler plus this offset, which is changed by
+    /// the writes to mtime.
+    mtime_offset: u64,
+    /// mtime ticks per second of the machine.
+    timebase_frequency: u64,
 }
 
 impl Clint {
-    pub fn new() -> Self {
+    pub fn new(scheduler_: SharedScheduler, timebase_frequency_: u64) -> Self {
         Clint {
-            cycle: 0,
+            scheduler: scheduler_,
             msip: [0; 5],
             mtimecmp: [0; 5],
-            mtime: 0,
+            mtime_offset: 0,
+            timebase_frequency: timebase_frequency_,
         }
     }
 
-    pub fn read_mtime(&self) -> u64 {
-        self.mtime
+    pub fn write_mtime(&mut self, data: u64) {
+        let time = self.scheduler.borrow().get_time();
+        self.mtime_offset = data.wrapping_sub(time);
+        for core in 0..self.mtimecmp.len() {
+            self.schedule_expiry(core);
+        }
     }
 
-    pub fn write_mtime(&mut self, data: u64) {
-        self.mtime = data
+    fn write_mtimecmp(&mut self, core: usize, data: u64) {
+        self.mtimecmp[core] = data;
+        self.schedule_expiry(core);
+    }
+
+    /// schedule the time when mtime reaches mtimecmp of the hart. The timer
+    /// interrupt is pending while mtime >= mtimecmp, so the event only tells
+    /// when the interrupt becomes pending.
+    fn schedule_expiry(&mut self, core: usize) {
+        let mtime = self.read_mtime();
+        let mut scheduler = self.scheduler.borrow_mut();
+        scheduler.cancel(Event::TimerExpiry(core));
+        if self.mtimecmp[core] != 0 && mtime < self.mtimecmp[core] {
+            let time = scheduler.get_time();
+            scheduler.schedule(
+                time.saturating_add(self.mtimecmp[core] - mtime),
+                Event::TimerExpiry(core),
+            );
+        }
     }
 }
 
 impl Timer for Clint {
-    fn tick(&mut self) {
-        self.cycle = self.cycle.wrapping_add(1);
+    // todo: Correctly care for the clock frequency (1MHz clock @ RTCCLK).
+    fn read_mtime(&self) -> u64 {
+        let time = self.scheduler.borrow().get_time();
+        time.wrapping_add(self.mtime_offset)
+    }
+
+    /// mtime counts the cycles of the scheduler.
+    fn get_mtime_after(&self, cycles: u64) -> u64 {
+        self.read_mtime().wrapping_add(cycles)
+    }
 
-        // todo: Correctly care for the clock frequency (1MHz clock @ RTCCLK).
-        //if self.cycle % 0xfffff == 0 {
-            self.mtime = self.mtime.wrapping_add(1);
-        //}
+    fn get_timebase_frequency(&self) -> u64 {
+        self.timebase_frequency
     }
 
     fn is_pending_software_interrupt(&mut self, core: usize) -> bool {
@@ -54,7 +87,7 @@ impl Timer for Clint {
     }
 
     fn is_pending_timer_interrupt(&mut self, core: usize) -> bool {
-        self.mtimecmp[core] != 0 && self.mtime >= self.mtimecmp[core]
+        self.mtimecmp[core] != 0 && self.read_mtime() >= self.mtimecmp[core]
     }
 
     fn read(&mut self, addr: u64) -> u32 {
@@ -74,8 +107,8 @@ impl Timer for Clint {
             0x401c => ((self.mtimecmp[3] >> 32) & 0xffffffff) as u32,
             0x4020 => (self.mtimecmp[4] & 0xffffffff) as u32,
             0x4024 => ((self.mtimecmp[4] >> 32) & 0xffffffff) as u32,
-            0xbff8 => (self.mtime & 0xffffffff) as u32,
-            0xbffc => ((self.mtime >> 32) & 0xffffffff) as u32,
+            0xbff8 => (self.read_mtime() & 0xffffffff) as u32,
+            0xbffc => ((self.read_mtime() >> 32) & 0xffffffff) as u32,
             n => panic!("Read reserved address: {:x}", n),
         }
     }
@@ -87,18 +120,38 @@ impl Timer for Clint {
             0x8 => self.msip[2] = data,
             0xc => self.msip[3] = data,
             0x10 => self.msip[4] = data,
-            0x4000 => self.mtimecmp[0] = (self.mtimecmp[0] & 0xffffffff_00000000) | data as u64,
-            0x4004 => self.mtimecmp[0] = (self.mtimecmp[0] & 0xffffffff) | ((data as u64) << 32),
-            0x4008 => self.mtimecmp[1] = (self.mtimecmp[1] & 0xffffffff_00000000) | data as u64,
-            0x400c => self.mtimecmp[1] = (self.mtimecmp[1] & 0xffffffff) | ((data as u64) << 32),
-            0x4010 => self.mtimecmp[2] = (self.mtimecmp[2] & 0xffffffff_00000000) | data as u64,
-            0x4014 => self.mtimecmp[2] = (self.mtimecmp[2] & 0xffffffff) | ((data as u64) << 32),
-            0x4018 => self.mtimecmp[3] = (self.mtimecmp[3] & 0xffffffff_00000000) | data as u64,
-            0x401c => self.mtimecmp[3] = (self.mtimecmp[3] & 0xffffffff) | ((data as u64) << 32),
-            0x4020 => self.mtimecmp[4] = (self.mtimecmp[4] & 0xffffffff_00000000) | data as u64,
-            0x4024 => self.mtimecmp[4] = (self.mtimecmp[4] & 0xffffffff) | ((data as u64) << 32),
-            0xbff8 => self.mtime = (self.mtime & 0xffffffff_00000000) | data as u64,
-            0xbffc => self.mtime = (self.mtime & 0xffffffff) | ((data as u64) << 32),
+            0x4000 => {
+                self.write_mtimecmp(0, (self.mtimecmp[0] & 0xffffffff_00000000) | data as u64)
+            }
+            0x4004 => {
+                self.write_mtimecmp(0, (self.mtimecmp[0] & 0xffffffff) | ((data as u64) << 32))
+            }
+            0x4008 => {
+                self.write_mtimecmp(1, (self.mtimecmp[1] & 0xffffffff_00000000) | data as u64)
+            }
+            0x400c => {
+                self.write_mtimecmp(1, (self.mtimecmp[1] & 0xffffffff) | ((data as u64) << 32))
+            }
+            0x4010 => {
+                self.write_mtimecmp(2, (self.mtimecmp[2] & 0xffffffff_00000000) | data as u64)
+            }
+            0x4014 => {
+                self.write_mtimecmp(2, (self.mtimecmp[2] & 0xffffffff) | ((data as u64) << 32))
+            }
+            0x4018 => {
+                self.write_mtimecmp(3, (self.mtimecmp[3] & 0xffffffff_00000000) | data as u64)
+            }
+            0x401c => {
+                self.write_mtimecmp(3, (self.mtimecmp[3] & 0xffffffff) | ((data as u64) << 32))
+            }
+            0x4020 => {
+                self.write_mtimecmp(4, (self.mtimecmp[4] & 0xffffffff_00000000) | data as u64)
+            }
+            0x4024 => {
+                self.write_mtimecmp(4, (self.mtimecmp[4] & 0xffffffff) | ((data as u64) << 32))
+            }
+            0xbff8 => self.write_mtime((self.read_mtime() & 0xffffffff_00000000) | data as u64),
+            0xbffc => self.write_mtime((self.read_mtime() & 0xffffffff) | ((data as u64) << 32)),
             n => panic!("Write reserved address: {:x}", n),
         }
     }
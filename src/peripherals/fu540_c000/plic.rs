@@ -37,6 +37,7 @@ pub struct Plic {
     sthreshold: [u32; PLIC_CORE_MAX],
     mclaim: [u32; PLIC_CORE_MAX],
     sclaim: [u32; PLIC_CORE_MAX],
+    claimed: u32,
 }
 
 impl Plic {
@@ -50,6 +51,23 @@ impl Plic {
             sthreshold: [0; PLIC_CORE_MAX],
             mclaim: [0; PLIC_CORE_MAX],
             sclaim: [0; PLIC_CORE_MAX],
+            claimed: 0,
+        }
+    }
+
+    /// An interrupt can be claimed by only one hart. The other harts read 0 from
+    /// the claim register until the interrupt is completed.
+    fn claim(&mut self, id: u32) -> u32 {
+        if id == 0 || ((self.claimed >> id) & 0x1) > 0 {
+            return 0;
+        }
+        self.claimed |= 1 << id;
+        id
+    }
+
+    fn complete(&mut self, id: u32) {
+        if id < 32 {
+            self.claimed &= !(1 << id);
         }
     }
 }
@@ -61,6 +79,10 @@ impl Intc for Plic {
         let mut irq_s = 0;
         let mut max_priority_s = 0;
         for id in interrupts {
+            // the interrupt claimed by a hart is not forwarded until its completion.
+            if ((self.claimed >> id) & 0x1) > 0 {
+                continue;
+            }
             if ((self.menable[core] >> id) & 0x1) > 0 {
                 if self.priority[id] > self.mthreshold[core] && self.priority[id] > max_priority_m {
                     irq_m = id as u32;
@@ -134,7 +156,7 @@ impl Intc for Plic {
                 } else {
                     if e_addr <= PLIC_MCLAIM_BASE + 0x2000 * PLIC_CORE_MAX as u64 {
                         let idx = ((e_addr - PLIC_MCLAIM_BASE) / 0x2000) as usize;
-                        return self.mclaim[idx];
+                        return self.claim(self.mclaim[idx]);
                     } else {
                         panic!("Write to reserved area: {:x}", addr);
                     }
@@ -150,7 +172,7 @@ impl Intc for Plic {
                 } else {
                     if e_addr <= PLIC_SCLAIM_BASE + 0x2000 * PLIC_CORE_MAX as u64 {
                         let idx = ((e_addr - PLIC_SCLAIM_BASE) / 0x2000) as usize;
-                        return self.sclaim[idx];
+                        return self.claim(self.sclaim[idx]);
                     } else {
                         panic!("Read to reserved area: {:x}", addr);
                     }
@@ -202,6 +224,7 @@ impl Intc for Plic {
                     if e_addr <= PLIC_MCLAIM_BASE + 0x2000 * PLIC_CORE_MAX as u64 {
                         let idx = ((e_addr - PLIC_MCLAIM_BASE) / 0x2000) as usize;
                         // clear the interrupt when it writes the same interrupt id to the register.
+                        self.complete(data);
                         if self.mclaim[idx] == data {
                             self.mclaim[idx] = 0;
                         }
@@ -221,6 +244,7 @@ impl Intc for Plic {
                     if e_addr <= PLIC_SCLAIM_BASE + 0x2000 * PLIC_CORE_MAX as u64 {
                         let idx = ((e_addr - PLIC_SCLAIM_BASE) / 0x2000) as usize;
                         // clear the interrupt when it writes the same interrupt id to the register.
+                        self.complete(data);
                         if self.sclaim[idx] == data {
                             self.sclaim[idx] = 0;
                         }
@@ -1,24 +1,55 @@
+use std::cell::Cell;
+use std::rc::Rc;
+
+const PAGE_SHIFT: usize = 12;
+
+/// write counters of the 4 KiB pages of a memory. They are shared with the decoded
+/// instruction caches of the harts, and an instruction decoded from a page is valid
+/// while the counter of the page is unchanged. Stores of the harts and DMA writes of
+/// the peripherals are counted in the same way since both write the memory here.
+pub type PageWrites = Rc<Vec<Cell<u64>>>;
+
 pub struct Memory {
     pub mem: Vec<u8>,
+    page_writes: PageWrites,
 }
 
 impl Memory {
     pub fn new(max_size: usize) -> Self {
+        let pages = (max_size + (1 << PAGE_SHIFT) - 1) >> PAGE_SHIFT;
         Self {
             mem: vec![0; max_size],
+            page_writes: Rc::new(vec![Cell::new(0); pages]),
         }
     }
 
     pub fn initialize(&mut self, data: Vec<u8>) {
+        self.count_writes(0, data.len());
         self.mem.splice(..data.len(), data.iter().cloned());
     }
 
+    pub fn get_page_writes(&self) -> PageWrites {
+        self.page_writes.clone()
+    }
+
+    fn count_writes(&self, index: usize, size: usize) {
+        if size == 0 {
+            return;
+        }
+        for page in (index >> PAGE_SHIFT)..=((index + size - 1) >> PAGE_SHIFT) {
+            let writes = &self.page_writes[page];
+            writes.set(writes.get() + 1);
+        }
+    }
+
     pub fn write8(&mut self, addr: u64, data: u8) {
+        self.count_writes(addr as usize, 1);
         self.mem[addr as usize] = data;
     }
 
     pub fn write16(&mut self, addr: u64, data: u16) {
         let index = addr as usize;
+        self.count_writes(index, 2);
         for i in 0..2 {
             self.mem[index + i] = ((data >> (i * 8)) & 0xff) as u8;
         }
@@ -26,6 +57,7 @@ impl Memory {
 
     pub fn write32(&mut self, addr: u64, data: u32) {
         let index = addr as usize;
+        self.count_writes(index, 4);
         for i in 0..4 {
             self.mem[index + i] = ((data >> (i * 8)) & 0xff) as u8;
         }
@@ -33,11 +65,33 @@ impl Memory {
 
     pub fn write64(&mut self, addr: u64, data: u64) {
         let index = addr as usize;
+        self.count_writes(index, 8);
         for i in 0..8 {
             self.mem[index + i] = ((data >> (i * 8)) & 0xff) as u8;
         }
     }
 
+    /// the memory has size bytes from addr.
+    pub fn contains(&self, addr: u64, size: u64) -> bool {
+        match addr.checked_add(size) {
+            Some(end) => end <= self.mem.len() as u64,
+            None => false,
+        }
+    }
+
+    /// copy the bytes from addr into data by a DMA of a peripheral.
+    pub fn read_bytes(&self, addr: u64, data: &mut [u8]) {
+        let index = addr as usize;
+        data.copy_from_slice(&self.mem[index..index + data.len()]);
+    }
+
+    /// copy data to addr by a DMA of a peripheral.
+    pub fn write_bytes(&mut self, addr: u64, data: &[u8]) {
+        let index = addr as usize;
+        self.count_writes(index, data.len());
+        self.mem[index..index + data.len()].copy_from_slice(data);
+    }
+
     pub fn read8(&self, addr: u64) -> u8 {
         let index = addr as usize;
         self.mem[index]
@@ -4,4 +4,10 @@ pub mod intc;
 pub mod timer;
 pub mod uart;
 pub mod virtio;
+pub mod virtio_9p;
+pub mod virtio_console;
+pub mod virtio_mmio;
+pub mod virtio_net;
+pub mod virtio_rng;
 pub mod memory;
+pub mod scheduler;
@@ -0,0 +1,254 @@
+// Timed-event queue of the devices
+
+use std::cell::RefCell;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::rc::Rc;
+
+/// The events of the devices. The index tells the instance of a device which the
+/// bus has more than one of.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Event {
+    /// poll the console for a received character.
+    UartReceive(usize),
+    /// send a character of the transmitter to the console.
+    UartTransmit(usize),
+    /// complete the oldest request notified to the virtio device.
+    VirtioCompletion,
+    /// poll the network for the frames to the virtio network device.
+    VirtioNetReceive,
+    /// send the frames of the transmit queue of the virtio network device.
+    VirtioNetTransmit,
+    /// poll the consoles of the ports of the virtio console device.
+    VirtioConsoleReceive,
+    /// write the transmit queues of the virtio console device to the consoles.
+    VirtioConsoleTransmit,
+    /// fill the requests of the virtio entropy device.
+    VirtioRngCompletion,
+    /// handle the requests of the virtio 9P device.
+    Virtio9pCompletion,
+    /// mtime of the CLINT reaches mtimecmp of the hart.
+    TimerExpiry(usize),
+}
+
+/// The scheduler is shared by the bus and its devices. time is the number of cycles
+/// that the bus has run, and the devices schedule the events at a future time
+/// instead of being ticked every cycle. The events at the same time run in the
+/// order in which they are scheduled, so the timing seen by the devices only
+/// depends on the instruction stream.
+pub struct Scheduler {
+    time: u64,
+    sequence: u64,
+    events: BinaryHeap<Reverse<(u64, u64, Event)>>,
+}
+
+pub type SharedScheduler = Rc<RefCell<Scheduler>>;
+
+impl Scheduler {
+    pub fn new() -> Self {
+        Scheduler {
+            time: 0,
+            sequence: 0,
+            events: BinaryHeap::new(),
+        }
+    }
+
+    pub fn new_shared() -> SharedScheduler {
+        Rc::new(RefCell::new(Scheduler::new()))
+    }
+
+    pub fn get_time(&self) -> u64 {
+        self.time
+    }
+
+    /// schedule the event at time, which is no earlier than the current time.
+    pub fn schedule(&mut self, time: u64, event: Event) {
+        let time = time.max(self.time);
+        self.events.push(Reverse((time, self.sequence, event)));
+        self.sequence = self.sequence.wrapping_add(1);
+    }
+
+    pub fn cancel(&mut self, event: Event) {
+        self.events.retain(|Reverse((_, _, e))| *e != event);
+    }
+
+    pub fn is_scheduled(&self, event: Event) -> bool {
+        self.events.iter().any(|Reverse((_, _, e))| *e == event)
+    }
+
+    /// the time of the earliest event.
+    pub fn get_next_event_time(&self) -> Option<u64> {
+        self.events.peek().map(|Reverse((time, _, _))| *time)
+    }
+
+    /// take the earliest event scheduled no later than time, and advance the
+    /// current time to the event. None after the current time has reached time.
+    pub fn pop(&mut self, time: u64) -> Option<Event> {
+        match self.events.peek() {
+            Some(Reverse((event_time, _, _))) if *event_time <= time => {
+                let Reverse((event_time, _, event)) = self.events.pop().unwrap();
+                self.time = event_time;
+                Some(event)
+            }
+            _ => {
+                self.time = time;
+                None
+            }
+        }
+    }
+}
+
+impl Default for Scheduler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pop_all(scheduler: &mut Scheduler, time: u64) -> Vec<(u64, Event)> {
+        let mut events = vec![];
+        while let Some(event) = scheduler.pop(time) {
+            events.push((scheduler.get_time(), event));
+        }
+        events
+    }
+
+    #[test]
+    fn events_run_in_time_order() {
+        let mut scheduler = Scheduler::new();
+        scheduler.schedule(30, Event::UartReceive(0));
+        scheduler.schedule(10, Event::VirtioCompletion);
+        scheduler.schedule(20, Event::TimerExpiry(1));
+        assert_eq!(Some(10), scheduler.get_next_event_time());
+        assert_eq!(
+            vec![
+                (10, Event::VirtioCompletion),
+                (20, Event::TimerExpiry(1)),
+                (30, Event::UartReceive(0)),
+            ],
+            pop_all(&mut scheduler, 100)
+        );
+        assert_eq!(100, scheduler.get_time());
+        assert_eq!(None, scheduler.get_next_event_time());
+    }
+
+    #[test]
+    fn events_at_the_same_time_run_in_scheduled_order() {
+        let mut scheduler = Scheduler::new();
+        scheduler.schedule(5, Event::VirtioNetTransmit);
+        scheduler.schedule(5, Event::UartTransmit(1));
+        scheduler.schedule(5, Event::UartTransmit(0));
+        assert_eq!(
+            vec![
+                (5, Event::VirtioNetTransmit),
+                (5, Event::UartTransmit(1)),
+                (5, Event::UartTransmit(0)),
+            ],
+            pop_all(&mut scheduler, 5)
+        );
+    }
+
+    #[test]
+    fn pop_stops_at_time() {
+        let mut scheduler = Scheduler::new();
+        scheduler.schedule(10, Event::VirtioCompletion);
+        scheduler.schedule(11, Event::VirtioRngCompletion);
+        assert_eq!(
+            vec![(10, Event::VirtioCompletion)],
+            pop_all(&mut scheduler, 10)
+        );
+        assert_eq!(10, scheduler.get_time());
+        assert_eq!(None, scheduler.pop(10));
+        assert_eq!(Some(Event::VirtioRngCompletion), scheduler.pop(11));
+        assert_eq!(11, scheduler.get_time());
+    }
+
+    #[test]
+    fn event_in_the_past_runs_at_the_current_time() {
+        let mut scheduler = Scheduler::new();
+        assert_eq!(None, scheduler.pop(50));
+        scheduler.schedule(20, Event::Virtio9pCompletion);
+        assert_eq!(Some(50), scheduler.get_next_event_time());
+        assert_eq!(
+            vec![(50, Event::Virtio9pCompletion)],
+            pop_all(&mut scheduler, 50)
+        );
+    }
+
+    #[test]
+    fn cancel_removes_every_instance_of_the_event() {
+        let mut scheduler = Scheduler::new();
+        scheduler.schedule(10, Event::TimerExpiry(0));
+        scheduler.schedule(20, Event::TimerExpiry(1));
+        scheduler.schedule(30, Event::TimerExpiry(0));
+        assert!(scheduler.is_scheduled(Event::TimerExpiry(0)));
+        scheduler.cancel(Event::TimerExpiry(0));
+        assert!(!scheduler.is_scheduled(Event::TimerExpiry(0)));
+        assert!(scheduler.is_scheduled(Event::TimerExpiry(1)));
+        assert_eq!(
+            vec![(20, Event::TimerExpiry(1))],
+            pop_all(&mut scheduler, 100)
+        );
+    }
+
+    #[test]
+    fn rescheduled_event_moves_to_the_new_time() {
+        let mut scheduler = Scheduler::new();
+        scheduler.schedule(10, Event::TimerExpiry(0));
+        scheduler.schedule(20, Event::UartReceive(0));
+        scheduler.cancel(Event::TimerExpiry(0));
+        scheduler.schedule(30, Event::TimerExpiry(0));
+        assert_eq!(Some(20), scheduler.get_next_event_time());
+        assert_eq!(
+            vec![(20, Event::UartReceive(0)), (30, Event::TimerExpiry(0))],
+            pop_all(&mut scheduler, 100)
+        );
+
+        // an event scheduled while the events are run is run in the same pass.
+        scheduler.schedule(110, Event::VirtioConsoleReceive);
+        let mut events = vec![];
+        while let Some(event) = scheduler.pop(200) {
+            if event == Event::VirtioConsoleReceive && events.is_empty() {
+                scheduler.schedule(scheduler.get_time() + 40, event);
+            }
+            events.push((scheduler.get_time(), event));
+        }
+        assert_eq!(
+            vec![
+                (110, Event::VirtioConsoleReceive),
+                (150, Event::VirtioConsoleReceive)
+            ],
+            events
+        );
+        assert_eq!(200, scheduler.get_time());
+    }
+
+    #[test]
+    fn same_schedule_runs_the_same_events() {
+        let run = || {
+            let mut scheduler = Scheduler::new();
+            let mut events = vec![];
+            for step in 0..64u64 {
+                let event = match step % 4 {
+                    0 => Event::UartReceive((step % 3) as usize),
+                    1 => Event::VirtioNetReceive,
+                    2 => Event::TimerExpiry((step % 2) as usize),
+                    _ => Event::VirtioConsoleTransmit,
+                };
+                scheduler.schedule(step * 7 % 23 + step, event);
+                if step % 5 == 0 {
+                    events.extend(pop_all(&mut scheduler, step * 2));
+                }
+            }
+            events.extend(pop_all(&mut scheduler, u64::MAX));
+            events
+        };
+        let events = run();
+        assert_eq!(64, events.len());
+        assert!(events.windows(2).all(|w| w[0].0 <= w[1].0));
+        assert_eq!(events, run());
+    }
+}
@@ -1,5 +1,9 @@
 pub trait Timer {
-    fn tick(&mut self);
+    fn read_mtime(&self) -> u64;
+    /// the value of mtime after the bus runs cycles.
+    fn get_mtime_after(&self, cycles: u64) -> u64;
+    /// mtime ticks per second, which is timebase-frequency of the device tree.
+    fn get_timebase_frequency(&self) -> u64;
     fn is_pending_software_interrupt(&mut self, core: usize) -> bool;
     fn is_pending_timer_interrupt(&mut self, core: usize) -> bool;
     fn read(&mut self, addr: u64) -> u32;
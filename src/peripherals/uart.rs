@@ -2,6 +2,7 @@
 // http://byterunner.com/16550.html
 
 use crate::console::Console;
+use crate::peripherals::scheduler::{Event, SharedScheduler};
 
 const IER_DATA_READY: u8 = 0x01;
 const IER_THR_EMPTY: u8 = 0x02;
@@ -14,6 +15,13 @@ const LCR_DIVISOR_LATCH_ENABLE: u8 = 0x80;
 const LSR_DATA_READY: u8 = 0x01;
 const LSR_THR_EMPTY: u8 = 0x20;
 
+// TODO: Correctly care for the clock frequency (1MHz clock @ RTCCLK).
+// The current settings have no reason.
+/// cycles between the polls of the console.
+const RECEIVE_INTERVAL: u64 = 0x10000;
+/// a character is sent at a multiple of these cycles.
+const TRANSMIT_INTERVAL: u64 = 0x10;
+
 pub struct Uart {
     /// Receive Hold Register, RO
     rhr: u8,
@@ -37,12 +45,15 @@ pub struct Uart {
     spr: u8,
     /// Terminal for serial console.
     console: Box<dyn Console>,
-    /// current clock cycle.
-    cycle: u64,
+    /// scheduler of the bus.
+    scheduler: SharedScheduler,
 }
 
 impl Uart {
-    pub fn new(console_: Box<dyn Console>) -> Self {
+    pub fn new(console_: Box<dyn Console>, scheduler_: SharedScheduler) -> Self {
+        scheduler_
+            .borrow_mut()
+            .schedule(RECEIVE_INTERVAL, Event::UartReceive(0));
         Uart {
             rhr: 0,
             thr: 0,
@@ -55,7 +66,7 @@ impl Uart {
             msr: 0,
             spr: 0,
             console: console_,
-            cycle: 0,
+            scheduler: scheduler_,
         }
     }
 
@@ -63,13 +74,9 @@ impl Uart {
         &mut self.console
     }
 
-    pub fn tick(&mut self) {
-        self.cycle = self.cycle.wrapping_add(1);
-
-        // TODO: Correctly care for the clock frequency (1MHz clock @ RTCCLK).
-        // The current settings have no reason.
-        // receiver
-        if (self.cycle & 0xffff) == 0 && self.rhr == 0 {
+    /// [Event::UartReceive] poll the console, and schedule the next poll.
+    pub fn receive(&mut self) {
+        if self.rhr == 0 {
             match self.console.getchar() {
                 0 => {}
                 c => {
@@ -78,9 +85,14 @@ impl Uart {
                 }
             }
         }
+        let mut scheduler = self.scheduler.borrow_mut();
+        let time = scheduler.get_time();
+        scheduler.schedule(time + RECEIVE_INTERVAL, Event::UartReceive(0));
+    }
 
-        // transmitter
-        if (self.cycle & 0xf) == 0 && self.thr != 0 {
+    /// [Event::UartTransmit] send the character of the transmit hold register.
+    pub fn transmit(&mut self) {
+        if self.thr != 0 {
             self.console.putchar(self.thr);
             self.thr = 0;
             self.lsr |= LSR_THR_EMPTY;
@@ -118,6 +130,12 @@ impl Uart {
                 if self.lcr & LCR_DIVISOR_LATCH_ENABLE == 0 {
                     self.thr = data;
                     self.lsr &= !LSR_THR_EMPTY;
+                    let mut scheduler = self.scheduler.borrow_mut();
+                    if !scheduler.is_scheduled(Event::UartTransmit(0)) {
+                        let time = scheduler.get_time();
+                        scheduler
+                            .schedule((time | (TRANSMIT_INTERVAL - 1)) + 1, Event::UartTransmit(0));
+                    }
                 }
             }
             1 => {
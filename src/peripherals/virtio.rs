@@ -1,352 +1,435 @@
-// Virtio (Virtual I/O Device)
-// https://docs.oasis-open.org/virtio/virtio/v1.1/csprd01/virtio-v1.1-csprd01.html
+// Virtio (Virtual I/O Device) Block Device
+// https://docs.oasis-open.org/virtio/virtio/v1.1/csprd01/virtio-v1.1-csprd01.html#x1-2390002
 // https://github.com/mit-pdos/xv6-riscv/blob/riscv/kernel/virtio_disk.c
 // https://syuu1228.github.io/howto_implement_hypervisor/part12.html
 // https://syuu1228.github.io/howto_implement_hypervisor/part20.html
 
+use std::ops::Range;
+
 use crate::peripherals::memory::Memory;
+use crate::peripherals::scheduler::{Event, SharedScheduler};
+use crate::peripherals::virtio_mmio::*;
 
 const CONFIG_QUEUE_NUM_MAX: u32 = 0x1000; // Linux boot fails if the value is too small.
 const CONFIG_DISK_SECTOR_SIZE: u64 = 512;
 const CONFIG_DMA_DELAY: u64 = 128;
+/// the serial number of the disk for VIRTIO_BLK_T_GET_ID.
+const CONFIG_DISK_ID: &str = "riscv-emu-disk";
 
-const VIRTIO_MAGIC_VALUE: u64 = 0x000;
-const VIRTIO_VERSION: u64 = 0x004;
-const VIRTIO_DEVICE_ID: u64 = 0x008;
-const VIRTIO_VENDOR_ID: u64 = 0x00c;
-const VIRTIO_DEVICE_FEATURES: u64 = 0x010;
-const VIRTIO_DEVICE_FEATURES_SEL: u64 = 0x014;
-const VIRTIO_DRIVER_FEATURES: u64 = 0x020;
-const VIRTIO_DRIVER_FEATURES_SEL: u64 = 0x024;
-const VIRTIO_GUEST_PAGE_SIZE: u64 = 0x028;
-const VIRTIO_QUEUE_SEL: u64 = 0x030;
-const VIRTIO_QUEUE_NUM_MAX: u64 = 0x034;
-const VIRTIO_QUEUE_NUM: u64 = 0x038;
-const VIRTIO_QUEUE_ALIGIN: u64 = 0x03c;
-const VIRTIO_QUEUE_PFN: u64 = 0x040;
-const VIRTIO_QUEUE_NOTIFY: u64 = 0x050;
-const VIRTIO_INTERRUPT_STATUS: u64 = 0x060;
-const VIRTIO_INTERRUPT_ACK: u64 = 0x64;
-const VIRTIO_DEVICE_STATUS: u64 = 0x070;
-const VIRTIO_CONFIG_SPACE0: u64 = 0x100;
-const VIRTIO_CONFIG_SPACE1: u64 = 0x104;
-
-const VIRTIO_INTERRUPT_QUEUE: u32 = 0x1;
-const VIRTIO_INTERRUPT_CONFIGURATION: u32 = 0x2;
-
-// Descriptor flags
-const DESCRIPTOR_SIZE: u64 = 16;
-const VRING_DESC_F_NEXT: u16 = 0x1;
-const VRING_DESC_F_WRITE: u16 = 0x2;
-const _VRING_DESC_F_INDIRECT: u16 = 0x4;
+// Feature bits
+const VIRTIO_BLK_F_RO: u64 = 1 << 5;
+const VIRTIO_BLK_F_FLUSH: u64 = 1 << 9;
+const DEVICE_FEATURES: u64 = VIRTIO_BLK_F_FLUSH | VIRTIO_RING_F_INDIRECT_DESC;
 
-const OK: u8 = 0;
-const _IOERR: u8 = 1;
-const _UNSUPP: u8 = 2;
+// Request types
+const VIRTIO_BLK_T_IN: u32 = 0;
+const VIRTIO_BLK_T_OUT: u32 = 1;
+const VIRTIO_BLK_T_FLUSH: u32 = 4;
+const VIRTIO_BLK_T_GET_ID: u32 = 8;
+const VIRTIO_BLK_ID_BYTES: usize = 20;
 
-struct Virtqueue {
-    descriptor_table_head: u64,
-    available_ring_head: u64,
-    used_ring_head: u64,
-}
+/// struct virtio_blk_outhdr: u32 type, u32 reserved, u64 sector.
+const REQUEST_HEADER_SIZE: usize = 16;
 
-struct Descriptor {
-    addr: u64,
-    len: u32,
-    flags: u16,
-    next: u64,
-}
+// Request status
+const OK: u8 = 0;
+const IOERR: u8 = 1;
+const UNSUPP: u8 = 2;
+
+const REQUESTQ: usize = 0;
 
 pub struct Virtio {
-    /// current clock cycle.
-    cycle: u64,
-    /// real user disk data.
-    disk_image: Vec<u64>,
-    /// last available ring index
-    last_available_idx: u64,
-    /// Main Memory Base Address
-    dram_base_addr: u64,
-
-    /// Device (host) features word selection (WO)
-    device_features_sel: u32,
-    ///	Flags representing device features understood and activated by the driver (WO)
-    driver_features: u32,
-    /// Activated (guest) features word selection (WO)
-    driver_features_sel: u32,
-    /// Guest page size (WO)
-    guest_page_size: u32,
-    /// Virtual queue index (WO)
-    queue_sel: u32,
-    /// Virtual queue size (WO)
-    queue_num: u32,
-    /// Used Ring alignment in the virtual queue (WO)
-    queue_align: u32,
-    /// Guest physical page number of the virtual queue (R/W)
-    queue_pfn: u32,
-    /// Queue notifier (WO)
-    queue_notify: Vec<u64>,
-    /// Interrupt status (RO)
-    interrupt_status: u32,
-    ///	Device status (R/W)
-    device_status: u32,
-    /// Configuration space (R/W)
-    config_space: Vec<u32>,
+    transport: VirtioMmio,
+    /// scheduler of the bus.
+    scheduler: SharedScheduler,
+    /// real user disk data. The device is absent without it.
+    disk_image: Vec<u8>,
+    /// the driver cannot write the disk.
+    read_only: bool,
 }
 
 impl Virtio {
-    pub fn new(dram_base_addr_: u64) -> Self {
+    pub fn new(dram_base_addr: u64, scheduler_: SharedScheduler) -> Self {
         Virtio {
-            cycle: 0,
+            transport: VirtioMmio::new(
+                dram_base_addr,
+                VIRTIO_DEVICE_ID_BLOCK,
+                DEVICE_FEATURES,
+                &[CONFIG_QUEUE_NUM_MAX],
+            ),
+            scheduler: scheduler_,
             disk_image: vec![],
-            last_available_idx: 0,
-            dram_base_addr: dram_base_addr_,
-            device_features_sel: 0,
-            driver_features: 0,
-            driver_features_sel: 0,
-            guest_page_size: 0,
-            queue_sel: 0,
-            queue_num: 0,
-            queue_align: 0x1000, // TODO: check the reson.
-            queue_pfn: 0,
-            queue_notify: Vec::new(),
-            interrupt_status: 0,
-            device_status: 0,
-            config_space: vec![0x20000, 0], // todo: block count?
+            read_only: false,
         }
     }
 
     pub fn init(&mut self, data: Vec<u8>) {
-        self.disk_image.resize((data.len() + 7) / 8, 0);
-        for i in 0..data.len() {
-            let idx = (i >> 3) as usize;
-            let pos = (i % 8) * 8;
-            self.disk_image[idx] |= (data[i] as u64) << pos;
-        }
+        self.disk_image = data;
     }
 
-    pub fn tick(&mut self, dram: &mut Memory) {
-        self.cycle = self.cycle.wrapping_add(1);
-
-        // If an interrupt is generated immediately, it will not operate normally,
-        // so it is necessary to set a delay time.
-        if self.queue_notify.len() > 0 && (self.cycle == self.queue_notify[0] + CONFIG_DMA_DELAY) {
-            self.transfer(dram);
-            self.interrupt_status |= VIRTIO_INTERRUPT_QUEUE;
-            self.queue_notify.remove(0);
-        }
+    /// forbid the writes of the driver, and tell it by VIRTIO_BLK_F_RO.
+    pub fn set_read_only(&mut self, read_only_: bool) {
+        self.read_only = read_only_;
+        self.transport.set_device_features(match read_only_ {
+            true => DEVICE_FEATURES | VIRTIO_BLK_F_RO,
+            false => DEVICE_FEATURES,
+        });
     }
 
     pub fn is_irq(&mut self) -> bool {
-        self.interrupt_status & 0x3 > 0
+        self.transport.is_irq()
     }
 
-    pub fn read(&mut self, addr: u64) -> u32 {
-        match addr {
-            VIRTIO_MAGIC_VALUE => 0x74726976, // "virt" string
-            VIRTIO_VERSION => 0x1,            // Legacy device returns value 0x1.
-            VIRTIO_DEVICE_ID => 0x2,          // device type; 1 is net, 2 is disk
-            VIRTIO_VENDOR_ID => 0x554d4551,   // from xv6-riscv source code.
-            VIRTIO_DEVICE_FEATURES => self.device_features_sel,
-            VIRTIO_QUEUE_NUM_MAX => CONFIG_QUEUE_NUM_MAX,
-            VIRTIO_QUEUE_PFN => self.queue_pfn,
-            VIRTIO_INTERRUPT_STATUS => self.interrupt_status,
-            VIRTIO_DEVICE_STATUS => self.device_status,
-            // Device-specific configuration space starts at the offset 0x100 and is accessed with byte alignment.
-            // Its meaning and size depend on the device and the driver.
-            VIRTIO_CONFIG_SPACE0 => self.config_space[0],
-            VIRTIO_CONFIG_SPACE1 => self.config_space[1],
-            _ => panic!("Read to reserved area: {:x}", addr),
+    /// [Event::VirtioCompletion] process the requests of the request queue, and
+    /// raise the interrupt.
+    pub fn complete(&mut self, dram: &mut Memory) {
+        let mut used = false;
+        while let Some(chain) = self.transport.pop(dram, REQUESTQ) {
+            let len = self.transfer(dram, &chain);
+            self.transport.push(dram, REQUESTQ, &chain, len as u32);
+            used = true;
+        }
+        if used {
+            self.transport.interrupt_queue();
         }
     }
 
-    pub fn write(&mut self, addr: u64, data: u32) {
-        match addr {
-            VIRTIO_DEVICE_FEATURES_SEL => self.device_features_sel = data,
-            VIRTIO_DRIVER_FEATURES => self.driver_features = data,
-            VIRTIO_DRIVER_FEATURES_SEL => self.driver_features_sel = data,
-            VIRTIO_GUEST_PAGE_SIZE => self.guest_page_size = data,
-            VIRTIO_QUEUE_SEL => self.queue_sel = data,
-            VIRTIO_QUEUE_NUM => self.queue_num = data,
-            VIRTIO_QUEUE_ALIGIN => self.queue_align = data,
-            VIRTIO_QUEUE_PFN => self.queue_pfn = data,
-            VIRTIO_QUEUE_NOTIFY => self.queue_notify.push(self.cycle),
-            VIRTIO_INTERRUPT_ACK => {
-                if data & VIRTIO_INTERRUPT_QUEUE > 0 {
-                    self.interrupt_status &= !VIRTIO_INTERRUPT_QUEUE;
-                }
-                if data & VIRTIO_INTERRUPT_CONFIGURATION > 0 {
-                    self.interrupt_status &= !VIRTIO_INTERRUPT_CONFIGURATION;
-                }
+    /// the number of the sectors of the disk.
+    fn get_capacity(&self) -> u64 {
+        self.disk_image.len() as u64 / CONFIG_DISK_SECTOR_SIZE
+    }
+
+    /// the bytes of the disk image from the sector, or None if they are beyond the
+    /// capacity.
+    fn get_range(&self, sector: u64, size: usize) -> Option<Range<usize>> {
+        let start = match sector.checked_mul(CONFIG_DISK_SECTOR_SIZE) {
+            Some(start) => start,
+            None => return None,
+        };
+        match start.checked_add(size as u64) {
+            Some(end) if end <= self.get_capacity() * CONFIG_DISK_SECTOR_SIZE => {
+                Some(start as usize..end as usize)
             }
-            VIRTIO_DEVICE_STATUS => self.device_status = data,
-            VIRTIO_CONFIG_SPACE0 => self.config_space[0] = data,
-            VIRTIO_CONFIG_SPACE1 => self.config_space[1] = data,
-            _ => panic!("Write to reserved area: {:x}", addr),
+            _ => None,
         }
     }
 
-    fn transfer(&mut self, dram: &mut Memory) {
-        let queue_size = self.queue_num as u64;
-        let vq = self.get_virtqueue();
-
-        // get latest entry idx from available ring.
-        /* Available Ring
-         * ----------------
-         * u16 flags
-         * u16 idx
-         * u16[QUEUE_NUM] ring
-         * u16 used_event
-         */
-        let descriptor_idx = (dram.read16(
-            vq.available_ring_head
-                .wrapping_add(4 + self.last_available_idx * 2),
-        ) as u64)
-            % queue_size;
-
-        // first descriptor (virtio_blk_outhdr)
-        let descriptor0 = self.get_descriptor(dram, vq.descriptor_table_head, descriptor_idx);
-        let sector_idx = dram.read64(descriptor0.addr.wrapping_add(8));
-
-        // Read/Write disk
-        let descriptor1 = self.get_descriptor(dram, vq.descriptor_table_head, descriptor0.next);
-        let disk_addr = sector_idx * CONFIG_DISK_SECTOR_SIZE;
-        if (descriptor1.flags & VRING_DESC_F_WRITE) == 0 {
-            // write only from Host side.
-            if (descriptor1.addr % 8) == 0 && (descriptor1.len % 8) == 0 && (disk_addr % 8) == 0 {
-                self.dma_memory_to_disk(dram, descriptor1.addr, disk_addr, descriptor1.len as u64);
-            } else {
-                for i in 0..descriptor1.len as u64 {
-                    let data = dram.read8(descriptor1.addr + i);
-                    self.write_disk8(disk_addr + i, data);
-                }
+    /// process a request, and return the number of the bytes written to it. The
+    /// header is device-readable, and the status is the last device-writable byte.
+    fn transfer(&mut self, dram: &mut Memory, chain: &DescriptorChain) -> usize {
+        let writable_size = chain.get_writable_size();
+        if writable_size == 0 {
+            // the request has no room for the status.
+            return 0;
+        }
+        let data_size = writable_size - 1;
+        let request = chain.read(dram);
+        let (mut response, status) = match request.len() >= REQUEST_HEADER_SIZE {
+            true => {
+                let request_type =
+                    u32::from_le_bytes([request[0], request[1], request[2], request[3]]);
+                let mut sector = [0; 8];
+                sector.copy_from_slice(&request[8..REQUEST_HEADER_SIZE]);
+                let sector = u64::from_le_bytes(sector);
+                let data = &request[REQUEST_HEADER_SIZE..];
+                self.execute(request_type, sector, data, data_size)
             }
-        } else {
-            // read only from Host side.
-            if (descriptor1.addr % 8) == 0 && (descriptor1.len % 8) == 0 && (disk_addr % 8) == 0 {
-                self.dma_disk_to_memory(dram, descriptor1.addr, disk_addr, descriptor1.len as u64);
-            } else {
-                for i in 0..descriptor1.len as u64 {
-                    let data = self.read_disk8(disk_addr + i);
-                    dram.write8(descriptor1.addr + i, data);
+            false => (vec![], IOERR),
+        };
+        response.resize(data_size, 0);
+        response.push(status);
+        chain.write(dram, &response)
+    }
+
+    /// execute the request, and return the data for the driver and the status.
+    fn execute(
+        &mut self,
+        request_type: u32,
+        sector: u64,
+        data: &[u8],
+        data_size: usize,
+    ) -> (Vec<u8>, u8) {
+        match request_type {
+            VIRTIO_BLK_T_IN => match self.get_range(sector, data_size) {
+                Some(range) => (self.disk_image[range].to_vec(), OK),
+                None => (vec![], IOERR),
+            },
+            VIRTIO_BLK_T_OUT => match self.get_range(sector, data.len()) {
+                Some(_) if self.read_only => (vec![], IOERR),
+                Some(range) => {
+                    self.disk_image[range].copy_from_slice(data);
+                    (vec![], OK)
                 }
+                None => (vec![], IOERR),
+            },
+            // the disk image is in the memory, so the writes are already done.
+            VIRTIO_BLK_T_FLUSH => (vec![], OK),
+            VIRTIO_BLK_T_GET_ID => {
+                let mut id = CONFIG_DISK_ID.as_bytes().to_vec();
+                id.resize(VIRTIO_BLK_ID_BYTES, 0);
+                id.truncate(data_size);
+                (id, OK)
             }
+            _ => (vec![], UNSUPP),
         }
+    }
 
-        // put result.
-        {
-            let descriptor2 = self.get_descriptor(dram, vq.descriptor_table_head, descriptor1.next);
-            dram.write8(descriptor2.addr, OK);
-            debug_assert!(
-                (descriptor2.flags & VRING_DESC_F_NEXT) != 0,
-                "Thrid descriptor is not last entry: {:x}",
-                descriptor2.flags
-            );
-            debug_assert!(
-                (descriptor2.flags & VRING_DESC_F_WRITE) == 0,
-                "Thrid descriptor is not write type: {:x}",
-                descriptor2.flags
-            );
-            debug_assert!(
-                descriptor2.len != 1,
-                "Thrid descriptor len is more than one: {:x}",
-                descriptor2.flags
-            );
+    pub fn read(&mut self, addr: u64) -> u32 {
+        match addr {
+            // the device ID 0 tells the driver that the slot is empty.
+            VIRTIO_DEVICE_ID if self.disk_image.is_empty() => 0,
+            // Device-specific configuration space starts at the offset 0x100.
+            // It has the capacity in the sectors.
+            _ if addr >= VIRTIO_CONFIG_SPACE => read_config(
+                &self.get_capacity().to_le_bytes(),
+                addr - VIRTIO_CONFIG_SPACE,
+            ),
+            _ => self.transport.read(addr),
         }
+    }
 
-        // update used ring.
-        {
-            // put latest entry idx to used ring.
-            /* Used Ring
-             * ----------------
-             * u16 flags
-             * u16 idx
-             * UsedRingEntry[QUEUE_NUM] ring
-             * u16 avail_event
-             */
-            dram.write32(
-                vq.used_ring_head
-                    .wrapping_add(4 + self.last_available_idx * 8),
-                descriptor_idx as u32,
-            );
-
-            // update latest entry of used ring.
-            self.last_available_idx = self.last_available_idx.wrapping_add(1) % queue_size;
-            dram.write16(
-                vq.used_ring_head.wrapping_add(2),
-                self.last_available_idx as u16,
-            );
+    pub fn write(&mut self, addr: u64, data: u32) {
+        match addr {
+            VIRTIO_QUEUE_NOTIFY => {
+                // If an interrupt is generated immediately, it will not operate normally,
+                // so it is necessary to set a delay time.
+                let mut scheduler = self.scheduler.borrow_mut();
+                if !scheduler.is_scheduled(Event::VirtioCompletion) {
+                    let time = scheduler.get_time();
+                    scheduler.schedule(time + CONFIG_DMA_DELAY, Event::VirtioCompletion);
+                }
+            }
+            // the capacity is read-only.
+            _ if addr >= VIRTIO_CONFIG_SPACE => {}
+            _ => self.transport.write(addr, data),
         }
     }
+}
 
-    fn get_virtqueue(&mut self) -> Virtqueue {
-        let queue_size = self.queue_num as u64;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::peripherals::scheduler::Scheduler;
+
+    const DRAM_BASE: u64 = 0x8000_0000;
+    const QUEUE_NUM: u16 = 8;
+    const VRING_DESC_F_NEXT: u16 = 0x1;
+    const VRING_DESC_F_WRITE: u16 = 0x2;
+    const VRING_DESC_F_INDIRECT: u16 = 0x4;
+    const SECTORS: usize = 4;
+
+    /// the driver of the guest. The queue takes two pages from 0x2000 of the memory,
+    /// the indirect descriptor table is at 0x8000, and the buffers of the requests
+    /// are from 0x10000.
+    struct Driver {
+        device: Virtio,
+        scheduler: SharedScheduler,
+        dram: Memory,
+        available_idx: u16,
+        used_idx: u16,
+    }
 
-        // descriptor table head address
-        let v_desc_table_addr;
-        {
-            let page_addr = self.queue_pfn as u64 * self.guest_page_size as u64;
-            v_desc_table_addr = page_addr.wrapping_sub(self.dram_base_addr);
+    impl Driver {
+        fn new(read_only: bool) -> Self {
+            let scheduler = Scheduler::new_shared();
+            let mut device = Virtio::new(DRAM_BASE, scheduler.clone());
+            let disk = (0..SECTORS * CONFIG_DISK_SECTOR_SIZE as usize)
+                .map(|i| (i / CONFIG_DISK_SECTOR_SIZE as usize) as u8 + 1)
+                .collect();
+            device.init(disk);
+            device.set_read_only(read_only);
+            device.write(0x024, 0); // DriverFeaturesSel
+            device.write(0x020, DEVICE_FEATURES as u32); // DriverFeatures
+            device.write(0x028, 0x1000); // GuestPageSize
+            device.write(0x030, REQUESTQ as u32); // QueueSel
+            device.write(0x038, QUEUE_NUM as u32); // QueueNum
+            device.write(0x040, ((DRAM_BASE + 0x2000) >> 12) as u32); // QueuePFN
+            Driver {
+                device,
+                scheduler,
+                dram: Memory::new(0x20000),
+                available_idx: 0,
+                used_idx: 0,
+            }
         }
 
-        // available ring head address
-        let v_available_addr = v_desc_table_addr.wrapping_add(queue_size * 16);
-
-        // used ring head address
-        let v_used_addr;
-        {
-            let align = self.queue_align as u64;
-            v_used_addr =
-                ((v_available_addr.wrapping_add(4 + queue_size * 2 + align - 1)) / align) * align;
+        /// send a request of the header, the data to the device, and the room for
+        /// the data from the device and the status. Return the data and the status
+        /// written by the device.
+        fn request(
+            &mut self,
+            request_type: u32,
+            sector: u64,
+            data: &[u8],
+            read_size: usize,
+            indirect: bool,
+        ) -> (Vec<u8>, u8) {
+            let mut header = request_type.to_le_bytes().to_vec();
+            header.extend_from_slice(&[0; 4]);
+            header.extend_from_slice(&sector.to_le_bytes());
+            let mut buffers = vec![(header, 0)];
+            if !data.is_empty() {
+                buffers.push((data.to_vec(), 0));
+            }
+            if read_size > 0 {
+                buffers.push((vec![0xff; read_size], VRING_DESC_F_WRITE));
+            }
+            buffers.push((vec![0xff], VRING_DESC_F_WRITE));
+            let table = match indirect {
+                true => 0x8000,
+                false => 0x2000,
+            };
+            let mut address = 0x10000;
+            for (i, (buffer, flags)) in buffers.iter().enumerate() {
+                let entry = table + i as u64 * 16;
+                let next = match i + 1 < buffers.len() {
+                    true => VRING_DESC_F_NEXT,
+                    false => 0,
+                };
+                self.dram.write_bytes(address, buffer);
+                self.dram.write64(entry, DRAM_BASE + address);
+                self.dram.write32(entry + 8, buffer.len() as u32);
+                self.dram.write16(entry + 12, flags | next);
+                self.dram.write16(entry + 14, i as u16 + 1);
+                address += 0x1000;
+            }
+            if indirect {
+                self.dram.write64(0x2000, DRAM_BASE + 0x8000);
+                self.dram.write32(0x2008, buffers.len() as u32 * 16);
+                self.dram.write16(0x200c, VRING_DESC_F_INDIRECT);
+            }
+            let available_ring = 0x2000 + QUEUE_NUM as u64 * 16;
+            self.dram.write16(
+                available_ring + 4 + (self.available_idx % QUEUE_NUM) as u64 * 2,
+                0,
+            );
+            self.available_idx = self.available_idx.wrapping_add(1);
+            self.dram.write16(available_ring + 2, self.available_idx);
+            self.device.write(VIRTIO_QUEUE_NOTIFY, REQUESTQ as u32);
+
+            let event = self.scheduler.borrow_mut().pop(u64::MAX);
+            assert!(matches!(event, Some(Event::VirtioCompletion)));
+            self.device.complete(&mut self.dram);
+            assert!(self.device.is_irq());
+            let used_ring = 0x3000;
+            assert_eq!(
+                self.used_idx.wrapping_add(1),
+                self.dram.read16(used_ring + 2)
+            );
+            let slot = (self.used_idx % QUEUE_NUM) as u64;
+            let len = self.dram.read32(used_ring + 8 + slot * 8) as usize;
+            assert_eq!(read_size + 1, len);
+            self.used_idx = self.used_idx.wrapping_add(1);
+            let mut response = vec![0; read_size];
+            let status_address = 0x10000 + (buffers.len() as u64 - 1) * 0x1000;
+            self.dram.read_bytes(status_address - 0x1000, &mut response);
+            (response, self.dram.read8(status_address))
         }
 
-        Virtqueue {
-            descriptor_table_head: v_desc_table_addr,
-            available_ring_head: v_available_addr,
-            used_ring_head: v_used_addr,
+        fn get_sector(&self, sector: usize) -> &[u8] {
+            let size = CONFIG_DISK_SECTOR_SIZE as usize;
+            &self.device.disk_image[sector * size..(sector + 1) * size]
         }
     }
 
-    fn get_descriptor(&mut self, dram: &mut Memory, table_head: u64, prev: u64) -> Descriptor {
-        /* Descriptor entiry
-         * -----------------
-         * u64 addr
-         * u32 len
-         * u16 flags
-         * u16 next
-         */
-        let queue_size = self.queue_num as u64;
-        let entity = table_head + DESCRIPTOR_SIZE * prev;
-        Descriptor {
-            addr: dram.read64(entity) - self.dram_base_addr,
-            len: dram.read32(entity.wrapping_add(8)),
-            flags: dram.read16(entity.wrapping_add(12)),
-            next: (dram.read16(entity.wrapping_add(14)) as u64) % queue_size,
-        }
+    #[test]
+    fn reads_and_writes_sectors() {
+        let mut driver = Driver::new(false);
+        assert_eq!(VIRTIO_DEVICE_ID_BLOCK, driver.device.read(VIRTIO_DEVICE_ID));
+        assert_eq!(0, driver.device.read(0x010) as u64 & VIRTIO_BLK_F_RO);
+        // the capacity in the sectors.
+        assert_eq!(SECTORS as u32, driver.device.read(VIRTIO_CONFIG_SPACE));
+        assert_eq!(0, driver.device.read(VIRTIO_CONFIG_SPACE + 4));
+
+        assert_eq!(
+            (vec![2; 512], OK),
+            driver.request(VIRTIO_BLK_T_IN, 1, &[], 512, false)
+        );
+        assert_eq!(
+            (vec![4; 512], OK),
+            driver.request(VIRTIO_BLK_T_IN, 3, &[], 512, true)
+        );
+        assert_eq!(
+            (vec![], OK),
+            driver.request(VIRTIO_BLK_T_OUT, 2, &[0xaa; 512], 0, false)
+        );
+        assert_eq!(&[0xaa; 512][..], driver.get_sector(2));
+        assert_eq!(&[2; 512][..], driver.get_sector(1));
+        assert_eq!(
+            (vec![], OK),
+            driver.request(VIRTIO_BLK_T_FLUSH, 0, &[], 0, false)
+        );
     }
 
-    fn dma_disk_to_memory(&mut self, dram: &mut Memory, mem_addr: u64, disk_addr: u64, len: u64) {
-        for i in 0..(len / 8) {
-            let idx = ((disk_addr + i * 8) >> 3) as usize;
-            dram.write64(mem_addr + i * 8, self.disk_image[idx]);
-        }
+    #[test]
+    fn writes_to_read_only_disks_are_errors() {
+        let mut driver = Driver::new(true);
+        assert_eq!(
+            VIRTIO_BLK_F_RO,
+            driver.device.read(0x010) as u64 & VIRTIO_BLK_F_RO
+        );
+        assert_eq!(
+            (vec![], IOERR),
+            driver.request(VIRTIO_BLK_T_OUT, 2, &[0xaa; 512], 0, false)
+        );
+        assert_eq!(&[3; 512][..], driver.get_sector(2));
+        // the disk is still readable.
+        assert_eq!(
+            (vec![3; 512], OK),
+            driver.request(VIRTIO_BLK_T_IN, 2, &[], 512, false)
+        );
     }
 
-    fn dma_memory_to_disk(&mut self, dram: &mut Memory, mem_addr: u64, disk_addr: u64, len: u64) {
-        for i in 0..(len / 8) {
-            let idx = ((disk_addr + i * 8) >> 3) as usize;
-            self.disk_image[idx] = dram.read64(mem_addr + i * 8);
-        }
+    #[test]
+    fn sectors_beyond_the_capacity_are_errors() {
+        let mut driver = Driver::new(false);
+        let sectors = SECTORS as u64;
+        assert_eq!(
+            IOERR,
+            driver.request(VIRTIO_BLK_T_IN, sectors, &[], 512, false).1
+        );
+        // the request must end in the disk.
+        assert_eq!(
+            IOERR,
+            driver
+                .request(VIRTIO_BLK_T_IN, sectors - 1, &[], 1024, false)
+                .1
+        );
+        assert_eq!(
+            IOERR,
+            driver.request(VIRTIO_BLK_T_IN, u64::MAX, &[], 512, false).1
+        );
+        assert_eq!(
+            IOERR,
+            driver
+                .request(VIRTIO_BLK_T_OUT, sectors - 1, &[0xaa; 1024], 0, false)
+                .1
+        );
+        assert_eq!(&[4; 512][..], driver.get_sector(SECTORS - 1));
     }
 
-    fn read_disk8(&mut self, addr: u64) -> u8 {
-        let idx = (addr >> 3) as usize;
-        let pos = (addr % 8) * 8;
-        (self.disk_image[idx] >> pos) as u8
+    #[test]
+    fn unknown_requests_are_unsupported() {
+        let mut driver = Driver::new(false);
+        assert_eq!(
+            (vec![0; 16], UNSUPP),
+            driver.request(0xff, 0, &[], 16, false)
+        );
     }
 
-    fn write_disk8(&mut self, addr: u64, data: u8) {
-        let idx = (addr >> 3) as usize;
-        let pos = (addr % 8) * 8;
-        self.disk_image[idx] = (self.disk_image[idx] & !(0xff << pos)) | ((data as u64) << pos);
+    #[test]
+    fn get_id_returns_the_serial_number() {
+        let mut driver = Driver::new(false);
+        let mut id = CONFIG_DISK_ID.as_bytes().to_vec();
+        id.resize(VIRTIO_BLK_ID_BYTES, 0);
+        assert_eq!(
+            (id.clone(), OK),
+            driver.request(VIRTIO_BLK_T_GET_ID, 0, &[], 20, false)
+        );
+        // the id is truncated to the buffer.
+        assert_eq!(
+            (id[..8].to_vec(), OK),
+            driver.request(VIRTIO_BLK_T_GET_ID, 0, &[], 8, true)
+        );
     }
 }
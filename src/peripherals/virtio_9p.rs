@@ -0,0 +1,103 @@
+// Virtio 9P Transport Device
+// https://docs.oasis-open.org/virtio/virtio/v1.1/csprd01/virtio-v1.1-csprd01.html#x1-4290005
+// The guest mounts the exported directory by the mount tag:
+//   mount -t 9p -o trans=virtio,version=9p2000.L host0 /mnt
+
+use std::path::Path;
+
+use crate::p9::P9Server;
+use crate::peripherals::memory::Memory;
+use crate::peripherals::scheduler::{Event, SharedScheduler};
+use crate::peripherals::virtio_mmio::*;
+
+const CONFIG_QUEUE_NUM_MAX: u32 = 0x80;
+const CONFIG_DMA_DELAY: u64 = 128;
+pub const CONFIG_MOUNT_TAG: &str = "host0";
+
+// Feature bits
+const VIRTIO_9P_MOUNT_TAG: u64 = 1 << 0;
+
+const REQUESTQ: usize = 0;
+
+pub struct Virtio9p {
+    transport: VirtioMmio,
+    /// scheduler of the bus.
+    scheduler: SharedScheduler,
+    /// The device is absent without the exported directory.
+    server: Option<P9Server>,
+    /// Configuration space: tag_len and tag.
+    config_space: Vec<u8>,
+}
+
+impl Virtio9p {
+    pub fn new(dram_base_addr: u64, scheduler_: SharedScheduler) -> Self {
+        let mut config_space = (CONFIG_MOUNT_TAG.len() as u16).to_le_bytes().to_vec();
+        config_space.extend_from_slice(CONFIG_MOUNT_TAG.as_bytes());
+        Virtio9p {
+            transport: VirtioMmio::new(
+                dram_base_addr,
+                VIRTIO_DEVICE_ID_9P,
+                VIRTIO_9P_MOUNT_TAG,
+                &[CONFIG_QUEUE_NUM_MAX],
+            ),
+            scheduler: scheduler_,
+            server: None,
+            config_space,
+        }
+    }
+
+    /// export the directory of the host to the guest.
+    pub fn set_shared_directory(&mut self, path: &Path, read_only: bool) {
+        self.server = Some(P9Server::new(path, read_only));
+    }
+
+    pub fn is_irq(&mut self) -> bool {
+        self.transport.is_irq()
+    }
+
+    /// [Event::Virtio9pCompletion] handle the T-messages of the request queue, and
+    /// put the R-messages into their buffers.
+    pub fn complete(&mut self, dram: &mut Memory) {
+        let server = match self.server.as_mut() {
+            Some(server) => server,
+            None => return,
+        };
+        let mut used = false;
+        while let Some(chain) = self.transport.pop(dram, REQUESTQ) {
+            let request = chain.read(dram);
+            let response = server.handle(&request, chain.get_writable_size());
+            let len = chain.write(dram, &response);
+            self.transport.push(dram, REQUESTQ, &chain, len as u32);
+            used = true;
+        }
+        if used {
+            self.transport.interrupt_queue();
+        }
+    }
+
+    pub fn read(&mut self, addr: u64) -> u32 {
+        match addr {
+            // the device ID 0 tells the driver that the slot is empty.
+            VIRTIO_DEVICE_ID if self.server.is_none() => 0,
+            _ if addr >= VIRTIO_CONFIG_SPACE => {
+                read_config(&self.config_space, addr - VIRTIO_CONFIG_SPACE)
+            }
+            _ => self.transport.read(addr),
+        }
+    }
+
+    pub fn write(&mut self, addr: u64, data: u32) {
+        match addr {
+            VIRTIO_QUEUE_NOTIFY => {
+                let mut scheduler = self.scheduler.borrow_mut();
+                if !scheduler.is_scheduled(Event::Virtio9pCompletion) {
+                    let time = scheduler.get_time();
+                    scheduler.schedule(time + CONFIG_DMA_DELAY, Event::Virtio9pCompletion);
+                }
+            }
+            // the mount tag is read-only.
+            _ if addr >= VIRTIO_CONFIG_SPACE => {}
+            _ => self.transport.write(addr, data),
+        }
+    }
+}
@@ -0,0 +1,586 @@
+// Virtio Console Device
+// https://docs.oasis-open.org/virtio/virtio/v1.1/csprd01/virtio-v1.1-csprd01.html#x1-2900003
+
+use std::collections::VecDeque;
+
+use crate::console::Console;
+use crate::peripherals::memory::Memory;
+use crate::peripherals::scheduler::{Event, SharedScheduler};
+use crate::peripherals::virtio_mmio::*;
+
+const CONFIG_QUEUE_NUM_MAX: u32 = 0x80;
+const CONFIG_DMA_DELAY: u64 = 128;
+/// cycles between the polls of the consoles of the ports.
+const RECEIVE_INTERVAL: u64 = 0x4000;
+/// the number of the ports. Port 0 is the console port, and the others are named.
+pub const CONFIG_MAX_NR_PORTS: u32 = 16;
+/// bytes taken from the console of a port while the driver has no receive buffer.
+const INPUT_BUFFER_SIZE: usize = 0x1000;
+
+// Feature bits
+const VIRTIO_CONSOLE_F_MULTIPORT: u64 = 1 << 1;
+
+// Events of the control messages
+const VIRTIO_CONSOLE_DEVICE_READY: u16 = 0;
+const VIRTIO_CONSOLE_DEVICE_ADD: u16 = 1;
+const VIRTIO_CONSOLE_PORT_READY: u16 = 3;
+const VIRTIO_CONSOLE_CONSOLE_PORT: u16 = 4;
+const VIRTIO_CONSOLE_PORT_OPEN: u16 = 6;
+const VIRTIO_CONSOLE_PORT_NAME: u16 = 7;
+
+/// struct virtio_console_control: u32 id, u16 event, u16 value.
+const CONTROL_MESSAGE_SIZE: usize = 8;
+
+const CONTROL_RECEIVEQ: usize = 2;
+const CONTROL_TRANSMITQ: usize = 3;
+
+struct Port {
+    /// the name under /dev/virtio-ports/ of the guest, or empty.
+    name: String,
+    console: Box<dyn Console>,
+    /// the input from the console which waits for the receive buffers.
+    input: VecDeque<u8>,
+    /// the guest opened the port. The console port is always open.
+    open: bool,
+}
+
+pub struct VirtioConsole {
+    transport: VirtioMmio,
+    /// scheduler of the bus.
+    scheduler: SharedScheduler,
+    /// the ports by id. The device is absent without any port.
+    ports: Vec<Option<Port>>,
+    /// the control messages to the driver which wait for the control receive buffers.
+    control_messages: VecDeque<Vec<u8>>,
+    /// Configuration space: cols, rows and max_nr_ports.
+    config_space: Vec<u8>,
+}
+
+impl VirtioConsole {
+    pub fn new(dram_base_addr: u64, scheduler_: SharedScheduler) -> Self {
+        let mut config_space = vec![0; 4];
+        config_space.extend_from_slice(&CONFIG_MAX_NR_PORTS.to_le_bytes());
+        // receiveq and transmitq of port 0, the control queues, and the queues of
+        // the other ports.
+        let queues = 2 * (CONFIG_MAX_NR_PORTS as usize + 1);
+        VirtioConsole {
+            transport: VirtioMmio::new(
+                dram_base_addr,
+                VIRTIO_DEVICE_ID_CONSOLE,
+                VIRTIO_CONSOLE_F_MULTIPORT,
+                &vec![CONFIG_QUEUE_NUM_MAX; queues],
+            ),
+            scheduler: scheduler_,
+            ports: (0..CONFIG_MAX_NR_PORTS).map(|_| None).collect(),
+            control_messages: VecDeque::new(),
+            config_space,
+        }
+    }
+
+    /// connect the port of id to the console. Port 0 is the console port (hvc0 of
+    /// Linux), and the other ports appear as /dev/virtio-ports/name. Err if id is
+    /// not less than CONFIG_MAX_NR_PORTS.
+    pub fn set_port(&mut self, id: u32, name: &str, console: Box<dyn Console>) -> Result<(), ()> {
+        if id >= CONFIG_MAX_NR_PORTS {
+            return Err(());
+        }
+        if self.ports.iter().all(|port| port.is_none()) {
+            let mut scheduler = self.scheduler.borrow_mut();
+            let time = scheduler.get_time();
+            scheduler.schedule(time + RECEIVE_INTERVAL, Event::VirtioConsoleReceive);
+        }
+        self.ports[id as usize] = Some(Port {
+            name: name.to_string(),
+            console,
+            input: VecDeque::new(),
+            open: id == 0,
+        });
+        Ok(())
+    }
+
+    pub fn is_irq(&mut self) -> bool {
+        self.transport.is_irq()
+    }
+
+    /// [Event::VirtioConsoleReceive] take the input of the consoles of the ports,
+    /// put it into the receive buffers, and schedule the next poll.
+    pub fn receive(&mut self, dram: &mut Memory) {
+        for port in self.ports.iter_mut().flatten() {
+            while port.input.len() < INPUT_BUFFER_SIZE {
+                match port.console.getchar() {
+                    0 => break,
+                    c => port.input.push_back(c),
+                }
+            }
+        }
+        self.deliver(dram);
+        let mut scheduler = self.scheduler.borrow_mut();
+        let time = scheduler.get_time();
+        scheduler.schedule(time + RECEIVE_INTERVAL, Event::VirtioConsoleReceive);
+    }
+
+    /// [Event::VirtioConsoleTransmit] write the output of the transmit queues to the
+    /// consoles of the ports, and handle the control messages of the driver.
+    pub fn transmit(&mut self, dram: &mut Memory) {
+        let mut used = false;
+        if self.is_multiport() {
+            while let Some(chain) = self.transport.pop(dram, CONTROL_TRANSMITQ) {
+                let message = chain.read(dram);
+                self.control(&message);
+                self.transport.push(dram, CONTROL_TRANSMITQ, &chain, 0);
+                used = true;
+            }
+        }
+        for id in 0..self.ports.len() {
+            if id != 0 && !self.is_multiport() {
+                break;
+            }
+            let queue = get_receiveq(id) + 1;
+            let port = match self.ports[id].as_mut() {
+                Some(port) => port,
+                None => continue,
+            };
+            while let Some(chain) = self.transport.pop(dram, queue) {
+                for c in chain.read(dram) {
+                    port.console.putchar(c);
+                }
+                self.transport.push(dram, queue, &chain, 0);
+                used = true;
+            }
+        }
+        if used {
+            self.transport.interrupt_queue();
+        }
+        self.deliver(dram);
+    }
+
+    fn is_multiport(&self) -> bool {
+        (self.transport.get_driver_features() & VIRTIO_CONSOLE_F_MULTIPORT) != 0
+    }
+
+    /// put the control messages and the input of the open ports into the receive
+    /// buffers while the driver has them.
+    fn deliver(&mut self, dram: &mut Memory) {
+        let mut used = false;
+        if self.is_multiport() {
+            while !self.control_messages.is_empty() {
+                let chain = match self.transport.pop(dram, CONTROL_RECEIVEQ) {
+                    Some(chain) => chain,
+                    None => break,
+                };
+                let message = self.control_messages.pop_front().unwrap();
+                let len = chain.write(dram, &message);
+                self.transport
+                    .push(dram, CONTROL_RECEIVEQ, &chain, len as u32);
+                used = true;
+            }
+        }
+        for id in 0..self.ports.len() {
+            if id != 0 && !self.is_multiport() {
+                break;
+            }
+            let queue = get_receiveq(id);
+            let port = match self.ports[id].as_mut() {
+                Some(port) if port.open => port,
+                _ => continue,
+            };
+            while !port.input.is_empty() {
+                let chain = match self.transport.pop(dram, queue) {
+                    Some(chain) => chain,
+                    None => break,
+                };
+                let size = chain.get_writable_size().min(port.input.len());
+                let data: Vec<u8> = port.input.drain(..size).collect();
+                let len = chain.write(dram, &data);
+                self.transport.push(dram, queue, &chain, len as u32);
+                used = true;
+            }
+        }
+        if used {
+            self.transport.interrupt_queue();
+        }
+    }
+
+    /// handle a control message of the driver. The ports are added when the driver
+    /// is ready, and each port is set up when the driver is ready for it.
+    fn control(&mut self, message: &[u8]) {
+        if message.len() < CONTROL_MESSAGE_SIZE {
+            return;
+        }
+        let id = u32::from_le_bytes([message[0], message[1], message[2], message[3]]);
+        let event = u16::from_le_bytes([message[4], message[5]]);
+        let value = u16::from_le_bytes([message[6], message[7]]);
+        match event {
+            VIRTIO_CONSOLE_DEVICE_READY if value == 1 => {
+                for id in 0..self.ports.len() {
+                    if self.ports[id].is_some() {
+                        self.send_control(id as u32, VIRTIO_CONSOLE_DEVICE_ADD, 1, &[]);
+                    }
+                }
+            }
+            VIRTIO_CONSOLE_PORT_READY if value == 1 => {
+                let name = match self.ports.get(id as usize) {
+                    Some(Some(port)) => port.name.clone(),
+                    _ => return,
+                };
+                if id == 0 {
+                    self.send_control(id, VIRTIO_CONSOLE_CONSOLE_PORT, 1, &[]);
+                }
+                if !name.is_empty() {
+                    self.send_control(id, VIRTIO_CONSOLE_PORT_NAME, 1, name.as_bytes());
+                }
+                // the console of the port is always connected.
+                self.send_control(id, VIRTIO_CONSOLE_PORT_OPEN, 1, &[]);
+            }
+            VIRTIO_CONSOLE_PORT_OPEN => match self.ports.get_mut(id as usize) {
+                Some(Some(port)) => port.open = id == 0 || value == 1,
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+
+    fn send_control(&mut self, id: u32, event: u16, value: u16, data: &[u8]) {
+        let mut message = vec![];
+        message.extend_from_slice(&id.to_le_bytes());
+        message.extend_from_slice(&event.to_le_bytes());
+        message.extend_from_slice(&value.to_le_bytes());
+        message.extend_from_slice(data);
+        self.control_messages.push_back(message);
+    }
+
+    pub fn read(&mut self, addr: u64) -> u32 {
+        match addr {
+            // the device ID 0 tells the driver that the slot is empty.
+            VIRTIO_DEVICE_ID if self.ports.iter().all(|port| port.is_none()) => 0,
+            _ if addr >= VIRTIO_CONFIG_SPACE => {
+                read_config(&self.config_space, addr - VIRTIO_CONFIG_SPACE)
+            }
+            _ => self.transport.read(addr),
+        }
+    }
+
+    pub fn write(&mut self, addr: u64, data: u32) {
+        match addr {
+            VIRTIO_QUEUE_NOTIFY => {
+                let mut scheduler = self.scheduler.borrow_mut();
+                let time = scheduler.get_time();
+                match data {
+                    // new receive buffers are filled without waiting for the next poll.
+                    // The receive queues are even, and the transmit queues are odd.
+                    _ if data & 1 == 0 => {
+                        scheduler.cancel(Event::VirtioConsoleReceive);
+                        scheduler.schedule(time + CONFIG_DMA_DELAY, Event::VirtioConsoleReceive);
+                    }
+                    _ if !scheduler.is_scheduled(Event::VirtioConsoleTransmit) => {
+                        scheduler.schedule(time + CONFIG_DMA_DELAY, Event::VirtioConsoleTransmit);
+                    }
+                    _ => {}
+                }
+            }
+            VIRTIO_DEVICE_STATUS if data == 0 => {
+                self.control_messages.clear();
+                for (id, port) in self.ports.iter_mut().enumerate() {
+                    match port {
+                        Some(port) => port.open = id == 0,
+                        None => {}
+                    }
+                }
+                self.transport.write(addr, data);
+            }
+            // cols and rows are read-only without VIRTIO_CONSOLE_F_SIZE.
+            _ if addr >= VIRTIO_CONFIG_SPACE => {}
+            _ => self.transport.write(addr, data),
+        }
+    }
+}
+
+/// the receiveq of the port. Its transmitq follows it.
+fn get_receiveq(id: usize) -> usize {
+    match id {
+        0 => 0,
+        _ => 2 + id * 2,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::peripherals::scheduler::Scheduler;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    const DRAM_BASE: u64 = 0x8000_0000;
+    const QUEUE_NUM: u16 = 8;
+    const BUFFER_SIZE: u32 = 0x100;
+    const VRING_DESC_F_WRITE: u16 = 0x2;
+
+    /// a console on the buffers shared with the test.
+    struct TestConsole {
+        input: Rc<RefCell<VecDeque<u8>>>,
+        output: Rc<RefCell<Vec<u8>>>,
+    }
+
+    impl Console for TestConsole {
+        fn putchar(&mut self, c: u8) {
+            self.output.borrow_mut().push(c);
+        }
+
+        fn getchar(&mut self) -> u8 {
+            self.input.borrow_mut().pop_front().unwrap_or(0)
+        }
+
+        fn set_input(&mut self, _c: u8) {}
+
+        fn get_output(&mut self) -> u8 {
+            0
+        }
+    }
+
+    /// the driver of the guest. Each queue takes two pages from (queue + 1) * 0x2000
+    /// of the memory, and its buffers are from 0x100000 + queue * 0x1000.
+    struct Driver {
+        device: VirtioConsole,
+        scheduler: SharedScheduler,
+        dram: Memory,
+        inputs: Vec<Rc<RefCell<VecDeque<u8>>>>,
+        outputs: Vec<Rc<RefCell<Vec<u8>>>>,
+        available_idx: Vec<u16>,
+        used_idx: Vec<u16>,
+    }
+
+    impl Driver {
+        fn new(ports: &[(u32, &str)], features: u64) -> Self {
+            let scheduler = Scheduler::new_shared();
+            let mut device = VirtioConsole::new(DRAM_BASE, scheduler.clone());
+            let mut inputs = vec![];
+            let mut outputs = vec![];
+            for (id, name) in ports.iter() {
+                let input = Rc::new(RefCell::new(VecDeque::new()));
+                let output = Rc::new(RefCell::new(vec![]));
+                let console = Box::new(TestConsole {
+                    input: input.clone(),
+                    output: output.clone(),
+                });
+                assert_eq!(Ok(()), device.set_port(*id, name, console));
+                inputs.push(input);
+                outputs.push(output);
+            }
+            device.write(0x024, 0); // DriverFeaturesSel
+            device.write(0x020, features as u32); // DriverFeatures
+            device.write(0x028, 0x1000); // GuestPageSize
+            let queues = 2 * (CONFIG_MAX_NR_PORTS as usize + 1);
+            for queue in 0..queues {
+                device.write(0x030, queue as u32); // QueueSel
+                device.write(0x038, QUEUE_NUM as u32); // QueueNum
+                let pfn = (DRAM_BASE + (queue as u64 + 1) * 0x2000) >> 12;
+                device.write(0x040, pfn as u32); // QueuePFN
+            }
+            Driver {
+                device,
+                scheduler,
+                dram: Memory::new(0x200000),
+                inputs,
+                outputs,
+                available_idx: vec![0; queues],
+                used_idx: vec![0; queues],
+            }
+        }
+
+        /// put a buffer of data, or a writable buffer without data, to the queue.
+        fn add_buffer(&mut self, queue: usize, data: Option<&[u8]>) {
+            let idx = self.available_idx[queue];
+            let slot = (idx % QUEUE_NUM) as u64;
+            let buffer = 0x100000 + queue as u64 * 0x1000 + slot * BUFFER_SIZE as u64;
+            let (len, flags) = match data {
+                Some(data) => {
+                    self.dram.write_bytes(buffer, data);
+                    (data.len() as u32, 0)
+                }
+                None => (BUFFER_SIZE, VRING_DESC_F_WRITE),
+            };
+            let descriptor_table = (queue as u64 + 1) * 0x2000;
+            let entry = descriptor_table + slot * 16;
+            self.dram.write64(entry, DRAM_BASE + buffer);
+            self.dram.write32(entry + 8, len);
+            self.dram.write16(entry + 12, flags);
+            let available_ring = descriptor_table + QUEUE_NUM as u64 * 16;
+            self.dram
+                .write16(available_ring + 4 + slot * 2, slot as u16);
+            self.available_idx[queue] = idx.wrapping_add(1);
+            self.dram
+                .write16(available_ring + 2, self.available_idx[queue]);
+            self.device.write(VIRTIO_QUEUE_NOTIFY, queue as u32);
+        }
+
+        /// send a control message of the driver.
+        fn control(&mut self, id: u32, event: u16, value: u16) {
+            let message = control_message(id, event, value, &[]);
+            self.add_buffer(CONTROL_TRANSMITQ, Some(&message));
+        }
+
+        /// the data written to the buffers which the device returned to the queue.
+        fn take_used(&mut self, queue: usize) -> Vec<Vec<u8>> {
+            let used_ring = (queue as u64 + 1) * 0x2000 + 0x1000;
+            let mut buffers = vec![];
+            while self.used_idx[queue] != self.dram.read16(used_ring + 2) {
+                let slot = (self.used_idx[queue] % QUEUE_NUM) as u64;
+                let head = self.dram.read32(used_ring + 4 + slot * 8) as u64;
+                let len = self.dram.read32(used_ring + 8 + slot * 8) as usize;
+                let buffer = 0x100000 + queue as u64 * 0x1000 + head * BUFFER_SIZE as u64;
+                let mut data = vec![0; len];
+                self.dram.read_bytes(buffer, &mut data);
+                buffers.push(data);
+                self.used_idx[queue] = self.used_idx[queue].wrapping_add(1);
+            }
+            buffers
+        }
+
+        /// run the events of the device for a while.
+        fn run(&mut self) {
+            let time = self.scheduler.borrow().get_time() + 2 * RECEIVE_INTERVAL;
+            loop {
+                let event = self.scheduler.borrow_mut().pop(time);
+                match event {
+                    Some(Event::VirtioConsoleReceive) => self.device.receive(&mut self.dram),
+                    Some(Event::VirtioConsoleTransmit) => self.device.transmit(&mut self.dram),
+                    Some(_) => {}
+                    None => break,
+                }
+            }
+        }
+    }
+
+    fn control_message(id: u32, event: u16, value: u16, data: &[u8]) -> Vec<u8> {
+        let mut message = id.to_le_bytes().to_vec();
+        message.extend_from_slice(&event.to_le_bytes());
+        message.extend_from_slice(&value.to_le_bytes());
+        message.extend_from_slice(data);
+        message
+    }
+
+    fn get_open_ports(device: &VirtioConsole) -> Vec<bool> {
+        device
+            .ports
+            .iter()
+            .flatten()
+            .map(|port| port.open)
+            .collect()
+    }
+
+    #[test]
+    fn ports_out_of_range_are_errors() {
+        let mut device = VirtioConsole::new(DRAM_BASE, Scheduler::new_shared());
+        // the slot is empty without any port.
+        assert_eq!(0, device.read(VIRTIO_DEVICE_ID));
+        let console = Box::new(TestConsole {
+            input: Rc::new(RefCell::new(VecDeque::new())),
+            output: Rc::new(RefCell::new(vec![])),
+        });
+        assert_eq!(
+            Err(()),
+            device.set_port(CONFIG_MAX_NR_PORTS, "port", console)
+        );
+        assert_eq!(0, device.read(VIRTIO_DEVICE_ID));
+        let console = Box::new(TestConsole {
+            input: Rc::new(RefCell::new(VecDeque::new())),
+            output: Rc::new(RefCell::new(vec![])),
+        });
+        assert_eq!(
+            Ok(()),
+            device.set_port(CONFIG_MAX_NR_PORTS - 1, "port", console)
+        );
+        assert_eq!(VIRTIO_DEVICE_ID_CONSOLE, device.read(VIRTIO_DEVICE_ID));
+        assert_eq!(CONFIG_MAX_NR_PORTS, device.read(VIRTIO_CONFIG_SPACE + 4));
+    }
+
+    #[test]
+    fn console_port_works_without_multiport() {
+        let mut driver = Driver::new(&[(0, ""), (1, "port1")], 0);
+        driver.inputs[0].borrow_mut().extend(b"in");
+        driver.inputs[1].borrow_mut().extend(b"ignored");
+        driver.add_buffer(0, None);
+        driver.add_buffer(get_receiveq(1), None);
+        driver.add_buffer(1, Some(b"out"));
+        driver.add_buffer(get_receiveq(1) + 1, Some(b"ignored"));
+        driver.run();
+        assert_eq!(vec![b"in".to_vec()], driver.take_used(0));
+        assert_eq!(1, driver.take_used(1).len());
+        assert_eq!(b"out".to_vec(), *driver.outputs[0].borrow());
+        // the queues of the other ports and the control queues are not used.
+        assert!(driver.take_used(get_receiveq(1)).is_empty());
+        assert!(driver.take_used(get_receiveq(1) + 1).is_empty());
+        assert!(driver.outputs[1].borrow().is_empty());
+        driver.control(0, VIRTIO_CONSOLE_DEVICE_READY, 1);
+        driver.run();
+        assert!(driver.take_used(CONTROL_TRANSMITQ).is_empty());
+    }
+
+    #[test]
+    fn control_messages_add_name_and_open_the_ports() {
+        let mut driver = Driver::new(&[(0, ""), (3, "org.test.port")], VIRTIO_CONSOLE_F_MULTIPORT);
+        for _ in 0..QUEUE_NUM {
+            driver.add_buffer(CONTROL_RECEIVEQ, None);
+        }
+        driver.control(0, VIRTIO_CONSOLE_DEVICE_READY, 1);
+        driver.run();
+        assert_eq!(1, driver.take_used(CONTROL_TRANSMITQ).len());
+        assert_eq!(
+            vec![
+                control_message(0, VIRTIO_CONSOLE_DEVICE_ADD, 1, &[]),
+                control_message(3, VIRTIO_CONSOLE_DEVICE_ADD, 1, &[]),
+            ],
+            driver.take_used(CONTROL_RECEIVEQ)
+        );
+
+        driver.control(0, VIRTIO_CONSOLE_PORT_READY, 1);
+        driver.control(3, VIRTIO_CONSOLE_PORT_READY, 1);
+        // a port which is not added is ignored.
+        driver.control(5, VIRTIO_CONSOLE_PORT_READY, 1);
+        driver.run();
+        assert_eq!(
+            vec![
+                control_message(0, VIRTIO_CONSOLE_CONSOLE_PORT, 1, &[]),
+                control_message(0, VIRTIO_CONSOLE_PORT_OPEN, 1, &[]),
+                control_message(3, VIRTIO_CONSOLE_PORT_NAME, 1, b"org.test.port"),
+                control_message(3, VIRTIO_CONSOLE_PORT_OPEN, 1, &[]),
+            ],
+            driver.take_used(CONTROL_RECEIVEQ)
+        );
+
+        // the input of the named port waits until the guest opens it.
+        let queue = get_receiveq(3);
+        driver.inputs[1].borrow_mut().extend(b"named");
+        driver.add_buffer(queue, None);
+        driver.run();
+        assert!(driver.take_used(queue).is_empty());
+        driver.control(3, VIRTIO_CONSOLE_PORT_OPEN, 1);
+        driver.run();
+        assert_eq!(vec![b"named".to_vec()], driver.take_used(queue));
+        driver.add_buffer(queue + 1, Some(b"output"));
+        driver.run();
+        assert_eq!(b"output".to_vec(), *driver.outputs[1].borrow());
+        assert!(driver.outputs[0].borrow().is_empty());
+
+        // closing the port holds the input again.
+        driver.control(3, VIRTIO_CONSOLE_PORT_OPEN, 0);
+        driver.inputs[1].borrow_mut().extend(b"closed");
+        driver.add_buffer(queue, None);
+        driver.run();
+        assert!(driver.take_used(queue).is_empty());
+    }
+
+    #[test]
+    fn reset_closes_the_named_ports_and_drops_the_control_messages() {
+        let mut driver = Driver::new(&[(0, ""), (1, "port1")], VIRTIO_CONSOLE_F_MULTIPORT);
+        driver.control(0, VIRTIO_CONSOLE_DEVICE_READY, 1);
+        driver.control(1, VIRTIO_CONSOLE_PORT_OPEN, 1);
+        driver.run();
+        // the control messages wait for the control receive buffers.
+        assert_eq!(2, driver.device.control_messages.len());
+        assert_eq!(vec![true, true], get_open_ports(&driver.device));
+        driver.device.write(VIRTIO_DEVICE_STATUS, 0);
+        assert!(driver.device.control_messages.is_empty());
+        assert_eq!(vec![true, false], get_open_ports(&driver.device));
+    }
+}
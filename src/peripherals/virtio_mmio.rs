@@ -0,0 +1,429 @@
+// Virtio over MMIO (legacy interface) shared by the virtio devices
+// https://docs.oasis-open.org/virtio/virtio/v1.1/csprd01/virtio-v1.1-csprd01.html#x1-1560004
+
+use crate::peripherals::memory::Memory;
+
+pub const VIRTIO_DEVICE_ID_NET: u32 = 1;
+pub const VIRTIO_DEVICE_ID_BLOCK: u32 = 2;
+pub const VIRTIO_DEVICE_ID_CONSOLE: u32 = 3;
+pub const VIRTIO_DEVICE_ID_ENTROPY: u32 = 4;
+pub const VIRTIO_DEVICE_ID_9P: u32 = 9;
+
+const VIRTIO_MAGIC_VALUE: u64 = 0x000;
+const VIRTIO_VERSION: u64 = 0x004;
+pub const VIRTIO_DEVICE_ID: u64 = 0x008;
+const VIRTIO_VENDOR_ID: u64 = 0x00c;
+const VIRTIO_DEVICE_FEATURES: u64 = 0x010;
+const VIRTIO_DEVICE_FEATURES_SEL: u64 = 0x014;
+const VIRTIO_DRIVER_FEATURES: u64 = 0x020;
+const VIRTIO_DRIVER_FEATURES_SEL: u64 = 0x024;
+const VIRTIO_GUEST_PAGE_SIZE: u64 = 0x028;
+const VIRTIO_QUEUE_SEL: u64 = 0x030;
+const VIRTIO_QUEUE_NUM_MAX: u64 = 0x034;
+const VIRTIO_QUEUE_NUM: u64 = 0x038;
+const VIRTIO_QUEUE_ALIGN: u64 = 0x03c;
+const VIRTIO_QUEUE_PFN: u64 = 0x040;
+pub const VIRTIO_QUEUE_NOTIFY: u64 = 0x050;
+const VIRTIO_INTERRUPT_STATUS: u64 = 0x060;
+const VIRTIO_INTERRUPT_ACK: u64 = 0x064;
+pub const VIRTIO_DEVICE_STATUS: u64 = 0x070;
+pub const VIRTIO_CONFIG_SPACE: u64 = 0x100;
+
+const VIRTIO_INTERRUPT_QUEUE: u32 = 0x1;
+const VIRTIO_INTERRUPT_CONFIGURATION: u32 = 0x2;
+
+// Descriptor flags
+const DESCRIPTOR_SIZE: u64 = 16;
+const VRING_DESC_F_NEXT: u16 = 0x1;
+const VRING_DESC_F_WRITE: u16 = 0x2;
+const VRING_DESC_F_INDIRECT: u16 = 0x4;
+
+// Feature bits of the virtqueues
+pub const VIRTIO_RING_F_INDIRECT_DESC: u64 = 1 << 28;
+
+/// A buffer of the guest. addr is the offset in the main memory.
+pub struct Descriptor {
+    pub addr: u64,
+    pub len: u32,
+    pub flags: u16,
+}
+
+impl Descriptor {
+    /// the device writes the buffer, and the driver reads it.
+    pub fn is_writable(&self) -> bool {
+        (self.flags & VRING_DESC_F_WRITE) != 0
+    }
+}
+
+/// A request of the driver, which is the chain of the descriptors from the head
+/// taken from the available ring. The device-readable descriptors come first.
+pub struct DescriptorChain {
+    pub head: u16,
+    pub descriptors: Vec<Descriptor>,
+}
+
+impl DescriptorChain {
+    /// the bytes of the device-readable descriptors.
+    pub fn read(&self, dram: &Memory) -> Vec<u8> {
+        let mut data = vec![];
+        for descriptor in self.descriptors.iter().filter(|d| !d.is_writable()) {
+            let start = data.len();
+            data.resize(start + descriptor.len as usize, 0);
+            dram.read_bytes(descriptor.addr, &mut data[start..]);
+        }
+        data
+    }
+
+    /// the total size of the device-writable descriptors.
+    pub fn get_writable_size(&self) -> usize {
+        self.descriptors
+            .iter()
+            .filter(|d| d.is_writable())
+            .map(|d| d.len as usize)
+            .sum()
+    }
+
+    /// write data to the device-writable descriptors in order, and return the
+    /// number of the written bytes.
+    pub fn write(&self, dram: &mut Memory, data: &[u8]) -> usize {
+        let mut written = 0;
+        for descriptor in self.descriptors.iter().filter(|d| d.is_writable()) {
+            if written == data.len() {
+                break;
+            }
+            let size = (descriptor.len as usize).min(data.len() - written);
+            dram.write_bytes(descriptor.addr, &data[written..written + size]);
+            written += size;
+        }
+        written
+    }
+}
+
+/// A virtqueue of the legacy interface. The descriptor table, the available ring
+/// and the used ring are in the contiguous pages from queue_pfn.
+struct Virtqueue {
+    /// Maximum virtual queue size (RO)
+    num_max: u32,
+    /// Virtual queue size (WO)
+    num: u32,
+    /// Used Ring alignment in the virtual queue (WO)
+    align: u32,
+    /// Guest physical page number of the virtual queue (R/W)
+    pfn: u32,
+    /// index of the available ring which the device takes next.
+    last_available_idx: u16,
+    /// index of the used ring which the device puts next.
+    used_idx: u16,
+}
+
+impl Virtqueue {
+    fn new(num_max_: u32) -> Self {
+        Virtqueue {
+            num_max: num_max_,
+            num: 0,
+            align: 0x1000,
+            pfn: 0,
+            last_available_idx: 0,
+            used_idx: 0,
+        }
+    }
+}
+
+/// The registers of a virtio-mmio device and its virtqueues. The device handles
+/// the notifications and the configuration space.
+pub struct VirtioMmio {
+    /// Main Memory Base Address
+    dram_base_addr: u64,
+    /// Virtio Subsystem Device ID (RO)
+    device_id: u32,
+    /// Flags representing features the device supports (RO)
+    device_features: u64,
+    /// Device (host) features word selection (WO)
+    device_features_sel: u32,
+    /// Flags representing device features understood and activated by the driver (WO)
+    driver_features: u64,
+    /// Activated (guest) features word selection (WO)
+    driver_features_sel: u32,
+    /// Guest page size (WO)
+    guest_page_size: u32,
+    /// Virtual queue index (WO)
+    queue_sel: u32,
+    queues: Vec<Virtqueue>,
+    /// Interrupt status (RO)
+    interrupt_status: u32,
+    /// Device status (R/W)
+    device_status: u32,
+}
+
+impl VirtioMmio {
+    /// queue_num_max has the maximum size of each virtqueue of the device.
+    pub fn new(
+        dram_base_addr_: u64,
+        device_id_: u32,
+        device_features_: u64,
+        queue_num_max: &[u32],
+    ) -> Self {
+        VirtioMmio {
+            dram_base_addr: dram_base_addr_,
+            device_id: device_id_,
+            device_features: device_features_,
+            device_features_sel: 0,
+            driver_features: 0,
+            driver_features_sel: 0,
+            guest_page_size: 0,
+            queue_sel: 0,
+            queues: queue_num_max.iter().map(|n| Virtqueue::new(*n)).collect(),
+            interrupt_status: 0,
+            device_status: 0,
+        }
+    }
+
+    /// change the features which the device offers to the driver.
+    pub fn set_device_features(&mut self, device_features_: u64) {
+        self.device_features = device_features_;
+    }
+
+    pub fn get_driver_features(&self) -> u64 {
+        self.driver_features
+    }
+
+    pub fn is_irq(&self) -> bool {
+        self.interrupt_status & 0x3 > 0
+    }
+
+    /// notify the driver that the used ring is updated.
+    pub fn interrupt_queue(&mut self) {
+        self.interrupt_status |= VIRTIO_INTERRUPT_QUEUE;
+    }
+
+    /// notify the driver that the configuration space is changed.
+    pub fn interrupt_configuration(&mut self) {
+        self.interrupt_status |= VIRTIO_INTERRUPT_CONFIGURATION;
+    }
+
+    pub fn read(&mut self, addr: u64) -> u32 {
+        match addr {
+            VIRTIO_MAGIC_VALUE => 0x74726976, // "virt" string
+            VIRTIO_VERSION => 0x1,            // Legacy device returns value 0x1.
+            VIRTIO_DEVICE_ID => self.device_id,
+            VIRTIO_VENDOR_ID => 0x554d4551, // from xv6-riscv source code.
+            VIRTIO_DEVICE_FEATURES => match self.device_features_sel {
+                0 => self.device_features as u32,
+                1 => (self.device_features >> 32) as u32,
+                _ => 0,
+            },
+            VIRTIO_QUEUE_NUM_MAX => match self.get_selected_queue() {
+                Some(queue) => queue.num_max,
+                None => 0,
+            },
+            VIRTIO_QUEUE_PFN => match self.get_selected_queue() {
+                Some(queue) => queue.pfn,
+                None => 0,
+            },
+            VIRTIO_INTERRUPT_STATUS => self.interrupt_status,
+            VIRTIO_DEVICE_STATUS => self.device_status,
+            // the reserved registers read as zero.
+            _ => 0,
+        }
+    }
+
+    pub fn write(&mut self, addr: u64, data: u32) {
+        match addr {
+            VIRTIO_DEVICE_FEATURES_SEL => self.device_features_sel = data,
+            VIRTIO_DRIVER_FEATURES => match self.driver_features_sel {
+                0 => self.driver_features = (self.driver_features & !0xffffffff) | data as u64,
+                1 => {
+                    self.driver_features =
+                        (self.driver_features & 0xffffffff) | ((data as u64) << 32)
+                }
+                _ => {}
+            },
+            VIRTIO_DRIVER_FEATURES_SEL => self.driver_features_sel = data,
+            VIRTIO_GUEST_PAGE_SIZE => self.guest_page_size = data,
+            VIRTIO_QUEUE_SEL => self.queue_sel = data,
+            VIRTIO_QUEUE_NUM => match self.get_selected_queue() {
+                Some(queue) => queue.num = data.min(queue.num_max),
+                None => {}
+            },
+            VIRTIO_QUEUE_ALIGN => match self.get_selected_queue() {
+                Some(queue) => queue.align = data,
+                None => {}
+            },
+            VIRTIO_QUEUE_PFN => match self.get_selected_queue() {
+                Some(queue) => {
+                    queue.pfn = data;
+                    queue.last_available_idx = 0;
+                    queue.used_idx = 0;
+                }
+                None => {}
+            },
+            VIRTIO_QUEUE_NOTIFY => {}
+            VIRTIO_INTERRUPT_ACK => self.interrupt_status &= !data,
+            VIRTIO_DEVICE_STATUS => {
+                self.device_status = data;
+                // writing zero resets the device.
+                if data == 0 {
+                    self.reset();
+                }
+            }
+            // the writes to the read-only or reserved registers are ignored.
+            _ => {}
+        }
+    }
+
+    fn reset(&mut self) {
+        self.driver_features = 0;
+        self.driver_features_sel = 0;
+        self.device_features_sel = 0;
+        self.queue_sel = 0;
+        self.interrupt_status = 0;
+        for queue in self.queues.iter_mut() {
+            *queue = Virtqueue::new(queue.num_max);
+        }
+    }
+
+    fn get_selected_queue(&mut self) -> Option<&mut Virtqueue> {
+        self.queues.get_mut(self.queue_sel as usize)
+    }
+
+    /// the offsets of the descriptor table, the available ring and the used ring of
+    /// the queue in the main memory. None if the queue is not set up, or it is not
+    /// in the main memory.
+    fn get_rings(&self, dram: &Memory, queue: usize) -> Option<(u64, u64, u64)> {
+        let vq = &self.queues[queue];
+        if vq.pfn == 0 || vq.num == 0 {
+            return None;
+        }
+        let num = vq.num as u64;
+        let align = (vq.align as u64).max(1);
+        let descriptor_table =
+            (vq.pfn as u64 * self.guest_page_size as u64).wrapping_sub(self.dram_base_addr);
+        let available_ring = descriptor_table.wrapping_add(num * DESCRIPTOR_SIZE);
+        let used_ring = (available_ring.wrapping_add(6 + num * 2 + align - 1) / align) * align;
+        match dram.contains(descriptor_table, num * DESCRIPTOR_SIZE)
+            && dram.contains(available_ring, 6 + num * 2)
+            && dram.contains(used_ring, 6 + num * 8)
+        {
+            true => Some((descriptor_table, available_ring, used_ring)),
+            false => None,
+        }
+    }
+
+    /// take the next request of the driver from the available ring of the queue.
+    /// The descriptors outside the main memory are dropped.
+    pub fn pop(&mut self, dram: &Memory, queue: usize) -> Option<DescriptorChain> {
+        let (descriptor_table, available_ring, _) = match self.get_rings(dram, queue) {
+            Some(rings) => rings,
+            None => return None,
+        };
+        let dram_base_addr = self.dram_base_addr;
+        let vq = &mut self.queues[queue];
+        let num = vq.num as u64;
+
+        /* Available Ring
+         * ----------------
+         * u16 flags
+         * u16 idx
+         * u16[QUEUE_NUM] ring
+         * u16 used_event
+         */
+        if dram.read16(available_ring.wrapping_add(2)) == vq.last_available_idx {
+            return None;
+        }
+        let slot = vq.last_available_idx as u64 % num;
+        let head = dram.read16(available_ring.wrapping_add(4 + slot * 2));
+        vq.last_available_idx = vq.last_available_idx.wrapping_add(1);
+
+        let descriptors =
+            read_descriptors(dram, descriptor_table, num, head as u64, dram_base_addr, true);
+        Some(DescriptorChain { head, descriptors })
+    }
+
+    /// return the request to the driver by the used ring of the queue. len is the
+    /// number of the bytes written to the request.
+    pub fn push(&mut self, dram: &mut Memory, queue: usize, chain: &DescriptorChain, len: u32) {
+        let (_, _, used_ring) = match self.get_rings(dram, queue) {
+            Some(rings) => rings,
+            None => return,
+        };
+        let vq = &mut self.queues[queue];
+
+        /* Used Ring
+         * ----------------
+         * u16 flags
+         * u16 idx
+         * UsedRingEntry[QUEUE_NUM] ring
+         * u16 avail_event
+         */
+        let slot = vq.used_idx as u64 % vq.num as u64;
+        let entry = used_ring.wrapping_add(4 + slot * 8);
+        dram.write32(entry, chain.head as u32);
+        dram.write32(entry.wrapping_add(4), len);
+        vq.used_idx = vq.used_idx.wrapping_add(1);
+        dram.write16(used_ring.wrapping_add(2), vq.used_idx);
+    }
+}
+
+/// read the chain of the descriptors from index of the descriptor table of num
+/// entries. A descriptor with VRING_DESC_F_INDIRECT is replaced by the chain of
+/// its indirect table, which cannot be nested.
+fn read_descriptors(
+    dram: &Memory,
+    table: u64,
+    num: u64,
+    index: u64,
+    dram_base_addr: u64,
+    indirect: bool,
+) -> Vec<Descriptor> {
+    let mut descriptors = vec![];
+    if num == 0 {
+        return descriptors;
+    }
+
+    /* Descriptor entiry
+     * -----------------
+     * u64 addr
+     * u32 len
+     * u16 flags
+     * u16 next
+     */
+    let mut index = index % num;
+    // a chain visits each descriptor at most once.
+    for _ in 0..num {
+        let entry = table + DESCRIPTOR_SIZE * index;
+        let addr = dram.read64(entry).wrapping_sub(dram_base_addr);
+        let len = dram.read32(entry.wrapping_add(8));
+        let flags = dram.read16(entry.wrapping_add(12));
+        if (flags & VRING_DESC_F_INDIRECT) != 0 {
+            if indirect && dram.contains(addr, len as u64) {
+                descriptors.extend(read_descriptors(
+                    dram,
+                    addr,
+                    len as u64 / DESCRIPTOR_SIZE,
+                    0,
+                    dram_base_addr,
+                    false,
+                ));
+            }
+        } else if dram.contains(addr, len as u64) {
+            descriptors.push(Descriptor { addr, len, flags });
+        }
+        if (flags & VRING_DESC_F_NEXT) == 0 {
+            break;
+        }
+        index = dram.read16(entry.wrapping_add(14)) as u64 % num;
+    }
+    descriptors
+}
+
+/// read the 32-bit word at addr of the configuration space from its bytes.
+pub fn read_config(config: &[u8], addr: u64) -> u32 {
+    let mut data = 0;
+    for i in 0..4 {
+        match config.get(addr as usize + i) {
+            Some(byte) => data |= (*byte as u32) << (i * 8),
+            None => {}
+        }
+    }
+    data
+}
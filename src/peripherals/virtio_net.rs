@@ -0,0 +1,179 @@
+// Virtio Network Device
+// https://docs.oasis-open.org/virtio/virtio/v1.1/csprd01/virtio-v1.1-csprd01.html#x1-1940001
+
+use crate::network::Network;
+use crate::peripherals::memory::Memory;
+use crate::peripherals::scheduler::{Event, SharedScheduler};
+use crate::peripherals::virtio_mmio::*;
+
+const CONFIG_QUEUE_NUM_MAX: u32 = 0x100;
+const CONFIG_DMA_DELAY: u64 = 128;
+/// cycles between the polls of the network for the frames to the guest.
+const RECEIVE_INTERVAL: u64 = 0x4000;
+/// the MAC address of the first NIC of QEMU.
+const CONFIG_MAC: [u8; 6] = [0x52, 0x54, 0x00, 0x12, 0x34, 0x56];
+
+// Feature bits
+const VIRTIO_NET_F_MAC: u64 = 1 << 5;
+const VIRTIO_NET_F_STATUS: u64 = 1 << 16;
+
+const VIRTIO_NET_S_LINK_UP: u16 = 1;
+
+/// struct virtio_net_hdr precedes each frame. It has no num_buffers field without
+/// VIRTIO_NET_F_MRG_RXBUF, and it is all zero without the offloads.
+const VIRTIO_NET_HDR_SIZE: usize = 10;
+
+const RECEIVEQ: usize = 0;
+const TRANSMITQ: usize = 1;
+
+pub struct VirtioNet {
+    transport: VirtioMmio,
+    /// scheduler of the bus.
+    scheduler: SharedScheduler,
+    /// backend of the device. The device is absent without it.
+    network: Option<Box<dyn Network>>,
+    /// the frame from the network which waits for a receive buffer.
+    pending_frame: Option<Vec<u8>>,
+    /// Configuration space: mac and status.
+    config_space: Vec<u8>,
+}
+
+impl VirtioNet {
+    pub fn new(dram_base_addr: u64, scheduler_: SharedScheduler) -> Self {
+        let mut config_space = CONFIG_MAC.to_vec();
+        config_space.extend_from_slice(&VIRTIO_NET_S_LINK_UP.to_le_bytes());
+        VirtioNet {
+            transport: VirtioMmio::new(
+                dram_base_addr,
+                VIRTIO_DEVICE_ID_NET,
+                VIRTIO_NET_F_MAC | VIRTIO_NET_F_STATUS,
+                &[CONFIG_QUEUE_NUM_MAX, CONFIG_QUEUE_NUM_MAX],
+            ),
+            scheduler: scheduler_,
+            network: None,
+            pending_frame: None,
+            config_space,
+        }
+    }
+
+    /// connect the device to the network, and start polling it.
+    pub fn set_network(&mut self, network: Box<dyn Network>) {
+        if self.network.is_none() {
+            let mut scheduler = self.scheduler.borrow_mut();
+            let time = scheduler.get_time();
+            scheduler.schedule(time + RECEIVE_INTERVAL, Event::VirtioNetReceive);
+        }
+        self.network = Some(network);
+    }
+
+    pub fn is_irq(&mut self) -> bool {
+        self.transport.is_irq()
+    }
+
+    /// [Event::VirtioNetReceive] put the frames from the network into the receive
+    /// buffers, and schedule the next poll.
+    pub fn receive(&mut self, dram: &mut Memory) {
+        self.deliver(dram);
+        let mut scheduler = self.scheduler.borrow_mut();
+        let time = scheduler.get_time();
+        scheduler.schedule(time + RECEIVE_INTERVAL, Event::VirtioNetReceive);
+    }
+
+    /// [Event::VirtioNetTransmit] send the frames of the transmit queue to the
+    /// network. The frames sent back at once are received without waiting for
+    /// the next poll.
+    pub fn transmit(&mut self, dram: &mut Memory) {
+        let network = match self.network.as_mut() {
+            Some(network) => network,
+            None => return,
+        };
+        let mut used = false;
+        while let Some(chain) = self.transport.pop(dram, TRANSMITQ) {
+            let data = chain.read(dram);
+            if data.len() > VIRTIO_NET_HDR_SIZE {
+                network.send(&data[VIRTIO_NET_HDR_SIZE..]);
+            }
+            self.transport.push(dram, TRANSMITQ, &chain, 0);
+            used = true;
+        }
+        if used {
+            self.transport.interrupt_queue();
+        }
+        self.deliver(dram);
+    }
+
+    /// put the frames from the network into the receive buffers while the driver
+    /// has them. A frame larger than the buffer is dropped.
+    fn deliver(&mut self, dram: &mut Memory) {
+        let network = match self.network.as_mut() {
+            Some(network) => network,
+            None => return,
+        };
+        let mut used = false;
+        loop {
+            let frame = match self.pending_frame.take() {
+                Some(frame) => frame,
+                None => match network.receive() {
+                    Some(frame) => frame,
+                    None => break,
+                },
+            };
+            let chain = match self.transport.pop(dram, RECEIVEQ) {
+                Some(chain) => chain,
+                None => {
+                    self.pending_frame = Some(frame);
+                    break;
+                }
+            };
+            let mut data = vec![0; VIRTIO_NET_HDR_SIZE];
+            data.extend_from_slice(&frame);
+            let len = match data.len() <= chain.get_writable_size() {
+                true => chain.write(dram, &data),
+                false => 0,
+            };
+            self.transport.push(dram, RECEIVEQ, &chain, len as u32);
+            used = true;
+        }
+        if used {
+            self.transport.interrupt_queue();
+        }
+    }
+
+    pub fn read(&mut self, addr: u64) -> u32 {
+        match addr {
+            // the device ID 0 tells the driver that the slot is empty.
+            VIRTIO_DEVICE_ID if self.network.is_none() => 0,
+            _ if addr >= VIRTIO_CONFIG_SPACE => {
+                read_config(&self.config_space, addr - VIRTIO_CONFIG_SPACE)
+            }
+            _ => self.transport.read(addr),
+        }
+    }
+
+    pub fn write(&mut self, addr: u64, data: u32) {
+        match addr {
+            VIRTIO_QUEUE_NOTIFY => {
+                let mut scheduler = self.scheduler.borrow_mut();
+                let time = scheduler.get_time();
+                match data as usize {
+                    // new receive buffers are filled without waiting for the next poll.
+                    RECEIVEQ if self.network.is_some() => {
+                        scheduler.cancel(Event::VirtioNetReceive);
+                        scheduler.schedule(time + CONFIG_DMA_DELAY, Event::VirtioNetReceive);
+                    }
+                    TRANSMITQ if !scheduler.is_scheduled(Event::VirtioNetTransmit) => {
+                        scheduler.schedule(time + CONFIG_DMA_DELAY, Event::VirtioNetTransmit);
+                    }
+                    _ => {}
+                }
+            }
+            VIRTIO_DEVICE_STATUS if data == 0 => {
+                self.pending_frame = None;
+                self.transport.write(addr, data);
+            }
+            // the MAC address is read-only with VIRTIO_NET_F_MAC.
+            _ if addr >= VIRTIO_CONFIG_SPACE => {}
+            _ => self.transport.write(addr, data),
+        }
+    }
+}
@@ -0,0 +1,202 @@
+// Virtio Entropy Device
+// https://docs.oasis-open.org/virtio/virtio/v1.1/csprd01/virtio-v1.1-csprd01.html#x1-3050004
+
+use std::fs::File;
+use std::io::Read;
+
+use crate::peripherals::memory::Memory;
+use crate::peripherals::scheduler::{Event, SharedScheduler};
+use crate::peripherals::virtio_mmio::*;
+
+const CONFIG_QUEUE_NUM_MAX: u32 = 0x40;
+const CONFIG_DMA_DELAY: u64 = 128;
+const HOST_RNG_PATH: &str = "/dev/urandom";
+
+const REQUESTQ: usize = 0;
+
+/// The source of the entropy which the device gives to the driver.
+#[derive(Clone, Debug)]
+pub enum EntropySource {
+    /// the random number generator of the host OS.
+    Host,
+    /// the pseudo-random numbers from the seed, which make the runs reproducible.
+    Seed(u64),
+}
+
+enum Generator {
+    Host(File),
+    /// state of SplitMix64.
+    Seed(u64),
+}
+
+impl Generator {
+    /// Err if the random number generator of the host can not be opened.
+    fn new(source: EntropySource) -> Result<Self, String> {
+        match source {
+            EntropySource::Host => Generator::open(HOST_RNG_PATH),
+            EntropySource::Seed(seed) => Ok(Generator::Seed(seed)),
+        }
+    }
+
+    fn open(path: &str) -> Result<Self, String> {
+        match File::open(path) {
+            Ok(file) => Ok(Generator::Host(file)),
+            Err(e) => Err(format!("Failed to open {}: {}", path, e)),
+        }
+    }
+
+    /// Err if the host fails to give the entropy. The data is not filled then.
+    fn fill(&mut self, data: &mut [u8]) -> Result<(), ()> {
+        match self {
+            Generator::Host(file) => match file.read_exact(data) {
+                Ok(()) => Ok(()),
+                Err(_e) => Err(()),
+            },
+            Generator::Seed(state) => {
+                for chunk in data.chunks_mut(8) {
+                    // http://xoshiro.di.unimi.it/splitmix64.c
+                    *state = state.wrapping_add(0x9e3779b97f4a7c15);
+                    let mut z = *state;
+                    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+                    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+                    z ^= z >> 31;
+                    chunk.copy_from_slice(&z.to_le_bytes()[..chunk.len()]);
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+pub struct VirtioRng {
+    transport: VirtioMmio,
+    /// scheduler of the bus.
+    scheduler: SharedScheduler,
+    /// The device is absent without the source of the entropy.
+    generator: Option<Generator>,
+}
+
+impl VirtioRng {
+    pub fn new(dram_base_addr: u64, scheduler_: SharedScheduler) -> Self {
+        VirtioRng {
+            transport: VirtioMmio::new(
+                dram_base_addr,
+                VIRTIO_DEVICE_ID_ENTROPY,
+                0,
+                &[CONFIG_QUEUE_NUM_MAX],
+            ),
+            scheduler: scheduler_,
+            generator: None,
+        }
+    }
+
+    /// Err if the source of the entropy is not available.
+    pub fn set_entropy_source(&mut self, source: EntropySource) -> Result<(), String> {
+        match Generator::new(source) {
+            Ok(generator) => {
+                self.generator = Some(generator);
+                Ok(())
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn is_irq(&mut self) -> bool {
+        self.transport.is_irq()
+    }
+
+    /// [Event::VirtioRngCompletion] fill the buffers of the request queue with the
+    /// entropy.
+    pub fn complete(&mut self, dram: &mut Memory) {
+        let generator = match self.generator.as_mut() {
+            Some(generator) => generator,
+            None => return,
+        };
+        let mut used = false;
+        while let Some(chain) = self.transport.pop(dram, REQUESTQ) {
+            let mut data = vec![0; chain.get_writable_size()];
+            // the driver gets no entropy rather than the bytes which are not random
+            // if the host fails.
+            let len = match generator.fill(&mut data) {
+                Ok(()) => chain.write(dram, &data),
+                Err(()) => 0,
+            };
+            self.transport.push(dram, REQUESTQ, &chain, len as u32);
+            used = true;
+        }
+        if used {
+            self.transport.interrupt_queue();
+        }
+    }
+
+    pub fn read(&mut self, addr: u64) -> u32 {
+        match addr {
+            // the device ID 0 tells the driver that the slot is empty.
+            VIRTIO_DEVICE_ID if self.generator.is_none() => 0,
+            // the device has no configuration space.
+            _ if addr >= VIRTIO_CONFIG_SPACE => 0,
+            _ => self.transport.read(addr),
+        }
+    }
+
+    pub fn write(&mut self, addr: u64, data: u32) {
+        match addr {
+            VIRTIO_QUEUE_NOTIFY => {
+                let mut scheduler = self.scheduler.borrow_mut();
+                if !scheduler.is_scheduled(Event::VirtioRngCompletion) {
+                    let time = scheduler.get_time();
+                    scheduler.schedule(time + CONFIG_DMA_DELAY, Event::VirtioRngCompletion);
+                }
+            }
+            _ if addr >= VIRTIO_CONFIG_SPACE => {}
+            _ => self.transport.write(addr, data),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn generate(source: EntropySource, size: usize) -> Vec<u8> {
+        let mut generator = Generator::new(source).unwrap();
+        let mut data = vec![0; size];
+        generator.fill(&mut data).unwrap();
+        data
+    }
+
+    #[test]
+    fn seeded_source_is_deterministic() {
+        // the first output of SplitMix64 from the seed 0.
+        assert_eq!(
+            0xe220a8397b1dcdaf_u64.to_le_bytes().to_vec(),
+            generate(EntropySource::Seed(0), 8)
+        );
+        let data = generate(EntropySource::Seed(42), 64);
+        assert_eq!(data, generate(EntropySource::Seed(42), 64));
+        assert_ne!(data, generate(EntropySource::Seed(43), 64));
+        // a request continues the sequence of the previous requests.
+        let mut generator = Generator::new(EntropySource::Seed(42)).unwrap();
+        let mut requests = vec![0; 64];
+        generator.fill(&mut requests[..24]).unwrap();
+        generator.fill(&mut requests[24..]).unwrap();
+        assert_eq!(data, requests);
+        // a request of a partial word takes the low bytes of the word.
+        assert_eq!(data[..13], generate(EntropySource::Seed(42), 13)[..]);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn host_failure_is_reported() {
+        match Generator::open("/nonexistent/urandom") {
+            Ok(_) => panic!("opened a missing generator"),
+            Err(e) => assert!(e.starts_with("Failed to open /nonexistent/urandom")),
+        }
+        // reading a directory fails.
+        let mut generator = Generator::open("/").unwrap();
+        let mut data = vec![0; 64];
+        assert_eq!(Err(()), generator.fill(&mut data));
+        assert!(matches!(generator, Generator::Host(_)));
+        assert!(data.iter().all(|byte| *byte == 0));
+    }
+}
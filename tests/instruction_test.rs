@@ -7,7 +7,8 @@ use std::path::Path;
 use std::path::PathBuf;
 
 use riscv_emu::console::TtyDummy;
-use riscv_emu::emulator::Emulator;
+use riscv_emu::cpu::mmu::MisalignedAccess;
+use riscv_emu::emulator::{Emulator, ExecutionEngine};
 use riscv_emu::machine::Machine;
 
 fn _read_file(filename: &Path) -> io::Result<Vec<u8>> {
@@ -23,6 +24,37 @@ fn _read_file(filename: &Path) -> io::Result<Vec<u8>> {
 }
 
 fn instruction_test(filename: &'static str) -> u32 {
+    smp_instruction_test(filename, 1)
+}
+
+fn smp_instruction_test(filename: &'static str, harts: usize) -> u32 {
+    run_instruction_test(
+        filename,
+        harts,
+        MisalignedAccess::Hardware,
+        ExecutionEngine::Interpreter,
+    )
+}
+
+fn misaligned_instruction_test(filename: &'static str, misaligned_access: MisalignedAccess) -> u32 {
+    run_instruction_test(filename, 1, misaligned_access, ExecutionEngine::Interpreter)
+}
+
+fn block_instruction_test(filename: &'static str) -> u32 {
+    run_instruction_test(
+        filename,
+        1,
+        MisalignedAccess::Hardware,
+        ExecutionEngine::Block,
+    )
+}
+
+fn run_instruction_test(
+    filename: &'static str,
+    harts: usize,
+    misaligned_access: MisalignedAccess,
+    engine: ExecutionEngine,
+) -> u32 {
     // load program
     let mut root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
     root.push("tests/bin");
@@ -33,6 +65,9 @@ fn instruction_test(filename: &'static str) -> u32 {
     let testmode = true;
     let tty = Box::new(TtyDummy::new());
     let mut emu = Emulator::new(Machine::SiFiveU, tty, testmode);
+    emu.set_harts(harts);
+    emu.set_misaligned_access(misaligned_access);
+    emu.set_execution_engine(engine);
     emu.load_program_from_file(root.as_path());
     let result = match emu.run() {
         Ok(ret) => ret,
@@ -1380,6 +1415,11 @@ fn rv64ua_p_lrsc() {
     assert_eq!(1, instruction_test("rv64ua-p-lrsc"));
 }
 
+#[test]
+fn rv64ua_p_reservation() {
+    assert_eq!(1, instruction_test("rv64ua-p-reservation"));
+}
+
 //***********************************************************************
 // rv64ua (RV64A user-level, Atomic only), virtual memory is enable
 //***********************************************************************
@@ -1511,122 +1551,999 @@ fn rv64uc_v_rvc() {
 }
 
 //***********************************************************************
-// rv32/64si (supervisor-level), integer only
+// rv32uf (RV32F user-level, Single-Precision Floating-Point), virtual memory is disabled
 //***********************************************************************
 #[test]
-fn rv32si_p_csr() {
-    assert_eq!(1, instruction_test("rv32si-p-csr"));
+fn rv32uf_p_fadd() {
+    assert_eq!(1, instruction_test("rv32uf-p-fadd"));
 }
 
-//#[test]
-//fn rv32si_p_dirty() { assert_eq!(1, instruction_test("rv32si-p-dirty")); }
+#[test]
+fn rv32uf_p_fclass() {
+    assert_eq!(1, instruction_test("rv32uf-p-fclass"));
+}
 
-//#[test]
-//fn rv32si_p_ma_fetch() { assert_eq!(1, instruction_test("rv32si-p-ma_fetch")); }
+#[test]
+fn rv32uf_p_fcmp() {
+    assert_eq!(1, instruction_test("rv32uf-p-fcmp"));
+}
 
-//#[test]
-//fn rv32si_p_sbreak() { assert_eq!(1, instruction_test("rv32si-p-sbreak")); }
+#[test]
+fn rv32uf_p_fcvt() {
+    assert_eq!(1, instruction_test("rv32uf-p-fcvt"));
+}
 
 #[test]
-fn rv32si_p_scall() {
-    assert_eq!(1, instruction_test("rv32si-p-scall"));
+fn rv32uf_p_fcvt_w() {
+    assert_eq!(1, instruction_test("rv32uf-p-fcvt_w"));
 }
 
-//#[test]
-//fn rv32si_p_wfi() { assert_eq!(1, instruction_test("rv32si-p-wfi")); }
+#[test]
+fn rv32uf_p_fdiv() {
+    assert_eq!(1, instruction_test("rv32uf-p-fdiv"));
+}
 
-//#[test]
-//fn rv64si_p_csr() { assert_eq!(1, instruction_test("rv64si-p-csr")); }
+#[test]
+fn rv32uf_p_fmadd() {
+    assert_eq!(1, instruction_test("rv32uf-p-fmadd"));
+}
 
 #[test]
-fn rv64si_p_icache() {
-    assert_eq!(1, instruction_test("rv64si-p-icache-alias"));
+fn rv32uf_p_fmin() {
+    assert_eq!(1, instruction_test("rv32uf-p-fmin"));
 }
 
-//#[test]
-//fn rv64si_p_sbreak() { assert_eq!(1, instruction_test("rv64si-p-sbreak")); }
+#[test]
+fn rv32uf_p_ldst() {
+    assert_eq!(1, instruction_test("rv32uf-p-ldst"));
+}
 
-//#[test]
-//fn rv64si_p_wfi() { assert_eq!(1, instruction_test("rv64si-p-wfi")); }
+#[test]
+fn rv32uf_p_move() {
+    assert_eq!(1, instruction_test("rv32uf-p-move"));
+}
 
-//#[test]
-//fn rv64si_p_dirty() { assert_eq!(1, instruction_test("rv64si-p-dirty")); }
+#[test]
+fn rv32uf_p_recoding() {
+    assert_eq!(1, instruction_test("rv32uf-p-recoding"));
+}
 
-//#[test]
-//fn rv64si_p_ma_fetch() { assert_eq!(1, instruction_test("rv64si-p-ma_fetch")); }
+//***********************************************************************
+// rv32uf (RV32F user-level, Single-Precision Floating-Point), virtual memory is enabled
+//***********************************************************************
+#[test]
+fn rv32uf_v_fadd() {
+    assert_eq!(1, instruction_test("rv32uf-v-fadd"));
+}
 
 #[test]
-fn rv64si_p_scall() {
-    assert_eq!(1, instruction_test("rv64si-p-scall"));
+fn rv32uf_v_fclass() {
+    assert_eq!(1, instruction_test("rv32uf-v-fclass"));
+}
+
+#[test]
+fn rv32uf_v_fcmp() {
+    assert_eq!(1, instruction_test("rv32uf-v-fcmp"));
+}
+
+#[test]
+fn rv32uf_v_fcvt() {
+    assert_eq!(1, instruction_test("rv32uf-v-fcvt"));
+}
+
+#[test]
+fn rv32uf_v_fcvt_w() {
+    assert_eq!(1, instruction_test("rv32uf-v-fcvt_w"));
+}
+
+#[test]
+fn rv32uf_v_fdiv() {
+    assert_eq!(1, instruction_test("rv32uf-v-fdiv"));
+}
+
+#[test]
+fn rv32uf_v_fmadd() {
+    assert_eq!(1, instruction_test("rv32uf-v-fmadd"));
+}
+
+#[test]
+fn rv32uf_v_fmin() {
+    assert_eq!(1, instruction_test("rv32uf-v-fmin"));
+}
+
+#[test]
+fn rv32uf_v_ldst() {
+    assert_eq!(1, instruction_test("rv32uf-v-ldst"));
+}
+
+#[test]
+fn rv32uf_v_move() {
+    assert_eq!(1, instruction_test("rv32uf-v-move"));
+}
+
+#[test]
+fn rv32uf_v_recoding() {
+    assert_eq!(1, instruction_test("rv32uf-v-recoding"));
 }
 
 //***********************************************************************
-// rv32/64mi (machine-level), integer only
+// rv64uf (RV64F user-level, Single-Precision Floating-Point), virtual memory is disabled
 //***********************************************************************
-//#[test]
-//fn rv32mi_p_breakpoint() { assert_eq!(1, instruction_test("rv32mi-p-breakpoint")); }
+#[test]
+fn rv64uf_p_fadd() {
+    assert_eq!(1, instruction_test("rv64uf-p-fadd"));
+}
 
-//#[test]
-//fn rv32mi_p_illegal() { assert_eq!(1, instruction_test("rv32mi-p-illegal")); }
+#[test]
+fn rv64uf_p_fclass() {
+    assert_eq!(1, instruction_test("rv64uf-p-fclass"));
+}
 
-//#[test]
-//fn rv32mi_p_ma_fetch() { assert_eq!(1, instruction_test("rv32mi-p-ma_fetch")); }
+#[test]
+fn rv64uf_p_fcmp() {
+    assert_eq!(1, instruction_test("rv64uf-p-fcmp"));
+}
 
 #[test]
-fn rv32mi_p_sbreak() {
-    assert_eq!(1, instruction_test("rv32mi-p-sbreak"));
+fn rv64uf_p_fcvt() {
+    assert_eq!(1, instruction_test("rv64uf-p-fcvt"));
 }
 
-//#[test]
-//fn rv32mi_p_shamt() { assert_eq!(1, instruction_test("rv32mi-p-shamt")); }
+#[test]
+fn rv64uf_p_fcvt_w() {
+    assert_eq!(1, instruction_test("rv64uf-p-fcvt_w"));
+}
 
 #[test]
-fn rv32mi_p_csr() {
-    assert_eq!(1, instruction_test("rv32mi-p-csr"));
+fn rv64uf_p_fdiv() {
+    assert_eq!(1, instruction_test("rv64uf-p-fdiv"));
 }
 
 #[test]
-fn rv32mi_p_ma_addr() {
-    assert_eq!(1, instruction_test("rv32mi-p-ma_addr"));
+fn rv64uf_p_fmadd() {
+    assert_eq!(1, instruction_test("rv64uf-p-fmadd"));
 }
 
-//#[test]
-//fn rv32mi_p_mcsr() { assert_eq!(1, instruction_test("rv32mi-p-mcsr")); }
+#[test]
+fn rv64uf_p_fmin() {
+    assert_eq!(1, instruction_test("rv64uf-p-fmin"));
+}
 
 #[test]
-fn rv32mi_p_scall() {
-    assert_eq!(1, instruction_test("rv32mi-p-scall"));
+fn rv64uf_p_ldst() {
+    assert_eq!(1, instruction_test("rv64uf-p-ldst"));
 }
 
-//#[test]
-//fn rv64mi_p_access() { assert_eq!(1, instruction_test("rv64mi-p-access")); }
+#[test]
+fn rv64uf_p_move() {
+    assert_eq!(1, instruction_test("rv64uf-p-move"));
+}
 
-//#[test]
-//fn rv64mi_p_csr() { assert_eq!(1, instruction_test("rv64mi-p-csr")); }
+#[test]
+fn rv64uf_p_recoding() {
+    assert_eq!(1, instruction_test("rv64uf-p-recoding"));
+}
 
+//***********************************************************************
+// rv64uf (RV64F user-level, Single-Precision Floating-Point), virtual memory is enabled
+//***********************************************************************
 #[test]
-fn rv64mi_p_ma_addr() {
-    assert_eq!(1, instruction_test("rv64mi-p-ma_addr"));
+fn rv64uf_v_fadd() {
+    assert_eq!(1, instruction_test("rv64uf-v-fadd"));
 }
 
-//#[test]
-//fn rv64mi_p_mcsr() { assert_eq!(1, instruction_test("rv64mi-p-mcsr")); }
+#[test]
+fn rv64uf_v_fclass() {
+    assert_eq!(1, instruction_test("rv64uf-v-fclass"));
+}
 
 #[test]
-fn rv64mi_p_scall() {
-    assert_eq!(1, instruction_test("rv64mi-p-scall"));
+fn rv64uf_v_fcmp() {
+    assert_eq!(1, instruction_test("rv64uf-v-fcmp"));
 }
 
-//#[test]
-//fn rv64mi_p_breakpoint() { assert_eq!(1, instruction_test("rv64mi-p-breakpoint")); }
+#[test]
+fn rv64uf_v_fcvt() {
+    assert_eq!(1, instruction_test("rv64uf-v-fcvt"));
+}
 
-//#[test]
-//fn rv64mi_p_illegal() { assert_eq!(1, instruction_test("rv64mi-p-illegal")); }
+#[test]
+fn rv64uf_v_fcvt_w() {
+    assert_eq!(1, instruction_test("rv64uf-v-fcvt_w"));
+}
 
-//#[test]
-//fn rv64mi_p_ma_fetch() { assert_eq!(1, instruction_test("rv64mi-p-ma_fetch")); }
+#[test]
+fn rv64uf_v_fdiv() {
+    assert_eq!(1, instruction_test("rv64uf-v-fdiv"));
+}
 
 #[test]
-fn rv64mi_p_sbreak() {
-    assert_eq!(1, instruction_test("rv64mi-p-sbreak"));
+fn rv64uf_v_fmadd() {
+    assert_eq!(1, instruction_test("rv64uf-v-fmadd"));
+}
+
+#[test]
+fn rv64uf_v_fmin() {
+    assert_eq!(1, instruction_test("rv64uf-v-fmin"));
+}
+
+#[test]
+fn rv64uf_v_ldst() {
+    assert_eq!(1, instruction_test("rv64uf-v-ldst"));
+}
+
+#[test]
+fn rv64uf_v_move() {
+    assert_eq!(1, instruction_test("rv64uf-v-move"));
+}
+
+#[test]
+fn rv64uf_v_recoding() {
+    assert_eq!(1, instruction_test("rv64uf-v-recoding"));
+}
+
+//***********************************************************************
+// rv32ud (RV32D user-level, Double-Precision Floating-Point), virtual memory is disabled
+//***********************************************************************
+#[test]
+fn rv32ud_p_fadd() {
+    assert_eq!(1, instruction_test("rv32ud-p-fadd"));
+}
+
+#[test]
+fn rv32ud_p_fclass() {
+    assert_eq!(1, instruction_test("rv32ud-p-fclass"));
+}
+
+#[test]
+fn rv32ud_p_fcmp() {
+    assert_eq!(1, instruction_test("rv32ud-p-fcmp"));
+}
+
+#[test]
+fn rv32ud_p_fcvt() {
+    assert_eq!(1, instruction_test("rv32ud-p-fcvt"));
+}
+
+#[test]
+fn rv32ud_p_fcvt_w() {
+    assert_eq!(1, instruction_test("rv32ud-p-fcvt_w"));
+}
+
+#[test]
+fn rv32ud_p_fdiv() {
+    assert_eq!(1, instruction_test("rv32ud-p-fdiv"));
+}
+
+#[test]
+fn rv32ud_p_fmadd() {
+    assert_eq!(1, instruction_test("rv32ud-p-fmadd"));
+}
+
+#[test]
+fn rv32ud_p_fmin() {
+    assert_eq!(1, instruction_test("rv32ud-p-fmin"));
+}
+
+#[test]
+fn rv32ud_p_ldst() {
+    assert_eq!(1, instruction_test("rv32ud-p-ldst"));
+}
+
+#[test]
+fn rv32ud_p_recoding() {
+    assert_eq!(1, instruction_test("rv32ud-p-recoding"));
+}
+
+//***********************************************************************
+// rv32ud (RV32D user-level, Double-Precision Floating-Point), virtual memory is enabled
+//***********************************************************************
+#[test]
+fn rv32ud_v_fadd() {
+    assert_eq!(1, instruction_test("rv32ud-v-fadd"));
+}
+
+#[test]
+fn rv32ud_v_fclass() {
+    assert_eq!(1, instruction_test("rv32ud-v-fclass"));
+}
+
+#[test]
+fn rv32ud_v_fcmp() {
+    assert_eq!(1, instruction_test("rv32ud-v-fcmp"));
+}
+
+#[test]
+fn rv32ud_v_fcvt() {
+    assert_eq!(1, instruction_test("rv32ud-v-fcvt"));
+}
+
+#[test]
+fn rv32ud_v_fcvt_w() {
+    assert_eq!(1, instruction_test("rv32ud-v-fcvt_w"));
+}
+
+#[test]
+fn rv32ud_v_fdiv() {
+    assert_eq!(1, instruction_test("rv32ud-v-fdiv"));
+}
+
+#[test]
+fn rv32ud_v_fmadd() {
+    assert_eq!(1, instruction_test("rv32ud-v-fmadd"));
+}
+
+#[test]
+fn rv32ud_v_fmin() {
+    assert_eq!(1, instruction_test("rv32ud-v-fmin"));
+}
+
+#[test]
+fn rv32ud_v_ldst() {
+    assert_eq!(1, instruction_test("rv32ud-v-ldst"));
+}
+
+#[test]
+fn rv32ud_v_recoding() {
+    assert_eq!(1, instruction_test("rv32ud-v-recoding"));
+}
+
+//***********************************************************************
+// rv64ud (RV64D user-level, Double-Precision Floating-Point), virtual memory is disabled
+//***********************************************************************
+#[test]
+fn rv64ud_p_fadd() {
+    assert_eq!(1, instruction_test("rv64ud-p-fadd"));
+}
+
+#[test]
+fn rv64ud_p_fclass() {
+    assert_eq!(1, instruction_test("rv64ud-p-fclass"));
+}
+
+#[test]
+fn rv64ud_p_fcmp() {
+    assert_eq!(1, instruction_test("rv64ud-p-fcmp"));
+}
+
+#[test]
+fn rv64ud_p_fcvt() {
+    assert_eq!(1, instruction_test("rv64ud-p-fcvt"));
+}
+
+#[test]
+fn rv64ud_p_fcvt_w() {
+    assert_eq!(1, instruction_test("rv64ud-p-fcvt_w"));
+}
+
+#[test]
+fn rv64ud_p_fdiv() {
+    assert_eq!(1, instruction_test("rv64ud-p-fdiv"));
+}
+
+#[test]
+fn rv64ud_p_fmadd() {
+    assert_eq!(1, instruction_test("rv64ud-p-fmadd"));
+}
+
+#[test]
+fn rv64ud_p_fmin() {
+    assert_eq!(1, instruction_test("rv64ud-p-fmin"));
+}
+
+#[test]
+fn rv64ud_p_ldst() {
+    assert_eq!(1, instruction_test("rv64ud-p-ldst"));
+}
+
+#[test]
+fn rv64ud_p_move() {
+    assert_eq!(1, instruction_test("rv64ud-p-move"));
+}
+
+#[test]
+fn rv64ud_p_recoding() {
+    assert_eq!(1, instruction_test("rv64ud-p-recoding"));
+}
+
+#[test]
+fn rv64ud_p_structural() {
+    assert_eq!(1, instruction_test("rv64ud-p-structural"));
+}
+
+//***********************************************************************
+// rv64ud (RV64D user-level, Double-Precision Floating-Point), virtual memory is enabled
+//***********************************************************************
+#[test]
+fn rv64ud_v_fadd() {
+    assert_eq!(1, instruction_test("rv64ud-v-fadd"));
+}
+
+#[test]
+fn rv64ud_v_fclass() {
+    assert_eq!(1, instruction_test("rv64ud-v-fclass"));
+}
+
+#[test]
+fn rv64ud_v_fcmp() {
+    assert_eq!(1, instruction_test("rv64ud-v-fcmp"));
+}
+
+#[test]
+fn rv64ud_v_fcvt() {
+    assert_eq!(1, instruction_test("rv64ud-v-fcvt"));
+}
+
+#[test]
+fn rv64ud_v_fcvt_w() {
+    assert_eq!(1, instruction_test("rv64ud-v-fcvt_w"));
+}
+
+#[test]
+fn rv64ud_v_fdiv() {
+    assert_eq!(1, instruction_test("rv64ud-v-fdiv"));
+}
+
+#[test]
+fn rv64ud_v_fmadd() {
+    assert_eq!(1, instruction_test("rv64ud-v-fmadd"));
+}
+
+#[test]
+fn rv64ud_v_fmin() {
+    assert_eq!(1, instruction_test("rv64ud-v-fmin"));
+}
+
+#[test]
+fn rv64ud_v_ldst() {
+    assert_eq!(1, instruction_test("rv64ud-v-ldst"));
+}
+
+#[test]
+fn rv64ud_v_move() {
+    assert_eq!(1, instruction_test("rv64ud-v-move"));
+}
+
+#[test]
+fn rv64ud_v_recoding() {
+    assert_eq!(1, instruction_test("rv64ud-v-recoding"));
+}
+
+#[test]
+fn rv64ud_v_structural() {
+    assert_eq!(1, instruction_test("rv64ud-v-structural"));
+}
+
+//***********************************************************************
+// rv32uv (RV32V user-level, Vector), virtual memory is disable
+//***********************************************************************
+#[test]
+fn rv32uv_p_vsetvl() {
+    assert_eq!(1, instruction_test("rv32uv-p-vsetvl"));
+}
+
+#[test]
+fn rv32uv_p_vmem() {
+    assert_eq!(1, instruction_test("rv32uv-p-vmem"));
+}
+
+#[test]
+fn rv32uv_p_vint() {
+    assert_eq!(1, instruction_test("rv32uv-p-vint"));
+}
+
+#[test]
+fn rv32uv_p_vwiden() {
+    assert_eq!(1, instruction_test("rv32uv-p-vwiden"));
+}
+
+#[test]
+fn rv32uv_p_vfixed() {
+    assert_eq!(1, instruction_test("rv32uv-p-vfixed"));
+}
+
+#[test]
+fn rv32uv_p_vred() {
+    assert_eq!(1, instruction_test("rv32uv-p-vred"));
+}
+
+#[test]
+fn rv32uv_p_vmask() {
+    assert_eq!(1, instruction_test("rv32uv-p-vmask"));
+}
+
+#[test]
+fn rv32uv_p_vperm() {
+    assert_eq!(1, instruction_test("rv32uv-p-vperm"));
+}
+
+//***********************************************************************
+// rv64uv (RV64V user-level, Vector), virtual memory is disable
+//***********************************************************************
+#[test]
+fn rv64uv_p_vsetvl() {
+    assert_eq!(1, instruction_test("rv64uv-p-vsetvl"));
+}
+
+#[test]
+fn rv64uv_p_vmem() {
+    assert_eq!(1, instruction_test("rv64uv-p-vmem"));
+}
+
+#[test]
+fn rv64uv_p_vint() {
+    assert_eq!(1, instruction_test("rv64uv-p-vint"));
+}
+
+#[test]
+fn rv64uv_p_vwiden() {
+    assert_eq!(1, instruction_test("rv64uv-p-vwiden"));
+}
+
+#[test]
+fn rv64uv_p_vfixed() {
+    assert_eq!(1, instruction_test("rv64uv-p-vfixed"));
+}
+
+#[test]
+fn rv64uv_p_vred() {
+    assert_eq!(1, instruction_test("rv64uv-p-vred"));
+}
+
+#[test]
+fn rv64uv_p_vmask() {
+    assert_eq!(1, instruction_test("rv64uv-p-vmask"));
+}
+
+#[test]
+fn rv64uv_p_vperm() {
+    assert_eq!(1, instruction_test("rv64uv-p-vperm"));
+}
+
+//***********************************************************************
+// rv32uzb (RV32 user-level, Bit-Manipulation), virtual memory is disable
+//***********************************************************************
+
+#[test]
+fn rv32uzba_p_zba() {
+    assert_eq!(1, instruction_test("rv32uzba-p-zba"));
+}
+
+#[test]
+fn rv32uzbb_p_zbb() {
+    assert_eq!(1, instruction_test("rv32uzbb-p-zbb"));
+}
+
+#[test]
+fn rv32uzbc_p_zbc() {
+    assert_eq!(1, instruction_test("rv32uzbc-p-zbc"));
+}
+
+#[test]
+fn rv32uzbs_p_zbs() {
+    assert_eq!(1, instruction_test("rv32uzbs-p-zbs"));
+}
+
+//***********************************************************************
+// rv64uzb (RV64 user-level, Bit-Manipulation), virtual memory is disable
+//***********************************************************************
+
+#[test]
+fn rv64uzba_p_zba() {
+    assert_eq!(1, instruction_test("rv64uzba-p-zba"));
+}
+
+#[test]
+fn rv64uzbb_p_zbb() {
+    assert_eq!(1, instruction_test("rv64uzbb-p-zbb"));
+}
+
+#[test]
+fn rv64uzbc_p_zbc() {
+    assert_eq!(1, instruction_test("rv64uzbc-p-zbc"));
+}
+
+#[test]
+fn rv64uzbs_p_zbs() {
+    assert_eq!(1, instruction_test("rv64uzbs-p-zbs"));
+}
+
+//***********************************************************************
+// rv32/64si (supervisor-level), integer only
+//***********************************************************************
+#[test]
+fn rv32si_p_csr() {
+    assert_eq!(1, instruction_test("rv32si-p-csr"));
+}
+
+//#[test]
+//fn rv32si_p_dirty() { assert_eq!(1, instruction_test("rv32si-p-dirty")); }
+
+//#[test]
+//fn rv32si_p_ma_fetch() { assert_eq!(1, instruction_test("rv32si-p-ma_fetch")); }
+
+//#[test]
+//fn rv32si_p_sbreak() { assert_eq!(1, instruction_test("rv32si-p-sbreak")); }
+
+#[test]
+fn rv32si_p_scall() {
+    assert_eq!(1, instruction_test("rv32si-p-scall"));
+}
+
+//#[test]
+//fn rv32si_p_wfi() { assert_eq!(1, instruction_test("rv32si-p-wfi")); }
+
+#[test]
+fn rv64si_p_csr() {
+    assert_eq!(1, instruction_test("rv64si-p-csr"));
+}
+
+#[test]
+fn rv64si_p_icache() {
+    assert_eq!(1, instruction_test("rv64si-p-icache-alias"));
+}
+
+//#[test]
+//fn rv64si_p_sbreak() { assert_eq!(1, instruction_test("rv64si-p-sbreak")); }
+
+//#[test]
+//fn rv64si_p_wfi() { assert_eq!(1, instruction_test("rv64si-p-wfi")); }
+
+//#[test]
+//fn rv64si_p_dirty() { assert_eq!(1, instruction_test("rv64si-p-dirty")); }
+
+//#[test]
+//fn rv64si_p_ma_fetch() { assert_eq!(1, instruction_test("rv64si-p-ma_fetch")); }
+
+#[test]
+fn rv64si_p_scall() {
+    assert_eq!(1, instruction_test("rv64si-p-scall"));
+}
+
+//***********************************************************************
+// rv32/64mi (machine-level), integer only
+//***********************************************************************
+//#[test]
+//fn rv32mi_p_breakpoint() { assert_eq!(1, instruction_test("rv32mi-p-breakpoint")); }
+
+//#[test]
+//fn rv32mi_p_illegal() { assert_eq!(1, instruction_test("rv32mi-p-illegal")); }
+
+//#[test]
+//fn rv32mi_p_ma_fetch() { assert_eq!(1, instruction_test("rv32mi-p-ma_fetch")); }
+
+#[test]
+fn rv32mi_p_sbreak() {
+    assert_eq!(1, instruction_test("rv32mi-p-sbreak"));
+}
+
+//#[test]
+//fn rv32mi_p_shamt() { assert_eq!(1, instruction_test("rv32mi-p-shamt")); }
+
+#[test]
+fn rv32mi_p_csr() {
+    assert_eq!(1, instruction_test("rv32mi-p-csr"));
+}
+
+#[test]
+fn rv32mi_p_ma_addr() {
+    assert_eq!(1, instruction_test("rv32mi-p-ma_addr"));
+}
+
+#[test]
+fn rv32mi_p_mcsr() {
+    assert_eq!(1, instruction_test("rv32mi-p-mcsr"));
+}
+
+#[test]
+fn rv32mi_p_scall() {
+    assert_eq!(1, instruction_test("rv32mi-p-scall"));
+}
+
+#[test]
+fn rv32mi_p_pmpaddr() {
+    assert_eq!(1, instruction_test("rv32mi-p-pmpaddr"));
+}
+
+#[test]
+fn rv32mi_p_illegal_decode() {
+    assert_eq!(1, instruction_test("rv32mi-p-illegal_decode"));
+}
+
+#[test]
+fn rv32mi_p_counters() {
+    assert_eq!(1, instruction_test("rv32mi-p-counters"));
+}
+
+#[test]
+fn rv32mi_p_sstc() {
+    assert_eq!(1, instruction_test("rv32mi-p-sstc"));
+}
+
+#[test]
+fn rv32mi_p_tvec() {
+    assert_eq!(1, instruction_test("rv32mi-p-tvec"));
+}
+
+#[test]
+fn rv32mi_p_csr_table() {
+    assert_eq!(1, instruction_test("rv32mi-p-csr_table"));
+}
+
+#[test]
+fn rv32mi_p_csr_audit() {
+    assert_eq!(1, instruction_test("rv32mi-p-csr_audit"));
+}
+
+#[test]
+fn rv32mi_p_misaligned_trap() {
+    assert_eq!(
+        1,
+        misaligned_instruction_test("rv32mi-p-misaligned_trap", MisalignedAccess::Trap)
+    );
+}
+
+#[test]
+fn rv32mi_p_misaligned_hardware() {
+    assert_eq!(
+        1,
+        misaligned_instruction_test("rv32mi-p-misaligned_hardware", MisalignedAccess::Hardware)
+    );
+}
+
+#[test]
+fn rv32mi_p_misaligned_emulate() {
+    assert_eq!(
+        1,
+        misaligned_instruction_test("rv32mi-p-misaligned_emulate", MisalignedAccess::Emulate)
+    );
+}
+
+#[test]
+fn rv32mi_p_smc() {
+    assert_eq!(1, instruction_test("rv32mi-p-smc"));
+}
+
+//#[test]
+//fn rv64mi_p_access() { assert_eq!(1, instruction_test("rv64mi-p-access")); }
+
+#[test]
+fn rv64mi_p_csr() {
+    assert_eq!(1, instruction_test("rv64mi-p-csr"));
+}
+
+#[test]
+fn rv64mi_p_ma_addr() {
+    assert_eq!(1, instruction_test("rv64mi-p-ma_addr"));
+}
+
+#[test]
+fn rv64mi_p_mcsr() {
+    assert_eq!(1, instruction_test("rv64mi-p-mcsr"));
+}
+
+#[test]
+fn rv64mi_p_scall() {
+    assert_eq!(1, instruction_test("rv64mi-p-scall"));
+}
+
+#[test]
+fn rv64mi_p_pmpaddr() {
+    assert_eq!(1, instruction_test("rv64mi-p-pmpaddr"));
+}
+
+#[test]
+fn rv64mi_p_smp() {
+    assert_eq!(1, smp_instruction_test("rv64mi-p-smp", 2));
+}
+
+#[test]
+fn rv64mi_p_illegal_decode() {
+    assert_eq!(1, instruction_test("rv64mi-p-illegal_decode"));
+}
+
+#[test]
+fn rv64mi_p_counters() {
+    assert_eq!(1, instruction_test("rv64mi-p-counters"));
+}
+
+#[test]
+fn rv64mi_p_sstc() {
+    assert_eq!(1, instruction_test("rv64mi-p-sstc"));
+}
+
+#[test]
+fn rv64mi_p_tvec() {
+    assert_eq!(1, instruction_test("rv64mi-p-tvec"));
+}
+
+#[test]
+fn rv64mi_p_csr_table() {
+    assert_eq!(1, instruction_test("rv64mi-p-csr_table"));
+}
+
+#[test]
+fn rv64mi_p_csr_audit() {
+    assert_eq!(1, instruction_test("rv64mi-p-csr_audit"));
+}
+
+#[test]
+fn rv64mi_p_misaligned_trap() {
+    assert_eq!(
+        1,
+        misaligned_instruction_test("rv64mi-p-misaligned_trap", MisalignedAccess::Trap)
+    );
+}
+
+#[test]
+fn rv64mi_p_misaligned_hardware() {
+    assert_eq!(
+        1,
+        misaligned_instruction_test("rv64mi-p-misaligned_hardware", MisalignedAccess::Hardware)
+    );
+}
+
+#[test]
+fn rv64mi_p_misaligned_emulate() {
+    assert_eq!(
+        1,
+        misaligned_instruction_test("rv64mi-p-misaligned_emulate", MisalignedAccess::Emulate)
+    );
+}
+
+#[test]
+fn rv64mi_p_hypervisor() {
+    assert_eq!(1, instruction_test("rv64mi-p-hypervisor"));
+}
+
+#[test]
+fn rv64mi_p_sv48_sv57() {
+    assert_eq!(1, instruction_test("rv64mi-p-sv48_sv57"));
+}
+
+#[test]
+fn rv64mi_p_tlb() {
+    assert_eq!(1, instruction_test("rv64mi-p-tlb"));
+}
+
+#[test]
+fn rv64mi_p_smc() {
+    assert_eq!(1, instruction_test("rv64mi-p-smc"));
+}
+
+#[test]
+fn rv64mi_p_idle_skip() {
+    assert_eq!(1, instruction_test("rv64mi-p-idle_skip"));
+}
+
+//#[test]
+//fn rv64mi_p_breakpoint() { assert_eq!(1, instruction_test("rv64mi-p-breakpoint")); }
+
+//#[test]
+//fn rv64mi_p_illegal() { assert_eq!(1, instruction_test("rv64mi-p-illegal")); }
+
+//#[test]
+//fn rv64mi_p_ma_fetch() { assert_eq!(1, instruction_test("rv64mi-p-ma_fetch")); }
+
+#[test]
+fn rv64mi_p_sbreak() {
+    assert_eq!(1, instruction_test("rv64mi-p-sbreak"));
+}
+
+//***********************************************************************
+// basic-block engine
+//***********************************************************************
+#[test]
+fn block_rv32ui_p_lw() {
+    assert_eq!(1, block_instruction_test("rv32ui-p-lw"));
+}
+
+#[test]
+fn block_rv32ui_p_fence_i() {
+    assert_eq!(1, block_instruction_test("rv32ui-p-fence_i"));
+}
+
+#[test]
+fn block_rv32ui_v_fence_i() {
+    assert_eq!(1, block_instruction_test("rv32ui-v-fence_i"));
+}
+
+#[test]
+fn block_rv32uc_p_rvc() {
+    assert_eq!(1, block_instruction_test("rv32uc-p-rvc"));
+}
+
+#[test]
+fn block_rv32mi_p_sstc() {
+    assert_eq!(1, block_instruction_test("rv32mi-p-sstc"));
+}
+
+#[test]
+fn block_rv64ui_p_fence_i() {
+    assert_eq!(1, block_instruction_test("rv64ui-p-fence_i"));
+}
+
+#[test]
+fn block_rv64ui_v_add() {
+    assert_eq!(1, block_instruction_test("rv64ui-v-add"));
+}
+
+#[test]
+fn block_rv64ui_v_fence_i() {
+    assert_eq!(1, block_instruction_test("rv64ui-v-fence_i"));
+}
+
+#[test]
+fn block_rv64uc_v_rvc() {
+    assert_eq!(1, block_instruction_test("rv64uc-v-rvc"));
+}
+
+#[test]
+fn block_rv64ua_p_lrsc() {
+    assert_eq!(1, block_instruction_test("rv64ua-p-lrsc"));
+}
+
+#[test]
+fn block_rv64mi_p_mcsr() {
+    assert_eq!(1, block_instruction_test("rv64mi-p-mcsr"));
+}
+
+#[test]
+fn block_rv64mi_p_sstc() {
+    assert_eq!(1, block_instruction_test("rv64mi-p-sstc"));
+}
+
+#[test]
+fn block_rv32mi_p_smc() {
+    assert_eq!(1, block_instruction_test("rv32mi-p-smc"));
+}
+
+#[test]
+fn block_rv64mi_p_smc() {
+    assert_eq!(1, block_instruction_test("rv64mi-p-smc"));
+}
+
+#[test]
+fn block_rv64mi_p_idle_skip() {
+    assert_eq!(1, block_instruction_test("rv64mi-p-idle_skip"));
+}
+
+#[test]
+fn block_rv32uzba_p_zba() {
+    assert_eq!(1, block_instruction_test("rv32uzba-p-zba"));
+}
+
+#[test]
+fn block_rv32uzbb_p_zbb() {
+    assert_eq!(1, block_instruction_test("rv32uzbb-p-zbb"));
+}
+
+#[test]
+fn block_rv32uzbc_p_zbc() {
+    assert_eq!(1, block_instruction_test("rv32uzbc-p-zbc"));
+}
+
+#[test]
+fn block_rv32uzbs_p_zbs() {
+    assert_eq!(1, block_instruction_test("rv32uzbs-p-zbs"));
+}
+
+#[test]
+fn block_rv64uzba_p_zba() {
+    assert_eq!(1, block_instruction_test("rv64uzba-p-zba"));
+}
+
+#[test]
+fn block_rv64uzbb_p_zbb() {
+    assert_eq!(1, block_instruction_test("rv64uzbb-p-zbb"));
+}
+
+#[test]
+fn block_rv64uzbc_p_zbc() {
+    assert_eq!(1, block_instruction_test("rv64uzbc-p-zbc"));
+}
+
+#[test]
+fn block_rv64uzbs_p_zbs() {
+    assert_eq!(1, block_instruction_test("rv64uzbs-p-zbs"));
 }
@@ -0,0 +1,159 @@
+#!/usr/bin/env python3
+# Generate the csr_audit test, which reads all 4096 CSR addresses, and then reads and
+# writes back each of them, in M-mode. The illegal instruction exceptions are recorded
+# in bitmaps, which are compared with the CSRs expected from the privileged spec.
+#
+# usage: csr_audit.py > csr_audit.S
+
+def exists(a, xlen):
+    rv32 = xlen == 32
+    s = set()
+    s |= {0x001, 0x002, 0x003, 0x008, 0x009, 0x00a, 0x00f, 0xc20, 0xc21, 0xc22}
+    s |= set(range(0xc00, 0xc20))
+    if rv32:
+        s |= set(range(0xc80, 0xca0))
+    s |= {0x100, 0x104, 0x105, 0x106, 0x140, 0x141, 0x142, 0x143, 0x144, 0x14d, 0x180}
+    if rv32:
+        s |= {0x15d}
+    s |= {0x600, 0x602, 0x603, 0x604, 0x605, 0x606, 0x607, 0x643, 0x644, 0x645,
+          0x64a, 0x680, 0xe12}
+    if rv32:
+        s |= {0x615}
+    s |= {0x200, 0x204, 0x205, 0x240, 0x241, 0x242, 0x243, 0x244, 0x280}
+    s |= {0xf11, 0xf12, 0xf13, 0xf14, 0xf15}
+    s |= {0x300, 0x301, 0x302, 0x303, 0x304, 0x305, 0x306, 0x30a, 0x340, 0x341,
+          0x342, 0x343, 0x344, 0x34a, 0x34b}
+    if rv32:
+        s |= {0x310, 0x31a}
+    s |= {0x3a0 + i for i in range(16) if rv32 or i % 2 == 0}
+    s |= set(range(0x3b0, 0x3f0))
+    s |= {0xb00, 0xb02, 0x320}
+    s |= set(range(0xb03, 0xb20))
+    s |= set(range(0x323, 0x340))
+    if rv32:
+        s |= {0xb80, 0xb82}
+        s |= set(range(0xb83, 0xba0))
+    return a in s
+
+def read_traps(xlen):
+    return [not exists(a, xlen) for a in range(4096)]
+
+def rw_traps(xlen):
+    traps = []
+    for a in range(4096):
+        traps += [not exists(a, xlen), not exists(a, xlen) or (a >> 10) == 3]
+    return traps
+
+def bitmap(bits):
+    out = bytearray(len(bits) // 8)
+    for i, b in enumerate(bits):
+        if b:
+            out[i // 8] |= 1 << (i % 8)
+    return out
+
+def print_bytes(data):
+    for i in range(0, len(data), 16):
+        print('  .byte ' + ', '.join('0x%02x' % b for b in data[i:i + 16]))
+
+def print_bitmaps(bits):
+    print('.if XLEN == 32')
+    print_bytes(bitmap(bits(32)))
+    print('.else')
+    print_bytes(bitmap(bits(64)))
+    print('.endif')
+
+print('''# Generated by csr_audit.py
+.option norelax
+.equ BITMAP, 0x80100000
+_start:
+  j reset_vector
+.align 6
+trap_vector:
+  csrr t5, mcause
+  li t6, 2
+  bne t5, t6, fail
+  csrr t5, mepc
+  sub t5, t5, s0
+  srli t5, t5, 2
+  srli t6, t5, 3
+  add t6, t6, s1
+  lbu a0, 0(t6)
+  andi t5, t5, 7
+  li a1, 1
+  sll a1, a1, t5
+  or a0, a0, a1
+  sb a0, 0(t6)
+  csrr t5, mepc
+  addi t5, t5, 4
+  csrw mepc, t5
+  mret
+pass:
+  li t0, TOHOST
+  li t1, 1
+  sw t1, 0(t0)
+1:
+  j 1b
+fail:
+  slli t1, gp, 1
+  ori t1, t1, 1
+  li t0, TOHOST
+  sw t1, 0(t0)
+1:
+  j 1b
+# compare a1 bytes at a0 with the expected bytes at a2. gp is 2 + the offset of
+# the first mismatching byte on a mismatch.
+compare:
+  li t2, 0
+1:
+  add t3, a0, t2
+  lbu t3, 0(t3)
+  add t4, a2, t2
+  lbu t4, 0(t4)
+  beq t3, t4, 2f
+  addi gp, t2, 2
+  add gp, gp, s2
+  j fail
+2:
+  addi t2, t2, 1
+  bne t2, a1, 1b
+  ret
+reset_vector:
+  lla t0, trap_vector
+  csrw mtvec, t0
+  li t0, 0x6600
+  csrs mstatus, t0
+  li s1, BITMAP
+  li t0, 0
+1:
+  add t1, s1, t0
+  sb zero, 0(t1)
+  addi t0, t0, 1
+  li t1, 1536
+  bne t0, t1, 1b
+
+  li gp, 1
+  lla s0, read_all
+read_all:''')
+for a in range(4096):
+    print('  csrrs x0, 0x%03x, x0' % a)
+print('''  li s1, BITMAP + 512
+  lla s0, write_all
+write_all:''')
+for a in range(4096):
+    print('  csrrs t0, 0x%03x, x0' % a)
+    print('  csrrw x0, 0x%03x, t0' % a)
+print('''  li s2, 0
+  li a0, BITMAP
+  li a1, 512
+  lla a2, expected_read
+  call compare
+  li s2, 10000
+  li a0, BITMAP + 512
+  li a1, 1024
+  lla a2, expected_write
+  call compare
+  j pass
+expected_read:''')
+print_bitmaps(read_traps)
+print('expected_write:')
+print_bitmaps(rw_traps)
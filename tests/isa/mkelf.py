@@ -0,0 +1,48 @@
+#!/usr/bin/env python3
+# Wrap a flat image loaded at 0x80000000 into a riscv-tests style ELF, as
+# there is no RISC-V linker needed to build the tests in this directory.
+#
+#   mkelf.py XLEN IMAGE OUTPUT [TOHOST_OFFSET]
+#
+# The image is .text.init, followed by .tohost at TOHOST_OFFSET (0x1000 by
+# default) and 0x3000 bytes of .data.
+import struct, sys
+xlen = int(sys.argv[1]); img = open(sys.argv[2], 'rb').read(); out = sys.argv[3]
+tohost = int(sys.argv[4], 0) if len(sys.argv) > 4 else 0x1000
+base = 0x80000000
+if len(img) > tohost:
+    sys.exit('%s: the image of 0x%x bytes overlaps .tohost at 0x%x' % (sys.argv[2], len(img), tohost))
+img = img.ljust(tohost + 0x4000, b'\0')
+secs = [('.text.init', 0x0, tohost, 6), ('.tohost', tohost, 0x48, 3), ('.data', tohost + 0x1000, 0x3000, 3)]
+shstr = b'\0' + b''.join(n.encode() + b'\0' for n, *_ in secs) + b'.shstrtab\0'
+names = []; off = 1
+for n, *_ in secs:
+    names.append(off); off += len(n) + 1
+shstr_name = off
+is64 = xlen == 64
+ehsize = 64 if is64 else 52; phent = 56 if is64 else 32; shent = 64 if is64 else 40
+data_off = 0x1000
+phnum = len(secs)
+shstr_off = data_off + len(img)
+shoff = (shstr_off + len(shstr) + 7) & ~7
+f = bytearray()
+ident = b'\x7fELF' + bytes([2 if is64 else 1, 1, 1, 0]) + b'\0' * 8
+fmt = '<HHIQQQIHHHHHH' if is64 else '<HHIIIIIHHHHHH'
+f += ident + struct.pack(fmt, 2, 243, 1, base, ehsize, shoff, 0, ehsize, phent, phnum, shent, len(secs) + 2, len(secs) + 1)
+for n, o, sz, fl in secs:
+    pflags = 5 if fl == 6 else 6
+    if is64:
+        f += struct.pack('<IIQQQQQQ', 1, pflags, data_off + o, base + o, base + o, sz, sz, 0x1000)
+    else:
+        f += struct.pack('<IIIIIIII', 1, data_off + o, base + o, base + o, sz, sz, pflags, 0x1000)
+f = f.ljust(data_off, b'\0') + img + shstr
+f = f.ljust(shoff, b'\0')
+def sh(name, typ, flags, addr, off, size):
+    if is64:
+        return struct.pack('<IIQQQQIIQQ', name, typ, flags, addr, off, size, 0, 0, 16, 0)
+    return struct.pack('<IIIIIIIIII', name, typ, flags, addr, off, size, 0, 0, 16, 0)
+f += sh(0, 0, 0, 0, 0, 0)
+for (n, o, sz, fl), nm in zip(secs, names):
+    f += sh(nm, 1, fl, base + o, data_off + o, sz)
+f += sh(shstr_name, 3, 0, 0, shstr_off, len(shstr))
+open(out, 'wb').write(f)
@@ -0,0 +1,1299 @@
+#!/usr/bin/env python3
+# Generate riscv-tests style RVV 1.0 test programs checked against a Python
+# reference model. usage: vector.py XLEN SUITE > rvXXuv-p-SUITE.S
+import random, sys, math
+
+VLEN = 128
+VLENB = VLEN // 8
+XLEN = int(sys.argv[1])
+SUITE = sys.argv[2]
+rng = random.Random(XLEN * 1000 + sum(map(ord, SUITE)))
+
+SCRATCH = 0x80100000  # result dumps
+FAULT = 0x80400000  # the page is made inaccessible by a locked PMP entry
+
+
+def m(bits):
+    return (1 << bits) - 1
+
+
+def sx(v, bits):
+    v &= m(bits)
+    return v - (1 << bits) if v >> (bits - 1) else v
+
+
+class Gen:
+    def __init__(self):
+        self.code = []
+        self.data = []
+        self.labels = 0
+        self.test = 1
+
+    def label(self):
+        self.labels += 1
+        return 'd%d' % self.labels
+
+    def emit(self, s):
+        self.code.append('  ' + s)
+
+    def bytes_data(self, b, align=3):
+        lab = self.label()
+        self.data.append('.balign %d, 0' % (1 << align))
+        self.data.append('%s:' % lab)
+        # keep the data a multiple of 8 bytes, as the text section cannot be padded
+        # by the odd bytes.
+        b = bytes(b) + bytes(-len(b) % 8)
+        for i in range(0, len(b), 16):
+            self.data.append('  .byte ' + ','.join(str(x) for x in b[i:i + 16]))
+        if not b:
+            self.data.append('  .byte 0')
+        return lab
+
+    def new_test(self, comment=''):
+        self.test += 1
+        self.emit('TEST %d  # %s' % (self.test, comment))
+
+    def li(self, reg, value):
+        value &= m(XLEN)
+        self.emit('li %s, %d' % (reg, sx(value, XLEN)))
+
+    def check_x(self, reg, value):
+        self.li('t6', value)
+        self.emit('bne %s, t6, fail' % reg)
+
+    def check_mem(self, addr, expected, care=None):
+        """compare the bytes at addr with expected where care is set."""
+        if care is None:
+            care = bytes([0xff] * len(expected))
+        e = self.bytes_data(bytes(expected))
+        c = self.bytes_data(bytes(care))
+        self.li('a0', addr)
+        self.emit('lla a1, %s' % e)
+        self.emit('lla a2, %s' % c)
+        self.li('a3', len(expected))
+        self.emit('call check')
+
+
+# ---------------------------------------------------------------------------
+# reference model of the vector register file
+class VRF:
+    def __init__(self):
+        self.b = bytearray(32 * VLENB)
+
+    def get(self, reg, i, eew):
+        o = reg * VLENB + i * eew // 8
+        return int.from_bytes(self.b[o:o + eew // 8], 'little')
+
+    def set(self, reg, i, eew, val):
+        o = reg * VLENB + i * eew // 8
+        self.b[o:o + eew // 8] = (val & m(eew)).to_bytes(eew // 8, 'little')
+
+    def getm(self, reg, i):
+        return (self.b[reg * VLENB + i // 8] >> (i % 8)) & 1
+
+    def setm(self, reg, i, v):
+        o = reg * VLENB + i // 8
+        if v:
+            self.b[o] |= 1 << (i % 8)
+        else:
+            self.b[o] &= ~(1 << (i % 8)) & 0xff
+
+    def group(self, reg, n):
+        return bytes(self.b[reg * VLENB:(reg + n) * VLENB])
+
+
+LMULS = {'mf8': -3, 'mf4': -2, 'mf2': -1, 'm1': 0, 'm2': 1, 'm4': 2, 'm8': 3}
+
+
+def vlmax(sew, lmul):
+    l = LMULS[lmul]
+    return (VLEN // sew) << l if l >= 0 else (VLEN // sew) >> -l
+
+
+def regs(lmul_log2):
+    return 1 << max(0, lmul_log2)
+
+
+def interesting(bits):
+    vals = [0, 1, m(bits), 1 << (bits - 1), m(bits - 1), 2, m(bits) - 1, 3]
+    if rng.random() < 0.4:
+        return rng.choice(vals)
+    return rng.getrandbits(bits)
+
+
+g = Gen()
+v = VRF()
+
+
+def load_group(reg, n, content=None):
+    """fill the register group with random or the given bytes by a whole register load."""
+    if content is None:
+        content = bytes(rng.getrandbits(8) for _ in range(n * VLENB))
+    v.b[reg * VLENB:(reg + n) * VLENB] = content
+    lab = g.bytes_data(content)
+    g.emit('lla a0, %s' % lab)
+    g.emit('vl%dre8.v v%d, (a0)' % (n, reg))
+
+
+def load_elements(reg, n, sew, gen):
+    content = bytearray()
+    for i in range(n * VLENB * 8 // sew):
+        content += (gen(i) & m(sew)).to_bytes(sew // 8, 'little')
+    load_group(reg, n, bytes(content))
+
+
+def vset(sew, lmul, avl, check=True):
+    g.li('t0', avl)
+    g.emit('vsetvli t1, t0, e%d, %s, tu, mu' % (sew, lmul))
+    vl = min(avl, vlmax(sew, lmul))
+    if check:
+        g.check_x('t1', vl)
+    return vl
+
+
+def dump(reg, n, care=None):
+    g.li('a0', SCRATCH)
+    g.emit('vs%dr.v v%d, (a0)' % (n, reg))
+    g.check_mem(SCRATCH, v.group(reg, n), care)
+
+
+def mask_care(vl):
+    """only the body bits of a mask destination are checked; its tail is agnostic."""
+    care = bytearray(VLENB)
+    for i in range(vl):
+        care[i // 8] |= 1 << (i % 8)
+    return bytes(care)
+
+
+# ---------------------------------------------------------------------------
+# element operations: f(sew, a, b) where a is vs2 and b is vs1/rs1/imm
+def sdiv(sew, a, b):
+    a, b = sx(a, sew), sx(b, sew)
+    if b == 0:
+        return -1
+    if a == -(1 << (sew - 1)) and b == -1:
+        return a
+    q = abs(a) // abs(b)
+    return q if (a < 0) == (b < 0) else -q
+
+
+def srem(sew, a, b):
+    a2, b2 = sx(a, sew), sx(b, sew)
+    if b2 == 0:
+        return a2
+    if a2 == -(1 << (sew - 1)) and b2 == -1:
+        return 0
+    r = abs(a2) % abs(b2)
+    return -r if a2 < 0 else r
+
+
+INT_OPS = {
+    'vadd': lambda s, a, b: a + b,
+    'vsub': lambda s, a, b: a - b,
+    'vrsub': lambda s, a, b: b - a,
+    'vand': lambda s, a, b: a & b,
+    'vor': lambda s, a, b: a | b,
+    'vxor': lambda s, a, b: a ^ b,
+    'vsll': lambda s, a, b: a << (b & (s - 1)),
+    'vsrl': lambda s, a, b: a >> (b & (s - 1)),
+    'vsra': lambda s, a, b: sx(a, s) >> (b & (s - 1)),
+    'vminu': lambda s, a, b: min(a, b),
+    'vmin': lambda s, a, b: min(sx(a, s), sx(b, s)),
+    'vmaxu': lambda s, a, b: max(a, b),
+    'vmax': lambda s, a, b: max(sx(a, s), sx(b, s)),
+    'vmul': lambda s, a, b: a * b,
+    'vmulh': lambda s, a, b: (sx(a, s) * sx(b, s)) >> s,
+    'vmulhu': lambda s, a, b: (a * b) >> s,
+    'vmulhsu': lambda s, a, b: (sx(a, s) * b) >> s,
+    'vdivu': lambda s, a, b: m(s) if b == 0 else a // b,
+    'vdiv': sdiv,
+    'vremu': lambda s, a, b: a if b == 0 else a % b,
+    'vrem': srem,
+}
+FORMS = {
+    'vadd': 'vxi', 'vsub': 'vx', 'vrsub': 'xi', 'vand': 'vxi', 'vor': 'vxi', 'vxor': 'vxi',
+    'vsll': 'vxu', 'vsrl': 'vxu', 'vsra': 'vxu', 'vminu': 'vx', 'vmin': 'vx', 'vmaxu': 'vx',
+    'vmax': 'vx', 'vmul': 'vx', 'vmulh': 'vx', 'vmulhu': 'vx', 'vmulhsu': 'vx', 'vdivu': 'vx',
+    'vdiv': 'vx', 'vremu': 'vx', 'vrem': 'vx',
+}
+
+CMP_OPS = {
+    'vmseq': (lambda s, a, b: a == b, 'vxi'),
+    'vmsne': (lambda s, a, b: a != b, 'vxi'),
+    'vmsltu': (lambda s, a, b: a < b, 'vx'),
+    'vmslt': (lambda s, a, b: sx(a, s) < sx(b, s), 'vx'),
+    'vmsleu': (lambda s, a, b: a <= b, 'vxi'),
+    'vmsle': (lambda s, a, b: sx(a, s) <= sx(b, s), 'vxi'),
+    'vmsgtu': (lambda s, a, b: a > b, 'xi'),
+    'vmsgt': (lambda s, a, b: sx(a, s) > sx(b, s), 'xi'),
+}
+
+
+def scalar_operand(sew):
+    """a scalar of x register: the element is its low SEW bits, or the
+    sign-extended register if SEW > XLEN."""
+    x = interesting(min(sew, XLEN))
+    x = sx(x, min(sew, XLEN)) if rng.random() < 0.5 else x
+    g.li('a1', x)
+    if sew > XLEN:
+        return sx(x, XLEN) & m(sew)
+    return x & m(sew)
+
+
+def operand(form, sew, i_vs1, imm_unsigned=False):
+    """emit the setup of the second operand, and return (asm suffix, asm operand, getter)."""
+    if form == 'v':
+        return 'vv', 'v16', lambda i: v.get(16, i, sew)
+    if form == 'x':
+        x = scalar_operand(sew)
+        return 'vx', 'a1', lambda i: x
+    if form == 'i':
+        imm = rng.randint(-16, 15)
+        return 'vi', str(imm), lambda i: imm & m(sew)
+    if form == 'u':
+        imm = rng.randint(0, 31)
+        return 'vi', str(imm), lambda i: imm
+    raise ValueError(form)
+
+
+def masked():
+    return rng.random() < 0.5
+
+
+def random_config(sews=(8, 16, 32, 64), lmuls=None):
+    sew = rng.choice(sews)
+    choices = [l for l in (lmuls or LMULS) if vlmax(sew, l) >= 1 and LMULS[l] >= int(math.log2(sew)) - 6]
+    lmul = rng.choice(choices)
+    vm = vlmax(sew, lmul)
+    avl = rng.choice([vm, rng.randint(0, vm), rng.randint(1, vm), vm + 5])
+    return sew, lmul, avl
+
+
+def setup_sources(sew, lmul, n):
+    load_elements(8, n, sew, lambda i: interesting(sew))
+    load_elements(16, n, sew, lambda i: interesting(sew) if rng.random() < 0.8 else 0)
+    load_group(24, n)
+    load_group(0, 1)
+
+
+def run_binary(name, fn, form):
+    sew, lmul, avl = random_config()
+    n = regs(LMULS[lmul])
+    setup_sources(sew, lmul, n)
+    vl = vset(sew, lmul, avl)
+    suffix, op2, get2 = operand(form, sew, 16)
+    vm = masked()
+    g.emit('%s.%s v24, v8, %s%s' % (name, suffix, op2, ', v0.t' if vm else ''))
+    for i in range(vl):
+        if vm and not v.getm(0, i):
+            continue
+        v.set(24, i, sew, fn(sew, v.get(8, i, sew), get2(i)))
+    dump(24, n)
+
+
+def run_compare(name, fn, form):
+    sew, lmul, avl = random_config()
+    n = regs(LMULS[lmul])
+    setup_sources(sew, lmul, n)
+    # mask destination in v4, which does not overlap the sources.
+    load_group(4, 1)
+    vl = vset(sew, lmul, avl)
+    suffix, op2, get2 = operand(form, sew, 16)
+    vm = masked()
+    g.emit('%s.%s v4, v8, %s%s' % (name, suffix, op2, ', v0.t' if vm else ''))
+    for i in range(vl):
+        if vm and not v.getm(0, i):
+            continue
+        v.setm(4, i, fn(sew, v.get(8, i, sew), get2(i)))
+    dump(4, 1, mask_care(vl))
+
+
+def suite_vint():
+    for name, fn in INT_OPS.items():
+        for form in FORMS[name]:
+            for _ in range(3):
+                g.new_test('%s %s' % (name, form))
+                run_binary(name, fn, form)
+    for name, (fn, forms) in CMP_OPS.items():
+        for form in forms:
+            for _ in range(2):
+                g.new_test('%s %s' % (name, form))
+                run_compare(name, fn, form)
+    # add-with-carry and subtract-with-borrow
+    for name in ('vadc', 'vsbc', 'vmadc', 'vmsbc'):
+        forms = 'vxi' if name in ('vadc', 'vmadc') else 'vx'
+        for form in forms:
+            for use_carry in (True, False) if name in ('vmadc', 'vmsbc') else (True,):
+                g.new_test('%s %s' % (name, form))
+                sew, lmul, avl = random_config()
+                n = regs(LMULS[lmul])
+                setup_sources(sew, lmul, n)
+                load_group(4, 1)
+                vl = vset(sew, lmul, avl)
+                suffix, op2, get2 = operand(form, sew, 16)
+                dst = 'v4' if name.startswith('vm') else 'v24'
+                g.emit('%s.%s%s %s, v8, %s%s' % (name, suffix, 'm' if use_carry else '', dst, op2,
+                                               ', v0' if use_carry else ''))
+                for i in range(vl):
+                    a, b = v.get(8, i, sew), get2(i)
+                    c = v.getm(0, i) if use_carry else 0
+                    if name == 'vadc':
+                        v.set(24, i, sew, a + b + c)
+                    elif name == 'vsbc':
+                        v.set(24, i, sew, a - b - c)
+                    elif name == 'vmadc':
+                        v.setm(4, i, (a + b + c) >> sew)
+                    else:
+                        v.setm(4, i, a < b + c)
+                if dst == 'v4':
+                    dump(4, 1, mask_care(vl))
+                else:
+                    dump(24, n)
+    # merge and move
+    for form in 'vxi':
+        g.new_test('vmerge %s' % form)
+        sew, lmul, avl = random_config()
+        n = regs(LMULS[lmul])
+        setup_sources(sew, lmul, n)
+        vl = vset(sew, lmul, avl)
+        suffix, op2, get2 = operand(form, sew, 16)
+        g.emit('vmerge.%sm v24, v8, %s, v0' % (suffix, op2))
+        for i in range(vl):
+            v.set(24, i, sew, get2(i) if v.getm(0, i) else v.get(8, i, sew))
+        dump(24, n)
+        g.new_test('vmv.v.%s' % form)
+        suffix, op2, get2 = operand(form, sew, 16)
+        g.emit('vmv.v.%s v24, %s' % (suffix[1], op2))
+        for i in range(vl):
+            v.set(24, i, sew, get2(i))
+        dump(24, n)
+    # multiply-add
+    MADD = {
+        'vmacc': lambda d, a, b: a * b + d,
+        'vnmsac': lambda d, a, b: -(a * b) + d,
+        'vmadd': lambda d, a, b: a * d + b,
+        'vnmsub': lambda d, a, b: -(a * d) + b,
+    }
+    for name, fn in MADD.items():
+        for form in 'vx':
+            g.new_test('%s %s' % (name, form))
+            sew, lmul, avl = random_config()
+            n = regs(LMULS[lmul])
+            setup_sources(sew, lmul, n)
+            vl = vset(sew, lmul, avl)
+            suffix, op2, get1 = operand(form, sew, 16)
+            vm = masked()
+            # vd, vs1/rs1, vs2
+            g.emit('%s.%s v24, %s, v8%s' % (name, suffix, op2, ', v0.t' if vm else ''))
+            for i in range(vl):
+                if vm and not v.getm(0, i):
+                    continue
+                v.set(24, i, sew, fn(v.get(24, i, sew), get1(i), v.get(8, i, sew)))
+            dump(24, n)
+    # integer extension
+    for name in ('vzext.vf2', 'vsext.vf2', 'vzext.vf4', 'vsext.vf4', 'vzext.vf8', 'vsext.vf8'):
+        f = int(name[-1])
+        g.new_test(name)
+        sew = rng.choice([s for s in (16, 32, 64) if s // f >= 8])
+        lmul = rng.choice([l for l in ('m1', 'm2', 'm4') if True])
+        n = regs(LMULS[lmul])
+        load_elements(8, max(1, n // f), sew // f, lambda i: interesting(sew // f))
+        load_group(24, n)
+        load_group(0, 1)
+        vl = vset(sew, lmul, rng.randint(1, vlmax(sew, lmul)))
+        vm = masked()
+        g.emit('%s v24, v8%s' % (name, ', v0.t' if vm else ''))
+        for i in range(vl):
+            if vm and not v.getm(0, i):
+                continue
+            x = v.get(8, i, sew // f)
+            v.set(24, i, sew, sx(x, sew // f) if name.startswith('vsext') else x)
+        dump(24, n)
+
+
+# ---------------------------------------------------------------------------
+def suite_vwiden():
+    W = {
+        'vwaddu': lambda s, a, b: a + b,
+        'vwadd': lambda s, a, b: sx(a, s) + sx(b, s),
+        'vwsubu': lambda s, a, b: a - b,
+        'vwsub': lambda s, a, b: sx(a, s) - sx(b, s),
+        'vwmulu': lambda s, a, b: a * b,
+        'vwmul': lambda s, a, b: sx(a, s) * sx(b, s),
+        'vwmulsu': lambda s, a, b: sx(a, s) * b,
+    }
+    for name, fn in W.items():
+        for form in 'vx':
+            g.new_test('%s %s' % (name, form))
+            sew, lmul, avl = random_config((8, 16, 32), ('mf4', 'mf2', 'm1', 'm2', 'm4'))
+            n = regs(LMULS[lmul])
+            wn = regs(LMULS[lmul] + 1)
+            setup_sources(sew, lmul, n)
+            load_group(24, wn)
+            vl = vset(sew, lmul, avl)
+            suffix, op2, get2 = operand(form, sew, 16)
+            vm = masked()
+            g.emit('%s.%s v24, v8, %s%s' % (name, suffix, op2, ', v0.t' if vm else ''))
+            for i in range(vl):
+                if vm and not v.getm(0, i):
+                    continue
+                v.set(24, i, 2 * sew, fn(sew, v.get(8, i, sew), get2(i)))
+            dump(24, wn)
+    # .wv and .wx forms: vs2 is wide
+    for name in ('vwaddu', 'vwadd', 'vwsubu', 'vwsub'):
+        for form in 'vx':
+            g.new_test('%s.w %s' % (name, form))
+            sew, lmul, avl = random_config((8, 16, 32), ('mf4', 'mf2', 'm1', 'm2', 'm4'))
+            n = regs(LMULS[lmul])
+            wn = regs(LMULS[lmul] + 1)
+            load_elements(8, wn, 2 * sew, lambda i: interesting(2 * sew))
+            load_elements(16, n, sew, lambda i: interesting(sew))
+            load_group(24, wn)
+            load_group(0, 1)
+            vl = vset(sew, lmul, avl)
+            suffix, op2, get2 = operand(form, sew, 16)
+            vm = masked()
+            g.emit('%s.w%s v24, v8, %s%s' % (name, suffix[1], op2, ', v0.t' if vm else ''))
+            signed = not name.endswith('u')
+            for i in range(vl):
+                if vm and not v.getm(0, i):
+                    continue
+                a = v.get(8, i, 2 * sew)
+                b = sx(get2(i), sew) if signed else get2(i)
+                v.set(24, i, 2 * sew, a + b if 'add' in name else a - b)
+            dump(24, wn)
+    # widening multiply-add
+    WM = {
+        'vwmaccu': lambda s, d, a, b: d + a * b,
+        'vwmacc': lambda s, d, a, b: d + sx(a, s) * sx(b, s),
+        'vwmaccsu': lambda s, d, a, b: d + sx(b, s) * a,
+    }
+    for name, fn in WM.items():
+        for form in 'vx':
+            g.new_test('%s %s' % (name, form))
+            sew, lmul, avl = random_config((8, 16, 32), ('mf2', 'm1', 'm2', 'm4'))
+            n = regs(LMULS[lmul])
+            wn = regs(LMULS[lmul] + 1)
+            setup_sources(sew, lmul, n)
+            load_group(24, wn)
+            vl = vset(sew, lmul, avl)
+            suffix, op2, get1 = operand(form, sew, 16)
+            vm = masked()
+            g.emit('%s.%s v24, %s, v8%s' % (name, suffix, op2, ', v0.t' if vm else ''))
+            for i in range(vl):
+                if vm and not v.getm(0, i):
+                    continue
+                # vs1/rs1 is the first multiplicand, vs2 the second
+                d = v.get(24, i, 2 * sew)
+                v.set(24, i, 2 * sew, fn(sew, d, v.get(8, i, sew), get1(i)))
+            dump(24, wn)
+    # narrowing shifts
+    for name in ('vnsrl', 'vnsra'):
+        for form in 'vxu':
+            g.new_test('%s %s' % (name, form))
+            sew, lmul, avl = random_config((8, 16, 32), ('mf4', 'mf2', 'm1', 'm2', 'm4'))
+            n = regs(LMULS[lmul])
+            wn = regs(LMULS[lmul] + 1)
+            load_elements(8, wn, 2 * sew, lambda i: interesting(2 * sew))
+            load_elements(16, n, sew, lambda i: rng.getrandbits(sew))
+            load_group(24, n)
+            load_group(0, 1)
+            vl = vset(sew, lmul, avl)
+            suffix, op2, get2 = operand(form, sew, 16)
+            vm = masked()
+            g.emit('%s.w%s v24, v8, %s%s' % (name, suffix[1], op2, ', v0.t' if vm else ''))
+            for i in range(vl):
+                if vm and not v.getm(0, i):
+                    continue
+                a = v.get(8, i, 2 * sew)
+                sh = get2(i) & (2 * sew - 1)
+                v.set(24, i, sew, (sx(a, 2 * sew) if name == 'vnsra' else a) >> sh)
+            dump(24, n)
+
+
+# ---------------------------------------------------------------------------
+def roundoff(x, d, vxrm):
+    """(x >> d) rounded by vxrm. x is an integer of any sign."""
+    if d == 0:
+        return x
+    lsb = (x >> d) & 1
+    half = (x >> (d - 1)) & 1
+    rest = x & m(d - 1) if d > 1 else 0
+    if vxrm == 0:
+        r = half
+    elif vxrm == 1:
+        r = half & (1 if (rest != 0 or lsb) else 0)
+    elif vxrm == 2:
+        r = 0
+    else:
+        r = 0 if lsb else (1 if (x & m(d)) != 0 else 0)
+    return (x >> d) + r
+
+
+def suite_vfixed():
+    sat = {'v': 0}
+
+    def clipu(x, s):
+        if x > m(s):
+            sat['v'] = 1
+            return m(s)
+        if x < 0:
+            sat['v'] = 1
+            return 0
+        return x
+
+    def clips(x, s):
+        if x > m(s - 1):
+            sat['v'] = 1
+            return m(s - 1)
+        if x < -(1 << (s - 1)):
+            sat['v'] = 1
+            return -(1 << (s - 1))
+        return x
+
+    F = {
+        'vsaddu': ('vxi', lambda s, a, b, r: clipu(a + b, s)),
+        'vsadd': ('vxi', lambda s, a, b, r: clips(sx(a, s) + sx(b, s), s)),
+        'vssubu': ('vx', lambda s, a, b, r: clipu(a - b, s)),
+        'vssub': ('vx', lambda s, a, b, r: clips(sx(a, s) - sx(b, s), s)),
+        'vaaddu': ('vx', lambda s, a, b, r: roundoff(a + b, 1, r)),
+        'vaadd': ('vx', lambda s, a, b, r: roundoff(sx(a, s) + sx(b, s), 1, r)),
+        'vasubu': ('vx', lambda s, a, b, r: roundoff(a - b, 1, r)),
+        'vasub': ('vx', lambda s, a, b, r: roundoff(sx(a, s) - sx(b, s), 1, r)),
+        'vsmul': ('vx', lambda s, a, b, r: clips(roundoff(sx(a, s) * sx(b, s), s - 1, r), s)),
+        'vssrl': ('vxu', lambda s, a, b, r: roundoff(a, b & (s - 1), r)),
+        'vssra': ('vxu', lambda s, a, b, r: roundoff(sx(a, s), b & (s - 1), r)),
+    }
+    for name, (forms, fn) in F.items():
+        for form in forms:
+            for vxrm in range(4):
+                g.new_test('%s %s vxrm=%d' % (name, form, vxrm))
+                sew, lmul, avl = random_config()
+                n = regs(LMULS[lmul])
+                setup_sources(sew, lmul, n)
+                g.emit('csrwi vxrm, %d' % vxrm)
+                g.emit('csrwi vxsat, 0')
+                vl = vset(sew, lmul, avl)
+                suffix, op2, get2 = operand(form, sew, 16)
+                vm = masked()
+                g.emit('%s.%s v24, v8, %s%s' % (name, suffix, op2, ', v0.t' if vm else ''))
+                sat['v'] = 0
+                for i in range(vl):
+                    if vm and not v.getm(0, i):
+                        continue
+                    v.set(24, i, sew, fn(sew, v.get(8, i, sew), get2(i), vxrm))
+                dump(24, n)
+                g.emit('csrr t2, vxsat')
+                g.check_x('t2', sat['v'])
+    # narrowing clips
+    for name in ('vnclipu', 'vnclip'):
+        for form in 'vxu':
+            for vxrm in range(4):
+                g.new_test('%s %s vxrm=%d' % (name, form, vxrm))
+                sew, lmul, avl = random_config((8, 16, 32), ('mf4', 'mf2', 'm1', 'm2', 'm4'))
+                n = regs(LMULS[lmul])
+                wn = regs(LMULS[lmul] + 1)
+                load_elements(8, wn, 2 * sew, lambda i: interesting(2 * sew))
+                load_elements(16, n, sew, lambda i: rng.getrandbits(sew))
+                load_group(24, n)
+                load_group(0, 1)
+                g.emit('csrwi vxrm, %d' % vxrm)
+                g.emit('csrwi vxsat, 0')
+                vl = vset(sew, lmul, avl)
+                suffix, op2, get2 = operand(form, sew, 16)
+                vm = masked()
+                g.emit('%s.w%s v24, v8, %s%s' % (name, suffix[1], op2, ', v0.t' if vm else ''))
+                sat['v'] = 0
+                for i in range(vl):
+                    if vm and not v.getm(0, i):
+                        continue
+                    a = v.get(8, i, 2 * sew)
+                    sh = get2(i) & (2 * sew - 1)
+                    if name == 'vnclipu':
+                        v.set(24, i, sew, clipu(roundoff(a, sh, vxrm), sew))
+                    else:
+                        v.set(24, i, sew, clips(roundoff(sx(a, 2 * sew), sh, vxrm), sew))
+                dump(24, n)
+                g.emit('csrr t2, vxsat')
+                g.check_x('t2', sat['v'])
+    # vcsr holds vxrm and vxsat
+    g.new_test('vcsr')
+    g.emit('csrwi vcsr, 7')
+    g.emit('csrr t2, vxrm')
+    g.check_x('t2', 3)
+    g.emit('csrr t2, vxsat')
+    g.check_x('t2', 1)
+    g.emit('csrwi vxrm, 1')
+    g.emit('csrr t2, vcsr')
+    g.check_x('t2', 3)
+
+
+# ---------------------------------------------------------------------------
+def suite_vred():
+    R = {
+        'vredsum': lambda s, a, b: a + b,
+        'vredand': lambda s, a, b: a & b,
+        'vredor': lambda s, a, b: a | b,
+        'vredxor': lambda s, a, b: a ^ b,
+        'vredminu': lambda s, a, b: min(a, b),
+        'vredmin': lambda s, a, b: min(sx(a, s), sx(b, s)),
+        'vredmaxu': lambda s, a, b: max(a, b),
+        'vredmax': lambda s, a, b: max(sx(a, s), sx(b, s)),
+    }
+    for name, fn in R.items():
+        for _ in range(4):
+            g.new_test(name)
+            sew, lmul, avl = random_config()
+            n = regs(LMULS[lmul])
+            setup_sources(sew, lmul, n)
+            vl = vset(sew, lmul, avl)
+            vm = masked()
+            g.emit('%s.vs v24, v8, v16%s' % (name, ', v0.t' if vm else ''))
+            if vl > 0:
+                acc = v.get(16, 0, sew)
+                for i in range(vl):
+                    if vm and not v.getm(0, i):
+                        continue
+                    acc = fn(sew, acc, v.get(8, i, sew)) & m(sew)
+                v.set(24, 0, sew, acc)
+            # only element 0 of the destination is written
+            care = bytes([0xff] * (sew // 8)) + bytes(VLENB - sew // 8)
+            dump(24, 1, care)
+    for name in ('vwredsumu', 'vwredsum'):
+        for _ in range(4):
+            g.new_test(name)
+            sew, lmul, avl = random_config((8, 16, 32))
+            n = regs(LMULS[lmul])
+            setup_sources(sew, lmul, n)
+            load_elements(16, 1, 2 * sew, lambda i: interesting(2 * sew))
+            vl = vset(sew, lmul, avl)
+            vm = masked()
+            g.emit('%s.vs v24, v8, v16%s' % (name, ', v0.t' if vm else ''))
+            if vl > 0:
+                acc = v.get(16, 0, 2 * sew)
+                for i in range(vl):
+                    if vm and not v.getm(0, i):
+                        continue
+                    x = v.get(8, i, sew)
+                    acc = (acc + (x if name == 'vwredsumu' else sx(x, sew))) & m(2 * sew)
+                v.set(24, 0, 2 * sew, acc)
+            care = bytes([0xff] * (sew // 4)) + bytes(VLENB - sew // 4)
+            dump(24, 1, care)
+
+
+# ---------------------------------------------------------------------------
+def suite_vmask():
+    L = {
+        'vmand': lambda a, b: a & b,
+        'vmnand': lambda a, b: 1 - (a & b),
+        'vmandn': lambda a, b: a & (1 - b),
+        'vmxor': lambda a, b: a ^ b,
+        'vmor': lambda a, b: a | b,
+        'vmnor': lambda a, b: 1 - (a | b),
+        'vmorn': lambda a, b: a | (1 - b),
+        'vmxnor': lambda a, b: 1 - (a ^ b),
+    }
+    for name, fn in L.items():
+        for _ in range(2):
+            g.new_test(name)
+            sew, lmul, avl = random_config()
+            load_group(8, 1)
+            load_group(16, 1)
+            load_group(24, 1)
+            vl = vset(sew, lmul, avl)
+            g.emit('%s.mm v24, v8, v16' % name)
+            for i in range(vl):
+                v.setm(24, i, fn(v.getm(8, i), v.getm(16, i)))
+            dump(24, 1, mask_care(vl))
+
+    def sparse_mask(reg):
+        p = rng.choice([0.0, 0.05, 0.3, 0.7])
+        load_group(reg, 1, bytes(sum((rng.random() < p) << b for b in range(8)) for _ in range(VLENB)))
+
+    for _ in range(6):
+        g.new_test('vcpop.m vfirst.m')
+        sew, lmul, avl = random_config()
+        sparse_mask(8)
+        load_group(0, 1)
+        vl = vset(sew, lmul, avl)
+        vm = masked()
+        g.emit('vcpop.m a4, v8%s' % (', v0.t' if vm else ''))
+        g.emit('vfirst.m a5, v8%s' % (', v0.t' if vm else ''))
+        act = [i for i in range(vl) if (not vm or v.getm(0, i)) and v.getm(8, i)]
+        g.check_x('a4', len(act))
+        g.check_x('a5', act[0] if act else -1)
+
+    for name in ('vmsbf', 'vmsif', 'vmsof'):
+        for _ in range(3):
+            g.new_test(name)
+            sew, lmul, avl = random_config()
+            sparse_mask(8)
+            load_group(0, 1)
+            load_group(4, 1)
+            vl = vset(sew, lmul, avl)
+            vm = masked()
+            g.emit('%s.m v4, v8%s' % (name, ', v0.t' if vm else ''))
+            found = False
+            for i in range(vl):
+                if vm and not v.getm(0, i):
+                    continue
+                bit = v.getm(8, i)
+                if name == 'vmsbf':
+                    r = 0 if (found or bit) else 1
+                elif name == 'vmsif':
+                    r = 0 if found else 1
+                else:
+                    r = 1 if (bit and not found) else 0
+                found = found or bit
+                v.setm(4, i, r)
+            dump(4, 1, mask_care(vl))
+
+    for _ in range(4):
+        g.new_test('viota.m')
+        sew, lmul, avl = random_config()
+        n = regs(LMULS[lmul])
+        sparse_mask(8)
+        load_group(0, 1)
+        load_group(24, n)
+        vl = vset(sew, lmul, avl)
+        vm = masked()
+        g.emit('viota.m v24, v8%s' % (', v0.t' if vm else ''))
+        c = 0
+        for i in range(vl):
+            if vm and not v.getm(0, i):
+                continue
+            v.set(24, i, sew, c)
+            c += v.getm(8, i)
+        dump(24, n)
+    for _ in range(3):
+        g.new_test('vid.v')
+        sew, lmul, avl = random_config()
+        n = regs(LMULS[lmul])
+        load_group(0, 1)
+        load_group(24, n)
+        vl = vset(sew, lmul, avl)
+        vm = masked()
+        g.emit('vid.v v24%s' % (', v0.t' if vm else ''))
+        for i in range(vl):
+            if vm and not v.getm(0, i):
+                continue
+            v.set(24, i, sew, i)
+        dump(24, n)
+    # mask loads and stores
+    g.new_test('vlm.v vsm.v')
+    sew, lmul, avl = 8, 'm8', 100
+    content = bytes(rng.getrandbits(8) for _ in range(VLENB))
+    lab = g.bytes_data(content)
+    load_group(8, 1)
+    vl = vset(sew, lmul, avl)
+    g.emit('lla a0, %s' % lab)
+    g.emit('vlm.v v8, (a0)')
+    nb = (vl + 7) // 8
+    v.b[8 * VLENB:8 * VLENB + nb] = content[:nb]
+    dump(8, 1, bytes([0xff] * nb) + bytes(VLENB - nb))
+    g.li('a0', SCRATCH + 0x800)
+    g.emit('vsm.v v8, (a0)')
+    g.check_mem(SCRATCH + 0x800, content[:nb])
+
+
+# ---------------------------------------------------------------------------
+def suite_vperm():
+    for _ in range(4):
+        g.new_test('vmv.x.s vmv.s.x')
+        sew, lmul, avl = random_config()
+        n = regs(LMULS[lmul])
+        load_elements(8, n, sew, lambda i: interesting(sew))
+        load_group(24, 1)
+        vl = vset(sew, lmul, avl)
+        g.emit('vmv.x.s a4, v8')
+        x = v.get(8, 0, sew)
+        g.check_x('a4', sx(x, sew) if sew <= XLEN else x)
+        y = scalar_operand(sew)
+        g.emit('vmv.s.x v24, a1')
+        if vl > 0:
+            v.set(24, 0, sew, y)
+        dump(24, 1)
+    for name in ('vslideup', 'vslidedown'):
+        for form in 'xu':
+            for _ in range(3):
+                g.new_test('%s %s' % (name, form))
+                sew, lmul, avl = random_config()
+                n = regs(LMULS[lmul])
+                setup_sources(sew, lmul, n)
+                vl = vset(sew, lmul, avl)
+                vmx = vlmax(sew, lmul)
+                if form == 'x':
+                    off = rng.choice([0, 1, rng.randint(0, vmx + 2), 1 << (XLEN - 1)])
+                    g.li('a1', off)
+                    op = 'vx', 'a1'
+                else:
+                    off = rng.randint(0, 31)
+                    op = 'vi', str(off)
+                vm = masked()
+                g.emit('%s.%s v24, v8, %s%s' % (name, op[0], op[1], ', v0.t' if vm else ''))
+                old = v.group(24, n)
+                for i in range(vl):
+                    if vm and not v.getm(0, i):
+                        continue
+                    if name == 'vslideup':
+                        if i >= off:
+                            v.set(24, i, sew, v.get(8, i - off, sew))
+                    else:
+                        j = i + off
+                        v.set(24, i, sew, v.get(8, j, sew) if j < vmx else 0)
+                dump(24, n)
+    for name in ('vslide1up', 'vslide1down'):
+        for _ in range(3):
+            g.new_test(name)
+            sew, lmul, avl = random_config()
+            n = regs(LMULS[lmul])
+            setup_sources(sew, lmul, n)
+            vl = vset(sew, lmul, avl)
+            x = scalar_operand(sew)
+            vm = masked()
+            g.emit('%s.vx v24, v8, a1%s' % (name, ', v0.t' if vm else ''))
+            for i in range(vl):
+                if vm and not v.getm(0, i):
+                    continue
+                if name == 'vslide1up':
+                    v.set(24, i, sew, x if i == 0 else v.get(8, i - 1, sew))
+                else:
+                    v.set(24, i, sew, x if i == vl - 1 else v.get(8, i + 1, sew))
+            dump(24, n)
+    for form in 'vxu':
+        for _ in range(3):
+            g.new_test('vrgather %s' % form)
+            sew, lmul, avl = random_config()
+            n = regs(LMULS[lmul])
+            vmx = vlmax(sew, lmul)
+            load_elements(8, n, sew, lambda i: interesting(sew))
+            load_elements(16, n, sew, lambda i: rng.choice([rng.randint(0, vmx - 1), vmx + 3, m(sew)]))
+            load_group(24, n)
+            load_group(0, 1)
+            vl = vset(sew, lmul, avl)
+            if form == 'v':
+                op = 'vv', 'v16'
+                idx = lambda i: v.get(16, i, sew)
+            elif form == 'x':
+                k = rng.choice([0, rng.randint(0, vmx), m(XLEN)])
+                g.li('a1', k)
+                op = 'vx', 'a1'
+                idx = lambda i: k
+            else:
+                k = rng.randint(0, 31)
+                op = 'vi', str(k)
+                idx = lambda i: k
+            vm = masked()
+            g.emit('vrgather.%s v24, v8, %s%s' % (op[0], op[1], ', v0.t' if vm else ''))
+            for i in range(vl):
+                if vm and not v.getm(0, i):
+                    continue
+                j = idx(i)
+                v.set(24, i, sew, v.get(8, j, sew) if j < vmx else 0)
+            dump(24, n)
+    for _ in range(3):
+        g.new_test('vrgatherei16')
+        sew, lmul, avl = random_config((8, 16, 32, 64), ('mf2', 'm1', 'm2', 'm4'))
+        n = regs(LMULS[lmul])
+        vmx = vlmax(sew, lmul)
+        # the indices are 16 bits: EMUL = 16 / SEW * LMUL
+        emul = LMULS[lmul] + int(math.log2(16 // sew)) if sew <= 16 else LMULS[lmul] - int(math.log2(sew // 16))
+        if emul > 3:
+            continue
+        load_elements(8, n, sew, lambda i: interesting(sew))
+        load_elements(16, regs(emul), 16, lambda i: rng.choice([rng.randint(0, vmx - 1), vmx + 1]))
+        load_group(24, n)
+        load_group(0, 1)
+        vl = vset(sew, lmul, avl)
+        g.emit('vrgatherei16.vv v24, v8, v16')
+        for i in range(vl):
+            j = v.get(16, i, 16)
+            v.set(24, i, sew, v.get(8, j, sew) if j < vmx else 0)
+        dump(24, n)
+    for _ in range(4):
+        g.new_test('vcompress')
+        sew, lmul, avl = random_config()
+        n = regs(LMULS[lmul])
+        load_elements(8, n, sew, lambda i: interesting(sew))
+        load_group(4, 1)
+        load_group(24, n)
+        vl = vset(sew, lmul, avl)
+        g.emit('vcompress.vm v24, v8, v4')
+        k = 0
+        for i in range(vl):
+            if v.getm(4, i):
+                v.set(24, k, sew, v.get(8, i, sew))
+                k += 1
+        dump(24, n)
+    for nr in (1, 2, 4, 8):
+        g.new_test('vmv%dr.v' % nr)
+        load_group(8, 8)
+        load_group(24, 8)
+        vset(8, 'mf8', 1)
+        g.emit('vmv%dr.v v24, v8' % nr)
+        v.b[24 * VLENB:(24 + nr) * VLENB] = v.b[8 * VLENB:(8 + nr) * VLENB]
+        dump(24, 8)
+
+
+# ---------------------------------------------------------------------------
+MEMBUF = 0x80200000
+
+
+def mem_fill(size):
+    content = bytes(rng.getrandbits(8) for _ in range(size))
+    lab = g.bytes_data(content)
+    # copy the data to MEMBUF by a byte loop
+    g.emit('lla a0, %s' % lab)
+    g.li('a1', MEMBUF)
+    g.li('a2', size)
+    g.emit('call copy')
+    return bytearray(content)
+
+
+def suite_vmem():
+    EEWS = (8, 16, 32, 64)
+    # unit-stride
+    for eew in EEWS:
+        for _ in range(3):
+            g.new_test('vle%d vse%d' % (eew, eew))
+            sew, lmul, avl = random_config()
+            emul = LMULS[lmul] + int(math.log2(eew)) - int(math.log2(sew))
+            if not -3 <= emul <= 3:
+                continue
+            n = regs(emul)
+            mem = mem_fill(n * VLENB + 64)
+            load_group(24, n)
+            load_group(0, 1)
+            vl = vset(sew, lmul, avl)
+            vm = masked()
+            g.li('a0', MEMBUF)
+            g.emit('vle%d.v v24, (a0)%s' % (eew, ', v0.t' if vm else ''))
+            for i in range(vl):
+                if vm and not v.getm(0, i):
+                    continue
+                v.set(24, i, eew, int.from_bytes(mem[i * eew // 8:(i + 1) * eew // 8], 'little'))
+            dump(24, n)
+            # store back to another location over a known pattern
+            g.li('a0', SCRATCH + 0x1000)
+            g.li('a1', n * VLENB)
+            g.emit('call clear')
+            vm = masked()
+            g.li('a0', SCRATCH + 0x1000)
+            g.emit('vse%d.v v24, (a0)%s' % (eew, ', v0.t' if vm else ''))
+            out = bytearray(n * VLENB)
+            for i in range(vl):
+                if vm and not v.getm(0, i):
+                    continue
+                out[i * eew // 8:(i + 1) * eew // 8] = v.get(24, i, eew).to_bytes(eew // 8, 'little')
+            g.check_mem(SCRATCH + 0x1000, out)
+    # strided
+    for eew in EEWS:
+        for _ in range(2):
+            g.new_test('vlse%d vsse%d' % (eew, eew))
+            sew = eew
+            lmul = rng.choice(['m1', 'm2'])
+            n = regs(LMULS[lmul])
+            vl = vset(sew, lmul, rng.randint(1, vlmax(sew, lmul)))
+            stride = rng.choice([0, eew // 8, 3 * eew // 8, -(eew // 8), 24, -40])
+            base = MEMBUF + 0x400
+            mem = mem_fill(0x800)
+            load_group(24, n)
+            load_group(0, 1)
+            g.li('a0', base)
+            g.li('a1', stride)
+            vm = masked()
+            g.emit('vlse%d.v v24, (a0), a1%s' % (eew, ', v0.t' if vm else ''))
+            for i in range(vl):
+                if vm and not v.getm(0, i):
+                    continue
+                o = 0x400 + i * stride
+                v.set(24, i, eew, int.from_bytes(mem[o:o + eew // 8], 'little'))
+            dump(24, n)
+            g.li('a0', SCRATCH + 0x2000)
+            g.li('a1', 0x1000)
+            g.emit('call clear')
+            stride = rng.choice([eew // 8, 2 * eew // 8, -(eew // 8) * 2])
+            g.li('a0', SCRATCH + 0x2800)
+            g.li('a1', stride)
+            vm = masked()
+            g.emit('vsse%d.v v24, (a0), a1%s' % (eew, ', v0.t' if vm else ''))
+            out = bytearray(0x1000)
+            for i in range(vl):
+                if vm and not v.getm(0, i):
+                    continue
+                o = 0x800 + i * stride
+                out[o:o + eew // 8] = v.get(24, i, eew).to_bytes(eew // 8, 'little')
+            g.check_mem(SCRATCH + 0x2000, out)
+    # indexed: data EEW is SEW, index EEW from the instruction
+    for ieew in EEWS:
+        for order in ('u', 'o'):
+            g.new_test('vl%sxei%d vs%sxei%d' % (order, ieew, order, ieew))
+            sew = rng.choice(EEWS)
+            lmul = 'm1'
+            iemul = int(math.log2(ieew)) - int(math.log2(sew))
+            if not -3 <= iemul <= 3 or ieew > XLEN:
+                continue
+            vl = vset(sew, lmul, rng.randint(1, vlmax(sew, lmul)))
+            mem = mem_fill(0x400)
+            idx = [rng.randrange(0, 0x100 - sew // 8) for _ in range(VLEN // ieew * regs(iemul))]
+            if ieew == 8:
+                idx = [x & 0xf8 for x in idx]
+            load_elements(16, regs(iemul), ieew, lambda i: idx[i])
+            load_group(24, 1)
+            load_group(0, 1)
+            g.li('a0', MEMBUF)
+            vm = masked()
+            g.emit('vl%sxei%d.v v24, (a0), v16%s' % (order, ieew, ', v0.t' if vm else ''))
+            for i in range(vl):
+                if vm and not v.getm(0, i):
+                    continue
+                o = idx[i]
+                v.set(24, i, sew, int.from_bytes(mem[o:o + sew // 8], 'little'))
+            dump(24, 1)
+            # stores with distinct aligned indices
+            perm = rng.sample(range(0, 0x100 // (sew // 8)), vl)
+            idx2 = [p * (sew // 8) for p in perm] + [0] * (VLEN // ieew * regs(iemul) - vl)
+            if ieew == 8 and max(idx2) > 255:
+                continue
+            load_elements(16, regs(iemul), ieew, lambda i: idx2[i])
+            g.li('a0', SCRATCH + 0x3000)
+            g.li('a1', 0x100)
+            g.emit('call clear')
+            vm = masked()
+            g.emit('vs%sxei%d.v v24, (a0), v16%s' % (order, ieew, ', v0.t' if vm else ''))
+            out = bytearray(0x100)
+            for i in range(vl):
+                if vm and not v.getm(0, i):
+                    continue
+                o = idx2[i]
+                out[o:o + sew // 8] = v.get(24, i, sew).to_bytes(sew // 8, 'little')
+            g.check_mem(SCRATCH + 0x3000, out)
+    # segments
+    for nf in (2, 3, 4, 8):
+        for eew in (8, 32):
+            g.new_test('vlseg%de%d vsseg%de%d' % (nf, eew, nf, eew))
+            sew = eew
+            vl = vset(sew, 'm1', rng.randint(1, vlmax(sew, 'm1')))
+            mem = mem_fill(nf * VLENB + 16)
+            for f in range(nf):
+                load_group(16 + f, 1)
+            load_group(0, 1)
+            vm = masked()
+            g.li('a0', MEMBUF)
+            g.emit('vlseg%de%d.v v16, (a0)%s' % (nf, eew, ', v0.t' if vm else ''))
+            for i in range(vl):
+                if vm and not v.getm(0, i):
+                    continue
+                for f in range(nf):
+                    o = (i * nf + f) * eew // 8
+                    v.set(16 + f, i, eew, int.from_bytes(mem[o:o + eew // 8], 'little'))
+            dump(16, 8 if nf > 4 else (4 if nf > 2 else 2)) if False else None
+            for f in range(nf):
+                dump(16 + f, 1)
+            g.li('a0', SCRATCH + 0x3000)
+            g.li('a1', 0x100)
+            g.emit('call clear')
+            g.emit('vsseg%de%d.v v16, (a0)' % (nf, eew))
+            out = bytearray(0x100)
+            for i in range(vl):
+                for f in range(nf):
+                    o = (i * nf + f) * eew // 8
+                    out[o:o + eew // 8] = v.get(16 + f, i, eew).to_bytes(eew // 8, 'little')
+            g.check_mem(SCRATCH + 0x3000, out)
+    # strided segment
+    g.new_test('vlsseg2e16')
+    vl = vset(16, 'm1', 5)
+    mem = mem_fill(0x200)
+    load_group(16, 1)
+    load_group(17, 1)
+    g.li('a0', MEMBUF)
+    g.li('a1', 10)
+    g.emit('vlsseg2e16.v v16, (a0), a1')
+    for i in range(vl):
+        for f in range(2):
+            o = i * 10 + f * 2
+            v.set(16 + f, i, 16, int.from_bytes(mem[o:o + 2], 'little'))
+    dump(16, 2)
+    # whole register loads and stores ignore vl and vtype
+    for nr in (1, 2, 4, 8):
+        g.new_test('vl%dre*.v vs%dr.v' % (nr, nr))
+        mem = mem_fill(nr * VLENB)
+        vset(8, 'mf8', 0, check=True)
+        g.li('a0', MEMBUF)
+        eew = rng.choice(EEWS)
+        g.emit('vl%dre%d.v v8, (a0)' % (nr, eew))
+        v.b[8 * VLENB:(8 + nr) * VLENB] = mem
+        dump(8, nr)
+    # fault-only-first: the fault on an element other than the first trims vl
+    g.li('t0', FAULT >> 2)
+    g.emit('csrw pmpaddr0, t0')
+    g.li('t0', (FAULT + 0x1000) >> 2)
+    g.emit('csrw pmpaddr1, t0')
+    g.li('t0', -1)
+    g.emit('csrw pmpaddr2, t0')
+    g.li('t0', 0x0f880f)
+    g.emit('csrw pmpcfg0, t0')
+    for eew in (8, 64):
+        g.new_test('vle%dff.v trims vl' % eew)
+        vl = vset(eew, 'm1', vlmax(eew, 'm1'))
+        load_group(24, 1)
+        k = rng.randint(1, vl - 1)
+        base = FAULT - k * eew // 8
+        g.li('a0', base)
+        g.li('a1', k * eew // 8)
+        g.emit('call clear')
+        g.emit('vle%dff.v v24, (a0)' % eew)
+        g.emit('csrr t2, vl')
+        g.check_x('t2', k)
+        for i in range(k):
+            v.set(24, i, eew, 0)
+        dump(24, 1)
+    g.new_test('vle32ff.v traps on the first element')
+    vl = vset(32, 'm1', 4)
+    g.li('a0', FAULT)
+    g.emit('EXPECT_TRAP 5, 1f')
+    g.emit('vle32ff.v v24, (a0)')
+    g.code.append('1:')
+    g.emit('CHECK_TRAPPED')
+    g.emit('csrr t2, mtval')
+    g.check_x('t2', FAULT)
+    g.emit('csrr t2, vl')
+    g.check_x('t2', vl)
+    # a trap on a later element of a normal load records vstart
+    g.new_test('vle32.v traps with vstart')
+    vl = vset(32, 'm1', 4)
+    g.li('a0', FAULT - 8)
+    g.emit('EXPECT_TRAP 5, 1f')
+    g.emit('vle32.v v24, (a0)')
+    g.code.append('1:')
+    g.emit('CHECK_TRAPPED')
+    g.emit('csrr t2, vstart')
+    g.check_x('t2', 2)
+    g.emit('csrw vstart, zero')
+
+
+# ---------------------------------------------------------------------------
+def vtype_bits(sew, lmul, ta=0, ma=0):
+    l = LMULS[lmul] & 7
+    return l | (int(math.log2(sew)) - 3) << 3 | ta << 6 | ma << 7
+
+
+def suite_vsetvl():
+    g.new_test('vlenb')
+    g.emit('csrr t2, vlenb')
+    g.check_x('t2', VLENB)
+    for sew in (8, 16, 32, 64):
+        for lmul in LMULS:
+            if LMULS[lmul] < int(math.log2(sew)) - 6:
+                continue
+            vm = vlmax(sew, lmul)
+            for avl in (0, 1, vm - 1, vm, vm + 1, 2 * vm + 1, m(XLEN)):
+                if avl < 0:
+                    continue
+                g.new_test('vsetvli e%d %s avl=%d' % (sew, lmul, avl))
+                g.li('t0', avl)
+                g.emit('vsetvli t1, t0, e%d, %s, ta, ma' % (sew, lmul))
+                # AVL <= VLMAX gives AVL and AVL >= 2 * VLMAX gives VLMAX
+                if avl <= vm or avl >= 2 * vm:
+                    g.check_x('t1', min(avl, vm))
+                else:
+                    g.emit('blt t1, zero, fail')
+                    g.li('t6', (avl + 1) // 2)
+                    g.emit('blt t1, t6, fail')
+                    g.li('t6', vm)
+                    g.emit('blt t6, t1, fail')
+                g.emit('csrr t2, vl')
+                g.emit('bne t1, t2, fail')
+                g.emit('csrr t2, vtype')
+                g.check_x('t2', vtype_bits(sew, lmul, 1, 1))
+    g.new_test('vsetvli rs1=x0 rd!=x0 sets VLMAX')
+    g.emit('vsetvli t1, zero, e16, m2, ta, ma')
+    g.check_x('t1', vlmax(16, 'm2'))
+    g.new_test('vsetvli rs1=x0 rd=x0 keeps vl')
+    g.li('t0', 3)
+    g.emit('vsetvli t1, t0, e32, m1, ta, ma')
+    g.emit('vsetvli zero, zero, e8, mf4, ta, ma')
+    g.emit('csrr t2, vl')
+    g.check_x('t2', 3)
+    g.new_test('vsetivli')
+    g.emit('vsetivli t1, 9, e8, m1, tu, mu')
+    g.check_x('t1', 9)
+    g.emit('vsetivli t1, 31, e64, m1, tu, mu')
+    g.check_x('t1', 2)
+    g.new_test('vsetvl')
+    g.li('t0', 7)
+    g.li('t2', vtype_bits(32, 'm2'))
+    g.emit('vsetvl t1, t0, t2')
+    g.check_x('t1', 7)
+    g.emit('csrr t3, vtype')
+    g.check_x('t3', vtype_bits(32, 'm2'))
+    # unsupported configurations set vill, and vector instructions become illegal
+    for vt in (vtype_bits(64, 'mf8'), vtype_bits(8, 'm1') | 0x100, 4, (4 << 3)):
+        g.new_test('vill vtype=%#x' % vt)
+        g.li('t0', 5)
+        g.li('t2', vt)
+        g.emit('vsetvl t1, t0, t2')
+        g.check_x('t1', 0)
+        g.emit('csrr t3, vtype')
+        g.check_x('t3', 1 << (XLEN - 1))
+        g.emit('EXPECT_TRAP 2, 1f')
+        g.emit('vadd.vv v1, v2, v3')
+        g.code.append('1:')
+        g.emit('CHECK_TRAPPED')
+    g.new_test('misaligned register group is illegal')
+    g.emit('vsetivli t1, 4, e8, m2, ta, ma')
+    g.emit('EXPECT_TRAP 2, 1f')
+    g.emit('vadd.vv v1, v2, v4')
+    g.code.append('1:')
+    g.emit('CHECK_TRAPPED')
+    g.new_test('masked destination v0 is illegal')
+    g.emit('EXPECT_TRAP 2, 1f')
+    g.emit('.word 0x00220057  # vadd.vv v0, v2, v4, v0.t')
+    g.code.append('1:')
+    g.emit('CHECK_TRAPPED')
+    g.new_test('vector instructions are illegal while mstatus.VS is off')
+    g.li('t0', 0x600)
+    g.emit('csrc mstatus, t0')
+    g.emit('EXPECT_TRAP 2, 1f')
+    g.emit('vsetivli t1, 4, e8, m1, ta, ma')
+    g.code.append('1:')
+    g.emit('CHECK_TRAPPED')
+    g.emit('EXPECT_TRAP 2, 1f')
+    g.emit('csrr t1, vl')
+    g.code.append('1:')
+    g.emit('CHECK_TRAPPED')
+    g.li('t0', 0x200)
+    g.emit('csrs mstatus, t0')
+    g.new_test('vector instructions make mstatus.VS dirty')
+    g.emit('vsetivli t1, 4, e8, m1, ta, ma')
+    g.emit('vadd.vv v1, v2, v3')
+    g.emit('csrr t2, mstatus')
+    g.li('t0', 0x600)
+    g.emit('and t2, t2, t0')
+    g.check_x('t2', 0x600)
+    g.emit('csrr t2, mstatus')
+    g.emit('bgez t2, fail')
+
+
+# ---------------------------------------------------------------------------
+SUITES = {
+    'vsetvl': suite_vsetvl, 'vmem': suite_vmem, 'vint': suite_vint, 'vwiden': suite_vwiden,
+    'vfixed': suite_vfixed, 'vred': suite_vred, 'vmask': suite_vmask, 'vperm': suite_vperm,
+}
+SUITES[SUITE]()
+
+print('# Generated by vector.py')
+print('.include "vector_test.S"')
+INV = {'bne': 'beq', 'beq': 'bne', 'blt': 'bge', 'bge': 'blt', 'bgez': 'bltz', 'bltz': 'bgez'}
+for line in g.code:
+    parts = line.split()
+    if parts and parts[0] in INV and parts[-1] == 'fail':
+        print('  %s %s 99f' % (INV[parts[0]], ' '.join(parts[1:-1])))
+        print('  j fail')
+        print('99:')
+    else:
+        print(line)
+print('  j pass')
+print('.align 3')
+print('\n'.join(g.data))
+print('.balign 8, 0')